@@ -0,0 +1,181 @@
+//! Clipboard history
+//!
+//! Keeps the most recently copied selections so an older copy can be pasted
+//! again from a picker without re-selecting it. The history lives in memory
+//! only and is shared by all windows of the process; it is never written to
+//! session, crash or upgrade state.
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use crate::config::GeneralConfig;
+
+/// Ring of recently copied texts, most recent first
+#[derive(Debug, Clone)]
+pub struct ClipboardHistory {
+    entries: VecDeque<String>,
+    max_entries: usize,
+    max_entry_bytes: usize,
+}
+
+impl ClipboardHistory {
+    /// Create an empty history (`max_entries` 0 disables it)
+    pub const fn new(max_entries: usize, max_entry_bytes: usize) -> Self {
+        Self {
+            entries: VecDeque::new(),
+            max_entries,
+            max_entry_bytes,
+        }
+    }
+
+    /// Change the limits, dropping entries that no longer fit
+    pub fn set_limits(&mut self, max_entries: usize, max_entry_bytes: usize) {
+        self.max_entries = max_entries;
+        self.max_entry_bytes = max_entry_bytes;
+        self.entries.retain(|text| text.len() <= max_entry_bytes);
+        self.entries.truncate(max_entries);
+    }
+
+    /// Remember a copied text
+    ///
+    /// Sensitive copies (e.g. from incognito tabs), blank text and text larger
+    /// than the size limit are not remembered. Copying an entry again moves it
+    /// to the front. Returns whether the text was added.
+    pub fn push(&mut self, text: &str, sensitive: bool) -> bool {
+        if sensitive
+            || self.max_entries == 0
+            || text.trim().is_empty()
+            || text.len() > self.max_entry_bytes
+        {
+            return false;
+        }
+
+        if let Some(pos) = self.entries.iter().position(|entry| entry == text) {
+            self.entries.remove(pos);
+        }
+        self.entries.push_front(text.to_string());
+        self.entries.truncate(self.max_entries);
+        true
+    }
+
+    /// Get an entry (0 = most recent)
+    pub fn get(&self, index: usize) -> Option<&str> {
+        self.entries.get(index).map(String::as_str)
+    }
+
+    /// Iterate over the entries, most recent first
+    pub fn entries(&self) -> impl Iterator<Item = &str> {
+        self.entries.iter().map(String::as_str)
+    }
+
+    /// Number of remembered entries
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether nothing has been remembered
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Forget all entries
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+/// Default number of entries kept
+pub const DEFAULT_MAX_ENTRIES: usize = 20;
+
+/// Default size limit of a single entry, in bytes
+pub const DEFAULT_MAX_ENTRY_BYTES: usize = 64 * 1024;
+
+/// Process-wide clipboard history
+static HISTORY: Mutex<ClipboardHistory> = Mutex::new(ClipboardHistory::new(
+    DEFAULT_MAX_ENTRIES,
+    DEFAULT_MAX_ENTRY_BYTES,
+));
+
+/// Apply the limits from the configuration to the shared history
+pub fn configure(general: &GeneralConfig) {
+    if let Ok(mut history) = HISTORY.lock() {
+        history.set_limits(
+            general.clipboard_history,
+            general.clipboard_history_max_bytes,
+        );
+    }
+}
+
+/// Remember a copied text in the shared history
+pub fn record(text: &str, sensitive: bool) {
+    if let Ok(mut history) = HISTORY.lock() {
+        history.push(text, sensitive);
+    }
+}
+
+/// Get the shared history's entries, most recent first
+pub fn entries() -> Vec<String> {
+    HISTORY
+        .lock()
+        .map(|history| history.entries().map(str::to_string).collect())
+        .unwrap_or_default()
+}
+
+/// Shorten an entry to a single line for display in a picker
+///
+/// Runs of whitespace (including newlines) become one space, and text longer
+/// than `max_chars` is cut with an ellipsis.
+pub fn preview(text: &str, max_chars: usize) -> String {
+    let line = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if line.chars().count() <= max_chars {
+        line
+    } else {
+        let mut short: String = line.chars().take(max_chars.saturating_sub(1)).collect();
+        short.push('…');
+        short
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_push_order_and_limits() {
+        let mut history = ClipboardHistory::new(3, 10);
+        assert!(history.push("one", false));
+        assert!(history.push("two", false));
+        assert!(history.push("three", false));
+        assert!(history.push("four", false));
+        assert_eq!(
+            history.entries().collect::<Vec<_>>(),
+            vec!["four", "three", "two"]
+        );
+
+        // Copying an entry again moves it to the front
+        assert!(history.push("two", false));
+        assert_eq!(history.get(0), Some("two"));
+        assert_eq!(history.len(), 3);
+
+        // Sensitive, blank and oversized copies are skipped
+        assert!(!history.push("secret", true));
+        assert!(!history.push("  \n", false));
+        assert!(!history.push("far too long", false));
+        assert_eq!(history.get(0), Some("two"));
+
+        history.set_limits(1, 10);
+        assert_eq!(history.entries().collect::<Vec<_>>(), vec!["two"]);
+
+        // Zero entries disables the history
+        history.set_limits(0, 10);
+        assert!(!history.push("five", false));
+        assert!(history.is_empty());
+    }
+
+    #[test]
+    fn test_preview() {
+        assert_eq!(preview("ls -la\n  /tmp", 20), "ls -la /tmp");
+        assert_eq!(preview("abcdefghij", 5), "abcd…");
+        assert_eq!(preview("abcde", 5), "abcde");
+    }
+}
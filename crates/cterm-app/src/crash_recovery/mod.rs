@@ -5,12 +5,19 @@
 //! - Crash state file for persisting terminal state
 //! - FD passing between watchdog and main process
 //! - Recovery and restart after crashes
+//! - Crash reports for diagnosing crashes after the fact
 
+#[cfg(unix)]
+mod report;
 #[cfg(unix)]
 mod state;
 #[cfg(unix)]
 mod watchdog;
 
+#[cfg(unix)]
+pub use report::{
+    crash_reports_dir, take_pending_crash_report, write_crash_report, CrashReport, CrashReportTab,
+};
 #[cfg(unix)]
 pub use state::{
     clear_crash_state, crash_marker_path, crash_state_path, read_crash_marker, read_crash_state,
@@ -0,0 +1,249 @@
+//! Crash reports
+//!
+//! When the watchdog detects a crash it writes a JSON report describing what
+//! happened (version, platform, exit status, open tabs and the most recent log
+//! lines) so that crashes can be reported instead of being silently restarted.
+
+use std::fs;
+use std::io;
+use std::os::unix::process::ExitStatusExt;
+use std::path::{Path, PathBuf};
+use std::process::ExitStatus;
+
+use serde::{Deserialize, Serialize};
+
+use super::state::{cache_dir, CrashState};
+
+/// Summary of a tab that was open when the crash happened
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CrashReportTab {
+    /// Tab title
+    pub title: String,
+    /// Working directory of the shell, if known
+    pub cwd: Option<String>,
+    /// Template the tab was opened from, if any
+    pub template_name: Option<String>,
+}
+
+/// Structured crash report written by the watchdog
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrashReport {
+    /// cterm version that crashed
+    pub version: String,
+    /// Operating system (e.g. "macos", "linux")
+    pub os: String,
+    /// CPU architecture (e.g. "aarch64")
+    pub arch: String,
+    /// Timestamp of the crash (seconds since Unix epoch)
+    pub timestamp: u64,
+    /// PID of the crashed process
+    pub pid: u32,
+    /// Exit code, if the process exited normally with a non-zero status
+    pub exit_code: Option<i32>,
+    /// Signal that terminated the process, if any
+    pub signal: Option<i32>,
+    /// Number of restarts performed by the watchdog so far
+    pub restart_count: u32,
+    /// Tabs that were open at the last state save
+    pub tabs: Vec<CrashReportTab>,
+    /// Most recent log lines captured before the crash
+    pub log_tail: Vec<String>,
+}
+
+impl CrashReport {
+    /// Create a report for a process that terminated with the given status
+    pub fn new(pid: u32, status: ExitStatus, restart_count: u32) -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            os: std::env::consts::OS.to_string(),
+            arch: std::env::consts::ARCH.to_string(),
+            timestamp: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+            pid,
+            exit_code: status.code(),
+            signal: status.signal(),
+            restart_count,
+            tabs: Vec::new(),
+            log_tail: Vec::new(),
+        }
+    }
+
+    /// Fill in tab summary and log tail from the last saved crash state
+    ///
+    /// The state is ignored if it was written by a different process.
+    pub fn with_state(mut self, state: &CrashState) -> Self {
+        if state.pid != self.pid {
+            return self;
+        }
+        self.tabs = state
+            .state
+            .windows
+            .iter()
+            .flat_map(|w| w.tabs.iter())
+            .map(|t| CrashReportTab {
+                title: t.custom_title.clone().unwrap_or_else(|| t.title.clone()),
+                cwd: t.cwd.clone(),
+                template_name: t.template_name.clone(),
+            })
+            .collect();
+        self.log_tail = state.log_tail.clone();
+        self
+    }
+
+    /// File name used when writing this report
+    pub fn file_name(&self) -> String {
+        format!("crash-{}-{}.json", self.timestamp, self.pid)
+    }
+}
+
+/// Get the directory where crash reports are written
+pub fn crash_reports_dir() -> PathBuf {
+    cache_dir().join("crash_reports")
+}
+
+/// Get the path to the pending crash report pointer file
+fn pending_report_path() -> PathBuf {
+    cache_dir().join("pending_crash_report")
+}
+
+/// Write a crash report to `dir` and return its path
+fn write_report_to(report: &CrashReport, dir: &Path) -> io::Result<PathBuf> {
+    fs::create_dir_all(dir)?;
+
+    let json = serde_json::to_string_pretty(report)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+    let path = dir.join(report.file_name());
+    let temp_path = path.with_extension("tmp");
+    fs::write(&temp_path, json)?;
+
+    // Restrictive permissions (log lines and titles may contain sensitive data)
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let perms = std::fs::Permissions::from_mode(0o600);
+        fs::set_permissions(&temp_path, perms)?;
+    }
+
+    fs::rename(&temp_path, &path)?;
+
+    Ok(path)
+}
+
+/// Write a crash report and remember it for display on next launch
+pub fn write_crash_report(report: &CrashReport) -> io::Result<PathBuf> {
+    let path = write_report_to(report, &crash_reports_dir())?;
+    fs::write(pending_report_path(), path.to_string_lossy().as_bytes())?;
+
+    log::info!("Wrote crash report to {}", path.display());
+
+    Ok(path)
+}
+
+/// Take the path of the crash report written since the last launch, if any
+pub fn take_pending_crash_report() -> Option<PathBuf> {
+    let pointer = pending_report_path();
+
+    // Check for symlink before reading/removing
+    let meta = fs::symlink_metadata(&pointer).ok()?;
+    if meta.is_symlink() {
+        log::warn!("Pending crash report path is a symlink, ignoring");
+        return None;
+    }
+
+    let content = fs::read_to_string(&pointer).ok()?;
+    let _ = fs::remove_file(&pointer);
+
+    let path = PathBuf::from(content.trim());
+    path.is_file().then_some(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::upgrade::{TabUpgradeState, UpgradeState, WindowUpgradeState};
+
+    fn state_with_tabs(pid: u32) -> CrashState {
+        let mut window = WindowUpgradeState::new();
+        let mut tab = TabUpgradeState::new(1, 0, 42);
+        tab.title = "vim".to_string();
+        tab.cwd = Some("/home/user".to_string());
+        window.tabs.push(tab);
+        let mut tab = TabUpgradeState::new(2, 1, 43);
+        tab.title = "zsh".to_string();
+        tab.custom_title = Some("Build".to_string());
+        tab.template_name = Some("Dev".to_string());
+        window.tabs.push(tab);
+
+        let mut upgrade = UpgradeState::new("0.0.0");
+        upgrade.windows.push(window);
+
+        let mut state = CrashState::new(upgrade);
+        state.pid = pid;
+        state.log_tail = vec!["INFO last line".to_string()];
+        state
+    }
+
+    #[test]
+    fn test_report_from_signal() {
+        // Raw wait status for a process killed by SIGSEGV
+        let status = ExitStatus::from_raw(libc::SIGSEGV);
+        let report = CrashReport::new(1234, status, 1);
+        assert_eq!(report.signal, Some(libc::SIGSEGV));
+        assert_eq!(report.exit_code, None);
+        assert_eq!(report.version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(report.os, std::env::consts::OS);
+    }
+
+    #[test]
+    fn test_report_tab_summary() {
+        let status = ExitStatus::from_raw(101 << 8);
+        let report = CrashReport::new(1234, status, 0).with_state(&state_with_tabs(1234));
+        assert_eq!(report.exit_code, Some(101));
+        assert_eq!(report.signal, None);
+        assert_eq!(
+            report.tabs,
+            vec![
+                CrashReportTab {
+                    title: "vim".to_string(),
+                    cwd: Some("/home/user".to_string()),
+                    template_name: None,
+                },
+                CrashReportTab {
+                    title: "Build".to_string(),
+                    cwd: None,
+                    template_name: Some("Dev".to_string()),
+                },
+            ]
+        );
+        assert_eq!(report.log_tail, vec!["INFO last line".to_string()]);
+    }
+
+    #[test]
+    fn test_report_ignores_stale_state() {
+        let status = ExitStatus::from_raw(libc::SIGABRT);
+        let report = CrashReport::new(1234, status, 0).with_state(&state_with_tabs(999));
+        assert!(report.tabs.is_empty());
+        assert!(report.log_tail.is_empty());
+    }
+
+    #[test]
+    fn test_write_report() {
+        let dir = tempfile::tempdir().unwrap();
+        let status = ExitStatus::from_raw(libc::SIGSEGV);
+        let report = CrashReport::new(1234, status, 0).with_state(&state_with_tabs(1234));
+
+        let path = write_report_to(&report, dir.path()).unwrap();
+        assert_eq!(path.file_name().unwrap(), report.file_name().as_str());
+
+        let parsed: CrashReport =
+            serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(parsed.signal, Some(libc::SIGSEGV));
+        assert_eq!(parsed.tabs, report.tabs);
+
+        use std::os::unix::fs::PermissionsExt;
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+}
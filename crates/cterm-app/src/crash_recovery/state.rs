@@ -9,8 +9,12 @@ use std::path::PathBuf;
 use directories::ProjectDirs;
 use serde::{Deserialize, Serialize};
 
+use crate::log_capture;
 use crate::upgrade::UpgradeState;
 
+/// Number of recent log lines kept in the crash state for crash reports
+const CRASH_LOG_LINES: usize = 200;
+
 /// Crash state file - contains all info needed to recover
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CrashState {
@@ -20,6 +24,31 @@ pub struct CrashState {
     pub timestamp: u64,
     /// PID of the process that wrote this state
     pub pid: u32,
+    /// Most recent log lines at the time the state was written
+    #[serde(default)]
+    pub log_tail: Vec<String>,
+}
+
+/// Crash state as written before `log_tail` was added
+///
+/// bincode reads struct fields by position, so `#[serde(default)]` alone
+/// can't fill in a field missing from the end of an older file.
+#[derive(Deserialize)]
+struct LegacyCrashState {
+    state: UpgradeState,
+    timestamp: u64,
+    pid: u32,
+}
+
+impl From<LegacyCrashState> for CrashState {
+    fn from(legacy: LegacyCrashState) -> Self {
+        Self {
+            state: legacy.state,
+            timestamp: legacy.timestamp,
+            pid: legacy.pid,
+            log_tail: Vec::new(),
+        }
+    }
 }
 
 impl CrashState {
@@ -32,12 +61,16 @@ impl CrashState {
                 .map(|d| d.as_secs())
                 .unwrap_or(0),
             pid: std::process::id(),
+            log_tail: log_capture::get_recent_logs(CRASH_LOG_LINES)
+                .iter()
+                .map(|entry| entry.format())
+                .collect(),
         }
     }
 }
 
 /// Get the cache directory for cterm
-fn cache_dir() -> PathBuf {
+pub(super) fn cache_dir() -> PathBuf {
     ProjectDirs::from("com", "cterm", "cterm")
         .map(|dirs| dirs.cache_dir().to_path_buf())
         .unwrap_or_else(|| PathBuf::from("/tmp/cterm"))
@@ -90,8 +123,8 @@ pub fn read_crash_state() -> io::Result<CrashState> {
     let path = crash_state_path();
     let bytes = fs::read(&path)?;
 
-    let state: CrashState =
-        bincode::deserialize(&bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    let state =
+        decode_crash_state(&bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
 
     log::info!(
         "Read crash state: {} windows, written by PID {} at timestamp {}",
@@ -103,6 +136,17 @@ pub fn read_crash_state() -> io::Result<CrashState> {
     Ok(state)
 }
 
+/// Decode a crash state file, including ones written by earlier releases
+fn decode_crash_state(bytes: &[u8]) -> bincode::Result<CrashState> {
+    bincode::deserialize(bytes).or_else(|e| {
+        // Written by a release from before log_tail, when crash recovery
+        // matters most: right after upgrading
+        bincode::deserialize::<LegacyCrashState>(bytes)
+            .map(CrashState::from)
+            .map_err(|_| e)
+    })
+}
+
 /// Clear crash state file (called after successful startup)
 pub fn clear_crash_state() -> io::Result<()> {
     let path = crash_state_path();
@@ -160,3 +204,73 @@ pub fn read_crash_marker() -> Option<(i32, u32)> {
 
     Some((signal, pid))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_crash_state_without_log_tail() {
+        /// The layout written by releases before `log_tail`
+        #[derive(Serialize)]
+        struct OldCrashState {
+            state: UpgradeState,
+            timestamp: u64,
+            pid: u32,
+        }
+
+        let mut upgrade = UpgradeState::new("0.0.0");
+        upgrade
+            .windows
+            .push(crate::upgrade::WindowUpgradeState::new());
+        let old = OldCrashState {
+            state: upgrade.clone(),
+            timestamp: 1_700_000_000,
+            pid: 4242,
+        };
+        let bytes = bincode::serialize(&old).unwrap();
+
+        let state = decode_crash_state(&bytes).unwrap();
+        assert_eq!(state.state.windows.len(), 1);
+        assert_eq!(state.timestamp, 1_700_000_000);
+        assert_eq!(state.pid, 4242);
+        assert!(state.log_tail.is_empty());
+
+        // The current layout round-trips with its log lines
+        let mut current = CrashState::new(upgrade);
+        current.log_tail = vec!["INFO started".to_string()];
+        let bytes = bincode::serialize(&current).unwrap();
+        let state = decode_crash_state(&bytes).unwrap();
+        assert_eq!(state.log_tail, vec!["INFO started".to_string()]);
+        assert_eq!(state.pid, current.pid);
+    }
+
+    #[test]
+    fn test_decode_crash_state_with_grid() {
+        let mut terminal = cterm_core::Terminal::new(20, 5, Default::default());
+        for i in 0..10 {
+            terminal.process(format!("line-{}\r\n", i).as_bytes());
+        }
+        terminal.process("cafe\u{301}".as_bytes());
+
+        // Leave the optional fields unset
+        let mut window = crate::upgrade::WindowUpgradeState::new();
+        let mut tab = crate::upgrade::TabUpgradeState::new(1, 0, 42);
+        tab.terminal = crate::upgrade::TerminalUpgradeState::from_screen(terminal.screen());
+        assert!(!tab.terminal.scrollback.is_empty());
+        window.tabs.push(tab);
+        let mut upgrade = UpgradeState::new("0.0.0");
+        upgrade.windows.push(window);
+
+        let bytes = bincode::serialize(&CrashState::new(upgrade)).unwrap();
+        let state = decode_crash_state(&bytes).unwrap();
+
+        let tab = &state.state.windows[0].tabs[0];
+        assert!(tab.custom_title.is_none());
+        assert!(tab.terminal.scrollback_file.is_none());
+        assert_eq!(tab.terminal.scrollback[0].text().trim_end(), "line-0");
+        let row = tab.terminal.grid.row(4).unwrap();
+        assert_eq!(row.text(), "cafe");
+        assert_eq!(row[3].grapheme_text(), "e\u{301}");
+    }
+}
@@ -12,11 +12,12 @@ use std::os::unix::io::{AsRawFd, FromRawFd, RawFd};
 use std::os::unix::net::UnixStream;
 use std::os::unix::process::CommandExt;
 use std::path::Path;
-use std::process::{Child, Command};
+use std::process::{Child, Command, ExitStatus};
 
 use cterm_core::fd_passing;
 
-use super::state::write_crash_marker;
+use super::report::{write_crash_report, CrashReport};
+use super::state::{read_crash_state, write_crash_marker};
 
 /// Errors from the watchdog
 #[derive(Debug, thiserror::Error)]
@@ -157,16 +158,20 @@ pub fn run_watchdog(binary_path: &Path, args: &[String]) -> Result<i32, Watchdog
             for (_, held) in pty_fds.drain() {
                 unsafe { libc::close(held.fd) };
             }
-            return Ok(exit_status.unwrap_or(0));
+            return Ok(exit_status.code().unwrap_or(0));
         }
 
         // Check if it was a crash (signal) vs normal exit
-        let crashed = exit_status.is_none() || exit_status.unwrap() != 0;
+        let crashed = !exit_status.success();
+
+        if crashed {
+            write_report(child_pid as u32, exit_status, restart_count);
+        }
 
         if crashed && !pty_fds.is_empty() {
             restart_count += 1;
             log::warn!(
-                "Watchdog: child crashed with status {:?}, restart {}/{} (preserving {} FDs)",
+                "Watchdog: child crashed ({}), restart {}/{} (preserving {} FDs)",
                 exit_status,
                 restart_count,
                 max_restarts,
@@ -182,7 +187,7 @@ pub fn run_watchdog(binary_path: &Path, args: &[String]) -> Result<i32, Watchdog
             }
 
             // Write crash marker for the new process
-            let _ = write_crash_marker(exit_status.unwrap_or(-1));
+            let _ = write_crash_marker(marker_status(exit_status));
 
             // Small delay before restart
             std::thread::sleep(std::time::Duration::from_millis(100));
@@ -192,7 +197,7 @@ pub fn run_watchdog(binary_path: &Path, args: &[String]) -> Result<i32, Watchdog
             // Crashed but no FDs to recover - still restart but no recovery mode
             restart_count += 1;
             log::warn!(
-                "Watchdog: child crashed with status {:?}, restart {}/{} (no FDs to recover)",
+                "Watchdog: child crashed ({}), restart {}/{} (no FDs to recover)",
                 exit_status,
                 restart_count,
                 max_restarts
@@ -203,7 +208,7 @@ pub fn run_watchdog(binary_path: &Path, args: &[String]) -> Result<i32, Watchdog
                 return Ok(1);
             }
 
-            let _ = write_crash_marker(exit_status.unwrap_or(-1));
+            let _ = write_crash_marker(marker_status(exit_status));
             std::thread::sleep(std::time::Duration::from_millis(100));
         } else {
             log::info!("Watchdog: child exited normally");
@@ -215,6 +220,23 @@ pub fn run_watchdog(binary_path: &Path, args: &[String]) -> Result<i32, Watchdog
     }
 }
 
+/// Value recorded in the crash marker: the signal if killed by one, else the exit code
+fn marker_status(status: ExitStatus) -> i32 {
+    use std::os::unix::process::ExitStatusExt;
+    status.signal().or(status.code()).unwrap_or(-1)
+}
+
+/// Write a crash report for a crashed child, using its last saved crash state
+fn write_report(pid: u32, status: ExitStatus, restart_count: u32) {
+    let mut report = CrashReport::new(pid, status, restart_count);
+    if let Ok(state) = read_crash_state() {
+        report = report.with_state(&state);
+    }
+    if let Err(e) = write_crash_report(&report) {
+        log::error!("Watchdog: failed to write crash report: {}", e);
+    }
+}
+
 /// Send recovery FDs to the new process
 fn send_recovery_fds(sock: &UnixStream, pty_fds: &HashMap<u64, HeldFd>) -> io::Result<()> {
     // Build list of (id, fd, pid) tuples
@@ -260,7 +282,7 @@ fn monitor_child(
     next_fd_id: &mut u64,
     graceful_shutdown: &mut bool,
     child_pid: i32,
-) -> Result<Option<i32>, WatchdogError> {
+) -> Result<ExitStatus, WatchdogError> {
     let mut buf = [0u8; 1024];
 
     // Keep socket in blocking mode for recv_fds to work properly
@@ -269,7 +291,7 @@ fn monitor_child(
     loop {
         // Check if child has exited
         if let Some(status) = child.try_wait()? {
-            return Ok(status.code());
+            return Ok(status);
         }
 
         // Use poll to check if data is available (with 50ms timeout)
@@ -294,7 +316,7 @@ fn monitor_child(
                 if data_len == 0 && fds.is_empty() {
                     // Child closed socket, wait for exit
                     let status = child.wait()?;
-                    return Ok(status.code());
+                    return Ok(status);
                 }
 
                 // Process message
@@ -370,7 +392,7 @@ fn monitor_child(
                 // On EOF (socket closed), wait for child exit
                 if e.kind() == io::ErrorKind::UnexpectedEof {
                     let status = child.wait()?;
-                    return Ok(status.code());
+                    return Ok(status);
                 }
                 return Err(WatchdogError::Io(e));
             }
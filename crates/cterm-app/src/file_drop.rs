@@ -70,6 +70,15 @@ pub fn shell_escape(s: &str) -> String {
     out
 }
 
+/// Shell-escaped paths of several dropped files, separated by spaces.
+pub fn paste_paths(paths: &[PathBuf]) -> String {
+    paths
+        .iter()
+        .map(|path| shell_escape(&path.to_string_lossy()))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
 /// Build the string that should be written to the PTY for the given action.
 pub fn build_pty_input(info: &FileDropInfo, action: FileDropAction) -> io::Result<String> {
     match action {
@@ -171,6 +180,16 @@ mod tests {
         assert_eq!(result, "'/tmp/my file.txt'");
     }
 
+    #[test]
+    fn test_paste_paths() {
+        let paths = [
+            PathBuf::from("/tmp/a.txt"),
+            PathBuf::from("/tmp/it's b.txt"),
+        ];
+        assert_eq!(paste_paths(&paths), "'/tmp/a.txt' '/tmp/it'\\''s b.txt'");
+        assert_eq!(paste_paths(&paths[..1]), "'/tmp/a.txt'");
+    }
+
     #[test]
     fn test_build_create_via_base64() {
         let dir = tempfile::tempdir().unwrap();
@@ -2,10 +2,15 @@
 //!
 //! Manages pending file transfers received via OSC 1337 with inline=0.
 //! Supports both small in-memory files and large streaming files stored on disk.
+//! Transfers over `general.max_file_transfer_bytes` are aborted by the parser
+//! before they reach the manager.
 
 use cterm_core::StreamingFileData;
 use std::path::PathBuf;
 
+/// Default largest file accepted over a transfer (4 GiB)
+pub const DEFAULT_MAX_FILE_TRANSFER_BYTES: u64 = 4 * 1024 * 1024 * 1024;
+
 /// Where the file data is stored
 #[derive(Debug)]
 pub enum FileData {
@@ -2,8 +2,9 @@
 //!
 //! Provides git operations for syncing configuration across machines.
 
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use thiserror::Error;
 
@@ -478,12 +479,72 @@ pub fn clone_repo(remote_url: &str, target_dir: &Path) -> Result<(), GitError> {
     }
 }
 
+/// Expand the tokens in a template's working directory
+///
+/// `{date}` is the date of `now` as `YYYY-MM-DD` (UTC) and `{home}` the home
+/// directory; `~` and environment variables were already expanded when the
+/// templates were loaded. If a placeholder is left in the result, i.e. an
+/// unset variable or an unknown token, the home directory is used instead.
+pub fn expand_working_directory(working_dir: &Path, now: SystemTime) -> PathBuf {
+    let home = directories::BaseDirs::new().map(|dirs| dirs.home_dir().to_path_buf());
+    let mut expanded = working_dir.to_string_lossy().into_owned();
+    if expanded.contains("{date}") {
+        let secs = now
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let (year, month, day) = crate::util::civil_from_days((secs / 86400) as i64);
+        let date = format!("{:04}-{:02}-{:02}", year, month, day);
+        expanded = expanded.replace("{date}", &date);
+    }
+    if let Some(ref home) = home {
+        expanded = expanded.replace("{home}", &home.to_string_lossy());
+    }
+
+    let path = PathBuf::from(&expanded);
+    if !has_placeholder(&expanded) {
+        return path;
+    }
+    match home {
+        Some(home) => {
+            log::warn!(
+                "Working directory {} expanded to {}, using {} instead",
+                working_dir.display(),
+                expanded,
+                home.display()
+            );
+            home
+        }
+        None => path,
+    }
+}
+
+/// Whether `path` still holds a `$VAR`, `${VAR}` or `{token}` placeholder
+fn has_placeholder(path: &str) -> bool {
+    // Only checked when a template tab opens, so compiling it each time is fine
+    regex::Regex::new(r"\$\{?[A-Za-z_][A-Za-z0-9_]*\}?|\{[A-Za-z_][A-Za-z0-9_]*\}")
+        .expect("valid placeholder pattern")
+        .is_match(path)
+}
+
 /// Prepare a template's working directory
-/// If the directory doesn't exist but a git remote is configured, clone it
-/// Returns Ok(true) if cloning was performed, Ok(false) if directory already existed
+/// Expands its tokens (see [`expand_working_directory`]), then if the
+/// directory doesn't exist, clones the git remote into it or creates it.
+/// Returns the expanded directory, and whether cloning was performed
 pub fn prepare_working_directory(
     working_dir: &Path,
     git_remote: Option<&str>,
+) -> Result<(PathBuf, bool), GitError> {
+    let working_dir = expand_working_directory(working_dir, SystemTime::now());
+    let cloned = create_working_directory(&working_dir, git_remote)?;
+    Ok((working_dir, cloned))
+}
+
+/// Clone or create a working directory that may not exist yet
+/// Returns Ok(true) if cloning was performed, Ok(false) otherwise
+fn create_working_directory(
+    working_dir: &Path,
+    git_remote: Option<&str>,
 ) -> Result<bool, GitError> {
     if working_dir.exists() {
         return Ok(false);
@@ -763,8 +824,9 @@ mod tests {
     fn test_prepare_working_directory_exists() {
         let temp = TempDir::new().unwrap();
         // Directory already exists, should return Ok(false)
-        let result = prepare_working_directory(temp.path(), None).unwrap();
-        assert!(!result);
+        let (dir, cloned) = prepare_working_directory(temp.path(), None).unwrap();
+        assert_eq!(dir, temp.path());
+        assert!(!cloned);
     }
 
     #[test]
@@ -772,11 +834,68 @@ mod tests {
         let temp = TempDir::new().unwrap();
         let new_dir = temp.path().join("subdir");
         // Directory doesn't exist, no remote, should create it
-        let result = prepare_working_directory(&new_dir, None).unwrap();
-        assert!(!result); // false because no cloning was done
+        let (_, cloned) = prepare_working_directory(&new_dir, None).unwrap();
+        assert!(!cloned); // false because no cloning was done
         assert!(new_dir.exists());
     }
 
+    #[test]
+    fn test_expand_working_directory() {
+        let home = directories::BaseDirs::new()
+            .unwrap()
+            .home_dir()
+            .to_path_buf();
+        // 2025-01-31 12:00 UTC
+        let now = UNIX_EPOCH + std::time::Duration::from_secs(1_738_324_800);
+
+        assert_eq!(
+            expand_working_directory(Path::new("/tmp/scratch/{date}"), now),
+            PathBuf::from("/tmp/scratch/2025-01-31")
+        );
+        assert_eq!(
+            expand_working_directory(Path::new("{home}/scratch"), now),
+            home.join("scratch")
+        );
+        assert_eq!(
+            expand_working_directory(Path::new("/srv/project"), now),
+            PathBuf::from("/srv/project")
+        );
+
+        // Relative paths and other braces are kept as they are
+        assert_eq!(
+            expand_working_directory(Path::new("scratch"), now),
+            PathBuf::from("scratch")
+        );
+        assert_eq!(
+            expand_working_directory(Path::new("/srv/a{1,2}/$"), now),
+            PathBuf::from("/srv/a{1,2}/$")
+        );
+
+        // Unset variables and unknown tokens fall back to home
+        assert_eq!(
+            expand_working_directory(Path::new("$CTERM_UNSET_VAR/x"), now),
+            home
+        );
+        assert_eq!(
+            expand_working_directory(Path::new("${CTERM_UNSET_VAR}/x"), now),
+            home
+        );
+        assert_eq!(
+            expand_working_directory(Path::new("/tmp/{branch}"), now),
+            home
+        );
+    }
+
+    #[test]
+    fn test_prepare_working_directory_expands_date() {
+        let temp = TempDir::new().unwrap();
+        let template = temp.path().join("scratch").join("{date}");
+        let (dir, _) = prepare_working_directory(&template, None).unwrap();
+        assert!(dir.starts_with(temp.path().join("scratch")));
+        assert!(!dir.to_string_lossy().contains("{date}"));
+        assert!(dir.is_dir());
+    }
+
     #[test]
     fn test_prepare_working_directory_clone_fails_invalid_remote() {
         let temp = TempDir::new().unwrap();
@@ -2,8 +2,9 @@
 //!
 //! This crate contains the application logic that is independent of the UI,
 //! including configuration management, session handling, sticky tabs,
-//! seamless upgrade functionality, and crash recovery.
+//! seamless upgrade functionality, crash recovery, and persistent sessions.
 
+pub mod clipboard_history;
 pub mod config;
 #[cfg(unix)]
 pub mod crash_recovery;
@@ -12,10 +13,18 @@ pub mod file_drop;
 pub mod file_transfer;
 pub mod git_sync;
 pub mod log_capture;
+pub mod notify;
 pub mod quick_open;
+pub mod recording;
+pub mod replay;
+pub mod send_bytes;
 pub mod session;
+#[cfg(unix)]
+pub mod session_keeper;
 pub mod shortcuts;
 pub mod upgrade;
+mod util;
+pub mod workspace;
 
 pub use config::{
     background_sync, load_config, load_sticky_tabs, load_tool_shortcuts, save_config,
@@ -23,17 +32,18 @@ pub use config::{
 };
 #[cfg(unix)]
 pub use crash_recovery::{
-    clear_crash_state, crash_marker_path, crash_state_path, notify_watchdog_shutdown,
-    read_crash_marker, read_crash_state, receive_recovery_fds, register_fd_with_watchdog,
-    run_watchdog, unregister_fd_with_watchdog, write_crash_state, CrashState, RecoveredFd,
-    WatchdogError,
+    clear_crash_state, crash_marker_path, crash_reports_dir, crash_state_path,
+    notify_watchdog_shutdown, read_crash_marker, read_crash_state, receive_recovery_fds,
+    register_fd_with_watchdog, run_watchdog, take_pending_crash_report,
+    unregister_fd_with_watchdog, write_crash_report, write_crash_state, CrashReport,
+    CrashReportTab, CrashState, RecoveredFd, WatchdogError,
 };
 pub use git_sync::{
-    clone_repo, get_directory_remote_url, get_remote_url, get_sync_status, init_with_remote,
-    is_git_repo, prepare_working_directory, pull_with_conflict_resolution, GitError, InitResult,
-    PullResult, SyncStatus,
+    clone_repo, expand_working_directory, get_directory_remote_url, get_remote_url,
+    get_sync_status, init_with_remote, is_git_repo, prepare_working_directory,
+    pull_with_conflict_resolution, GitError, InitResult, PullResult, SyncStatus,
 };
-pub use session::{Session, TabState, WindowState};
+pub use session::{ClosedWindows, QuitSummary, Session, TabState, WindowState};
 pub use shortcuts::ShortcutManager;
 #[cfg(windows)]
 pub use upgrade::{execute_upgrade, receive_upgrade, HandleInfo, UpgradeError, WindowsUpgradeData};
@@ -42,3 +52,4 @@ pub use upgrade::{execute_upgrade, receive_upgrade, UpgradeError};
 pub use upgrade::{UpdateError, UpdateInfo, Updater, UpgradeState};
 
 pub use quick_open::{template_type_indicator, QuickOpenMatcher, TemplateMatch};
+pub use workspace::{load_workspace, Workspace, WorkspaceError, WorkspaceTab, WorkspaceWindow};
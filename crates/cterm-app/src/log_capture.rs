@@ -78,6 +78,11 @@ impl LogBuffer {
     fn get_all(&self) -> Vec<LogEntry> {
         self.entries.iter().cloned().collect()
     }
+
+    fn get_recent(&self, count: usize) -> Vec<LogEntry> {
+        let skip = self.entries.len().saturating_sub(count);
+        self.entries.iter().skip(skip).cloned().collect()
+    }
 }
 
 /// Global log buffer
@@ -192,6 +197,16 @@ pub fn get_logs() -> Vec<LogEntry> {
     Vec::new()
 }
 
+/// Get the most recent `count` captured log entries, oldest first
+pub fn get_recent_logs(count: usize) -> Vec<LogEntry> {
+    if let Ok(guard) = LOG_BUFFER.lock() {
+        if let Some(ref buffer) = *guard {
+            return buffer.get_recent(count);
+        }
+    }
+    Vec::new()
+}
+
 /// Get logs formatted as a single string
 pub fn get_logs_formatted() -> String {
     get_logs()
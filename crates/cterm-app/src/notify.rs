@@ -0,0 +1,301 @@
+//! Desktop notifications
+//!
+//! A bell in a tab you aren't looking at only marks the tab. When a program
+//! keeps ringing (a build that failed, a chat client, a prompt waiting for
+//! input) that's easy to miss, so after enough bells in a short time the
+//! frontends escalate to a desktop notification. This module decides when
+//! to do that and what the notification says; showing it is up to each UI.
+//! Before any of that, bells are rate limited so a runaway program can't make
+//! the tab and title indicators flicker.
+//!
+//! Events can also play a sound, chosen per event in `general.sounds`, so a
+//! finished file transfer sounds different from a bell.
+
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::{expand_path, GeneralConfig};
+
+/// A desktop notification to show
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Notification {
+    pub title: String,
+    pub body: String,
+}
+
+/// Counts the bells of one tab and decides when to notify
+#[derive(Debug, Clone)]
+pub struct BellEscalation {
+    /// Number of bells that trigger a notification (0 = never)
+    threshold: usize,
+    /// Time window the bells have to fall into
+    window: Duration,
+    /// Times of the recent bells in an unfocused tab, oldest first
+    bells: VecDeque<Instant>,
+}
+
+impl BellEscalation {
+    /// Notify after `threshold` bells within `window` (0 disables it)
+    pub fn new(threshold: usize, window: Duration) -> Self {
+        Self {
+            threshold,
+            window,
+            bells: VecDeque::new(),
+        }
+    }
+
+    /// Escalation as configured by `bell_notification_threshold` and
+    /// `bell_notification_window_secs`
+    pub fn from_config(general: &GeneralConfig) -> Self {
+        Self::new(
+            general.bell_notification_threshold,
+            Duration::from_secs(general.bell_notification_window_secs),
+        )
+    }
+
+    /// Whether bells can escalate at all
+    pub fn is_enabled(&self) -> bool {
+        self.threshold > 0
+    }
+
+    /// Record a bell in an unfocused tab
+    ///
+    /// Returns true when this bell is the `threshold`th within the window;
+    /// the count then starts over, so a tab that keeps ringing notifies
+    /// again only after another `threshold` bells.
+    pub fn ring(&mut self, now: Instant) -> bool {
+        if !self.is_enabled() {
+            return false;
+        }
+
+        while let Some(&first) = self.bells.front() {
+            if now.saturating_duration_since(first) <= self.window {
+                break;
+            }
+            self.bells.pop_front();
+        }
+        self.bells.push_back(now);
+
+        if self.bells.len() >= self.threshold {
+            self.bells.clear();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Forget the counted bells (the tab was looked at)
+    pub fn reset(&mut self) {
+        self.bells.clear();
+    }
+}
+
+/// Coalesces bells that ring in quick succession
+#[derive(Debug, Clone)]
+pub struct BellRateLimiter {
+    /// Minimum time between handled bells (zero = no limit)
+    interval: Duration,
+    /// When the last handled bell rang
+    last: Option<Instant>,
+}
+
+impl BellRateLimiter {
+    /// Handle at most one bell per `interval`
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            last: None,
+        }
+    }
+
+    /// Rate limit as configured by `bell_rate_limit_ms`
+    pub fn from_config(general: &GeneralConfig) -> Self {
+        Self::new(Duration::from_millis(general.bell_rate_limit_ms))
+    }
+
+    /// Whether a bell ringing at `now` should be handled
+    ///
+    /// Bells within `interval` of the last handled one are dropped, so a
+    /// steady flood is handled once per interval.
+    pub fn allow(&mut self, now: Instant) -> bool {
+        if let Some(last) = self.last {
+            if now.saturating_duration_since(last) < self.interval {
+                return false;
+            }
+        }
+        self.last = Some(now);
+        true
+    }
+}
+
+/// Events that play a sound
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SoundEvent {
+    /// The bell rang
+    Bell,
+    /// A file arrived over OSC 1337
+    FileReceived,
+}
+
+/// A sound to play
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Sound {
+    /// The system beep
+    Beep,
+    /// A sound from the platform's sound theme (e.g. "complete" from the
+    /// freedesktop theme, "Glass" on macOS, "SystemAsterisk" on Windows)
+    Named(String),
+    /// A sound file
+    File(PathBuf),
+}
+
+impl Sound {
+    /// Parse a `general.sounds` value: "beep", "none", a path (anything with
+    /// a slash, or starting with `~`) or a system sound name
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim();
+        match value {
+            "" | "none" => None,
+            "beep" => Some(Sound::Beep),
+            _ if value.starts_with('~') || value.contains(['/', '\\']) => {
+                Some(Sound::File(expand_path(Path::new(value))))
+            }
+            _ => Some(Sound::Named(value.to_string())),
+        }
+    }
+}
+
+/// The sound to play for `event`, or `None` if it is silent or sounds are
+/// muted
+pub fn sound_for(general: &GeneralConfig, event: SoundEvent) -> Option<Sound> {
+    if general.mute_sounds {
+        return None;
+    }
+    match general.sounds.get(&event) {
+        Some(value) => Sound::parse(value),
+        None => Some(Sound::Beep),
+    }
+}
+
+/// The notification shown when bells in the tab titled `tab_title` escalate
+pub fn bell_notification(tab_title: &str) -> Notification {
+    let tab_title = tab_title.trim();
+    Notification {
+        title: "cterm".to_string(),
+        body: if tab_title.is_empty() {
+            "A tab keeps ringing the bell".to_string()
+        } else {
+            format!("\"{}\" keeps ringing the bell", tab_title)
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ring() {
+        let start = Instant::now();
+        let at = |secs: u64| start + Duration::from_secs(secs);
+        let mut escalation = BellEscalation::new(3, Duration::from_secs(10));
+
+        assert!(!escalation.ring(at(0)));
+        assert!(!escalation.ring(at(1)));
+        assert!(escalation.ring(at(2)));
+
+        // Counting starts over after a notification
+        assert!(!escalation.ring(at(3)));
+        assert!(!escalation.ring(at(4)));
+
+        // Bells older than the window don't count
+        assert!(!escalation.ring(at(20)));
+        assert!(!escalation.ring(at(21)));
+        assert!(escalation.ring(at(22)));
+
+        escalation.ring(at(30));
+        escalation.ring(at(31));
+        escalation.reset();
+        assert!(!escalation.ring(at(32)));
+
+        let mut disabled = BellEscalation::new(0, Duration::from_secs(10));
+        assert!(!disabled.is_enabled());
+        assert!(!disabled.ring(at(0)));
+    }
+
+    #[test]
+    fn test_bell_rate_limiter() {
+        let start = Instant::now();
+        let at = |ms: u64| start + Duration::from_millis(ms);
+        let mut limiter = BellRateLimiter::new(Duration::from_millis(100));
+
+        assert!(limiter.allow(at(0)));
+        assert!(!limiter.allow(at(10)));
+        assert!(!limiter.allow(at(99)));
+        // Measured from the last handled bell, so a flood still rings
+        assert!(limiter.allow(at(100)));
+        assert!(!limiter.allow(at(150)));
+        assert!(limiter.allow(at(500)));
+
+        let mut unlimited = BellRateLimiter::new(Duration::ZERO);
+        assert!(unlimited.allow(at(0)));
+        assert!(unlimited.allow(at(0)));
+    }
+
+    #[test]
+    fn test_sound_for() {
+        let mut general = GeneralConfig::default();
+        assert_eq!(sound_for(&general, SoundEvent::Bell), Some(Sound::Beep));
+
+        general
+            .sounds
+            .insert(SoundEvent::Bell, "complete".to_string());
+        general
+            .sounds
+            .insert(SoundEvent::FileReceived, "/tmp/done.oga".to_string());
+        assert_eq!(
+            sound_for(&general, SoundEvent::Bell),
+            Some(Sound::Named("complete".to_string()))
+        );
+        assert_eq!(
+            sound_for(&general, SoundEvent::FileReceived),
+            Some(Sound::File(PathBuf::from("/tmp/done.oga")))
+        );
+
+        general.sounds.insert(SoundEvent::Bell, "none".to_string());
+        assert_eq!(sound_for(&general, SoundEvent::Bell), None);
+
+        general.mute_sounds = true;
+        assert_eq!(sound_for(&general, SoundEvent::FileReceived), None);
+    }
+
+    #[test]
+    fn test_sounds_config() {
+        let config: crate::config::Config = toml::from_str(
+            r#"
+            [general.sounds]
+            bell = "beep"
+            file_received = "~/sounds/done.oga"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.general.sounds[&SoundEvent::Bell], "beep");
+        assert!(matches!(
+            sound_for(&config.general, SoundEvent::FileReceived),
+            Some(Sound::File(path)) if path.ends_with("sounds/done.oga")
+        ));
+    }
+
+    #[test]
+    fn test_bell_notification() {
+        assert_eq!(
+            bell_notification("make").body,
+            "\"make\" keeps ringing the bell"
+        );
+        assert_eq!(bell_notification(" ").body, "A tab keeps ringing the bell");
+    }
+}
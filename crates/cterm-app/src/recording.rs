@@ -0,0 +1,136 @@
+//! Session recording
+//!
+//! Starts and stops `script(1)`-style recordings of a tab's session. The
+//! recorder itself lives in `cterm_core::recording`; this module picks the
+//! file from the configuration.
+
+use std::io;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use cterm_core::recording::{RecordingMode, SessionRecorder};
+use cterm_core::Terminal;
+
+use crate::config::GeneralConfig;
+use crate::util::civil_from_days;
+
+/// Marker shown in the tab title of a tab that is being recorded
+pub const RECORDING_INDICATOR: &str = "⏺";
+
+/// What toggling a recording did
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RecordingToggle {
+    /// A new recording was started in this file
+    Started(PathBuf),
+    /// The recording was stopped and saved to this file
+    Stopped(PathBuf),
+}
+
+/// Directory new recordings are written to
+///
+/// `recording_directory` from the configuration, or `cterm-recordings` in
+/// the home directory.
+pub fn recording_directory(general: &GeneralConfig) -> Option<PathBuf> {
+    general.recording_directory.clone().or_else(|| {
+        directories::UserDirs::new().map(|dirs| dirs.home_dir().join("cterm-recordings"))
+    })
+}
+
+/// File name for a recording started at `time`, e.g.
+/// `cterm-20250131-142501.txt` (UTC)
+pub fn recording_file_name(mode: RecordingMode, time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let (year, month, day) = civil_from_days((secs / 86400) as i64);
+    let secs_of_day = secs % 86400;
+    format!(
+        "cterm-{:04}{:02}{:02}-{:02}{:02}{:02}.{}",
+        year,
+        month,
+        day,
+        secs_of_day / 3600,
+        secs_of_day / 60 % 60,
+        secs_of_day % 60,
+        mode.extension()
+    )
+}
+
+/// Start recording a terminal into a new file in the recording directory
+pub fn start(terminal: &mut Terminal, general: &GeneralConfig) -> io::Result<PathBuf> {
+    let dir = recording_directory(general).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            "No recording directory configured and no home directory found",
+        )
+    })?;
+    std::fs::create_dir_all(&dir)?;
+
+    let path = dir.join(recording_file_name(
+        general.recording_mode,
+        SystemTime::now(),
+    ));
+    let recorder = SessionRecorder::create(&path, general.recording_mode, general.record_input)?;
+    terminal.start_recording(recorder);
+    log::info!("Recording session to {:?}", path);
+    Ok(path)
+}
+
+/// Stop the terminal's recording, or start one if it isn't being recorded
+pub fn toggle(terminal: &mut Terminal, general: &GeneralConfig) -> io::Result<RecordingToggle> {
+    match terminal.stop_recording() {
+        Some(result) => {
+            let path = result?;
+            log::info!("Saved session recording to {:?}", path);
+            Ok(RecordingToggle::Stopped(path))
+        }
+        None => start(terminal, general).map(RecordingToggle::Started),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_recording_file_name() {
+        let time = UNIX_EPOCH + Duration::from_secs(1_738_333_501);
+        assert_eq!(
+            recording_file_name(RecordingMode::Text, time),
+            "cterm-20250131-142501.txt"
+        );
+        assert_eq!(
+            recording_file_name(
+                RecordingMode::Raw,
+                UNIX_EPOCH + Duration::from_secs(951_782_400)
+            ),
+            "cterm-20000229-000000.log"
+        );
+    }
+
+    #[test]
+    fn test_toggle() {
+        let dir = std::env::temp_dir().join(format!("cterm-recordings-{}", std::process::id()));
+        let general = GeneralConfig {
+            recording_directory: Some(dir.clone()),
+            ..GeneralConfig::default()
+        };
+        let mut terminal = Terminal::new(80, 24, Default::default());
+
+        let started = toggle(&mut terminal, &general).unwrap();
+        let RecordingToggle::Started(path) = started else {
+            panic!("expected a new recording, got {:?}", started);
+        };
+        assert!(path.starts_with(&dir));
+        terminal.process(b"hello\r\n");
+
+        assert_eq!(
+            toggle(&mut terminal, &general).unwrap(),
+            RecordingToggle::Stopped(path.clone())
+        );
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "hello\n");
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
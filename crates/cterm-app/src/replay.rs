@@ -0,0 +1,133 @@
+//! Feeding captured output for the "Replay File" debug action
+//!
+//! Rendering bugs are easiest to track down with the exact bytes a program
+//! wrote. A capture (a `script` typescript, a raw log or a session recording
+//! made with `recording_mode = "raw"`) is fed to the terminal as if it came
+//! from the PTY, throttled so each step can be watched.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Default replay speed, in bytes per second
+pub const DEFAULT_REPLAY_BYTES_PER_SEC: u64 = 4096;
+
+/// Most bytes fed in one step
+const CHUNK_SIZE: usize = 4096;
+
+/// Pause between steps while waiting for the next bytes to be due
+const TICK: Duration = Duration::from_millis(10);
+
+/// A replay running on a background thread
+///
+/// Dropping it stops the replay.
+pub struct Replay {
+    stop: Arc<AtomicBool>,
+    finished: Arc<AtomicBool>,
+}
+
+impl Replay {
+    /// Start feeding `data` to `feed`, `bytes_per_sec` bytes per second
+    /// (0 = as fast as `feed` takes them)
+    ///
+    /// `feed` runs on the replay thread; returning false stops the replay
+    /// (e.g. because the tab was closed).
+    pub fn start<F>(data: Vec<u8>, bytes_per_sec: u64, mut feed: F) -> Self
+    where
+        F: FnMut(&[u8]) -> bool + Send + 'static,
+    {
+        let stop = Arc::new(AtomicBool::new(false));
+        let finished = Arc::new(AtomicBool::new(false));
+        let replay = Self {
+            stop: Arc::clone(&stop),
+            finished: Arc::clone(&finished),
+        };
+
+        thread::spawn(move || {
+            let started = Instant::now();
+            let mut sent = 0;
+            while sent < data.len() && !stop.load(Ordering::Relaxed) {
+                let mut end = (sent + CHUNK_SIZE).min(data.len());
+                if bytes_per_sec > 0 {
+                    let due = (started.elapsed().as_secs_f64() * bytes_per_sec as f64) as usize;
+                    if due <= sent {
+                        thread::sleep(TICK);
+                        continue;
+                    }
+                    end = end.min(due);
+                }
+                if !feed(&data[sent..end]) {
+                    break;
+                }
+                sent = end;
+            }
+            log::info!("Replay finished after {} of {} bytes", sent, data.len());
+            finished.store(true, Ordering::Relaxed);
+        });
+
+        replay
+    }
+
+    /// Stop the replay; bytes already fed stay on screen
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether all bytes were fed or the replay was stopped
+    pub fn is_finished(&self) -> bool {
+        self.finished.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for Replay {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    fn wait_until_finished(replay: &Replay) {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !replay.is_finished() {
+            assert!(Instant::now() < deadline, "replay didn't finish");
+            thread::sleep(TICK);
+        }
+    }
+
+    #[test]
+    fn test_replay() {
+        let data: Vec<u8> = (0..10_000u32).map(|i| i as u8).collect();
+        let fed = Arc::new(Mutex::new(Vec::new()));
+
+        let fed_clone = Arc::clone(&fed);
+        let replay = Replay::start(data.clone(), 0, move |chunk| {
+            fed_clone.lock().unwrap().extend_from_slice(chunk);
+            true
+        });
+        wait_until_finished(&replay);
+        assert_eq!(*fed.lock().unwrap(), data);
+
+        // A slow replay is stopped long before it's done
+        fed.lock().unwrap().clear();
+        let fed_clone = Arc::clone(&fed);
+        let replay = Replay::start(data.clone(), 100, move |chunk| {
+            fed_clone.lock().unwrap().extend_from_slice(chunk);
+            true
+        });
+        thread::sleep(Duration::from_millis(50));
+        replay.stop();
+        wait_until_finished(&replay);
+        let fed_len = fed.lock().unwrap().len();
+        assert!(fed_len < 100, "fed {} bytes", fed_len);
+        assert_eq!(*fed.lock().unwrap(), data[..fed_len]);
+
+        // The feed going away ends the replay
+        let replay = Replay::start(data, 0, |_| false);
+        wait_until_finished(&replay);
+    }
+}
@@ -0,0 +1,127 @@
+//! Decoding input for the "Send Bytes" debug action
+//!
+//! Lets escape-sequence bugs be reproduced by typing the exact bytes to send
+//! to the shell, instead of crafting a `printf` one-liner. Two notations are
+//! accepted:
+//!
+//! - hex byte pairs separated by spaces, e.g. `1b 5b 41`
+//! - text with C-style escapes, e.g. `\e[A` or `\x1b]0;title\x07`
+
+use thiserror::Error;
+
+/// Errors in "Send Bytes" input
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum SendBytesError {
+    #[error("Nothing to send")]
+    Empty,
+
+    #[error("Unknown escape \\{0}")]
+    UnknownEscape(char),
+
+    #[error("\\x needs two hex digits")]
+    InvalidHexEscape,
+
+    #[error("Trailing backslash")]
+    TrailingBackslash,
+}
+
+/// Decode "Send Bytes" input into the bytes to write to the PTY
+///
+/// Input made only of space-separated two-digit hex tokens is read as hex;
+/// anything else is text where `\xNN`, `\e` (ESC), `\a`, `\b`, `\t`, `\n`,
+/// `\r`, `\0` and `\\` are expanded. Unlike `send_text` snippets, unknown
+/// escapes are rejected rather than kept, so typos don't send the wrong bytes.
+pub fn parse_send_bytes(input: &str) -> Result<Vec<u8>, SendBytesError> {
+    if input.trim().is_empty() {
+        return Err(SendBytesError::Empty);
+    }
+
+    if let Some(bytes) = parse_hex_pairs(input) {
+        return Ok(bytes);
+    }
+
+    let mut bytes = Vec::with_capacity(input.len());
+    let mut chars = input.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            let mut buf = [0u8; 4];
+            bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+            continue;
+        }
+        let byte = match chars.next() {
+            Some('x') => {
+                let hex: String = chars.by_ref().take(2).collect();
+                if hex.len() != 2 {
+                    return Err(SendBytesError::InvalidHexEscape);
+                }
+                u8::from_str_radix(&hex, 16).map_err(|_| SendBytesError::InvalidHexEscape)?
+            }
+            Some('e') => 0x1b,
+            Some('a') => 0x07,
+            Some('b') => 0x08,
+            Some('t') => b'\t',
+            Some('n') => b'\n',
+            Some('r') => b'\r',
+            Some('0') => 0,
+            Some('\\') => b'\\',
+            Some(other) => return Err(SendBytesError::UnknownEscape(other)),
+            None => return Err(SendBytesError::TrailingBackslash),
+        };
+        bytes.push(byte);
+    }
+    Ok(bytes)
+}
+
+/// Read `1b 5b 41` style input; None if any token isn't two hex digits
+fn parse_hex_pairs(input: &str) -> Option<Vec<u8>> {
+    input
+        .split_whitespace()
+        .map(|token| {
+            if token.len() == 2 {
+                u8::from_str_radix(token, 16).ok()
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_send_bytes() {
+        assert_eq!(parse_send_bytes("1b 5b 41"), Ok(vec![0x1b, b'[', b'A']));
+        assert_eq!(parse_send_bytes("\\e[A"), Ok(vec![0x1b, b'[', b'A']));
+        assert_eq!(
+            parse_send_bytes("\\x1b]0;hi\\x07"),
+            Ok(b"\x1b]0;hi\x07".to_vec())
+        );
+        assert_eq!(
+            parse_send_bytes("a\\tb\\r\\n\\\\"),
+            Ok(b"a\tb\r\n\\".to_vec())
+        );
+        assert_eq!(parse_send_bytes("é"), Ok("é".as_bytes().to_vec()));
+        // Not all hex pairs, so read as text
+        assert_eq!(parse_send_bytes("1b [A"), Ok(b"1b [A".to_vec()));
+
+        assert_eq!(parse_send_bytes("  "), Err(SendBytesError::Empty));
+        assert_eq!(
+            parse_send_bytes("\\q"),
+            Err(SendBytesError::UnknownEscape('q'))
+        );
+        assert_eq!(
+            parse_send_bytes("\\x1"),
+            Err(SendBytesError::InvalidHexEscape)
+        );
+        assert_eq!(
+            parse_send_bytes("\\xzz"),
+            Err(SendBytesError::InvalidHexEscape)
+        );
+        assert_eq!(
+            parse_send_bytes("abc\\"),
+            Err(SendBytesError::TrailingBackslash)
+        );
+    }
+}
@@ -2,6 +2,7 @@
 //!
 //! Handles terminal sessions, tabs, and window state.
 
+use std::collections::VecDeque;
 use std::path::PathBuf;
 use std::sync::atomic::{AtomicU64, Ordering};
 
@@ -11,7 +12,10 @@ use cterm_core::pty::{PtyConfig, PtyError};
 use cterm_core::screen::ScreenConfig;
 use cterm_core::term::Terminal;
 
-use crate::config::StickyTabConfig;
+use crate::config::{GeneralConfig, StickyTabConfig};
+
+/// Maximum number of closed windows kept for "Reopen Closed Window"
+pub const MAX_CLOSED_WINDOWS: usize = 5;
 
 /// Global tab ID counter
 static TAB_ID_COUNTER: AtomicU64 = AtomicU64::new(1);
@@ -42,8 +46,12 @@ pub struct TabState {
     pub sticky_config: Option<StickyTabConfig>,
     /// Tab color override
     pub color: Option<String>,
+    /// Tab title text color override
+    pub title_color: Option<String>,
     /// Whether there's unread output
     pub has_unread: bool,
+    /// Whether the tab is pinned to the start of the tab bar
+    pub pinned: bool,
     /// Working directory
     pub cwd: Option<PathBuf>,
 }
@@ -63,7 +71,9 @@ impl TabState {
             custom_title: None,
             sticky_config: None,
             color: None,
+            title_color: None,
             has_unread: false,
+            pinned: false,
             cwd: None,
         })
     }
@@ -97,7 +107,9 @@ impl TabState {
             custom_title: Some(config.name.clone()),
             sticky_config: Some(config.clone()),
             color: config.color.clone(),
+            title_color: config.title_color.clone(),
             has_unread: false,
+            pinned: false,
             cwd: config.working_directory.clone(),
         })
     }
@@ -128,7 +140,9 @@ impl TabState {
             custom_title: None,
             sticky_config: None,
             color: None,
+            title_color: None,
             has_unread: false,
+            pinned: false,
             cwd,
         })
     }
@@ -178,6 +192,8 @@ impl TabState {
             custom_title: self.custom_title.clone(),
             cwd: self.cwd.clone(),
             color: self.color.clone(),
+            title_color: self.title_color.clone(),
+            pinned: self.pinned,
         }
     }
 }
@@ -225,7 +241,8 @@ impl WindowState {
                 self.active_tab = self.tabs.len() - 1;
             }
             TabPosition::AfterCurrent => {
-                let idx = (self.active_tab + 1).min(self.tabs.len());
+                let idx = unpinned_position(self.active_tab + 1, self.pinned_count())
+                    .min(self.tabs.len());
                 self.tabs.insert(idx, tab);
                 self.active_tab = idx;
             }
@@ -280,11 +297,24 @@ impl WindowState {
     }
 
     /// Move tab from one position to another
+    ///
+    /// Pinned tabs stay in the pinned group at the start and unpinned tabs
+    /// stay after it, so `to` is clamped to the tab's own group.
     pub fn move_tab(&mut self, from: usize, to: usize) {
         if from >= self.tabs.len() || to >= self.tabs.len() {
             return;
         }
+        let pinned = self.pinned_count();
+        let to = if self.tabs[from].pinned {
+            to.min(pinned - 1)
+        } else {
+            unpinned_position(to, pinned)
+        };
+        self.reorder(from, to);
+    }
 
+    /// Move a tab and keep `active_tab` pointing at the same tab
+    fn reorder(&mut self, from: usize, to: usize) {
         let tab = self.tabs.remove(from);
         self.tabs.insert(to, tab);
 
@@ -298,6 +328,21 @@ impl WindowState {
         }
     }
 
+    /// Number of pinned tabs, which always come first
+    pub fn pinned_count(&self) -> usize {
+        self.tabs.iter().take_while(|t| t.pinned).count()
+    }
+
+    /// Pin or unpin a tab, moving it to the edge of the pinned group
+    pub fn set_pinned(&mut self, index: usize, pinned: bool) {
+        if self.tabs.get(index).is_none_or(|t| t.pinned == pinned) {
+            return;
+        }
+        let target = pin_target(self.pinned_count(), pinned);
+        self.tabs[index].pinned = pinned;
+        self.reorder(index, target);
+    }
+
     /// Find tab by ID
     pub fn find_tab(&self, id: u64) -> Option<usize> {
         self.tabs.iter().position(|t| t.id == id)
@@ -327,6 +372,25 @@ pub enum TabPosition {
     AfterCurrent,
 }
 
+/// Clamp the position of a new or moved unpinned tab so it lands after
+/// the `pinned` tabs at the start of the tab bar
+pub fn unpinned_position(position: usize, pinned: usize) -> usize {
+    position.max(pinned)
+}
+
+/// Index a tab moves to when it is pinned or unpinned, given the number of
+/// pinned tabs before the change
+///
+/// Pinning appends the tab to the pinned group; unpinning puts it right
+/// after the group, so the other tabs keep their order.
+pub fn pin_target(pinned: usize, pin: bool) -> usize {
+    if pin {
+        pinned
+    } else {
+        pinned.saturating_sub(1)
+    }
+}
+
 /// Window geometry
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WindowGeometry {
@@ -378,6 +442,12 @@ pub struct TabSessionState {
     pub cwd: Option<PathBuf>,
     /// Tab color override
     pub color: Option<String>,
+    /// Tab title text color override
+    #[serde(default)]
+    pub title_color: Option<String>,
+    /// Whether the tab was pinned
+    #[serde(default)]
+    pub pinned: bool,
 }
 
 impl SessionState {
@@ -415,6 +485,98 @@ impl SessionState {
     }
 }
 
+/// Recently closed windows that can be reopened
+///
+/// Bounded to [`MAX_CLOSED_WINDOWS`]; the oldest entry is dropped first.
+#[derive(Debug, Default)]
+pub struct ClosedWindows {
+    windows: VecDeque<WindowSessionState>,
+}
+
+impl ClosedWindows {
+    /// Create an empty list
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Remember a closed window (windows without tabs are ignored)
+    pub fn push(&mut self, window: WindowSessionState) {
+        if window.tabs.is_empty() {
+            return;
+        }
+        if self.windows.len() >= MAX_CLOSED_WINDOWS {
+            self.windows.pop_front();
+        }
+        self.windows.push_back(window);
+    }
+
+    /// Take the most recently closed window
+    pub fn pop(&mut self) -> Option<WindowSessionState> {
+        self.windows.pop_back()
+    }
+
+    /// Number of windows that can be reopened
+    pub fn len(&self) -> usize {
+        self.windows.len()
+    }
+
+    /// Check if there is nothing to reopen
+    pub fn is_empty(&self) -> bool {
+        self.windows.is_empty()
+    }
+}
+
+/// What quitting the application closes, for the quit confirmation
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct QuitSummary {
+    /// Open windows
+    pub windows: usize,
+    /// Open tabs in all windows
+    pub tabs: usize,
+    /// Programs still running in the tabs
+    pub running: Vec<String>,
+}
+
+impl QuitSummary {
+    /// Whether to ask before quitting: always with `confirm_quit`, otherwise
+    /// only for running programs with `confirm_close_with_running`
+    pub fn needs_confirmation(&self, general: &GeneralConfig) -> bool {
+        if general.confirm_quit {
+            return self.tabs > 0;
+        }
+        general.confirm_close_with_running && !self.running.is_empty()
+    }
+
+    /// Counts for the confirmation, e.g. "3 windows, 12 tabs, 2 running
+    /// processes"
+    pub fn description(&self) -> String {
+        let count =
+            |n: usize, one: &str, many: &str| format!("{} {}", n, if n == 1 { one } else { many });
+        let mut parts = vec![
+            count(self.windows, "window", "windows"),
+            count(self.tabs, "tab", "tabs"),
+        ];
+        if !self.running.is_empty() {
+            parts.push(count(
+                self.running.len(),
+                "running process",
+                "running processes",
+            ));
+        }
+        parts.join(", ")
+    }
+
+    /// Details for the confirmation: the counts and the running programs
+    pub fn details(&self) -> String {
+        let mut details = self.description();
+        if !self.running.is_empty() {
+            details.push_str(&format!(" ({})", self.running.join(", ")));
+        }
+        details.push_str(". Everything will be closed.");
+        details
+    }
+}
+
 /// Application session managing all windows
 pub struct Session {
     /// All windows
@@ -489,6 +651,40 @@ impl Session {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_quit_summary() {
+        let mut general = GeneralConfig::default();
+        let mut summary = QuitSummary {
+            windows: 1,
+            tabs: 1,
+            running: Vec::new(),
+        };
+        assert!(!summary.needs_confirmation(&general));
+        assert_eq!(summary.description(), "1 window, 1 tab");
+
+        general.confirm_quit = true;
+        assert!(summary.needs_confirmation(&general));
+
+        general.confirm_quit = false;
+        summary = QuitSummary {
+            windows: 3,
+            tabs: 12,
+            running: vec!["vim".to_string(), "ssh".to_string()],
+        };
+        assert!(summary.needs_confirmation(&general));
+        assert_eq!(
+            summary.description(),
+            "3 windows, 12 tabs, 2 running processes"
+        );
+        assert_eq!(
+            summary.details(),
+            "3 windows, 12 tabs, 2 running processes (vim, ssh). Everything will be closed."
+        );
+
+        general.confirm_close_with_running = false;
+        assert!(!summary.needs_confirmation(&general));
+    }
+
     #[test]
     fn test_tab_id_generation() {
         let id1 = next_tab_id();
@@ -496,6 +692,72 @@ mod tests {
         assert_ne!(id1, id2);
     }
 
+    fn closed_window(cwd: &str) -> WindowSessionState {
+        WindowSessionState {
+            geometry: WindowGeometry::default(),
+            tabs: vec![TabSessionState {
+                template_name: None,
+                custom_title: None,
+                cwd: Some(PathBuf::from(cwd)),
+                color: None,
+                title_color: None,
+                pinned: false,
+            }],
+            active_tab: 0,
+        }
+    }
+
+    #[test]
+    fn test_closed_windows_most_recent_first() {
+        let mut closed = ClosedWindows::new();
+        closed.push(closed_window("/a"));
+        closed.push(closed_window("/b"));
+        assert_eq!(closed.len(), 2);
+
+        let window = closed.pop().unwrap();
+        assert_eq!(window.tabs[0].cwd, Some(PathBuf::from("/b")));
+        let window = closed.pop().unwrap();
+        assert_eq!(window.tabs[0].cwd, Some(PathBuf::from("/a")));
+        assert!(closed.pop().is_none());
+    }
+
+    #[test]
+    fn test_closed_windows_bounded() {
+        let mut closed = ClosedWindows::new();
+        for i in 0..MAX_CLOSED_WINDOWS + 2 {
+            closed.push(closed_window(&format!("/{}", i)));
+        }
+        assert_eq!(closed.len(), MAX_CLOSED_WINDOWS);
+
+        // The two oldest were dropped
+        let mut last = None;
+        while let Some(window) = closed.pop() {
+            last = window.tabs[0].cwd.clone();
+        }
+        assert_eq!(last, Some(PathBuf::from("/2")));
+    }
+
+    #[test]
+    fn test_closed_windows_ignores_empty() {
+        let mut closed = ClosedWindows::new();
+        let mut window = closed_window("/a");
+        window.tabs.clear();
+        closed.push(window);
+        assert!(closed.is_empty());
+    }
+
+    #[test]
+    fn test_pinned_positions() {
+        assert_eq!(unpinned_position(0, 2), 2);
+        assert_eq!(unpinned_position(3, 2), 3);
+
+        // Pinning the tab at index 3 with two pinned tabs moves it to 2
+        assert_eq!(pin_target(2, true), 2);
+        // Unpinning any of two pinned tabs moves it to 1, just after the other
+        assert_eq!(pin_target(2, false), 1);
+        assert_eq!(pin_target(0, true), 0);
+    }
+
     #[test]
     fn test_window_geometry_default() {
         let geo = WindowGeometry::default();
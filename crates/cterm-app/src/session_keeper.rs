@@ -0,0 +1,526 @@
+//! Persistent sessions
+//!
+//! The session keeper is a small background process that holds the PTYs of
+//! detached windows, so the UI can be closed and the sessions picked up again
+//! later, like a lightweight tmux:
+//!
+//! 1. "Detach" sends the window state and PTY FDs to the keeper (starting it
+//!    if needed) the same way a seamless upgrade hands them to a new process
+//! 2. The keeper keeps reading the PTYs into its own screens, so output
+//!    written while detached isn't lost
+//! 3. `cterm --attach` fetches the state and FDs back and rebuilds the windows
+//!
+//! The keeper listens on a Unix socket in the user's runtime directory and
+//! exits once it holds no sessions.
+
+use std::io::{self, Read, Write};
+use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+use std::os::unix::io::{AsRawFd, IntoRawFd, RawFd};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+use cterm_core::fd_passing;
+use cterm_core::pty::Pty;
+use cterm_core::screen::ScreenConfig;
+use cterm_core::term::Terminal;
+use directories::ProjectDirs;
+
+use crate::upgrade::{
+    deserialize_state, serialize_state, TerminalUpgradeState, UpgradeError, UpgradeState,
+    WindowUpgradeState, MAX_FDS, MAX_STATE_SIZE,
+};
+
+/// How long a newly started keeper waits for its first session
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How long the UI waits for a keeper it started to listen
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long the keeper waits on a client before giving up on it
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Errors from detaching or attaching sessions
+#[derive(Debug, thiserror::Error)]
+pub enum SessionKeeperError {
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+
+    #[error("State error: {0}")]
+    State(#[from] UpgradeError),
+
+    #[error("Failed to start the session keeper: {0}")]
+    Spawn(String),
+
+    #[error("Protocol error: {0}")]
+    Protocol(String),
+}
+
+/// Request sent to the keeper, as the first byte of a message
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum KeeperMessage {
+    /// Hand over windows (state and PTY FDs follow); the keeper acks
+    Detach = 1,
+    /// Ask for all held windows; the keeper replies with state and FDs
+    /// (empty when it holds none) and waits for an ack
+    Attach = 2,
+}
+
+/// Acknowledgment byte, as in the upgrade protocol
+const ACK: u8 = 1;
+
+/// Windows held by the keeper, with a terminal per tab
+struct HeldWindow {
+    state: WindowUpgradeState,
+    terminals: Vec<Terminal>,
+}
+
+/// Get the path of the keeper's socket
+pub fn socket_path() -> PathBuf {
+    ProjectDirs::from("com", "cterm", "cterm")
+        .map(|dirs| {
+            dirs.runtime_dir()
+                .unwrap_or_else(|| dirs.cache_dir())
+                .to_path_buf()
+        })
+        .unwrap_or_else(|| PathBuf::from("/tmp/cterm"))
+        .join("sessions.sock")
+}
+
+/// Hand windows to the session keeper, starting it with
+/// `binary --session-keeper` if it isn't running
+///
+/// On success the keeper owns the sessions and this process should exit
+/// without hanging up its PTYs.
+pub fn detach_sessions(
+    binary: &Path,
+    state: &UpgradeState,
+    fds: &[RawFd],
+) -> Result<(), SessionKeeperError> {
+    detach_to(&socket_path(), Some(binary), state, fds)
+}
+
+/// Take all detached windows back from the session keeper
+///
+/// Returns `None` if no keeper is running or it holds no sessions.
+pub fn attach_sessions() -> Result<Option<(UpgradeState, Vec<RawFd>)>, SessionKeeperError> {
+    attach_from(&socket_path())
+}
+
+/// Run the session keeper (called with `--session-keeper`)
+///
+/// Returns right away if another keeper is already listening.
+pub fn run_session_keeper(config: ScreenConfig) -> Result<(), SessionKeeperError> {
+    let path = socket_path();
+    if let Some(dir) = path.parent() {
+        std::fs::DirBuilder::new()
+            .recursive(true)
+            .mode(0o700)
+            .create(dir)?;
+    }
+
+    if UnixStream::connect(&path).is_ok() {
+        log::info!("Session keeper already running at {}", path.display());
+        return Ok(());
+    }
+    // Left over from a keeper that didn't exit cleanly
+    let _ = std::fs::remove_file(&path);
+
+    let listener = UnixListener::bind(&path)?;
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600))?;
+    log::info!("Session keeper listening on {}", path.display());
+
+    let result = serve(&listener, config);
+    let _ = std::fs::remove_file(&path);
+    result
+}
+
+fn detach_to(
+    path: &Path,
+    binary: Option<&Path>,
+    state: &UpgradeState,
+    fds: &[RawFd],
+) -> Result<(), SessionKeeperError> {
+    if fds.len() > MAX_FDS {
+        return Err(UpgradeError::TooManyFds(fds.len(), MAX_FDS).into());
+    }
+
+    let mut data = vec![KeeperMessage::Detach as u8];
+    data.extend(serialize_state(state)?);
+
+    let mut stream = connect(path, binary)?;
+    fd_passing::send_fds(&stream, fds, &data)?;
+
+    let mut ack = [0u8; 1];
+    stream.read_exact(&mut ack)?;
+    if ack[0] != ACK {
+        return Err(SessionKeeperError::Protocol(
+            "invalid acknowledgment".to_string(),
+        ));
+    }
+
+    log::info!("Detached {} sessions", fds.len());
+    Ok(())
+}
+
+fn attach_from(path: &Path) -> Result<Option<(UpgradeState, Vec<RawFd>)>, SessionKeeperError> {
+    let mut stream = match UnixStream::connect(path) {
+        Ok(stream) => stream,
+        Err(e)
+            if matches!(
+                e.kind(),
+                io::ErrorKind::NotFound | io::ErrorKind::ConnectionRefused
+            ) =>
+        {
+            return Ok(None)
+        }
+        Err(e) => return Err(e.into()),
+    };
+    fd_passing::send_fds(&stream, &[], &[KeeperMessage::Attach as u8])?;
+
+    let mut buf = vec![0u8; MAX_STATE_SIZE];
+    let (fds, data_len) = fd_passing::recv_fds(&stream, MAX_FDS, &mut buf)?;
+    if data_len == 0 {
+        close_fds(&fds);
+        return Ok(None);
+    }
+
+    let state = match deserialize_state(&buf[..data_len]) {
+        Ok(state) => state,
+        Err(e) => {
+            close_fds(&fds);
+            return Err(e.into());
+        }
+    };
+
+    stream.write_all(&[ACK])?;
+    log::info!("Attached {} sessions", fds.len());
+    Ok(Some((state, fds)))
+}
+
+/// Connect to the keeper, starting it from `binary` if it isn't running
+fn connect(path: &Path, binary: Option<&Path>) -> Result<UnixStream, SessionKeeperError> {
+    let err = match UnixStream::connect(path) {
+        Ok(stream) => return Ok(stream),
+        Err(e) => e,
+    };
+    let Some(binary) = binary else {
+        return Err(err.into());
+    };
+
+    log::info!("Starting session keeper: {}", binary.display());
+    unsafe {
+        Command::new(binary)
+            .arg("--session-keeper")
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .pre_exec(|| {
+                // Outlive the UI and its session
+                libc::setsid();
+                Ok(())
+            })
+            .spawn()
+            .map_err(|e| SessionKeeperError::Spawn(e.to_string()))?;
+    }
+
+    let started = Instant::now();
+    loop {
+        std::thread::sleep(Duration::from_millis(50));
+        match UnixStream::connect(path) {
+            Ok(stream) => return Ok(stream),
+            Err(e) if started.elapsed() > CONNECT_TIMEOUT => {
+                return Err(SessionKeeperError::Spawn(e.to_string()))
+            }
+            Err(_) => {}
+        }
+    }
+}
+
+/// Serve clients and read the held PTYs until no sessions are left
+fn serve(listener: &UnixListener, config: ScreenConfig) -> Result<(), SessionKeeperError> {
+    let started = Instant::now();
+    let mut contacted = false;
+    let mut held: Vec<HeldWindow> = Vec::new();
+    let mut buf = [0u8; 8192];
+
+    loop {
+        if held.is_empty() && (contacted || started.elapsed() > STARTUP_TIMEOUT) {
+            log::info!("No sessions left, session keeper exiting");
+            return Ok(());
+        }
+
+        let mut pollfds = vec![libc::pollfd {
+            fd: listener.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        }];
+        for window in &held {
+            for terminal in &window.terminals {
+                pollfds.push(libc::pollfd {
+                    fd: terminal.pty().map_or(-1, |pty| pty.raw_fd()),
+                    events: libc::POLLIN,
+                    revents: 0,
+                });
+            }
+        }
+
+        let ret = unsafe { libc::poll(pollfds.as_mut_ptr(), pollfds.len() as _, 1000) };
+        if ret < 0 {
+            let err = io::Error::last_os_error();
+            if err.kind() == io::ErrorKind::Interrupted {
+                continue;
+            }
+            return Err(err.into());
+        }
+
+        // Read the PTYs first: pollfds is laid out in the order of `held`
+        let mut ready = pollfds[1..].iter().map(|p| p.revents != 0);
+        for window in &mut held {
+            let mut index = 0;
+            window.terminals.retain_mut(|terminal| {
+                let alive = !ready.next().unwrap_or(false) || read_pty(terminal, &mut buf);
+                if !alive {
+                    log::info!("Session {} ended", window.state.tabs[index].id);
+                    window.state.tabs.remove(index);
+                } else {
+                    index += 1;
+                }
+                alive
+            });
+        }
+        held.retain(|window| !window.terminals.is_empty());
+
+        if pollfds[0].revents & libc::POLLIN != 0 {
+            let (stream, _) = listener.accept()?;
+            contacted = true;
+            if let Err(e) = handle_client(stream, &mut held, &config) {
+                log::error!("Session keeper client failed: {}", e);
+            }
+        }
+    }
+}
+
+/// Feed available PTY output to the terminal; false once the PTY is closed
+fn read_pty(terminal: &mut Terminal, buf: &mut [u8]) -> bool {
+    let Some(pty) = terminal.pty_mut() else {
+        return false;
+    };
+    match pty.read(buf) {
+        Ok(0) => false,
+        Ok(n) => {
+            terminal.process(&buf[..n]);
+            true
+        }
+        Err(e) => matches!(
+            e.kind(),
+            io::ErrorKind::WouldBlock | io::ErrorKind::Interrupted
+        ),
+    }
+}
+
+fn handle_client(
+    mut stream: UnixStream,
+    held: &mut Vec<HeldWindow>,
+    config: &ScreenConfig,
+) -> Result<(), SessionKeeperError> {
+    stream.set_read_timeout(Some(CLIENT_TIMEOUT))?;
+
+    let mut buf = vec![0u8; MAX_STATE_SIZE];
+    let (fds, data_len) = fd_passing::recv_fds(&stream, MAX_FDS, &mut buf)?;
+    match buf[..data_len].split_first() {
+        Some((&b, state)) if b == KeeperMessage::Detach as u8 => {
+            let state = match deserialize_state(state) {
+                Ok(state) => state,
+                Err(e) => {
+                    close_fds(&fds);
+                    return Err(e.into());
+                }
+            };
+            hold_sessions(state, &fds, held, config);
+            stream.write_all(&[ACK])?;
+        }
+        Some((&b, _)) if b == KeeperMessage::Attach as u8 => {
+            close_fds(&fds);
+            release_sessions(&mut stream, held)?;
+        }
+        _ => {
+            close_fds(&fds);
+            return Err(SessionKeeperError::Protocol("unknown request".to_string()));
+        }
+    }
+    Ok(())
+}
+
+/// Take ownership of the detached windows' PTYs
+fn hold_sessions(
+    state: UpgradeState,
+    fds: &[RawFd],
+    held: &mut Vec<HeldWindow>,
+    config: &ScreenConfig,
+) {
+    let mut used = vec![false; fds.len()];
+    for mut window_state in state.windows {
+        let tabs = std::mem::take(&mut window_state.tabs);
+        let mut window = HeldWindow {
+            state: window_state,
+            terminals: Vec::new(),
+        };
+        for tab in tabs {
+            let index = tab.pty_fd_index;
+            if tab.child_pid <= 0 || used.get(index) != Some(&false) {
+                log::warn!("Tab {} has no PTY, not keeping it", tab.id);
+                continue;
+            }
+            used[index] = true;
+            // SAFETY: the FD was just received and is owned by this Pty only
+            let pty = unsafe { Pty::from_raw_fd(fds[index], tab.child_pid) };
+            let terminal = Terminal::from_restored(
+                tab.terminal.to_screen(ScreenConfig {
+                    scrollback_lines: config.scrollback_lines,
+                }),
+                pty,
+            );
+            window.state.tabs.push(tab);
+            window.terminals.push(terminal);
+        }
+        if !window.terminals.is_empty() {
+            held.push(window);
+        }
+    }
+
+    for (fd, used) in fds.iter().zip(used) {
+        if !used {
+            unsafe { libc::close(*fd) };
+        }
+    }
+    log::info!("Holding {} detached windows", held.len());
+}
+
+/// Send all held windows to an attaching UI, letting go of them once it acks
+fn release_sessions(
+    stream: &mut UnixStream,
+    held: &mut Vec<HeldWindow>,
+) -> Result<(), SessionKeeperError> {
+    if held.is_empty() {
+        fd_passing::send_fds(stream, &[], &[])?;
+        return Ok(());
+    }
+
+    let mut state = UpgradeState::new(env!("CARGO_PKG_VERSION"));
+    let mut fds = Vec::new();
+    for window in held.iter() {
+        let mut window_state = window.state.clone();
+        for (tab, terminal) in window_state.tabs.iter_mut().zip(&window.terminals) {
+            tab.terminal = TerminalUpgradeState::from_screen(terminal.screen());
+            // Pick up title changes made while detached
+            if tab.custom_title.is_none() && !terminal.title().is_empty() {
+                tab.title = terminal.title().to_string();
+            }
+            if let Some(cwd) = terminal.foreground_cwd() {
+                tab.cwd = Some(cwd.to_string_lossy().into_owned());
+            }
+            if let Some(pty) = terminal.pty() {
+                tab.pty_fd_index = fds.len();
+                tab.child_pid = pty.child_pid();
+                fds.push(pty.raw_fd());
+            }
+        }
+        state.windows.push(window_state);
+    }
+
+    fd_passing::send_fds(stream, &fds, &serialize_state(&state)?)?;
+
+    let mut ack = [0u8; 1];
+    stream.read_exact(&mut ack)?;
+    if ack[0] != ACK {
+        return Err(SessionKeeperError::Protocol(
+            "invalid acknowledgment".to_string(),
+        ));
+    }
+
+    // The UI has its own copies now; close ours without hanging up
+    for window in held.drain(..) {
+        for mut terminal in window.terminals {
+            if let Some(pty) = terminal.take_pty() {
+                unsafe { libc::close(pty.into_raw_fd()) };
+            }
+        }
+    }
+    Ok(())
+}
+
+fn close_fds(fds: &[RawFd]) {
+    for &fd in fds {
+        unsafe { libc::close(fd) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::upgrade::TabUpgradeState;
+    use cterm_core::pty::{PtyConfig, PtySize};
+
+    #[test]
+    fn test_detach_and_attach() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("sessions.sock");
+        let listener = UnixListener::bind(&path).unwrap();
+        let keeper = std::thread::spawn(move || serve(&listener, ScreenConfig::default()));
+
+        let pty = Pty::new(&PtyConfig {
+            size: PtySize {
+                rows: 24,
+                cols: 80,
+                ..Default::default()
+            },
+            shell: Some("/bin/sh".to_string()),
+            args: vec![
+                "-c".to_string(),
+                "sleep 0.3; echo keeper-$((1+1)); sleep 10".to_string(),
+            ],
+            ..Default::default()
+        })
+        .unwrap();
+        let child_pid = pty.child_pid();
+
+        // Enough lines to push some into the scrollback, which is spilled to
+        // a file on the way to the keeper and back
+        let mut terminal = Terminal::new(80, 24, ScreenConfig::default());
+        for i in 0..30 {
+            terminal.process(format!("history-{}\r\n", i).as_bytes());
+        }
+        let mut tab = TabUpgradeState::new(7, 0, child_pid);
+        tab.terminal = TerminalUpgradeState::from_screen(terminal.screen());
+        assert!(!tab.terminal.scrollback.is_empty());
+        let mut window = WindowUpgradeState::new();
+        window.tabs.push(tab);
+        let mut state = UpgradeState::new("test");
+        state.windows.push(window);
+
+        detach_to(&path, None, &state, &[pty.raw_fd()]).unwrap();
+        // The keeper has its own copy of the PTY now
+        unsafe { libc::close(pty.into_raw_fd()) };
+
+        // Output written while detached is kept
+        std::thread::sleep(Duration::from_millis(1000));
+        let (state, fds) = attach_from(&path).unwrap().unwrap();
+        assert_eq!(fds.len(), 1);
+        let tab = &state.windows[0].tabs[0];
+        assert_eq!(tab.id, 7);
+        assert_eq!(tab.child_pid, child_pid);
+        assert!(tab.terminal.grid.text().contains("keeper-2"));
+        assert!(tab.terminal.scrollback_file.is_none());
+        assert_eq!(tab.terminal.scrollback[0].text().trim_end(), "history-0");
+
+        // With nothing left to hold, the keeper exits
+        keeper.join().unwrap().unwrap();
+        assert!(attach_from(&path).unwrap().is_none());
+
+        drop(unsafe { Pty::from_raw_fd(fds[0], child_pid) });
+    }
+}
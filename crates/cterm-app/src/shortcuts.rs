@@ -1,13 +1,62 @@
 //! Keyboard shortcut management
 //!
-//! Handles parsing, matching, and managing keyboard shortcuts.
+//! Handles parsing, matching, and managing keyboard shortcuts, and reading
+//! and writing them as a keybindings file of their own.
 
 use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
 
 use cterm_ui::events::{Action, KeyCode, Modifiers, Shortcut};
+use thiserror::Error;
 
 use crate::config::ShortcutsConfig;
 
+/// Keybindings file errors
+#[derive(Error, Debug)]
+pub enum KeybindingsError {
+    #[error("Failed to read keybindings file: {0}")]
+    Read(#[from] std::io::Error),
+
+    #[error("Failed to parse keybindings file: {0}")]
+    Parse(#[from] toml::de::Error),
+
+    #[error("Failed to serialize keybindings: {0}")]
+    Serialize(#[from] toml::ser::Error),
+
+    #[error("Invalid shortcut \"{shortcut}\" for {binding}")]
+    InvalidShortcut { binding: String, shortcut: String },
+}
+
+/// A shortcut that a keybindings file binds more than once
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShortcutConflict {
+    /// The shortcut, formatted for display
+    pub shortcut: String,
+    /// Config keys of the bindings using it, in the order they're applied
+    /// (the last one wins)
+    pub bindings: Vec<String>,
+}
+
+impl fmt::Display for ShortcutConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.shortcut, self.bindings.join(", "))
+    }
+}
+
+/// A note listing `conflicts` for the user, or None if there are none
+pub fn describe_conflicts(conflicts: &[ShortcutConflict]) -> Option<String> {
+    if conflicts.is_empty() {
+        return None;
+    }
+    let mut note =
+        "These shortcuts are bound more than once; the last binding is used:".to_string();
+    for conflict in conflicts {
+        note.push_str(&format!("\n{}", conflict));
+    }
+    Some(note)
+}
+
 /// Manages keyboard shortcuts
 #[derive(Clone)]
 pub struct ShortcutManager {
@@ -34,33 +83,47 @@ impl ShortcutManager {
         let mut manager = Self::new();
 
         // Override with config values
-        manager.bind_str(&config.new_tab, Action::NewTab);
-        manager.bind_str(&config.close_tab, Action::CloseTab);
-        manager.bind_str(&config.next_tab, Action::NextTab);
-        manager.bind_str(&config.prev_tab, Action::PrevTab);
-        manager.bind_str(&config.new_window, Action::NewWindow);
-        manager.bind_str(&config.close_window, Action::CloseWindow);
-        manager.bind_str(&config.copy, Action::Copy);
-        manager.bind_str(&config.paste, Action::Paste);
-        manager.bind_str(&config.select_all, Action::SelectAll);
-        manager.bind_str(&config.zoom_in, Action::ZoomIn);
-        manager.bind_str(&config.zoom_out, Action::ZoomOut);
-        manager.bind_str(&config.zoom_reset, Action::ZoomReset);
-        manager.bind_str(&config.scroll_up, Action::ScrollUp);
-        manager.bind_str(&config.scroll_down, Action::ScrollDown);
-        manager.bind_str(&config.scroll_page_up, Action::ScrollPageUp);
-        manager.bind_str(&config.scroll_page_down, Action::ScrollPageDown);
-        manager.bind_str(&config.preferences, Action::OpenPreferences);
-        manager.bind_str(&config.find, Action::FindText);
-        manager.bind_str(&config.reset, Action::ResetTerminal);
+        for (_, shortcut, action) in config_bindings(config) {
+            manager.bind_str(shortcut, action);
+        }
 
         manager
     }
 
+    /// Write the shortcuts of `config` to a keybindings file
+    ///
+    /// The file is TOML with the keys of the config file's `[shortcuts]`
+    /// section, so a set of bindings can be shared without the rest of the
+    /// config.
+    pub fn to_file(config: &ShortcutsConfig, path: &Path) -> Result<(), KeybindingsError> {
+        let content = toml::to_string_pretty(config)?;
+        std::fs::write(path, content)?;
+        Ok(())
+    }
+
+    /// Read a keybindings file written by [`ShortcutManager::to_file`]
+    ///
+    /// A whole config file works too; its `[shortcuts]` section is used.
+    /// Every shortcut has to parse. Shortcuts bound more than once are
+    /// returned as conflicts.
+    pub fn from_file(
+        path: &Path,
+    ) -> Result<(ShortcutsConfig, Vec<ShortcutConflict>), KeybindingsError> {
+        let content = std::fs::read_to_string(path)?;
+        let mut table: toml::Table = toml::from_str(&content)?;
+        if let Some(toml::Value::Table(shortcuts)) = table.remove("shortcuts") {
+            table = shortcuts;
+        }
+        let config: ShortcutsConfig = toml::Value::Table(table).try_into()?;
+        let conflicts = find_conflicts(&config)?;
+        Ok((config, conflicts))
+    }
+
     /// Load default shortcuts
     fn load_defaults(&mut self) {
         // Tab shortcuts
         self.bind(Shortcut::ctrl_shift(KeyCode::T), Action::NewTab);
+        self.bind(Shortcut::ctrl_shift(KeyCode::P), Action::NewIncognitoTab);
         self.bind(Shortcut::ctrl_shift(KeyCode::W), Action::CloseTab);
         self.bind(
             Shortcut::new(KeyCode::Tab, Modifiers::CTRL),
@@ -85,17 +148,31 @@ impl ShortcutManager {
         // Window shortcuts
         self.bind(Shortcut::ctrl_shift(KeyCode::N), Action::NewWindow);
         self.bind(Shortcut::ctrl_shift(KeyCode::Q), Action::CloseWindow);
+        self.bind(Shortcut::ctrl_shift(KeyCode::O), Action::ReopenClosedWindow);
 
         // Edit shortcuts
         self.bind(Shortcut::ctrl_shift(KeyCode::C), Action::Copy);
         self.bind(Shortcut::ctrl_shift(KeyCode::V), Action::Paste);
         self.bind(Shortcut::ctrl_shift(KeyCode::A), Action::SelectAll);
+        self.bind(Shortcut::ctrl_shift(KeyCode::H), Action::ClipboardHistory);
+
+        // Shell integration shortcuts (need OSC 133 prompt marks)
+        self.bind(
+            Shortcut::ctrl_shift(KeyCode::Y),
+            Action::CopyLastCommandOutput,
+        );
+        self.bind(Shortcut::ctrl_shift(KeyCode::E), Action::RerunLastCommand);
+        self.bind(Shortcut::ctrl_shift(KeyCode::K), Action::ClearToLastPrompt);
 
         // Zoom shortcuts
         self.bind(Shortcut::ctrl(KeyCode::Equals), Action::ZoomIn);
         self.bind(Shortcut::ctrl(KeyCode::Minus), Action::ZoomOut);
         self.bind(Shortcut::ctrl(KeyCode::Key0), Action::ZoomReset);
 
+        // Window opacity shortcuts
+        self.bind(Shortcut::ctrl_shift(KeyCode::Up), Action::IncreaseOpacity);
+        self.bind(Shortcut::ctrl_shift(KeyCode::Down), Action::DecreaseOpacity);
+
         // Scroll shortcuts
         self.bind(
             Shortcut::new(KeyCode::PageUp, Modifiers::SHIFT),
@@ -108,9 +185,14 @@ impl ShortcutManager {
         self.bind(Shortcut::ctrl_shift(KeyCode::Home), Action::ScrollToTop);
         self.bind(Shortcut::ctrl_shift(KeyCode::End), Action::ScrollToBottom);
 
+        // Scrollback bookmarks
+        self.bind(Shortcut::ctrl_shift(KeyCode::M), Action::SetMark);
+        self.bind(Shortcut::ctrl_shift(KeyCode::J), Action::JumpMark);
+
         // Other shortcuts
         self.bind(Shortcut::ctrl(KeyCode::Comma), Action::OpenPreferences);
         self.bind(Shortcut::ctrl_shift(KeyCode::F), Action::FindText);
+        self.bind(Shortcut::ctrl_shift(KeyCode::L), Action::ToggleRecording);
 
         // Quick open (Cmd+G on macOS, Ctrl+Shift+G on Linux/Windows)
         #[cfg(target_os = "macos")]
@@ -173,6 +255,110 @@ impl Default for ShortcutManager {
     }
 }
 
+/// The bindings `config` sets, as (config key, shortcut, action) in the
+/// order they're applied
+fn config_bindings(config: &ShortcutsConfig) -> Vec<(String, &str, Action)> {
+    let mut bindings: Vec<(String, &str, Action)> = [
+        ("new_tab", &config.new_tab, Action::NewTab),
+        ("close_tab", &config.close_tab, Action::CloseTab),
+        ("next_tab", &config.next_tab, Action::NextTab),
+        ("prev_tab", &config.prev_tab, Action::PrevTab),
+        ("new_window", &config.new_window, Action::NewWindow),
+        ("close_window", &config.close_window, Action::CloseWindow),
+        ("copy", &config.copy, Action::Copy),
+        ("paste", &config.paste, Action::Paste),
+        ("select_all", &config.select_all, Action::SelectAll),
+        ("zoom_in", &config.zoom_in, Action::ZoomIn),
+        ("zoom_out", &config.zoom_out, Action::ZoomOut),
+        ("zoom_reset", &config.zoom_reset, Action::ZoomReset),
+        ("scroll_up", &config.scroll_up, Action::ScrollUp),
+        ("scroll_down", &config.scroll_down, Action::ScrollDown),
+        (
+            "scroll_page_up",
+            &config.scroll_page_up,
+            Action::ScrollPageUp,
+        ),
+        (
+            "scroll_page_down",
+            &config.scroll_page_down,
+            Action::ScrollPageDown,
+        ),
+        ("preferences", &config.preferences, Action::OpenPreferences),
+        ("find", &config.find, Action::FindText),
+        ("reset", &config.reset, Action::ResetTerminal),
+    ]
+    .into_iter()
+    .map(|(key, shortcut, action)| (key.to_string(), shortcut.as_str(), action))
+    .collect();
+
+    let mut send_text: Vec<_> = config.send_text.iter().collect();
+    send_text.sort();
+    for (shortcut, text) in send_text {
+        bindings.push((
+            format!("send_text {:?}", text),
+            shortcut.as_str(),
+            Action::SendText(expand_snippet(text)),
+        ));
+    }
+    bindings
+}
+
+/// Check that every shortcut in `config` parses, and find the ones bound
+/// more than once
+fn find_conflicts(config: &ShortcutsConfig) -> Result<Vec<ShortcutConflict>, KeybindingsError> {
+    let mut uses: Vec<(Shortcut, Vec<String>)> = Vec::new();
+    for (binding, shortcut_str, _) in config_bindings(config) {
+        // An empty shortcut keeps the default binding
+        if shortcut_str.trim().is_empty() {
+            continue;
+        }
+        let shortcut =
+            parse_shortcut(shortcut_str).ok_or_else(|| KeybindingsError::InvalidShortcut {
+                binding: binding.clone(),
+                shortcut: shortcut_str.to_string(),
+            })?;
+        match uses.iter_mut().find(|(s, _)| *s == shortcut) {
+            Some((_, bindings)) => bindings.push(binding),
+            None => uses.push((shortcut, vec![binding])),
+        }
+    }
+
+    Ok(uses
+        .into_iter()
+        .filter(|(_, bindings)| bindings.len() > 1)
+        .map(|(shortcut, bindings)| ShortcutConflict {
+            shortcut: format_shortcut(&shortcut),
+            bindings,
+        })
+        .collect())
+}
+
+/// Expand the escapes in a `send_text` snippet: `\n`, `\r`, `\t`, `\e`
+/// (ESC) and `\\`; other backslashes are kept as typed
+pub fn expand_snippet(text: &str) -> String {
+    let mut expanded = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            expanded.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => expanded.push('\n'),
+            Some('r') => expanded.push('\r'),
+            Some('t') => expanded.push('\t'),
+            Some('e') => expanded.push('\x1b'),
+            Some('\\') => expanded.push('\\'),
+            Some(other) => {
+                expanded.push('\\');
+                expanded.push(other);
+            }
+            None => expanded.push('\\'),
+        }
+    }
+    expanded
+}
+
 /// Parse a shortcut string like "Ctrl+Shift+T"
 pub fn parse_shortcut(s: &str) -> Option<Shortcut> {
     let parts: Vec<&str> = s.split('+').map(|p| p.trim()).collect();
@@ -200,35 +386,15 @@ pub fn parse_shortcut(s: &str) -> Option<Shortcut> {
 
 /// Parse a key name
 fn parse_key(s: &str) -> Option<KeyCode> {
-    match s.to_lowercase().as_str() {
-        // Letters
-        "a" => Some(KeyCode::A),
-        "b" => Some(KeyCode::B),
-        "c" => Some(KeyCode::C),
-        "d" => Some(KeyCode::D),
-        "e" => Some(KeyCode::E),
-        "f" => Some(KeyCode::F),
-        "g" => Some(KeyCode::G),
-        "h" => Some(KeyCode::H),
-        "i" => Some(KeyCode::I),
-        "j" => Some(KeyCode::J),
-        "k" => Some(KeyCode::K),
-        "l" => Some(KeyCode::L),
-        "m" => Some(KeyCode::M),
-        "n" => Some(KeyCode::N),
-        "o" => Some(KeyCode::O),
-        "p" => Some(KeyCode::P),
-        "q" => Some(KeyCode::Q),
-        "r" => Some(KeyCode::R),
-        "s" => Some(KeyCode::S),
-        "t" => Some(KeyCode::T),
-        "u" => Some(KeyCode::U),
-        "v" => Some(KeyCode::V),
-        "w" => Some(KeyCode::W),
-        "x" => Some(KeyCode::X),
-        "y" => Some(KeyCode::Y),
-        "z" => Some(KeyCode::Z),
+    // Letters, in either case
+    let mut chars = s.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        if c.is_ascii_alphabetic() {
+            return KeyCode::from_char(c);
+        }
+    }
 
+    match s.to_lowercase().as_str() {
         // Numbers
         "0" => Some(KeyCode::Key0),
         "1" => Some(KeyCode::Key1),
@@ -418,5 +584,108 @@ mod tests {
         let manager = ShortcutManager::new();
         let action = manager.match_event(KeyCode::T, Modifiers::CTRL | Modifiers::SHIFT);
         assert_eq!(action, Some(&Action::NewTab));
+
+        let action = manager.match_event(KeyCode::P, Modifiers::CTRL | Modifiers::SHIFT);
+        assert_eq!(action, Some(&Action::NewIncognitoTab));
+
+        let action = manager.match_event(KeyCode::O, Modifiers::CTRL | Modifiers::SHIFT);
+        assert_eq!(action, Some(&Action::ReopenClosedWindow));
+
+        let action = manager.match_event(KeyCode::Y, Modifiers::CTRL | Modifiers::SHIFT);
+        assert_eq!(action, Some(&Action::CopyLastCommandOutput));
+
+        let action = manager.match_event(KeyCode::H, Modifiers::CTRL | Modifiers::SHIFT);
+        assert_eq!(action, Some(&Action::ClipboardHistory));
+
+        let action = manager.match_event(KeyCode::M, Modifiers::CTRL | Modifiers::SHIFT);
+        assert_eq!(action, Some(&Action::SetMark));
+
+        let action = manager.match_event(KeyCode::K, Modifiers::CTRL | Modifiers::SHIFT);
+        assert_eq!(action, Some(&Action::ClearToLastPrompt));
+
+        let action = manager.match_event(KeyCode::L, Modifiers::CTRL | Modifiers::SHIFT);
+        assert_eq!(action, Some(&Action::ToggleRecording));
+    }
+
+    #[test]
+    fn test_shortcut_ignores_letter_case() {
+        // Caps Lock only changes the letter's case, never the modifiers
+        let manager = ShortcutManager::new();
+        for c in ['t', 'T'] {
+            let key = KeyCode::from_char(c).unwrap();
+            let action = manager.match_event(key, Modifiers::CTRL | Modifiers::SHIFT);
+            assert_eq!(action, Some(&Action::NewTab));
+        }
+
+        let mut manager = ShortcutManager::new();
+        manager.bind_str("ctrl+alt+t", Action::NewWindow);
+        for c in ['t', 'T'] {
+            let key = KeyCode::from_char(c).unwrap();
+            let action = manager.match_event(key, Modifiers::CTRL | Modifiers::ALT);
+            assert_eq!(action, Some(&Action::NewWindow));
+        }
+        assert_eq!(parse_shortcut("Ctrl+T"), parse_shortcut("ctrl+t"));
+    }
+
+    #[test]
+    fn test_expand_snippet() {
+        assert_eq!(expand_snippet("git status\\n"), "git status\n");
+        assert_eq!(expand_snippet("a\\tb\\r"), "a\tb\r");
+        assert_eq!(expand_snippet("\\e[A"), "\x1b[A");
+        assert_eq!(expand_snippet("C:\\\\dir \\x \\"), "C:\\dir \\x \\");
+    }
+
+    #[test]
+    fn test_send_text_shortcut() {
+        let mut config = ShortcutsConfig::default();
+        config
+            .send_text
+            .insert("Ctrl+Alt+G".to_string(), "git status\\n".to_string());
+        let manager = ShortcutManager::from_config(&config);
+
+        let action = manager.match_event(KeyCode::G, Modifiers::CTRL | Modifiers::ALT);
+        assert_eq!(action, Some(&Action::SendText("git status\n".to_string())));
+        assert_eq!(manager.match_event(KeyCode::G, Modifiers::CTRL), None);
+    }
+
+    #[test]
+    fn test_keybindings_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("keybindings.toml");
+
+        let mut config = ShortcutsConfig {
+            find: "Ctrl+Alt+F".into(),
+            ..Default::default()
+        };
+        config
+            .send_text
+            .insert("Ctrl+Alt+G".to_string(), "git status\\n".to_string());
+        ShortcutManager::to_file(&config, &path).unwrap();
+        let (read, conflicts) = ShortcutManager::from_file(&path).unwrap();
+        assert_eq!(read.find, "Ctrl+Alt+F");
+        assert_eq!(read.send_text, config.send_text);
+        assert!(conflicts.is_empty());
+
+        // The shortcuts section of a whole config file, with a conflict
+        std::fs::write(
+            &path,
+            "[general]\nscrollback_lines = 5\n\n[shortcuts]\ncopy = \"Ctrl+Alt+C\"\n\n[shortcuts.send_text]\n\"ctrl+alt+c\" = \"ls\"\n",
+        )
+        .unwrap();
+        let (read, conflicts) = ShortcutManager::from_file(&path).unwrap();
+        assert_eq!(read.copy, "Ctrl+Alt+C");
+        assert_eq!(
+            conflicts,
+            vec![ShortcutConflict {
+                shortcut: "Ctrl+Alt+C".to_string(),
+                bindings: vec!["copy".to_string(), "send_text \"ls\"".to_string()],
+            }]
+        );
+
+        std::fs::write(&path, "paste = \"Ctrl+Nope\"\n").unwrap();
+        assert!(matches!(
+            ShortcutManager::from_file(&path),
+            Err(KeybindingsError::InvalidShortcut { ref binding, .. }) if binding == "paste"
+        ));
     }
 }
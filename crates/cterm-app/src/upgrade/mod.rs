@@ -16,6 +16,8 @@ mod protocol;
 mod state;
 mod updater;
 
+#[cfg(unix)]
+pub(crate) use protocol::{deserialize_state, serialize_state};
 #[cfg(windows)]
 pub use protocol::{
     execute_upgrade, receive_upgrade, HandleInfo, UpgradeError, WindowsUpgradeData, MAX_FDS,
@@ -58,6 +58,55 @@ pub enum UpgradeError {
     TooManyFds(usize, usize),
 }
 
+/// Serialize upgrade state for sending to another process
+///
+/// Scrollback is spilled to temp files first to keep the state within
+/// socket buffer limits; `deserialize_state` loads it back.
+pub(crate) fn serialize_state(state: &UpgradeState) -> Result<Vec<u8>, UpgradeError> {
+    let mut state = state.clone();
+    for (w_idx, window) in state.windows.iter_mut().enumerate() {
+        for (t_idx, tab) in window.tabs.iter_mut().enumerate() {
+            let index = w_idx * 1000 + t_idx;
+            if let Err(e) = tab.terminal.save_scrollback_to_file(index) {
+                log::warn!("Failed to save scrollback to file for tab {}: {}", index, e);
+            }
+        }
+    }
+
+    // Serialize the state as JSON for forward/backward compatibility
+    serde_json::to_vec(&state).map_err(|e| UpgradeError::Serialization(e.to_string()))
+}
+
+/// Deserialize upgrade state written by `serialize_state`
+pub(crate) fn deserialize_state(data: &[u8]) -> Result<UpgradeState, UpgradeError> {
+    // Try JSON first (new format), fall back to bincode (legacy)
+    let mut state: UpgradeState = if data.first() == Some(&b'{') {
+        log::info!("Detected JSON upgrade format");
+        serde_json::from_slice(data).map_err(|e| UpgradeError::Deserialization(e.to_string()))?
+    } else {
+        log::info!("Detected legacy bincode upgrade format");
+        bincode::deserialize(data).map_err(|e| UpgradeError::Deserialization(e.to_string()))?
+    };
+
+    // Load scrollback from temp files if spilled by the sender. Keep going
+    // after a failure so every tab's file is still removed.
+    let mut result = Ok(());
+    for window in &mut state.windows {
+        for tab in &mut window.tabs {
+            if let Err(e) = tab.terminal.load_scrollback_from_file() {
+                log::error!("Failed to load scrollback for tab {}: {}", tab.id, e);
+                if result.is_ok() {
+                    result = Err(UpgradeError::Deserialization(format!(
+                        "failed to load scrollback for tab {}: {}",
+                        tab.id, e
+                    )));
+                }
+            }
+        }
+    }
+    result.map(|()| state)
+}
+
 /// Execute an upgrade by sending state to a new process
 ///
 /// # Arguments
@@ -89,20 +138,7 @@ pub fn execute_upgrade(
 
     log::info!("Created socketpair, child FD: {}", child_fd);
 
-    // Spill scrollback to temp files to keep state within socket buffer limits
-    let mut state = state.clone();
-    for (w_idx, window) in state.windows.iter_mut().enumerate() {
-        for (t_idx, tab) in window.tabs.iter_mut().enumerate() {
-            let index = w_idx * 1000 + t_idx;
-            if let Err(e) = tab.terminal.save_scrollback_to_file(index) {
-                log::warn!("Failed to save scrollback to file for tab {}: {}", index, e);
-            }
-        }
-    }
-
-    // Serialize the state as JSON for forward/backward compatibility
-    let state_bytes =
-        serde_json::to_vec(&state).map_err(|e| UpgradeError::Serialization(e.to_string()))?;
+    let state_bytes = serialize_state(state)?;
 
     log::info!(
         "State serialized: {} bytes, {} FDs",
@@ -228,20 +264,7 @@ pub fn execute_upgrade(
         return Err(UpgradeError::TooManyFds(handles.len(), MAX_FDS));
     }
 
-    // Spill scrollback to temp files to keep state within socket buffer limits
-    let mut state = state.clone();
-    for (w_idx, window) in state.windows.iter_mut().enumerate() {
-        for (t_idx, tab) in window.tabs.iter_mut().enumerate() {
-            let index = w_idx * 1000 + t_idx;
-            if let Err(e) = tab.terminal.save_scrollback_to_file(index) {
-                log::warn!("Failed to save scrollback to file for tab {}: {}", index, e);
-            }
-        }
-    }
-
-    // Serialize the state as JSON for forward/backward compatibility
-    let state_bytes =
-        serde_json::to_vec(&state).map_err(|e| UpgradeError::Serialization(e.to_string()))?;
+    let state_bytes = serialize_state(state)?;
 
     log::info!(
         "State serialized: {} bytes, {} handle sets",
@@ -492,27 +515,7 @@ pub fn receive_upgrade(fd: RawFd) -> Result<(UpgradeState, Vec<RawFd>), UpgradeE
         fds.len()
     );
 
-    let state_data = &buf[..data_len];
-
-    // Try JSON first (new format), fall back to bincode (legacy)
-    let mut state: UpgradeState = if state_data.first() == Some(&b'{') {
-        log::info!("Detected JSON upgrade format");
-        serde_json::from_slice(state_data)
-            .map_err(|e| UpgradeError::Deserialization(e.to_string()))?
-    } else {
-        log::info!("Detected legacy bincode upgrade format");
-        bincode::deserialize(state_data)
-            .map_err(|e| UpgradeError::Deserialization(e.to_string()))?
-    };
-
-    // Load scrollback from temp files if spilled by the sender
-    for window in &mut state.windows {
-        for tab in &mut window.tabs {
-            if let Err(e) = tab.terminal.load_scrollback_from_file() {
-                log::warn!("Failed to load scrollback from file: {}", e);
-            }
-        }
-    }
+    let state = deserialize_state(&buf[..data_len])?;
 
     log::info!(
         "State deserialized: format_version={}, cterm_version={}, windows={}",
@@ -596,27 +599,7 @@ pub fn receive_upgrade(
 
     log::info!("Received {} handle sets", upgrade_data.handles.len());
 
-    let state_bytes = &upgrade_data.state_bytes;
-
-    // Try JSON first (new format), fall back to bincode (legacy)
-    let mut state: UpgradeState = if state_bytes.first() == Some(&b'{') {
-        log::info!("Detected JSON upgrade format");
-        serde_json::from_slice(state_bytes)
-            .map_err(|e| UpgradeError::Deserialization(e.to_string()))?
-    } else {
-        log::info!("Detected legacy bincode upgrade format");
-        bincode::deserialize(state_bytes)
-            .map_err(|e| UpgradeError::Deserialization(e.to_string()))?
-    };
-
-    // Load scrollback from temp files if spilled by the sender
-    for window in &mut state.windows {
-        for tab in &mut window.tabs {
-            if let Err(e) = tab.terminal.load_scrollback_from_file() {
-                log::warn!("Failed to load scrollback from file: {}", e);
-            }
-        }
-    }
+    let state = deserialize_state(&upgrade_data.state_bytes)?;
 
     log::info!(
         "State deserialized: format_version={}, cterm_version={}, windows={}",
@@ -9,7 +9,9 @@ use serde::{Deserialize, Serialize};
 
 use cterm_core::cell::CellStyle;
 use cterm_core::grid::{Grid, Row};
-use cterm_core::screen::{Cursor, CursorStyle, MouseMode, ScrollRegion, TerminalModes};
+use cterm_core::screen::{
+    Cursor, CursorStyle, MouseMode, Screen, ScreenConfig, ScrollRegion, TerminalModes,
+};
 
 /// Complete upgrade state for all windows
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -88,10 +90,13 @@ pub struct TabUpgradeState {
     /// Tab title
     pub title: String,
     /// Custom title set by user (locks out OSC title updates when Some)
-    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
     pub custom_title: Option<String>,
     /// Tab color (if sticky tab)
     pub color: Option<String>,
+    /// Tab title text color
+    #[serde(default)]
+    pub title_color: Option<String>,
     /// Template name (for sticky/unique tabs)
     pub template_name: Option<String>,
     /// Terminal state
@@ -121,6 +126,7 @@ impl TabUpgradeState {
             title: String::new(),
             custom_title: None,
             color: None,
+            title_color: None,
             template_name: None,
             terminal: TerminalUpgradeState::default(),
             pty_fd_index,
@@ -138,6 +144,7 @@ impl TabUpgradeState {
             title: String::new(),
             custom_title: None,
             color: None,
+            title_color: None,
             template_name: None,
             terminal: TerminalUpgradeState::default(),
             pty_fd_index,
@@ -159,6 +166,7 @@ impl TabUpgradeState {
             title: String::new(),
             custom_title: None,
             color: None,
+            title_color: None,
             template_name: None,
             terminal: TerminalUpgradeState::default(),
             pty_fd_index,
@@ -181,7 +189,7 @@ pub struct TerminalUpgradeState {
     /// Scrollback buffer (may be empty if spilled to file)
     pub scrollback: Vec<Row>,
     /// Path to temp file containing bincode-serialized scrollback (when spilled to disk)
-    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
     pub scrollback_file: Option<String>,
     /// Alternate screen grid (for vim, less, etc.)
     pub alternate_grid: Option<Grid>,
@@ -209,6 +217,12 @@ pub struct TerminalUpgradeState {
     pub cursor_style: CursorStyle,
     /// Mouse mode
     pub mouse_mode: MouseMode,
+    /// Icon name (OSC 1)
+    #[serde(default)]
+    pub icon_name: String,
+    /// Badge format (OSC 1337 SetBadgeFormat)
+    #[serde(default)]
+    pub badge_format: Option<String>,
 }
 
 impl Default for TerminalUpgradeState {
@@ -232,11 +246,70 @@ impl Default for TerminalUpgradeState {
             alternate_active: false,
             cursor_style: CursorStyle::default(),
             mouse_mode: MouseMode::default(),
+            icon_name: String::new(),
+            badge_format: None,
         }
     }
 }
 
 impl TerminalUpgradeState {
+    /// Capture the state of `screen`
+    pub fn from_screen(screen: &Screen) -> Self {
+        Self {
+            cols: screen.grid().width(),
+            rows: screen.grid().height(),
+            grid: screen.grid().clone(),
+            scrollback: screen.scrollback().iter().cloned().collect(),
+            scrollback_file: None,
+            alternate_grid: screen.alternate_grid().cloned(),
+            cursor: screen.cursor.clone(),
+            saved_cursor: screen.saved_cursor().cloned(),
+            alt_saved_cursor: screen.alt_saved_cursor().cloned(),
+            scroll_region: *screen.scroll_region(),
+            style: screen.style.clone(),
+            modes: screen.modes.clone(),
+            title: screen.title.clone(),
+            scroll_offset: screen.scroll_offset,
+            tab_stops: screen.tab_stops().to_vec(),
+            alternate_active: screen.alternate_grid().is_some(),
+            cursor_style: screen.cursor.style,
+            mouse_mode: screen.modes.mouse_mode,
+            icon_name: screen.icon_name.clone(),
+            badge_format: screen.badge_format().map(str::to_string),
+        }
+    }
+
+    /// Rebuild the screen this state was captured from
+    pub fn to_screen(&self, config: ScreenConfig) -> Screen {
+        let mut screen = Screen::from_upgrade_state(
+            self.grid.clone(),
+            self.scrollback.clone(),
+            self.alternate_grid.clone(),
+            self.cursor.clone(),
+            self.saved_cursor.clone(),
+            self.alt_saved_cursor.clone(),
+            self.scroll_region,
+            self.style.clone(),
+            self.modes.clone(),
+            self.title.clone(),
+            self.scroll_offset,
+            self.tab_stops.clone(),
+            config,
+        );
+        self.apply_display_hints(&mut screen);
+        screen
+    }
+
+    /// Apply the state `Screen::from_upgrade_state` doesn't take to a
+    /// reconstructed screen: cursor shape, icon name and badge
+    pub fn apply_display_hints(&self, screen: &mut Screen) {
+        screen.cursor.style = self.cursor_style;
+        screen.icon_name = self.icon_name.clone();
+        if let Some(ref format) = self.badge_format {
+            screen.set_badge_format(format);
+        }
+    }
+
     /// Spill scrollback to a temp file using bincode serialization.
     /// Called before serialization to keep the state compact for socket transfer.
     pub fn save_scrollback_to_file(&mut self, index: usize) -> io::Result<()> {
@@ -273,12 +346,14 @@ impl TerminalUpgradeState {
 
     /// Load scrollback from a temp file if one was used.
     /// Called after deserialization to restore spilled scrollback data.
+    /// The file is removed whether or not it could be read.
     pub fn load_scrollback_from_file(&mut self) -> io::Result<()> {
         if let Some(path) = self.scrollback_file.take() {
-            let file = std::fs::File::open(&path)?;
-            self.scrollback = bincode::deserialize_from(std::io::BufReader::new(file))
-                .map_err(io::Error::other)?;
+            let scrollback = std::fs::File::open(&path).and_then(|file| {
+                bincode::deserialize_from(std::io::BufReader::new(file)).map_err(io::Error::other)
+            });
             let _ = std::fs::remove_file(&path);
+            self.scrollback = scrollback?;
         }
         Ok(())
     }
@@ -339,4 +414,79 @@ mod tests {
         assert_eq!(restored.cols, 120);
         assert_eq!(restored.rows, 40);
     }
+
+    #[test]
+    fn test_scrollback_spill_round_trip() {
+        use cterm_core::cell::Cell;
+
+        let mut long_line = Row::new(4);
+        long_line.get_mut(0).unwrap().c = 'l';
+        long_line.set_overflow(0, Cell::new('!'));
+        let mut short_line = Row::new(4);
+        short_line.get_mut(0).unwrap().c = 's';
+
+        let mut terminal = TerminalUpgradeState {
+            scrollback: vec![long_line, short_line],
+            ..Default::default()
+        };
+        terminal.save_scrollback_to_file(usize::MAX).unwrap();
+        assert!(terminal.scrollback.is_empty());
+        let path = terminal.scrollback_file.clone().unwrap();
+
+        // The state itself goes over as JSON
+        let bytes = serde_json::to_vec(&terminal).unwrap();
+        let mut restored: TerminalUpgradeState = serde_json::from_slice(&bytes).unwrap();
+        restored.load_scrollback_from_file().unwrap();
+
+        assert_eq!(restored.scrollback.len(), 2);
+        assert_eq!(restored.scrollback[0].get_extended(4).unwrap().c, '!');
+        assert_eq!(restored.scrollback[1].text().trim_end(), "s");
+        assert_eq!(restored.scrollback[1].overflow_len(), 0);
+        assert!(!std::path::Path::new(&path).exists());
+    }
+
+    #[test]
+    fn test_scrollback_spill_load_failure() {
+        let path = std::env::temp_dir().join(format!(
+            "cterm_scrollback_test_{}_corrupt.bin",
+            std::process::id()
+        ));
+        std::fs::write(&path, b"\xff\xff\xff\xff\xff\xff\xff\xff").unwrap();
+        let mut terminal = TerminalUpgradeState {
+            scrollback_file: Some(path.to_string_lossy().into_owned()),
+            ..Default::default()
+        };
+
+        assert!(terminal.load_scrollback_from_file().is_err());
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_terminal_state_display_hints() {
+        let mut terminal = TerminalUpgradeState::default();
+        terminal.cursor.style = CursorStyle::Bar;
+        terminal.cursor_style = CursorStyle::Bar;
+        terminal.icon_name = "vim".into();
+        terminal.badge_format = Some("prod".into());
+
+        let bytes = serde_json::to_vec(&terminal).expect("Failed to serialize");
+        let restored: TerminalUpgradeState =
+            serde_json::from_slice(&bytes).expect("Failed to deserialize");
+        assert_eq!(restored.cursor.style, CursorStyle::Bar);
+
+        let mut screen = Screen::new(80, 24, Default::default());
+        restored.apply_display_hints(&mut screen);
+        assert_eq!(screen.cursor.style, CursorStyle::Bar);
+        assert_eq!(screen.icon_name, "vim");
+        assert_eq!(screen.badge().as_deref(), Some("prod"));
+
+        // States from older versions don't have the new fields
+        let mut value = serde_json::to_value(&terminal).unwrap();
+        let object = value.as_object_mut().unwrap();
+        object.remove("icon_name");
+        object.remove("badge_format");
+        let old: TerminalUpgradeState = serde_json::from_value(value).unwrap();
+        assert!(old.icon_name.is_empty());
+        assert!(old.badge_format.is_none());
+    }
 }
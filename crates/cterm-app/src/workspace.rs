@@ -0,0 +1,380 @@
+//! Workspace files
+//!
+//! A workspace opens a whole layout at once: windows, their tabs, and each
+//! tab's directory, command and colors. It's a session written by hand, or a
+//! set of tab templates opened together. Workspaces are TOML, or JSON when the
+//! file name ends in `.json`:
+//!
+//! ```toml
+//! name = "myapp"
+//!
+//! [[windows]]
+//! maximized = true
+//! active_tab = 1
+//!
+//! [[windows.tabs]]
+//! name = "Server"
+//! command = "npm"
+//! args = ["run", "dev"]
+//! working_directory = "~/projects/myapp"
+//! color = "#22c55e"
+//!
+//! [[windows.tabs]]
+//! template = "Claude"
+//! working_directory = "."
+//! ```
+//!
+//! A file with top-level `[[tabs]]` instead of `[[windows]]` is one window.
+//! Relative working directories are relative to the workspace file.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::config::{find_template, load_sticky_tabs, ConfigError, StickyTabConfig};
+
+/// File extension used for workspace files
+pub const WORKSPACE_EXTENSION: &str = "workspace";
+
+/// Workspace errors
+#[derive(Error, Debug)]
+pub enum WorkspaceError {
+    #[error("Failed to read workspace file: {0}")]
+    Read(#[from] std::io::Error),
+
+    #[error("Failed to parse workspace file: {0}")]
+    Parse(#[from] toml::de::Error),
+
+    #[error("Failed to parse workspace file: {0}")]
+    ParseJson(#[from] serde_json::Error),
+
+    #[error("Workspace has no windows or tabs")]
+    Empty,
+
+    #[error(transparent)]
+    Template(#[from] ConfigError),
+}
+
+/// A workspace file
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Workspace {
+    /// Display name, for logs
+    pub name: Option<String>,
+    /// Windows to open, in order
+    pub windows: Vec<WorkspaceWindow>,
+    /// Tabs of a single window (shorthand for one `[[windows]]`)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tabs: Vec<WorkspaceTab>,
+}
+
+/// A window of a workspace
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WorkspaceWindow {
+    /// Window width in pixels (0 = default size)
+    pub width: u32,
+    /// Window height in pixels (0 = default size)
+    pub height: u32,
+    /// Whether the window starts maximized
+    pub maximized: bool,
+    /// Index of the tab focused after opening
+    pub active_tab: usize,
+    /// Tabs to open, in order; an empty window gets a shell
+    pub tabs: Vec<WorkspaceTab>,
+}
+
+/// A tab of a workspace window
+///
+/// Fields left out come from `template` if set, otherwise the tab is a shell.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WorkspaceTab {
+    /// Tab title (defaults to the template name, command or directory)
+    pub name: Option<String>,
+    /// Tab template to start from
+    pub template: Option<String>,
+    /// Command to run (None = template command or default shell)
+    pub command: Option<String>,
+    /// Command arguments
+    pub args: Vec<String>,
+    /// Working directory
+    pub working_directory: Option<PathBuf>,
+    /// Tab color (hex)
+    pub color: Option<String>,
+    /// Tab title text color (hex)
+    pub title_color: Option<String>,
+    /// Theme override for this tab
+    pub theme: Option<String>,
+    /// Environment variables, added to the template's
+    pub env: HashMap<String, String>,
+    /// Keep the tab open after the process exits
+    pub keep_open: Option<bool>,
+    /// Pin the tab to the start of the tab bar
+    pub pinned: bool,
+}
+
+impl Workspace {
+    /// Parse a workspace from TOML, or JSON if `json` is set
+    pub fn parse(content: &str, json: bool) -> Result<Self, WorkspaceError> {
+        let mut workspace: Workspace = if json {
+            serde_json::from_str(content)?
+        } else {
+            toml::from_str(content)?
+        };
+
+        if !workspace.tabs.is_empty() {
+            let tabs = std::mem::take(&mut workspace.tabs);
+            workspace.windows.insert(
+                0,
+                WorkspaceWindow {
+                    tabs,
+                    ..Default::default()
+                },
+            );
+        }
+        if workspace.windows.is_empty() {
+            return Err(WorkspaceError::Empty);
+        }
+        Ok(workspace)
+    }
+
+    /// Make relative working directories relative to `base`
+    fn resolve_directories(&mut self, base: &Path) {
+        let tabs = self.windows.iter_mut().flat_map(|w| w.tabs.iter_mut());
+        for tab in tabs {
+            if let Some(ref mut dir) = tab.working_directory {
+                let is_relative = dir.is_relative()
+                    && !dir.starts_with("~")
+                    && !dir.to_string_lossy().starts_with(['$', '{']);
+                if is_relative {
+                    *dir = base.join(&*dir);
+                }
+            }
+        }
+    }
+}
+
+impl WorkspaceTab {
+    /// The tab template this tab opens, starting from `template` if set
+    pub fn to_template(
+        &self,
+        templates: &[StickyTabConfig],
+    ) -> Result<StickyTabConfig, ConfigError> {
+        let mut config = match self.template {
+            Some(ref name) => find_template(templates, name)?,
+            None => StickyTabConfig {
+                name: self.default_name(),
+                ..Default::default()
+            },
+        };
+
+        if let Some(ref name) = self.name {
+            config.name = name.clone();
+        }
+        if let Some(ref command) = self.command {
+            config.command = Some(command.clone());
+            config.args = self.args.clone();
+        } else if !self.args.is_empty() {
+            config.args = self.args.clone();
+        }
+        if let Some(ref dir) = self.working_directory {
+            config.working_directory = Some(dir.clone());
+        }
+        if let Some(ref color) = self.color {
+            config.color = Some(color.clone());
+        }
+        if let Some(ref color) = self.title_color {
+            config.title_color = Some(color.clone());
+        }
+        if let Some(ref theme) = self.theme {
+            config.theme = Some(theme.clone());
+        }
+        config.env.extend(self.env.clone());
+        if let Some(keep_open) = self.keep_open {
+            config.keep_open = keep_open;
+        }
+        Ok(config)
+    }
+
+    /// Title for an unnamed tab: the program, else the directory name
+    fn default_name(&self) -> String {
+        let program = self
+            .command
+            .as_deref()
+            .and_then(|c| Path::new(c).file_name());
+        let directory = self
+            .working_directory
+            .as_deref()
+            .and_then(|d| d.file_name());
+        program
+            .or(directory)
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "Terminal".to_string())
+    }
+}
+
+/// Load a workspace file, checking that the templates it uses exist
+pub fn load_workspace(path: &Path) -> Result<Workspace, WorkspaceError> {
+    let content = std::fs::read_to_string(path)?;
+    let json = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+    let mut workspace = Workspace::parse(&content, json)?;
+    if let Some(base) = path.parent() {
+        workspace.resolve_directories(base);
+    }
+
+    let tabs = || workspace.windows.iter().flat_map(|w| w.tabs.iter());
+    if tabs().any(|tab| tab.template.is_some()) {
+        let templates = load_sticky_tabs()?;
+        for tab in tabs() {
+            tab.to_template(&templates)?;
+        }
+    }
+    Ok(workspace)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_workspace() {
+        let workspace = Workspace::parse(
+            r##"
+name = "myapp"
+
+[[windows]]
+maximized = true
+active_tab = 1
+
+[[windows.tabs]]
+name = "Server"
+command = "npm"
+args = ["run", "dev"]
+color = "#22c55e"
+
+[[windows.tabs]]
+working_directory = "src"
+
+[[windows]]
+"##,
+            false,
+        )
+        .unwrap();
+        assert_eq!(workspace.name.as_deref(), Some("myapp"));
+        assert_eq!(workspace.windows.len(), 2);
+        assert!(workspace.windows[0].maximized);
+        assert_eq!(workspace.windows[0].active_tab, 1);
+        assert_eq!(workspace.windows[0].tabs.len(), 2);
+        assert!(workspace.windows[1].tabs.is_empty());
+
+        // Top-level tabs are one window
+        let workspace = Workspace::parse(
+            r#"{"tabs": [{"command": "htop"}, {"name": "Logs", "pinned": true}]}"#,
+            true,
+        )
+        .unwrap();
+        assert_eq!(workspace.windows.len(), 1);
+        assert_eq!(workspace.windows[0].tabs.len(), 2);
+        assert!(workspace.windows[0].tabs[1].pinned);
+
+        assert!(matches!(
+            Workspace::parse("name = \"empty\"", false),
+            Err(WorkspaceError::Empty)
+        ));
+        assert!(matches!(
+            Workspace::parse("windows = 3", false),
+            Err(WorkspaceError::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn test_resolve_directories() {
+        let mut workspace = Workspace::parse(
+            r#"
+[[tabs]]
+working_directory = "src"
+[[tabs]]
+working_directory = "~/x"
+[[tabs]]
+working_directory = "/tmp"
+"#,
+            false,
+        )
+        .unwrap();
+        workspace.resolve_directories(Path::new("/projects/myapp"));
+        let dirs: Vec<_> = workspace.windows[0]
+            .tabs
+            .iter()
+            .map(|t| t.working_directory.clone().unwrap())
+            .collect();
+        assert_eq!(
+            dirs,
+            vec![
+                PathBuf::from("/projects/myapp/src"),
+                PathBuf::from("~/x"),
+                PathBuf::from("/tmp"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tab_to_template() {
+        let templates = vec![StickyTabConfig {
+            name: "Claude".into(),
+            command: Some("claude".into()),
+            color: Some("#7c3aed".into()),
+            keep_open: true,
+            ..Default::default()
+        }];
+
+        let tab = WorkspaceTab {
+            template: Some("claude".into()),
+            working_directory: Some(PathBuf::from("/src")),
+            title_color: Some("#ffffff".into()),
+            ..Default::default()
+        };
+        let config = tab.to_template(&templates).unwrap();
+        assert_eq!(config.name, "Claude");
+        assert_eq!(config.command.as_deref(), Some("claude"));
+        assert_eq!(config.color.as_deref(), Some("#7c3aed"));
+        assert_eq!(config.title_color.as_deref(), Some("#ffffff"));
+        assert_eq!(config.working_directory, Some(PathBuf::from("/src")));
+        assert!(config.keep_open);
+
+        let tab = WorkspaceTab {
+            command: Some("/usr/bin/htop".into()),
+            keep_open: Some(true),
+            ..Default::default()
+        };
+        let config = tab.to_template(&templates).unwrap();
+        assert_eq!(config.name, "htop");
+        assert!(config.keep_open);
+
+        let tab = WorkspaceTab {
+            working_directory: Some(PathBuf::from("/projects/myapp")),
+            ..Default::default()
+        };
+        assert_eq!(tab.to_template(&templates).unwrap().name, "myapp");
+        assert_eq!(
+            WorkspaceTab::default()
+                .to_template(&templates)
+                .unwrap()
+                .name,
+            "Terminal"
+        );
+
+        let tab = WorkspaceTab {
+            template: Some("Missing".into()),
+            ..Default::default()
+        };
+        assert!(matches!(
+            tab.to_template(&templates),
+            Err(ConfigError::UnknownTemplate { .. })
+        ));
+    }
+}
@@ -13,6 +13,8 @@ use objc2_foundation::{MainThreadMarker, NSNotification, NSObject, NSObjectProto
 use std::path::PathBuf;
 
 use cterm_app::config::{load_config, Config};
+use cterm_app::session::QuitSummary;
+use cterm_app::workspace::Workspace;
 use cterm_ui::theme::Theme;
 
 use crate::menu;
@@ -30,6 +32,23 @@ pub struct Args {
     #[arg(short = 'e', long = "execute")]
     pub command: Option<String>,
 
+    /// Run the -e command directly instead of through the shell
+    #[arg(long, requires = "command")]
+    pub no_shell: bool,
+
+    /// Start the terminal in raw mode (no echo or line editing), for
+    /// programs that manage their own line discipline
+    #[arg(long)]
+    pub raw: bool,
+
+    /// Open the first tab from the named tab template
+    #[arg(long, visible_alias = "profile", conflicts_with = "command")]
+    pub template: Option<String>,
+
+    /// Open the windows and tabs of a workspace file
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["command", "template"])]
+    pub workspace: Option<PathBuf>,
+
     /// Set the working directory
     #[arg(short = 'd', long = "directory")]
     pub directory: Option<PathBuf>,
@@ -299,6 +318,8 @@ define_class!(
                 // Clear crash state file after successful recovery
                 let _ = cterm_app::clear_crash_state();
 
+                self.show_pending_crash_report(mtm);
+
                 // Start periodic state saving
                 self.start_state_save_timer(mtm);
 
@@ -327,7 +348,49 @@ define_class!(
 
             // Normal startup - create the main window
             log::debug!("Creating main window...");
-            let window = CtermWindow::new(mtm, &self.ivars().config.borrow(), &self.ivars().theme.borrow());
+
+            // Run the -e command in the main window instead of the shell
+            let mut config = self.ivars().config.borrow().clone();
+            let args = get_args();
+            if args.no_shell {
+                config.general.execute_without_shell = true;
+            }
+            if args.raw {
+                config.general.raw_pty = true;
+            }
+            if let Some(ref command) = args.command {
+                if let Err(e) = config.general.set_execute_command(command) {
+                    log::error!("Ignoring -e: {}", e);
+                }
+            }
+            // Open the workspace's windows instead of the main window
+            let workspace = args.workspace.as_deref().and_then(|path| {
+                cterm_app::workspace::load_workspace(path)
+                    .map_err(|e| log::error!("Ignoring --workspace: {}", e))
+                    .ok()
+            });
+            if let Some(workspace) = workspace {
+                self.open_workspace(mtm, &config, &workspace);
+                #[allow(deprecated)]
+                NSApplication::sharedApplication(mtm).activateIgnoringOtherApps(true);
+                return;
+            }
+
+            // Open the first tab from --template if given
+            let template = args.template.as_deref().and_then(|name| {
+                cterm_app::config::load_template(name)
+                    .map_err(|e| log::error!("Ignoring --template: {}", e))
+                    .ok()
+            });
+            let window = match template {
+                Some(template) => CtermWindow::from_template(
+                    mtm,
+                    &config,
+                    &self.ivars().theme.borrow(),
+                    &template,
+                ),
+                None => CtermWindow::new(mtm, &config, &self.ivars().theme.borrow()),
+            };
             log::debug!("Main window created");
 
             // Store window reference
@@ -338,6 +401,9 @@ define_class!(
             window.makeKeyAndOrderFront(None);
             log::info!("Window shown (makeKeyAndOrderFront)");
 
+            #[cfg(unix)]
+            self.show_pending_crash_report(mtm);
+
             // Activate the app to bring window to front
             #[allow(deprecated)]
             NSApplication::sharedApplication(mtm).activateIgnoringOtherApps(true);
@@ -367,49 +433,17 @@ define_class!(
                 return NSApplicationTerminateReply::TerminateNow;
             }
 
-            // Check if config says to confirm close with running processes
-            if !self.ivars().config.borrow().general.confirm_close_with_running {
-                return NSApplicationTerminateReply::TerminateNow;
-            }
-
-            // Collect all windows with running processes
-            #[cfg(unix)]
-            let running_processes: Vec<String> = {
-                let windows = self.ivars().windows.borrow();
-                windows
-                    .iter()
-                    .filter_map(|window| {
-                        if let Some(terminal) = window.active_terminal() {
-                            if terminal.has_foreground_process() {
-                                return terminal.foreground_process_name();
-                            }
-                        }
-                        None
-                    })
-                    .collect()
-            };
-
-            #[cfg(not(unix))]
-            let running_processes: Vec<String> = Vec::new();
-
-            if running_processes.is_empty() {
+            // Ask first with confirm_quit, or when programs are running
+            let summary = self.quit_summary();
+            if !summary.needs_confirmation(&self.ivars().config.borrow().general) {
                 return NSApplicationTerminateReply::TerminateNow;
             }
 
             // Show confirmation dialog
             let mtm = MainThreadMarker::from(self);
             let alert = NSAlert::new(mtm);
-
-            let message = if running_processes.len() == 1 {
-                format!("\"{}\" is still running", running_processes[0])
-            } else {
-                format!("{} processes are still running", running_processes.len())
-            };
-
-            alert.setMessageText(&NSString::from_str(&message));
-            alert.setInformativeText(&NSString::from_str(
-                "Quitting will terminate the running process(es). Are you sure you want to quit?",
-            ));
+            alert.setMessageText(&NSString::from_str("Quit cterm?"));
+            alert.setInformativeText(&NSString::from_str(&summary.details()));
             alert.setAlertStyle(NSAlertStyle::Warning);
 
             alert.addButtonWithTitle(&NSString::from_str("Quit"));
@@ -442,10 +476,107 @@ define_class!(
                 let delegate = unsafe { &*self_ptr };
                 *delegate.ivars().config.borrow_mut() = new_config.clone();
                 *delegate.ivars().theme.borrow_mut() = get_theme(&new_config);
+                cterm_app::clipboard_history::configure(&new_config.general);
                 log::info!("Preferences saved");
             });
         }
 
+        #[unsafe(method(importTheme:))]
+        fn action_import_theme(&self, _sender: Option<&objc2::runtime::AnyObject>) {
+            let mtm = MainThreadMarker::from(self);
+            let Some(path) = crate::dialogs::show_open_panel(mtm) else {
+                return;
+            };
+            let mut config = self.ivars().config.borrow().clone();
+            match cterm_app::config::import_theme(&mut config, &path) {
+                Ok(theme) => {
+                    log::info!("Imported theme {:?} from {:?}", theme.name, path);
+                    // New windows pick up the cached theme
+                    *self.ivars().theme.borrow_mut() = get_theme(&config);
+                    *self.ivars().config.borrow_mut() = config;
+                    crate::dialogs::show_info(
+                        mtm,
+                        "Theme Imported",
+                        &format!("Imported theme \"{}\". New windows use it.", theme.name),
+                    );
+                }
+                Err(e) => {
+                    log::error!("Failed to import theme from {:?}: {}", path, e);
+                    crate::dialogs::show_error(mtm, None, "Import Theme Failed", &e.to_string());
+                }
+            }
+        }
+
+        #[unsafe(method(importKeybindings:))]
+        fn action_import_keybindings(&self, _sender: Option<&objc2::runtime::AnyObject>) {
+            let mtm = MainThreadMarker::from(self);
+            let Some(path) = crate::dialogs::show_open_panel(mtm) else {
+                return;
+            };
+            let mut config = self.ivars().config.borrow().clone();
+            match cterm_app::config::import_keybindings(&mut config, &path) {
+                Ok(conflicts) => {
+                    log::info!("Imported keybindings from {:?}", path);
+                    *self.ivars().config.borrow_mut() = config;
+                    let mut message = "Imported keybindings. New windows use them.".to_string();
+                    if let Some(note) = cterm_app::shortcuts::describe_conflicts(&conflicts) {
+                        message.push_str(&format!("\n\n{}", note));
+                    }
+                    crate::dialogs::show_info(mtm, "Keybindings Imported", &message);
+                }
+                Err(e) => {
+                    log::error!("Failed to import keybindings from {:?}: {}", path, e);
+                    crate::dialogs::show_error(
+                        mtm,
+                        None,
+                        "Import Keybindings Failed",
+                        &e.to_string(),
+                    );
+                }
+            }
+        }
+
+        #[unsafe(method(exportKeybindings:))]
+        fn action_export_keybindings(&self, _sender: Option<&objc2::runtime::AnyObject>) {
+            let mtm = MainThreadMarker::from(self);
+            let Some(path) =
+                crate::dialogs::show_save_panel(mtm, None, Some("keybindings.toml"), None)
+            else {
+                return;
+            };
+            let shortcuts = self.ivars().config.borrow().shortcuts.clone();
+            match cterm_app::shortcuts::ShortcutManager::to_file(&shortcuts, &path) {
+                Ok(()) => log::info!("Exported keybindings to {:?}", path),
+                Err(e) => {
+                    log::error!("Failed to export keybindings to {:?}: {}", path, e);
+                    crate::dialogs::show_error(
+                        mtm,
+                        None,
+                        "Export Keybindings Failed",
+                        &e.to_string(),
+                    );
+                }
+            }
+        }
+
+        #[unsafe(method(openWorkspace:))]
+        fn action_open_workspace(&self, _sender: Option<&objc2::runtime::AnyObject>) {
+            let mtm = MainThreadMarker::from(self);
+            let Some(path) = crate::dialogs::show_open_panel(mtm) else {
+                return;
+            };
+            match cterm_app::workspace::load_workspace(&path) {
+                Ok(workspace) => {
+                    let config = self.ivars().config.borrow().clone();
+                    self.open_workspace(mtm, &config, &workspace);
+                }
+                Err(e) => {
+                    log::error!("Failed to open workspace {:?}: {}", path, e);
+                    crate::dialogs::show_error(mtm, None, "Open Workspace Failed", &e.to_string());
+                }
+            }
+        }
+
         #[unsafe(method(showTabTemplates:))]
         fn action_show_tab_templates(&self, _sender: Option<&objc2::runtime::AnyObject>) {
             let mtm = MainThreadMarker::from(self);
@@ -529,7 +660,7 @@ define_class!(
                                 {
                                     cterm_window
                                         .active_terminal()
-                                        .and_then(|t| t.foreground_cwd())
+                                        .and_then(|t| t.working_directory())
                                 }
                                 #[cfg(not(unix))]
                                 {
@@ -574,7 +705,30 @@ define_class!(
             use objc2_app_kit::NSWindowTabbingMode;
 
             let mtm = MainThreadMarker::from(self);
-            let window = CtermWindow::new(mtm, &self.ivars().config.borrow(), &self.ivars().theme.borrow());
+
+            // Start in the working directory of the active terminal
+            #[cfg(unix)]
+            let cwd = NSApplication::sharedApplication(mtm)
+                .keyWindow()
+                .filter(|key_window| unsafe {
+                    msg_send![&**key_window, isKindOfClass: objc2::class!(CtermWindow)]
+                })
+                .and_then(|key_window| {
+                    let cterm_window: &CtermWindow =
+                        unsafe { &*(&*key_window as *const NSWindow as *const CtermWindow) };
+                    cterm_window
+                        .active_terminal()
+                        .and_then(|t| t.working_directory())
+                });
+            #[cfg(not(unix))]
+            let cwd: Option<String> = None;
+
+            let window = CtermWindow::new_with_cwd(
+                mtm,
+                &self.ivars().config.borrow(),
+                &self.ivars().theme.borrow(),
+                cwd,
+            );
 
             // Temporarily disable tabbing to force a new window instead of a tab
             window.setTabbingMode(NSWindowTabbingMode::Disallowed);
@@ -915,6 +1069,65 @@ impl AppDelegate {
         }
     }
 
+    /// Open every window of a workspace, its tabs as native tabs
+    fn open_workspace(&self, mtm: MainThreadMarker, config: &Config, workspace: &Workspace) {
+        use objc2_app_kit::{NSWindowOrderingMode, NSWindowTabbingMode};
+        use objc2_foundation::NSSize;
+
+        log::info!(
+            "Opening workspace {} with {} window(s)",
+            workspace.name.as_deref().unwrap_or("(unnamed)"),
+            workspace.windows.len()
+        );
+        let templates = cterm_app::config::load_sticky_tabs().unwrap_or_default();
+        let theme = self.ivars().theme.borrow().clone();
+        for workspace_window in &workspace.windows {
+            let mut tabs: Vec<(Retained<CtermWindow>, Option<String>)> = Vec::new();
+            for tab in &workspace_window.tabs {
+                match tab.to_template(&templates) {
+                    Ok(template) => tabs.push((
+                        CtermWindow::from_template(mtm, config, &theme, &template),
+                        template.color.clone(),
+                    )),
+                    Err(e) => log::error!("Skipping workspace tab: {}", e),
+                }
+            }
+            if tabs.is_empty() {
+                tabs.push((CtermWindow::new(mtm, config, &theme), None));
+            }
+
+            // Show the first tab as a window of its own, then group the rest
+            let first = tabs[0].0.clone();
+            if workspace_window.width > 0 && workspace_window.height > 0 {
+                let mut frame = first.frame();
+                frame.size = NSSize::new(
+                    workspace_window.width as f64,
+                    workspace_window.height as f64,
+                );
+                first.setFrame_display(frame, true);
+            }
+            first.setTabbingMode(NSWindowTabbingMode::Disallowed);
+            first.makeKeyAndOrderFront(None);
+            first.setTabbingMode(NSWindowTabbingMode::Preferred);
+            for (window, _) in &tabs[1..] {
+                first.addTabbedWindow_ordered(window, NSWindowOrderingMode::Above);
+            }
+            if workspace_window.maximized {
+                first.zoom(None);
+            }
+
+            // Tab colors need the windows to be in place
+            for (window, color) in &tabs {
+                if let Some(color) = color {
+                    window.set_tab_color(Some(color));
+                }
+                self.ivars().windows.borrow_mut().push(window.clone());
+            }
+            let active = workspace_window.active_tab.min(tabs.len() - 1);
+            tabs[active].0.makeKeyAndOrderFront(None);
+        }
+    }
+
     /// Restore windows from seamless upgrade state
     #[cfg(unix)]
     fn restore_from_upgrade(
@@ -966,7 +1179,7 @@ impl AppDelegate {
                     scrollback_lines: self.ivars().config.borrow().general.scrollback_lines,
                 };
 
-                let screen = Screen::from_upgrade_state(
+                let mut screen = Screen::from_upgrade_state(
                     term_state.grid.clone(),
                     term_state.scrollback.clone(),
                     term_state.alternate_grid.clone(),
@@ -981,6 +1194,7 @@ impl AppDelegate {
                     term_state.tab_stops.clone(),
                     screen_config,
                 );
+                term_state.apply_display_hints(&mut screen);
 
                 // Create Terminal with the restored screen and PTY
                 let terminal = Terminal::from_restored(screen, pty);
@@ -1038,6 +1252,10 @@ impl AppDelegate {
                         tab_idx
                     );
                 }
+
+                // Now that the window is in place, re-establish focus and
+                // the IME context for its terminal
+                window.reset_terminal_input();
             }
 
             if first_window.is_some() {
@@ -1058,6 +1276,10 @@ impl AppDelegate {
         let mut upgrade_state = UpgradeState::new(env!("CARGO_PKG_VERSION"));
 
         for window in windows.iter() {
+            // Incognito tabs are never written to disk
+            if window.active_terminal().is_some_and(|t| t.is_incognito()) {
+                continue;
+            }
             let mut window_state = WindowUpgradeState::new();
 
             // Get window frame
@@ -1163,6 +1385,50 @@ impl AppDelegate {
         log::info!("Started crash state save timer (interval: {}s)", interval);
     }
 
+    /// Show the crash report written by the watchdog, if there is one
+    #[cfg(unix)]
+    fn show_pending_crash_report(&self, mtm: MainThreadMarker) {
+        let Some(path) = cterm_app::take_pending_crash_report() else {
+            return;
+        };
+        log::warn!("Previous process crashed, report at {}", path.display());
+        if self.ivars().config.borrow().general.show_crash_report {
+            crate::dialogs::show_crash_report(mtm, &path);
+        }
+    }
+
+    /// Windows, tabs and running programs that quitting would close
+    fn quit_summary(&self) -> QuitSummary {
+        let windows = self.ivars().windows.borrow();
+        let mut summary = QuitSummary {
+            tabs: windows.len(),
+            ..Default::default()
+        };
+        for window in windows.iter() {
+            // Each tab is a window; count a tab group once, by its first tab
+            let tabbed: Option<Retained<objc2_foundation::NSArray<NSWindow>>> =
+                unsafe { msg_send![&**window, tabbedWindows] };
+            let first = tabbed.and_then(|tabbed| tabbed.firstObject());
+            if first.is_none_or(|first| {
+                Retained::as_ptr(&first) == Retained::as_ptr(window) as *const NSWindow
+            }) {
+                summary.windows += 1;
+            }
+
+            #[cfg(unix)]
+            if let Some(terminal) = window.active_terminal() {
+                if terminal.has_foreground_process() {
+                    summary.running.push(
+                        terminal
+                            .foreground_process_name()
+                            .unwrap_or_else(|| "a process".to_string()),
+                    );
+                }
+            }
+        }
+        summary
+    }
+
     /// Perform a seamless relaunch, preserving all windows and tabs
     ///
     /// This collects state from all windows, duplicates PTY file descriptors,
@@ -1226,6 +1492,10 @@ impl AppDelegate {
         drop(windows);
 
         for window in ordered_windows.iter() {
+            // Incognito tabs are not carried over; they close with the old process
+            if window.active_terminal().is_some_and(|t| t.is_incognito()) {
+                continue;
+            }
             let mut window_state = WindowUpgradeState::new();
 
             // Get window frame
@@ -1375,6 +1645,20 @@ pub fn run() {
 
     log::info!("Starting cterm (native macOS)");
 
+    // Fail before opening a window if the template doesn't exist
+    if let Some(ref name) = args.template {
+        if let Err(e) = cterm_app::config::load_template(name) {
+            eprintln!("cterm: {}", e);
+            std::process::exit(2);
+        }
+    }
+    if let Some(ref path) = args.workspace {
+        if let Err(e) = cterm_app::workspace::load_workspace(path) {
+            eprintln!("cterm: {}: {}", path.display(), e);
+            std::process::exit(2);
+        }
+    }
+
     // Check if we're in upgrade receiver mode
     #[cfg(unix)]
     if let Some(fd) = args.upgrade_receiver {
@@ -1454,20 +1738,26 @@ pub fn run_app_internal() {
     }
 
     // Load configuration
-    let config = load_config().unwrap_or_else(|e| {
+    let mut config = load_config().unwrap_or_else(|e| {
         log::warn!("Failed to load config, using defaults: {}", e);
         Config::default()
     });
 
-    // Get theme
-    let theme = get_theme(&config);
-
     // Get the shared application instance
     let app = NSApplication::sharedApplication(mtm);
 
     // Set activation policy to regular (shows in Dock)
     app.setActivationPolicy(NSApplicationActivationPolicy::Regular);
 
+    // On first run, pick a theme, font and shell before the first window opens
+    if cterm_app::config::is_first_run() {
+        config = crate::preferences::run_setup_assistant(mtm, &config);
+    }
+    cterm_app::clipboard_history::configure(&config.general);
+
+    // Get theme
+    let theme = get_theme(&config);
+
     // Create and set the application delegate
     let delegate = AppDelegate::new(mtm, config, theme);
     app.setDelegate(Some(ProtocolObject::from_ref(&*delegate)));
@@ -3,35 +3,82 @@
 //! Renders terminal content using CoreGraphics for text drawing.
 //! This is simpler than Metal but sufficient for basic functionality.
 
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
 use objc2::rc::Retained;
 use objc2::runtime::AnyObject;
-use objc2::{class, msg_send};
-use objc2_app_kit::{NSFont, NSGraphicsContext};
+use objc2::{class, msg_send, AllocAnyThread};
+use objc2_app_kit::{NSCompositingOperation, NSFont, NSGraphicsContext, NSImage};
 use objc2_foundation::{MainThreadMarker, NSPoint, NSRect, NSSize, NSString};
 
-use cterm_core::cell::CellAttrs;
-use cterm_core::color::{Color, Rgb};
+use cterm_app::config::{
+    badge_color, ruler_color, ruler_positions, AppearanceConfig, BackgroundImageMode,
+    CursorLineHighlight, ScrollPositionIndicator,
+};
+use cterm_core::cell::{Cell, CellAttrs};
+use cterm_core::color::{Color, ColorOverrides, Rgb, DEFAULT_FAINT_OPACITY};
 use cterm_core::drcs::DrcsGlyph;
+use cterm_core::screen::CellRegion;
 use cterm_core::Terminal;
 use cterm_core::TerminalImage;
-use cterm_ui::theme::Theme;
+use cterm_ui::minimap::{minimap_viewport, Minimap, MINIMAP_WIDTH};
+use cterm_ui::theme::{FontConfig, Theme, SYSTEM_MONOSPACE};
+
+thread_local! {
+    /// Loaded background images by path, shared by all terminals
+    static BACKGROUND_IMAGES: RefCell<HashMap<PathBuf, Option<Retained<NSImage>>>> =
+        RefCell::new(HashMap::new());
+}
 
 /// CoreGraphics renderer for terminal display
 pub struct CGRenderer {
     font: Retained<NSFont>,
+    /// Theme with the program's color overrides applied
     theme: Theme,
+    /// Theme as configured
+    base_theme: Theme,
+    /// Default colors set by the program (OSC 10/11/12)
+    color_overrides: ColorOverrides,
     cell_width: f64,
     cell_height: f64,
     /// Optional background color override (from template)
     background_override: Option<Rgb>,
+    /// Cursor line highlight mode
+    cursor_line: CursorLineHighlight,
+    /// Whether to draw the badge set by programs (iTerm2 SetBadgeFormat)
+    show_badge: bool,
+    /// How visible faint (SGR 2) text is over its background
+    faint_opacity: f64,
+    /// Scrollback minimap drawn on the right edge, if enabled
+    minimap: Option<Minimap>,
+    /// Scroll position shown in the bottom-right corner while scrolled back
+    scroll_position: ScrollPositionIndicator,
+    /// Columns to draw a ruler after (`appearance.ruler_columns`)
+    ruler_columns: Vec<usize>,
+    /// Image drawn behind the text, if configured
+    background_image: Option<BackgroundImage>,
 }
 
 impl CGRenderer {
     /// Create a new CoreGraphics renderer
-    pub fn new(_mtm: MainThreadMarker, font_name: &str, font_size: f64, theme: &Theme) -> Self {
-        // Try to get the specified font, fall back to Menlo
-        let font = NSFont::fontWithName_size(&NSString::from_str(font_name), font_size)
-            .or_else(|| NSFont::fontWithName_size(&NSString::from_str("Menlo"), font_size))
+    pub fn new(_mtm: MainThreadMarker, font_config: &FontConfig, theme: &Theme) -> Self {
+        let font_size = font_config.size;
+        let load = |name: &str| {
+            if name.eq_ignore_ascii_case(SYSTEM_MONOSPACE) {
+                Some(NSFont::monospacedSystemFontOfSize_weight(font_size, 0.0))
+            } else {
+                NSFont::fontWithName_size(&NSString::from_str(name), font_size)
+            }
+        };
+
+        // Try the configured fonts, then the fallback chain, then the system font
+        let (font_name, substitution) = font_config.resolve(|name| load(name).is_some());
+        if let Some(substitution) = substitution {
+            log::warn!("{}", substitution);
+        }
+        let font = load(&font_name)
             .unwrap_or_else(|| NSFont::monospacedSystemFontOfSize_weight(font_size, 0.0));
 
         // Calculate cell dimensions using font metrics
@@ -48,12 +95,56 @@ impl CGRenderer {
         Self {
             font,
             theme: theme.clone(),
+            base_theme: theme.clone(),
+            color_overrides: ColorOverrides::default(),
             cell_width,
             cell_height,
             background_override: None,
+            cursor_line: CursorLineHighlight::Off,
+            show_badge: true,
+            faint_opacity: DEFAULT_FAINT_OPACITY,
+            minimap: None,
+            scroll_position: ScrollPositionIndicator::Off,
+            ruler_columns: Vec::new(),
+            background_image: None,
         }
     }
 
+    /// Set whether the row the cursor is on gets a background tint
+    pub fn set_cursor_line_highlight(&mut self, mode: CursorLineHighlight) {
+        self.cursor_line = mode;
+    }
+
+    /// Set whether the badge set by programs is drawn
+    pub fn set_show_badge(&mut self, show: bool) {
+        self.show_badge = show;
+    }
+
+    /// Set how visible faint (SGR 2) text is
+    pub fn set_faint_opacity(&mut self, opacity: f64) {
+        self.faint_opacity = opacity;
+    }
+
+    /// Set whether the scrollback minimap is drawn
+    pub fn set_minimap(&mut self, enabled: bool) {
+        self.minimap = enabled.then(Minimap::new);
+    }
+
+    /// Set how the scroll position is shown while scrolled back
+    pub fn set_scroll_position(&mut self, indicator: ScrollPositionIndicator) {
+        self.scroll_position = indicator;
+    }
+
+    /// Set the columns to draw a ruler after
+    pub fn set_ruler_columns(&mut self, columns: &[usize]) {
+        self.ruler_columns = columns.to_vec();
+    }
+
+    /// Set the image drawn behind the text (`appearance.background_image`)
+    pub fn set_background_image(&mut self, appearance: &AppearanceConfig) {
+        self.background_image = BackgroundImage::from_config(appearance);
+    }
+
     /// Set an optional background color override (hex string like "#1a1b26")
     pub fn set_background_override(&mut self, color: Option<&str>) {
         self.background_override = color.and_then(|hex| {
@@ -69,6 +160,25 @@ impl CGRenderer {
         });
     }
 
+    /// Apply the default colors set by the program (OSC 10/11/12)
+    pub fn set_color_overrides(&mut self, overrides: &ColorOverrides) {
+        if *overrides != self.color_overrides {
+            self.color_overrides = overrides.clone();
+            self.theme = self.base_theme.with_color_overrides(overrides);
+        }
+    }
+
+    /// Background color: set by the program, else the template's, else the
+    /// theme's
+    fn background(&self) -> Rgb {
+        match self.color_overrides.background {
+            Some(_) => self.theme.colors.background,
+            None => self
+                .background_override
+                .unwrap_or(self.theme.colors.background),
+        }
+    }
+
     /// Get the advance width for a character
     fn get_advance_for_glyph(font: &NSFont) -> f64 {
         // Use 'M' width as cell width for monospace
@@ -90,7 +200,10 @@ impl CGRenderer {
     }
 
     /// Render the terminal content
-    pub fn render(&self, terminal: &Terminal, bounds: NSRect) {
+    ///
+    /// Only the rows intersecting `dirty_rect` are drawn; AppKit clips the
+    /// rest and keeps what was drawn there before.
+    pub fn render(&self, terminal: &Terminal, bounds: NSRect, dirty_rect: NSRect) {
         let Some(_context) = NSGraphicsContext::currentContext() else {
             log::warn!("No graphics context");
             return;
@@ -100,113 +213,162 @@ impl CGRenderer {
         let cols = screen.width();
         let rows = screen.height();
 
-        // Draw background
-        self.draw_background(bounds);
+        // Rows touched by the dirty rect
+        let dirty_rows = {
+            let top = (dirty_rect.origin.y / self.cell_height).floor().max(0.0) as usize;
+            let bottom = ((dirty_rect.origin.y + dirty_rect.size.height) / self.cell_height)
+                .ceil()
+                .max(0.0) as usize;
+            top.min(rows)..bottom.min(rows)
+        };
+
+        // Draw background (the whole view on full redraws, so the area
+        // past the last row is covered too)
+        let background_rect = if dirty_rect.size.width >= bounds.size.width
+            && dirty_rect.size.height >= bounds.size.height
+        {
+            bounds
+        } else {
+            dirty_rect
+        };
+        self.draw_background(background_rect);
+
+        // The background image shows through the background color, under the text
+        if let Some(ref image) = self.background_image {
+            image.draw(bounds, background_rect);
+        }
+
+        // Tint the cursor line (drawn first so cell backgrounds and selection cover it)
+        if let Some(row) = self.cursor_line.highlighted_row(screen) {
+            let bg = self.background();
+            let tint = CursorLineHighlight::tint(bg, self.theme.colors.foreground);
+            let y = row as f64 * self.cell_height;
+            self.draw_cell_background_sized(0.0, y, cols as f64 * self.cell_width, &tint);
+        }
+
+        // Column rulers, under the text
+        let rulers = ruler_positions(&self.ruler_columns, screen);
+        if !rulers.is_empty() {
+            let color = ruler_color(self.background(), self.theme.colors.foreground);
+            for row in dirty_rows.clone() {
+                let y = row as f64 * self.cell_height;
+                for &col in &rulers {
+                    self.draw_cell_background_sized(col as f64 * self.cell_width, y, 1.0, &color);
+                }
+            }
+        }
+
+        // Draw the badge (iTerm2 SetBadgeFormat) behind the text, top-right
+        if let Some(badge) = self.show_badge.then(|| screen.badge()).flatten() {
+            self.draw_badge(&badge, cols);
+        }
+
+        // Draw images placed below the text (negative z-index)
+        self.render_images(screen, true);
+
+        // Columns truncated long lines are scrolled right by
+        let h_offset = screen.horizontal_offset();
 
         // Draw cells
-        for row in 0..rows {
+        for row in dirty_rows {
             // Get absolute line for scrollback access and selection checking
             let absolute_line = screen.visible_row_to_absolute_line(row);
 
+            // Display order of right-to-left text (None = as stored)
+            let bidi = screen.bidi_line(absolute_line);
+
+            // A truncated line that goes on past the right edge ends in "…"
+            let continues_right = screen.line_continues_right(absolute_line);
+
+            // Secrets are drawn as "•" (mask_secrets)
+            let secrets = screen.secret_columns(absolute_line);
+
             for col in 0..cols {
-                if let Some(cell) = screen.get_cell_with_scrollback(absolute_line, col) {
+                if let Some(cell) = screen.visible_cell(absolute_line, col) {
+                    let logical_col = col + h_offset;
+                    let ellipsis = continues_right && col + 1 == cols;
+
                     // Skip wide char spacers - background handled by the wide cell
-                    if cell.is_wide_spacer() {
+                    if cell.is_wide_spacer() && !ellipsis {
                         continue;
                     }
 
-                    let x = col as f64 * self.cell_width;
+                    let visual_col = bidi.as_ref().map_or(col, |b| b.visual_col(col));
+                    let x = visual_col as f64 * self.cell_width;
                     let y = row as f64 * self.cell_height;
 
                     // Check if cell is selected
-                    let is_selected = screen.is_selected(absolute_line, col);
+                    let is_selected = screen.is_selected(absolute_line, logical_col);
 
                     // XOR selection with INVERSE attribute to determine if colors should be inverted
                     let is_inverted = cell.attrs.contains(CellAttrs::INVERSE) != is_selected;
 
-                    // Determine actual foreground and background colors
-                    let (fg_color, bg_color) = if is_inverted {
-                        // Inverted: swap foreground and background
-                        let fg = if cell.bg.is_default() {
-                            self.theme.colors.background
-                        } else {
-                            self.color_to_rgb(&cell.bg)
-                        };
-                        let bg = if cell.fg.is_default() {
-                            self.theme.colors.foreground
-                        } else {
-                            self.color_to_rgb(&cell.fg)
-                        };
-                        (fg, bg)
-                    } else {
-                        (self.color_to_rgb(&cell.fg), self.color_to_rgb(&cell.bg))
-                    };
-
-                    // Use double width for wide characters
-                    let bg_width = if cell.is_wide() {
-                        self.cell_width * 2.0
+                    // Search matches get the theme's highlight, unless selected
+                    let search_bg = screen
+                        .search_highlight(absolute_line, logical_col)
+                        .filter(|_| !is_selected)
+                        .map(|highlight| self.theme.search_bg(highlight));
+
+                    // Concealed text (SGR 8) keeps only its background
+                    let c = if ellipsis {
+                        Some('…')
+                    } else if secrets.iter().any(|r| r.contains(&logical_col)) {
+                        (!screen.is_concealed(cell)).then_some('•')
                     } else {
-                        self.cell_width
+                        (!screen.is_concealed(cell)).then(|| {
+                            bidi.as_ref()
+                                .map_or(cell.c, |b| b.display_char(col, cell.c))
+                        })
                     };
+                    let drcs = screen.get_drcs_for_char(cell.c);
+                    self.draw_cell(cell, c, drcs, x, y, is_inverted, search_bg, is_selected);
+                }
+            }
+        }
 
-                    // Draw cell background if not default or if selected/inverted
-                    if !cell.bg.is_default() || is_inverted || is_selected {
-                        self.draw_cell_background_sized(x, y, bg_width, &bg_color);
-                    }
-
-                    // Draw character
-                    if cell.c != ' ' && cell.c != '\0' {
-                        // Check if this should be a DRCS glyph
-                        if let Some(glyph) = screen.get_drcs_for_char(cell.c) {
-                            self.draw_drcs_glyph(glyph, x, y, &fg_color);
-                        } else {
-                            self.draw_char_rgb(cell.c, x, y, &fg_color);
-                        }
-                    }
-
-                    // Draw underlines (regular underline attributes or hyperlinks)
-                    let has_hyperlink = cell.hyperlink.is_some();
-                    if cell.attrs.has_underline() || has_hyperlink {
-                        // Use hyperlink color (blue) for hyperlinks, otherwise use underline color or fg
-                        let underline_color = if has_hyperlink {
-                            Rgb {
-                                r: 100,
-                                g: 149,
-                                b: 237,
-                            } // Cornflower blue for hyperlinks
-                        } else if let Some(ref uc) = cell.underline_color {
-                            self.color_to_rgb(uc)
-                        } else {
-                            fg_color
-                        };
-
-                        self.draw_underline(x, y, &underline_color, &cell.attrs, has_hyperlink);
-                    }
+        // Draw images (Sixel, etc.) over the text
+        self.render_images(screen, false);
 
-                    // Draw strikethrough
-                    if cell.attrs.contains(CellAttrs::STRIKETHROUGH) {
-                        self.draw_strikethrough(x, y, &fg_color);
-                    }
+        // Mark bookmarked rows with a bar in the left gutter
+        let marker_width = (self.cell_width / 4.0).max(2.0);
+        for row in screen.visible_bookmark_rows() {
+            let y = row as f64 * self.cell_height;
+            self.draw_cell_background_sized(0.0, y, marker_width, &self.theme.ui.scrollbar_hover);
+        }
 
-                    // Draw overline
-                    if cell.attrs.contains(CellAttrs::OVERLINE) {
-                        self.draw_overline(x, y, &fg_color);
-                    }
-                }
+        // Draw the predicted local echo, or else the inline suggestion, as
+        // faint text at the cursor
+        if let Some(overlay) = screen
+            .predicted_input()
+            .or_else(|| screen.inline_suggestion())
+            .filter(|_| h_offset == 0)
+        {
+            let bg = self.background();
+            let rgb = self.theme.colors.foreground.faint(bg, self.faint_opacity);
+            let cursor = &screen.cursor;
+            let y = cursor.row as f64 * self.cell_height;
+            for (i, ch) in overlay
+                .chars()
+                .take(cols.saturating_sub(cursor.col))
+                .enumerate()
+            {
+                let x = (cursor.col + i) as f64 * self.cell_width;
+                self.draw_char_rgb(ch, x, y, &rgb);
             }
         }
 
-        // Draw images (Sixel, etc.)
-        self.render_images(screen);
-
         // Draw cursor (only when visible and not scrolled back)
         let cursor = &screen.cursor;
-        if screen.modes.show_cursor && screen.scroll_offset == 0 {
-            let cursor_x = cursor.col as f64 * self.cell_width;
+        if screen.modes.show_cursor && screen.scroll_offset == 0 && h_offset == 0 {
+            let cursor_col = screen.cursor_render_col();
+            let cursor_visual_col = screen
+                .bidi_line(screen.visible_row_to_absolute_line(cursor.row))
+                .map_or(cursor_col, |b| b.visual_col(cursor_col));
+            let cursor_x = cursor_visual_col as f64 * self.cell_width;
             let cursor_y = cursor.row as f64 * self.cell_height;
 
             // Check if cursor is on a wide character
-            let cursor_width = if let Some(cell) = screen.grid().get(cursor.row, cursor.col) {
+            let cursor_width = if let Some(cell) = screen.grid().get(cursor.row, cursor_col) {
                 if cell.is_wide() {
                     self.cell_width * 2.0
                 } else {
@@ -218,11 +380,107 @@ impl CGRenderer {
 
             self.draw_cursor(cursor_x, cursor_y, cursor_width);
         }
+
+        // Scroll position indicator, bottom-right, while scrolled back
+        if let Some(label) = self.scroll_position.label(screen) {
+            self.draw_scroll_position(&label, cols, rows);
+        }
+    }
+
+    /// Render a block of cells to a PNG, on the terminal background with a
+    /// one-cell margin
+    ///
+    /// `scale` is the display's backing scale factor, so the image is as
+    /// sharp as the text on screen.
+    pub fn render_region_png(
+        &self,
+        mtm: MainThreadMarker,
+        region: &CellRegion,
+        scale: f64,
+    ) -> Option<Vec<u8>> {
+        use objc2_app_kit::{NSBitmapImageFileType, NSBitmapImageRep};
+        use objc2_foundation::NSDictionary;
+
+        extern "C" {
+            fn CGContextTranslateCTM(context: *mut std::ffi::c_void, tx: f64, ty: f64);
+            fn CGContextScaleCTM(context: *mut std::ffi::c_void, sx: f64, sy: f64);
+        }
+
+        let margin_x = self.cell_width;
+        let margin_y = self.cell_height / 2.0;
+        let width = region.width() as f64 * self.cell_width + 2.0 * margin_x;
+        let height = region.height() as f64 * self.cell_height + 2.0 * margin_y;
+
+        unsafe {
+            let rep = NSBitmapImageRep::initWithBitmapDataPlanes_pixelsWide_pixelsHigh_bitsPerSample_samplesPerPixel_hasAlpha_isPlanar_colorSpaceName_bytesPerRow_bitsPerPixel(
+                mtm.alloc(),
+                std::ptr::null_mut(), // planes - will allocate
+                (width * scale).ceil() as isize,
+                (height * scale).ceil() as isize,
+                8, // bits per sample
+                4, // samples per pixel (RGBA)
+                true, // has alpha
+                false, // not planar
+                &NSString::from_str("NSDeviceRGBColorSpace"),
+                0, // bytes per row - computed
+                0, // bits per pixel - computed
+            )?;
+            // Draw in points; the rep maps them to its pixels
+            rep.setSize(NSSize::new(width, height));
+
+            // A flipped context, like the terminal view, so the drawing code
+            // works unchanged
+            let bitmap_context: Option<Retained<AnyObject>> =
+                msg_send![class!(NSGraphicsContext), graphicsContextWithBitmapImageRep: &*rep];
+            let port: *mut std::ffi::c_void = msg_send![&*bitmap_context?, graphicsPort];
+            let context: Option<Retained<AnyObject>> = msg_send![
+                class!(NSGraphicsContext),
+                graphicsContextWithGraphicsPort: port,
+                flipped: true
+            ];
+            let context = context?;
+
+            let _: () = msg_send![class!(NSGraphicsContext), saveGraphicsState];
+            let _: () = msg_send![class!(NSGraphicsContext), setCurrentContext: &*context];
+            CGContextTranslateCTM(port, 0.0, height);
+            CGContextScaleCTM(port, 1.0, -1.0);
+
+            self.draw_background(NSRect::new(
+                NSPoint::new(0.0, 0.0),
+                NSSize::new(width, height),
+            ));
+            for (row_idx, row) in region.rows.iter().enumerate() {
+                let y = margin_y + row_idx as f64 * self.cell_height;
+                for (col_idx, cell) in row.iter().enumerate() {
+                    if cell.is_wide_spacer() {
+                        continue;
+                    }
+                    let x = margin_x + col_idx as f64 * self.cell_width;
+                    let inverted = cell.attrs.contains(CellAttrs::INVERSE);
+                    self.draw_cell(cell, Some(cell.c), None, x, y, inverted, None, false);
+                }
+            }
+
+            let _: () = msg_send![&*context, flushGraphics];
+            let _: () = msg_send![class!(NSGraphicsContext), restoreGraphicsState];
+
+            let empty_dict: Retained<NSDictionary<NSString, AnyObject>> = NSDictionary::new();
+            let png =
+                rep.representationUsingType_properties(NSBitmapImageFileType::PNG, &empty_dict)?;
+            Some(png.to_vec())
+        }
     }
 
     /// Render terminal images (Sixel graphics, etc.)
-    fn render_images(&self, screen: &cterm_core::Screen) {
+    ///
+    /// Draws either the images below the text (negative z-index) or those
+    /// above it, in stacking order. Images partly scrolled out of view are
+    /// drawn at their real position and clipped by the view.
+    fn render_images(&self, screen: &cterm_core::Screen, below_text: bool) {
         for image in screen.visible_images() {
+            if (image.placement.z_index < 0) != below_text {
+                continue;
+            }
             if let Some(visible_row) = screen.image_visible_row(image) {
                 let x = image.col as f64 * self.cell_width;
                 let y = visible_row as f64 * self.cell_height;
@@ -375,10 +633,7 @@ impl CGRenderer {
 
     fn draw_background(&self, bounds: NSRect) {
         // Use background override if set, otherwise use theme background
-        let bg = self
-            .background_override
-            .as_ref()
-            .unwrap_or(&self.theme.colors.background);
+        let bg = &self.background();
         unsafe {
             let color = Self::ns_color(bg.r, bg.g, bg.b);
             let _: () = msg_send![&*color, setFill];
@@ -404,13 +659,138 @@ impl CGRenderer {
         }
     }
 
+    /// Draw one cell's background, character and lines with its top-left at
+    /// (x, y)
+    ///
+    /// `c` is the character to show (None hides it and its lines, for
+    /// concealed text) and `drcs` a soft-font glyph to draw instead. When `c`
+    /// is the cell's own character, the rest of its grapheme cluster is drawn
+    /// with it.
+    /// `inverted` swaps the cell's colors (SGR 7 or selection), `highlight`
+    /// replaces its background (search matches) and `fill` draws the
+    /// background even when it's the default (selection).
+    #[allow(clippy::too_many_arguments)]
+    fn draw_cell(
+        &self,
+        cell: &Cell,
+        c: Option<char>,
+        drcs: Option<&DrcsGlyph>,
+        x: f64,
+        y: f64,
+        inverted: bool,
+        highlight: Option<Rgb>,
+        fill: bool,
+    ) {
+        // Determine actual foreground and background colors
+        let (fg_color, bg_color) = if inverted {
+            // Inverted: swap foreground and background
+            let fg = if cell.bg.is_default() {
+                self.theme.colors.background
+            } else {
+                self.color_to_rgb(&cell.bg)
+            };
+            let bg = if cell.fg.is_default() {
+                self.theme.colors.foreground
+            } else {
+                self.color_to_rgb(&cell.fg)
+            };
+            (fg, bg)
+        } else {
+            (self.color_to_rgb(&cell.fg), self.color_to_rgb(&cell.bg))
+        };
+
+        // Fade faint text toward the background it's drawn over
+        let fg_color = if cell.attrs.contains(CellAttrs::DIM) {
+            let text_bg = if !inverted && cell.bg.is_default() {
+                self.theme.colors.background
+            } else {
+                bg_color
+            };
+            fg_color.faint(text_bg, self.faint_opacity)
+        } else {
+            fg_color
+        };
+
+        // Use double width for wide characters
+        let bg_width = if cell.is_wide() {
+            self.cell_width * 2.0
+        } else {
+            self.cell_width
+        };
+
+        // Draw cell background if not default or if selected/inverted/matched
+        if let Some(highlight) = highlight {
+            self.draw_cell_background_sized(x, y, bg_width, &highlight);
+        } else if !cell.bg.is_default() || inverted || fill {
+            self.draw_cell_background_sized(x, y, bg_width, &bg_color);
+        }
+
+        let Some(c) = c else {
+            return;
+        };
+
+        // Draw character
+        if c != ' ' && c != '\0' {
+            // Check if this should be a DRCS glyph
+            if let Some(glyph) = drcs {
+                self.draw_drcs_glyph(glyph, x, y, &fg_color);
+            } else if cell.is_wide() || cell.grapheme.is_some() {
+                let text = if c == cell.c {
+                    cell.grapheme_text()
+                } else {
+                    c.to_string()
+                };
+                let width = cell.is_wide().then_some(bg_width);
+                self.draw_text_rgb(&text, x, y, &fg_color, width);
+            } else {
+                self.draw_char_rgb(c, x, y, &fg_color);
+            }
+        }
+
+        // Draw underlines (regular underline attributes or hyperlinks)
+        let has_hyperlink = cell.hyperlink.is_some();
+        if cell.attrs.has_underline() || has_hyperlink {
+            // Use hyperlink color (blue) for hyperlinks, otherwise use underline color or fg
+            let underline_color = if has_hyperlink {
+                Rgb {
+                    r: 100,
+                    g: 149,
+                    b: 237,
+                } // Cornflower blue for hyperlinks
+            } else if let Some(ref uc) = cell.underline_color {
+                self.color_to_rgb(uc)
+            } else {
+                fg_color
+            };
+
+            self.draw_underline(x, y, &underline_color, &cell.attrs, has_hyperlink);
+        }
+
+        // Draw strikethrough
+        if cell.attrs.contains(CellAttrs::STRIKETHROUGH) {
+            self.draw_strikethrough(x, y, &fg_color);
+        }
+
+        // Draw overline
+        if cell.attrs.contains(CellAttrs::OVERLINE) {
+            self.draw_overline(x, y, &fg_color);
+        }
+    }
+
     fn draw_char(&self, ch: char, x: f64, y: f64, color: &Color) {
         let rgb = self.color_to_rgb(color);
         self.draw_char_rgb(ch, x, y, &rgb);
     }
 
     fn draw_char_rgb(&self, ch: char, x: f64, y: f64, rgb: &Rgb) {
-        let text = NSString::from_str(&ch.to_string());
+        self.draw_text_rgb(&ch.to_string(), x, y, rgb, None);
+    }
+
+    /// Draw `text` with its top-left at (x, y), or centered in and clipped to
+    /// `width` if given (wide cells, whose emoji glyphs may not be exactly
+    /// two columns wide)
+    fn draw_text_rgb(&self, text: &str, x: f64, y: f64, rgb: &Rgb, width: Option<f64>) {
+        let text = NSString::from_str(text);
 
         unsafe {
             let ns_color = Self::ns_color(rgb.r, rgb.g, rgb.b);
@@ -433,6 +813,86 @@ impl CGRenderer {
             ];
 
             // In a flipped view, drawAtPoint places text with point as top-left of the text
+            let Some(width) = width else {
+                let point = NSPoint::new(x, y);
+                let _: () = msg_send![&*text, drawAtPoint: point, withAttributes: &*dict];
+                return;
+            };
+            let size: NSSize = msg_send![&*text, sizeWithAttributes: &*dict];
+            let clip = NSRect::new(NSPoint::new(x, y), NSSize::new(width, self.cell_height));
+            let _: () = msg_send![class!(NSGraphicsContext), saveGraphicsState];
+            let _: () = msg_send![class!(NSBezierPath), clipRect: clip];
+            let point = NSPoint::new(x + (width - size.width) / 2.0, y);
+            let _: () = msg_send![&*text, drawAtPoint: point, withAttributes: &*dict];
+            let _: () = msg_send![class!(NSGraphicsContext), restoreGraphicsState];
+        }
+    }
+
+    /// Draw the badge text in the top-right corner
+    fn draw_badge(&self, badge: &str, cols: usize) {
+        let bg = self.background();
+        let rgb = badge_color(bg, self.theme.colors.foreground);
+        let text = NSString::from_str(badge);
+        let font = NSFont::boldSystemFontOfSize(self.font.pointSize() * 2.0);
+
+        unsafe {
+            let ns_color = Self::ns_color(rgb.r, rgb.g, rgb.b);
+
+            let font_key = NSString::from_str("NSFont");
+            let color_key = NSString::from_str("NSColor");
+
+            let keys: [&AnyObject; 2] = [
+                std::mem::transmute::<&NSString, &AnyObject>(&font_key),
+                std::mem::transmute::<&NSString, &AnyObject>(&color_key),
+            ];
+            let values: [&AnyObject; 2] = [&*font, &*ns_color];
+
+            let dict: Retained<AnyObject> = msg_send![
+                class!(NSDictionary),
+                dictionaryWithObjects: values.as_ptr(),
+                forKeys: keys.as_ptr(),
+                count: 2usize
+            ];
+
+            let size: NSSize = msg_send![&*text, sizeWithAttributes: &*dict];
+            let x = (cols as f64 * self.cell_width - size.width - self.cell_width).max(0.0);
+            let point = NSPoint::new(x, self.cell_height * 0.5);
+            let _: () = msg_send![&*text, drawAtPoint: point, withAttributes: &*dict];
+        }
+    }
+
+    /// Draw the scroll position label in a box in the bottom-right corner
+    fn draw_scroll_position(&self, label: &str, cols: usize, rows: usize) {
+        let bg = self.background();
+        let box_rgb = badge_color(bg, self.theme.colors.foreground);
+        let text_rgb = self.theme.colors.foreground;
+        let text = NSString::from_str(label);
+
+        unsafe {
+            let ns_color = Self::ns_color(text_rgb.r, text_rgb.g, text_rgb.b);
+
+            let font_key = NSString::from_str("NSFont");
+            let color_key = NSString::from_str("NSColor");
+
+            let keys: [&AnyObject; 2] = [
+                std::mem::transmute::<&NSString, &AnyObject>(&font_key),
+                std::mem::transmute::<&NSString, &AnyObject>(&color_key),
+            ];
+            let values: [&AnyObject; 2] = [&*self.font, &*ns_color];
+
+            let dict: Retained<AnyObject> = msg_send![
+                class!(NSDictionary),
+                dictionaryWithObjects: values.as_ptr(),
+                forKeys: keys.as_ptr(),
+                count: 2usize
+            ];
+
+            let size: NSSize = msg_send![&*text, sizeWithAttributes: &*dict];
+            let pad = self.cell_width * 0.5;
+            let x = (cols as f64 * self.cell_width - size.width - pad * 3.0).max(0.0);
+            let y = (rows as f64 * self.cell_height - self.cell_height - pad * 2.0).max(0.0);
+            self.draw_cell_background_sized(x - pad, y, size.width + pad * 2.0, &box_rgb);
+
             let point = NSPoint::new(x, y);
             let _: () = msg_send![&*text, drawAtPoint: point, withAttributes: &*dict];
         }
@@ -626,7 +1086,54 @@ impl CGRenderer {
 
     /// Update theme colors
     pub fn set_theme(&mut self, theme: &Theme) {
-        self.theme = theme.clone();
+        self.base_theme = theme.clone();
+        self.theme = theme.with_color_overrides(&self.color_overrides);
+        if let Some(minimap) = &mut self.minimap {
+            minimap.invalidate();
+        }
+    }
+
+    /// Render the scrollback minimap along the right edge of `bounds`
+    ///
+    /// The whole strip is drawn; the view invalidates it with every change.
+    pub fn render_minimap(&mut self, terminal: &Terminal, bounds: NSRect) {
+        let Some(mut minimap) = self.minimap.take() else {
+            return;
+        };
+        let screen = terminal.screen();
+        minimap.update(screen, &self.theme.colors);
+
+        let left = bounds.size.width - MINIMAP_WIDTH;
+        let height = bounds.size.height;
+        let fill = |x: f64, y: f64, width: f64, height: f64, rgb: &Rgb, alpha: f64| unsafe {
+            let rect = NSRect::new(NSPoint::new(x, y), NSSize::new(width, height));
+            let color = Self::ns_color_alpha(rgb.r, rgb.g, rgb.b, alpha);
+            let _: () = msg_send![&*color, setFill];
+            let _: () = msg_send![class!(NSBezierPath), fillRect: rect];
+        };
+
+        let strip = self.theme.ui.scrollbar;
+        fill(left, 0.0, MINIMAP_WIDTH, height, &self.background(), 1.0);
+        fill(left, 0.0, MINIMAP_WIDTH, height, &strip, 0.25);
+
+        let inset = 2.0;
+        for bar in minimap.bars(height) {
+            fill(
+                left + inset,
+                bar.y,
+                (MINIMAP_WIDTH - 2.0 * inset) * bar.width,
+                bar.height,
+                &bar.color,
+                0.8,
+            );
+        }
+
+        // Outline the part of the scrollback that's on screen
+        let (top, view_height) = minimap_viewport(screen, height);
+        let hover = self.theme.ui.scrollbar_hover;
+        fill(left, top, MINIMAP_WIDTH, view_height.max(2.0), &hover, 0.35);
+
+        self.minimap = Some(minimap);
     }
 
     /// Render IME marked text (composition text) at cursor position
@@ -692,3 +1199,113 @@ impl CGRenderer {
         }
     }
 }
+
+/// The configured background image (`appearance.background_image`)
+struct BackgroundImage {
+    image: Retained<NSImage>,
+    mode: BackgroundImageMode,
+    opacity: f64,
+    /// The image laid out at the last drawn size, reused until the size
+    /// changes so redraws don't scale it again
+    scaled: RefCell<Option<(NSSize, Retained<NSImage>)>>,
+}
+
+impl BackgroundImage {
+    /// Load the configured background image, if any
+    ///
+    /// Each file is loaded once and shared by all terminals; a file that
+    /// can't be read is logged and left out.
+    fn from_config(appearance: &AppearanceConfig) -> Option<Self> {
+        let path = appearance.background_image.as_ref()?;
+        let image = BACKGROUND_IMAGES.with(|images| {
+            images
+                .borrow_mut()
+                .entry(path.clone())
+                .or_insert_with(|| {
+                    let file = NSString::from_str(&path.to_string_lossy());
+                    let image = NSImage::initWithContentsOfFile(NSImage::alloc(), &file);
+                    if image.is_none() {
+                        log::warn!("Failed to load background image {}", path.display());
+                    }
+                    image
+                })
+                .clone()
+        })?;
+        Some(Self {
+            image,
+            mode: appearance.background_image_mode,
+            opacity: appearance.background_image_opacity.clamp(0.0, 1.0),
+            scaled: RefCell::new(None),
+        })
+    }
+
+    /// Draw the part of the image inside `rect` of a view sized `bounds`
+    fn draw(&self, bounds: NSRect, rect: NSRect) {
+        let mut scaled = self.scaled.borrow_mut();
+        if scaled.as_ref().is_none_or(|(size, _)| *size != bounds.size) {
+            *scaled = Some((bounds.size, self.layout(bounds.size)));
+        }
+        let Some((_, ref image)) = *scaled else {
+            return;
+        };
+
+        // Image coordinates start at the bottom left, the view's at the top left
+        let from = NSRect::new(
+            NSPoint::new(
+                rect.origin.x,
+                bounds.size.height - rect.origin.y - rect.size.height,
+            ),
+            rect.size,
+        );
+        unsafe {
+            image.drawInRect_fromRect_operation_fraction_respectFlipped_hints(
+                rect,
+                from,
+                NSCompositingOperation::SourceOver,
+                self.opacity,
+                true,
+                None,
+            );
+        }
+    }
+
+    /// Draw the image laid out for an area of size `area`
+    fn layout(&self, area: NSSize) -> Retained<NSImage> {
+        let scaled = NSImage::initWithSize(NSImage::alloc(), area);
+        let size = self.image.size();
+        let (x, y, w, h) = self
+            .mode
+            .layout((size.width, size.height), (area.width, area.height));
+        if w <= 0.0 || h <= 0.0 {
+            return scaled;
+        }
+
+        let origins = if self.mode == BackgroundImageMode::Tile {
+            let cols = (area.width / w).ceil() as usize;
+            let rows = (area.height / h).ceil() as usize;
+            (0..rows)
+                .flat_map(|row| (0..cols).map(move |col| (col as f64 * w, row as f64 * h)))
+                .collect()
+        } else {
+            vec![(x, y)]
+        };
+
+        scaled.lockFocusFlipped(true);
+        for (x, y) in origins {
+            let rect = NSRect::new(NSPoint::new(x, y), NSSize::new(w, h));
+            unsafe {
+                self.image
+                    .drawInRect_fromRect_operation_fraction_respectFlipped_hints(
+                        rect,
+                        NSRect::ZERO,
+                        NSCompositingOperation::SourceOver,
+                        1.0,
+                        true,
+                        None,
+                    );
+            }
+        }
+        scaled.unlockFocus();
+        scaled
+    }
+}
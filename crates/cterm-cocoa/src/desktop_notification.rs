@@ -0,0 +1,74 @@
+//! Desktop notifications and event sounds
+//!
+//! Delivers notifications through `NSUserNotificationCenter`, which works
+//! without asking the user for permission first. It is unavailable when
+//! cterm doesn't run from an app bundle, in which case the notification is
+//! only logged. Sounds are played with `NSSound`.
+
+use std::cell::RefCell;
+
+use objc2::rc::Retained;
+use objc2::runtime::AnyObject;
+use objc2::{class, msg_send};
+use objc2_foundation::NSString;
+
+use cterm_app::notify::{Notification, Sound};
+
+thread_local! {
+    /// The sound playing, kept alive until it is replaced
+    static PLAYING_SOUND: RefCell<Option<Retained<AnyObject>>> = const { RefCell::new(None) };
+}
+
+/// Show a desktop notification
+pub fn show(notification: &Notification) {
+    unsafe {
+        let center: Option<Retained<AnyObject>> = msg_send![
+            class!(NSUserNotificationCenter),
+            defaultUserNotificationCenter
+        ];
+        let Some(center) = center else {
+            log::info!(
+                "Notifications unavailable outside an app bundle: {}",
+                notification.body
+            );
+            return;
+        };
+
+        let user_notification: Retained<AnyObject> = msg_send![class!(NSUserNotification), new];
+        let title = NSString::from_str(&notification.title);
+        let body = NSString::from_str(&notification.body);
+        let _: () = msg_send![&user_notification, setTitle: &*title];
+        let _: () = msg_send![&user_notification, setInformativeText: &*body];
+        let _: () = msg_send![&center, deliverNotification: &*user_notification];
+    }
+}
+
+/// Play an event's sound (main thread only), beeping if it can't be found
+pub fn play_sound(sound: &Sound) {
+    unsafe {
+        let ns_sound: Option<Retained<AnyObject>> = match sound {
+            Sound::Beep => None,
+            Sound::Named(name) => {
+                let name = NSString::from_str(name);
+                let ptr: *mut AnyObject = msg_send![class!(NSSound), soundNamed: &*name];
+                Retained::retain(ptr)
+            }
+            Sound::File(path) => {
+                let path = NSString::from_str(&path.to_string_lossy());
+                let ptr: *mut AnyObject = msg_send![class!(NSSound), alloc];
+                let ptr: *mut AnyObject =
+                    msg_send![ptr, initWithContentsOfFile: &*path, byReference: true];
+                Retained::from_raw(ptr)
+            }
+        };
+        let Some(ns_sound) = ns_sound else {
+            if *sound != Sound::Beep {
+                log::warn!("Sound {:?} not found, beeping instead", sound);
+            }
+            objc2_app_kit::NSBeep();
+            return;
+        };
+        let _: bool = msg_send![&ns_sound, play];
+        PLAYING_SOUND.with(|playing| *playing.borrow_mut() = Some(ns_sound));
+    }
+}
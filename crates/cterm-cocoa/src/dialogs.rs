@@ -3,10 +3,10 @@
 //! Native macOS dialogs using NSAlert and other AppKit dialogs.
 
 use objc2_app_kit::{
-    NSAlert, NSAlertFirstButtonReturn, NSAlertStyle, NSModalResponseOK, NSSavePanel, NSTextField,
-    NSWindow,
+    NSAlert, NSAlertFirstButtonReturn, NSAlertStyle, NSButton, NSModalResponseOK, NSOpenPanel,
+    NSPopUpButton, NSSavePanel, NSTextField, NSView, NSWindow,
 };
-use objc2_foundation::{MainThreadMarker, NSPoint, NSSize, NSString, NSURL};
+use objc2_foundation::{MainThreadMarker, NSPoint, NSRect, NSSize, NSString, NSURL};
 use std::path::PathBuf;
 
 /// Show an error dialog
@@ -26,6 +26,16 @@ pub fn show_error(mtm: MainThreadMarker, parent: Option<&NSWindow>, title: &str,
     }
 }
 
+/// Show an informational dialog
+pub fn show_info(mtm: MainThreadMarker, title: &str, message: &str) {
+    let alert = NSAlert::new(mtm);
+    alert.setAlertStyle(NSAlertStyle::Informational);
+    alert.setMessageText(&NSString::from_str(title));
+    alert.setInformativeText(&NSString::from_str(message));
+    alert.addButtonWithTitle(&NSString::from_str("OK"));
+    alert.runModal();
+}
+
 /// Show a confirmation dialog
 /// Returns true if user clicked OK/Yes
 pub fn show_confirm(
@@ -87,6 +97,123 @@ pub fn show_input(
     }
 }
 
+/// Show a dialog to pick one of `items`
+/// Returns the index of the chosen item, or None if cancelled
+pub fn show_choice(
+    mtm: MainThreadMarker,
+    _parent: Option<&NSWindow>,
+    title: &str,
+    message: &str,
+    items: &[String],
+) -> Option<usize> {
+    let alert = NSAlert::new(mtm);
+    alert.setAlertStyle(NSAlertStyle::Informational);
+    alert.setMessageText(&NSString::from_str(title));
+    alert.setInformativeText(&NSString::from_str(message));
+    alert.addButtonWithTitle(&NSString::from_str("OK"));
+    alert.addButtonWithTitle(&NSString::from_str("Cancel"));
+
+    let popup = unsafe {
+        let popup = NSPopUpButton::new(mtm);
+        // Number the items, as the popup merges items with the same title
+        for (i, item) in items.iter().enumerate() {
+            popup.addItemWithTitle(&NSString::from_str(&format!("{}. {}", i + 1, item)));
+        }
+        popup.setFrameSize(NSSize::new(400.0, 26.0));
+        popup
+    };
+    alert.setAccessoryView(Some(&popup));
+
+    let response = alert.runModal();
+
+    // First button (OK) returns NSAlertFirstButtonReturn
+    if response == NSAlertFirstButtonReturn {
+        usize::try_from(popup.indexOfSelectedItem()).ok()
+    } else {
+        None
+    }
+}
+
+/// Query and options entered in the find dialog
+#[derive(Debug, Clone, Default)]
+pub struct FindOptions {
+    pub text: String,
+    pub case_sensitive: bool,
+    pub regex: bool,
+    /// Skip the visible screen, so a repainting full-screen app doesn't move
+    /// the matches
+    pub scrollback_only: bool,
+}
+
+/// Show the find dialog, filled in with `previous`
+/// Returns the query, or None if cancelled
+pub fn show_find(mtm: MainThreadMarker, previous: &FindOptions) -> Option<FindOptions> {
+    let alert = NSAlert::new(mtm);
+    alert.setAlertStyle(NSAlertStyle::Informational);
+    alert.setMessageText(&NSString::from_str("Find in Terminal"));
+    alert.setInformativeText(&NSString::from_str(
+        "Searching again for the same text moves to the next match.",
+    ));
+    alert.addButtonWithTitle(&NSString::from_str("Find Next"));
+    alert.addButtonWithTitle(&NSString::from_str("Cancel"));
+
+    let frame = NSRect::new(NSPoint::ZERO, NSSize::new(300.0, 96.0));
+    let accessory = NSView::initWithFrame(mtm.alloc(), frame);
+
+    let text_field = unsafe {
+        let field = NSTextField::new(mtm);
+        field.setStringValue(&NSString::from_str(&previous.text));
+        field.setFrame(NSRect::new(
+            NSPoint::new(0.0, 72.0),
+            NSSize::new(300.0, 24.0),
+        ));
+        field
+    };
+    accessory.addSubview(&text_field);
+
+    let checkbox = |title: &str, checked: bool, y: f64| {
+        let checkbox = unsafe {
+            let btn = NSButton::checkboxWithTitle_target_action(
+                &NSString::from_str(title),
+                None,
+                None,
+                mtm,
+            );
+            btn.setState(if checked { 1 } else { 0 });
+            btn.setFrame(NSRect::new(NSPoint::new(0.0, y), NSSize::new(300.0, 20.0)));
+            btn
+        };
+        accessory.addSubview(&checkbox);
+        checkbox
+    };
+    let case_check = checkbox("Case sensitive", previous.case_sensitive, 46.0);
+    let regex_check = checkbox("Regular expression", previous.regex, 24.0);
+    let scrollback_check = checkbox("Scrollback only", previous.scrollback_only, 2.0);
+
+    alert.setAccessoryView(Some(&accessory));
+
+    // Make text field first responder
+    let window = unsafe { alert.window() };
+    window.makeFirstResponder(Some(&text_field));
+
+    let response = alert.runModal();
+
+    // First button (Find Next) returns NSAlertFirstButtonReturn
+    if response != NSAlertFirstButtonReturn {
+        return None;
+    }
+    let text = text_field.stringValue().to_string();
+    if text.is_empty() {
+        return None;
+    }
+    Some(FindOptions {
+        text,
+        case_sensitive: case_check.state() == 1,
+        regex: regex_check.state() == 1,
+        scrollback_only: scrollback_check.state() == 1,
+    })
+}
+
 /// Show about dialog
 pub fn show_about(mtm: MainThreadMarker) {
     let alert = NSAlert::new(mtm);
@@ -144,6 +271,41 @@ pub fn show_crash_recovery(
     response == NSAlertFirstButtonReturn
 }
 
+/// Show the crash report written by the watchdog after a crash
+///
+/// Offers to open the folder containing the report or copy its path.
+#[cfg(unix)]
+pub fn show_crash_report(mtm: MainThreadMarker, report_path: &std::path::Path) {
+    use objc2_app_kit::{NSAlertSecondButtonReturn, NSWorkspace};
+
+    let alert = NSAlert::new(mtm);
+    alert.setAlertStyle(NSAlertStyle::Warning);
+    alert.setMessageText(&NSString::from_str("cterm crashed"));
+
+    let info = format!(
+        "A crash report was saved to:\n\n{}\n\n\
+        It contains the version, platform, open tabs and recent log lines. \
+        Please review it before attaching it to a bug report.",
+        report_path.display()
+    );
+    alert.setInformativeText(&NSString::from_str(&info));
+
+    alert.addButtonWithTitle(&NSString::from_str("Open Folder"));
+    alert.addButtonWithTitle(&NSString::from_str("Copy Path"));
+    alert.addButtonWithTitle(&NSString::from_str("Close"));
+
+    let response = alert.runModal();
+
+    if response == NSAlertFirstButtonReturn {
+        if let Some(dir) = report_path.parent().and_then(|d| d.to_str()) {
+            let url = NSURL::fileURLWithPath(&NSString::from_str(dir));
+            NSWorkspace::sharedWorkspace().openURL(&url);
+        }
+    } else if response == NSAlertSecondButtonReturn {
+        crate::clipboard::set_text(&report_path.to_string_lossy());
+    }
+}
+
 /// Show a save panel for saving a file
 ///
 /// Returns the selected path, or None if cancelled.
@@ -183,6 +345,24 @@ pub fn show_save_panel(
     }
 }
 
+/// Show an open panel for choosing a single file
+///
+/// Returns the selected path, or None if cancelled.
+pub fn show_open_panel(mtm: MainThreadMarker) -> Option<PathBuf> {
+    let panel = NSOpenPanel::openPanel(mtm);
+    panel.setCanChooseFiles(true);
+    panel.setCanChooseDirectories(false);
+    panel.setAllowsMultipleSelection(false);
+
+    if panel.runModal() == NSModalResponseOK {
+        panel
+            .URL()
+            .and_then(|url| url.path().map(|path| PathBuf::from(path.to_string())))
+    } else {
+        None
+    }
+}
+
 /// Result of the file drop dialog
 pub enum FileDropChoice {
     PastePath,
@@ -0,0 +1,81 @@
+//! Filter Scrollback results window
+//!
+//! Read-only window listing the scrollback lines that matched a filter.
+
+use objc2::rc::Retained;
+use objc2::{msg_send, MainThreadOnly};
+use objc2_app_kit::{NSScrollView, NSTextView, NSWindow, NSWindowStyleMask};
+use objc2_foundation::{MainThreadMarker, NSPoint, NSRect, NSSize, NSString};
+
+/// Show the lines that matched `pattern` in a read-only window
+pub fn show_filter_results(
+    mtm: MainThreadMarker,
+    pattern: &str,
+    lines: &[String],
+) -> Retained<NSWindow> {
+    let frame = NSRect::new(NSPoint::ZERO, NSSize::new(800.0, 500.0));
+    let style_mask = NSWindowStyleMask::Titled
+        | NSWindowStyleMask::Closable
+        | NSWindowStyleMask::Resizable
+        | NSWindowStyleMask::Miniaturizable;
+
+    let window: Retained<NSWindow> = unsafe {
+        msg_send![
+            NSWindow::alloc(mtm),
+            initWithContentRect: frame,
+            styleMask: style_mask,
+            backing: 2u64, // NSBackingStoreBuffered
+            defer: false
+        ]
+    };
+    let title = match lines.len() {
+        1 => format!("Filter: {} (1 line)", pattern),
+        n => format!("Filter: {} ({} lines)", pattern, n),
+    };
+    window.setTitle(&NSString::from_str(&title));
+    // Prevent double-free when window closes
+    unsafe { window.setReleasedWhenClosed(false) };
+
+    let scroll_view = unsafe {
+        let scroll = NSScrollView::initWithFrame(NSScrollView::alloc(mtm), frame);
+        scroll.setHasVerticalScroller(true);
+        scroll.setHasHorizontalScroller(true);
+        scroll
+    };
+
+    let text_view = unsafe {
+        let text = NSTextView::initWithFrame(NSTextView::alloc(mtm), frame);
+        text.setEditable(false);
+        text.setSelectable(true);
+
+        let font: *mut objc2::runtime::AnyObject = msg_send![
+            objc2::class!(NSFont),
+            monospacedSystemFontOfSize: 12.0f64,
+            weight: 0.0f64  // Regular weight
+        ];
+        let _: () = msg_send![&*text, setFont: font];
+
+        // Long lines scroll horizontally instead of wrapping
+        let _: () = msg_send![&*text, setHorizontallyResizable: true];
+        let _: () = msg_send![&*text, setMaxSize: NSSize::new(f64::MAX, f64::MAX)];
+        let container: *mut objc2::runtime::AnyObject = msg_send![&*text, textContainer];
+        if !container.is_null() {
+            let _: () = msg_send![container, setWidthTracksTextView: false];
+            let _: () = msg_send![container, setContainerSize: NSSize::new(1000000.0, 1000000.0)];
+        }
+        text
+    };
+
+    let text = if lines.is_empty() {
+        "No matching lines".to_string()
+    } else {
+        lines.join("\n")
+    };
+    text_view.setString(&NSString::from_str(&text));
+
+    scroll_view.setDocumentView(Some(&text_view));
+    window.setContentView(Some(&scroll_view));
+    window.center();
+    window.makeKeyAndOrderFront(None);
+    window
+}
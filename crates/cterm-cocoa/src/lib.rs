@@ -26,8 +26,10 @@
 pub mod app;
 pub mod cg_renderer;
 pub mod clipboard;
+pub mod desktop_notification;
 pub mod dialogs;
 pub mod file_transfer;
+pub mod filter_viewer;
 pub mod log_capture;
 pub mod log_viewer;
 pub mod menu;
@@ -13,6 +13,7 @@ use objc2_foundation::{MainThreadMarker, NSString};
 // Thread-local storage for the debug menu item (must be accessed on main thread)
 thread_local! {
     static DEBUG_MENU_ITEM: RefCell<Option<Retained<NSMenuItem>>> = const { RefCell::new(None) };
+    static UNKNOWN_SEQUENCES_ITEM: RefCell<Option<Retained<NSMenuItem>>> = const { RefCell::new(None) };
 }
 
 /// Store the debug menu item for later show/hide
@@ -22,6 +23,14 @@ fn set_debug_menu_item(item: Retained<NSMenuItem>) {
     });
 }
 
+/// Title of the debug menu item showing the unknown escape sequence count
+fn unknown_sequences_title() -> String {
+    format!(
+        "Unknown Sequences: {}",
+        cterm_core::parser::unknown_sequence_count()
+    )
+}
+
 /// Show or hide the debug menu
 pub fn set_debug_menu_visible(visible: bool) {
     DEBUG_MENU_ITEM.with(|cell| {
@@ -29,6 +38,15 @@ pub fn set_debug_menu_visible(visible: bool) {
             item.setHidden(!visible);
         }
     });
+
+    // Refresh the counter each time the menu is revealed
+    if visible {
+        UNKNOWN_SEQUENCES_ITEM.with(|cell| {
+            if let Some(ref item) = *cell.borrow() {
+                item.setTitle(&NSString::from_str(&unknown_sequences_title()));
+            }
+        });
+    }
 }
 
 /// Check if the debug menu is currently visible
@@ -96,6 +114,27 @@ fn create_app_menu(mtm: MainThreadMarker) -> Retained<NSMenuItem> {
         NSEventModifierFlags::Command,
     ));
 
+    menu.addItem(&create_menu_item(
+        mtm,
+        "Import Theme...",
+        Some(sel!(importTheme:)),
+        "",
+    ));
+
+    menu.addItem(&create_menu_item(
+        mtm,
+        "Import Keybindings...",
+        Some(sel!(importKeybindings:)),
+        "",
+    ));
+
+    menu.addItem(&create_menu_item(
+        mtm,
+        "Export Keybindings...",
+        Some(sel!(exportKeybindings:)),
+        "",
+    ));
+
     menu.addItem(&NSMenuItem::separatorItem(mtm));
 
     // Services submenu (standard macOS)
@@ -160,6 +199,15 @@ fn create_file_menu(mtm: MainThreadMarker) -> Retained<NSMenuItem> {
         NSEventModifierFlags::Command,
     ));
 
+    // New Incognito Tab (no scrollback, never saved)
+    menu.addItem(&create_menu_item_with_key(
+        mtm,
+        "New Incognito Tab",
+        Some(sel!(newIncognitoTab:)),
+        "p",
+        NSEventModifierFlags::Command.union(NSEventModifierFlags::Shift),
+    ));
+
     // New Window
     menu.addItem(&create_menu_item_with_key(
         mtm,
@@ -180,6 +228,13 @@ fn create_file_menu(mtm: MainThreadMarker) -> Retained<NSMenuItem> {
         NSEventModifierFlags::Command,
     ));
 
+    menu.addItem(&create_menu_item(
+        mtm,
+        "Open Workspace...",
+        Some(sel!(openWorkspace:)),
+        "",
+    ));
+
     menu.addItem(&NSMenuItem::separatorItem(mtm));
 
     // Tab Templates submenu
@@ -260,6 +315,15 @@ fn create_file_menu(mtm: MainThreadMarker) -> Retained<NSMenuItem> {
         NSEventModifierFlags::Command.union(NSEventModifierFlags::Shift),
     ));
 
+    // Reopen Closed Window
+    menu.addItem(&create_menu_item_with_key(
+        mtm,
+        "Reopen Closed Window",
+        Some(sel!(reopenClosedWindow:)),
+        "o",
+        NSEventModifierFlags::Command.union(NSEventModifierFlags::Shift),
+    ));
+
     let menu_item = NSMenuItem::new(mtm);
     menu_item.setSubmenu(Some(&menu));
     menu_item
@@ -313,6 +377,28 @@ fn create_edit_menu(mtm: MainThreadMarker) -> Retained<NSMenuItem> {
         NSEventModifierFlags::Command.union(NSEventModifierFlags::Shift),
     ));
 
+    menu.addItem(&create_menu_item(
+        mtm,
+        "Copy as Image",
+        Some(sel!(copyAsImage:)),
+        "",
+    ));
+
+    menu.addItem(&create_menu_item(
+        mtm,
+        "Save as Image...",
+        Some(sel!(saveAsImage:)),
+        "",
+    ));
+
+    menu.addItem(&create_menu_item_with_key(
+        mtm,
+        "Copy Last Command Output",
+        Some(sel!(copyLastCommandOutput:)),
+        "y",
+        NSEventModifierFlags::Command.union(NSEventModifierFlags::Shift),
+    ));
+
     menu.addItem(&create_menu_item_with_key(
         mtm,
         "Paste",
@@ -321,6 +407,14 @@ fn create_edit_menu(mtm: MainThreadMarker) -> Retained<NSMenuItem> {
         NSEventModifierFlags::Command,
     ));
 
+    menu.addItem(&create_menu_item_with_key(
+        mtm,
+        "Paste from History...",
+        Some(sel!(pasteFromHistory:)),
+        "h",
+        NSEventModifierFlags::Command.union(NSEventModifierFlags::Shift),
+    ));
+
     menu.addItem(&create_menu_item_with_key(
         mtm,
         "Select All",
@@ -340,6 +434,13 @@ fn create_edit_menu(mtm: MainThreadMarker) -> Retained<NSMenuItem> {
         NSEventModifierFlags::Command,
     ));
 
+    menu.addItem(&create_menu_item(
+        mtm,
+        "Filter Scrollback...",
+        Some(sel!(filterScrollback:)),
+        "",
+    ));
+
     let menu_item = NSMenuItem::new(mtm);
     menu_item.setSubmenu(Some(&menu));
     menu_item
@@ -376,6 +477,25 @@ fn create_view_menu(mtm: MainThreadMarker) -> Retained<NSMenuItem> {
 
     menu.addItem(&NSMenuItem::separatorItem(mtm));
 
+    // Opacity
+    menu.addItem(&create_menu_item_with_key(
+        mtm,
+        "Increase Opacity",
+        Some(sel!(increaseOpacity:)),
+        "\u{F700}",
+        NSEventModifierFlags::Command.union(NSEventModifierFlags::Shift),
+    ));
+
+    menu.addItem(&create_menu_item_with_key(
+        mtm,
+        "Decrease Opacity",
+        Some(sel!(decreaseOpacity:)),
+        "\u{F701}",
+        NSEventModifierFlags::Command.union(NSEventModifierFlags::Shift),
+    ));
+
+    menu.addItem(&NSMenuItem::separatorItem(mtm));
+
     // Fullscreen
     menu.addItem(&create_menu_item_with_key(
         mtm,
@@ -394,6 +514,50 @@ fn create_terminal_menu(mtm: MainThreadMarker) -> Retained<NSMenuItem> {
     let menu = NSMenu::new(mtm);
     menu.setTitle(&NSString::from_str("Terminal"));
 
+    menu.addItem(&create_menu_item_with_key(
+        mtm,
+        "Rerun Last Command",
+        Some(sel!(rerunLastCommand:)),
+        "e",
+        NSEventModifierFlags::Command.union(NSEventModifierFlags::Shift),
+    ));
+
+    menu.addItem(&create_menu_item_with_key(
+        mtm,
+        "Clear to Last Prompt",
+        Some(sel!(clearToLastPrompt:)),
+        "k",
+        NSEventModifierFlags::Command.union(NSEventModifierFlags::Shift),
+    ));
+
+    menu.addItem(&NSMenuItem::separatorItem(mtm));
+
+    menu.addItem(&create_menu_item_with_key(
+        mtm,
+        "Set Mark",
+        Some(sel!(setMark:)),
+        "m",
+        NSEventModifierFlags::Command.union(NSEventModifierFlags::Shift),
+    ));
+
+    menu.addItem(&create_menu_item_with_key(
+        mtm,
+        "Jump to Mark",
+        Some(sel!(jumpToMark:)),
+        "j",
+        NSEventModifierFlags::Command.union(NSEventModifierFlags::Shift),
+    ));
+
+    menu.addItem(&create_menu_item_with_key(
+        mtm,
+        "Toggle Recording",
+        Some(sel!(toggleRecording:)),
+        "l",
+        NSEventModifierFlags::Command.union(NSEventModifierFlags::Shift),
+    ));
+
+    menu.addItem(&NSMenuItem::separatorItem(mtm));
+
     // Reset
     menu.addItem(&create_menu_item(
         mtm,
@@ -741,6 +905,27 @@ fn create_help_menu(mtm: MainThreadMarker) -> Retained<NSMenuItem> {
         "",
     ));
 
+    debug_menu.addItem(&create_menu_item(
+        mtm,
+        "Send Bytes...",
+        Some(sel!(debugSendBytes:)),
+        "",
+    ));
+
+    debug_menu.addItem(&create_menu_item(
+        mtm,
+        "Replay File...",
+        Some(sel!(debugReplayFile:)),
+        "",
+    ));
+
+    debug_menu.addItem(&create_menu_item(
+        mtm,
+        "Stop Replay",
+        Some(sel!(debugStopReplay:)),
+        "",
+    ));
+
     debug_menu.addItem(&create_menu_item(
         mtm,
         "View Logs",
@@ -748,6 +933,13 @@ fn create_help_menu(mtm: MainThreadMarker) -> Retained<NSMenuItem> {
         "",
     ));
 
+    // Informational only (no action, so it is shown disabled)
+    let unknown_sequences_item = create_menu_item(mtm, &unknown_sequences_title(), None, "");
+    debug_menu.addItem(&unknown_sequences_item);
+    UNKNOWN_SEQUENCES_ITEM.with(|cell| {
+        *cell.borrow_mut() = Some(unknown_sequences_item);
+    });
+
     debug_menu.addItem(&NSMenuItem::separatorItem(mtm));
 
     // Log Level submenu
@@ -22,6 +22,7 @@ use cterm_app::config::{
     TabBarVisibility, ToolShortcutEntry,
 };
 use cterm_app::{git_sync, PullResult};
+use cterm_ui::theme::Theme;
 
 /// Format a Unix timestamp as a human-readable relative time
 fn format_timestamp(ts: i64) -> String {
@@ -56,6 +57,7 @@ pub struct PreferencesWindowIvars {
     // General tab controls
     scrollback_field: RefCell<Option<Retained<NSTextField>>>,
     confirm_close_checkbox: RefCell<Option<Retained<NSButton>>>,
+    confirm_quit_checkbox: RefCell<Option<Retained<NSButton>>>,
     copy_on_select_checkbox: RefCell<Option<Retained<NSButton>>>,
     // Appearance tab controls
     theme_popup: RefCell<Option<Retained<NSPopUpButton>>>,
@@ -69,7 +71,6 @@ pub struct PreferencesWindowIvars {
     show_tab_bar_popup: RefCell<Option<Retained<NSPopUpButton>>>,
     tab_position_popup: RefCell<Option<Retained<NSPopUpButton>>>,
     new_tab_popup: RefCell<Option<Retained<NSPopUpButton>>>,
-    show_close_checkbox: RefCell<Option<Retained<NSButton>>>,
     // Tools tab controls
     tool_entries_stack: RefCell<Option<Retained<NSStackView>>>,
     tool_entries: RefCell<
@@ -174,6 +175,7 @@ impl PreferencesWindow {
             on_save: RefCell::new(Some(Box::new(on_save))),
             scrollback_field: RefCell::new(None),
             confirm_close_checkbox: RefCell::new(None),
+            confirm_quit_checkbox: RefCell::new(None),
             copy_on_select_checkbox: RefCell::new(None),
             theme_popup: RefCell::new(None),
             font_field: RefCell::new(None),
@@ -185,7 +187,6 @@ impl PreferencesWindow {
             show_tab_bar_popup: RefCell::new(None),
             tab_position_popup: RefCell::new(None),
             new_tab_popup: RefCell::new(None),
-            show_close_checkbox: RefCell::new(None),
             tool_entries_stack: RefCell::new(None),
             tool_entries: RefCell::new(Vec::new()),
             git_remote_field: RefCell::new(None),
@@ -381,7 +382,7 @@ impl PreferencesWindow {
         };
 
         // Scrollback lines
-        let scrollback_row = self.create_label_field_row(
+        let scrollback_row = create_label_field_row(
             mtm,
             "Scrollback lines:",
             &config.general.scrollback_lines.to_string(),
@@ -402,6 +403,14 @@ impl PreferencesWindow {
             stack.addArrangedSubview(&confirm_checkbox);
         }
 
+        // Confirm quit
+        let confirm_quit_checkbox =
+            self.create_checkbox(mtm, "Confirm before quitting", config.general.confirm_quit);
+        *self.ivars().confirm_quit_checkbox.borrow_mut() = Some(confirm_quit_checkbox.clone());
+        unsafe {
+            stack.addArrangedSubview(&confirm_quit_checkbox);
+        }
+
         // Copy on select
         let copy_checkbox =
             self.create_checkbox(mtm, "Copy on select", config.general.copy_on_select);
@@ -436,34 +445,31 @@ impl PreferencesWindow {
             stack
         };
 
-        // Theme popup
-        let themes = [
-            ("dark", "Default Dark"),
-            ("light", "Default Light"),
-            ("tokyo_night", "Tokyo Night"),
-            ("dracula", "Dracula"),
-            ("nord", "Nord"),
-        ];
-        let theme_row =
-            self.create_label_popup_row(mtm, "Theme:", &themes, &config.appearance.theme);
+        // Theme popup (themes are looked up by name, so the names are the ids)
+        let theme_names: Vec<String> = Theme::builtin_themes()
+            .into_iter()
+            .map(|theme| theme.name)
+            .collect();
+        let themes: Vec<(&str, &str)> = theme_names
+            .iter()
+            .map(|name| (name.as_str(), name.as_str()))
+            .collect();
+        let theme_row = create_label_popup_row(mtm, "Theme:", &themes, &config.appearance.theme);
         *self.ivars().theme_popup.borrow_mut() = Some(theme_row.1.clone());
         unsafe {
             stack.addArrangedSubview(&theme_row.0);
         }
 
         // Font
-        let font_row = self.create_label_field_row(mtm, "Font:", &config.appearance.font.family);
+        let font_row = create_label_field_row(mtm, "Font:", &config.appearance.font.family);
         *self.ivars().font_field.borrow_mut() = Some(font_row.1.clone());
         unsafe {
             stack.addArrangedSubview(&font_row.0);
         }
 
         // Font size
-        let size_row = self.create_label_field_row(
-            mtm,
-            "Font size:",
-            &config.appearance.font.size.to_string(),
-        );
+        let size_row =
+            create_label_field_row(mtm, "Font size:", &config.appearance.font.size.to_string());
         *self.ivars().font_size_field.borrow_mut() = Some(size_row.1.clone());
         unsafe {
             stack.addArrangedSubview(&size_row.0);
@@ -480,8 +486,7 @@ impl PreferencesWindow {
             CursorStyleConfig::Underline => "underline",
             CursorStyleConfig::Bar => "bar",
         };
-        let cursor_row =
-            self.create_label_popup_row(mtm, "Cursor style:", &cursor_styles, cursor_id);
+        let cursor_row = create_label_popup_row(mtm, "Cursor style:", &cursor_styles, cursor_id);
         *self.ivars().cursor_popup.borrow_mut() = Some(cursor_row.1.clone());
         unsafe {
             stack.addArrangedSubview(&cursor_row.0);
@@ -547,7 +552,7 @@ impl PreferencesWindow {
             TabBarVisibility::Multiple => "multiple",
             TabBarVisibility::Never => "never",
         };
-        let show_row = self.create_label_popup_row(mtm, "Show tab bar:", &show_options, show_id);
+        let show_row = create_label_popup_row(mtm, "Show tab bar:", &show_options, show_id);
         *self.ivars().show_tab_bar_popup.borrow_mut() = Some(show_row.1.clone());
         unsafe {
             stack.addArrangedSubview(&show_row.0);
@@ -560,7 +565,7 @@ impl PreferencesWindow {
             TabBarPosition::Bottom => "bottom",
         };
         let position_row =
-            self.create_label_popup_row(mtm, "Tab bar position:", &position_options, position_id);
+            create_label_popup_row(mtm, "Tab bar position:", &position_options, position_id);
         *self.ivars().tab_position_popup.borrow_mut() = Some(position_row.1.clone());
         unsafe {
             stack.addArrangedSubview(&position_row.0);
@@ -572,23 +577,12 @@ impl PreferencesWindow {
             NewTabPosition::End => "end",
             NewTabPosition::AfterCurrent => "after_current",
         };
-        let new_row = self.create_label_popup_row(mtm, "New tab position:", &new_options, new_id);
+        let new_row = create_label_popup_row(mtm, "New tab position:", &new_options, new_id);
         *self.ivars().new_tab_popup.borrow_mut() = Some(new_row.1.clone());
         unsafe {
             stack.addArrangedSubview(&new_row.0);
         }
 
-        // Show close button
-        let close_checkbox = self.create_checkbox(
-            mtm,
-            "Show close button on tabs",
-            config.tabs.show_close_button,
-        );
-        *self.ivars().show_close_checkbox.borrow_mut() = Some(close_checkbox.clone());
-        unsafe {
-            stack.addArrangedSubview(&close_checkbox);
-        }
-
         tab.setView(Some(&stack));
         tab
     }
@@ -785,7 +779,7 @@ impl PreferencesWindow {
         }
 
         let existing_remote = status.remote_url.clone().unwrap_or_default();
-        let git_remote_row = self.create_label_field_row(mtm, "Git Remote URL:", &existing_remote);
+        let git_remote_row = create_label_field_row(mtm, "Git Remote URL:", &existing_remote);
         git_remote_row
             .1
             .setPlaceholderString(Some(&NSString::from_str(
@@ -816,7 +810,7 @@ impl PreferencesWindow {
         } else {
             "Configured"
         };
-        let status_row = self.create_label_field_row(mtm, "Status:", status_text);
+        let status_row = create_label_field_row(mtm, "Status:", status_text);
         status_row.1.setEditable(false);
         status_row.1.setDrawsBackground(false);
         status_row.1.setBordered(false);
@@ -827,7 +821,7 @@ impl PreferencesWindow {
 
         // Branch
         let branch_text = status.branch.clone().unwrap_or_else(|| "-".to_string());
-        let branch_row = self.create_label_field_row(mtm, "Branch:", &branch_text);
+        let branch_row = create_label_field_row(mtm, "Branch:", &branch_text);
         branch_row.1.setEditable(false);
         branch_row.1.setDrawsBackground(false);
         branch_row.1.setBordered(false);
@@ -842,7 +836,7 @@ impl PreferencesWindow {
         } else {
             "-".to_string()
         };
-        let last_sync_row = self.create_label_field_row(mtm, "Last sync:", &last_sync_text);
+        let last_sync_row = create_label_field_row(mtm, "Last sync:", &last_sync_text);
         last_sync_row.1.setEditable(false);
         last_sync_row.1.setDrawsBackground(false);
         last_sync_row.1.setBordered(false);
@@ -863,7 +857,7 @@ impl PreferencesWindow {
         } else {
             "Up to date"
         };
-        let changes_row = self.create_label_field_row(mtm, "Changes:", changes_text);
+        let changes_row = create_label_field_row(mtm, "Changes:", changes_text);
         changes_row.1.setEditable(false);
         changes_row.1.setDrawsBackground(false);
         changes_row.1.setBordered(false);
@@ -1021,86 +1015,6 @@ impl PreferencesWindow {
         }
     }
 
-    fn create_label_field_row(
-        &self,
-        mtm: MainThreadMarker,
-        label: &str,
-        value: &str,
-    ) -> (Retained<NSStackView>, Retained<NSTextField>) {
-        let row = unsafe {
-            let stack = NSStackView::new(mtm);
-            stack.setOrientation(objc2_app_kit::NSUserInterfaceLayoutOrientation::Horizontal);
-            stack.setSpacing(8.0);
-            stack
-        };
-
-        let label_view = NSTextField::labelWithString(&NSString::from_str(label), mtm);
-        unsafe {
-            let _: () = msg_send![&label_view, setAlignment: 2i64]; // NSTextAlignmentRight
-        }
-        unsafe {
-            row.addArrangedSubview(&label_view);
-        }
-
-        let field = NSTextField::new(mtm);
-        field.setStringValue(&NSString::from_str(value));
-        field.setEditable(true);
-        field.setBordered(true);
-        field.setDrawsBackground(true);
-        unsafe {
-            let size = NSSize::new(200.0, 22.0);
-            let _: () = msg_send![&field, setFrameSize: size];
-        }
-        unsafe {
-            row.addArrangedSubview(&field);
-        }
-
-        (row, field)
-    }
-
-    fn create_label_popup_row(
-        &self,
-        mtm: MainThreadMarker,
-        label: &str,
-        options: &[(&str, &str)],
-        selected: &str,
-    ) -> (Retained<NSStackView>, Retained<NSPopUpButton>) {
-        let row = unsafe {
-            let stack = NSStackView::new(mtm);
-            stack.setOrientation(objc2_app_kit::NSUserInterfaceLayoutOrientation::Horizontal);
-            stack.setSpacing(8.0);
-            stack
-        };
-
-        let label_view = NSTextField::labelWithString(&NSString::from_str(label), mtm);
-        unsafe {
-            row.addArrangedSubview(&label_view);
-        }
-
-        let popup = unsafe {
-            let popup = NSPopUpButton::new(mtm);
-            for (id, title) in options {
-                popup.addItemWithTitle(&NSString::from_str(title));
-                if let Some(item) = popup.lastItem() {
-                    item.setRepresentedObject(Some(&NSString::from_str(id)));
-                }
-            }
-            // Select the matching item
-            for (i, (id, _)) in options.iter().enumerate() {
-                if *id == selected {
-                    popup.selectItemAtIndex(i as isize);
-                    break;
-                }
-            }
-            popup
-        };
-        unsafe {
-            row.addArrangedSubview(&popup);
-        }
-
-        (row, popup)
-    }
-
     fn create_label_slider_row(
         &self,
         mtm: MainThreadMarker,
@@ -1169,6 +1083,9 @@ impl PreferencesWindow {
         if let Some(ref checkbox) = *self.ivars().confirm_close_checkbox.borrow() {
             config.general.confirm_close_with_running = checkbox.state() == 1;
         }
+        if let Some(ref checkbox) = *self.ivars().confirm_quit_checkbox.borrow() {
+            config.general.confirm_quit = checkbox.state() == 1;
+        }
         if let Some(ref checkbox) = *self.ivars().copy_on_select_checkbox.borrow() {
             config.general.copy_on_select = checkbox.state() == 1;
         }
@@ -1248,9 +1165,6 @@ impl PreferencesWindow {
                 }
             }
         }
-        if let Some(ref checkbox) = *self.ivars().show_close_checkbox.borrow() {
-            config.tabs.show_close_button = checkbox.state() == 1;
-        }
 
         // Save config to file
         if let Err(e) = save_config(&config) {
@@ -1308,6 +1222,87 @@ impl PreferencesWindow {
     }
 }
 
+/// A row with a right-aligned label and an editable text field
+pub(crate) fn create_label_field_row(
+    mtm: MainThreadMarker,
+    label: &str,
+    value: &str,
+) -> (Retained<NSStackView>, Retained<NSTextField>) {
+    let row = unsafe {
+        let stack = NSStackView::new(mtm);
+        stack.setOrientation(objc2_app_kit::NSUserInterfaceLayoutOrientation::Horizontal);
+        stack.setSpacing(8.0);
+        stack
+    };
+
+    let label_view = NSTextField::labelWithString(&NSString::from_str(label), mtm);
+    unsafe {
+        let _: () = msg_send![&label_view, setAlignment: 2i64]; // NSTextAlignmentRight
+    }
+    unsafe {
+        row.addArrangedSubview(&label_view);
+    }
+
+    let field = NSTextField::new(mtm);
+    field.setStringValue(&NSString::from_str(value));
+    field.setEditable(true);
+    field.setBordered(true);
+    field.setDrawsBackground(true);
+    unsafe {
+        let size = NSSize::new(200.0, 22.0);
+        let _: () = msg_send![&field, setFrameSize: size];
+    }
+    unsafe {
+        row.addArrangedSubview(&field);
+    }
+
+    (row, field)
+}
+
+/// A row with a label and a popup of `(id, title)` options, the item with
+/// id `selected` chosen; the ids are the items' represented objects
+pub(crate) fn create_label_popup_row(
+    mtm: MainThreadMarker,
+    label: &str,
+    options: &[(&str, &str)],
+    selected: &str,
+) -> (Retained<NSStackView>, Retained<NSPopUpButton>) {
+    let row = unsafe {
+        let stack = NSStackView::new(mtm);
+        stack.setOrientation(objc2_app_kit::NSUserInterfaceLayoutOrientation::Horizontal);
+        stack.setSpacing(8.0);
+        stack
+    };
+
+    let label_view = NSTextField::labelWithString(&NSString::from_str(label), mtm);
+    unsafe {
+        row.addArrangedSubview(&label_view);
+    }
+
+    let popup = unsafe {
+        let popup = NSPopUpButton::new(mtm);
+        for (id, title) in options {
+            popup.addItemWithTitle(&NSString::from_str(title));
+            if let Some(item) = popup.lastItem() {
+                item.setRepresentedObject(Some(&NSString::from_str(id)));
+            }
+        }
+        // Select the matching item
+        for (i, (id, _)) in options.iter().enumerate() {
+            if *id == selected {
+                popup.selectItemAtIndex(i as isize);
+                break;
+            }
+        }
+        popup
+    };
+    unsafe {
+        row.addArrangedSubview(&popup);
+    }
+
+    (row, popup)
+}
+
 /// Show the preferences window
 pub fn show_preferences(
     mtm: MainThreadMarker,
@@ -1318,3 +1313,88 @@ pub fn show_preferences(
     window.center();
     window.makeKeyAndOrderFront(None);
 }
+
+/// Run the first-run setup assistant
+///
+/// Offers the theme, font and shell, and returns the config with the choices
+/// applied. The config is saved even when skipped (with the defaults), so the
+/// assistant is only shown once.
+pub fn run_setup_assistant(mtm: MainThreadMarker, config: &Config) -> Config {
+    use objc2_app_kit::{NSAlert, NSAlertFirstButtonReturn, NSAlertStyle};
+
+    let alert = NSAlert::new(mtm);
+    alert.setAlertStyle(NSAlertStyle::Informational);
+    alert.setMessageText(&NSString::from_str("Welcome to cterm"));
+    alert.setInformativeText(&NSString::from_str(
+        "Pick a theme, font and shell to get started. Everything can be changed later in Settings.",
+    ));
+    alert.addButtonWithTitle(&NSString::from_str("Continue"));
+    alert.addButtonWithTitle(&NSString::from_str("Skip"));
+
+    let stack = unsafe {
+        let stack = NSStackView::new(mtm);
+        stack.setOrientation(objc2_app_kit::NSUserInterfaceLayoutOrientation::Vertical);
+        stack.setAlignment(objc2_app_kit::NSLayoutAttribute::Leading);
+        stack.setSpacing(8.0);
+        stack.setFrameSize(NSSize::new(360.0, 120.0));
+        stack
+    };
+
+    let theme_names: Vec<String> = Theme::builtin_themes()
+        .into_iter()
+        .map(|theme| theme.name)
+        .collect();
+    let themes: Vec<(&str, &str)> = theme_names
+        .iter()
+        .map(|name| (name.as_str(), name.as_str()))
+        .collect();
+    let theme_row = create_label_popup_row(mtm, "Theme:", &themes, &config.appearance.theme);
+    let font_row = create_label_field_row(mtm, "Font:", &config.appearance.font.family);
+    let size_row =
+        create_label_field_row(mtm, "Font size:", &config.appearance.font.size.to_string());
+
+    // Shell, from /etc/shells ("" = the system default)
+    let shell_paths = cterm_app::config::available_shells();
+    let mut shells: Vec<(&str, &str)> = vec![("", "System default")];
+    shells.extend(
+        shell_paths
+            .iter()
+            .map(|path| (path.as_str(), path.as_str())),
+    );
+    let shell_row = create_label_popup_row(
+        mtm,
+        "Shell:",
+        &shells,
+        config.general.default_shell.as_deref().unwrap_or(""),
+    );
+
+    unsafe {
+        stack.addArrangedSubview(&theme_row.0);
+        stack.addArrangedSubview(&font_row.0);
+        stack.addArrangedSubview(&size_row.0);
+        stack.addArrangedSubview(&shell_row.0);
+    }
+    alert.setAccessoryView(Some(&stack));
+
+    let mut config = config.clone();
+    if alert.runModal() == NSAlertFirstButtonReturn {
+        let selected_id = |popup: &NSPopUpButton| {
+            let obj = popup.selectedItem()?.representedObject()?;
+            let id: &NSString = unsafe { &*(&*obj as *const _ as *const NSString) };
+            Some(id.to_string())
+        };
+        if let Some(theme) = selected_id(&theme_row.1) {
+            config.appearance.theme = theme;
+        }
+        config.appearance.font.family = font_row.1.stringValue().to_string();
+        if let Ok(size) = size_row.1.stringValue().to_string().parse::<f64>() {
+            config.appearance.font.size = size;
+        }
+        config.general.default_shell = selected_id(&shell_row.1).filter(|id| !id.is_empty());
+    }
+
+    if let Err(e) = save_config(&config) {
+        log::error!("Failed to save config: {}", e);
+    }
+    config
+}
@@ -5,6 +5,7 @@
 use std::cell::{Cell, RefCell};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use objc2::rc::Retained;
 use objc2::runtime::AnyObject;
@@ -15,25 +16,44 @@ use objc2_app_kit::{
 };
 use objc2_foundation::{
     MainThreadMarker, NSArray, NSAttributedString, NSNumber, NSObjectProtocol, NSPoint, NSRange,
-    NSRect, NSSize, NSString,
+    NSRect, NSSize, NSString, NSTimer,
 };
 use parking_lot::Mutex;
 
-use cterm_app::config::Config;
+use cterm_app::config::{Config, GeneralConfig, TabsConfig, TitleFilter};
+use cterm_app::notify::{
+    bell_notification, sound_for, BellEscalation, BellRateLimiter, Sound, SoundEvent,
+};
+use cterm_app::recording::{RecordingToggle, RECORDING_INDICATOR};
+use cterm_app::replay::Replay;
+use cterm_app::shortcuts::ShortcutManager;
 use cterm_app::upgrade::{
     execute_upgrade, TabUpgradeState, TerminalUpgradeState, UpgradeState, WindowUpgradeState,
 };
 use cterm_core::screen::{ScreenConfig, SelectionMode};
-use cterm_core::term::TerminalEvent;
-use cterm_core::{Pty, PtyConfig, PtySize, Terminal};
+use cterm_core::term::{ScrollTrigger, SelectionClearTrigger, TerminalEvent};
+use cterm_core::{Pty, PtyConfig, Terminal};
+use cterm_ui::events::Action;
+use cterm_ui::minimap::{minimap_line_at, MINIMAP_WIDTH};
 use cterm_ui::theme::Theme;
+use cterm_ui::utils::{selection_autoscroll_lines, ClickCounter, SELECTION_AUTOSCROLL_INTERVAL};
 
 use crate::cg_renderer::CGRenderer;
+use crate::desktop_notification;
+use crate::dialogs::FindOptions;
 use crate::file_transfer::PendingFileManager;
 use crate::mouse::{self, MouseButton, MouseModifiers};
 use crate::notification_bar::{NotificationBar, NOTIFICATION_BAR_HEIGHT};
 use crate::{clipboard, keycode};
 
+/// Marker prepended to the window (tab) title of incognito terminals
+pub const INCOGNITO_TITLE_PREFIX: &str = "🕶 ";
+
+/// Marker prepended to the window (tab) title while the session is recorded
+fn recording_title_prefix() -> String {
+    format!("{} ", RECORDING_INDICATOR)
+}
+
 /// Shared state between the view and PTY thread
 struct ViewState {
     needs_redraw: AtomicBool,
@@ -46,8 +66,22 @@ struct ViewState {
     title_changed: AtomicBool,
     /// Whether title was explicitly set by user or template (locks out OSC updates)
     title_locked: AtomicBool,
+    /// Which program title changes are applied (update mode and ignore patterns)
+    title_filter: TitleFilter,
+    /// Directory titles can show (updated from PTY thread)
+    directory: std::sync::RwLock<Option<std::path::PathBuf>>,
+    /// Tab settings, for the title format
+    tabs_config: TabsConfig,
     /// Flag indicating bell was triggered and needs UI update
     bell_changed: AtomicBool,
+    /// Bells counted towards a desktop notification
+    bell_escalation: Mutex<BellEscalation>,
+    /// Coalesces bells ringing in quick succession
+    bell_limiter: Mutex<BellRateLimiter>,
+    /// Sound played for a bell (None = silent)
+    bell_sound: Option<Sound>,
+    /// Sound played when a file arrives (None = silent)
+    file_received_sound: Option<Sound>,
 }
 
 impl Default for ViewState {
@@ -59,7 +93,14 @@ impl Default for ViewState {
             title: std::sync::RwLock::new(String::new()),
             title_changed: AtomicBool::new(false),
             title_locked: AtomicBool::new(false),
+            title_filter: TitleFilter::default(),
+            directory: std::sync::RwLock::new(None),
+            tabs_config: TabsConfig::default(),
             bell_changed: AtomicBool::new(false),
+            bell_escalation: Mutex::new(BellEscalation::new(0, Duration::ZERO)),
+            bell_limiter: Mutex::new(BellRateLimiter::new(Duration::ZERO)),
+            bell_sound: None,
+            file_received_sound: None,
         }
     }
 }
@@ -74,6 +115,18 @@ pub struct TerminalViewIvars {
     state: Arc<ViewState>,
     /// Whether we're currently in a selection drag
     is_selecting: Cell<bool>,
+    /// Whether the current drag is reported to the program (mouse
+    /// reporting) rather than selecting
+    reporting_drag: Cell<bool>,
+    /// Shortcuts, for the snippets bound in [shortcuts.send_text] (other
+    /// shortcuts are menu key equivalents)
+    shortcuts: ShortcutManager,
+    /// Whether the scrollback minimap is shown on the right edge
+    minimap: bool,
+    /// Whether the mouse went down in the minimap (dragging scrolls)
+    minimap_dragging: Cell<bool>,
+    /// Whether the wheel steps through the shell's history at an idle prompt
+    wheel_scrolls_history: bool,
     /// Template name (if this view was created from a template)
     template_name: RefCell<Option<String>>,
     /// Watchdog FD ID (for crash recovery unregistration)
@@ -87,6 +140,30 @@ pub struct TerminalViewIvars {
     file_manager: RefCell<PendingFileManager>,
     /// Color palette for HTML export
     color_palette: cterm_core::color::ColorPalette,
+    /// Faint text opacity for HTML export
+    faint_opacity: f64,
+    /// Click counter used instead of the system count when a custom
+    /// multi-click interval is configured
+    click_counter: RefCell<Option<ClickCounter>>,
+    /// Timer that scrolls while a selection is dragged past the top or bottom edge
+    autoscroll_timer: RefCell<Option<Retained<NSTimer>>>,
+    /// Last drag location in view coordinates, read by the auto-scroll timer
+    autoscroll_point: Cell<NSPoint>,
+    /// Lines scrolled per auto-scroll tick and row beyond the edge (0 disables)
+    selection_autoscroll_speed: usize,
+    /// Strip trailing newlines from clipboard pastes
+    paste_strip_trailing_newline: bool,
+    /// Foreground programs that clipboard pastes ask for confirmation before
+    confirm_paste_into: Vec<String>,
+    /// Incognito: no scrollback, and never saved to session, crash or upgrade state
+    incognito: bool,
+    /// General settings, for the session recording directory and mode and
+    /// bracketed paste
+    general: GeneralConfig,
+    /// Last query of the find dialog, to fill it in next time
+    find_options: RefCell<FindOptions>,
+    /// Capture being replayed by the "Replay File" debug action
+    replay: RefCell<Option<Replay>>,
 }
 
 define_class!(
@@ -149,19 +226,21 @@ define_class!(
         }
 
         #[unsafe(method(drawRect:))]
-        fn draw_rect(&self, _dirty_rect: NSRect) {
+        fn draw_rect(&self, dirty_rect: NSRect) {
             // Clear the redraw flag
             self.ivars().state.needs_redraw.store(false, Ordering::Relaxed);
 
             // Check for file transfers
             self.check_file_transfers();
 
-            if let Some(ref renderer) = *self.ivars().renderer.borrow() {
+            if let Some(ref mut renderer) = *self.ivars().renderer.borrow_mut() {
                 let terminal = self.ivars().terminal.lock();
-                // Always use full view bounds for rendering to avoid artifacts
-                // from partial dirty_rect updates after resize/fullscreen
+                renderer.set_color_overrides(terminal.screen().color_overrides());
+                // Output only invalidates the rows it changed (see
+                // invalidate_damage); resize/fullscreen invalidate the whole view
                 let bounds: NSRect = unsafe { msg_send![self, bounds] };
-                renderer.render(&terminal, bounds);
+                renderer.render(&terminal, bounds, dirty_rect);
+                renderer.render_minimap(&terminal, bounds);
 
                 // Render IME marked text if present
                 let marked_text = self.ivars().marked_text.borrow();
@@ -201,6 +280,15 @@ define_class!(
 
             let modifiers = keycode::modifiers_from_event(event);
 
+            // Snippets bound in [shortcuts.send_text] (Command+key ones only
+            // get here when no menu item has the same shortcut)
+            if let Some(Action::SendText(text)) = keycode::keycode_from_event(event)
+                .and_then(|key| self.ivars().shortcuts.match_event(key, modifiers))
+            {
+                self.write_to_pty(text.as_bytes());
+                return;
+            }
+
             // Let Command+key combinations pass through to the menu system
             // Command is never part of terminal sequences
             if modifiers.contains(cterm_ui::events::Modifiers::SUPER) {
@@ -220,15 +308,14 @@ define_class!(
                 return;
             }
 
-            // Reset scroll offset when any key is pressed (return to current content)
-            {
-                let mut terminal = self.ivars().terminal.lock();
-                if terminal.screen().scroll_offset != 0 {
-                    terminal.screen_mut().scroll_offset = 0;
-                    drop(terminal);
-                    self.set_needs_display();
-                }
+            // Return to current content and drop the selection when a key
+            // is pressed (if configured)
+            let mut terminal = self.ivars().terminal.lock();
+            let scrolled = terminal.scroll_to_bottom_for(ScrollTrigger::Keypress);
+            if terminal.clear_selection_for(SelectionClearTrigger::Keypress) || scrolled {
+                self.set_needs_display();
             }
+            drop(terminal);
 
             // Handle Option+Arrow keys specially to match macOS Terminal.app behavior
             let raw_keycode = event.keyCode();
@@ -282,6 +369,14 @@ define_class!(
                 _ => None,
             };
 
+            // Keypad Enter is always told apart from Return; the other keypad
+            // keys only differ from the main keys in application keypad mode
+            let key = key.or_else(|| {
+                let keypad = keycode::keycode_from_event(event)?.to_keypad_key()?;
+                let app_keypad = self.ivars().terminal.lock().screen().modes.application_keypad;
+                (app_keypad || keypad == Key::KeypadEnter).then_some(keypad)
+            });
+
             // Convert cterm_ui Modifiers to cterm_core Modifiers
             let core_mods = cterm_core::term::Modifiers::from_bits_truncate(modifiers.bits());
 
@@ -333,6 +428,12 @@ define_class!(
             let location_in_window = event.locationInWindow();
             let location = self.convert_point_from_view(location_in_window, None);
 
+            if self.ivars().minimap && location.x >= self.frame().size.width - MINIMAP_WIDTH {
+                self.ivars().minimap_dragging.set(true);
+                self.minimap_jump(location.y);
+                return;
+            }
+
             // Calculate cell position
             let col = (location.x / self.ivars().cell_width).floor().max(0.0) as usize;
             let row = (location.y / self.ivars().cell_height).floor().max(0.0) as usize;
@@ -360,7 +461,17 @@ define_class!(
             let sgr_mouse = terminal.screen().modes.sgr_mouse;
             drop(terminal);
 
-            if mouse::should_capture_mouse(mouse_mode) {
+            // Holding the bypass modifier (Shift by default) selects text
+            // even when the program has grabbed the mouse
+            let bypass = self
+                .ivars()
+                .general
+                .selection_bypass_modifier
+                .is_held(keycode::modifiers_from_event(event));
+            let reporting = mouse::should_capture_mouse(mouse_mode) && !bypass;
+            self.ivars().reporting_drag.set(reporting);
+
+            if reporting {
                 // Send mouse event to application
                 let button = match event.buttonNumber() {
                     0 => MouseButton::Left,
@@ -388,7 +499,12 @@ define_class!(
 
             // Normal selection mode
             // Determine selection mode based on click count and modifiers
-            let click_count = event.clickCount();
+            let click_count = match *self.ivars().click_counter.borrow_mut() {
+                Some(ref mut counter) => {
+                    counter.click(location.x, location.y, std::time::Instant::now()) as isize
+                }
+                None => event.clickCount(),
+            };
             let mode = if flags.contains(NSEventModifierFlags::Option) {
                 // Option+drag = block/rectangular selection
                 SelectionMode::Block
@@ -403,6 +519,7 @@ define_class!(
             // Start selection
             let mut terminal = self.ivars().terminal.lock();
             let line = terminal.screen().visible_row_to_absolute_line(row);
+            let col = terminal.screen().logical_col(line, col);
             terminal.screen_mut().start_selection(line, col, mode);
             drop(terminal);
 
@@ -414,11 +531,13 @@ define_class!(
 
         #[unsafe(method(mouseUp:))]
         fn mouse_up(&self, event: &NSEvent) {
+            self.ivars().minimap_dragging.set(false);
             if !self.ivars().is_selecting.get() {
                 return;
             }
 
             self.ivars().is_selecting.set(false);
+            self.stop_selection_autoscroll();
 
             // Check if mouse reporting is active
             let terminal = self.ivars().terminal.lock();
@@ -426,7 +545,7 @@ define_class!(
             let sgr_mouse = terminal.screen().modes.sgr_mouse;
             drop(terminal);
 
-            if mouse::should_capture_mouse(mouse_mode) {
+            if self.ivars().reporting_drag.get() && mouse::should_capture_mouse(mouse_mode) {
                 // Send mouse release event to application
                 let location_in_window = event.locationInWindow();
                 let location = self.convert_point_from_view(location_in_window, None);
@@ -468,6 +587,7 @@ define_class!(
                     if let Some(text) = terminal.screen().get_selected_text() {
                         drop(terminal);
                         clipboard::set_text(&text);
+                        cterm_app::clipboard_history::record(&text, self.is_incognito());
                         log::debug!("Copied {} chars to clipboard", text.len());
                     }
                 }
@@ -476,6 +596,11 @@ define_class!(
 
         #[unsafe(method(mouseDragged:))]
         fn mouse_dragged(&self, event: &NSEvent) {
+            if self.ivars().minimap_dragging.get() {
+                let location = self.convert_point_from_view(event.locationInWindow(), None);
+                self.minimap_jump(location.y);
+                return;
+            }
             if !self.ivars().is_selecting.get() {
                 return;
             }
@@ -494,7 +619,7 @@ define_class!(
             let sgr_mouse = terminal.screen().modes.sgr_mouse;
             drop(terminal);
 
-            if mouse::should_capture_mouse(mouse_mode) {
+            if self.ivars().reporting_drag.get() && mouse::should_capture_mouse(mouse_mode) {
                 // Send drag event to application (ButtonEvent or AnyEvent mode)
                 let button = match event.buttonNumber() {
                     0 => MouseButton::Left,
@@ -521,6 +646,50 @@ define_class!(
             // Normal selection mode - extend selection
             let mut terminal = self.ivars().terminal.lock();
             let line = terminal.screen().visible_row_to_absolute_line(row);
+            let col = terminal.screen().logical_col(line, col);
+            terminal.screen_mut().extend_selection(line, col);
+            drop(terminal);
+
+            self.set_needs_display();
+
+            self.ivars().autoscroll_point.set(location);
+            if self.selection_autoscroll_step() != 0 && self.ivars().autoscroll_timer.borrow().is_none()
+            {
+                let timer = unsafe {
+                    NSTimer::scheduledTimerWithTimeInterval_target_selector_userInfo_repeats(
+                        SELECTION_AUTOSCROLL_INTERVAL.as_secs_f64(),
+                        self,
+                        sel!(selectionAutoscroll:),
+                        None,
+                        true,
+                    )
+                };
+                *self.ivars().autoscroll_timer.borrow_mut() = Some(timer);
+            }
+        }
+
+        /// Timer tick while a selection is dragged past the view edge
+        #[unsafe(method(selectionAutoscroll:))]
+        fn selection_autoscroll(&self, _timer: Option<&AnyObject>) {
+            let lines = self.selection_autoscroll_step();
+            if !self.ivars().is_selecting.get() || lines == 0 {
+                self.stop_selection_autoscroll();
+                return;
+            }
+
+            let location = self.ivars().autoscroll_point.get();
+            let col = (location.x / self.ivars().cell_width).floor().max(0.0) as usize;
+
+            let mut terminal = self.ivars().terminal.lock();
+            let row = if lines < 0 {
+                terminal.scroll_viewport_up(lines.unsigned_abs());
+                0
+            } else {
+                terminal.scroll_viewport_down(lines as usize);
+                terminal.screen().height().saturating_sub(1)
+            };
+            let line = terminal.screen().visible_row_to_absolute_line(row);
+            let col = terminal.screen().logical_col(line, col);
             terminal.screen_mut().extend_selection(line, col);
             drop(terminal);
 
@@ -529,6 +698,8 @@ define_class!(
 
         #[unsafe(method(scrollWheel:))]
         fn scroll_wheel(&self, event: &NSEvent) {
+            use objc2_app_kit::NSEventModifierFlags;
+
             let delta_y = event.scrollingDeltaY();
             log::trace!("Scroll wheel delta: {}", delta_y);
 
@@ -572,6 +743,27 @@ define_class!(
                 return;
             }
 
+            // Sideways scrolling of truncated long lines: horizontal swipes,
+            // or Shift+wheel (which macOS may already turn into horizontal)
+            let delta_x = event.scrollingDeltaX();
+            let shift = event.modifierFlags().contains(NSEventModifierFlags::Shift);
+            let horizontal = if delta_x.abs() > delta_y.abs() {
+                Some(delta_x)
+            } else {
+                shift.then_some(delta_y)
+            };
+            if let Some(delta) = horizontal {
+                let cols = (delta.abs() / 2.0).max(1.0) as isize;
+                let cols = if delta > 0.0 { -cols } else { cols };
+                self.ivars()
+                    .terminal
+                    .lock()
+                    .screen_mut()
+                    .scroll_horizontal(cols);
+                self.set_needs_display();
+                return;
+            }
+
             // Normal scrollback mode
             let scroll_lines = (delta_y.abs() / 2.0) as usize;
             if scroll_lines == 0 {
@@ -579,6 +771,16 @@ define_class!(
             }
 
             let mut terminal = self.ivars().terminal.lock();
+
+            // At an idle shell prompt the wheel can step through its history
+            if self.ivars().wheel_scrolls_history {
+                if let Some(keys) = terminal.wheel_history_keys(delta_y > 0.0, scroll_lines) {
+                    drop(terminal);
+                    self.write_to_pty(&keys);
+                    return;
+                }
+            }
+
             if delta_y > 0.0 {
                 terminal.scroll_viewport_up(scroll_lines);
             } else if delta_y < 0.0 {
@@ -665,12 +867,7 @@ define_class!(
         /// Copy selection to clipboard (Command+C)
         #[unsafe(method(copy:))]
         fn action_copy(&self, _sender: Option<&objc2::runtime::AnyObject>) {
-            let terminal = self.ivars().terminal.lock();
-            if let Some(text) = terminal.screen().get_selected_text() {
-                drop(terminal);
-                clipboard::set_text(&text);
-                log::debug!("Copied {} chars to clipboard", text.len());
-            }
+            self.copy_selection();
         }
 
         /// Copy selection to clipboard as HTML (Command+Shift+C)
@@ -678,7 +875,8 @@ define_class!(
         fn action_copy_as_html(&self, _sender: Option<&objc2::runtime::AnyObject>) {
             let terminal = self.ivars().terminal.lock();
             let palette = &self.ivars().color_palette;
-            if let Some(html) = terminal.screen().get_selected_html(palette) {
+            let faint_opacity = self.ivars().faint_opacity;
+            if let Some(html) = terminal.screen().get_selected_html(palette, faint_opacity) {
                 let plain_text = terminal.screen().get_selected_text().unwrap_or_default();
                 drop(terminal);
                 clipboard::set_html(&html, &plain_text);
@@ -686,22 +884,207 @@ define_class!(
             }
         }
 
+        /// Copy the selection, or the visible screen, as a PNG image
+        #[unsafe(method(copyAsImage:))]
+        fn action_copy_as_image(&self, _sender: Option<&objc2::runtime::AnyObject>) {
+            use objc2_app_kit::NSPasteboard;
+
+            let Some(png) = self.render_image() else {
+                return;
+            };
+            let pasteboard = NSPasteboard::generalPasteboard();
+            pasteboard.clearContents();
+            let data = objc2_foundation::NSData::with_bytes(&png);
+            pasteboard.setData_forType(Some(&data), &NSString::from_str("public.png"));
+            log::debug!("Copied {} byte image to pasteboard", png.len());
+        }
+
+        /// Save the selection, or the visible screen, as a PNG file
+        #[unsafe(method(saveAsImage:))]
+        fn action_save_as_image(&self, _sender: Option<&objc2::runtime::AnyObject>) {
+            // Render first so the image matches the screen when the menu was used
+            let Some(png) = self.render_image() else {
+                return;
+            };
+            let mtm = MainThreadMarker::from(self);
+            if let Some(path) = crate::dialogs::show_save_panel(
+                mtm,
+                self.window().as_deref(),
+                Some("cterm.png"),
+                None,
+            ) {
+                if let Err(e) = std::fs::write(&path, &png) {
+                    log::error!("Failed to save image to {:?}: {}", path, e);
+                }
+            }
+        }
+
+        /// Copy the output of the last finished command (Command+Shift+Y)
+        #[unsafe(method(copyLastCommandOutput:))]
+        fn action_copy_last_command_output(&self, _sender: Option<&objc2::runtime::AnyObject>) {
+            let terminal = self.ivars().terminal.lock();
+            if let Some(text) = terminal.screen().last_command_output() {
+                drop(terminal);
+                clipboard::set_text(&text);
+                cterm_app::clipboard_history::record(&text, self.is_incognito());
+                log::debug!("Copied {} chars of command output to clipboard", text.len());
+            }
+        }
+
+        /// Run the last finished command again (Command+Shift+E)
+        #[unsafe(method(rerunLastCommand:))]
+        fn action_rerun_last_command(&self, _sender: Option<&objc2::runtime::AnyObject>) {
+            let mut terminal = self.ivars().terminal.lock();
+            if let Err(e) = terminal.rerun_last_command() {
+                log::error!("Failed to write to PTY: {}", e);
+            }
+        }
+
+        /// Delete the output of the last command, keeping earlier history (Command+Shift+K)
+        #[unsafe(method(clearToLastPrompt:))]
+        fn action_clear_to_last_prompt(&self, _sender: Option<&objc2::runtime::AnyObject>) {
+            if self.ivars().terminal.lock().screen_mut().clear_to_last_prompt() {
+                self.set_needs_display();
+            }
+        }
+
+        /// Search the scrollback and screen, highlighting the matches (Command+F)
+        ///
+        /// Searching again for the same text moves to the next match.
+        #[unsafe(method(performFindPanelAction:))]
+        fn action_find(&self, _sender: Option<&objc2::runtime::AnyObject>) {
+            let mtm = MainThreadMarker::from(self);
+            let previous = self.ivars().find_options.borrow().clone();
+            let Some(options) = crate::dialogs::show_find(mtm, &previous) else {
+                self.ivars().terminal.lock().screen_mut().clear_search();
+                self.set_needs_display();
+                return;
+            };
+
+            let mut terminal = self.ivars().terminal.lock();
+            terminal
+                .screen_mut()
+                .set_search_scrollback_only(options.scrollback_only);
+            let current = terminal.screen_mut().search_next(
+                &options.text,
+                options.case_sensitive,
+                options.regex,
+            );
+            match current {
+                Some(current) => terminal.scroll_to_line(current.line),
+                None => log::info!("No matches for: {}", options.text),
+            }
+            drop(terminal);
+
+            *self.ivars().find_options.borrow_mut() = options;
+            self.set_needs_display();
+        }
+
+        /// Show the scrollback lines matching a regex in a read-only window
+        #[unsafe(method(filterScrollback:))]
+        fn action_filter_scrollback(&self, _sender: Option<&objc2::runtime::AnyObject>) {
+            let mtm = MainThreadMarker::from(self);
+            let Some(pattern) = crate::dialogs::show_input(
+                mtm,
+                self.window().as_deref(),
+                "Filter Scrollback",
+                "Show lines matching (regular expression):",
+                "",
+            ) else {
+                return;
+            };
+            let lines = self
+                .ivars()
+                .terminal
+                .lock()
+                .screen()
+                .extract_matches(&pattern, true, true);
+            crate::filter_viewer::show_filter_results(mtm, &pattern, &lines);
+        }
+
+        /// Start or stop recording the session to a file (Command+Shift+L)
+        #[unsafe(method(toggleRecording:))]
+        fn action_toggle_recording(&self, _sender: Option<&objc2::runtime::AnyObject>) {
+            let result = cterm_app::recording::toggle(
+                &mut self.ivars().terminal.lock(),
+                &self.ivars().general,
+            );
+            let window = self.window();
+            let prefix = recording_title_prefix();
+            match result {
+                Ok(RecordingToggle::Started(_)) => {
+                    if let Some(window) = &window {
+                        let title = format!("{}{}", prefix, window.title());
+                        window.setTitle(&NSString::from_str(&title));
+                    }
+                }
+                Ok(RecordingToggle::Stopped(path)) => {
+                    if let Some(window) = &window {
+                        let title = window.title().to_string().replacen(&prefix, "", 1);
+                        window.setTitle(&NSString::from_str(&title));
+                    }
+                    log::info!("Session recording saved to {}", path.display());
+                }
+                Err(e) => {
+                    crate::dialogs::show_error(
+                        MainThreadMarker::from(self),
+                        window.as_deref(),
+                        "Session Recording Failed",
+                        &e.to_string(),
+                    );
+                }
+            }
+        }
+
+        /// Toggle a scrollback bookmark at the current position (Command+Shift+M)
+        #[unsafe(method(setMark:))]
+        fn action_set_mark(&self, _sender: Option<&objc2::runtime::AnyObject>) {
+            self.ivars().terminal.lock().screen_mut().toggle_bookmark();
+            self.set_needs_display();
+        }
+
+        /// Scroll to the previous scrollback bookmark (Command+Shift+J)
+        #[unsafe(method(jumpToMark:))]
+        fn action_jump_to_mark(&self, _sender: Option<&objc2::runtime::AnyObject>) {
+            if self.ivars().terminal.lock().screen_mut().jump_to_bookmark() {
+                self.set_needs_display();
+            }
+        }
+
         /// Paste from clipboard (Command+V)
         #[unsafe(method(paste:))]
         fn action_paste(&self, _sender: Option<&objc2::runtime::AnyObject>) {
             if let Some(text) = clipboard::get_text() {
-                // Check if bracketed paste mode is enabled
-                let terminal = self.ivars().terminal.lock();
-                let bracketed = terminal.screen().modes.bracketed_paste;
-                drop(terminal);
-
-                let paste_text = if bracketed {
-                    format!("\x1b[200~{}\x1b[201~", text)
-                } else {
-                    text
-                };
+                self.paste_text(text);
+            }
+        }
 
-                self.write_to_pty(paste_text.as_bytes());
+        /// Paste an earlier copy picked from the clipboard history (Command+Shift+H)
+        #[unsafe(method(pasteFromHistory:))]
+        fn action_paste_from_history(&self, _sender: Option<&objc2::runtime::AnyObject>) {
+            let entries = cterm_app::clipboard_history::entries();
+            let mtm = MainThreadMarker::from(self);
+            if entries.is_empty() {
+                crate::dialogs::show_error(
+                    mtm,
+                    self.window().as_deref(),
+                    "Paste from History",
+                    "Nothing has been copied yet.",
+                );
+                return;
+            }
+            let previews: Vec<String> = entries
+                .iter()
+                .map(|text| cterm_app::clipboard_history::preview(text, 60))
+                .collect();
+            if let Some(index) = crate::dialogs::show_choice(
+                mtm,
+                self.window().as_deref(),
+                "Paste from History",
+                "Choose a recent copy to paste:",
+                &previews,
+            ) {
+                self.paste_text(entries[index].clone());
             }
         }
 
@@ -749,6 +1132,78 @@ define_class!(
             log::info!("  Total lines (with scrollback): {}", screen.total_lines());
             log::info!("  Selection: {:?}", screen.selection);
             log::info!("  Modes: {:?}", screen.modes);
+            let (images, bytes) = screen.image_memory();
+            log::info!("  Images: {} ({} bytes of pixel data)", images, bytes);
+            log::info!(
+                "  Unknown sequences (all tabs): {}",
+                cterm_core::parser::unknown_sequence_count()
+            );
+        }
+
+        /// Debug: Write hand-typed bytes to the PTY
+        #[unsafe(method(debugSendBytes:))]
+        fn action_debug_send_bytes(&self, _sender: Option<&objc2::runtime::AnyObject>) {
+            let mtm = MainThreadMarker::from(self);
+            let window = self.window();
+            let Some(input) = crate::dialogs::show_input(
+                mtm,
+                window.as_deref(),
+                "Send Bytes",
+                "Hex (1b 5b 41) or text with escapes (\\e[A, \\x07, \\t, \\n):",
+                "",
+            ) else {
+                return;
+            };
+            match cterm_app::send_bytes::parse_send_bytes(&input) {
+                Ok(bytes) => self.write_to_pty(&bytes),
+                Err(e) => crate::dialogs::show_error(
+                    mtm,
+                    window.as_deref(),
+                    "Send Bytes",
+                    &e.to_string(),
+                ),
+            }
+        }
+
+        /// Debug: Feed a captured byte stream to the terminal as if the
+        /// program wrote it
+        #[unsafe(method(debugReplayFile:))]
+        fn action_debug_replay_file(&self, _sender: Option<&objc2::runtime::AnyObject>) {
+            let mtm = MainThreadMarker::from(self);
+            let Some(path) = crate::dialogs::show_open_panel(mtm) else {
+                return;
+            };
+            let data = match std::fs::read(&path) {
+                Ok(data) => data,
+                Err(e) => {
+                    crate::dialogs::show_error(
+                        mtm,
+                        self.window().as_deref(),
+                        "Replay File",
+                        &format!("Failed to read {}: {}", path.display(), e),
+                    );
+                    return;
+                }
+            };
+            log::info!("Replaying {} bytes from {:?}", data.len(), path);
+
+            let terminal = Arc::clone(&self.ivars().terminal);
+            let state = Arc::clone(&self.ivars().state);
+            let bytes_per_sec = self.ivars().general.replay_bytes_per_sec;
+            let replay = Replay::start(data, bytes_per_sec, move |chunk| {
+                Self::handle_output(&terminal, &state, chunk);
+                !state.pty_closed.load(Ordering::Relaxed)
+            });
+            // Replacing a running replay stops it
+            *self.ivars().replay.borrow_mut() = Some(replay);
+        }
+
+        /// Debug: Stop the replay started with "Replay File..."
+        #[unsafe(method(debugStopReplay:))]
+        fn action_debug_stop_replay(&self, _sender: Option<&objc2::runtime::AnyObject>) {
+            if self.ivars().replay.borrow_mut().take().is_some() {
+                log::info!("Replay stopped");
+            }
         }
 
         /// Debug: Trigger a crash to test crash recovery
@@ -1191,10 +1646,11 @@ define_class!(
             // Use cursor position
             let terminal = self.ivars().terminal.lock();
             let cursor = &terminal.screen().cursor;
+            let cursor_col = terminal.screen().cursor_render_col();
             let cell_width = self.ivars().cell_width;
             let cell_height = self.ivars().cell_height;
 
-            let x = cursor.col as f64 * cell_width;
+            let x = cursor_col as f64 * cell_width;
             let y = cursor.row as f64 * cell_height;
             drop(terminal);
 
@@ -1231,15 +1687,64 @@ impl TerminalView {
     /// Common initialization: allocate NSView, set ivars, init frame, setup notification bar
     fn init_view(
         mtm: MainThreadMarker,
-        renderer: CGRenderer,
+        mut renderer: CGRenderer,
         terminal: Arc<Mutex<Terminal>>,
+        config: &Config,
         theme: &Theme,
         options: ViewInitOptions,
     ) -> (Retained<Self>, Arc<ViewState>) {
         let (cell_width, cell_height) = renderer.cell_size();
-        let state = Arc::new(ViewState::default());
+        let state = Arc::new(ViewState {
+            title_filter: TitleFilter::new(&config.appearance),
+            tabs_config: config.tabs.clone(),
+            bell_escalation: Mutex::new(BellEscalation::from_config(&config.general)),
+            bell_limiter: Mutex::new(BellRateLimiter::from_config(&config.general)),
+            bell_sound: sound_for(&config.general, SoundEvent::Bell),
+            file_received_sound: sound_for(&config.general, SoundEvent::FileReceived),
+            ..Default::default()
+        });
         let frame = NSRect::new(NSPoint::ZERO, NSSize::new(800.0, 600.0));
 
+        {
+            let mut term = terminal.lock();
+            term.set_erase_sequences(config.general.backspace_sends, config.general.delete_sends);
+            term.set_home_end_style(config.general.home_end_style);
+            term.set_encoding(config.general.encoding);
+            term.set_local_echo(
+                config.general.local_echo,
+                std::time::Duration::from_millis(config.general.local_echo_threshold_ms),
+            );
+            term.screen_mut()
+                .set_inline_suggestions(config.general.inline_suggestions);
+            term.screen_mut().set_bidi(config.appearance.enable_bidi);
+            term.screen_mut()
+                .set_copy_hyperlink_mode(config.general.copy_hyperlink_mode);
+            term.screen_mut().set_tab_width(config.general.tab_width);
+            term.screen_mut()
+                .set_long_line_mode(config.general.long_line_mode);
+            term.screen_mut()
+                .set_grapheme_clusters(config.general.grapheme_clusters);
+            term.screen_mut()
+                .set_secret_matcher(config.general.secret_matcher());
+            term.screen_mut()
+                .set_reveal_concealed(config.general.reveal_concealed);
+            term.set_unknown_sequence_mode(config.general.unknown_sequence_mode);
+            term.set_print_control_as_caret(config.general.print_control_as_caret);
+            term.set_max_file_transfer_bytes(config.general.file_transfer_limit());
+            term.set_search_follows_output(config.general.search_follows_output);
+            term.set_cursor_visibility_per_screen(config.general.cursor_visibility_per_screen);
+            term.set_max_images(config.general.max_scrollback_images);
+            term.set_scroll_to_bottom_on(&config.general.scroll_to_bottom_on);
+            term.set_clear_selection_on(config.general.clear_selection_on);
+        }
+        renderer.set_cursor_line_highlight(config.appearance.highlight_cursor_line);
+        renderer.set_show_badge(config.appearance.show_badge);
+        renderer.set_faint_opacity(config.appearance.faint_opacity);
+        renderer.set_background_image(&config.appearance);
+        renderer.set_minimap(config.appearance.minimap);
+        renderer.set_scroll_position(config.appearance.show_scroll_position);
+        renderer.set_ruler_columns(&config.appearance.ruler_columns);
+
         let this = mtm.alloc::<Self>();
         let this = this.set_ivars(TerminalViewIvars {
             terminal: terminal.clone(),
@@ -1248,6 +1753,11 @@ impl TerminalView {
             cell_height,
             state: state.clone(),
             is_selecting: Cell::new(false),
+            reporting_drag: Cell::new(false),
+            shortcuts: ShortcutManager::from_config(&config.shortcuts),
+            minimap: config.appearance.minimap,
+            wheel_scrolls_history: config.general.wheel_scrolls_history,
+            minimap_dragging: Cell::new(false),
             template_name: RefCell::new(options.template_name),
             #[cfg(unix)]
             watchdog_fd_id: Cell::new(options.watchdog_fd_id),
@@ -1255,6 +1765,22 @@ impl TerminalView {
             notification_bar: RefCell::new(None),
             file_manager: RefCell::new(PendingFileManager::new()),
             color_palette: theme.colors.clone(),
+            faint_opacity: config.appearance.faint_opacity,
+            click_counter: RefCell::new(
+                config
+                    .general
+                    .multi_click_ms
+                    .map(|ms| ClickCounter::new(std::time::Duration::from_millis(ms))),
+            ),
+            autoscroll_timer: RefCell::new(None),
+            autoscroll_point: Cell::new(NSPoint::new(0.0, 0.0)),
+            selection_autoscroll_speed: config.general.selection_autoscroll_speed,
+            paste_strip_trailing_newline: config.general.paste_strip_trailing_newline,
+            confirm_paste_into: config.general.confirm_paste_into.clone(),
+            incognito: config.general.no_scrollback,
+            general: config.general.clone(),
+            find_options: RefCell::new(FindOptions::default()),
+            replay: RefCell::new(None),
         });
 
         let this: Retained<Self> = unsafe { msg_send![super(this), initWithFrame: frame] };
@@ -1282,21 +1808,24 @@ impl TerminalView {
         theme: &Theme,
         cwd: Option<String>,
     ) -> Retained<Self> {
-        let renderer = CGRenderer::new(
-            mtm,
-            &config.appearance.font.family,
-            config.appearance.font.size,
-            theme,
-        );
+        let renderer = CGRenderer::new(mtm, &config.appearance.font, theme);
         let (cell_width, cell_height) = renderer.cell_size();
 
-        let mut terminal = Terminal::new(80, 24, ScreenConfig::default());
-        terminal.screen_mut().set_cell_height_hint(cell_height);
-        terminal.screen_mut().set_cell_width_hint(cell_width);
+        let screen_config = ScreenConfig {
+            scrollback_lines: config.general.effective_scrollback_lines(),
+        };
+        let mut terminal = Terminal::new(80, 24, screen_config);
+        terminal.set_cell_size(cell_width, cell_height);
         let terminal = Arc::new(Mutex::new(terminal));
 
-        let (this, state) =
-            Self::init_view(mtm, renderer, terminal, theme, ViewInitOptions::default());
+        let (this, state) = Self::init_view(
+            mtm,
+            renderer,
+            terminal,
+            config,
+            theme,
+            ViewInitOptions::default(),
+        );
 
         let view_ptr = &*this as *const _ as usize;
         this.spawn_shell(config, state.clone(), cwd);
@@ -1313,27 +1842,29 @@ impl TerminalView {
         theme: &Theme,
         template: &cterm_app::config::StickyTabConfig,
     ) -> Retained<Self> {
-        let mut renderer = CGRenderer::new(
-            mtm,
-            &config.appearance.font.family,
-            config.appearance.font.size,
-            theme,
-        );
+        let mut config = config.clone();
+        config.general.no_scrollback |= template.no_scrollback;
+        let config = &config;
+        let theme = &template.resolve_theme(theme);
+
+        let mut renderer = CGRenderer::new(mtm, &config.appearance.font, theme);
         if let Some(ref bg_color) = template.background_color {
             renderer.set_background_override(Some(bg_color));
         }
         let (cell_width, cell_height) = renderer.cell_size();
 
-        let mut terminal = Terminal::new(80, 24, ScreenConfig::default());
-        terminal.screen_mut().set_cell_height_hint(cell_height);
-        terminal.screen_mut().set_cell_width_hint(cell_width);
+        let screen_config = ScreenConfig {
+            scrollback_lines: config.general.effective_scrollback_lines(),
+        };
+        let mut terminal = Terminal::new(80, 24, screen_config);
+        terminal.set_cell_size(cell_width, cell_height);
         let terminal = Arc::new(Mutex::new(terminal));
 
         let options = ViewInitOptions {
             template_name: Some(template.name.clone()),
             ..Default::default()
         };
-        let (this, state) = Self::init_view(mtm, renderer, terminal, theme, options);
+        let (this, state) = Self::init_view(mtm, renderer, terminal, config, theme, options);
 
         let view_ptr = &*this as *const _ as usize;
         this.spawn_template_shell(config, template, state.clone());
@@ -1355,12 +1886,7 @@ impl TerminalView {
     ) -> Retained<Self> {
         use cterm_core::screen::{Screen, ScreenConfig};
 
-        let renderer = CGRenderer::new(
-            mtm,
-            &config.appearance.font.family,
-            config.appearance.font.size,
-            theme,
-        );
+        let renderer = CGRenderer::new(mtm, &config.appearance.font, theme);
         let (cell_width, cell_height) = renderer.cell_size();
 
         let mut screen = Screen::new(80, 24, ScreenConfig::default());
@@ -1376,7 +1902,8 @@ impl TerminalView {
             watchdog_fd_id: recovered.id,
             ..Default::default()
         };
-        let (this, state) = Self::init_view(mtm, renderer, terminal.clone(), theme, options);
+        let (this, state) =
+            Self::init_view(mtm, renderer, terminal.clone(), config, theme, options);
 
         let view_ptr = &*this as *const _ as usize;
         if let Some(reader) = pty_reader {
@@ -1409,16 +1936,10 @@ impl TerminalView {
         theme: &Theme,
         mut terminal: Terminal,
     ) -> Retained<Self> {
-        let renderer = CGRenderer::new(
-            mtm,
-            &config.appearance.font.family,
-            config.appearance.font.size,
-            theme,
-        );
+        let renderer = CGRenderer::new(mtm, &config.appearance.font, theme);
         let (cell_width, cell_height) = renderer.cell_size();
 
-        terminal.screen_mut().set_cell_height_hint(cell_height);
-        terminal.screen_mut().set_cell_width_hint(cell_width);
+        terminal.set_cell_size(cell_width, cell_height);
         let pty_reader = terminal.pty_reader();
         let terminal = Arc::new(Mutex::new(terminal));
 
@@ -1426,6 +1947,7 @@ impl TerminalView {
             mtm,
             renderer,
             terminal.clone(),
+            config,
             theme,
             ViewInitOptions::default(),
         );
@@ -1471,6 +1993,9 @@ impl TerminalView {
                                 unsafe {
                                     let view = &*(view_ptr as *const TerminalView);
                                     if let Some(window) = view.window() {
+                                        let cterm_window = &*(Retained::as_ptr(&window)
+                                            as *const crate::window::CtermWindow);
+                                        cterm_window.open_replacement_if_last_tab();
                                         window.close();
                                     }
                                 }
@@ -1480,14 +2005,19 @@ impl TerminalView {
                     break;
                 }
 
-                // Check for title change (only if title is not locked by user/template)
+                // Check for title change (subject to the title update mode,
+                // ignore patterns and whether the title is locked by user/template)
                 if state.title_changed.swap(false, Ordering::Relaxed) {
-                    // Only update if title is not locked and view is still valid
-                    if !state.title_locked.load(Ordering::Relaxed)
+                    let new_title = state.title.read().map(|t| t.clone()).unwrap_or_default();
+                    let locked = state.title_locked.load(Ordering::Relaxed);
+                    // Only update if the filter accepts it and view is still valid
+                    if state.title_filter.accepts(&new_title, locked)
                         && !state.view_invalid.load(Ordering::SeqCst)
                     {
-                        // Get the new title
-                        let new_title = state.title.read().map(|t| t.clone()).unwrap_or_default();
+                        let directory = state.directory.read().ok().and_then(|d| d.clone());
+                        let new_title = state
+                            .tabs_config
+                            .format_title(&new_title, directory.as_deref());
                         let state_clone = state.clone();
                         #[allow(deprecated)]
                         dispatch2::Queue::main().exec_async(move || {
@@ -1495,7 +2025,15 @@ impl TerminalView {
                                 unsafe {
                                     let view = &*(view_ptr as *const TerminalView);
                                     if let Some(window) = view.window() {
-                                        window.setTitle(&NSString::from_str(&new_title));
+                                        let mut title = if view.is_incognito() {
+                                            format!("{}{}", INCOGNITO_TITLE_PREFIX, new_title)
+                                        } else {
+                                            new_title
+                                        };
+                                        if view.is_recording() {
+                                            title.insert_str(0, &recording_title_prefix());
+                                        }
+                                        window.setTitle(&NSString::from_str(&title));
                                     }
                                 }
                             }
@@ -1506,11 +2044,15 @@ impl TerminalView {
                 // Check for bell
                 if state.bell_changed.swap(false, Ordering::Relaxed)
                     && !state.view_invalid.load(Ordering::SeqCst)
+                    && state.bell_limiter.lock().allow(Instant::now())
                 {
                     let state_clone = state.clone();
                     #[allow(deprecated)]
                     dispatch2::Queue::main().exec_async(move || {
                         if !state_clone.view_invalid.load(Ordering::SeqCst) && view_ptr != 0 {
+                            if let Some(ref sound) = state_clone.bell_sound {
+                                desktop_notification::play_sound(sound);
+                            }
                             unsafe {
                                 let view = &*(view_ptr as *const TerminalView);
                                 if let Some(window) = view.window() {
@@ -1530,6 +2072,18 @@ impl TerminalView {
                                         let cterm_window: &crate::window::CtermWindow =
                                             &*window_ptr;
                                         cterm_window.set_bell(true);
+
+                                        // Escalate repeated bells to a notification
+                                        if state_clone.bell_escalation.lock().ring(Instant::now()) {
+                                            let title = state_clone
+                                                .title
+                                                .read()
+                                                .map(|t| t.clone())
+                                                .unwrap_or_default();
+                                            desktop_notification::show(&bell_notification(&title));
+                                        }
+                                    } else {
+                                        state_clone.bell_escalation.lock().reset();
                                     }
                                     // Request attention in the dock
                                     let app = NSApplication::sharedApplication(
@@ -1555,7 +2109,7 @@ impl TerminalView {
                             if !state_clone.view_invalid.load(Ordering::SeqCst) && view_ptr != 0 {
                                 unsafe {
                                     let view = &*(view_ptr as *const TerminalView);
-                                    let _: () = msg_send![view, setNeedsDisplay: true];
+                                    view.invalidate_damage();
                                 }
                             }
                         });
@@ -1577,17 +2131,14 @@ impl TerminalView {
         let terminal = self.ivars().terminal.clone();
 
         let pty_config = PtyConfig {
-            size: PtySize {
-                cols: 80,
-                rows: 24,
-                pixel_width: 0,
-                pixel_height: 0,
-            },
+            size: terminal.lock().pty_size(),
             shell: Some(shell.clone()),
             args,
             cwd: cwd.map(std::path::PathBuf::from),
             env: Vec::new(),
             term: config.general.term.clone(),
+            advertise_truecolor: config.general.advertise_truecolor,
+            raw: config.general.raw_pty,
         };
 
         match Pty::new(&pty_config) {
@@ -1656,17 +2207,14 @@ impl TerminalView {
         let terminal = self.ivars().terminal.clone();
 
         let pty_config = PtyConfig {
-            size: PtySize {
-                cols: 80,
-                rows: 24,
-                pixel_width: 0,
-                pixel_height: 0,
-            },
+            size: terminal.lock().pty_size(),
             shell: Some(shell.clone()),
             args,
             cwd,
             env,
             term: config.general.term.clone(),
+            advertise_truecolor: config.general.advertise_truecolor,
+            raw: false,
         };
 
         match Pty::new(&pty_config) {
@@ -1708,6 +2256,16 @@ impl TerminalView {
         }
     }
 
+    /// Whether this is an incognito terminal (no scrollback, never saved)
+    pub fn is_incognito(&self) -> bool {
+        self.ivars().incognito
+    }
+
+    /// Whether the session is being recorded to a file
+    pub fn is_recording(&self) -> bool {
+        self.ivars().terminal.lock().recording_path().is_some()
+    }
+
     /// Get the template name (if this view was created from a template)
     pub fn template_name(&self) -> Option<String> {
         self.ivars().template_name.borrow().clone()
@@ -1734,6 +2292,38 @@ impl TerminalView {
             .store(locked, std::sync::atomic::Ordering::Relaxed);
     }
 
+    /// Process output from the program and flag what the main thread has to
+    /// update (runs on the reader thread)
+    fn handle_output(terminal: &Mutex<Terminal>, state: &ViewState, data: &[u8]) {
+        let mut term = terminal.lock();
+        let events = term.process(data);
+
+        // Check for terminal events
+        for event in events {
+            match event {
+                TerminalEvent::TitleChanged(ref title) => {
+                    // Update stored title
+                    if let Ok(mut current_title) = state.title.write() {
+                        *current_title = title.clone();
+                    }
+                    if let Ok(mut directory) = state.directory.write() {
+                        *directory = term.display_directory();
+                    }
+                    state.title_changed.store(true, Ordering::Relaxed);
+                }
+                TerminalEvent::Bell => {
+                    state.bell_changed.store(true, Ordering::Relaxed);
+                }
+                _ => {}
+            }
+        }
+
+        drop(term);
+
+        // Signal that we need a redraw
+        state.needs_redraw.store(true, Ordering::Relaxed);
+    }
+
     /// Background thread to read from PTY
     fn read_pty_loop(pty_fd: i32, terminal: Arc<Mutex<Terminal>>, state: Arc<ViewState>) {
         use std::io::Read;
@@ -1748,32 +2338,7 @@ impl TerminalView {
                     log::info!("PTY closed (EOF)");
                     break;
                 }
-                Ok(n) => {
-                    let mut term = terminal.lock();
-                    let events = term.process(&buf[..n]);
-
-                    // Check for terminal events
-                    for event in events {
-                        match event {
-                            TerminalEvent::TitleChanged(ref title) => {
-                                // Update stored title
-                                if let Ok(mut current_title) = state.title.write() {
-                                    *current_title = title.clone();
-                                }
-                                state.title_changed.store(true, Ordering::Relaxed);
-                            }
-                            TerminalEvent::Bell => {
-                                state.bell_changed.store(true, Ordering::Relaxed);
-                            }
-                            _ => {}
-                        }
-                    }
-
-                    drop(term);
-
-                    // Signal that we need a redraw
-                    state.needs_redraw.store(true, Ordering::Relaxed);
-                }
+                Ok(n) => Self::handle_output(&terminal, &state, &buf[..n]),
                 Err(e) => {
                     if e.kind() != std::io::ErrorKind::Interrupted {
                         log::error!("PTY read error: {}", e);
@@ -1811,28 +2376,7 @@ impl TerminalView {
                     log::info!("PTY closed (EOF) - reader loop");
                     break;
                 }
-                Ok(n) => {
-                    let mut term = terminal.lock();
-                    let events = term.process(&buf[..n]);
-
-                    for event in events {
-                        match event {
-                            TerminalEvent::TitleChanged(ref title) => {
-                                if let Ok(mut current_title) = state.title.write() {
-                                    *current_title = title.clone();
-                                }
-                                state.title_changed.store(true, Ordering::Relaxed);
-                            }
-                            TerminalEvent::Bell => {
-                                state.bell_changed.store(true, Ordering::Relaxed);
-                            }
-                            _ => {}
-                        }
-                    }
-
-                    drop(term);
-                    state.needs_redraw.store(true, Ordering::Relaxed);
-                }
+                Ok(n) => Self::handle_output(&terminal, &state, &buf[..n]),
                 Err(e) => {
                     if e.kind() != std::io::ErrorKind::Interrupted {
                         log::error!("PTY read error (reader loop): {}", e);
@@ -1868,7 +2412,12 @@ impl TerminalView {
             return;
         }
 
-        let cols = (frame.size.width / cell_width).floor() as usize;
+        let minimap_width = if self.ivars().minimap {
+            MINIMAP_WIDTH
+        } else {
+            0.0
+        };
+        let cols = ((frame.size.width - minimap_width) / cell_width).floor() as usize;
         let rows = (frame.size.height / cell_height).floor() as usize;
 
         if cols > 0 && rows > 0 {
@@ -1884,11 +2433,17 @@ impl TerminalView {
         if let Err(e) = terminal.write(data) {
             log::error!("Failed to write to PTY: {}", e);
         }
+        // Draw predicted echo now rather than with the next output
+        let local_echo = terminal.screen().local_echo();
+        drop(terminal);
+        if local_echo {
+            self.invalidate_damage();
+        }
     }
 
     /// Handle a drop operation — extract file URL, show dialog, write to PTY
     fn handle_drop(&self, sender: &AnyObject) -> bool {
-        use cterm_app::file_drop::{build_pty_input, FileDropAction, FileDropInfo};
+        use cterm_app::file_drop::{build_pty_input, paste_paths, FileDropAction, FileDropInfo};
         use objc2_app_kit::NSPasteboard;
 
         let mtm = MainThreadMarker::from(self);
@@ -1908,17 +2463,22 @@ impl TerminalView {
         let Some(urls) = urls else {
             return false;
         };
-        if urls.count() == 0 {
-            return false;
-        }
+        let paths: Vec<std::path::PathBuf> = (0..urls.count())
+            .filter_map(|i| {
+                let url: Retained<AnyObject> = unsafe { msg_send![&*urls, objectAtIndex: i] };
+                let path_str: Option<Retained<NSString>> = unsafe { msg_send![&*url, path] };
+                path_str.map(|p| std::path::PathBuf::from(p.to_string()))
+            })
+            .collect();
 
-        // Get the first URL
-        let url: Retained<AnyObject> = unsafe { msg_send![&*urls, objectAtIndex: 0usize] };
-        let path_str: Option<Retained<NSString>> = unsafe { msg_send![&*url, path] };
-        let Some(path_str) = path_str else {
+        // Several files, or no dialog wanted: paste the quoted paths
+        if paths.len() > 1 || (paths.len() == 1 && !self.ivars().general.file_drop_dialog) {
+            self.write_to_pty(paste_paths(&paths).as_bytes());
+            return true;
+        }
+        let Some(path) = paths.into_iter().next() else {
             return false;
         };
-        let path = std::path::PathBuf::from(path_str.to_string());
 
         let info = match FileDropInfo::from_path(&path) {
             Ok(info) => info,
@@ -1948,13 +2508,8 @@ impl TerminalView {
             Ok(text) => {
                 if use_bracketed {
                     let terminal = self.ivars().terminal.lock();
-                    let bracketed = terminal.screen().modes.bracketed_paste;
+                    let paste = self.ivars().general.bracketed_paste_text(&text, &terminal);
                     drop(terminal);
-                    let paste = if bracketed {
-                        format!("\x1b[200~{}\x1b[201~", text)
-                    } else {
-                        text
-                    };
                     self.write_to_pty(paste.as_bytes());
                 } else {
                     self.write_to_pty(text.as_bytes());
@@ -2002,23 +2557,111 @@ impl TerminalView {
         self.ivars().terminal.lock().foreground_process_name()
     }
 
-    /// Get the current working directory of the foreground process (if any)
+    /// Get the directory to start new shells in: the foreground process's,
+    /// or else the one reported by the shell (OSC 7)
     #[cfg(unix)]
-    pub fn foreground_cwd(&self) -> Option<String> {
+    pub fn working_directory(&self) -> Option<String> {
         self.ivars()
             .terminal
             .lock()
-            .foreground_cwd()
+            .working_directory()
             .map(|p| p.to_string_lossy().into_owned())
     }
 
     /// Request display update
+    /// Lines to auto-scroll for the last drag location (negative scrolls up)
+    fn selection_autoscroll_step(&self) -> isize {
+        selection_autoscroll_lines(
+            self.ivars().autoscroll_point.get().y,
+            self.bounds().size.height,
+            self.ivars().cell_height,
+            self.ivars().selection_autoscroll_speed,
+        )
+    }
+
+    /// Stop the selection auto-scroll timer, if running
+    fn stop_selection_autoscroll(&self) {
+        if let Some(timer) = self.ivars().autoscroll_timer.borrow_mut().take() {
+            timer.invalidate();
+        }
+    }
+
     fn set_needs_display(&self) {
         unsafe {
             let _: () = msg_send![self, setNeedsDisplay: true];
         }
     }
 
+    /// Drop any IME composition and tell the input context to start over
+    ///
+    /// Used after a seamless upgrade: the view is new, but AppKit may still
+    /// hold composition state from while the tabs were being rebuilt.
+    pub fn reset_input_context(&self) {
+        self.ivars().marked_text.borrow_mut().clear();
+        unsafe {
+            let context: *mut AnyObject = msg_send![self, inputContext];
+            if !context.is_null() {
+                let _: () = msg_send![context, discardMarkedText];
+                let _: () = msg_send![context, invalidateCharacterCoordinates];
+            }
+        }
+        self.set_needs_display();
+    }
+
+    /// Request a redraw of only the rows that changed since the last frame
+    fn invalidate_damage(&self) {
+        let damage = self.ivars().terminal.lock().screen_mut().take_damage();
+        if damage.is_full() {
+            self.set_needs_display();
+            return;
+        }
+
+        let bounds: NSRect = unsafe { msg_send![self, bounds] };
+        let cell_height = self.ivars().cell_height;
+        for row in damage.rows() {
+            let rect = NSRect::new(
+                NSPoint::new(0.0, row as f64 * cell_height),
+                NSSize::new(bounds.size.width, cell_height),
+            );
+            unsafe {
+                let _: () = msg_send![self, setNeedsDisplayInRect: rect];
+            }
+        }
+
+        // New lines change the whole minimap, not just the damaged rows
+        if self.ivars().minimap && !damage.is_empty() {
+            let rect = NSRect::new(
+                NSPoint::new(bounds.size.width - MINIMAP_WIDTH, 0.0),
+                NSSize::new(MINIMAP_WIDTH, bounds.size.height),
+            );
+            unsafe {
+                let _: () = msg_send![self, setNeedsDisplayInRect: rect];
+            }
+        }
+
+        // New lines while scrolled back change the scroll position label
+        // in the bottom-right corner
+        if !damage.is_empty() && self.ivars().terminal.lock().screen().scroll_offset > 0 {
+            let rect = NSRect::new(
+                NSPoint::new(0.0, bounds.size.height - cell_height * 3.0),
+                NSSize::new(bounds.size.width, cell_height * 3.0),
+            );
+            unsafe {
+                let _: () = msg_send![self, setNeedsDisplayInRect: rect];
+            }
+        }
+    }
+
+    /// Scroll so the line under `y` in the minimap is in the middle of the view
+    fn minimap_jump(&self, y: f64) {
+        let height = self.frame().size.height;
+        let mut terminal = self.ivars().terminal.lock();
+        let line = minimap_line_at(terminal.screen(), y, height);
+        terminal.scroll_to_line(line);
+        drop(terminal);
+        self.set_needs_display();
+    }
+
     /// Get frame rectangle
     fn frame(&self) -> NSRect {
         unsafe { msg_send![self, frame] }
@@ -2041,12 +2684,62 @@ impl TerminalView {
         }
     }
 
+    /// Paste text into the terminal, honoring the paste settings
+    fn paste_text(&self, mut text: String) {
+        // Leave the command at the prompt instead of running it
+        if self.ivars().paste_strip_trailing_newline {
+            text.truncate(text.trim_end_matches(['\r', '\n']).len());
+        }
+
+        // Don't dump the clipboard into ssh, a database prompt etc.
+        // without asking first
+        if let Some(process) = self
+            .foreground_process_name()
+            .filter(|p| self.ivars().confirm_paste_into.contains(p))
+        {
+            let lines = text.lines().count().max(1);
+            let preview: Vec<&str> = text.lines().take(5).collect();
+            let confirmed = crate::dialogs::show_confirm(
+                MainThreadMarker::from(self),
+                self.window().as_deref(),
+                &format!("Paste {} line(s) into \"{}\"?", lines, process),
+                &preview.join("\n"),
+            );
+            if !confirmed {
+                return;
+            }
+        }
+
+        // Use bracketed paste if the program enabled it
+        let terminal = self.ivars().terminal.lock();
+        let text = self.ivars().general.quote_pasted_path(&text, &terminal);
+        let paste_text = self.ivars().general.bracketed_paste_text(&text, &terminal);
+        drop(terminal);
+
+        self.write_to_pty(paste_text.as_bytes());
+    }
+
     /// Copy current selection to clipboard
+    ///
+    /// Asks first when the selection contains a masked secret.
     pub fn copy_selection(&self) {
         let terminal = self.ivars().terminal.lock();
         if let Some(text) = terminal.screen().get_selected_text() {
+            let has_secret = terminal.screen().selection_has_secret();
             drop(terminal);
+            if has_secret
+                && !crate::dialogs::show_confirm(
+                    objc2_foundation::MainThreadMarker::from(self),
+                    None,
+                    "Copy Secret?",
+                    "The selection looks like it contains a secret (a key or token). \
+                     Copy it anyway?",
+                )
+            {
+                return;
+            }
             clipboard::set_text(&text);
+            cterm_app::clipboard_history::record(&text, self.is_incognito());
             log::debug!("Copied {} chars to clipboard", text.len());
         }
     }
@@ -2101,6 +2794,8 @@ impl TerminalView {
             alternate_active: screen.alternate_grid().is_some(),
             cursor_style: screen.cursor.style,
             mouse_mode: screen.modes.mouse_mode,
+            icon_name: screen.icon_name.clone(),
+            badge_format: screen.badge_format().map(str::to_string),
         }
     }
 
@@ -2197,6 +2892,12 @@ impl TerminalView {
         let transfers = terminal.screen_mut().take_file_transfers();
         drop(terminal);
 
+        if !transfers.is_empty() {
+            if let Some(ref sound) = self.ivars().state.file_received_sound {
+                desktop_notification::play_sound(sound);
+            }
+        }
+
         for transfer in transfers {
             match transfer {
                 cterm_core::FileTransferOperation::FileReceived { id, name, data } => {
@@ -2420,6 +3121,26 @@ impl TerminalView {
         }
     }
 
+    /// Render the selection, or the visible screen if nothing is selected,
+    /// as a PNG
+    fn render_image(&self) -> Option<Vec<u8>> {
+        let region = {
+            let terminal = self.ivars().terminal.lock();
+            let screen = terminal.screen();
+            screen
+                .selected_region()
+                .unwrap_or_else(|| screen.visible_region())
+        };
+        if region.is_empty() {
+            return None;
+        }
+        let scale = self.window().map(|w| w.backingScaleFactor()).unwrap_or(2.0);
+        let renderer = self.ivars().renderer.borrow();
+        renderer
+            .as_ref()?
+            .render_region_png(MainThreadMarker::from(self), &region, scale)
+    }
+
     /// Copy an image to the pasteboard
     fn copy_image_to_pasteboard(&self, image: &cterm_core::TerminalImage) {
         use objc2_app_kit::{NSBitmapImageRep, NSPasteboard};
@@ -2514,7 +3235,7 @@ impl TerminalView {
             scrollback_lines: 10000, // Default, could get from config
         };
 
-        let restored_screen = cterm_core::Screen::from_upgrade_state(
+        let mut restored_screen = cterm_core::Screen::from_upgrade_state(
             state.grid.clone(),
             state.scrollback.clone(),
             state.alternate_grid.clone(),
@@ -2529,6 +3250,7 @@ impl TerminalView {
             state.tab_stops.clone(),
             screen_config,
         );
+        state.apply_display_hints(&mut restored_screen);
 
         // Replace the terminal's screen with the restored one
         terminal.restore_screen(restored_screen);
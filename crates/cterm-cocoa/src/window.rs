@@ -3,6 +3,7 @@
 //! Handles NSWindow creation and management using native macOS window tabbing.
 
 use std::cell::RefCell;
+use std::path::PathBuf;
 
 use objc2::rc::Retained;
 use objc2::runtime::ProtocolObject;
@@ -15,14 +16,20 @@ use objc2_foundation::{
     MainThreadMarker, NSArray, NSNotification, NSObjectProtocol, NSPoint, NSRect, NSSize, NSString,
 };
 
-use cterm_app::config::Config;
+use cterm_app::config::{AppearanceConfig, CloseLastTab, Config};
+use cterm_app::session::{ClosedWindows, TabSessionState, WindowGeometry, WindowSessionState};
 use cterm_app::shortcuts::ShortcutManager;
 use cterm_ui::theme::Theme;
 
 use cterm_core::Terminal;
 
 use crate::quick_open::{OpenTabEntry, QuickOpenOverlay, QUICK_OPEN_HEIGHT};
-use crate::terminal_view::TerminalView;
+use crate::terminal_view::{TerminalView, INCOGNITO_TITLE_PREFIX};
+
+thread_local! {
+    /// Recently closed windows for "Reopen Closed Window"
+    static CLOSED_WINDOWS: RefCell<ClosedWindows> = RefCell::new(ClosedWindows::new());
+}
 
 /// Window state stored in ivars
 pub struct CtermWindowIvars {
@@ -34,6 +41,9 @@ pub struct CtermWindowIvars {
     quick_open: RefCell<Option<Retained<QuickOpenOverlay>>>,
     /// Whether this window has an active bell notification
     has_active_bell: std::cell::Cell<bool>,
+    /// Set while the Close Tab action closes this window, so closing the
+    /// last tab can open a replacement (`close_last_tab = "new_tab"`)
+    closing_as_tab: std::cell::Cell<bool>,
 }
 
 define_class!(
@@ -84,24 +94,14 @@ define_class!(
 
         #[unsafe(method(windowShouldClose:))]
         fn window_should_close(&self, _sender: &NSWindow) -> objc2::runtime::Bool {
-            // Check if config says to confirm close with running processes
-            if !self.ivars().config.general.confirm_close_with_running {
-                return objc2::runtime::Bool::YES;
-            }
-
-            // Check if there's a foreground process running
-            #[cfg(unix)]
-            if let Some(terminal) = self.ivars().active_terminal.borrow().as_ref() {
-                if terminal.has_foreground_process() {
-                    let process_name = terminal
-                        .foreground_process_name()
-                        .unwrap_or_else(|| "a process".to_string());
-
-                    // Show confirmation dialog
-                    return objc2::runtime::Bool::new(self.show_close_confirmation(&process_name));
+            let should_close = self.confirm_close();
+            if should_close {
+                self.remember_closed_window();
+                if self.ivars().closing_as_tab.get() {
+                    self.open_replacement_if_last_tab();
                 }
             }
-            objc2::runtime::Bool::YES
+            objc2::runtime::Bool::new(should_close)
         }
 
         #[unsafe(method(windowWillClose:))]
@@ -137,7 +137,12 @@ define_class!(
     impl CtermWindow {
         #[unsafe(method(newTab:))]
         fn action_new_tab(&self, _sender: Option<&objc2::runtime::AnyObject>) {
-            self.create_new_tab();
+            self.create_new_tab(false);
+        }
+
+        #[unsafe(method(newIncognitoTab:))]
+        fn action_new_incognito_tab(&self, _sender: Option<&objc2::runtime::AnyObject>) {
+            self.create_new_tab(true);
         }
 
         #[unsafe(method(closeTab:))]
@@ -145,6 +150,11 @@ define_class!(
             self.close_current_tab();
         }
 
+        #[unsafe(method(reopenClosedWindow:))]
+        fn action_reopen_closed_window(&self, _sender: Option<&objc2::runtime::AnyObject>) {
+            self.reopen_closed_window();
+        }
+
         /// Called by macOS native tabbing when Command-T or tab bar + is pressed.
         /// Returns a new default window (not a template duplicate).
         #[unsafe(method(newWindowForTab:))]
@@ -158,7 +168,7 @@ define_class!(
                 .active_terminal
                 .borrow()
                 .as_ref()
-                .and_then(|t| t.foreground_cwd());
+                .and_then(|t| t.working_directory());
             #[cfg(not(unix))]
             let cwd: Option<String> = None;
 
@@ -258,6 +268,17 @@ define_class!(
         fn action_window_bottom_right_quarter(&self, _sender: Option<&objc2::runtime::AnyObject>) {
             self.position_bottom_right_quarter();
         }
+
+        // Opacity actions
+        #[unsafe(method(increaseOpacity:))]
+        fn action_increase_opacity(&self, _sender: Option<&objc2::runtime::AnyObject>) {
+            self.step_opacity(true);
+        }
+
+        #[unsafe(method(decreaseOpacity:))]
+        fn action_decrease_opacity(&self, _sender: Option<&objc2::runtime::AnyObject>) {
+            self.step_opacity(false);
+        }
     }
 );
 
@@ -296,6 +317,7 @@ impl CtermWindow {
             pending_tab_color: RefCell::new(pending_tab_color),
             quick_open: RefCell::new(None),
             has_active_bell: std::cell::Cell::new(false),
+            closing_as_tab: std::cell::Cell::new(false),
         });
 
         let this: Retained<Self> = unsafe {
@@ -312,6 +334,7 @@ impl CtermWindow {
         this.setMinSize(NSSize::new(400.0, 200.0));
         unsafe { this.setReleasedWhenClosed(false) };
         this.setTabbingMode(NSWindowTabbingMode::Preferred);
+        this.setAlphaValue(config.appearance.opacity);
         this.setDelegate(Some(ProtocolObject::from_ref(&*this)));
 
         this
@@ -319,12 +342,42 @@ impl CtermWindow {
 
     /// Attach a terminal view to this window as content and store it
     fn attach_terminal_view(&self, terminal: Retained<TerminalView>) {
+        if terminal.is_incognito() {
+            let title: Retained<NSString> = unsafe { msg_send![self, title] };
+            let title = format!("{}{}", INCOGNITO_TITLE_PREFIX, title);
+            self.setTitle(&NSString::from_str(&title));
+        }
         self.setContentView(Some(&terminal));
-        let (cell_width, cell_height) = terminal.cell_size();
-        self.setContentResizeIncrements(NSSize::new(cell_width, cell_height));
+        if self.ivars().config.appearance.resize_snap {
+            let (cell_width, cell_height) = terminal.cell_size();
+            self.setContentResizeIncrements(NSSize::new(cell_width, cell_height));
+        }
         *self.ivars().active_terminal.borrow_mut() = Some(terminal);
     }
 
+    /// Give the active terminal keyboard focus with a clean IME state
+    ///
+    /// Restored tabs otherwise keep a stale text input context until focus
+    /// moves away and back, which breaks IME composition.
+    pub fn reset_terminal_input(&self) {
+        if let Some(terminal) = self.ivars().active_terminal.borrow().as_ref() {
+            terminal.reset_input_context();
+            self.makeFirstResponder(Some(terminal));
+        }
+    }
+
+    /// Make the window more or less opaque, saving it if configured
+    fn step_opacity(&self, increase: bool) {
+        let opacity = AppearanceConfig::step_opacity(self.alphaValue(), increase);
+        self.setAlphaValue(opacity);
+
+        if self.ivars().config.appearance.persist_opacity {
+            if let Err(e) = cterm_app::config::save_opacity(opacity) {
+                log::error!("Failed to save opacity: {}", e);
+            }
+        }
+    }
+
     pub fn new(mtm: MainThreadMarker, config: &Config, theme: &Theme) -> Retained<Self> {
         Self::new_with_cwd(mtm, config, theme, None)
     }
@@ -379,7 +432,10 @@ impl CtermWindow {
     }
 
     /// Create a new tab (using native macOS window tabbing)
-    pub fn create_new_tab(&self) {
+    ///
+    /// Incognito tabs keep no scrollback and are never saved. New tabs opened
+    /// from an incognito tab are incognito as well.
+    pub fn create_new_tab(&self, incognito: bool) {
         let mtm = MainThreadMarker::from(self);
 
         // Get the current working directory from the active terminal
@@ -389,13 +445,14 @@ impl CtermWindow {
             .active_terminal
             .borrow()
             .as_ref()
-            .and_then(|t| t.foreground_cwd());
+            .and_then(|t| t.working_directory());
         #[cfg(not(unix))]
         let cwd: Option<String> = None;
 
         // Create a new window with the same configuration and inherited cwd
-        let new_window =
-            CtermWindow::new_with_cwd(mtm, &self.ivars().config, &self.ivars().theme, cwd);
+        let mut config = self.ivars().config.clone();
+        config.general.no_scrollback |= incognito;
+        let new_window = CtermWindow::new_with_cwd(mtm, &config, &self.ivars().theme, cwd);
 
         // Register with AppDelegate for tracking (important for relaunch/upgrade)
         let app = NSApplication::sharedApplication(mtm);
@@ -417,7 +474,25 @@ impl CtermWindow {
         // With native tabbing, just close the window
         // macOS will handle showing the next tab
         // Use performClose to trigger windowShouldClose: delegate method
+        self.ivars().closing_as_tab.set(true);
         self.performClose(None);
+        self.ivars().closing_as_tab.set(false);
+    }
+
+    /// Whether this window is the only tab in its tab group
+    fn is_last_tab(&self) -> bool {
+        let tabbed_windows: Option<Retained<NSArray<NSWindow>>> =
+            unsafe { msg_send![self, tabbedWindows] };
+        tabbed_windows.is_none_or(|windows| windows.count() <= 1)
+    }
+
+    /// Open a fresh tab next to this one if it is the last tab and
+    /// `close_last_tab` is `new_tab`, so the window stays open when it closes
+    pub fn open_replacement_if_last_tab(&self) {
+        if self.ivars().config.general.close_last_tab == CloseLastTab::NewTab && self.is_last_tab()
+        {
+            self.create_new_tab(false);
+        }
     }
 
     /// Get config reference
@@ -600,14 +675,16 @@ impl CtermWindow {
     ) -> Retained<Self> {
         let this = Self::init_window(mtm, config, theme, &template.name, template.color.clone());
 
-        // Prepare working directory (clone from git if needed)
+        // Prepare working directory (expand tokens, clone from git if needed)
+        let mut template = template.clone();
         if let Some(ref working_dir) = template.working_directory {
-            if let Err(e) =
-                cterm_app::prepare_working_directory(working_dir, template.git_remote.as_deref())
+            match cterm_app::prepare_working_directory(working_dir, template.git_remote.as_deref())
             {
-                log::error!("Failed to prepare working directory: {}", e);
+                Ok((dir, _)) => template.working_directory = Some(dir),
+                Err(e) => log::error!("Failed to prepare working directory: {}", e),
             }
         }
+        let template = &template;
 
         let terminal_view = TerminalView::from_template(mtm, config, theme, template);
         this.attach_terminal_view(terminal_view);
@@ -750,6 +827,121 @@ impl CtermWindow {
         }
     }
 
+    /// Check whether the window may close, asking if a process is still running
+    fn confirm_close(&self) -> bool {
+        // Check if config says to confirm close with running processes
+        if !self.ivars().config.general.confirm_close_with_running {
+            return true;
+        }
+
+        // Check if there's a foreground process running
+        #[cfg(unix)]
+        if let Some(terminal) = self.ivars().active_terminal.borrow().as_ref() {
+            if terminal.has_foreground_process() {
+                let process_name = terminal
+                    .foreground_process_name()
+                    .unwrap_or_else(|| "a process".to_string());
+
+                // Show confirmation dialog
+                return self.show_close_confirmation(&process_name);
+            }
+        }
+        true
+    }
+
+    /// Remember this window so it can be reopened with "Reopen Closed Window"
+    ///
+    /// Only user-initiated closes go through `windowShouldClose:`, so windows
+    /// closed because their shell exited are not remembered.
+    fn remember_closed_window(&self) {
+        if !self.ivars().config.general.remember_closed_windows {
+            return;
+        }
+        let Some(terminal) = self.active_terminal() else {
+            return;
+        };
+        if terminal.is_incognito() {
+            return;
+        }
+
+        #[cfg(unix)]
+        let cwd = terminal.working_directory().map(PathBuf::from);
+        #[cfg(not(unix))]
+        let cwd: Option<PathBuf> = None;
+
+        let title: Retained<NSString> = unsafe { msg_send![self, title] };
+        let title = title.to_string();
+        let title = title.strip_prefix("🔔 ").unwrap_or(&title).to_string();
+
+        let frame = self.frame();
+        let closed = WindowSessionState {
+            geometry: WindowGeometry {
+                x: frame.origin.x as i32,
+                y: frame.origin.y as i32,
+                width: frame.size.width as u32,
+                height: frame.size.height as u32,
+                maximized: false,
+            },
+            tabs: vec![TabSessionState {
+                template_name: terminal.template_name(),
+                custom_title: terminal.is_title_locked().then_some(title),
+                cwd,
+                color: self.tab_color(),
+                title_color: None,
+                pinned: false,
+            }],
+            active_tab: 0,
+        };
+        CLOSED_WINDOWS.with(|c| c.borrow_mut().push(closed));
+    }
+
+    /// Recreate the most recently closed window with a fresh shell in its saved directory
+    fn reopen_closed_window(&self) {
+        let Some(closed) = CLOSED_WINDOWS.with(|c| c.borrow_mut().pop()) else {
+            return;
+        };
+        let Some(tab) = closed.tabs.get(closed.active_tab).or(closed.tabs.first()) else {
+            return;
+        };
+
+        let mtm = MainThreadMarker::from(self);
+        let cwd = tab.cwd.as_ref().map(|p| p.to_string_lossy().into_owned());
+        let window = CtermWindow::new_with_cwd(mtm, &self.ivars().config, &self.ivars().theme, cwd);
+
+        // Register with AppDelegate for tracking
+        let app = NSApplication::sharedApplication(mtm);
+        if let Some(delegate) = app.delegate() {
+            let _: () = unsafe { msg_send![&*delegate, registerWindow: &*window] };
+        }
+
+        if let Some(terminal) = window.active_terminal() {
+            if let Some(ref title) = tab.custom_title {
+                window.setTitle(&NSString::from_str(title));
+                terminal.set_title_locked(true);
+            }
+            terminal.set_template_name(tab.template_name.clone());
+        }
+        if let Some(ref color) = tab.color {
+            window.set_tab_color(Some(color));
+        }
+
+        let geometry = &closed.geometry;
+        if geometry.width > 0 && geometry.height > 0 {
+            let frame = NSRect::new(
+                NSPoint::new(geometry.x as f64, geometry.y as f64),
+                NSSize::new(geometry.width as f64, geometry.height as f64),
+            );
+            window.setFrame_display(frame, true);
+        }
+
+        // Show as a separate window rather than merging into this one's tab group
+        window.setTabbingMode(NSWindowTabbingMode::Disallowed);
+        window.makeKeyAndOrderFront(None);
+        window.setTabbingMode(NSWindowTabbingMode::Preferred);
+
+        log::info!("Reopened closed window");
+    }
+
     /// Show a confirmation dialog when closing with a running process
     fn show_close_confirmation(&self, process_name: &str) -> bool {
         use objc2_app_kit::NSAlert;
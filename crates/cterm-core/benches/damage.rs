@@ -0,0 +1,94 @@
+//! Full repaint vs damage-tracked repaint
+//!
+//! Each iteration feeds one frame's worth of output to the parser and then
+//! "paints" the screen, either every row or only the rows reported by
+//! `Screen::take_damage`. Painting a row resolves every cell's colors and
+//! collects its text, standing in for the per-row work the renderers do.
+//!
+//! Run with `cargo bench -p cterm-core --bench damage`.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use cterm_core::color::{ColorPalette, Rgb};
+use cterm_core::screen::{Screen, ScreenConfig};
+use cterm_core::Parser;
+
+const COLS: usize = 200;
+const ROWS: usize = 60;
+
+/// Stand-in for a renderer drawing one row
+fn paint_row(screen: &Screen, row: usize, palette: &ColorPalette, text: &mut String) -> Rgb {
+    let mut last = Rgb::default();
+    text.clear();
+    if let Some(cells) = screen.grid().row(row) {
+        for cell in cells.iter() {
+            let fg = cell.fg.to_rgb(palette);
+            let bg = cell.bg.to_rgb(palette);
+            last = fg.blend(bg, 0.5);
+            text.push(cell.c);
+        }
+    }
+    last
+}
+
+fn paint(screen: &mut Screen, palette: &ColorPalette, damaged_only: bool, text: &mut String) {
+    let damage = screen.take_damage();
+    for row in 0..screen.height() {
+        if !damaged_only || damage.is_row_damaged(row) {
+            black_box(paint_row(screen, row, palette, text));
+        }
+    }
+}
+
+/// Output for one frame of each workload
+fn workloads() -> Vec<(&'static str, Vec<u8>)> {
+    let line = format!("{}\r\n", "x".repeat(COLS - 20));
+    vec![
+        // A progress bar redrawing the same line
+        (
+            "progress",
+            b"\r\x1b[32m[##########          ]\x1b[0m 50%".to_vec(),
+        ),
+        // Typing at a prompt: one character per frame
+        ("typing", b"a".to_vec()),
+        // Streaming output, one new line per frame
+        ("scroll_line", line.into_bytes()),
+        // A pager with a fixed status line scrolling its content region
+        (
+            "scroll_region",
+            format!("\x1b[1;{}r\x1b[{};1H\n\x1b[r", ROWS - 1, ROWS - 1).into_bytes(),
+        ),
+    ]
+}
+
+fn bench_repaint(c: &mut Criterion) {
+    let palette = ColorPalette::default();
+    let mut group = c.benchmark_group("repaint");
+
+    for (name, frame) in workloads() {
+        for damaged_only in [false, true] {
+            let id = if damaged_only { "damaged" } else { "full" };
+            group.bench_with_input(BenchmarkId::new(id, name), &frame, |b, frame| {
+                let mut screen = Screen::new(COLS, ROWS, ScreenConfig::default());
+                let mut parser = Parser::new();
+                let mut text = String::with_capacity(COLS);
+
+                // Start from a full screen, like an app that has been running a while
+                for _ in 0..ROWS {
+                    parser.parse(&mut screen, "y".repeat(COLS - 1).as_bytes());
+                    parser.parse(&mut screen, b"\r\n");
+                }
+                paint(&mut screen, &palette, false, &mut text);
+
+                b.iter(|| {
+                    parser.parse(&mut screen, frame);
+                    paint(&mut screen, &palette, damaged_only, &mut text);
+                });
+            });
+        }
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_repaint);
+criterion_main!(benches);
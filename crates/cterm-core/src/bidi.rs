@@ -0,0 +1,286 @@
+//! Bidirectional text display
+//!
+//! The grid keeps text in logical order, the order the program wrote it.
+//! Right-to-left scripts (Hebrew, Arabic, ...) have to be displayed in the
+//! opposite direction, so renderers that support it reorder each line for
+//! display with a [`BidiLine`], and map mouse positions back through it.
+//!
+//! This is a simplified version of the Unicode Bidirectional Algorithm for
+//! a left-to-right paragraph per line: characters are classified as
+//! left-to-right, right-to-left, European or Arabic numbers or neutral;
+//! numbers following right-to-left text and neutrals between right-to-left
+//! text take its direction, and runs are then reversed by level. Explicit
+//! embeddings, overrides and isolates are not supported.
+
+use crate::cell::Cell;
+
+/// Bidi character class, reduced to what the simplified algorithm uses
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BidiClass {
+    /// Strong left-to-right
+    L,
+    /// Strong right-to-left (including Arabic letters)
+    R,
+    /// European (ASCII) digits
+    EN,
+    /// Arabic-Indic digits
+    AN,
+    /// Whitespace, punctuation, symbols and empty cells
+    Neutral,
+}
+
+fn bidi_class(c: char) -> BidiClass {
+    match c as u32 {
+        0x30..=0x39 => BidiClass::EN,
+        0x0660..=0x0669 | 0x06F0..=0x06F9 => BidiClass::AN,
+        // Hebrew, Arabic, Syriac, Thaana, NKo, Samaritan, Mandaic
+        0x0590..=0x08FF
+        // Hebrew and Arabic presentation forms
+        | 0xFB1D..=0xFDFF
+        | 0xFE70..=0xFEFF
+        // Historic right-to-left scripts and Arabic mathematical symbols
+        | 0x10800..=0x10FFF
+        | 0x1E800..=0x1EFFF => BidiClass::R,
+        _ if c == '\0' || c.is_whitespace() => BidiClass::Neutral,
+        _ if c.is_ascii() && !c.is_ascii_alphanumeric() => BidiClass::Neutral,
+        // General punctuation, currency, arrows, math, box drawing, symbols
+        0x2000..=0x2BFF | 0x00A0..=0x00BF | 0x00D7 | 0x00F7 => BidiClass::Neutral,
+        // Emoji and pictographs
+        0x1F000..=0x1FAFF => BidiClass::Neutral,
+        _ => BidiClass::L,
+    }
+}
+
+/// The mirrored form of a character drawn right-to-left, e.g. `(` for `)`
+pub fn mirror(c: char) -> char {
+    match c {
+        '(' => ')',
+        ')' => '(',
+        '[' => ']',
+        ']' => '[',
+        '{' => '}',
+        '}' => '{',
+        '<' => '>',
+        '>' => '<',
+        '«' => '»',
+        '»' => '«',
+        '‹' => '›',
+        '›' => '‹',
+        _ => c,
+    }
+}
+
+/// Display order of one line
+///
+/// Columns are cells: a wide character and its spacer stay together.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BidiLine {
+    /// Visual column of each logical column
+    visual: Vec<usize>,
+    /// Logical column shown at each visual column
+    logical: Vec<usize>,
+    /// Whether each logical column is displayed right-to-left
+    rtl: Vec<bool>,
+}
+
+impl BidiLine {
+    /// Compute the display order of a line of cells
+    ///
+    /// Returns None when the line has no right-to-left text, so it can be
+    /// drawn as is.
+    pub fn new<'a>(cells: impl IntoIterator<Item = &'a Cell>) -> Option<Self> {
+        // Group the cells into characters: (first column, width, class)
+        let mut units: Vec<(usize, usize, BidiClass)> = Vec::new();
+        let mut width = 0;
+        for cell in cells {
+            match units.last_mut() {
+                Some(unit) if cell.is_wide_spacer() => unit.1 += 1,
+                _ => units.push((width, 1, bidi_class(cell.c))),
+            }
+            width += 1;
+        }
+
+        if !units
+            .iter()
+            .any(|&(_, _, class)| matches!(class, BidiClass::R | BidiClass::AN))
+        {
+            return None;
+        }
+
+        let levels = resolve_levels(&units.iter().map(|u| u.2).collect::<Vec<_>>());
+
+        // Reverse runs, from the highest level down to level 1
+        let mut order: Vec<usize> = (0..units.len()).collect();
+        let max_level = levels.iter().copied().max().unwrap_or(0);
+        for level in (1..=max_level).rev() {
+            let mut i = 0;
+            while i < order.len() {
+                if levels[order[i]] < level {
+                    i += 1;
+                    continue;
+                }
+                let start = i;
+                while i < order.len() && levels[order[i]] >= level {
+                    i += 1;
+                }
+                order[start..i].reverse();
+            }
+        }
+
+        let mut visual = vec![0; width];
+        let mut logical = vec![0; width];
+        let mut rtl = vec![false; width];
+        let mut col = 0;
+        for unit in order {
+            let (start, len, _) = units[unit];
+            for offset in 0..len {
+                visual[start + offset] = col + offset;
+                logical[col + offset] = start + offset;
+                rtl[start + offset] = levels[unit] % 2 == 1;
+            }
+            col += len;
+        }
+
+        Some(Self {
+            visual,
+            logical,
+            rtl,
+        })
+    }
+
+    /// Column a logical column is displayed at
+    pub fn visual_col(&self, col: usize) -> usize {
+        self.visual.get(col).copied().unwrap_or(col)
+    }
+
+    /// Logical column displayed at a visual column (e.g. under the mouse)
+    pub fn logical_col(&self, col: usize) -> usize {
+        self.logical.get(col).copied().unwrap_or(col)
+    }
+
+    /// Whether the character in a logical column is displayed right-to-left
+    pub fn is_rtl(&self, col: usize) -> bool {
+        self.rtl.get(col).copied().unwrap_or(false)
+    }
+
+    /// The character to draw for `c` in a logical column (mirrored if the
+    /// column is displayed right-to-left)
+    pub fn display_char(&self, col: usize, c: char) -> char {
+        if self.is_rtl(col) {
+            mirror(c)
+        } else {
+            c
+        }
+    }
+}
+
+/// Resolve the embedding level of each character of a left-to-right line
+fn resolve_levels(classes: &[BidiClass]) -> Vec<u8> {
+    // Numbers take the direction of the preceding strong text
+    let mut resolved = classes.to_vec();
+    let mut last_strong = BidiClass::L;
+    for class in &mut resolved {
+        match *class {
+            BidiClass::L | BidiClass::R => last_strong = *class,
+            BidiClass::EN if last_strong == BidiClass::L => *class = BidiClass::L,
+            _ => {}
+        }
+    }
+
+    // Neutrals between right-to-left text (numbers count as such) are
+    // right-to-left, others take the line's left-to-right direction
+    let is_rtl = |class: BidiClass| !matches!(class, BidiClass::L | BidiClass::Neutral);
+    let mut i = 0;
+    while i < resolved.len() {
+        if resolved[i] != BidiClass::Neutral {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        while i < resolved.len() && resolved[i] == BidiClass::Neutral {
+            i += 1;
+        }
+        let before = start.checked_sub(1).map(|j| resolved[j]);
+        let after = resolved.get(i).copied();
+        let class = match (before, after) {
+            (Some(before), Some(after)) if is_rtl(before) && is_rtl(after) => BidiClass::R,
+            _ => BidiClass::L,
+        };
+        resolved[start..i].fill(class);
+    }
+
+    resolved
+        .iter()
+        .map(|class| match class {
+            BidiClass::L | BidiClass::Neutral => 0,
+            BidiClass::R => 1,
+            BidiClass::EN | BidiClass::AN => 2,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cell::CellAttrs;
+
+    fn cells(text: &str) -> Vec<Cell> {
+        text.chars()
+            .map(|c| Cell {
+                c,
+                ..Cell::default()
+            })
+            .collect()
+    }
+
+    /// The line as displayed, with mirroring
+    fn display(text: &str) -> String {
+        let cells = cells(text);
+        let Some(line) = BidiLine::new(&cells) else {
+            return text.to_string();
+        };
+        (0..cells.len())
+            .map(|visual| {
+                let col = line.logical_col(visual);
+                line.display_char(col, cells[col].c)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_reorder() {
+        assert!(BidiLine::new(&cells("plain ascii 123")).is_none());
+        assert_eq!(display("שלום"), "םולש");
+        assert_eq!(display("ls שלום עולם!"), "ls םלוע םולש!");
+        // Numbers keep their order inside right-to-left text
+        assert_eq!(display("אב 123 גד"), "דג 123 בא");
+        // Brackets are mirrored
+        assert_eq!(display("א (ב) ג"), "ג (ב) א");
+    }
+
+    #[test]
+    fn test_column_mapping() {
+        let line = BidiLine::new(&cells("a אב b")).unwrap();
+        assert_eq!(line.visual_col(2), 3);
+        assert_eq!(line.visual_col(3), 2);
+        assert_eq!(line.logical_col(2), 3);
+        assert!(line.is_rtl(2));
+        assert!(!line.is_rtl(0));
+        assert_eq!(line.visual_col(4), 4);
+
+        // A wide character keeps its spacer after it
+        let mut wide = cells("א😀 ב");
+        wide.insert(
+            2,
+            Cell {
+                attrs: CellAttrs::WIDE_SPACER,
+                ..Cell::default()
+            },
+        );
+        let line = BidiLine::new(&wide).unwrap();
+        assert_eq!(
+            (0..5).map(|col| line.visual_col(col)).collect::<Vec<_>>(),
+            vec![4, 2, 3, 1, 0]
+        );
+    }
+}
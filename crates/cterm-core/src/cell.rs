@@ -103,6 +103,10 @@ pub struct Cell {
     pub attrs: CellAttrs,
     /// Hyperlink if present (shared via Arc for efficiency)
     pub hyperlink: Option<Arc<Hyperlink>>,
+    /// Characters joined to `c` in one grapheme cluster: combining marks,
+    /// variation selectors, emoji ZWJ sequences and flag pairs
+    #[serde(default)]
+    pub grapheme: Option<Arc<str>>,
 }
 
 impl Default for Cell {
@@ -114,6 +118,7 @@ impl Default for Cell {
             underline_color: None,
             attrs: CellAttrs::empty(),
             hyperlink: None,
+            grapheme: None,
         }
     }
 }
@@ -139,6 +144,7 @@ impl Cell {
             && self.bg == Color::Default
             && self.attrs.is_empty()
             && self.hyperlink.is_none()
+            && self.grapheme.is_none()
     }
 
     /// Check if this cell is a wide character
@@ -151,6 +157,34 @@ impl Cell {
         self.attrs.contains(CellAttrs::WIDE_SPACER)
     }
 
+    /// Replace the character, dropping any characters joined to it
+    pub fn set_char(&mut self, c: char) {
+        self.c = c;
+        self.grapheme = None;
+    }
+
+    /// Join `c` to this cell's grapheme cluster
+    pub fn push_grapheme(&mut self, c: char) {
+        let mut grapheme = self.grapheme.as_deref().unwrap_or_default().to_string();
+        grapheme.push(c);
+        self.grapheme = Some(grapheme.into());
+    }
+
+    /// Append the cell's whole grapheme cluster to `buf`
+    pub fn write_grapheme_to(&self, buf: &mut String) {
+        buf.push(self.c);
+        if let Some(ref grapheme) = self.grapheme {
+            buf.push_str(grapheme);
+        }
+    }
+
+    /// The cell's whole grapheme cluster
+    pub fn grapheme_text(&self) -> String {
+        let mut text = String::new();
+        self.write_grapheme_to(&mut text);
+        text
+    }
+
     /// Reset cell to empty state
     pub fn reset(&mut self) {
         *self = Self::default();
@@ -195,6 +229,7 @@ impl CellStyle {
             underline_color: self.underline_color,
             attrs: self.attrs,
             hyperlink: self.hyperlink.clone(),
+            grapheme: None,
         }
     }
 
@@ -202,6 +237,59 @@ impl CellStyle {
     pub fn reset(&mut self) {
         *self = Self::default();
     }
+
+    /// SGR parameters that select this style from the default one
+    ///
+    /// Starts with `0`, e.g. `0;1;31`, as reported by DECRQSS.
+    pub fn sgr_params(&self) -> String {
+        let mut params = vec!["0".to_string()];
+        let flags = [
+            (CellAttrs::BOLD, "1"),
+            (CellAttrs::DIM, "2"),
+            (CellAttrs::ITALIC, "3"),
+            (CellAttrs::UNDERLINE, "4"),
+            (CellAttrs::DOUBLE_UNDERLINE, "4:2"),
+            (CellAttrs::CURLY_UNDERLINE, "4:3"),
+            (CellAttrs::DOTTED_UNDERLINE, "4:4"),
+            (CellAttrs::DASHED_UNDERLINE, "4:5"),
+            (CellAttrs::BLINK, "5"),
+            (CellAttrs::INVERSE, "7"),
+            (CellAttrs::HIDDEN, "8"),
+            (CellAttrs::STRIKETHROUGH, "9"),
+            (CellAttrs::OVERLINE, "53"),
+        ];
+        for (flag, param) in flags {
+            if self.attrs.contains(flag) {
+                params.push(param.to_string());
+            }
+        }
+        params.extend(sgr_color_param(self.fg, Some(30), 38));
+        params.extend(sgr_color_param(self.bg, Some(40), 48));
+        if let Some(color) = self.underline_color {
+            params.extend(sgr_color_param(color, None, 58));
+        }
+        params.join(";")
+    }
+}
+
+/// SGR parameter selecting `color`
+///
+/// `base` is the parameter of the first ANSI color (30 or 40; bright colors
+/// are 60 higher), `extended` that of indexed and RGB colors (38, 48 or 58).
+fn sgr_color_param(color: Color, base: Option<usize>, extended: usize) -> Option<String> {
+    match color {
+        Color::Default => None,
+        Color::Ansi(ansi) => {
+            let index = ansi as usize;
+            Some(match base {
+                Some(base) if index < 8 => (base + index).to_string(),
+                Some(base) => (base + 60 + index - 8).to_string(),
+                None => format!("{};5;{}", extended, index),
+            })
+        }
+        Color::Indexed(index) => Some(format!("{};5;{}", extended, index)),
+        Color::Rgb(rgb) => Some(format!("{};2;{};{};{}", extended, rgb.r, rgb.g, rgb.b)),
+    }
 }
 
 #[cfg(test)]
@@ -215,6 +303,19 @@ mod tests {
         assert!(cell.is_empty());
     }
 
+    #[test]
+    fn test_cell_bincode_round_trip() {
+        // Scrollback spill files and crash state are bincode, which can't
+        // skip fields
+        let mut combined = Cell::new('e');
+        combined.grapheme = Some(Arc::from("\u{301}"));
+        let cells = vec![combined, Cell::new('x')];
+
+        let bytes = bincode::serialize(&cells).unwrap();
+        let decoded: Vec<Cell> = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(decoded, cells);
+    }
+
     #[test]
     fn test_cell_not_empty() {
         let mut cell = Cell::new('A');
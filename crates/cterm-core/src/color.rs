@@ -5,8 +5,13 @@
 //! - 256 indexed colors
 //! - 24-bit true color (RGB)
 
+use std::collections::BTreeMap;
+
 use serde::{Deserialize, Serialize};
 
+/// Default opacity of faint (SGR 2) text
+pub const DEFAULT_FAINT_OPACITY: f64 = 0.5;
+
 /// RGB color value
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
 pub struct Rgb {
@@ -32,6 +37,44 @@ impl Rgb {
         Some(Self { r, g, b })
     }
 
+    /// Parse an X11 color specification as used by OSC 4/10/11/12
+    ///
+    /// Accepts `rgb:R/G/B` with 1 to 4 hex digits per component and `#RGB`,
+    /// `#RRGGBB`, `#RRRGGGBBB` or `#RRRRGGGGBBBB`.
+    pub fn from_x11_spec(spec: &str) -> Option<Self> {
+        // Scale a component of 1 to 4 hex digits to 8 bits
+        fn component(hex: &str) -> Option<u8> {
+            if hex.is_empty() || hex.len() > 4 {
+                return None;
+            }
+            let value = u32::from_str_radix(hex, 16).ok()?;
+            let max = (1u32 << (4 * hex.len())) - 1;
+            Some(((value * 255 + max / 2) / max) as u8)
+        }
+
+        if let Some(rgb) = spec.strip_prefix("rgb:") {
+            let mut parts = rgb.split('/');
+            let r = component(parts.next()?)?;
+            let g = component(parts.next()?)?;
+            let b = component(parts.next()?)?;
+            return parts.next().is_none().then_some(Self { r, g, b });
+        }
+
+        let hex = spec.strip_prefix('#')?;
+        if hex.is_empty() || hex.len() % 3 != 0 || !hex.is_ascii() {
+            return None;
+        }
+        // Unlike rgb:, the digits are the most significant bits
+        let n = hex.len() / 3;
+        let high = |i: usize| u8::from_str_radix(&hex[i * n..(i * n + n).min(i * n + 2)], 16).ok();
+        let scale = |v: u8| if n == 1 { v * 17 } else { v };
+        Some(Self {
+            r: scale(high(0)?),
+            g: scale(high(1)?),
+            b: scale(high(2)?),
+        })
+    }
+
     /// Convert to hex string
     pub fn to_hex(&self) -> String {
         format!("#{:02x}{:02x}{:02x}", self.r, self.g, self.b)
@@ -45,6 +88,43 @@ impl Rgb {
             self.b as f64 / 255.0,
         )
     }
+
+    /// Mix toward `other` by `amount` (0.0 = self, 1.0 = other)
+    pub fn blend(&self, other: Rgb, amount: f64) -> Rgb {
+        let amount = amount.clamp(0.0, 1.0);
+        let mix = |a: u8, b: u8| (a as f64 + (b as f64 - a as f64) * amount).round() as u8;
+        Rgb::new(
+            mix(self.r, other.r),
+            mix(self.g, other.g),
+            mix(self.b, other.b),
+        )
+    }
+
+    /// This color drawn as faint text over `background`
+    ///
+    /// `opacity` is how much of the color remains (1.0 = unchanged, 0.0 = invisible).
+    pub fn faint(&self, background: Rgb, opacity: f64) -> Rgb {
+        background.blend(*self, opacity)
+    }
+
+    /// Relative luminance as defined by WCAG (0.0 = black, 1.0 = white)
+    pub fn luminance(&self) -> f64 {
+        let linear = |c: f64| {
+            if c <= 0.03928 {
+                c / 12.92
+            } else {
+                ((c + 0.055) / 1.055).powf(2.4)
+            }
+        };
+        let (r, g, b) = self.to_f64();
+        0.2126 * linear(r) + 0.7152 * linear(g) + 0.0722 * linear(b)
+    }
+
+    /// WCAG contrast ratio against `other`, from 1.0 (same) to 21.0
+    pub fn contrast(&self, other: Rgb) -> f64 {
+        let (a, b) = (self.luminance(), other.luminance());
+        (a.max(b) + 0.05) / (a.min(b) + 0.05)
+    }
 }
 
 /// Standard ANSI colors (0-15)
@@ -159,6 +239,27 @@ impl Color {
     }
 }
 
+/// Colors changed by programs: the default colors (OSC 10/11/12) and
+/// palette entries (OSC 4)
+///
+/// They replace the theme's colors until the program resets them (OSC
+/// 110/111/112, OSC 104) or the terminal is reset.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ColorOverrides {
+    pub foreground: Option<Rgb>,
+    pub background: Option<Rgb>,
+    pub cursor: Option<Rgb>,
+    /// Palette entries by index (0-255)
+    pub palette: BTreeMap<u8, Rgb>,
+}
+
+impl ColorOverrides {
+    /// Whether no color is overridden
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
 /// Color palette for rendering
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ColorPalette {
@@ -172,6 +273,10 @@ pub struct ColorPalette {
     pub cursor: Rgb,
     /// Selection background color
     pub selection: Rgb,
+    /// Colors 16-255 replaced by a program (OSC 4); the others come from
+    /// the standard color cube and gray ramp
+    #[serde(skip)]
+    pub indexed: BTreeMap<u8, Rgb>,
 }
 
 impl Default for ColorPalette {
@@ -206,6 +311,7 @@ impl ColorPalette {
             background: Rgb::new(0x1d, 0x1f, 0x21),
             cursor: Rgb::new(0xc5, 0xc8, 0xc6),
             selection: Rgb::new(0x37, 0x3b, 0x41),
+            indexed: BTreeMap::new(),
         }
     }
 
@@ -234,12 +340,16 @@ impl ColorPalette {
             background: Rgb::new(0xff, 0xff, 0xff),
             cursor: Rgb::new(0x00, 0x00, 0x00),
             selection: Rgb::new(0xee, 0xe8, 0xd5),
+            indexed: BTreeMap::new(),
         }
     }
 }
 
 /// Convert 256-color index to RGB
 fn index_to_rgb(index: u8, palette: &ColorPalette) -> Rgb {
+    if let Some(rgb) = palette.indexed.get(&index) {
+        return *rgb;
+    }
     match index {
         // Standard ANSI colors (0-15)
         0..=15 => palette.ansi[index as usize],
@@ -279,12 +389,68 @@ mod tests {
         assert_eq!(Rgb::from_hex("#invalid"), None);
     }
 
+    #[test]
+    fn test_rgb_from_x11_spec() {
+        assert_eq!(
+            Rgb::from_x11_spec("rgb:ff/80/00"),
+            Some(Rgb::new(255, 128, 0))
+        );
+        assert_eq!(
+            Rgb::from_x11_spec("rgb:ffff/0/8080"),
+            Some(Rgb::new(255, 0, 128))
+        );
+        assert_eq!(
+            Rgb::from_x11_spec("#1a2b3c"),
+            Some(Rgb::new(0x1a, 0x2b, 0x3c))
+        );
+        assert_eq!(Rgb::from_x11_spec("#f0a"), Some(Rgb::new(255, 0, 170)));
+        assert_eq!(
+            Rgb::from_x11_spec("#12345678abcd"),
+            Some(Rgb::new(0x12, 0x56, 0xab))
+        );
+        assert_eq!(Rgb::from_x11_spec("rgb:ff/80"), None);
+        assert_eq!(Rgb::from_x11_spec("red"), None);
+        assert_eq!(Rgb::from_x11_spec("#12345"), None);
+    }
+
     #[test]
     fn test_rgb_to_hex() {
         assert_eq!(Rgb::new(255, 0, 0).to_hex(), "#ff0000");
         assert_eq!(Rgb::new(0, 255, 0).to_hex(), "#00ff00");
     }
 
+    #[test]
+    fn test_rgb_blend() {
+        let black = Rgb::new(0, 0, 0);
+        let white = Rgb::new(255, 255, 255);
+        assert_eq!(black.blend(white, 0.0), black);
+        assert_eq!(black.blend(white, 1.0), white);
+        assert_eq!(black.blend(white, 0.5), Rgb::new(128, 128, 128));
+        assert_eq!(white.blend(black, 2.0), black);
+    }
+
+    #[test]
+    fn test_rgb_faint() {
+        let fg = Rgb::new(200, 200, 200);
+        let bg = Rgb::new(0, 0, 0);
+        assert_eq!(fg.faint(bg, 1.0), fg);
+        assert_eq!(fg.faint(bg, 0.5), Rgb::new(100, 100, 100));
+        assert_eq!(fg.faint(bg, 0.0), bg);
+    }
+
+    #[test]
+    fn test_rgb_contrast() {
+        let black = Rgb::new(0, 0, 0);
+        let white = Rgb::new(255, 255, 255);
+        assert_eq!(black.luminance(), 0.0);
+        assert_eq!(white.luminance(), 1.0);
+        assert_eq!(black.contrast(white), 21.0);
+        assert_eq!(white.contrast(black), 21.0);
+        assert_eq!(white.contrast(white), 1.0);
+        let gray = Rgb::new(0x76, 0x76, 0x76);
+        assert!((gray.contrast(white) - 4.54).abs() < 0.01);
+    }
+
     #[test]
     fn test_ansi_color_bright() {
         assert_eq!(AnsiColor::Red.bright(), AnsiColor::BrightRed);
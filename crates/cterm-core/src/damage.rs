@@ -0,0 +1,140 @@
+//! Damage tracking - which rows changed since the last frame
+//!
+//! The grid records the rows touched by each mutation, and the screen adds
+//! changes that aren't cell contents (cursor movement, selection, scrolling
+//! through history). Renderers take the accumulated damage once per frame and
+//! only repaint the rows it lists.
+
+/// Set of visible rows that need repainting
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Damage {
+    rows: Vec<bool>,
+    full: bool,
+}
+
+impl Damage {
+    /// No damage, for a screen of `height` rows
+    pub fn new(height: usize) -> Self {
+        Self {
+            rows: vec![false; height],
+            full: false,
+        }
+    }
+
+    /// Everything damaged, for a screen of `height` rows
+    pub fn full(height: usize) -> Self {
+        Self {
+            rows: vec![false; height],
+            full: true,
+        }
+    }
+
+    /// Mark a single row as damaged (out-of-range rows are ignored)
+    pub fn mark_row(&mut self, row: usize) {
+        if let Some(damaged) = self.rows.get_mut(row) {
+            *damaged = true;
+        }
+    }
+
+    /// Mark the rows in `start..end` as damaged
+    pub fn mark_rows(&mut self, start: usize, end: usize) {
+        let end = end.min(self.rows.len());
+        if start < end {
+            self.rows[start..end].fill(true);
+        }
+    }
+
+    /// Mark everything as damaged
+    pub fn mark_all(&mut self) {
+        self.full = true;
+    }
+
+    /// Change the number of rows; everything is damaged afterwards
+    pub fn resize(&mut self, height: usize) {
+        self.rows = vec![false; height];
+        self.full = true;
+    }
+
+    /// Whether the whole screen needs repainting
+    pub fn is_full(&self) -> bool {
+        self.full
+    }
+
+    /// Whether nothing needs repainting
+    pub fn is_empty(&self) -> bool {
+        !self.full && !self.rows.contains(&true)
+    }
+
+    /// Whether the given row needs repainting
+    pub fn is_row_damaged(&self, row: usize) -> bool {
+        self.full || self.rows.get(row).copied().unwrap_or(false)
+    }
+
+    /// The rows that need repainting, in order
+    pub fn rows(&self) -> impl Iterator<Item = usize> + '_ {
+        let full = self.full;
+        self.rows
+            .iter()
+            .enumerate()
+            .filter(move |(_, damaged)| full || **damaged)
+            .map(|(row, _)| row)
+    }
+
+    /// Merge the damage from `other` into this one
+    pub fn merge(&mut self, other: &Damage) {
+        self.full |= other.full;
+        for (damaged, other) in self.rows.iter_mut().zip(&other.rows) {
+            *damaged |= *other;
+        }
+    }
+
+    /// Take the accumulated damage, leaving none behind
+    pub fn take(&mut self) -> Damage {
+        let height = self.rows.len();
+        std::mem::replace(self, Damage::new(height))
+    }
+}
+
+impl Default for Damage {
+    /// Unknown previous contents, so everything must be painted
+    fn default() -> Self {
+        Self::full(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_damage_rows() {
+        let mut damage = Damage::new(5);
+        assert!(damage.is_empty());
+
+        damage.mark_row(1);
+        damage.mark_rows(3, 10);
+        damage.mark_row(42);
+        assert_eq!(damage.rows().collect::<Vec<_>>(), vec![1, 3, 4]);
+        assert!(damage.is_row_damaged(3));
+        assert!(!damage.is_row_damaged(2));
+
+        let taken = damage.take();
+        assert!(!taken.is_empty());
+        assert!(damage.is_empty());
+        assert_eq!(damage.rows().count(), 0);
+    }
+
+    #[test]
+    fn test_damage_full() {
+        let mut damage = Damage::new(3);
+        damage.mark_all();
+        assert!(damage.is_full());
+        assert_eq!(damage.rows().collect::<Vec<_>>(), vec![0, 1, 2]);
+
+        let mut other = Damage::new(3);
+        other.merge(&damage);
+        assert!(other.is_full());
+
+        assert!(Damage::default().is_full());
+    }
+}
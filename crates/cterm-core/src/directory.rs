@@ -0,0 +1,107 @@
+//! Working directory reported by the shell (OSC 7)
+//!
+//! Shells configured for it send `OSC 7 ; file://host/path ST` whenever the
+//! directory changes. Unlike reading the foreground process's directory from
+//! the OS (`/proc` on Linux), this works on every platform and over SSH,
+//! where the host tells the directory isn't on this machine.
+
+use std::path::PathBuf;
+
+/// A directory reported with OSC 7
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReportedDirectory {
+    /// Host the directory is on (empty if the URL had none)
+    pub host: String,
+    /// Path of the directory
+    pub path: PathBuf,
+}
+
+impl ReportedDirectory {
+    /// Parse the `file://host/path` URL of an OSC 7 sequence
+    ///
+    /// Percent-encoded bytes in the path are decoded.
+    pub fn from_url(url: &str) -> Option<Self> {
+        let rest = url.strip_prefix("file://")?;
+        let (host, path) = match rest.find('/') {
+            Some(slash) => rest.split_at(slash),
+            None => return None,
+        };
+        let path = String::from_utf8(percent_decode(path)).ok()?;
+        Some(Self {
+            host: host.to_string(),
+            path: PathBuf::from(path),
+        })
+    }
+
+    /// Whether the directory is on this machine, so new shells can start in it
+    pub fn is_local(&self) -> bool {
+        self.host.is_empty()
+            || self.host.eq_ignore_ascii_case("localhost")
+            || local_hostname().is_some_and(|name| self.host.eq_ignore_ascii_case(&name))
+    }
+}
+
+/// Decode `%XX` escapes; malformed ones are kept as they are
+fn percent_decode(s: &str) -> Vec<u8> {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                out.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                out.push(byte);
+                i += 1;
+            }
+        }
+    }
+    out
+}
+
+/// Name of this machine
+#[cfg(unix)]
+fn local_hostname() -> Option<String> {
+    let mut buf = [0u8; 256];
+    let ret = unsafe { libc::gethostname(buf.as_mut_ptr() as *mut libc::c_char, buf.len()) };
+    if ret != 0 {
+        return None;
+    }
+    let len = buf.iter().position(|&b| b == 0).unwrap_or(buf.len());
+    String::from_utf8(buf[..len].to_vec()).ok()
+}
+
+/// Name of this machine
+#[cfg(windows)]
+fn local_hostname() -> Option<String> {
+    std::env::var("COMPUTERNAME").ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_url() {
+        let dir = ReportedDirectory::from_url("file://box/home/me/My%20Project").unwrap();
+        assert_eq!(dir.host, "box");
+        assert_eq!(dir.path, PathBuf::from("/home/me/My Project"));
+
+        let dir = ReportedDirectory::from_url("file:///tmp/100%").unwrap();
+        assert_eq!(dir.host, "");
+        assert_eq!(dir.path, PathBuf::from("/tmp/100%"));
+        assert!(dir.is_local());
+        assert!(ReportedDirectory::from_url("file://localhost/")
+            .unwrap()
+            .is_local());
+
+        assert_eq!(ReportedDirectory::from_url("http://box/tmp"), None);
+        assert_eq!(ReportedDirectory::from_url("file://box"), None);
+    }
+}
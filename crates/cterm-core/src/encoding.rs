@@ -0,0 +1,262 @@
+//! Legacy character encodings
+//!
+//! Programs on older systems (reached over SSH or a serial line) may write
+//! Latin-1, Shift-JIS or GBK instead of UTF-8. Their output is decoded to
+//! UTF-8 before it reaches the parser, and typed text is encoded back.
+
+use std::borrow::Cow;
+
+use encoding_rs::{EncoderResult, Encoding, GBK, ISO_8859_15, SHIFT_JIS};
+use serde::{Deserialize, Serialize};
+
+/// Character encoding of the program running in a terminal
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum TerminalEncoding {
+    /// UTF-8 (no conversion)
+    #[default]
+    #[serde(rename = "utf-8")]
+    Utf8,
+    /// ISO-8859-1 (Latin-1); bytes 0x80-0x9f are C1 controls
+    #[serde(rename = "iso-8859-1")]
+    Latin1,
+    /// ISO-8859-15 (Latin-9, Latin-1 with the euro sign); bytes 0x80-0x9f
+    /// are C1 controls
+    #[serde(rename = "iso-8859-15")]
+    Latin9,
+    /// Shift-JIS (Japanese)
+    #[serde(rename = "shift_jis")]
+    ShiftJis,
+    /// GBK (Simplified Chinese)
+    #[serde(rename = "gbk")]
+    Gbk,
+}
+
+impl TerminalEncoding {
+    /// All encodings, in menu order
+    pub const ALL: [TerminalEncoding; 5] = [
+        TerminalEncoding::Utf8,
+        TerminalEncoding::Latin1,
+        TerminalEncoding::Latin9,
+        TerminalEncoding::ShiftJis,
+        TerminalEncoding::Gbk,
+    ];
+
+    /// Parse an encoding name, ignoring case and punctuation
+    /// (`"UTF-8"`, `"utf8"`, `"latin1"`, `"iso8859-15"`, `"sjis"`, ...)
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name: String = name
+            .chars()
+            .filter(char::is_ascii_alphanumeric)
+            .map(|c| c.to_ascii_lowercase())
+            .collect();
+        match name.as_str() {
+            "utf8" => Some(TerminalEncoding::Utf8),
+            "iso88591" | "latin1" => Some(TerminalEncoding::Latin1),
+            "iso885915" | "latin9" => Some(TerminalEncoding::Latin9),
+            "shiftjis" | "sjis" => Some(TerminalEncoding::ShiftJis),
+            "gbk" | "cp936" => Some(TerminalEncoding::Gbk),
+            _ => None,
+        }
+    }
+
+    /// Name used in the config file
+    pub fn name(self) -> &'static str {
+        match self {
+            TerminalEncoding::Utf8 => "utf-8",
+            TerminalEncoding::Latin1 => "iso-8859-1",
+            TerminalEncoding::Latin9 => "iso-8859-15",
+            TerminalEncoding::ShiftJis => "shift_jis",
+            TerminalEncoding::Gbk => "gbk",
+        }
+    }
+
+    /// Name shown in menus
+    pub fn label(self) -> &'static str {
+        match self {
+            TerminalEncoding::Utf8 => "UTF-8",
+            TerminalEncoding::Latin1 => "ISO-8859-1",
+            TerminalEncoding::Latin9 => "ISO-8859-15",
+            TerminalEncoding::ShiftJis => "Shift-JIS",
+            TerminalEncoding::Gbk => "GBK",
+        }
+    }
+
+    /// The encoding_rs codec, for the encodings it handles
+    ///
+    /// encoding_rs follows the WHATWG standard, which treats ISO-8859-1 as
+    /// windows-1252 and would turn C1 controls into printable characters, so
+    /// Latin-1 is mapped byte for byte instead.
+    fn codec(self) -> Option<&'static Encoding> {
+        match self {
+            TerminalEncoding::Utf8 | TerminalEncoding::Latin1 => None,
+            TerminalEncoding::Latin9 => Some(ISO_8859_15),
+            TerminalEncoding::ShiftJis => Some(SHIFT_JIS),
+            TerminalEncoding::Gbk => Some(GBK),
+        }
+    }
+
+    /// Whether bytes 0x80-0x9f are C1 controls rather than parts of
+    /// multi-byte characters
+    fn has_c1_controls(self) -> bool {
+        matches!(self, TerminalEncoding::Latin1 | TerminalEncoding::Latin9)
+    }
+
+    /// Encode typed or pasted text for the program
+    ///
+    /// Characters the encoding can't represent are sent as `?`. Input that
+    /// isn't valid UTF-8 (raw key sequences) is passed through unchanged.
+    pub fn encode<'a>(self, data: &'a [u8]) -> Cow<'a, [u8]> {
+        if self == TerminalEncoding::Utf8 || data.is_ascii() {
+            return Cow::Borrowed(data);
+        }
+        let Ok(text) = std::str::from_utf8(data) else {
+            return Cow::Borrowed(data);
+        };
+        match self.codec() {
+            None => Cow::Owned(
+                text.chars()
+                    .map(|c| u8::try_from(c).unwrap_or(b'?'))
+                    .collect(),
+            ),
+            Some(codec) => {
+                let mut encoder = codec.new_encoder();
+                let mut out = Vec::with_capacity(text.len());
+                let mut rest = text;
+                loop {
+                    let (result, read) =
+                        encoder.encode_from_utf8_to_vec_without_replacement(rest, &mut out, true);
+                    rest = &rest[read..];
+                    match result {
+                        EncoderResult::InputEmpty => break,
+                        EncoderResult::OutputFull => out.reserve(rest.len().max(8)),
+                        EncoderResult::Unmappable(_) => out.push(b'?'),
+                    }
+                }
+                Cow::Owned(out)
+            }
+        }
+    }
+}
+
+/// Streaming decoder from a terminal's encoding to UTF-8
+///
+/// Keeps the state between reads, so a multi-byte character split across
+/// two reads from the PTY is still decoded whole.
+pub struct OutputDecoder {
+    encoding: TerminalEncoding,
+    decoder: Option<encoding_rs::Decoder>,
+}
+
+impl OutputDecoder {
+    /// Create a decoder for `encoding`
+    pub fn new(encoding: TerminalEncoding) -> Self {
+        Self {
+            encoding,
+            decoder: encoding
+                .codec()
+                .map(Encoding::new_decoder_without_bom_handling),
+        }
+    }
+
+    /// The encoding being decoded
+    pub fn encoding(&self) -> TerminalEncoding {
+        self.encoding
+    }
+
+    /// Decode program output to UTF-8
+    ///
+    /// Invalid sequences become U+FFFD. UTF-8 output is returned unchanged.
+    /// In Latin-1 and Latin-9, C1 controls are sent on in their 7-bit form
+    /// (0x9b becomes ESC [), so the parser acts on them instead of printing
+    /// them as invisible U+0080-U+009F characters.
+    pub fn decode<'a>(&mut self, data: &'a [u8]) -> Cow<'a, [u8]> {
+        if self.encoding.has_c1_controls() && data.iter().any(|b| (0x80..0xa0).contains(b)) {
+            let data: Vec<u8> = data
+                .iter()
+                .flat_map(|&b| match b {
+                    0x80..=0x9f => vec![0x1b, b - 0x40],
+                    _ => vec![b],
+                })
+                .collect();
+            return Cow::Owned(self.decode(&data).into_owned());
+        }
+
+        if let Some(decoder) = self.decoder.as_mut() {
+            let capacity = decoder
+                .max_utf8_buffer_length(data.len())
+                .unwrap_or(data.len() * 3 + 16);
+            let mut out = String::with_capacity(capacity);
+            let (_, read, _) = decoder.decode_to_string(data, &mut out, false);
+            debug_assert_eq!(read, data.len());
+            Cow::Owned(out.into_bytes())
+        } else if self.encoding == TerminalEncoding::Latin1 && !data.is_ascii() {
+            Cow::Owned(
+                data.iter()
+                    .map(|&b| char::from(b))
+                    .collect::<String>()
+                    .into_bytes(),
+            )
+        } else {
+            Cow::Borrowed(data)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_name() {
+        assert_eq!(
+            TerminalEncoding::from_name("UTF-8"),
+            Some(TerminalEncoding::Utf8)
+        );
+        assert_eq!(
+            TerminalEncoding::from_name("iso8859-1"),
+            Some(TerminalEncoding::Latin1)
+        );
+        assert_eq!(
+            TerminalEncoding::from_name("Shift_JIS"),
+            Some(TerminalEncoding::ShiftJis)
+        );
+        assert_eq!(TerminalEncoding::from_name("ebcdic"), None);
+        for encoding in TerminalEncoding::ALL {
+            assert_eq!(TerminalEncoding::from_name(encoding.name()), Some(encoding));
+        }
+    }
+
+    #[test]
+    fn test_decode_split_character() {
+        // "日本" in Shift-JIS, split in the middle of the second character
+        let mut decoder = OutputDecoder::new(TerminalEncoding::ShiftJis);
+        let mut text = decoder.decode(&[0x93, 0xfa, 0x96]).into_owned();
+        text.extend_from_slice(&decoder.decode(&[0x7b, b'\r']));
+        assert_eq!(String::from_utf8(text).unwrap(), "日本\r");
+    }
+
+    #[test]
+    fn test_latin1_round_trip() {
+        let mut decoder = OutputDecoder::new(TerminalEncoding::Latin1);
+        assert_eq!(&*decoder.decode(b"caf\xe9"), "café".as_bytes());
+        // C1 controls become their 7-bit forms, not windows-1252 characters
+        // or U+009B
+        assert_eq!(&*decoder.decode(b"\x9b1m\xe9"), "\x1b[1mé".as_bytes());
+        assert_eq!(&*decoder.decode(b"\x9b"), b"\x1b[");
+        let mut decoder = OutputDecoder::new(TerminalEncoding::Latin9);
+        assert_eq!(&*decoder.decode(b"\x9b0m\xa4"), "\x1b[0m€".as_bytes());
+        assert_eq!(
+            &*TerminalEncoding::Latin1.encode("café €".as_bytes()),
+            b"caf\xe9 ?"
+        );
+    }
+
+    #[test]
+    fn test_encode_gbk() {
+        assert_eq!(
+            &*TerminalEncoding::Gbk.encode("中文".as_bytes()),
+            &[0xd6, 0xd0, 0xce, 0xc4]
+        );
+        assert_eq!(&*TerminalEncoding::Gbk.encode(b"\x1b[A"), b"\x1b[A");
+    }
+}
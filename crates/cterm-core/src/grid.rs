@@ -4,14 +4,18 @@
 //! access to cells by row and column.
 
 use crate::cell::Cell;
+use crate::damage::Damage;
 use serde::{Deserialize, Serialize};
 
 /// A row of cells in the terminal
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Row {
     cells: Vec<Cell>,
-    /// Whether this row has been wrapped from the previous row
+    /// Whether this row was auto-wrapped and continues on the next row
     pub wrapped: bool,
+    /// Cells past the last column of a truncated long line
+    #[serde(default)]
+    overflow: Vec<Cell>,
 }
 
 impl Row {
@@ -20,6 +24,7 @@ impl Row {
         Self {
             cells: vec![Cell::default(); width],
             wrapped: false,
+            overflow: Vec::new(),
         }
     }
 
@@ -34,7 +39,13 @@ impl Row {
     }
 
     /// Resize the row to a new width
+    ///
+    /// Growing the row brings truncated cells back into view.
     pub fn resize(&mut self, width: usize) {
+        if width > self.cells.len() && !self.overflow.is_empty() {
+            let take = (width - self.cells.len()).min(self.overflow.len());
+            self.cells.extend(self.overflow.drain(..take));
+        }
         self.cells.resize(width, Cell::default());
     }
 
@@ -44,6 +55,44 @@ impl Row {
             cell.reset();
         }
         self.wrapped = false;
+        self.overflow.clear();
+    }
+
+    /// Number of cells past the last column (truncated long line)
+    pub fn overflow_len(&self) -> usize {
+        self.overflow.len()
+    }
+
+    /// Write a cell past the last column, `index` cells after it
+    ///
+    /// Cells between the current end and `index` are filled with blanks.
+    pub fn set_overflow(&mut self, index: usize, cell: Cell) {
+        if index >= self.overflow.len() {
+            self.overflow.resize(index + 1, Cell::default());
+        }
+        self.overflow[index] = cell;
+    }
+
+    /// Drop the cells past the last column from `index` on
+    pub fn truncate_overflow(&mut self, index: usize) {
+        self.overflow.truncate(index);
+    }
+
+    /// Get a cell at the given column, including cells past the last column
+    pub fn get_extended(&self, col: usize) -> Option<&Cell> {
+        match col.checked_sub(self.cells.len()) {
+            Some(index) => self.overflow.get(index),
+            None => self.cells.get(col),
+        }
+    }
+
+    /// Get a mutable reference to a cell at the given column, including cells
+    /// past the last column
+    pub fn get_extended_mut(&mut self, col: usize) -> Option<&mut Cell> {
+        match col.checked_sub(self.cells.len()) {
+            Some(index) => self.overflow.get_mut(index),
+            None => self.cells.get_mut(col),
+        }
     }
 
     /// Get a reference to a cell at the given column
@@ -79,7 +128,7 @@ impl Row {
     /// This allows reusing a single String allocation across many rows.
     pub fn write_text_to(&self, buf: &mut String) {
         buf.clear();
-        for cell in &self.cells {
+        for cell in self.cells.iter().chain(&self.overflow) {
             buf.push(cell.c);
         }
         let trimmed_len = buf.trim_end().len();
@@ -112,6 +161,9 @@ pub struct Grid {
     rows: Vec<Row>,
     width: usize,
     height: usize,
+    /// Rows modified since the damage was last taken
+    #[serde(skip)]
+    damage: Damage,
 }
 
 impl Grid {
@@ -122,6 +174,7 @@ impl Grid {
             rows,
             width,
             height,
+            damage: Damage::full(height),
         }
     }
 
@@ -153,6 +206,7 @@ impl Grid {
 
         self.width = width;
         self.height = height;
+        self.damage.resize(height);
     }
 
     /// Get a reference to a row
@@ -162,6 +216,7 @@ impl Grid {
 
     /// Get a mutable reference to a row
     pub fn row_mut(&mut self, row: usize) -> Option<&mut Row> {
+        self.damage.mark_row(row);
         self.rows.get_mut(row)
     }
 
@@ -172,6 +227,7 @@ impl Grid {
 
     /// Get a mutable reference to a cell at (row, col)
     pub fn get_mut(&mut self, row: usize, col: usize) -> Option<&mut Cell> {
+        self.damage.mark_row(row);
         self.rows.get_mut(row)?.get_mut(col)
     }
 
@@ -180,6 +236,7 @@ impl Grid {
         for row in &mut self.rows {
             row.clear();
         }
+        self.damage.mark_all();
     }
 
     /// Clear a range of rows
@@ -187,6 +244,7 @@ impl Grid {
         for row in self.rows[start..end].iter_mut() {
             row.clear();
         }
+        self.damage.mark_rows(start, end);
     }
 
     /// Scroll the grid up by `count` lines
@@ -210,6 +268,7 @@ impl Grid {
             self.rows[i].clear();
         }
 
+        self.damage.mark_rows(top, bottom);
         scrolled_out
     }
 
@@ -229,6 +288,8 @@ impl Grid {
         for i in top..top + count {
             self.rows[i].clear();
         }
+
+        self.damage.mark_rows(top, bottom);
     }
 
     /// Iterator over rows
@@ -238,9 +299,15 @@ impl Grid {
 
     /// Mutable iterator over rows
     pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Row> {
+        self.damage.mark_all();
         self.rows.iter_mut()
     }
 
+    /// Take the rows modified since the last call
+    pub fn take_damage(&mut self) -> Damage {
+        self.damage.take()
+    }
+
     /// Get all text content from the grid
     pub fn text(&self) -> String {
         self.rows
@@ -261,6 +328,7 @@ impl std::ops::Index<usize> for Grid {
 
 impl std::ops::IndexMut<usize> for Grid {
     fn index_mut(&mut self, row: usize) -> &mut Self::Output {
+        self.damage.mark_row(row);
         &mut self.rows[row]
     }
 }
@@ -276,6 +344,31 @@ mod tests {
         assert_eq!(grid.height(), 24);
     }
 
+    #[test]
+    fn test_bincode_round_trip() {
+        // Scrollback spill files and crash state are bincode, which can't
+        // skip fields
+        let mut grid = Grid::new(4, 2);
+        grid.get_mut(0, 0).unwrap().c = 'a';
+        let row = grid.row_mut(1).unwrap();
+        row.wrapped = true;
+        row.set_overflow(0, Cell::new('z'));
+        let rows: Vec<Row> = grid.iter().cloned().collect();
+
+        let bytes = bincode::serialize(&grid).unwrap();
+        let decoded: Grid = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(decoded.text(), grid.text());
+        assert_eq!(decoded.row(1).unwrap().overflow_len(), 1);
+
+        let bytes = bincode::serialize(&rows).unwrap();
+        let decoded: Vec<Row> = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(decoded.len(), 2);
+        assert_eq!(decoded[0].text(), rows[0].text());
+        assert_eq!(decoded[0].overflow_len(), 0);
+        assert!(decoded[1].wrapped);
+        assert_eq!(decoded[1].get_extended(4).unwrap().c, 'z');
+    }
+
     #[test]
     fn test_grid_access() {
         let mut grid = Grid::new(80, 24);
@@ -5,34 +5,53 @@
 //! - Screen buffer management (grid, scrollback)
 //! - ANSI/VT sequence parsing
 //! - Cross-platform PTY handling
+//! - Session recording
+//! - Predictive local echo
+//! - Working directory reporting (OSC 7)
+//! - Secret masking in the display
+//! - Legacy character encodings (Latin-1, Shift-JIS, GBK)
 
+pub mod bidi;
 pub mod cell;
 pub mod color;
+pub mod damage;
+pub mod directory;
 pub mod drcs;
+pub mod encoding;
 #[cfg(unix)]
 pub mod fd_passing;
 pub mod grid;
 pub mod image_decode;
 pub mod iterm2;
+pub mod local_echo;
 pub mod parser;
 pub mod pty;
+pub mod recording;
 pub mod screen;
+pub mod secrets;
 pub mod sixel;
 pub mod streaming_file;
 pub mod term;
 
 pub use cell::{Cell, CellAttrs};
 pub use color::{AnsiColor, Color, Rgb};
+pub use damage::Damage;
+pub use directory::ReportedDirectory;
 pub use drcs::{DecdldDecoder, DrcsFont, DrcsGlyph};
+pub use encoding::{OutputDecoder, TerminalEncoding};
 pub use grid::Grid;
 pub use image_decode::{decode_image, DecodedImage, ImageDecodeError};
 pub use iterm2::{Iterm2Dimension, Iterm2FileParams};
-pub use parser::Parser;
+pub use local_echo::LocalEchoMode;
+pub use parser::{Parser, UnknownSequenceMode};
 pub use pty::{Pty, PtyConfig, PtyError, PtySize};
+pub use recording::{RecordingMode, SessionRecorder};
 pub use screen::{
-    ClipboardOperation, ClipboardSelection, ColorQuery, FileTransferOperation, Screen,
-    SearchResult, Selection, SelectionMode, SelectionPoint, TerminalImage,
+    CellRegion, ClipboardOperation, ClipboardSelection, ColorQuery, FileTransferOperation,
+    HyperlinkCopyMode, ImageAnchor, ImagePlacement, LongLineMode, Screen, SearchHighlight,
+    SearchResult, Selection, SelectionMode, SelectionPoint, SemanticMark, TerminalImage,
 };
+pub use secrets::{SecretMatcher, DEFAULT_SECRET_PATTERNS};
 pub use sixel::{SixelDecoder, SixelImage};
 pub use streaming_file::{StreamingFileData, StreamingFileReceiver, StreamingFileResult};
-pub use term::Terminal;
+pub use term::{EraseSequence, HomeEndStyle, ScrollTrigger, SelectionClearTrigger, Terminal};
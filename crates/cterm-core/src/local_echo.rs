@@ -0,0 +1,149 @@
+//! Predictive local echo
+//!
+//! Over a slow connection every typed character takes a round trip before it
+//! shows up. Like mosh, the terminal can instead draw typed characters right
+//! away, faint, and let the real echo replace them when it arrives. This is a
+//! simplified take: only printable characters typed at the end of a line are
+//! predicted, predictions are shown only once the program has been seen to
+//! echo on the current line (so passwords stay hidden), and any output that
+//! doesn't match drops them all.
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// When typed characters are echoed locally before the program echoes them
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LocalEchoMode {
+    /// Only show what the program echoes
+    #[default]
+    Off,
+    /// Always predict the echo
+    On,
+    /// Predict the echo while the measured echo latency is above a threshold
+    Auto,
+}
+
+/// Characters typed but not echoed by the program yet
+#[derive(Debug, Clone, Default)]
+pub struct Predictions {
+    /// Cell (row, column) the first pending character will be echoed to
+    anchor: (usize, usize),
+    /// Typed characters waiting for their echo, oldest first
+    pending: VecDeque<char>,
+    /// Whether the pending characters are drawn
+    shown: bool,
+}
+
+impl Predictions {
+    /// Whether no characters are waiting for their echo
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// The characters to draw at the cursor, if any are shown
+    pub fn shown(&self) -> Option<String> {
+        (self.shown && !self.pending.is_empty()).then(|| self.pending.iter().collect())
+    }
+
+    /// Number of cells the shown characters take
+    pub fn shown_len(&self) -> usize {
+        if self.shown {
+            self.pending.len()
+        } else {
+            0
+        }
+    }
+
+    /// Column just after the last pending character
+    pub fn end_col(&self) -> usize {
+        self.anchor.1 + self.pending.len()
+    }
+
+    /// Add a typed character, echoed at `cursor` if nothing is pending
+    pub fn push(&mut self, c: char, cursor: (usize, usize)) {
+        if self.pending.is_empty() {
+            self.anchor = cursor;
+        }
+        self.pending.push_back(c);
+    }
+
+    /// Remove the last typed character (Backspace); false if none is pending
+    pub fn pop(&mut self) -> bool {
+        self.pending.pop_back().is_some()
+    }
+
+    /// Stop drawing predictions until the program echoes one again
+    ///
+    /// Used for input that can't be predicted (Enter, control keys), after
+    /// which the program may have turned echo off.
+    pub fn hide(&mut self) {
+        self.shown = false;
+    }
+
+    /// Drop all predictions
+    pub fn clear(&mut self) {
+        self.pending.clear();
+        self.shown = false;
+    }
+
+    /// Match the predictions against the screen after output was processed
+    ///
+    /// Characters found at their cell with the cursor past them were echoed
+    /// and are dropped; echoing one shows the rest. If anything is still
+    /// pending, the cursor must be where the next echo goes, otherwise the
+    /// output didn't go as predicted and everything is dropped.
+    pub fn reconcile(
+        &mut self,
+        cursor: (usize, usize),
+        cell_char: impl Fn(usize, usize) -> Option<char>,
+    ) {
+        while let Some(&c) = self.pending.front() {
+            let (row, col) = self.anchor;
+            if cursor <= self.anchor || cell_char(row, col) != Some(c) {
+                break;
+            }
+            self.pending.pop_front();
+            self.anchor.1 += 1;
+            self.shown = true;
+        }
+
+        if !self.pending.is_empty() && cursor != self.anchor {
+            self.clear();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reconcile() {
+        let line = "$ ls";
+        let cell = |row: usize, col: usize| line.chars().nth(col).filter(|_| row == 0);
+
+        let mut predictions = Predictions::default();
+        predictions.push('l', (0, 2));
+        predictions.push('s', (0, 3));
+        assert_eq!(predictions.shown(), None);
+
+        // Nothing echoed yet
+        predictions.reconcile((0, 2), cell);
+        assert_eq!(predictions.end_col(), 4);
+
+        // 'l' echoed: 's' is shown from now on
+        predictions.reconcile((0, 3), cell);
+        assert_eq!(predictions.shown().as_deref(), Some("s"));
+        assert_eq!(predictions.shown_len(), 1);
+
+        predictions.reconcile((0, 4), cell);
+        assert!(predictions.is_empty());
+
+        // The cursor went somewhere else
+        predictions.push('x', (0, 4));
+        predictions.reconcile((1, 0), cell);
+        assert!(predictions.is_empty());
+        assert_eq!(predictions.shown(), None);
+    }
+}
@@ -6,21 +6,48 @@
 //! Special handling is provided for OSC 1337 (iTerm2) file transfers
 //! which are intercepted before VTE to enable streaming large files.
 
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use vte::Params;
 
 use crate::cell::{CellAttrs, Hyperlink};
 use crate::color::{AnsiColor, Color, Rgb};
+use crate::directory::ReportedDirectory;
 use crate::drcs::DecdldDecoder;
 use crate::image_decode::decode_image;
 use crate::iterm2::{Iterm2Dimension, Iterm2FileParams};
 use crate::screen::{
     ClearMode, ClipboardOperation, ClipboardSelection, CursorStyle, LineClearMode, MouseMode,
-    Screen,
+    Screen, SemanticMark,
 };
 use crate::sixel::{SixelDecoder, SixelImage};
 use crate::streaming_file::StreamingFileReceiver;
 
+/// How unknown or unsupported escape sequences are handled
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UnknownSequenceMode {
+    /// Silently drop the sequence
+    #[default]
+    Ignore,
+    /// Drop the sequence and log it as a warning
+    Log,
+    /// Log the sequence and render a visible ␛ glyph in its place
+    Show,
+}
+
+/// Glyph rendered in place of an unknown sequence in `Show` mode
+const UNKNOWN_SEQUENCE_GLYPH: char = '\u{241B}';
+
+/// Number of unknown sequences seen by all parsers in this process
+static UNKNOWN_SEQUENCE_COUNT: AtomicU64 = AtomicU64::new(0);
+
+/// Get the number of unknown escape sequences seen since startup
+pub fn unknown_sequence_count() -> u64 {
+    UNKNOWN_SEQUENCE_COUNT.load(Ordering::Relaxed)
+}
+
 /// DCS (Device Control String) state for handling multi-byte sequences
 enum DcsState {
     /// No DCS sequence active
@@ -33,8 +60,13 @@ enum DcsState {
     },
     /// DECDLD (soft font download) in progress
     Decdld { decoder: DecdldDecoder },
+    /// DECRQSS (request setting) in progress, collecting the setting name
+    Decrqss { request: Vec<u8> },
 }
 
+/// Longest setting name accepted in a DECRQSS request
+const MAX_DECRQSS_LEN: usize = 16;
+
 /// State for intercepting OSC 1337 File transfers before VTE buffers them
 #[derive(Debug, Default)]
 enum Osc1337State {
@@ -64,6 +96,12 @@ pub struct Parser {
     osc_1337_state: Osc1337State,
     /// Whether an OSC 1337 string terminator (BEL or ESC \) was seen
     osc_1337_terminated: bool,
+    /// How unknown sequences are handled
+    unknown_sequence_mode: UnknownSequenceMode,
+    /// Print unhandled C0 controls in caret notation (^@, ^A, ...)
+    print_control_as_caret: bool,
+    /// Largest file accepted over OSC 1337 File= (None = no limit)
+    max_file_transfer_bytes: Option<usize>,
 }
 
 impl Default for Parser {
@@ -79,9 +117,31 @@ impl Parser {
             dcs_state: DcsState::None,
             osc_1337_state: Osc1337State::None,
             osc_1337_terminated: false,
+            unknown_sequence_mode: UnknownSequenceMode::Ignore,
+            print_control_as_caret: false,
+            max_file_transfer_bytes: None,
         }
     }
 
+    /// Set how unknown or unsupported sequences are handled
+    pub fn set_unknown_sequence_mode(&mut self, mode: UnknownSequenceMode) {
+        self.unknown_sequence_mode = mode;
+    }
+
+    /// Set whether unhandled C0 controls are printed in caret notation
+    ///
+    /// Controls the terminal acts on (BEL, BS, HT, LF, CR, ...) are unaffected.
+    pub fn set_print_control_as_caret(&mut self, enabled: bool) {
+        self.print_control_as_caret = enabled;
+    }
+
+    /// Set the largest file accepted over OSC 1337 File= (None = no limit)
+    ///
+    /// Larger transfers are aborted and their data discarded.
+    pub fn set_max_file_transfer_bytes(&mut self, max_bytes: Option<usize>) {
+        self.max_file_transfer_bytes = max_bytes;
+    }
+
     /// Parse input bytes and apply actions to the screen
     ///
     /// This method intercepts OSC 1337 File transfers before VTE can buffer them,
@@ -102,6 +162,8 @@ impl Parser {
             let mut performer = ScreenPerformer {
                 screen,
                 dcs_state: &mut self.dcs_state,
+                unknown_sequence_mode: self.unknown_sequence_mode,
+                print_control_as_caret: self.print_control_as_caret,
             };
             self.state_machine.advance(&mut performer, byte);
         }
@@ -217,7 +279,10 @@ impl Parser {
                         file_params.inline
                     );
 
-                    let receiver = StreamingFileReceiver::new(file_params);
+                    let receiver = StreamingFileReceiver::with_max_bytes(
+                        file_params,
+                        self.max_file_transfer_bytes,
+                    );
                     self.osc_1337_state = Osc1337State::Osc1337Data(receiver);
                     return true;
                 }
@@ -239,11 +304,11 @@ impl Parser {
                     return true;
                 }
 
-                // Feed to streaming receiver
-                if !receiver.put(byte) {
-                    // Error occurred
+                // Feed to streaming receiver. After an error (such as the
+                // size limit) the rest of the data is still swallowed up to
+                // the terminator, rather than printed as text.
+                if !receiver.has_error() && !receiver.put(byte) {
                     log::warn!("OSC 1337 streaming error: {:?}", receiver.error());
-                    self.osc_1337_state = Osc1337State::None;
                 }
                 true
             }
@@ -258,8 +323,15 @@ impl Parser {
         self.osc_1337_terminated = false;
 
         match &self.osc_1337_state {
-            Osc1337State::Osc1337Content(_) | Osc1337State::Osc1337Params(_) => {
-                // Non-File content or terminated params without data - just reset
+            Osc1337State::Osc1337Content(_) => {
+                // Non-File content - handle simple key=value commands
+                let state = std::mem::replace(&mut self.osc_1337_state, Osc1337State::None);
+                if let Osc1337State::Osc1337Content(content) = state {
+                    Self::handle_osc_1337_command(&content, screen);
+                }
+            }
+            Osc1337State::Osc1337Params(_) => {
+                // Terminated params without data - just reset
                 self.osc_1337_state = Osc1337State::None;
             }
             Osc1337State::Osc1337Data(_) => {
@@ -270,6 +342,32 @@ impl Parser {
         }
     }
 
+    /// Handle a non-File OSC 1337 command (`key=value`)
+    fn handle_osc_1337_command(content: &[u8], screen: &mut Screen) {
+        let content = String::from_utf8_lossy(content);
+        let (key, value) = content.split_once('=').unwrap_or((&content, ""));
+
+        match key {
+            // Badge shown over the terminal; the value is base64-encoded
+            "SetBadgeFormat" => {
+                use base64::Engine;
+                match base64::engine::general_purpose::STANDARD.decode(value) {
+                    Ok(decoded) => {
+                        let format = String::from_utf8_lossy(&decoded);
+                        log::debug!("OSC 1337 SetBadgeFormat: {:?}", format);
+                        screen.set_badge_format(&format);
+                    }
+                    Err(e) => {
+                        log::warn!("OSC 1337 SetBadgeFormat: base64 decode failed: {}", e);
+                    }
+                }
+            }
+            _ => {
+                log::trace!("OSC 1337: unhandled subcommand {:?}", key);
+            }
+        }
+    }
+
     /// Finish streaming a file directly (called from check_osc_1337_finish)
     fn finish_streaming_file_direct(&mut self, screen: &mut Screen) {
         let state = std::mem::replace(&mut self.osc_1337_state, Osc1337State::None);
@@ -359,6 +457,8 @@ impl Parser {
 struct ScreenPerformer<'a> {
     screen: &'a mut Screen,
     dcs_state: &'a mut DcsState,
+    unknown_sequence_mode: UnknownSequenceMode,
+    print_control_as_caret: bool,
 }
 
 impl vte::Perform for ScreenPerformer<'_> {
@@ -375,6 +475,7 @@ impl vte::Perform for ScreenPerformer<'_> {
             }
             // Backspace (BS)
             0x08 => {
+                self.screen.clear_pending_wrap();
                 if self.screen.cursor.col > 0 {
                     self.screen.cursor.col -= 1;
                 }
@@ -404,6 +505,12 @@ impl vte::Perform for ScreenPerformer<'_> {
                 self.screen.modes.charset_g1_active = false;
                 log::trace!("Shift In: activated G0 charset");
             }
+            // Anything else (NUL, stray bytes from binary output, ...) is
+            // optionally made visible as ^@, ^A, ...
+            0x00..=0x1f if self.print_control_as_caret => {
+                self.screen.put_char('^');
+                self.screen.put_char((byte ^ 0x40) as char);
+            }
             _ => {
                 log::trace!("Unhandled execute byte: 0x{:02x}", byte);
             }
@@ -442,8 +549,17 @@ impl vte::Perform for ScreenPerformer<'_> {
                     decoder: DecdldDecoder::new(&params_vec),
                 };
             }
+            // Request setting (DECRQSS): DCS $ q Pt ST
+            'q' if intermediates == [b'$'] => {
+                *self.dcs_state = DcsState::Decrqss {
+                    request: Vec::new(),
+                };
+            }
             _ => {
-                log::trace!("Unhandled DCS action: {:?}", action);
+                self.unknown_sequence(format_args!(
+                    "DCS action={:?}, intermediates={:?}, params={:?}",
+                    action, intermediates, params_vec
+                ));
             }
         }
     }
@@ -459,6 +575,11 @@ impl vte::Perform for ScreenPerformer<'_> {
             DcsState::Decdld { ref mut decoder } => {
                 decoder.put(byte);
             }
+            DcsState::Decrqss { ref mut request } => {
+                if request.len() < MAX_DECRQSS_LEN {
+                    request.push(byte);
+                }
+            }
             DcsState::None => {}
         }
     }
@@ -542,6 +663,9 @@ impl vte::Perform for ScreenPerformer<'_> {
                     self.screen.add_drcs_font(font, erase_control, font_number);
                 }
             }
+            DcsState::Decrqss { request } => {
+                self.report_setting(&request);
+            }
             DcsState::None => {}
         }
     }
@@ -574,6 +698,18 @@ impl vte::Perform for ScreenPerformer<'_> {
                     }
                 }
             }
+            // Current working directory (OSC 7 ; file://host/path)
+            7 => {
+                if params.len() > 1 {
+                    // The path may contain semicolons, which split parameters
+                    let url = params[1..].join(&b';');
+                    let url = String::from_utf8_lossy(&url);
+                    match ReportedDirectory::from_url(&url) {
+                        Some(directory) => self.screen.current_directory = Some(directory),
+                        None => log::debug!("Ignoring OSC 7 with unsupported URL: {}", url),
+                    }
+                }
+            }
             // Hyperlink (OSC 8)
             8 => {
                 if params.len() >= 3 {
@@ -599,11 +735,52 @@ impl vte::Perform for ScreenPerformer<'_> {
                     }
                 }
             }
+            // Set palette colors: OSC 4 ; index ; spec [; index ; spec ...]
+            4 => {
+                for pair in params[1..].chunks(2) {
+                    let [index, spec] = pair else {
+                        break;
+                    };
+                    let Some(index) = std::str::from_utf8(index)
+                        .ok()
+                        .and_then(|s| s.parse::<u8>().ok())
+                    else {
+                        log::debug!("Invalid palette index in OSC 4");
+                        continue;
+                    };
+                    let spec = std::str::from_utf8(spec).unwrap_or("");
+                    if spec == "?" {
+                        log::trace!("Unhandled palette query for color {}", index);
+                    } else if let Some(color) = Rgb::from_x11_spec(spec) {
+                        self.screen.set_palette_color(index, Some(color));
+                    } else {
+                        log::debug!("Unsupported color in OSC 4: {}", spec);
+                    }
+                }
+            }
+            // Reset the listed palette colors, or all of them, to the theme's
+            104 => {
+                let indices: Vec<u8> = params[1..]
+                    .iter()
+                    .filter_map(|p| std::str::from_utf8(p).ok()?.parse().ok())
+                    .collect();
+                if indices.is_empty() {
+                    self.screen.reset_palette();
+                }
+                for index in indices {
+                    self.screen.set_palette_color(index, None);
+                }
+            }
             // Set/query colors (10-19)
-            // OSC 10 = foreground, 11 = background, 12 = cursor
+            // OSC 10 = foreground, 11 = background, 12 = cursor; extra
+            // parameters go to the following colors (OSC 10 ; fg ; bg)
             10..=12 => {
-                if params.len() > 1 {
-                    let query = std::str::from_utf8(params[1]).unwrap_or("");
+                for (offset, param) in params[1..].iter().enumerate() {
+                    let command = command + offset as u32;
+                    if command > 12 {
+                        break;
+                    }
+                    let query = std::str::from_utf8(param).unwrap_or("");
                     if query == "?" {
                         // Color query - respond with current color
                         // Format: OSC Ps ; rgb:RRRR/GGGG/BBBB ST
@@ -619,13 +796,17 @@ impl vte::Perform for ScreenPerformer<'_> {
                         // The actual response will be generated by the UI layer
                         // which has access to the theme colors
                         self.screen.queue_color_query(command as u8);
+                    } else if let Some(color) = Rgb::from_x11_spec(query) {
+                        self.screen.set_dynamic_color(command as u8, Some(color));
                     } else {
-                        // Color setting - log but don't implement
-                        // Dynamic color setting is rarely used
-                        log::trace!("Color set OSC {}: {}", command, query);
+                        log::debug!("Unsupported color in OSC {}: {}", command, query);
                     }
                 }
             }
+            // Reset colors set with OSC 10-12 to the theme's
+            110..=112 => {
+                self.screen.set_dynamic_color((command - 100) as u8, None);
+            }
             // Other color OSCs (13-19) - less common
             13..=19 => {
                 log::trace!("Unhandled color OSC: {}", command);
@@ -634,6 +815,29 @@ impl vte::Perform for ScreenPerformer<'_> {
             1337 => {
                 self.handle_osc_1337(params);
             }
+            // Semantic prompt marks (133), FinalTerm shell integration
+            // OSC 133 ; A|B|C|D [; exit code] ST
+            133 => {
+                let Some(kind) = params.get(1).and_then(|p| p.first()) else {
+                    return;
+                };
+                let mark = match kind {
+                    b'A' => SemanticMark::PromptStart,
+                    b'B' => SemanticMark::CommandStart,
+                    b'C' => SemanticMark::OutputStart,
+                    b'D' => SemanticMark::CommandEnd(
+                        params
+                            .get(2)
+                            .and_then(|p| std::str::from_utf8(p).ok())
+                            .and_then(|s| s.parse().ok()),
+                    ),
+                    _ => {
+                        log::trace!("Unhandled OSC 133 mark: {}", *kind as char);
+                        return;
+                    }
+                };
+                self.screen.semantic_mark(mark);
+            }
             // Copy to clipboard (52)
             52 => {
                 // OSC 52 ; Pc ; Pd ST
@@ -680,7 +884,7 @@ impl vte::Perform for ScreenPerformer<'_> {
                 }
             }
             _ => {
-                log::trace!("Unhandled OSC: {}", command);
+                self.unknown_sequence(format_args!("OSC {}", String::from_utf8_lossy(params[0])));
             }
         }
     }
@@ -853,17 +1057,18 @@ impl vte::Perform for ScreenPerformer<'_> {
                 self.screen.set_scroll_region(top, bottom);
                 self.screen.move_cursor(0, 0);
             }
-            // Save Cursor (DECSC)
-            ('s', []) => {
-                self.screen.save_cursor();
+            // Save Cursor Position (SCOSC); with parameters `CSI s` is
+            // DECSLRM (left/right margins), which isn't supported
+            ('s', []) if matches!(params_vec[..], [] | [0]) => {
+                self.screen.save_cursor_position();
             }
-            // Restore Cursor (DECRC)
-            ('u', []) => {
-                self.screen.restore_cursor();
+            // Restore Cursor Position (SCORC)
+            ('u', []) if matches!(params_vec[..], [] | [0]) => {
+                self.screen.restore_cursor_position();
             }
             // Window manipulation (XTWINOPS)
             ('t', []) => {
-                log::trace!("Window manipulation: {:?}", params_vec);
+                self.handle_window_op(&params_vec);
             }
             // Set Mode (SM) / Reset Mode (RM)
             ('h', [b'?']) | ('l', [b'?']) => {
@@ -881,10 +1086,7 @@ impl vte::Perform for ScreenPerformer<'_> {
             }
             // Soft reset (DECSTR)
             ('p', [b'!']) => {
-                self.screen.style.reset();
-                self.screen.modes.insert_mode = false;
-                self.screen.modes.origin_mode = false;
-                self.screen.reset_scroll_region();
+                self.screen.soft_reset();
             }
             // Set cursor style (DECSCUSR)
             ('q', [b' ']) => {
@@ -932,12 +1134,10 @@ impl vte::Perform for ScreenPerformer<'_> {
                 }
             }
             _ => {
-                log::trace!(
-                    "Unhandled CSI: action={:?}, intermediates={:?}, params={:?}",
-                    action,
-                    intermediates,
-                    params_vec
-                );
+                self.unknown_sequence(format_args!(
+                    "CSI action={:?}, intermediates={:?}, params={:?}",
+                    action, intermediates, params_vec
+                ));
             }
         }
     }
@@ -967,11 +1167,7 @@ impl vte::Perform for ScreenPerformer<'_> {
             }
             // Reverse Index (RI) - move cursor up, scroll if at top
             (b'M', []) => {
-                if self.screen.cursor.row == self.screen.scroll_region().top {
-                    self.screen.scroll_down(1);
-                } else if self.screen.cursor.row > 0 {
-                    self.screen.cursor.row -= 1;
-                }
+                self.screen.reverse_index();
             }
             // Application Keypad (DECKPAM)
             (b'=', []) => {
@@ -1012,18 +1208,34 @@ impl vte::Perform for ScreenPerformer<'_> {
                 self.screen.designate_charset(1, designator);
             }
             _ => {
-                log::trace!(
-                    "Unhandled ESC: byte=0x{:02x} ({:?}), intermediates={:?}",
-                    byte,
-                    byte as char,
-                    intermediates
-                );
+                self.unknown_sequence(format_args!(
+                    "ESC byte=0x{:02x} ({:?}), intermediates={:?}",
+                    byte, byte as char, intermediates
+                ));
             }
         }
     }
 }
 
 impl ScreenPerformer<'_> {
+    /// Handle a sequence that fell through every dispatch table
+    fn unknown_sequence(&mut self, description: std::fmt::Arguments) {
+        UNKNOWN_SEQUENCE_COUNT.fetch_add(1, Ordering::Relaxed);
+
+        match self.unknown_sequence_mode {
+            UnknownSequenceMode::Ignore => {
+                log::trace!("Unhandled sequence: {}", description);
+            }
+            UnknownSequenceMode::Log => {
+                log::warn!("Unhandled sequence: {}", description);
+            }
+            UnknownSequenceMode::Show => {
+                log::warn!("Unhandled sequence: {}", description);
+                self.screen.put_char(UNKNOWN_SEQUENCE_GLYPH);
+            }
+        }
+    }
+
     /// Handle OSC 1337 (iTerm2 inline images and file transfer)
     ///
     /// Protocol format: OSC 1337 ; File=[params] : base64data ST
@@ -1381,6 +1593,94 @@ impl ScreenPerformer<'_> {
         }
     }
 
+    /// Answer a DECRQSS request for the setting named `request`
+    ///
+    /// Replies with the control sequence that restores the setting, as
+    /// `DCS 1 $ r <sequence> ST`, or `DCS 0 $ r ST` for settings that can't
+    /// be reported.
+    fn report_setting(&mut self, request: &[u8]) {
+        let setting = match request {
+            // SGR
+            b"m" => Some(format!("{}m", self.screen.style.sgr_params())),
+            // DECSTBM
+            b"r" => {
+                let region = self.screen.scroll_region();
+                Some(format!("{};{}r", region.top + 1, region.bottom))
+            }
+            // DECSCUSR
+            b" q" => {
+                let cursor = &self.screen.cursor;
+                let style = match cursor.style {
+                    CursorStyle::Block => 1,
+                    CursorStyle::Underline => 3,
+                    CursorStyle::Bar => 5,
+                };
+                Some(format!("{} q", style + usize::from(!cursor.blink)))
+            }
+            _ => None,
+        };
+
+        let response = match setting {
+            Some(setting) => format!("\x1bP1$r{}\x1b\\", setting),
+            None => {
+                log::debug!(
+                    "Unsupported DECRQSS request: {:?}",
+                    String::from_utf8_lossy(request)
+                );
+                "\x1bP0$r\x1b\\".to_string()
+            }
+        };
+        self.screen.queue_response(response.into_bytes());
+    }
+
+    /// Handle window manipulation (XTWINOPS)
+    ///
+    /// Only the reports are answered. Requests to move, resize, raise or
+    /// iconify the window are ignored so programs can't rearrange the user's
+    /// windows.
+    fn handle_window_op(&mut self, params: &[usize]) {
+        let rows = self.screen.height();
+        let cols = self.screen.width();
+        let cell_width = self.screen.cell_width_hint();
+        let cell_height = self.screen.cell_height_hint();
+
+        let response = match first_param(params, 0) {
+            // Window state: never reported as iconified
+            11 => "\x1b[1t".to_string(),
+            // Text area (14) or window (14;2) size in pixels
+            14 => format!(
+                "\x1b[4;{};{}t",
+                (rows as f64 * cell_height).round() as usize,
+                (cols as f64 * cell_width).round() as usize
+            ),
+            // Cell size in pixels
+            16 => format!(
+                "\x1b[6;{};{}t",
+                cell_height.round() as usize,
+                cell_width.round() as usize
+            ),
+            // Text area size in characters
+            18 => format!("\x1b[8;{};{}t", rows, cols),
+            // Screen size in characters (the text area is all we expose)
+            19 => format!("\x1b[9;{};{}t", rows, cols),
+            // Save / restore the title (22;0|1|2, 23;0|1|2)
+            22 => {
+                self.screen.push_title();
+                return;
+            }
+            23 => {
+                self.screen
+                    .pop_title(params.get(1).copied().unwrap_or(0) as u16);
+                return;
+            }
+            _ => {
+                log::debug!("Ignoring window manipulation: {:?}", params);
+                return;
+            }
+        };
+        self.screen.queue_response(response.into_bytes());
+    }
+
     /// Handle DEC private mode set/reset
     fn handle_dec_mode(&mut self, mode: usize, set: bool) {
         match mode {
@@ -1434,11 +1734,22 @@ impl ScreenPerformer<'_> {
             1005 => { /* UTF-8 encoding for mouse coordinates - not implemented */ }
             // SGR Mouse Mode (extended coordinates)
             1006 => self.screen.modes.sgr_mouse = set,
-            // Alternate Screen Buffer
+            // Alternate Screen Buffer (legacy): switch only
+            47 => {
+                if set {
+                    self.screen.enter_alternate_screen();
+                } else {
+                    self.screen.exit_alternate_screen();
+                }
+            }
+            // Alternate Screen Buffer, cleared when leaving it
             1047 => {
                 if set {
                     self.screen.enter_alternate_screen();
                 } else {
+                    if self.screen.modes.alternate_screen {
+                        self.screen.clear(ClearMode::All);
+                    }
                     self.screen.exit_alternate_screen();
                 }
             }
@@ -1450,13 +1761,17 @@ impl ScreenPerformer<'_> {
                     self.screen.restore_cursor();
                 }
             }
-            // Alternate Screen Buffer with cursor save/restore
+            // Alternate Screen Buffer with cursor save/restore: the cursor is
+            // saved (as by DECSC) before switching, and the alternate buffer
+            // is cleared on entry
             1049 => {
                 if set {
-                    self.screen.save_cursor();
+                    if !self.screen.modes.alternate_screen {
+                        self.screen.save_cursor();
+                    }
                     self.screen.enter_alternate_screen();
                     self.screen.clear(ClearMode::All);
-                } else {
+                } else if self.screen.modes.alternate_screen {
                     self.screen.exit_alternate_screen();
                     self.screen.restore_cursor();
                 }
@@ -1532,6 +1847,34 @@ mod tests {
         assert_eq!(screen.cursor.col, 5);
     }
 
+    #[test]
+    fn test_soft_reset_keeps_content() {
+        let mut screen = make_screen();
+        let mut parser = Parser::new();
+
+        parser.parse(&mut screen, b"keep me\r\n");
+        // Margins, origin/insert/cursor-key modes, hidden cursor, red text
+        parser.parse(
+            &mut screen,
+            b"\x1b[5;10r\x1b[?6h\x1b[4h\x1b[?1h\x1b[?25l\x1b[31m",
+        );
+        parser.parse(&mut screen, b"\x1b[2;3H");
+        let cursor = (screen.cursor.row, screen.cursor.col);
+
+        parser.parse(&mut screen, b"\x1b[!p");
+
+        assert_eq!(screen.get_cell(0, 0).unwrap().c, 'k');
+        assert_eq!(screen.get_cell(0, 6).unwrap().c, 'e');
+        assert_eq!((screen.cursor.row, screen.cursor.col), cursor);
+        assert_eq!(screen.scroll_region().top, 0);
+        assert_eq!(screen.scroll_region().bottom, 24);
+        assert!(!screen.modes.origin_mode);
+        assert!(!screen.modes.insert_mode);
+        assert!(!screen.modes.application_cursor);
+        assert!(screen.modes.show_cursor);
+        assert_eq!(screen.style.fg, Color::Default);
+    }
+
     #[test]
     fn test_cursor_movement() {
         let mut screen = make_screen();
@@ -1563,6 +1906,28 @@ mod tests {
         assert_eq!(screen.style.bg, Color::Default);
     }
 
+    #[test]
+    fn test_sgr_bold_and_faint() {
+        let mut screen = make_screen();
+        let mut parser = Parser::new();
+
+        // Bold and faint can be set together
+        parser.parse(&mut screen, b"\x1b[1;2m");
+        assert!(screen.style.attrs.contains(CellAttrs::BOLD));
+        assert!(screen.style.attrs.contains(CellAttrs::DIM));
+
+        // Normal intensity clears both, leaving other attributes alone
+        parser.parse(&mut screen, b"\x1b[3;22m");
+        assert!(!screen.style.attrs.contains(CellAttrs::BOLD));
+        assert!(!screen.style.attrs.contains(CellAttrs::DIM));
+        assert!(screen.style.attrs.contains(CellAttrs::ITALIC));
+
+        // Faint is stored on printed cells and cleared by a full reset
+        parser.parse(&mut screen, b"\x1b[2mx\x1b[0m");
+        assert!(screen.grid()[0][0].attrs.contains(CellAttrs::DIM));
+        assert!(!screen.style.attrs.contains(CellAttrs::DIM));
+    }
+
     #[test]
     fn test_sgr_256_color() {
         let mut screen = make_screen();
@@ -1645,6 +2010,24 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_osc_1337_transfer_limit() {
+        let mut screen = make_screen();
+        let mut parser = Parser::new();
+        parser.set_max_file_transfer_bytes(Some(2));
+
+        // Declared too large: dropped, and the data isn't printed
+        parser.parse(&mut screen, b"\x1b]1337;File=size=4:AQAAAA==\x07");
+        // Undeclared size, found too large while decoding
+        parser.parse(&mut screen, b"\x1b]1337;File=:AQAAAA==\x07");
+
+        assert!(!screen.has_file_transfers());
+        assert_eq!(screen.get_cell(0, 0).unwrap().c, ' ');
+
+        parser.parse(&mut screen, b"\x1b]1337;File=:AQA=\x07");
+        assert!(screen.has_file_transfers());
+    }
+
     #[test]
     fn test_alternate_screen() {
         let mut screen = make_screen();
@@ -1658,4 +2041,436 @@ mod tests {
         assert!(!screen.modes.alternate_screen);
         assert_eq!(screen.get_cell(0, 0).unwrap().c, 'P');
     }
+
+    #[test]
+    fn test_alternate_screen_1049() {
+        let mut screen = make_screen();
+        let mut parser = Parser::new();
+        let row_text = |screen: &Screen, row: usize| {
+            screen
+                .grid()
+                .row(row)
+                .unwrap()
+                .text()
+                .trim_end()
+                .to_string()
+        };
+
+        // The cursor is saved on entry and restored on exit
+        parser.parse(&mut screen, b"\x1b[5;10H\x1b[?1049h");
+        assert!(screen.modes.alternate_screen);
+        assert_eq!((screen.cursor.row, screen.cursor.col), (4, 9));
+        parser.parse(&mut screen, b"\x1b[20;1Halt\x1b[?1049l");
+        assert!(!screen.modes.alternate_screen);
+        assert_eq!((screen.cursor.row, screen.cursor.col), (4, 9));
+        assert_eq!(row_text(&screen, 19), "");
+
+        // The alternate buffer is cleared on entry
+        parser.parse(&mut screen, b"\x1b[?1049h");
+        assert_eq!(row_text(&screen, 19), "");
+    }
+
+    #[test]
+    fn test_cursor_visibility_per_screen() {
+        let mut screen = make_screen();
+        let mut parser = Parser::new();
+
+        // A program that hides the cursor and exits without showing it
+        parser.parse(&mut screen, b"\x1b[?1049h\x1b[?25l\x1b[?1049l");
+        assert!(screen.modes.show_cursor);
+
+        // A hidden primary cursor stays hidden
+        parser.parse(&mut screen, b"\x1b[?25l\x1b[?1049h\x1b[?25h");
+        assert!(screen.modes.show_cursor);
+        parser.parse(&mut screen, b"\x1b[?1049l");
+        assert!(!screen.modes.show_cursor);
+
+        // The same goes for DECSET 47 and 1047
+        parser.parse(&mut screen, b"\x1b[?25h\x1b[?47h\x1b[?25l\x1b[?47l");
+        assert!(screen.modes.show_cursor);
+        parser.parse(&mut screen, b"\x1b[?1047h\x1b[?25l\x1b[?1047l");
+        assert!(screen.modes.show_cursor);
+
+        // Disabled, visibility is shared by both screens as in xterm
+        screen.set_cursor_visibility_per_screen(false);
+        parser.parse(&mut screen, b"\x1b[?1049h\x1b[?25l\x1b[?1049l");
+        assert!(!screen.modes.show_cursor);
+    }
+
+    #[test]
+    fn test_alternate_screen_47_and_1047() {
+        let mut screen = make_screen();
+        let mut parser = Parser::new();
+        let row_text = |screen: &Screen, row: usize| {
+            screen
+                .grid()
+                .row(row)
+                .unwrap()
+                .text()
+                .trim_end()
+                .to_string()
+        };
+
+        // 47 only switches buffers: the cursor doesn't move on entry or exit
+        parser.parse(&mut screen, b"\x1b[3;1H\x1b[?47h");
+        assert!(screen.modes.alternate_screen);
+        assert_eq!((screen.cursor.row, screen.cursor.col), (2, 0));
+        parser.parse(&mut screen, b"\x1b[10;1Hkept\x1b[?47l");
+        assert!(!screen.modes.alternate_screen);
+        assert_eq!((screen.cursor.row, screen.cursor.col), (9, 4));
+        assert_eq!(row_text(&screen, 9), "");
+
+        // ... and the alternate buffer keeps its contents
+        parser.parse(&mut screen, b"\x1b[?47h");
+        assert_eq!(row_text(&screen, 9), "kept");
+        parser.parse(&mut screen, b"\x1b[?47l");
+
+        // 1047 switches the same way, but clears the alternate buffer on exit
+        parser.parse(&mut screen, b"\x1b[?1047h");
+        assert_eq!(row_text(&screen, 9), "kept");
+        parser.parse(&mut screen, b"\x1b[1;1Hgone\x1b[?1047l");
+        assert_eq!((screen.cursor.row, screen.cursor.col), (0, 4));
+        assert_eq!(row_text(&screen, 0), "");
+        parser.parse(&mut screen, b"\x1b[?1047h");
+        assert_eq!(row_text(&screen, 0), "");
+        assert_eq!(row_text(&screen, 9), "");
+    }
+
+    #[test]
+    fn test_alternate_screen_saved_cursor_per_buffer() {
+        let mut screen = make_screen();
+        let mut parser = Parser::new();
+
+        // DECSC/DECRC inside the alternate buffer don't disturb the cursor
+        // 1049 saved for the primary one
+        parser.parse(
+            &mut screen,
+            b"\x1b[8;8H\x1b[?1049h\x1b[2;2H\x1b7\x1b[3;3H\x1b8",
+        );
+        assert_eq!((screen.cursor.row, screen.cursor.col), (1, 1));
+        parser.parse(&mut screen, b"\x1b[?1049l");
+        assert_eq!((screen.cursor.row, screen.cursor.col), (7, 7));
+    }
+
+    #[test]
+    fn test_sco_save_restore_cursor() {
+        let mut screen = make_screen();
+        let mut parser = Parser::new();
+
+        // The position can be restored more than once, e.g. to redraw a
+        // status line
+        parser.parse(&mut screen, b"\x1b[3;5H\x1b[s\x1b[8;1Hstatus\x1b[u");
+        assert_eq!((screen.cursor.row, screen.cursor.col), (2, 4));
+        parser.parse(&mut screen, b"abc\x1b[u");
+        assert_eq!((screen.cursor.row, screen.cursor.col), (2, 4));
+
+        // DECSC/DECRC keep their own saved cursor
+        parser.parse(&mut screen, b"\x1b[6;6H\x1b7\x1b[1;1H\x1b[u");
+        assert_eq!((screen.cursor.row, screen.cursor.col), (2, 4));
+        parser.parse(&mut screen, b"\x1b8");
+        assert_eq!((screen.cursor.row, screen.cursor.col), (5, 5));
+
+        // With parameters CSI s is DECSLRM, not a save
+        parser.parse(&mut screen, b"\x1b[2;2H\x1b[1;20s\x1b[4;4H\x1b[u");
+        assert_eq!((screen.cursor.row, screen.cursor.col), (2, 4));
+    }
+
+    #[test]
+    fn test_osc_133_command_zones() {
+        let mut screen = make_screen();
+        let mut parser = Parser::new();
+
+        let prompt = |parser: &mut Parser, screen: &mut Screen| {
+            parser.parse(screen, b"\x1b]133;A\x07$ \x1b]133;B\x07");
+        };
+
+        prompt(&mut parser, &mut screen);
+        parser.parse(&mut screen, b"ls\r\n\x1b]133;C\x07");
+        parser.parse(&mut screen, b"a.txt\r\nb.txt\r\n\x1b]133;D;0\x07");
+        prompt(&mut parser, &mut screen);
+
+        assert_eq!(screen.last_command_text().as_deref(), Some("ls"));
+        assert_eq!(
+            screen.last_command_output().as_deref(),
+            Some("a.txt\nb.txt")
+        );
+        assert_eq!(screen.last_command_exit_code(), Some(0));
+        assert!(screen.take_command_finished());
+        assert!(!screen.take_command_finished());
+
+        // Without 133;D the next prompt ends the command
+        parser.parse(&mut screen, b"false\r\n\x1b]133;C\x07oops\r\n");
+        prompt(&mut parser, &mut screen);
+        assert_eq!(screen.last_command_text().as_deref(), Some("false"));
+        assert_eq!(screen.last_command_output().as_deref(), Some("oops"));
+        assert_eq!(screen.last_command_exit_code(), None);
+        assert!(!screen.take_command_finished());
+    }
+
+    #[test]
+    fn test_unknown_sequence_mode() {
+        let mut screen = make_screen();
+        let mut parser = Parser::new();
+
+        // Ignored by default
+        let before = unknown_sequence_count();
+        parser.parse(&mut screen, b"a\x1b[5zb");
+        assert_eq!(screen.grid().row(0).unwrap().text().trim_end(), "ab");
+        assert!(unknown_sequence_count() > before);
+
+        parser.set_unknown_sequence_mode(UnknownSequenceMode::Show);
+        parser.parse(&mut screen, b"\r\n\x1b[5zc\x1b]9999;x\x07");
+        assert_eq!(
+            screen.grid().row(1).unwrap().text().trim_end(),
+            "\u{241B}c\u{241B}"
+        );
+
+        // Handled sequences never show the glyph
+        parser.parse(&mut screen, b"\r\n\x1b[1mx\x1b]0;title\x07");
+        assert_eq!(screen.grid().row(2).unwrap().text().trim_end(), "x");
+    }
+
+    #[test]
+    fn test_print_control_as_caret() {
+        let mut screen = make_screen();
+        let mut parser = Parser::new();
+
+        // Ignored by default
+        parser.parse(&mut screen, b"a\x00\x01b");
+        assert_eq!(screen.grid().row(0).unwrap().text().trim_end(), "ab");
+
+        parser.set_print_control_as_caret(true);
+        parser.parse(&mut screen, b"\r\na\x00\x01\x1fb");
+        assert_eq!(screen.grid().row(1).unwrap().text().trim_end(), "a^@^A^_b");
+
+        // Controls the terminal handles still take effect
+        parser.parse(&mut screen, b"\r\nx\ty\x08z");
+        assert_eq!(screen.grid().row(2).unwrap().text().trim_end(), "x       z");
+    }
+
+    #[test]
+    fn test_osc_1337_set_badge_format() {
+        let mut screen = make_screen();
+        let mut parser = Parser::new();
+
+        // "web-01" in base64
+        parser.parse(&mut screen, b"\x1b]1337;SetBadgeFormat=d2ViLTAx\x07");
+        assert_eq!(screen.badge().as_deref(), Some("web-01"));
+
+        parser.parse(&mut screen, b"\x1b]1337;SetBadgeFormat=\x1b\\");
+        assert_eq!(screen.badge(), None);
+    }
+
+    #[test]
+    fn test_osc_7_current_directory() {
+        let mut screen = make_screen();
+        let mut parser = Parser::new();
+
+        parser.parse(&mut screen, b"\x1b]7;file://box/home/me/a;b%20c\x07");
+        let directory = screen.current_directory.clone().unwrap();
+        assert_eq!(directory.host, "box");
+        assert_eq!(directory.path, std::path::PathBuf::from("/home/me/a;b c"));
+
+        // Unsupported URLs keep the last directory
+        parser.parse(&mut screen, b"\x1b]7;kitty-shell-cwd://box/tmp\x1b\\");
+        assert_eq!(screen.current_directory, Some(directory));
+    }
+
+    #[test]
+    fn test_window_reports() {
+        let mut screen = make_screen();
+        let mut parser = Parser::new();
+        screen.set_cell_width_hint(9.0);
+        screen.set_cell_height_hint(18.5);
+
+        parser.parse(&mut screen, b"\x1b[18t\x1b[14t\x1b[14;2t\x1b[16t\x1b[11t");
+        assert_eq!(
+            screen.take_pending_responses(),
+            vec![
+                b"\x1b[8;24;80t".to_vec(),
+                b"\x1b[4;444;720t".to_vec(),
+                b"\x1b[4;444;720t".to_vec(),
+                b"\x1b[6;19;9t".to_vec(),
+                b"\x1b[1t".to_vec(),
+            ]
+        );
+
+        // Resize, move and iconify requests are ignored
+        parser.parse(&mut screen, b"\x1b[8;50;100t\x1b[3;0;0t\x1b[2t");
+        assert!(screen.take_pending_responses().is_empty());
+        assert_eq!((screen.width(), screen.height()), (80, 24));
+    }
+
+    #[test]
+    fn test_decrqss() {
+        let mut screen = make_screen();
+        let mut parser = Parser::new();
+
+        // SGR
+        parser.parse(&mut screen, b"\x1bP$qm\x1b\\");
+        parser.parse(
+            &mut screen,
+            b"\x1b[1;4:3;91;48;5;200;58;2;1;2;3m\x1bP$qm\x1b\\",
+        );
+        assert_eq!(
+            screen.take_pending_responses(),
+            vec![
+                b"\x1bP1$r0m\x1b\\".to_vec(),
+                b"\x1bP1$r0;1;4:3;91;48;5;200;58;2;1;2;3m\x1b\\".to_vec(),
+            ]
+        );
+
+        // DECSTBM
+        parser.parse(&mut screen, b"\x1bP$qr\x1b\\\x1b[5;20r\x1bP$qr\x1b\\");
+        assert_eq!(
+            screen.take_pending_responses(),
+            vec![
+                b"\x1bP1$r1;24r\x1b\\".to_vec(),
+                b"\x1bP1$r5;20r\x1b\\".to_vec(),
+            ]
+        );
+
+        // DECSCUSR, and an unsupported request
+        parser.parse(&mut screen, b"\x1b[6 q\x1bP$q q\x1b\\\x1bP$qx\x1b\\");
+        assert_eq!(
+            screen.take_pending_responses(),
+            vec![b"\x1bP1$r6 q\x1b\\".to_vec(), b"\x1bP0$r\x1b\\".to_vec()]
+        );
+    }
+
+    #[test]
+    fn test_dynamic_colors() {
+        let mut screen = Screen::new(80, 24, ScreenConfig::default());
+        let mut parser = Parser::new();
+
+        parser.parse(
+            &mut screen,
+            b"\x1b]11;#102030\x07\x1b]12;rgb:ff/00/00\x1b\\",
+        );
+        assert_eq!(
+            screen.color_overrides().background,
+            Some(Rgb::new(0x10, 0x20, 0x30))
+        );
+        assert_eq!(screen.color_overrides().cursor, Some(Rgb::new(255, 0, 0)));
+
+        // Extra parameters set the following colors
+        parser.parse(&mut screen, b"\x1b]10;#ffffff;#000000\x07");
+        assert_eq!(
+            screen.color_overrides().foreground,
+            Some(Rgb::new(255, 255, 255))
+        );
+        assert_eq!(screen.color_overrides().background, Some(Rgb::new(0, 0, 0)));
+
+        parser.parse(&mut screen, b"\x1b]112\x07");
+        assert_eq!(screen.color_overrides().cursor, None);
+    }
+
+    #[test]
+    fn test_palette_colors() {
+        let mut screen = Screen::new(80, 24, ScreenConfig::default());
+        let mut parser = Parser::new();
+
+        parser.parse(&mut screen, b"\x1b]4;1;#ff0000;200;rgb:00/80/ff\x1b\\");
+        let palette = &screen.color_overrides().palette;
+        assert_eq!(palette.get(&1), Some(&Rgb::new(255, 0, 0)));
+        assert_eq!(palette.get(&200), Some(&Rgb::new(0, 0x80, 255)));
+
+        // Queries and bad indices don't change anything
+        parser.parse(&mut screen, b"\x1b]4;2;?;300;#ffffff\x07");
+        assert_eq!(screen.color_overrides().palette.len(), 2);
+
+        parser.parse(&mut screen, b"\x1b]104;1\x07");
+        assert_eq!(screen.color_overrides().palette.get(&1), None);
+        assert_eq!(screen.color_overrides().palette.len(), 1);
+
+        parser.parse(&mut screen, b"\x1b]104\x07");
+        assert!(screen.color_overrides().palette.is_empty());
+    }
+
+    #[test]
+    fn test_full_reset_clears_overrides_and_titles() {
+        let mut screen = Screen::new(80, 24, ScreenConfig::default());
+        let mut parser = Parser::new();
+
+        parser.parse(&mut screen, b"\x1b]0;vim\x07\x1b[22;0t\x1b]0;less\x07");
+        parser.parse(&mut screen, b"\x1b[23;0t");
+        assert_eq!(screen.title, "vim");
+
+        parser.parse(
+            &mut screen,
+            b"\x1b]10;#aabbcc\x07\x1b]11;#112233\x07\x1b[22t",
+        );
+        parser.parse(&mut screen, b"\x1bc");
+        assert!(screen.color_overrides().is_empty());
+        assert_eq!(screen.title, "");
+
+        // The saved title went with the reset
+        parser.parse(&mut screen, b"\x1b[23t");
+        assert_eq!(screen.title, "");
+    }
+
+    #[test]
+    fn test_tab_stops() {
+        let mut screen = make_screen();
+        let mut parser = Parser::new();
+
+        // Default stops every 8 columns
+        parser.parse(&mut screen, b"\tA");
+        assert_eq!(screen.get_cell(0, 8).unwrap().c, 'A');
+
+        // HTS at column 4, then tab from the start of the next line
+        parser.parse(&mut screen, b"\r\n\x1b[5G\x1bH\r\tB\tC");
+        assert_eq!(screen.get_cell(1, 4).unwrap().c, 'B');
+        assert_eq!(screen.get_cell(1, 8).unwrap().c, 'C');
+
+        // TBC 3 clears every stop, so tab goes to the last column
+        parser.parse(&mut screen, b"\r\n\x1b[3g\tD");
+        assert_eq!(screen.get_cell(2, 79).unwrap().c, 'D');
+
+        // A configured width replaces the stops, and RIS brings them back
+        screen.set_tab_width(4);
+        parser.parse(&mut screen, b"\r\n\tE\tF");
+        assert_eq!(screen.get_cell(3, 4).unwrap().c, 'E');
+        assert_eq!(screen.get_cell(3, 8).unwrap().c, 'F');
+        parser.parse(&mut screen, b"\x1b[3g\x1bc\tG");
+        assert_eq!(screen.get_cell(0, 4).unwrap().c, 'G');
+    }
+
+    #[test]
+    fn test_index_and_reverse_index_in_scroll_region() {
+        let row_text = |screen: &Screen, row: usize| screen.get_cell(row, 0).unwrap().c;
+        let mut screen = Screen::new(10, 6, ScreenConfig::default());
+        let mut parser = Parser::new();
+
+        // Header on row 0, body on rows 1-4, status bar on row 5
+        parser.parse(&mut screen, b"H\r\n1\r\n2\r\n3\r\n4\r\nS");
+        parser.parse(&mut screen, b"\x1b[2;5r");
+
+        // IND at the bottom margin scrolls only the region up
+        parser.parse(&mut screen, b"\x1b[5;1H\x1bD");
+        assert_eq!(screen.cursor.row, 4);
+        let rows: String = (0..6).map(|row| row_text(&screen, row)).collect();
+        assert_eq!(rows, "H234 S");
+        assert!(screen.scrollback().is_empty());
+
+        // RI at the top margin scrolls only the region down
+        parser.parse(&mut screen, b"\x1b[2;1H\x1bM");
+        assert_eq!(screen.cursor.row, 1);
+        let rows: String = (0..6).map(|row| row_text(&screen, row)).collect();
+        assert_eq!(rows, "H 234S");
+
+        // Inside the region they just move the cursor
+        parser.parse(&mut screen, b"\x1b[3;1H\x1bD");
+        assert_eq!(screen.cursor.row, 3);
+        parser.parse(&mut screen, b"\x1bM\x1bM");
+        assert_eq!(screen.cursor.row, 1);
+
+        // Outside the region they never scroll it
+        parser.parse(&mut screen, b"\x1b[6;1H\x1bD\x1bD");
+        assert_eq!(screen.cursor.row, 5);
+        parser.parse(&mut screen, b"\x1b[1;1H\x1bM");
+        assert_eq!(screen.cursor.row, 0);
+        let rows: String = (0..6).map(|row| row_text(&screen, row)).collect();
+        assert_eq!(rows, "H 234S");
+    }
 }
@@ -11,7 +11,7 @@ use std::path::PathBuf;
 use thiserror::Error;
 
 /// PTY size in rows and columns
-#[derive(Debug, Clone, Copy, Default)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
 pub struct PtySize {
     pub rows: u16,
     pub cols: u16,
@@ -19,6 +19,21 @@ pub struct PtySize {
     pub pixel_height: u16,
 }
 
+impl PtySize {
+    /// Size of a grid of `cell_width` x `cell_height` pixel cells
+    ///
+    /// Programs read the pixel size (`TIOCGWINSZ`) to scale sixel and kitty
+    /// images, so it should follow every resize and font size change.
+    pub fn with_cell_size(rows: u16, cols: u16, cell_width: f64, cell_height: f64) -> Self {
+        Self {
+            rows,
+            cols,
+            pixel_width: (cols as f64 * cell_width).round() as u16,
+            pixel_height: (rows as f64 * cell_height).round() as u16,
+        }
+    }
+}
+
 /// Errors that can occur with PTY operations
 #[derive(Error, Debug)]
 pub enum PtyError {
@@ -35,8 +50,11 @@ pub enum PtyError {
     NotRunning,
 }
 
+/// TERM value used when none is configured
+pub const DEFAULT_TERM: &str = "xterm-256color";
+
 /// PTY configuration
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone)]
 pub struct PtyConfig {
     /// Initial terminal size
     pub size: PtySize,
@@ -50,6 +68,101 @@ pub struct PtyConfig {
     pub env: Vec<(String, String)>,
     /// TERM environment variable value (default: xterm-256color)
     pub term: Option<String>,
+    /// Set COLORTERM=truecolor when TERM is a 256-color or direct-color
+    /// terminal type, unless `env` sets COLORTERM itself
+    pub advertise_truecolor: bool,
+    /// Start the PTY in raw mode (no echo, line editing or signal keys), for
+    /// programs that manage their own line discipline. Unix only.
+    pub raw: bool,
+}
+
+impl Default for PtyConfig {
+    fn default() -> Self {
+        Self {
+            size: PtySize::default(),
+            shell: None,
+            args: Vec::new(),
+            cwd: None,
+            env: Vec::new(),
+            term: None,
+            advertise_truecolor: true,
+            raw: false,
+        }
+    }
+}
+
+impl PtyConfig {
+    /// TERM value for the child
+    pub fn term(&self) -> &str {
+        self.term.as_deref().unwrap_or(DEFAULT_TERM)
+    }
+
+    /// COLORTERM value advertised to the child (None = unset)
+    pub fn colorterm(&self) -> Option<&'static str> {
+        (self.advertise_truecolor && term_supports_truecolor(self.term())).then_some("truecolor")
+    }
+
+    /// Whether `env` sets the variable `key`
+    pub fn sets_env(&self, key: &str) -> bool {
+        self.env.iter().any(|(k, _)| k == key)
+    }
+}
+
+/// Whether programs can be told a TERM type supports 24-bit color
+///
+/// Terminal types with 256 or direct colors are for terminals like cterm
+/// that also do 24-bit color; programs that want it look at COLORTERM,
+/// since few terminfo entries describe it.
+pub fn term_supports_truecolor(term: &str) -> bool {
+    term.ends_with("-256color")
+        || term.ends_with("-direct")
+        || term.contains("truecolor")
+        || term.contains("24bit")
+}
+
+/// Whether a terminfo entry for `term` is installed
+///
+/// Looks where ncurses does: $TERMINFO, ~/.terminfo, $TERMINFO_DIRS and the
+/// system directories, in both the Linux (`x/xterm`) and macOS (`78/xterm`)
+/// layouts.
+#[cfg(unix)]
+pub fn terminfo_installed(term: &str) -> bool {
+    let mut dirs: Vec<PathBuf> = Vec::new();
+    if let Some(dir) = std::env::var_os("TERMINFO") {
+        dirs.push(dir.into());
+    }
+    if let Some(home) = std::env::var_os("HOME") {
+        dirs.push(PathBuf::from(home).join(".terminfo"));
+    }
+    if let Some(list) = std::env::var_os("TERMINFO_DIRS") {
+        dirs.extend(std::env::split_paths(&list).filter(|dir| !dir.as_os_str().is_empty()));
+    }
+    dirs.extend(
+        [
+            "/etc/terminfo",
+            "/lib/terminfo",
+            "/usr/share/terminfo",
+            "/usr/lib/terminfo",
+            "/usr/local/share/terminfo",
+            "/opt/homebrew/share/terminfo",
+        ]
+        .iter()
+        .map(PathBuf::from),
+    );
+    terminfo_in_dirs(term, &dirs)
+}
+
+#[cfg(unix)]
+fn terminfo_in_dirs(term: &str, dirs: &[PathBuf]) -> bool {
+    let Some(first) = term.chars().next() else {
+        return false;
+    };
+    let subdirs = [first.to_string(), format!("{:02x}", first as u32)];
+    dirs.iter().any(|dir| {
+        subdirs
+            .iter()
+            .any(|subdir| dir.join(subdir).join(term).is_file())
+    })
 }
 
 // ============================================================================
@@ -60,7 +173,7 @@ pub struct PtyConfig {
 mod unix {
     use super::*;
     use std::ffi::{CStr, CString};
-    use std::os::unix::io::{AsRawFd, FromRawFd, RawFd};
+    use std::os::unix::io::{AsRawFd, FromRawFd, IntoRawFd, RawFd};
 
     /// Native PTY with exposed raw file descriptor
     pub struct Pty {
@@ -77,6 +190,12 @@ mod unix {
     impl Pty {
         /// Create a new PTY and spawn the shell
         pub fn new(config: &PtyConfig) -> Result<Self, PtyError> {
+            if !terminfo_installed(config.term()) {
+                log::warn!(
+                    "No terminfo entry for TERM={} is installed; programs may not work correctly",
+                    config.term()
+                );
+            }
             unsafe { Self::create_pty_and_spawn(config) }
         }
 
@@ -126,12 +245,12 @@ mod unix {
         }
 
         /// Resize the PTY
-        pub fn resize(&self, rows: u16, cols: u16) -> io::Result<()> {
+        pub fn resize(&self, size: PtySize) -> io::Result<()> {
             let size = libc::winsize {
-                ws_row: rows,
-                ws_col: cols,
-                ws_xpixel: 0,
-                ws_ypixel: 0,
+                ws_row: size.rows,
+                ws_col: size.cols,
+                ws_xpixel: size.pixel_width,
+                ws_ypixel: size.pixel_height,
             };
 
             let ret = unsafe { libc::ioctl(self.master_fd, libc::TIOCSWINSZ, &size) };
@@ -384,8 +503,8 @@ mod unix {
             let size = libc::winsize {
                 ws_row: config.size.rows,
                 ws_col: config.size.cols,
-                ws_xpixel: 0,
-                ws_ypixel: 0,
+                ws_xpixel: config.size.pixel_width,
+                ws_ypixel: config.size.pixel_height,
             };
             libc::ioctl(slave_fd, libc::TIOCSWINSZ, &size);
 
@@ -446,6 +565,15 @@ mod unix {
                 libc::close(slave_fd);
             }
 
+            // Switch the line discipline to raw mode if requested
+            if config.raw {
+                let mut termios: libc::termios = std::mem::zeroed();
+                if libc::tcgetattr(libc::STDIN_FILENO, &mut termios) == 0 {
+                    libc::cfmakeraw(&mut termios);
+                    libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &termios);
+                }
+            }
+
             // Change to the working directory if specified
             if let Some(ref cwd) = config.cwd {
                 match CString::new(cwd.to_string_lossy().as_bytes()) {
@@ -476,15 +604,24 @@ mod unix {
 
             // Set TERM environment variable
             let term = CString::new("TERM").unwrap();
-            let term_value = config.term.as_deref().unwrap_or("xterm-256color");
-            let term_value = CString::new(term_value)
-                .unwrap_or_else(|_| CString::new("xterm-256color").unwrap());
+            let term_value =
+                CString::new(config.term()).unwrap_or_else(|_| CString::new(DEFAULT_TERM).unwrap());
             libc::setenv(term.as_ptr(), term_value.as_ptr(), 1);
 
-            // Set COLORTERM to indicate true color support
-            let colorterm = CString::new("COLORTERM").unwrap();
-            let colorterm_value = CString::new("truecolor").unwrap();
-            libc::setenv(colorterm.as_ptr(), colorterm_value.as_ptr(), 1);
+            // Advertise true color support (or don't pass on the parent's),
+            // unless the configured environment sets COLORTERM
+            if !config.sets_env("COLORTERM") {
+                let colorterm = CString::new("COLORTERM").unwrap();
+                match config.colorterm() {
+                    Some(value) => {
+                        let colorterm_value = CString::new(value).unwrap();
+                        libc::setenv(colorterm.as_ptr(), colorterm_value.as_ptr(), 1);
+                    }
+                    None => {
+                        libc::unsetenv(colorterm.as_ptr());
+                    }
+                }
+            }
 
             // Determine the shell to execute
             let shell = config.shell.clone().unwrap_or_else(get_default_shell);
@@ -536,6 +673,16 @@ mod unix {
         }
     }
 
+    /// Gives up the PTY without hanging up the child, for handing the
+    /// session to another process
+    impl IntoRawFd for Pty {
+        fn into_raw_fd(self) -> RawFd {
+            let pty = std::mem::ManuallyDrop::new(self);
+            // SAFETY: `pty` is never dropped, so the File is moved out once
+            unsafe { std::ptr::read(&pty.master) }.into_raw_fd()
+        }
+    }
+
     /// Get the default shell for the current user
     fn get_default_shell() -> String {
         // Try to get shell from environment
@@ -660,10 +807,12 @@ mod windows {
         }
 
         /// Resize the PTY
-        pub fn resize(&self, rows: u16, cols: u16) -> io::Result<()> {
+        ///
+        /// ConPTY has no pixel size, so only the rows and columns are used.
+        pub fn resize(&self, size: PtySize) -> io::Result<()> {
             let size = COORD {
-                X: cols as i16,
-                Y: rows as i16,
+                X: size.cols as i16,
+                Y: size.rows as i16,
             };
             let hr = unsafe { ResizePseudoConsole(self.hpc, size) };
             if hr != S_OK {
@@ -1015,11 +1164,20 @@ mod windows {
         }
 
         // Set TERM (use config value or default to xterm-256color)
-        let term_value = config.term.as_deref().unwrap_or("xterm-256color");
-        env_map.insert("TERM".to_string(), term_value.to_string());
-
-        // Set COLORTERM to indicate true color support
-        env_map.insert("COLORTERM".to_string(), "truecolor".to_string());
+        env_map.insert("TERM".to_string(), config.term().to_string());
+
+        // Advertise true color support (or don't pass on the parent's),
+        // unless the configured environment sets COLORTERM
+        if !config.sets_env("COLORTERM") {
+            match config.colorterm() {
+                Some(value) => {
+                    env_map.insert("COLORTERM".to_string(), value.to_string());
+                }
+                None => {
+                    env_map.remove("COLORTERM");
+                }
+            }
+        }
 
         // Build the environment block
         // Format: KEY1=VALUE1\0KEY2=VALUE2\0...\0\0
@@ -1079,6 +1237,19 @@ mod tests {
         assert_eq!(size.pixel_height, 0);
     }
 
+    #[test]
+    fn test_pty_size_with_cell_size() {
+        let size = PtySize::with_cell_size(24, 80, 8.4, 17.5);
+        assert_eq!((size.rows, size.cols), (24, 80));
+        assert_eq!(size.pixel_width, 672);
+        assert_eq!(size.pixel_height, 420);
+
+        // Huge grids saturate instead of wrapping
+        let size = PtySize::with_cell_size(1000, 10000, 10.0, 100.0);
+        assert_eq!(size.pixel_width, u16::MAX);
+        assert_eq!(size.pixel_height, u16::MAX);
+    }
+
     /// Helper to wait with a timeout for tests
     #[allow(dead_code)]
     fn wait_with_timeout(pty: &mut Pty, timeout_ms: u64) -> Option<i32> {
@@ -1155,6 +1326,34 @@ mod tests {
         pty.send_signal(15).expect("Failed to send signal");
     }
 
+    #[test]
+    #[cfg(unix)]
+    fn test_pty_raw_mode_unix() {
+        let config = PtyConfig {
+            size: PtySize {
+                rows: 24,
+                cols: 80,
+                ..Default::default()
+            },
+            shell: Some("/bin/sh".to_string()),
+            args: vec!["-c".to_string(), "printf 'a\\nb\\n'".to_string()],
+            raw: true,
+            ..Default::default()
+        };
+
+        let mut pty = Pty::new(&config).expect("Failed to create PTY");
+
+        // Give the command time to produce output
+        std::thread::sleep(std::time::Duration::from_millis(100));
+
+        // Raw mode turns off output processing, so LF isn't expanded to CRLF
+        let mut buf = [0u8; 1024];
+        let n = pty.read(&mut buf).expect("Failed to read");
+        assert_eq!(&buf[..n], b"a\nb\n");
+
+        let _ = wait_with_timeout(&mut pty, 5000);
+    }
+
     #[test]
     #[cfg(unix)]
     fn test_pty_resize_unix() {
@@ -1172,8 +1371,14 @@ mod tests {
         let pty = Pty::new(&config).expect("Failed to create PTY");
 
         // Test resize
-        pty.resize(40, 120).expect("Failed to resize PTY");
-        pty.resize(25, 80).expect("Failed to resize PTY again");
+        pty.resize(PtySize::with_cell_size(40, 120, 8.0, 16.0))
+            .expect("Failed to resize PTY");
+        pty.resize(PtySize {
+            rows: 25,
+            cols: 80,
+            ..Default::default()
+        })
+        .expect("Failed to resize PTY again");
 
         // Clean up
         let _ = pty.send_signal(15);
@@ -1415,4 +1620,48 @@ mod tests {
         let output = String::from_utf8_lossy(&buf[..n]);
         assert!(output.contains("test_value_123"), "Output was: {}", output);
     }
+
+    #[test]
+    fn test_colorterm() {
+        let config = PtyConfig::default();
+        assert_eq!(config.term(), "xterm-256color");
+        assert_eq!(config.colorterm(), Some("truecolor"));
+
+        let vt100 = PtyConfig {
+            term: Some("vt100".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(vt100.colorterm(), None);
+        assert!(term_supports_truecolor("xterm-direct"));
+
+        let disabled = PtyConfig {
+            advertise_truecolor: false,
+            ..Default::default()
+        };
+        assert_eq!(disabled.colorterm(), None);
+
+        let overridden = PtyConfig {
+            env: vec![("COLORTERM".to_string(), "24bit".to_string())],
+            ..Default::default()
+        };
+        assert!(overridden.sets_env("COLORTERM"));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_terminfo_in_dirs() {
+        let dir = std::env::temp_dir().join(format!("cterm-terminfo-test-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("x")).unwrap();
+        std::fs::create_dir_all(dir.join("73")).unwrap();
+        std::fs::write(dir.join("x").join("xterm-cterm"), b"").unwrap();
+        std::fs::write(dir.join("73").join("screen-cterm"), b"").unwrap();
+
+        let dirs = [PathBuf::from("/nonexistent"), dir.clone()];
+        assert!(terminfo_in_dirs("xterm-cterm", &dirs));
+        assert!(terminfo_in_dirs("screen-cterm", &dirs));
+        assert!(!terminfo_in_dirs("other-cterm", &dirs));
+        assert!(!terminfo_in_dirs("", &dirs));
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
 }
@@ -0,0 +1,238 @@
+//! Session recording (typescript)
+//!
+//! Mirrors the bytes read from the PTY, and optionally the bytes written to
+//! it, into a file like `script(1)` does. Raw recordings keep every byte so
+//! they can be replayed with `cat`; text recordings drop escape sequences and
+//! control characters to leave a readable transcript.
+
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// What a session recording stores
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RecordingMode {
+    /// Plain text with escape sequences, carriage returns and other control
+    /// characters removed
+    #[default]
+    Text,
+    /// The bytes exactly as they went through the PTY
+    Raw,
+}
+
+impl RecordingMode {
+    /// File extension used for recordings in this mode
+    pub fn extension(self) -> &'static str {
+        match self {
+            RecordingMode::Text => "txt",
+            RecordingMode::Raw => "log",
+        }
+    }
+}
+
+/// State of the escape sequence filter used by text recordings
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum FilterState {
+    #[default]
+    Ground,
+    /// After ESC
+    Escape,
+    /// Inside an ESC sequence with intermediate bytes
+    EscapeIntermediate,
+    /// Inside a CSI sequence
+    Csi,
+    /// Inside an OSC, DCS, SOS, PM or APC string
+    String,
+    /// After ESC inside a string (possible ST)
+    StringEscape,
+}
+
+/// Removes escape sequences and control characters from a byte stream
+///
+/// The state is kept between calls, so sequences split across reads are
+/// still removed. Bytes of multi-byte UTF-8 characters pass through as-is.
+#[derive(Debug, Clone, Default)]
+struct TextFilter {
+    state: FilterState,
+    /// Turn CR into a line break (keyboard input sends CR for Enter)
+    cr_as_newline: bool,
+}
+
+impl TextFilter {
+    fn filter(&mut self, data: &[u8], out: &mut Vec<u8>) {
+        for &byte in data {
+            self.state = match self.state {
+                FilterState::Ground => match byte {
+                    0x1b => FilterState::Escape,
+                    b'\n' | b'\t' => {
+                        out.push(byte);
+                        FilterState::Ground
+                    }
+                    b'\r' if self.cr_as_newline => {
+                        out.push(b'\n');
+                        FilterState::Ground
+                    }
+                    0x00..=0x1f | 0x7f => FilterState::Ground,
+                    _ => {
+                        out.push(byte);
+                        FilterState::Ground
+                    }
+                },
+                FilterState::Escape => match byte {
+                    b'[' => FilterState::Csi,
+                    b']' | b'P' | b'X' | b'^' | b'_' => FilterState::String,
+                    0x1b => FilterState::Escape,
+                    0x20..=0x2f => FilterState::EscapeIntermediate,
+                    _ => FilterState::Ground,
+                },
+                FilterState::EscapeIntermediate => match byte {
+                    0x20..=0x2f => FilterState::EscapeIntermediate,
+                    _ => FilterState::Ground,
+                },
+                FilterState::Csi => match byte {
+                    0x40..=0x7e => FilterState::Ground,
+                    _ => FilterState::Csi,
+                },
+                FilterState::String => match byte {
+                    0x07 => FilterState::Ground,
+                    0x1b => FilterState::StringEscape,
+                    _ => FilterState::String,
+                },
+                FilterState::StringEscape => match byte {
+                    b'\\' => FilterState::Ground,
+                    0x1b => FilterState::StringEscape,
+                    _ => FilterState::String,
+                },
+            };
+        }
+    }
+}
+
+/// Writes the traffic of a terminal session to a file
+#[derive(Debug)]
+pub struct SessionRecorder {
+    path: PathBuf,
+    mode: RecordingMode,
+    record_input: bool,
+    writer: BufWriter<File>,
+    output_filter: TextFilter,
+    input_filter: TextFilter,
+    /// Scratch buffer for filtered text
+    buf: Vec<u8>,
+}
+
+impl SessionRecorder {
+    /// Start a recording, appending to `path` if it already exists
+    pub fn create(path: &Path, mode: RecordingMode, record_input: bool) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            mode,
+            record_input,
+            writer: BufWriter::new(file),
+            output_filter: TextFilter::default(),
+            input_filter: TextFilter {
+                cr_as_newline: true,
+                ..TextFilter::default()
+            },
+            buf: Vec::new(),
+        })
+    }
+
+    /// Path of the recording file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// What the recording stores
+    pub fn mode(&self) -> RecordingMode {
+        self.mode
+    }
+
+    /// Record bytes read from the PTY
+    pub fn record_output(&mut self, data: &[u8]) -> io::Result<()> {
+        match self.mode {
+            RecordingMode::Raw => self.writer.write_all(data),
+            RecordingMode::Text => {
+                self.buf.clear();
+                self.output_filter.filter(data, &mut self.buf);
+                self.writer.write_all(&self.buf)
+            }
+        }
+    }
+
+    /// Record bytes written to the PTY (ignored unless input is recorded)
+    pub fn record_input(&mut self, data: &[u8]) -> io::Result<()> {
+        if !self.record_input {
+            return Ok(());
+        }
+        match self.mode {
+            RecordingMode::Raw => self.writer.write_all(data),
+            RecordingMode::Text => {
+                self.buf.clear();
+                self.input_filter.filter(data, &mut self.buf);
+                self.writer.write_all(&self.buf)
+            }
+        }
+    }
+
+    /// Flush the recording and close the file, returning its path
+    pub fn finish(mut self) -> io::Result<PathBuf> {
+        self.writer.flush()?;
+        Ok(self.path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(data: &[&[u8]]) -> String {
+        let mut filter = TextFilter::default();
+        let mut out = Vec::new();
+        for chunk in data {
+            filter.filter(chunk, &mut out);
+        }
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_text_filter() {
+        assert_eq!(filter(&[b"\x1b[1;32mok\x1b[0m done\r\n"]), "ok done\n");
+        // OSC terminated by BEL and by ST, charset designation, bell
+        assert_eq!(
+            filter(&[b"\x1b]0;title\x07a\x1b]133;A\x1b\\b\x1b(Bc\x07"]),
+            "abc"
+        );
+        // Sequences split across reads
+        assert_eq!(
+            filter(&[b"x\x1b[3", b"8;5;1", "mé\x1b".as_bytes(), b"]8;;\x1b\\y"]),
+            "xéy"
+        );
+    }
+
+    #[test]
+    fn test_recorder_modes() {
+        let dir = std::env::temp_dir();
+        let text_path = dir.join(format!("cterm-recording-test-{}.txt", std::process::id()));
+        let raw_path = dir.join(format!("cterm-recording-test-{}.log", std::process::id()));
+
+        let mut text = SessionRecorder::create(&text_path, RecordingMode::Text, true).unwrap();
+        text.record_output(b"\x1b[1m$ \x1b[0m").unwrap();
+        text.record_input(b"ls\r").unwrap();
+        text.record_output(b"a b\r\n").unwrap();
+        assert_eq!(text.finish().unwrap(), text_path);
+        assert_eq!(std::fs::read_to_string(&text_path).unwrap(), "$ ls\na b\n");
+
+        let mut raw = SessionRecorder::create(&raw_path, RecordingMode::Raw, false).unwrap();
+        raw.record_output(b"\x1b[1mhi\x1b[0m\r\n").unwrap();
+        raw.record_input(b"ignored").unwrap();
+        raw.finish().unwrap();
+        assert_eq!(std::fs::read(&raw_path).unwrap(), b"\x1b[1mhi\x1b[0m\r\n");
+
+        std::fs::remove_file(&text_path).ok();
+        std::fs::remove_file(&raw_path).ok();
+    }
+}
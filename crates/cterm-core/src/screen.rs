@@ -3,9 +3,15 @@
 //! Manages the visible grid and scrollback history, handling resize
 //! and scroll operations.
 
-use crate::cell::{Cell, CellStyle};
+use crate::bidi::BidiLine;
+use crate::cell::{Cell, CellStyle, Hyperlink};
+use crate::color::{ColorOverrides, Rgb};
+use crate::damage::Damage;
+use crate::directory::ReportedDirectory;
 use crate::drcs::{DrcsFont, DrcsGlyph};
 use crate::grid::{Grid, Row};
+use crate::local_echo::Predictions;
+use crate::secrets::SecretMatcher;
 use crate::sixel::SixelImage;
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, VecDeque};
@@ -221,10 +227,14 @@ impl Ord for SelectionPoint {
 }
 
 /// Text selection state
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Selection {
     /// Starting point of selection (where mouse was pressed)
     pub anchor: SelectionPoint,
+    /// Start of original anchor region (for word/line mode). `anchor` is swapped
+    /// to the region end when extending backwards, so the start is kept here.
+    #[serde(default)]
+    pub anchor_start: Option<SelectionPoint>,
     /// End of original anchor region (for word/line mode, the originally selected word/line end)
     /// This ensures the original word/line stays selected when extending in either direction
     pub anchor_end: Option<SelectionPoint>,
@@ -248,11 +258,54 @@ pub enum SelectionMode {
     Block,
 }
 
+/// What is copied for text carrying an OSC 8 hyperlink
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HyperlinkCopyMode {
+    /// The visible text
+    #[default]
+    Text,
+    /// The link's URL instead of its text
+    Url,
+    /// A Markdown link, `[text](url)`
+    Markdown,
+}
+
+/// How lines longer than the screen width are shown
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LongLineMode {
+    /// Continue on the next row
+    #[default]
+    Wrap,
+    /// Cut off at the last column; the rest is kept for horizontal scrolling
+    Truncate,
+}
+
+impl HyperlinkCopyMode {
+    /// Copied form of the link text `label` pointing to `uri`
+    fn format(self, label: &str, uri: &str) -> String {
+        match self {
+            HyperlinkCopyMode::Text => label.to_string(),
+            HyperlinkCopyMode::Url => uri.to_string(),
+            HyperlinkCopyMode::Markdown if label.is_empty() => format!("<{}>", uri),
+            HyperlinkCopyMode::Markdown => format!(
+                "[{}]({})",
+                label.replace('[', "\\[").replace(']', "\\]"),
+                uri.replace(' ', "%20")
+                    .replace('(', "%28")
+                    .replace(')', "%29")
+            ),
+        }
+    }
+}
+
 impl Selection {
     /// Create a new selection starting at a point
     pub fn new(point: SelectionPoint, mode: SelectionMode) -> Self {
         Self {
             anchor: point,
+            anchor_start: None,
             anchor_end: None,
             end: point,
             mode,
@@ -267,6 +320,7 @@ impl Selection {
     ) -> Self {
         Self {
             anchor: anchor_start,
+            anchor_start: Some(anchor_start),
             anchor_end: Some(anchor_end),
             end: anchor_end,
             mode,
@@ -322,6 +376,52 @@ impl Selection {
     }
 }
 
+/// A block of cells copied out of the screen, so it can be drawn without
+/// the screen (e.g. to export a selection as an image)
+///
+/// Concealed text is already blanked and trailing blank cells are trimmed.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CellRegion {
+    /// Rows of cells, top to bottom
+    pub rows: Vec<Vec<Cell>>,
+}
+
+impl CellRegion {
+    /// Width in cells of the widest row
+    pub fn width(&self) -> usize {
+        self.rows.iter().map(Vec::len).max().unwrap_or(0)
+    }
+
+    /// Number of rows
+    pub fn height(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Whether there is nothing to draw
+    pub fn is_empty(&self) -> bool {
+        self.rows.iter().all(Vec::is_empty)
+    }
+}
+
+/// What an image is positioned relative to
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ImageAnchor {
+    /// Scrolls with the text; `TerminalImage::line` is a stable line number
+    #[default]
+    Grid,
+    /// Stays put in the viewport; `TerminalImage::line` is a visible row
+    Viewport,
+}
+
+/// How an image is layered and anchored (Kitty graphics placements)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ImagePlacement {
+    /// Stacking order: higher is drawn on top, negative is drawn below text
+    pub z_index: i32,
+    /// Whether the image scrolls with the text
+    pub anchor: ImageAnchor,
+}
+
 /// A terminal image (from Sixel or other protocols)
 #[derive(Debug, Clone)]
 pub struct TerminalImage {
@@ -329,24 +429,92 @@ pub struct TerminalImage {
     pub id: u64,
     /// Column position (cell coordinates)
     pub col: usize,
-    /// Absolute line number (scrollback.len() + row at time of creation)
+    /// Stable line number of the top row (`scrollback_total_pushed + row`
+    /// when placed), or the visible row for viewport-anchored images
     pub line: usize,
     /// Width in cells
     pub cell_width: usize,
     /// Height in cells
     pub cell_height: usize,
-    /// RGBA pixel data
+    /// RGBA pixel data, empty once evicted
     pub data: Arc<Vec<u8>>,
     /// Pixel width
     pub pixel_width: usize,
     /// Pixel height
     pub pixel_height: usize,
+    /// Stacking order and anchor
+    pub placement: ImagePlacement,
+}
+
+impl TerminalImage {
+    /// Whether the pixel data was dropped to stay within the image limit
+    ///
+    /// The image keeps its place so it can be reported, but isn't drawn.
+    pub fn is_evicted(&self) -> bool {
+        self.data.is_empty()
+    }
+}
+
+/// Default for [`Screen::set_max_images`]
+pub const DEFAULT_MAX_IMAGES: usize = 100;
+
+/// Semantic prompt mark (OSC 133, FinalTerm shell integration)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SemanticMark {
+    /// Start of the prompt (OSC 133;A)
+    PromptStart,
+    /// End of the prompt, start of the command line (OSC 133;B)
+    CommandStart,
+    /// Command was submitted, start of its output (OSC 133;C)
+    OutputStart,
+    /// Command finished, with optional exit code (OSC 133;D)
+    CommandEnd(Option<i32>),
 }
 
+/// Positions of the semantic marks for one prompt/command cycle
+///
+/// Lines are stable line numbers (lines ever pushed to scrollback + grid row),
+/// so they stay valid while scrollback is trimmed.
+#[derive(Debug, Clone, Copy, Default)]
+struct CommandZone {
+    command_start: Option<SelectionPoint>,
+    output_start: Option<SelectionPoint>,
+    command_end: Option<SelectionPoint>,
+    exit_code: Option<i32>,
+}
+
+/// Maximum badge length in characters
+const MAX_BADGE_LEN: usize = 256;
+
 /// Sentinel column value meaning "end of row" for line selection mode.
 /// Used in `SelectionPoint::col` to indicate the selection extends to the end of the line.
 const COL_END_OF_ROW: usize = usize::MAX;
 
+/// Maximum number of command lines remembered for inline suggestions
+const MAX_COMMAND_HISTORY: usize = 1000;
+
+/// Maximum number of titles saved with XTWINOPS 22
+const MAX_TITLE_STACK: usize = 10;
+
+/// Spacing of the default tab stops unless configured otherwise
+pub const DEFAULT_TAB_WIDTH: usize = 8;
+
+/// Maximum number of cells kept past the last column of a truncated line
+const MAX_LINE_OVERFLOW: usize = 16384;
+
+/// Joins the emoji either side of it into one glyph (e.g. a family)
+const ZERO_WIDTH_JOINER: char = '\u{200D}';
+
+/// Whether `c` is a regional indicator; two of them make a flag
+fn is_regional_indicator(c: char) -> bool {
+    matches!(c, '\u{1F1E6}'..='\u{1F1FF}')
+}
+
+/// Whether `c` is a skin tone modifier for the emoji before it
+fn is_emoji_modifier(c: char) -> bool {
+    matches!(c, '\u{1F3FB}'..='\u{1F3FF}')
+}
+
 /// Terminal screen state
 #[derive(Debug)]
 pub struct Screen {
@@ -362,8 +530,14 @@ pub struct Screen {
     pub cursor: Cursor,
     /// Saved cursor state (for save/restore)
     saved_cursor: Option<Cursor>,
-    /// Alternate saved cursor (for alternate screen)
+    /// Saved cursor of the buffer that isn't active (each buffer has its own)
     alt_saved_cursor: Option<Cursor>,
+    /// Position saved by SCOSC (`CSI s`), as (row, col), kept apart from the
+    /// DECSC state
+    saved_position: Option<(usize, usize)>,
+    /// Contents of the alternate buffer while the primary one is shown, kept
+    /// by DECSET 47 so switching back shows them again
+    inactive_alternate_grid: Option<Grid>,
     /// Scroll region
     scroll_region: ScrollRegion,
     /// Current cell styling
@@ -374,14 +548,22 @@ pub struct Screen {
     pub title: String,
     /// Icon name
     pub icon_name: String,
+    /// Working directory last reported by the shell (OSC 7)
+    pub current_directory: Option<ReportedDirectory>,
     /// Whether content has changed since last render
     pub dirty: bool,
     /// Current scroll offset (for viewing scrollback)
     pub scroll_offset: usize,
     /// Bell was triggered (should be cleared after notification)
     pub bell: bool,
+    /// A prompt was marked (OSC 133 A) since the last `take_prompt_started`
+    prompt_started: bool,
+    /// A command finished (OSC 133 D) since the last `take_command_finished`
+    command_finished: bool,
     /// Tab stop positions (columns where tabs stop)
     tab_stops: Vec<bool>,
+    /// Spacing of the default tab stops
+    tab_width: usize,
     /// Pending responses to send back to the PTY (for DSR etc)
     pending_responses: Vec<Vec<u8>>,
     /// Pending clipboard operations from OSC 52
@@ -392,6 +574,8 @@ pub struct Screen {
     pub selection: Option<Selection>,
     /// Terminal images (Sixel, etc.)
     images: HashMap<u64, TerminalImage>,
+    /// Most images that keep their pixel data (0 = no limit)
+    max_images: usize,
     /// Next image ID
     next_image_id: u64,
     /// Pending file transfer operations (iTerm2 OSC 1337 with inline=0)
@@ -405,8 +589,61 @@ pub struct Screen {
     /// DRCS fonts (soft fonts) keyed by designator
     drcs_fonts: HashMap<String, DrcsFont>,
     /// Total number of lines ever pushed to scrollback (monotonically increasing).
-    /// Used to compute correct absolute line numbers for image pruning.
+    /// Used for stable line numbers (images, bookmarks, search matches).
     scrollback_total_pushed: usize,
+    /// Semantic zone of the prompt/command currently in progress (OSC 133)
+    current_zone: Option<CommandZone>,
+    /// Semantic zone of the most recently finished command (OSC 133)
+    last_zone: Option<CommandZone>,
+    /// Command lines entered in this screen (OSC 133 B to C), oldest first
+    command_history: VecDeque<String>,
+    /// Whether to suggest completions from the command history at the prompt
+    inline_suggestions: bool,
+    /// Whether right-to-left text is reordered for display
+    bidi: bool,
+    /// Whether long lines wrap or are truncated
+    long_line_mode: LongLineMode,
+    /// Whether emoji ZWJ sequences, skin tones and flags join into one
+    /// two-cell glyph
+    grapheme_clusters: bool,
+    /// Cells written past the last column of the cursor row (truncate mode)
+    overflow_col: usize,
+    /// Columns the view is scrolled right by (truncated lines)
+    horizontal_offset: usize,
+    /// What is copied for hyperlinked text
+    copy_hyperlink_mode: HyperlinkCopyMode,
+    /// Whether concealed (SGR 8) text is shown anyway
+    reveal_concealed: bool,
+    /// Patterns of secrets masked in the display (None = no masking)
+    secret_matcher: Option<SecretMatcher>,
+    /// Default colors set by the program (OSC 10/11/12)
+    color_overrides: ColorOverrides,
+    /// Titles saved with XTWINOPS 22, as (title, icon name), most recent last
+    title_stack: Vec<(String, String)>,
+    /// Whether typed characters are drawn before the program echoes them
+    local_echo: bool,
+    /// Typed characters waiting for their echo (local echo)
+    predictions: Predictions,
+    /// User bookmarks (stable line numbers, like the semantic zones), sorted
+    bookmarks: Vec<usize>,
+    /// Badge format set by iTerm2 SetBadgeFormat (OSC 1337)
+    badge_format: Option<String>,
+    /// Active search, whose matches are highlighted by the renderers
+    search: Option<ActiveSearch>,
+    /// Whether the active search picks up matches in new output
+    search_follows_output: bool,
+    /// Whether searches skip the visible screen
+    search_scrollback_only: bool,
+    /// Whether leaving the alternate screen brings back the primary screen's
+    /// cursor visibility (DECTCEM)
+    cursor_visibility_per_screen: bool,
+    /// Cursor visibility of the primary screen while the alternate one is shown
+    primary_show_cursor: bool,
+    /// What the last rendered frame showed besides cell contents
+    last_frame: Option<FrameState>,
+    /// Whether something drawn over the whole view (images, DRCS glyphs,
+    /// search highlights) changed since the last frame
+    view_damaged: bool,
 }
 
 impl Screen {
@@ -439,20 +676,26 @@ impl Screen {
             cursor,
             saved_cursor,
             alt_saved_cursor,
+            saved_position: None,
+            inactive_alternate_grid: None,
             scroll_region,
             style,
             modes,
             title,
             icon_name: String::new(),
+            current_directory: None,
             dirty: true,
             scroll_offset,
             bell: false,
+            prompt_started: false,
+            command_finished: false,
             tab_stops,
             pending_responses: Vec::new(),
             pending_clipboard_ops: Vec::new(),
             pending_color_queries: Vec::new(),
             selection: None,
             images: HashMap::new(),
+            max_images: DEFAULT_MAX_IMAGES,
             next_image_id: 0,
             pending_file_transfers: Vec::new(),
             next_file_transfer_id: 0,
@@ -460,6 +703,32 @@ impl Screen {
             cell_width_hint: 8.0,   // Default assumption
             drcs_fonts: HashMap::new(),
             scrollback_total_pushed: scrollback_len,
+            current_zone: None,
+            last_zone: None,
+            command_history: VecDeque::new(),
+            inline_suggestions: false,
+            bidi: false,
+            long_line_mode: LongLineMode::Wrap,
+            grapheme_clusters: true,
+            overflow_col: 0,
+            horizontal_offset: 0,
+            tab_width: DEFAULT_TAB_WIDTH,
+            copy_hyperlink_mode: HyperlinkCopyMode::Text,
+            reveal_concealed: false,
+            secret_matcher: None,
+            color_overrides: ColorOverrides::default(),
+            title_stack: Vec::new(),
+            local_echo: false,
+            predictions: Predictions::default(),
+            bookmarks: Vec::new(),
+            badge_format: None,
+            search: None,
+            search_follows_output: true,
+            search_scrollback_only: false,
+            cursor_visibility_per_screen: true,
+            primary_show_cursor: true,
+            last_frame: None,
+            view_damaged: true,
         }
     }
 
@@ -483,6 +752,8 @@ impl Screen {
             },
             saved_cursor: None,
             alt_saved_cursor: None,
+            saved_position: None,
+            inactive_alternate_grid: None,
             scroll_region: ScrollRegion {
                 top: 0,
                 bottom: height,
@@ -491,15 +762,19 @@ impl Screen {
             modes,
             title: String::new(),
             icon_name: String::new(),
+            current_directory: None,
             dirty: true,
             scroll_offset: 0,
             bell: false,
-            tab_stops: Self::default_tab_stops(width),
+            prompt_started: false,
+            command_finished: false,
+            tab_stops: Self::default_tab_stops(width, DEFAULT_TAB_WIDTH),
             pending_responses: Vec::new(),
             pending_clipboard_ops: Vec::new(),
             pending_color_queries: Vec::new(),
             selection: None,
             images: HashMap::new(),
+            max_images: DEFAULT_MAX_IMAGES,
             next_image_id: 0,
             pending_file_transfers: Vec::new(),
             next_file_transfer_id: 0,
@@ -507,9 +782,56 @@ impl Screen {
             cell_width_hint: 8.0,   // Default assumption
             drcs_fonts: HashMap::new(),
             scrollback_total_pushed: 0,
+            current_zone: None,
+            last_zone: None,
+            command_history: VecDeque::new(),
+            inline_suggestions: false,
+            bidi: false,
+            long_line_mode: LongLineMode::Wrap,
+            grapheme_clusters: true,
+            overflow_col: 0,
+            horizontal_offset: 0,
+            tab_width: DEFAULT_TAB_WIDTH,
+            copy_hyperlink_mode: HyperlinkCopyMode::Text,
+            reveal_concealed: false,
+            secret_matcher: None,
+            color_overrides: ColorOverrides::default(),
+            title_stack: Vec::new(),
+            local_echo: false,
+            predictions: Predictions::default(),
+            bookmarks: Vec::new(),
+            badge_format: None,
+            search: None,
+            search_follows_output: true,
+            search_scrollback_only: false,
+            cursor_visibility_per_screen: true,
+            primary_show_cursor: true,
+            last_frame: None,
+            view_damaged: true,
         }
     }
 
+    /// Set the badge format (iTerm2 SetBadgeFormat); an empty format clears it
+    pub fn set_badge_format(&mut self, format: &str) {
+        let format: String = format.chars().take(MAX_BADGE_LEN).collect();
+        self.badge_format = (!format.trim().is_empty()).then_some(format);
+        self.dirty = true;
+    }
+
+    /// Get the badge format as set by the application, if any
+    pub fn badge_format(&self) -> Option<&str> {
+        self.badge_format.as_deref()
+    }
+
+    /// Get the badge text to display, if any
+    ///
+    /// `\(session.name)` is replaced with the window title; other
+    /// interpolated variables are shown literally.
+    pub fn badge(&self) -> Option<String> {
+        let format = self.badge_format.as_ref()?;
+        Some(format.replace("\\(session.name)", &self.title))
+    }
+
     /// Queue a response to be sent back through the PTY
     pub fn queue_response(&mut self, response: Vec<u8>) {
         self.pending_responses.push(response);
@@ -541,6 +863,74 @@ impl Screen {
         self.pending_color_queries.push(query);
     }
 
+    /// Set (or with None, reset) a default color from OSC 10-12 / 110-112
+    pub fn set_dynamic_color(&mut self, osc_code: u8, color: Option<Rgb>) {
+        let slot = match osc_code {
+            10 => &mut self.color_overrides.foreground,
+            11 => &mut self.color_overrides.background,
+            12 => &mut self.color_overrides.cursor,
+            _ => return,
+        };
+        if *slot != color {
+            *slot = color;
+            self.dirty = true;
+            self.view_damaged = true;
+        }
+    }
+
+    /// Set (or with None, reset) palette entry `index` from OSC 4 / 104
+    pub fn set_palette_color(&mut self, index: u8, color: Option<Rgb>) {
+        let changed = match color {
+            Some(color) => self.color_overrides.palette.insert(index, color) != Some(color),
+            None => self.color_overrides.palette.remove(&index).is_some(),
+        };
+        if changed {
+            self.dirty = true;
+            self.view_damaged = true;
+        }
+    }
+
+    /// Reset all palette entries set with OSC 4 to the theme's (OSC 104)
+    pub fn reset_palette(&mut self) {
+        if !self.color_overrides.palette.is_empty() {
+            self.color_overrides.palette.clear();
+            self.dirty = true;
+            self.view_damaged = true;
+        }
+    }
+
+    /// Colors set by the program, replacing the theme's
+    pub fn color_overrides(&self) -> &ColorOverrides {
+        &self.color_overrides
+    }
+
+    /// Save the title and icon name (XTWINOPS 22)
+    ///
+    /// The stack keeps the 10 most recent entries, like xterm's.
+    pub fn push_title(&mut self) {
+        if self.title_stack.len() >= MAX_TITLE_STACK {
+            self.title_stack.remove(0);
+        }
+        self.title_stack
+            .push((self.title.clone(), self.icon_name.clone()));
+    }
+
+    /// Restore the title and/or icon name saved last (XTWINOPS 23)
+    ///
+    /// `which` is 0 for both, 1 for the icon name only and 2 for the title
+    /// only.
+    pub fn pop_title(&mut self, which: u16) {
+        let Some((title, icon_name)) = self.title_stack.pop() else {
+            return;
+        };
+        if which != 1 {
+            self.title = title;
+        }
+        if which != 2 {
+            self.icon_name = icon_name;
+        }
+    }
+
     /// Take all pending color queries (drains the queue)
     pub fn take_color_queries(&mut self) -> Vec<ColorQuery> {
         std::mem::take(&mut self.pending_color_queries)
@@ -595,9 +985,26 @@ impl Screen {
         !self.pending_responses.is_empty()
     }
 
-    /// Create default tab stops (every 8 columns)
-    fn default_tab_stops(width: usize) -> Vec<bool> {
-        (0..width).map(|i| i % 8 == 0 && i > 0).collect()
+    /// Create default tab stops (every `tab_width` columns)
+    fn default_tab_stops(width: usize, tab_width: usize) -> Vec<bool> {
+        (0..width).map(|i| i % tab_width == 0 && i > 0).collect()
+    }
+
+    /// Set the spacing of the default tab stops
+    ///
+    /// Stops set with HTS or cleared with TBC are replaced by the new default
+    /// ones, which also come back after a full reset (RIS).
+    pub fn set_tab_width(&mut self, tab_width: usize) {
+        let tab_width = tab_width.max(1);
+        if self.tab_width != tab_width {
+            self.tab_width = tab_width;
+            self.tab_stops = Self::default_tab_stops(self.width(), tab_width);
+        }
+    }
+
+    /// Spacing of the default tab stops
+    pub fn tab_width(&self) -> usize {
+        self.tab_width
     }
 
     /// Set a tab stop at the current cursor position
@@ -735,10 +1142,14 @@ impl Screen {
         let old_width = self.width();
 
         self.grid.resize(width, height);
+        self.predictions.clear();
 
         if let Some(ref mut alt) = self.alternate_grid {
             alt.resize(width, height);
         }
+        if let Some(ref mut alt) = self.inactive_alternate_grid {
+            alt.resize(width, height);
+        }
 
         // Update scroll region
         // If scroll region was at full screen height, extend it to new height
@@ -755,9 +1166,9 @@ impl Screen {
 
         // Resize tab stops array to match new width
         self.tab_stops.resize(width, false);
-        // Set default tab stops (every 8 columns) for new columns
+        // Set default tab stops for new columns
         for i in old_width..width {
-            self.tab_stops[i] = i % 8 == 0;
+            self.tab_stops[i] = i % self.tab_width == 0;
         }
 
         self.dirty = true;
@@ -781,17 +1192,29 @@ impl Screen {
     /// Put a character at the current cursor position
     pub fn put_char(&mut self, c: char) {
         let width = unicode_width::UnicodeWidthChar::width(c).unwrap_or(1);
+        if self.join_grapheme(c, width) {
+            return;
+        }
 
-        // Handle auto-wrap
-        if self.cursor.col >= self.width() {
-            if self.modes.auto_wrap {
-                self.carriage_return();
-                self.line_feed();
+        // Handle auto-wrap. The cursor sits at `width` after the last column
+        // was written (wrap pending); a wide character that doesn't fit in the
+        // remaining columns wraps early as well.
+        if self.cursor.col + width > self.width() {
+            if self.modes.auto_wrap
+                && self.cursor.col > 0
+                && self.long_line_mode == LongLineMode::Truncate
+            {
+                self.put_overflow_char(c, width);
+                return;
+            }
+            if self.modes.auto_wrap && self.cursor.col > 0 {
                 if let Some(row) = self.grid.row_mut(self.cursor.row) {
                     row.wrapped = true;
                 }
+                self.carriage_return();
+                self.line_feed();
             } else {
-                self.cursor.col = self.width() - 1;
+                self.cursor.col = self.width().saturating_sub(width);
             }
         }
 
@@ -805,7 +1228,7 @@ impl Screen {
 
         // Write the character
         if let Some(cell) = self.grid.get_mut(self.cursor.row, self.cursor.col) {
-            cell.c = c;
+            cell.set_char(c);
             self.style.apply_to(cell);
 
             if width > 1 {
@@ -816,13 +1239,128 @@ impl Screen {
         // Handle wide characters (write spacer in next cell)
         if width > 1 && self.cursor.col + 1 < self.width() {
             if let Some(cell) = self.grid.get_mut(self.cursor.row, self.cursor.col + 1) {
-                cell.c = ' ';
+                cell.set_char(' ');
                 cell.attrs = crate::cell::CellAttrs::WIDE_SPACER;
             }
         }
 
         // Advance cursor
         self.cursor.col += width;
+        self.overflow_col = 0;
+        self.dirty = true;
+    }
+
+    /// Add `c` to the grapheme cluster left of the cursor if it continues it
+    ///
+    /// Zero-width characters (combining marks, ZWJ, variation selectors)
+    /// always join. With grapheme clusters on, so do an emoji after a ZWJ and
+    /// a skin tone after an emoji, and a second regional indicator widens the
+    /// first into a two-cell flag. Returns whether `c` was joined.
+    fn join_grapheme(&mut self, c: char, width: usize) -> bool {
+        use crate::cell::CellAttrs;
+
+        let screen_width = self.width();
+        let end = if self.cursor.col >= screen_width && self.overflow_col > 0 {
+            screen_width + self.overflow_col
+        } else {
+            self.cursor.col.min(screen_width)
+        };
+        if end == 0 {
+            return false;
+        }
+        let Some(row) = self.grid.row(self.cursor.row) else {
+            return false;
+        };
+        let mut col = end - 1;
+        if col > 0
+            && row
+                .get_extended(col)
+                .is_some_and(|cell| cell.is_wide_spacer())
+        {
+            col -= 1;
+        }
+        let Some(prev) = row.get_extended(col) else {
+            return false;
+        };
+
+        let last = prev.grapheme.as_deref().and_then(|g| g.chars().last());
+        let is_flag = self.grapheme_clusters
+            && is_regional_indicator(c)
+            && is_regional_indicator(prev.c)
+            && prev.grapheme.is_none()
+            && col + 1 == end
+            && col + 1 != screen_width;
+        let joins = width == 0
+            || is_flag
+            || (self.grapheme_clusters
+                && ((width == 2 && last == Some(ZERO_WIDTH_JOINER))
+                    || (is_emoji_modifier(c) && prev.is_wide())));
+        if !joins {
+            return false;
+        }
+
+        self.clear_selection_if_row_selected(self.cursor.row);
+        let Some(row) = self.grid.row_mut(self.cursor.row) else {
+            return false;
+        };
+        if let Some(prev) = row.get_extended_mut(col) {
+            prev.push_grapheme(c);
+            if is_flag {
+                prev.attrs.insert(CellAttrs::WIDE);
+            }
+        }
+        if is_flag {
+            let spacer = Cell {
+                c: ' ',
+                attrs: CellAttrs::WIDE_SPACER,
+                ..Default::default()
+            };
+            match (col + 1).checked_sub(screen_width) {
+                Some(index) => {
+                    row.set_overflow(index, spacer);
+                    self.overflow_col += 1;
+                }
+                None => {
+                    if let Some(cell) = row.get_mut(col + 1) {
+                        *cell = spacer;
+                    }
+                    self.cursor.col += 1;
+                }
+            }
+        }
+        self.dirty = true;
+        true
+    }
+
+    /// Keep a character that doesn't fit on a truncated line
+    ///
+    /// The cursor stays past the last column, as if a wrap were pending.
+    fn put_overflow_char(&mut self, c: char, width: usize) {
+        self.cursor.col = self.width();
+        if self.overflow_col + width > MAX_LINE_OVERFLOW {
+            return;
+        }
+        let mut cell = Cell {
+            c,
+            ..Default::default()
+        };
+        self.style.apply_to(&mut cell);
+        if width > 1 {
+            cell.attrs.insert(crate::cell::CellAttrs::WIDE);
+        }
+        let index = self.overflow_col;
+        if let Some(row) = self.grid.row_mut(self.cursor.row) {
+            row.set_overflow(index, cell);
+            if width > 1 {
+                let spacer = Cell {
+                    c: ' ',
+                    attrs: crate::cell::CellAttrs::WIDE_SPACER,
+                    ..Default::default()
+                };
+                row.set_overflow(index + 1, spacer);
+            }
+        }
+        self.overflow_col += width;
         self.dirty = true;
     }
 
@@ -851,16 +1389,46 @@ impl Screen {
         self.cursor.col = 0;
     }
 
-    /// Move cursor down, scrolling if needed
+    /// Column the cursor is drawn at
+    ///
+    /// After writing the last column the cursor stays one past it until the
+    /// next character wraps; it is shown on the last column meanwhile. Shown
+    /// local echo predictions move it past the predicted characters.
+    pub fn cursor_render_col(&self) -> usize {
+        (self.cursor.col + self.predictions.shown_len()).min(self.width().saturating_sub(1))
+    }
+
+    /// Cancel a pending wrap, leaving the cursor on the last column
+    pub fn clear_pending_wrap(&mut self) {
+        self.cursor.col = self.cursor.col.min(self.width().saturating_sub(1));
+    }
+
+    /// Move cursor down, scrolling if needed (LF/IND)
+    ///
+    /// Only the bottom margin scrolls, and only the scroll region; below the
+    /// region the cursor moves down until the last row, leaving lines outside
+    /// the region (e.g. a status bar) in place.
     pub fn line_feed(&mut self) {
-        if self.cursor.row + 1 >= self.scroll_region.bottom {
+        self.clear_pending_wrap();
+        if self.cursor.row + 1 == self.scroll_region.bottom {
             self.scroll_up(1);
-        } else {
+        } else if self.cursor.row + 1 < self.height() {
             self.cursor.row += 1;
         }
         self.dirty = true;
     }
 
+    /// Move cursor up, scrolling the region down at the top margin (RI)
+    pub fn reverse_index(&mut self) {
+        self.clear_pending_wrap();
+        if self.cursor.row == self.scroll_region.top {
+            self.scroll_down(1);
+        } else if self.cursor.row > 0 {
+            self.cursor.row -= 1;
+        }
+        self.dirty = true;
+    }
+
     /// Scroll up within scroll region
     pub fn scroll_up(&mut self, count: usize) {
         let scrolled =
@@ -902,12 +1470,24 @@ impl Screen {
                         // Adjust selection indices to account for removed lines
                         selection.anchor.line -= lines_removed;
                         selection.end.line -= lines_removed;
+                        if let Some(ref mut start) = selection.anchor_start {
+                            start.line -= lines_removed;
+                        }
+                        if let Some(ref mut end) = selection.anchor_end {
+                            end.line -= lines_removed;
+                        }
                     }
                 }
             }
 
             // Prune images that have scrolled off the top of the scrollback buffer
             self.prune_old_images();
+
+            // Drop bookmarks whose lines have left the scrollback buffer
+            if lines_removed > 0 {
+                let first_line = self.scrollback_total_pushed - self.scrollback.len();
+                self.bookmarks.retain(|&line| line >= first_line);
+            }
         }
 
         self.dirty = true;
@@ -957,36 +1537,68 @@ impl Screen {
         }
     }
 
-    /// Switch to alternate screen buffer
+    /// Save the cursor position (SCOSC, `CSI s`)
+    ///
+    /// Unlike DECSC only the position is saved, and separately, so the two
+    /// don't overwrite each other.
+    pub fn save_cursor_position(&mut self) {
+        self.clear_pending_wrap();
+        self.saved_position = Some((self.cursor.row, self.cursor.col));
+    }
+
+    /// Move the cursor back to the position saved by SCOSC (SCORC, `CSI u`)
+    ///
+    /// The position stays saved, so it can be restored again; nothing
+    /// happens if none was saved.
+    pub fn restore_cursor_position(&mut self) {
+        if let Some((row, col)) = self.saved_position {
+            self.cursor.row = row.min(self.height().saturating_sub(1));
+            self.cursor.col = col.min(self.width().saturating_sub(1));
+        }
+    }
+
+    /// Switch to alternate screen buffer (DECSET 47)
+    ///
+    /// The cursor stays where it is, and the alternate buffer shows whatever
+    /// was left in it the last time. DECSET 1047 and 1049 add clearing and
+    /// cursor save/restore on top of this.
     pub fn enter_alternate_screen(&mut self) {
         if self.modes.alternate_screen {
             return;
         }
 
         self.modes.alternate_screen = true;
-        self.alt_saved_cursor = Some(self.cursor.clone());
+        std::mem::swap(&mut self.saved_cursor, &mut self.alt_saved_cursor);
+        self.primary_show_cursor = self.modes.show_cursor;
 
-        let alt = Grid::new(self.width(), self.height());
+        let alt = self
+            .inactive_alternate_grid
+            .take()
+            .unwrap_or_else(|| Grid::new(self.width(), self.height()));
         self.alternate_grid = Some(std::mem::replace(&mut self.grid, alt));
 
-        self.cursor = Cursor::default();
         self.dirty = true;
     }
 
-    /// Switch back to primary screen buffer
+    /// Switch back to primary screen buffer (DECRST 47)
+    ///
+    /// The cursor stays where it is and the alternate buffer's contents are
+    /// kept for the next switch. The cursor is shown or hidden again as it
+    /// was on the primary screen, unless disabled with
+    /// [`Screen::set_cursor_visibility_per_screen`].
     pub fn exit_alternate_screen(&mut self) {
         if !self.modes.alternate_screen {
             return;
         }
 
         self.modes.alternate_screen = false;
-
-        if let Some(primary) = self.alternate_grid.take() {
-            self.grid = primary;
+        std::mem::swap(&mut self.saved_cursor, &mut self.alt_saved_cursor);
+        if self.cursor_visibility_per_screen {
+            self.modes.show_cursor = self.primary_show_cursor;
         }
 
-        if let Some(saved) = self.alt_saved_cursor.take() {
-            self.cursor = saved;
+        if let Some(primary) = self.alternate_grid.take() {
+            self.inactive_alternate_grid = Some(std::mem::replace(&mut self.grid, primary));
         }
 
         self.dirty = true;
@@ -1070,10 +1682,16 @@ impl Screen {
             LineClearMode::All => (0, width),
         };
 
+        let overflow_col = self.overflow_col;
         if let Some(row) = self.grid.row_mut(cursor_row) {
             for col in start..end.min(width) {
                 row[col].reset();
             }
+            match mode {
+                LineClearMode::Right if cursor_col >= width => row.truncate_overflow(overflow_col),
+                LineClearMode::Right | LineClearMode::All => row.truncate_overflow(0),
+                LineClearMode::Left => {}
+            }
         }
         self.dirty = true;
     }
@@ -1145,6 +1763,9 @@ impl Screen {
         };
         self.saved_cursor = None;
         self.alt_saved_cursor = None;
+        self.primary_show_cursor = true;
+        self.saved_position = None;
+        self.inactive_alternate_grid = None;
         self.scroll_region = ScrollRegion {
             top: 0,
             bottom: self.height(),
@@ -1158,139 +1779,309 @@ impl Screen {
         };
         self.title.clear();
         self.icon_name.clear();
+        self.title_stack.clear();
+        self.color_overrides = ColorOverrides::default();
+        self.tab_stops = Self::default_tab_stops(self.width(), self.tab_width);
         self.dirty = true;
         self.scroll_offset = 0;
         self.images.clear();
         self.drcs_fonts.clear();
+        self.current_zone = None;
+        self.last_zone = None;
+        self.command_history.clear();
+        self.bookmarks.clear();
+        self.badge_format = None;
+        self.search = None;
+        self.horizontal_offset = 0;
+        self.overflow_col = 0;
+        self.view_damaged = true;
+    }
+
+    /// Soft terminal reset (DECSTR)
+    ///
+    /// Puts modes, margins, character sets and SGR attributes back to their
+    /// defaults like a full reset, but keeps the screen content, scrollback
+    /// and cursor position.
+    pub fn soft_reset(&mut self) {
+        self.modes.show_cursor = true;
+        self.modes.insert_mode = false;
+        self.modes.origin_mode = false;
+        self.modes.auto_wrap = true;
+        self.modes.application_cursor = false;
+        self.modes.application_keypad = false;
+        self.modes.charset_g1_active = false;
+        self.modes.charset_g0 = None;
+        self.modes.charset_g1 = None;
+        self.reset_scroll_region();
+        self.style = CellStyle::default();
+        // DECSC state goes back to the home position with default attributes
+        self.saved_cursor = None;
+        self.dirty = true;
     }
 
     /// Search for text in scrollback and visible buffer
     ///
-    /// Returns all matches found, starting from the oldest scrollback line.
+    /// Only the scrollback is searched if [`Screen::set_search_scrollback_only`]
+    /// is set. Returns all matches found, starting from the oldest scrollback line.
     /// Line index 0 is the oldest scrollback line, and increases toward
     /// the most recent visible line.
     pub fn find(&self, pattern: &str, case_sensitive: bool, regex: bool) -> Vec<SearchResult> {
         let mut results = Vec::new();
 
-        if pattern.is_empty() {
+        let Some(matcher) = SearchMatcher::new(pattern, case_sensitive, regex) else {
             return results;
+        };
+
+        let first_line = self.first_scrollback_line();
+        self.find_from(&matcher, first_line, &mut results);
+        for result in &mut results {
+            result.line -= first_line;
         }
+        results
+    }
 
-        // Build the regex or prepare for simple search
-        let regex_pattern = if regex {
-            match regex::RegexBuilder::new(pattern)
-                .case_insensitive(!case_sensitive)
-                .build()
-            {
-                Ok(re) => Some(re),
-                Err(_) => return results, // Invalid regex
-            }
-        } else {
-            None
+    /// Get the lines of the scrollback and visible buffer that match a query
+    ///
+    /// Lines are returned oldest first with trailing whitespace trimmed, like
+    /// `grep` over the buffer. An empty query or an invalid regex matches nothing.
+    pub fn extract_matches(&self, pattern: &str, case_sensitive: bool, regex: bool) -> Vec<String> {
+        let Some(matcher) = SearchMatcher::new(pattern, case_sensitive, regex) else {
+            return Vec::new();
         };
 
-        let search_pattern = if !case_sensitive && !regex {
-            std::borrow::Cow::Owned(pattern.to_lowercase())
-        } else {
-            std::borrow::Cow::Borrowed(pattern)
-        };
+        let mut text_buf = String::new();
+        let mut lower_buf = String::new();
+        self.scrollback
+            .iter()
+            .chain((0..self.grid.height()).filter_map(|row| self.grid.row(row)))
+            .filter_map(|row| {
+                row.write_text_to(&mut text_buf);
+                matcher
+                    .is_match(&text_buf, &mut lower_buf)
+                    .then(|| text_buf.trim_end().to_string())
+            })
+            .collect()
+    }
+
+    /// Stable line number of the oldest line still in scrollback
+    pub fn first_scrollback_line(&self) -> usize {
+        self.scrollback_total_pushed - self.scrollback.len()
+    }
 
+    /// Append the matches in stable lines `start..` (scrollback, then the
+    /// visible grid unless searching the scrollback only) to `results`, with
+    /// stable line numbers
+    fn find_from(&self, matcher: &SearchMatcher, start: usize, results: &mut Vec<SearchResult>) {
         // Reuse a single text buffer across all rows to avoid per-row allocation
         let mut text_buf = String::new();
         // Reuse a lowercase buffer for case-insensitive search
         let mut lower_buf = String::new();
 
         // Search scrollback
-        for (line_idx, row) in self.scrollback.iter().enumerate() {
+        let first_line = self.first_scrollback_line();
+        let skip = start.saturating_sub(first_line);
+        for (idx, row) in self.scrollback.iter().enumerate().skip(skip) {
             row.write_text_to(&mut text_buf);
-            Self::search_in_text(
-                &text_buf,
-                &mut lower_buf,
-                line_idx,
-                &search_pattern,
-                case_sensitive,
-                &regex_pattern,
-                &mut results,
-            );
+            matcher.find_in(&text_buf, &mut lower_buf, first_line + idx, results);
+        }
+
+        if self.search_scrollback_only {
+            return;
         }
 
         // Search visible grid
-        let scrollback_len = self.scrollback.len();
-        for row_idx in 0..self.grid.height() {
+        let skip = start.saturating_sub(self.scrollback_total_pushed);
+        for row_idx in skip..self.grid.height() {
             if let Some(row) = self.grid.row(row_idx) {
                 row.write_text_to(&mut text_buf);
-                Self::search_in_text(
+                matcher.find_in(
                     &text_buf,
                     &mut lower_buf,
-                    scrollback_len + row_idx,
-                    &search_pattern,
-                    case_sensitive,
-                    &regex_pattern,
-                    &mut results,
+                    self.scrollback_total_pushed + row_idx,
+                    results,
                 );
             }
         }
-
-        results
     }
 
-    /// Search for pattern matches within a single row's text
-    fn search_in_text(
-        line_text: &str,
-        lower_buf: &mut String,
-        line_idx: usize,
+    /// Search and move to the next match
+    ///
+    /// Repeating the previous query advances to the following match, wrapping
+    /// around at the end; a new query starts at the first match. The matches
+    /// stay highlighted until [`Screen::clear_search`]. Returns the current match.
+    ///
+    /// Repeating a query steps through the matches found so far instead of
+    /// searching again, so output arriving meanwhile doesn't move the current
+    /// match; [`Screen::update_search`] brings the matches up to date.
+    pub fn search_next(
+        &mut self,
         pattern: &str,
         case_sensitive: bool,
-        regex_pattern: &Option<regex::Regex>,
-        results: &mut Vec<SearchResult>,
-    ) {
-        if let Some(re) = regex_pattern {
-            for m in re.find_iter(line_text) {
-                results.push(SearchResult {
-                    line: line_idx,
-                    col: m.start(),
-                    len: m.len(),
-                });
+        regex: bool,
+    ) -> Option<SearchResult> {
+        match &mut self.search {
+            Some(search)
+                if search.is_query(pattern, case_sensitive, regex)
+                    && !search.matches.is_empty() =>
+            {
+                search.current = (search.current + 1) % search.matches.len();
             }
-        } else {
-            // Simple string search - reuse lower_buf for case-insensitive
-            let search_text = if case_sensitive {
-                line_text
-            } else {
-                lower_buf.clear();
-                lower_buf.push_str(&line_text.to_lowercase());
-                lower_buf.as_str()
-            };
+            _ => {
+                let Some(matcher) = SearchMatcher::new(pattern, case_sensitive, regex) else {
+                    self.clear_search();
+                    return None;
+                };
+                let mut matches = Vec::new();
+                self.find_from(&matcher, self.first_scrollback_line(), &mut matches);
+                if matches.is_empty() {
+                    self.clear_search();
+                    return None;
+                }
 
-            let mut start = 0;
-            while let Some(pos) = search_text[start..].find(pattern) {
-                let col = start + pos;
-                results.push(SearchResult {
-                    line: line_idx,
-                    col,
-                    len: pattern.len(),
+                self.search = Some(ActiveSearch {
+                    pattern: pattern.to_string(),
+                    case_sensitive,
+                    regex,
+                    matcher,
+                    matches,
+                    current: 0,
+                    scanned_to: self.scrollback_total_pushed,
                 });
-                start = col + 1;
             }
         }
+        self.dirty = true;
+        self.view_damaged = true;
+
+        let search = self.search.as_ref()?;
+        let mut result = search.matches[search.current].clone();
+        result.line -= self.first_scrollback_line();
+        Some(result)
     }
 
-    /// Convert a line index from find() to scroll offset
+    /// Bring the active search's matches up to date with new output
     ///
-    /// Returns the scroll offset needed to show the given line at the top of the visible area.
-    pub fn line_to_scroll_offset(&self, line_idx: usize) -> usize {
-        let scrollback_len = self.scrollback.len();
-        // If line is in scrollback, return offset; otherwise 0 for visible area
-        scrollback_len.saturating_sub(line_idx)
+    /// Matches in lines that dropped out of the scrollback are removed and,
+    /// unless disabled with [`Screen::set_search_follows_output`], the lines
+    /// that scrolled into the scrollback or may have changed on screen since
+    /// the last update are searched again. The current match stays on the
+    /// same text rather than the same index, so it doesn't jump around while
+    /// output streams in. Call after processing PTY output.
+    pub fn update_search(&mut self) {
+        let first_line = self.first_scrollback_line();
+        let Some(mut search) = self.search.take() else {
+            return;
+        };
+
+        let current = search.matches.get(search.current).map(|m| (m.line, m.col));
+        let previous = search.matches.clone();
+        if self.search_follows_output {
+            let rescan_from = search.scanned_to.max(first_line);
+            search
+                .matches
+                .retain(|m| m.line >= first_line && m.line < rescan_from);
+            self.find_from(&search.matcher, rescan_from, &mut search.matches);
+            search.scanned_to = self.scrollback_total_pushed;
+        } else {
+            search.matches.retain(|m| m.line >= first_line);
+        }
+
+        // Keep the same match current, or the one after it if it's gone
+        search.current = current
+            .map_or(0, |pos| {
+                search.matches.partition_point(|m| (m.line, m.col) < pos)
+            })
+            .min(search.matches.len().saturating_sub(1));
+
+        if search.matches != previous {
+            self.dirty = true;
+            self.view_damaged = true;
+        }
+        self.search = Some(search);
     }
 
-    // ========== Image Methods ==========
+    /// Set whether the active search picks up matches in new output
+    ///
+    /// When disabled, the matches are a snapshot taken when the query was
+    /// entered; they are only dropped as their lines leave the scrollback.
+    pub fn set_search_follows_output(&mut self, enabled: bool) {
+        self.search_follows_output = enabled;
+    }
 
-    /// Add an image at the specified position (legacy method)
+    /// Set whether leaving the alternate screen restores the primary
+    /// screen's cursor visibility
     ///
-    /// The image is stored with an absolute line number that includes scrollback,
-    /// so it will scroll with the content.
-    pub fn add_image(&mut self, col: usize, row: usize, sixel_image: SixelImage) {
+    /// A full-screen program that hides the cursor and exits without showing
+    /// it again otherwise leaves the shell's cursor hidden, as in xterm.
+    pub fn set_cursor_visibility_per_screen(&mut self, enabled: bool) {
+        self.cursor_visibility_per_screen = enabled;
+    }
+
+    /// Set whether searches only look at the scrollback
+    ///
+    /// Matches then stay put while a full-screen program repaints the visible
+    /// screen. Changing this ends the active search, so the next
+    /// [`Screen::search_next`] searches again.
+    pub fn set_search_scrollback_only(&mut self, enabled: bool) {
+        if self.search_scrollback_only != enabled {
+            self.search_scrollback_only = enabled;
+            self.clear_search();
+        }
+    }
+
+    /// Whether searches only look at the scrollback
+    pub fn search_scrollback_only(&self) -> bool {
+        self.search_scrollback_only
+    }
+
+    /// Number of matches of the active search
+    pub fn search_match_count(&self) -> usize {
+        self.search.as_ref().map_or(0, |s| s.matches.len())
+    }
+
+    /// Stop highlighting search matches
+    pub fn clear_search(&mut self) {
+        if self.search.take().is_some() {
+            self.dirty = true;
+            self.view_damaged = true;
+        }
+    }
+
+    /// How the cell at an absolute line (as used by `find`) is highlighted by the active search
+    pub fn search_highlight(&self, line: usize, col: usize) -> Option<SearchHighlight> {
+        let search = self.search.as_ref()?;
+        let line = self.first_scrollback_line() + line;
+        let first = search.matches.partition_point(|m| m.line < line);
+        search.matches[first..]
+            .iter()
+            .enumerate()
+            .take_while(|(_, m)| m.line == line)
+            .filter(|(_, m)| col >= m.col && col < m.col + m.len)
+            .map(|(i, _)| {
+                if first + i == search.current {
+                    SearchHighlight::Current
+                } else {
+                    SearchHighlight::Match
+                }
+            })
+            .max()
+    }
+
+    /// Convert a line index from find() to scroll offset
+    ///
+    /// Returns the scroll offset needed to show the given line at the top of the visible area.
+    pub fn line_to_scroll_offset(&self, line_idx: usize) -> usize {
+        let scrollback_len = self.scrollback.len();
+        // If line is in scrollback, return offset; otherwise 0 for visible area
+        scrollback_len.saturating_sub(line_idx)
+    }
+
+    // ========== Image Methods ==========
+
+    /// Add an image at the specified position (legacy method)
+    ///
+    /// The image is stored with an absolute line number that includes scrollback,
+    /// so it will scroll with the content.
+    pub fn add_image(&mut self, col: usize, row: usize, sixel_image: SixelImage) {
         let cols = self.image_cols_for_width(sixel_image.width);
         let rows = self.image_rows_for_height(sixel_image.height);
         self.add_image_with_size(col, row, cols, rows, sixel_image);
@@ -1307,21 +2098,46 @@ impl Screen {
         cell_rows: usize,
         sixel_image: SixelImage,
     ) {
+        self.add_image_placement(
+            col,
+            row,
+            (cell_cols, cell_rows),
+            sixel_image,
+            ImagePlacement::default(),
+        );
+    }
+
+    /// Add an image with an explicit stacking order and anchor
+    ///
+    /// `row` is a visible grid row and `cells` the (columns, rows) the image
+    /// covers. Grid cells underneath are cleared like for Sixel images.
+    pub fn add_image_placement(
+        &mut self,
+        col: usize,
+        row: usize,
+        cells: (usize, usize),
+        sixel_image: SixelImage,
+        placement: ImagePlacement,
+    ) {
+        let (cell_cols, cell_rows) = cells;
         let id = self.next_image_id;
         self.next_image_id += 1;
 
-        // Calculate absolute line (scrollback + visible row)
-        let absolute_line = self.scrollback.len() + row;
+        let line = match placement.anchor {
+            ImageAnchor::Grid => self.scrollback_total_pushed + row,
+            ImageAnchor::Viewport => row,
+        };
 
         let image = TerminalImage {
             id,
             col,
-            line: absolute_line,
+            line,
             cell_width: cell_cols,
             cell_height: cell_rows,
             data: Arc::new(sixel_image.data),
             pixel_width: sixel_image.width,
             pixel_height: sixel_image.height,
+            placement,
         };
 
         // Clear grid cells underneath the image (xterm behavior)
@@ -1330,9 +2146,11 @@ impl Screen {
 
         self.images.insert(id, image);
         self.dirty = true;
+        self.view_damaged = true;
 
         // Prune old images that have scrolled too far
         self.prune_old_images();
+        self.evict_images();
     }
 
     /// Clear grid cells that will be covered by an image
@@ -1350,7 +2168,7 @@ impl Screen {
                         break;
                     }
                     // Clear the cell but keep it as a space (not truly empty)
-                    grid_row[c].c = ' ';
+                    grid_row[c].set_char(' ');
                     grid_row[c].attrs = crate::cell::CellAttrs::empty();
                 }
             }
@@ -1359,40 +2177,34 @@ impl Screen {
 
     /// Get images visible in the current viewport
     ///
-    /// Returns images that overlap with the currently visible portion of the screen.
+    /// Returns images that overlap with the currently visible portion of the
+    /// screen, in drawing order (lowest z-index first, then oldest first).
     pub fn visible_images(&self) -> Vec<&TerminalImage> {
-        let scrollback_len = self.scrollback.len();
-        let height = self.height();
-
-        // Calculate the range of absolute lines currently visible
-        let first_visible_line = scrollback_len.saturating_sub(self.scroll_offset);
-        let last_visible_line = first_visible_line + height;
-
-        self.images
+        let mut images: Vec<&TerminalImage> = self
+            .images
             .values()
-            .filter(|img| {
-                // Image is visible if any part of it overlaps with the viewport
-                let img_top = img.line;
-                let img_rows = self.image_rows_for_height(img.pixel_height).max(1);
-                let img_bottom = img.line + img_rows;
-
-                img_bottom > first_visible_line && img_top < last_visible_line
-            })
-            .collect()
+            .filter(|img| !img.is_evicted() && self.image_visible_row(img).is_some())
+            .collect();
+        images.sort_by_key(|img| (img.placement.z_index, img.id));
+        images
     }
 
-    /// Calculate the visible row for an image (relative to current viewport)
+    /// Calculate the visible row of an image's top edge (relative to current viewport)
     ///
-    /// Returns None if the image is not in the visible area.
-    pub fn image_visible_row(&self, image: &TerminalImage) -> Option<usize> {
-        let scrollback_len = self.scrollback.len();
-        let first_visible_line = scrollback_len.saturating_sub(self.scroll_offset);
+    /// The row is negative when the image is partly scrolled off the top, so
+    /// renderers should draw it there and let the viewport clip it. Returns
+    /// None if no part of the image is in the visible area.
+    pub fn image_visible_row(&self, image: &TerminalImage) -> Option<isize> {
+        let top = match image.placement.anchor {
+            ImageAnchor::Grid => {
+                let first_visible_line = self.scrollback_total_pushed - self.scroll_offset;
+                image.line as isize - first_visible_line as isize
+            }
+            ImageAnchor::Viewport => image.line as isize,
+        };
+        let rows = self.image_rows_for_height(image.pixel_height).max(1) as isize;
 
-        if image.line >= first_visible_line && image.line < first_visible_line + self.height() {
-            Some(image.line - first_visible_line)
-        } else {
-            None
-        }
+        (top + rows > 0 && top < self.height() as isize).then_some(top)
     }
 
     /// Get the image at a given visible row and column position
@@ -1400,25 +2212,21 @@ impl Screen {
     /// Returns the image if one exists at that position, or None otherwise.
     /// Used for right-click context menu on images.
     pub fn image_at_position(&self, row: usize, col: usize) -> Option<&TerminalImage> {
-        let scrollback_len = self.scrollback.len();
-        let first_visible_line = scrollback_len.saturating_sub(self.scroll_offset);
-        let absolute_line = first_visible_line + row;
+        let row = row as isize;
 
-        self.images.values().find(|img| {
+        // Topmost image first
+        self.visible_images().into_iter().rev().find(|img| {
             // Check if the click position is within the image bounds
-            let img_top = img.line;
-            let img_bottom = img.line + img.cell_height;
-            let img_left = img.col;
-            let img_right = img.col + img.cell_width;
-
-            absolute_line >= img_top
-                && absolute_line < img_bottom
-                && col >= img_left
-                && col < img_right
+            let Some(img_top) = self.image_visible_row(img) else {
+                return false;
+            };
+            let img_bottom = img_top + img.cell_height as isize;
+
+            row >= img_top && row < img_bottom && col >= img.col && col < img.col + img.cell_width
         })
     }
 
-    /// Get an image by its ID
+    /// Get an image by its ID (evicted images included)
     pub fn image_by_id(&self, id: u64) -> Option<&TerminalImage> {
         self.images.get(&id)
     }
@@ -1429,20 +2237,72 @@ impl Screen {
             return;
         }
 
-        // Image line numbers are absolute (scrollback.len() + row at creation time),
-        // so they grow monotonically. When scrollback is at capacity, old lines are
-        // discarded from the front. The minimum valid line is the total lines ever
-        // pushed minus the max scrollback capacity.
-        let min_valid_line = self
-            .scrollback_total_pushed
-            .saturating_sub(self.config.scrollback_lines);
+        // Grid-anchored images use stable line numbers; drop them once their
+        // last row has left the front of the scrollback buffer
+        let first_line = self.first_scrollback_line();
+
+        let count = self.images.len();
+        self.images.retain(|_, img| {
+            img.placement.anchor == ImageAnchor::Viewport
+                || img.line + img.cell_height.max(1) > first_line
+        });
+        if self.images.len() != count {
+            self.view_damaged = true;
+        }
+    }
+
+    /// Drop the pixel data of the oldest images beyond the limit
+    ///
+    /// Image IDs grow with each image placed, so the lowest IDs go first.
+    fn evict_images(&mut self) {
+        if self.max_images == 0 {
+            return;
+        }
+        let mut ids: Vec<u64> = self
+            .images
+            .values()
+            .filter(|img| !img.is_evicted())
+            .map(|img| img.id)
+            .collect();
+        if ids.len() <= self.max_images {
+            return;
+        }
+
+        ids.sort_unstable();
+        for id in &ids[..ids.len() - self.max_images] {
+            if let Some(image) = self.images.get_mut(id) {
+                log::debug!(
+                    "Evicting image {} ({}x{} pixels) over the limit of {}",
+                    id,
+                    image.pixel_width,
+                    image.pixel_height,
+                    self.max_images
+                );
+                image.data = Arc::new(Vec::new());
+            }
+        }
+        self.view_damaged = true;
+    }
+
+    /// Set how many images keep their pixel data (0 = no limit)
+    ///
+    /// Placing an image beyond the limit evicts the data of the oldest one,
+    /// so a session showing many plots doesn't keep them all in memory.
+    pub fn set_max_images(&mut self, limit: usize) {
+        self.max_images = limit;
+        self.evict_images();
+    }
 
-        self.images.retain(|_, img| img.line >= min_valid_line);
+    /// Number of images and the bytes of pixel data they hold
+    pub fn image_memory(&self) -> (usize, usize) {
+        let bytes = self.images.values().map(|img| img.data.len()).sum();
+        (self.images.len(), bytes)
     }
 
     /// Clear all images (called on screen clear)
     pub fn clear_images(&mut self) {
         self.images.clear();
+        self.view_damaged = true;
     }
 
     /// Set the cell height hint (call from UI layer when font metrics are known)
@@ -1495,6 +2355,8 @@ impl Screen {
     /// - 2: Erase all renditions
     pub fn add_drcs_font(&mut self, font: DrcsFont, erase_control: u8, _font_number: u8) {
         let designator = font.designator.clone();
+        // Text already on screen may now use the new glyphs
+        self.view_damaged = true;
 
         match erase_control {
             0 | 2 => {
@@ -1543,6 +2405,7 @@ impl Screen {
     /// Clear all DRCS fonts
     pub fn clear_drcs_fonts(&mut self) {
         self.drcs_fonts.clear();
+        self.view_damaged = true;
     }
 
     /// Designate a character set to G0 or G1
@@ -1657,6 +2520,7 @@ impl Screen {
                 self.selection = Some(Selection::new_with_range(anchor_start, anchor_end, mode));
             }
         }
+        self.snap_selection_to_wide_chars();
         self.dirty = true;
     }
 
@@ -1664,7 +2528,7 @@ impl Screen {
     pub fn extend_selection(&mut self, line: usize, col: usize) {
         // Extract mode and anchor info before mutating
         let (mode, anchor_start, anchor_end_opt) = match &self.selection {
-            Some(s) => (s.mode, s.anchor, s.anchor_end),
+            Some(s) => (s.mode, s.anchor_start.unwrap_or(s.anchor), s.anchor_end),
             None => return,
         };
 
@@ -1718,9 +2582,47 @@ impl Screen {
                 }
             }
         }
+        self.snap_selection_to_wide_chars();
         self.dirty = true;
     }
 
+    /// Widen the selection so it doesn't split a wide character
+    fn snap_selection_to_wide_chars(&mut self) {
+        if let Some(mut selection) = self.selection.take() {
+            self.snap_to_wide_chars(&mut selection);
+            self.selection = Some(selection);
+        }
+    }
+
+    /// Move the boundaries of `selection` off the middle of wide characters
+    ///
+    /// A boundary on the right half of a wide character moves to its left
+    /// half at the start of the selection, and takes in the right half at
+    /// the end, so the highlight and the copied text cover the whole glyph.
+    fn snap_to_wide_chars(&self, selection: &mut Selection) {
+        let anchor_first = match selection.mode {
+            SelectionMode::Line => return,
+            SelectionMode::Block => selection.anchor.col < selection.end.col,
+            SelectionMode::Char | SelectionMode::Word => selection.anchor.is_before(&selection.end),
+        };
+        let (first, last) = if anchor_first {
+            (&mut selection.anchor, &mut selection.end)
+        } else {
+            (&mut selection.end, &mut selection.anchor)
+        };
+        let cell = |point: &SelectionPoint| {
+            self.row_with_scrollback(point.line)
+                .and_then(|row| row.get_extended(point.col))
+        };
+
+        if first.col > 0 && cell(first).is_some_and(Cell::is_wide_spacer) {
+            first.col -= 1;
+        }
+        if cell(last).is_some_and(Cell::is_wide) {
+            last.col += 1;
+        }
+    }
+
     /// Clear the current selection
     pub fn clear_selection(&mut self) {
         if self.selection.is_some() {
@@ -1773,11 +2675,232 @@ impl Screen {
         scrollback_len.saturating_sub(self.scroll_offset) + visible_row
     }
 
+    /// Set what is copied for text carrying a hyperlink (OSC 8)
+    pub fn set_copy_hyperlink_mode(&mut self, mode: HyperlinkCopyMode) {
+        self.copy_hyperlink_mode = mode;
+    }
+
+    /// Set whether long lines wrap or are truncated
+    ///
+    /// Lines already written keep their layout.
+    pub fn set_long_line_mode(&mut self, mode: LongLineMode) {
+        self.long_line_mode = mode;
+        if mode == LongLineMode::Wrap {
+            self.overflow_col = 0;
+        }
+    }
+
+    /// Whether long lines wrap or are truncated
+    pub fn long_line_mode(&self) -> LongLineMode {
+        self.long_line_mode
+    }
+
+    /// Set whether emoji sequences and flags join into one two-cell glyph
+    ///
+    /// When off, each emoji of a sequence takes its own cells, as counted by
+    /// `wcwidth`. Combining marks join either way.
+    pub fn set_grapheme_clusters(&mut self, enabled: bool) {
+        self.grapheme_clusters = enabled;
+    }
+
+    /// Whether emoji sequences and flags join into one two-cell glyph
+    pub fn grapheme_clusters(&self) -> bool {
+        self.grapheme_clusters
+    }
+
+    /// Row at an absolute line (scrollback + visible area)
+    fn row_with_scrollback(&self, line: usize) -> Option<&Row> {
+        if line < self.scrollback.len() {
+            self.scrollback.get(line)
+        } else {
+            self.grid.row(line - self.scrollback.len())
+        }
+    }
+
+    /// Columns the view is scrolled right by
+    ///
+    /// Limited to the longest truncated line in view, so the offset drops
+    /// back as those lines scroll away or are cleared.
+    pub fn horizontal_offset(&self) -> usize {
+        if self.horizontal_offset == 0 {
+            return 0;
+        }
+        self.horizontal_offset.min(self.max_horizontal_offset())
+    }
+
+    /// Largest useful horizontal offset for the lines in view
+    fn max_horizontal_offset(&self) -> usize {
+        let first = self.visible_row_to_absolute_line(0);
+        (first..first + self.height())
+            .filter_map(|line| self.row_with_scrollback(line))
+            .map(|row| row.overflow_len())
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Scroll the view right (positive) or left (negative) by `cols` columns
+    pub fn scroll_horizontal(&mut self, cols: isize) {
+        let offset = self
+            .horizontal_offset()
+            .saturating_add_signed(cols)
+            .min(self.max_horizontal_offset());
+        if offset != self.horizontal_offset {
+            self.horizontal_offset = offset;
+            self.dirty = true;
+            self.view_damaged = true;
+        }
+    }
+
+    /// Cell shown at `col` of an absolute line, after the horizontal offset
+    pub fn visible_cell(&self, line: usize, col: usize) -> Option<&Cell> {
+        self.row_with_scrollback(line)?
+            .get_extended(col + self.horizontal_offset())
+    }
+
+    /// Whether a line continues past the right edge of the view
+    ///
+    /// Renderers draw a "…" on the last column of such lines.
+    pub fn line_continues_right(&self, line: usize) -> bool {
+        self.row_with_scrollback(line)
+            .is_some_and(|row| row.overflow_len() > self.horizontal_offset())
+    }
+
+    /// Set the patterns of secrets masked in the display (None = no masking)
+    pub fn set_secret_matcher(&mut self, matcher: Option<SecretMatcher>) {
+        self.secret_matcher = matcher;
+        self.dirty = true;
+        self.view_damaged = true;
+    }
+
+    /// Columns of an absolute line showing secrets, which renderers draw as
+    /// "•" instead of the real characters
+    ///
+    /// Empty unless secret masking is on. Secrets are matched row by row, so
+    /// one split by a wrap is only masked if each part matches.
+    pub fn secret_columns(&self, line: usize) -> Vec<std::ops::Range<usize>> {
+        let (Some(matcher), Some(row)) = (&self.secret_matcher, self.row_with_scrollback(line))
+        else {
+            return Vec::new();
+        };
+        matcher.find_chars(&row.text())
+    }
+
+    /// Whether the selected text contains a secret (secret masking on)
+    ///
+    /// Frontends ask before copying such a selection.
+    pub fn selection_has_secret(&self) -> bool {
+        let Some(ref matcher) = self.secret_matcher else {
+            return false;
+        };
+        self.get_selected_text()
+            .is_some_and(|text| matcher.is_match(&text))
+    }
+
+    /// Show concealed (SGR 8) text instead of hiding it, for debugging
+    pub fn set_reveal_concealed(&mut self, reveal: bool) {
+        if self.reveal_concealed != reveal {
+            self.reveal_concealed = reveal;
+            self.dirty = true;
+        }
+    }
+
+    /// Whether a cell's text should be hidden (SGR 8, unless revealed)
+    ///
+    /// Concealed text is still copied as plain text; renderers draw only its
+    /// background and HTML export replaces it with spaces.
+    pub fn is_concealed(&self, cell: &Cell) -> bool {
+        cell.attrs.contains(crate::cell::CellAttrs::HIDDEN) && !self.reveal_concealed
+    }
+
+    /// Enable or disable reordering right-to-left text for display
+    pub fn set_bidi(&mut self, enabled: bool) {
+        if self.bidi != enabled {
+            self.bidi = enabled;
+            self.dirty = true;
+        }
+    }
+
+    /// Whether right-to-left text is reordered for display
+    pub fn bidi(&self) -> bool {
+        self.bidi
+    }
+
+    /// Display order of an absolute line (scrollback + visible area)
+    ///
+    /// None when bidi is disabled or the line has no right-to-left text, in
+    /// which case columns are displayed in logical order.
+    pub fn bidi_line(&self, line: usize) -> Option<BidiLine> {
+        if !self.bidi || self.horizontal_offset() > 0 {
+            return None;
+        }
+        let row = if line < self.scrollback.len() {
+            self.scrollback.get(line)?
+        } else {
+            self.grid.row(line - self.scrollback.len())?
+        };
+        BidiLine::new(row.iter())
+    }
+
+    /// Logical column of an absolute line displayed at a visual column
+    ///
+    /// Used to map mouse positions to cells, so selections cover the
+    /// characters under the mouse even in reordered or scrolled lines.
+    pub fn logical_col(&self, line: usize, visual_col: usize) -> usize {
+        let offset = self.horizontal_offset();
+        if offset > 0 {
+            return visual_col + offset;
+        }
+        self.bidi_line(line)
+            .map_or(visual_col, |bidi| bidi.logical_col(visual_col))
+    }
+
+    /// Take the visible rows that need repainting since the previous call
+    ///
+    /// Combines the grid rows changed by output with cursor movement, selection
+    /// and scrollback viewing changes. Renderers call this once per frame;
+    /// anything they change themselves (theme, size, fonts) still needs a full
+    /// repaint on their side.
+    pub fn take_damage(&mut self) -> Damage {
+        let mut damage = self.grid.take_damage();
+        let frame = FrameState {
+            cursor: (self.cursor.row, self.cursor.col),
+            show_cursor: self.modes.show_cursor,
+            scroll_offset: self.scroll_offset,
+            scrollback_total_pushed: self.scrollback_total_pushed,
+            alternate_screen: self.modes.alternate_screen,
+            selection: self.selection.clone(),
+            badge: self.badge(),
+            predicted_input: self.predicted_input(),
+        };
+
+        match self.last_frame.replace(frame.clone()) {
+            Some(last) if !self.view_damaged && last.same_view(&frame) => {
+                if (last.cursor, last.show_cursor) != (frame.cursor, frame.show_cursor)
+                    || last.predicted_input != frame.predicted_input
+                {
+                    damage.mark_row(last.cursor.0);
+                    damage.mark_row(frame.cursor.0);
+                }
+            }
+            _ => damage.mark_all(),
+        }
+
+        // Grid rows are shifted on screen while viewing scrollback
+        if frame.scroll_offset > 0 && !damage.is_empty() {
+            damage.mark_all();
+        }
+
+        self.view_damaged = false;
+        damage
+    }
+
     /// Get the selected text as a string
     ///
+    /// Hyperlinked text is copied as set with `set_copy_hyperlink_mode`.
     /// Returns None if there's no selection or it's empty
     pub fn get_selected_text(&self) -> Option<String> {
-        let selection = self.selection.as_ref()?;
+        let mut selection = self.selection.clone()?;
+        self.snap_to_wide_chars(&mut selection);
         let (start, end) = selection.ordered();
 
         // Clamp to valid range
@@ -1789,6 +2912,18 @@ impl Screen {
         let mut result = String::new();
         let end_line = end.line.min(total - 1);
 
+        // Hyperlink of the text copied since the given position of `result`
+        let mut link: Option<(Arc<Hyperlink>, usize)> = None;
+        let finish_link = |result: &mut String, link: Option<(Arc<Hyperlink>, usize)>| {
+            let Some((link, start)) = link else {
+                return;
+            };
+            let label = result.split_off(start);
+            let trimmed = label.trim_end();
+            result.push_str(&self.copy_hyperlink_mode.format(trimmed, &link.uri));
+            result.push_str(&label[trimmed.len()..]);
+        };
+
         // For block selection, use consistent column range across all lines
         let is_block = selection.mode == SelectionMode::Block;
         let (block_start_col, block_end_col) = if is_block {
@@ -1804,30 +2939,38 @@ impl Screen {
 
         for line_idx in start.line..=end_line {
             let row = self.get_row_by_absolute_line(line_idx)?;
+            // Truncated long lines are copied in full
+            let row_len = row.len() + row.overflow_len();
 
             let (start_col, end_col) = if is_block {
                 // Block selection: same columns for all lines
                 (
                     block_start_col,
-                    block_end_col.min(row.len().saturating_sub(1)),
+                    block_end_col.min(row_len.saturating_sub(1)),
                 )
             } else {
                 // Normal selection: varies by line
                 let sc = if line_idx == start.line { start.col } else { 0 };
                 let ec = if line_idx == end.line {
-                    end.col.min(row.len().saturating_sub(1))
+                    end.col.min(row_len.saturating_sub(1))
                 } else {
-                    row.len().saturating_sub(1)
+                    row_len.saturating_sub(1)
                 };
                 (sc, ec)
             };
 
             // Extract characters from this row
             for col in start_col..=end_col {
-                if let Some(cell) = row.get(col) {
+                if let Some(cell) = row.get_extended(col) {
                     // Skip wide character spacers
                     if !cell.attrs.contains(crate::cell::CellAttrs::WIDE_SPACER) {
-                        result.push(cell.c);
+                        if self.copy_hyperlink_mode != HyperlinkCopyMode::Text
+                            && link.as_ref().map(|(link, _)| link) != cell.hyperlink.as_ref()
+                        {
+                            finish_link(&mut result, link.take());
+                            link = cell.hyperlink.clone().map(|link| (link, result.len()));
+                        }
+                        cell.write_grapheme_to(&mut result);
                     }
                 }
             }
@@ -1835,10 +2978,13 @@ impl Screen {
             // Add newline between lines
             // For block selection: always add newlines between lines
             // For normal selection: skip newline after wrapped lines
+            // (a link continues on the wrapped row)
             if line_idx < end_line && (is_block || !row.wrapped) {
+                finish_link(&mut result, link.take());
                 result.push('\n');
             }
         }
+        finish_link(&mut result, link.take());
 
         // Trim trailing whitespace from each line but keep newlines
         let trimmed: String = result
@@ -1857,8 +3003,13 @@ impl Screen {
     /// Get the selected text as HTML with styling
     ///
     /// Returns HTML with inline styles for colors and attributes.
-    /// The color palette is used to convert ANSI colors to RGB.
-    pub fn get_selected_html(&self, palette: &crate::color::ColorPalette) -> Option<String> {
+    /// The color palette is used to convert ANSI colors to RGB, and faint
+    /// text is blended toward its background by `faint_opacity`.
+    pub fn get_selected_html(
+        &self,
+        palette: &crate::color::ColorPalette,
+        faint_opacity: f64,
+    ) -> Option<String> {
         use crate::cell::CellAttrs;
         use crate::color::Color;
 
@@ -1947,8 +3098,17 @@ impl Screen {
                         // Build style string
                         let mut style_parts = Vec::new();
 
-                        // Foreground color (skip if default)
-                        if !cell.fg.is_default() {
+                        // Foreground color (skip if default, unless it has to be faded)
+                        if cell.attrs.contains(CellAttrs::DIM) {
+                            let bg = if cell.bg.is_default() {
+                                palette.background
+                            } else {
+                                cell.bg.to_rgb(palette)
+                            };
+                            let rgb = cell.fg.to_rgb(palette).faint(bg, faint_opacity);
+                            style_parts
+                                .push(format!("color: #{:02X}{:02X}{:02X}", rgb.r, rgb.g, rgb.b));
+                        } else if !cell.fg.is_default() {
                             let rgb = cell.fg.to_rgb(palette);
                             style_parts
                                 .push(format!("color: #{:02X}{:02X}{:02X}", rgb.r, rgb.g, rgb.b));
@@ -1968,11 +3128,6 @@ impl Screen {
                             style_parts.push("font-weight: bold".to_string());
                         }
 
-                        // Dim
-                        if cell.attrs.contains(CellAttrs::DIM) {
-                            style_parts.push("opacity: 0.5".to_string());
-                        }
-
                         // Italic
                         if cell.attrs.contains(CellAttrs::ITALIC) {
                             style_parts.push("font-style: italic".to_string());
@@ -2011,6 +3166,7 @@ impl Screen {
 
                     // Append character (HTML-escaped)
                     match cell.c {
+                        _ if self.is_concealed(cell) => result.push(' '),
                         '<' => result.push_str("&lt;"),
                         '>' => result.push_str("&gt;"),
                         '&' => result.push_str("&amp;"),
@@ -2044,52 +3200,758 @@ impl Screen {
         }
     }
 
-    /// Get a row by absolute line index (0 = oldest scrollback line)
-    fn get_row_by_absolute_line(&self, line: usize) -> Option<&Row> {
-        let scrollback_len = self.scrollback.len();
-        if line < scrollback_len {
-            self.scrollback.get(line)
+    /// Copy the selected cells out, e.g. to render them as an image
+    ///
+    /// Returns None without a selection or if it covers only blank cells.
+    pub fn selected_region(&self) -> Option<CellRegion> {
+        let selection = self.selection.as_ref()?;
+        let (start, end) = selection.ordered();
+        let total = self.total_lines();
+        if start.line >= total {
+            return None;
+        }
+
+        let (block_start, block_end) = if selection.anchor.col <= selection.end.col {
+            (selection.anchor.col, selection.end.col)
         } else {
-            let grid_row = line - scrollback_len;
-            self.grid.row(grid_row)
+            (selection.end.col, selection.anchor.col)
+        };
+
+        let mut rows = Vec::new();
+        for line in start.line..=end.line.min(total - 1) {
+            let Some(row) = self.get_row_by_absolute_line(line) else {
+                continue;
+            };
+            let (first, last) = if selection.mode == SelectionMode::Block {
+                (block_start, block_end)
+            } else {
+                let first = if line == start.line { start.col } else { 0 };
+                let last = if line == end.line {
+                    end.col
+                } else {
+                    usize::MAX
+                };
+                (first, last)
+            };
+            let last = last.min(row.len().saturating_sub(1));
+            let cells = if first <= last {
+                (first..=last).filter_map(|col| row.get(col)).collect()
+            } else {
+                Vec::new()
+            };
+            rows.push(self.region_row(cells));
         }
+
+        let region = CellRegion { rows };
+        (!region.is_empty()).then_some(region)
     }
-}
 
-/// Screen clear mode
-#[derive(Debug, Clone, Copy)]
-pub enum ClearMode {
-    /// Clear from cursor to end of screen
-    Below,
-    /// Clear from start of screen to cursor
-    Above,
-    /// Clear entire screen
-    All,
-    /// Clear scrollback buffer
-    Scrollback,
-}
+    /// Copy the cells on screen at the current scroll position
+    ///
+    /// Blank rows at the bottom are left out.
+    pub fn visible_region(&self) -> CellRegion {
+        let mut rows: Vec<_> = (0..self.height())
+            .map(|row| {
+                let line = self.visible_row_to_absolute_line(row);
+                let cells = self
+                    .get_row_by_absolute_line(line)
+                    .map(|row| (0..row.len()).filter_map(|col| row.get(col)).collect())
+                    .unwrap_or_default();
+                self.region_row(cells)
+            })
+            .collect();
+        while rows.last().is_some_and(Vec::is_empty) {
+            rows.pop();
+        }
+        CellRegion { rows }
+    }
 
-/// Search result in terminal buffer
-#[derive(Debug, Clone)]
-pub struct SearchResult {
-    /// Line index (0 = oldest scrollback line)
-    pub line: usize,
-    /// Column where match starts
-    pub col: usize,
-    /// Length of match
-    pub len: usize,
-}
+    /// Blank concealed cells and drop trailing blanks from a row of a region
+    fn region_row(&self, cells: Vec<&Cell>) -> Vec<Cell> {
+        use crate::cell::CellAttrs;
+        use crate::color::Color;
 
-/// Line clear mode
-#[derive(Debug, Clone, Copy)]
-pub enum LineClearMode {
-    /// Clear from cursor to end of line
-    Right,
-    /// Clear from start of line to cursor
-    Left,
-    /// Clear entire line
-    All,
-}
+        let mut row: Vec<Cell> = cells
+            .into_iter()
+            .map(|cell| {
+                let mut cell = cell.clone();
+                if self.is_concealed(&cell) {
+                    cell.set_char(' ');
+                }
+                cell
+            })
+            .collect();
+        while row.last().is_some_and(|cell| {
+            cell.c == ' ' && cell.bg == Color::Default && !cell.attrs.contains(CellAttrs::INVERSE)
+        }) {
+            row.pop();
+        }
+        row
+    }
+
+    /// Record a semantic prompt mark (OSC 133) at the cursor position
+    ///
+    /// Marks are ignored in the alternate screen, which has no scrollback.
+    pub fn semantic_mark(&mut self, mark: SemanticMark) {
+        if self.modes.alternate_screen {
+            return;
+        }
+
+        let point = SelectionPoint::new(
+            self.scrollback_total_pushed + self.cursor.row,
+            self.cursor.col,
+        );
+
+        match mark {
+            SemanticMark::PromptStart => {
+                // Shells that never send 133;D still start a new prompt after
+                // the output, so treat the prompt as the end of the command
+                if let Some(mut zone) = self.current_zone.take() {
+                    if zone.output_start.is_some() {
+                        zone.command_end = Some(point);
+                        self.last_zone = Some(zone);
+                    }
+                }
+                self.current_zone = Some(CommandZone::default());
+                self.prompt_started = true;
+            }
+            SemanticMark::CommandStart => {
+                self.current_zone
+                    .get_or_insert_with(Default::default)
+                    .command_start = Some(point);
+            }
+            SemanticMark::OutputStart => {
+                let zone = self.current_zone.get_or_insert_with(Default::default);
+                zone.output_start = Some(point);
+                if let Some(start) = zone.command_start {
+                    self.remember_command(start, point);
+                }
+            }
+            SemanticMark::CommandEnd(exit_code) => {
+                // A 133;D without output (e.g. an empty command line) is ignored
+                if let Some(mut zone) = self.current_zone.take() {
+                    if zone.output_start.is_some() {
+                        zone.command_end = Some(point);
+                        zone.exit_code = exit_code;
+                        self.last_zone = Some(zone);
+                        self.command_finished = true;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Whether a prompt was marked since the last call, clearing the flag
+    pub fn take_prompt_started(&mut self) -> bool {
+        std::mem::take(&mut self.prompt_started)
+    }
+
+    /// Whether a command finished (OSC 133 D) since the last call, clearing
+    /// the flag; its exit code is [`Screen::last_command_exit_code`]
+    pub fn take_command_finished(&mut self) -> bool {
+        std::mem::take(&mut self.command_finished)
+    }
+
+    /// Get the command line of the last finished command (OSC 133 B to C)
+    ///
+    /// Returns None if no command was marked or it has scrolled out of the buffer.
+    pub fn last_command_text(&self) -> Option<String> {
+        let zone = self.last_zone?;
+        let text = self.text_between(zone.command_start?, zone.output_start?)?;
+        let text = text.trim();
+        (!text.is_empty()).then(|| text.to_string())
+    }
+
+    /// Add the command line between two points to the command history
+    ///
+    /// Multi-line commands are not remembered, and a repeated command moves
+    /// to the end instead of being stored twice.
+    fn remember_command(&mut self, start: SelectionPoint, end: SelectionPoint) {
+        let Some(text) = self.text_between(start, end) else {
+            return;
+        };
+        let command = text.trim();
+        if command.is_empty() || command.contains('\n') {
+            return;
+        }
+        if let Some(pos) = self.command_history.iter().position(|c| c == command) {
+            self.command_history.remove(pos);
+        } else if self.command_history.len() >= MAX_COMMAND_HISTORY {
+            self.command_history.pop_front();
+        }
+        self.command_history.push_back(command.to_string());
+    }
+
+    /// Enable or disable inline suggestions from the command history
+    pub fn set_inline_suggestions(&mut self, enabled: bool) {
+        if self.inline_suggestions != enabled {
+            self.inline_suggestions = enabled;
+            self.dirty = true;
+        }
+    }
+
+    /// Get the completion suggested for the command line being typed
+    ///
+    /// Returns the rest of the most recent history entry that starts with the
+    /// text between the OSC 133 command start and the cursor, as long as
+    /// nothing follows the cursor on its line. Returns None when suggestions
+    /// are disabled, the shell is not at a prompt or nothing matches.
+    pub fn inline_suggestion(&self) -> Option<String> {
+        if !self.inline_suggestions
+            || self.modes.alternate_screen
+            || self.scroll_offset != 0
+            || self.predictions.shown_len() > 0
+        {
+            return None;
+        }
+        let zone = self.current_zone?;
+        let start = zone.command_start?;
+        if zone.output_start.is_some() {
+            return None;
+        }
+
+        // Only suggest at the end of the line, where the suggestion has room
+        let row = self.grid.row(self.cursor.row)?;
+        if (self.cursor.col..row.len()).any(|col| row.get(col).is_some_and(|c| c.c != ' ')) {
+            return None;
+        }
+
+        let cursor = SelectionPoint::new(
+            self.scrollback_total_pushed + self.cursor.row,
+            self.cursor.col,
+        );
+        let typed = self.raw_text_between(start, cursor)?;
+        if typed.trim().is_empty() || typed.contains('\n') {
+            return None;
+        }
+        self.command_history
+            .iter()
+            .rev()
+            .find(|c| c.len() > typed.len() && c.starts_with(&typed))
+            .map(|c| c[typed.len()..].to_string())
+    }
+
+    /// Enable or disable predictive local echo
+    pub fn set_local_echo(&mut self, enabled: bool) {
+        if self.local_echo != enabled {
+            self.local_echo = enabled;
+            self.predictions.clear();
+        }
+    }
+
+    /// Whether typed characters are drawn before the program echoes them
+    pub fn local_echo(&self) -> bool {
+        self.local_echo
+    }
+
+    /// Predict the echo of input written to the PTY
+    ///
+    /// Printable characters typed at the end of a line are predicted and
+    /// Backspace takes back the last prediction. Anything else (Enter,
+    /// control keys, escape sequences) hides the predictions until the
+    /// program has echoed one again, since it may have turned echo off.
+    pub fn predict_input(&mut self, data: &[u8]) {
+        if !self.local_echo {
+            return;
+        }
+        if self.modes.alternate_screen {
+            self.predictions.clear();
+            return;
+        }
+        let Ok(text) = std::str::from_utf8(data) else {
+            self.predictions.hide();
+            return;
+        };
+
+        for c in text.chars() {
+            match c {
+                '' | '' => {
+                    if !self.predictions.pop() {
+                        self.predictions.hide();
+                    }
+                }
+                c if !c.is_control()
+                    && unicode_width::UnicodeWidthChar::width(c) == Some(1)
+                    && self.can_predict() =>
+                {
+                    self.predictions.push(c, (self.cursor.row, self.cursor.col));
+                }
+                _ => self.predictions.hide(),
+            }
+        }
+    }
+
+    /// Whether a typed character can be predicted: the cursor is at the end
+    /// of its line, with room left for the character
+    fn can_predict(&self) -> bool {
+        let col = if self.predictions.is_empty() {
+            self.cursor.col
+        } else {
+            self.predictions.end_col()
+        };
+        let Some(row) = self.grid.row(self.cursor.row) else {
+            return false;
+        };
+        col < self.width() && (col..row.len()).all(|c| row.get(c).is_none_or(|cell| cell.c == ' '))
+    }
+
+    /// Match the predicted echo against the output processed since the last call
+    pub fn reconcile_predictions(&mut self) {
+        if self.predictions.is_empty() {
+            return;
+        }
+        if self.modes.alternate_screen {
+            self.predictions.clear();
+            return;
+        }
+        let grid = &self.grid;
+        self.predictions
+            .reconcile((self.cursor.row, self.cursor.col), |row, col| {
+                grid.get(row, col).map(|cell| cell.c)
+            });
+    }
+
+    /// Typed characters drawn at the cursor until the program echoes them
+    pub fn predicted_input(&self) -> Option<String> {
+        if self.scroll_offset != 0 {
+            return None;
+        }
+        self.predictions.shown()
+    }
+
+    /// Get the output of the last finished command (OSC 133 C to D)
+    ///
+    /// Returns None if no command was marked, its output was empty or it has
+    /// scrolled out of the buffer.
+    pub fn last_command_output(&self) -> Option<String> {
+        let zone = self.last_zone?;
+        let text = self.text_between(zone.output_start?, zone.command_end?)?;
+        let text = text.trim_end_matches('\n');
+        (!text.is_empty()).then(|| text.to_string())
+    }
+
+    /// Get the exit code reported for the last finished command (OSC 133;D)
+    pub fn last_command_exit_code(&self) -> Option<i32> {
+        self.last_zone?.exit_code
+    }
+
+    /// Delete the output of the last finished command (OSC 133 C to D)
+    ///
+    /// The lines it took up are removed from the scrollback and the screen,
+    /// and everything after them - normally the current prompt - moves up in
+    /// their place, so a screenful of unwanted output goes away while the
+    /// command line and earlier history stay. Returns false if there is no
+    /// marked output left in the buffer.
+    pub fn clear_to_last_prompt(&mut self) -> bool {
+        if self.modes.alternate_screen {
+            return false;
+        }
+        let Some(zone) = self.last_zone else {
+            return false;
+        };
+        let (Some(start), Some(end)) = (zone.output_start, zone.command_end) else {
+            return false;
+        };
+
+        // Whole lines only, never reaching the cursor line
+        let first_line = self.first_scrollback_line();
+        let cursor_line = self.scrollback_total_pushed + self.cursor.row;
+        let start_line = start.line.max(first_line);
+        let end_line = end.line.min(cursor_line);
+        if start_line >= end_line {
+            return false;
+        }
+        let count = end_line - start_line;
+
+        // Remove the part on the screen first, while grid rows still map to
+        // the same stable lines
+        let grid_start =
+            start_line.max(self.scrollback_total_pushed) - self.scrollback_total_pushed;
+        let grid_end = end_line.saturating_sub(self.scrollback_total_pushed);
+        if grid_start < grid_end {
+            let height = self.grid.height();
+            self.grid
+                .scroll_up(grid_end - grid_start, grid_start, height);
+            self.cursor.row -= grid_end - grid_start;
+        }
+
+        // Then the part in the scrollback
+        let scrollback_end = end_line.min(self.scrollback_total_pushed);
+        if start_line < scrollback_end {
+            self.scrollback
+                .drain(start_line - first_line..scrollback_end - first_line);
+            self.scrollback_total_pushed -= scrollback_end - start_line;
+            self.scroll_offset = self.scroll_offset.min(self.scrollback.len());
+        }
+
+        // Lines after the removed ones move up
+        let shift = |line: usize| if line >= end_line { line - count } else { line };
+        self.bookmarks
+            .retain(|&line| line < start_line || line >= end_line);
+        for line in &mut self.bookmarks {
+            *line = shift(*line);
+        }
+        if let Some(zone) = &mut self.current_zone {
+            for point in [
+                &mut zone.command_start,
+                &mut zone.output_start,
+                &mut zone.command_end,
+            ]
+            .into_iter()
+            .flatten()
+            {
+                point.line = shift(point.line);
+            }
+        }
+        self.last_zone = None;
+
+        // Grid-anchored images use stable lines too
+        self.images.retain(|_, img| {
+            img.placement.anchor == ImageAnchor::Viewport
+                || img.line + img.cell_height <= start_line
+                || img.line >= end_line
+        });
+        for img in self.images.values_mut() {
+            if img.placement.anchor == ImageAnchor::Grid {
+                img.line = shift(img.line);
+            }
+        }
+
+        self.selection = None;
+        self.search = None;
+        self.dirty = true;
+        self.view_damaged = true;
+        true
+    }
+
+    /// Stable line number of the top visible line
+    fn view_top_line(&self) -> usize {
+        self.scrollback_total_pushed - self.scroll_offset
+    }
+
+    /// Toggle a bookmark at the current position
+    ///
+    /// While scrolled back the bookmark goes on the top visible line, otherwise
+    /// on the cursor line. Bookmarks are ignored in the alternate screen.
+    /// Returns true if a bookmark was added, false if one was removed.
+    pub fn toggle_bookmark(&mut self) -> bool {
+        if self.modes.alternate_screen {
+            return false;
+        }
+
+        let line = if self.scroll_offset > 0 {
+            self.view_top_line()
+        } else {
+            self.scrollback_total_pushed + self.cursor.row
+        };
+
+        self.view_damaged = true;
+        match self.bookmarks.binary_search(&line) {
+            Ok(idx) => {
+                self.bookmarks.remove(idx);
+                false
+            }
+            Err(idx) => {
+                self.bookmarks.insert(idx, line);
+                true
+            }
+        }
+    }
+
+    /// Scroll to the nearest bookmark above the top of the view
+    ///
+    /// Past the oldest bookmark this wraps around to the newest one. Returns
+    /// false if there is no bookmark to jump to.
+    pub fn jump_to_bookmark(&mut self) -> bool {
+        if self.modes.alternate_screen {
+            return false;
+        }
+
+        let top = self.view_top_line();
+        let Some(&line) = self
+            .bookmarks
+            .iter()
+            .rev()
+            .find(|&&line| line < top)
+            .or_else(|| self.bookmarks.last())
+        else {
+            return false;
+        };
+
+        let first_line = self.scrollback_total_pushed - self.scrollback.len();
+        self.scroll_offset = self.line_to_scroll_offset(line - first_line);
+        true
+    }
+
+    /// Rows of the current view that carry a bookmark
+    pub fn visible_bookmark_rows(&self) -> impl Iterator<Item = usize> + '_ {
+        let top = self.view_top_line();
+        let height = self.height();
+        let alternate = self.modes.alternate_screen;
+        self.bookmarks
+            .iter()
+            .filter(move |_| !alternate)
+            .filter_map(move |&line| line.checked_sub(top).filter(|&row| row < height))
+    }
+
+    /// Extract text between two stable line positions (end exclusive)
+    fn text_between(&self, start: SelectionPoint, end: SelectionPoint) -> Option<String> {
+        let result = self.raw_text_between(start, end)?;
+
+        // Trim trailing whitespace from each line but keep newlines
+        Some(
+            result
+                .lines()
+                .map(|l| l.trim_end())
+                .collect::<Vec<_>>()
+                .join("\n"),
+        )
+    }
+
+    /// Extract text between two stable line positions (end exclusive),
+    /// keeping trailing whitespace
+    fn raw_text_between(&self, start: SelectionPoint, end: SelectionPoint) -> Option<String> {
+        // Stable line number of the oldest line still in the scrollback
+        let first_line = self.scrollback_total_pushed - self.scrollback.len();
+        if start.line < first_line || !start.is_before(&end) {
+            return None;
+        }
+        let start_line = start.line - first_line;
+        let end_line = end.line - first_line;
+
+        let mut result = String::new();
+        for line_idx in start_line..=end_line {
+            let Some(row) = self.get_row_by_absolute_line(line_idx) else {
+                break;
+            };
+
+            let start_col = if line_idx == start_line { start.col } else { 0 };
+            let end_col = if line_idx == end_line {
+                end.col.min(row.len())
+            } else {
+                row.len()
+            };
+
+            for col in start_col..end_col {
+                if let Some(cell) = row.get(col) {
+                    // Skip wide character spacers
+                    if !cell.attrs.contains(crate::cell::CellAttrs::WIDE_SPACER) {
+                        cell.write_grapheme_to(&mut result);
+                    }
+                }
+            }
+
+            // Skip newline after wrapped lines
+            if line_idx < end_line && !row.wrapped {
+                result.push('\n');
+            }
+        }
+
+        Some(result)
+    }
+
+    /// Get a row by absolute line index (0 = oldest scrollback line)
+    fn get_row_by_absolute_line(&self, line: usize) -> Option<&Row> {
+        let scrollback_len = self.scrollback.len();
+        if line < scrollback_len {
+            self.scrollback.get(line)
+        } else {
+            let grid_row = line - scrollback_len;
+            self.grid.row(grid_row)
+        }
+    }
+}
+
+/// Screen clear mode
+#[derive(Debug, Clone, Copy)]
+pub enum ClearMode {
+    /// Clear from cursor to end of screen
+    Below,
+    /// Clear from start of screen to cursor
+    Above,
+    /// Clear entire screen
+    All,
+    /// Clear scrollback buffer
+    Scrollback,
+}
+
+/// Search result in terminal buffer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchResult {
+    /// Line index (0 = oldest scrollback line)
+    pub line: usize,
+    /// Column where match starts
+    pub col: usize,
+    /// Length of match
+    pub len: usize,
+}
+
+/// How a cell is highlighted by the active search
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SearchHighlight {
+    /// Part of a search match
+    Match,
+    /// Part of the current search match
+    Current,
+}
+
+/// What a rendered frame showed besides cell contents, to detect changes
+/// that need repainting rows the grid didn't touch
+#[derive(Debug, Clone, PartialEq)]
+struct FrameState {
+    cursor: (usize, usize),
+    show_cursor: bool,
+    scroll_offset: usize,
+    scrollback_total_pushed: usize,
+    alternate_screen: bool,
+    selection: Option<Selection>,
+    badge: Option<String>,
+    predicted_input: Option<String>,
+}
+
+impl FrameState {
+    /// Whether both frames show the same region with the same overlays,
+    /// ignoring the cursor
+    fn same_view(&self, other: &FrameState) -> bool {
+        self.scroll_offset == other.scroll_offset
+            && (self.scroll_offset == 0
+                || self.scrollback_total_pushed == other.scrollback_total_pushed)
+            && self.alternate_screen == other.alternate_screen
+            && self.selection == other.selection
+            && self.badge == other.badge
+    }
+}
+
+/// The query and matches of the search being cycled through
+#[derive(Debug, Clone)]
+struct ActiveSearch {
+    pattern: String,
+    case_sensitive: bool,
+    regex: bool,
+    matcher: SearchMatcher,
+    /// Matches with stable line numbers, in order
+    matches: Vec<SearchResult>,
+    current: usize,
+    /// Stable line number of the first line that wasn't in the scrollback
+    /// when the matches were last updated
+    scanned_to: usize,
+}
+
+impl ActiveSearch {
+    fn is_query(&self, pattern: &str, case_sensitive: bool, regex: bool) -> bool {
+        self.pattern == pattern && self.case_sensitive == case_sensitive && self.regex == regex
+    }
+}
+
+/// A compiled search query
+#[derive(Debug, Clone)]
+enum SearchMatcher {
+    Regex(regex::Regex),
+    /// Plain text, lowercased when the search is case-insensitive
+    Text {
+        pattern: String,
+        case_sensitive: bool,
+    },
+}
+
+impl SearchMatcher {
+    /// Compile a query; None if it's empty or an invalid regex
+    fn new(pattern: &str, case_sensitive: bool, regex: bool) -> Option<Self> {
+        if pattern.is_empty() {
+            return None;
+        }
+
+        if regex {
+            regex::RegexBuilder::new(pattern)
+                .case_insensitive(!case_sensitive)
+                .build()
+                .ok()
+                .map(Self::Regex)
+        } else if case_sensitive {
+            Some(Self::Text {
+                pattern: pattern.to_string(),
+                case_sensitive,
+            })
+        } else {
+            Some(Self::Text {
+                pattern: pattern.to_lowercase(),
+                case_sensitive,
+            })
+        }
+    }
+
+    /// Check whether a single row's text contains a match
+    fn is_match(&self, line_text: &str, lower_buf: &mut String) -> bool {
+        match self {
+            Self::Regex(re) => re.is_match(line_text),
+            Self::Text {
+                pattern,
+                case_sensitive: true,
+            } => line_text.contains(pattern.as_str()),
+            Self::Text { pattern, .. } => {
+                lower_buf.clear();
+                lower_buf.push_str(&line_text.to_lowercase());
+                lower_buf.contains(pattern.as_str())
+            }
+        }
+    }
+
+    /// Search for pattern matches within a single row's text
+    fn find_in(
+        &self,
+        line_text: &str,
+        lower_buf: &mut String,
+        line_idx: usize,
+        results: &mut Vec<SearchResult>,
+    ) {
+        match self {
+            Self::Regex(re) => {
+                for m in re.find_iter(line_text) {
+                    results.push(SearchResult {
+                        line: line_idx,
+                        col: m.start(),
+                        len: m.len(),
+                    });
+                }
+            }
+            Self::Text {
+                pattern,
+                case_sensitive,
+            } => {
+                // Simple string search - reuse lower_buf for case-insensitive
+                let search_text = if *case_sensitive {
+                    line_text
+                } else {
+                    lower_buf.clear();
+                    lower_buf.push_str(&line_text.to_lowercase());
+                    lower_buf.as_str()
+                };
+
+                let mut start = 0;
+                while let Some(pos) = search_text[start..].find(pattern.as_str()) {
+                    let col = start + pos;
+                    results.push(SearchResult {
+                        line: line_idx,
+                        col,
+                        len: pattern.len(),
+                    });
+                    start = col + 1;
+                }
+            }
+        }
+    }
+}
+
+/// Line clear mode
+#[derive(Debug, Clone, Copy)]
+pub enum LineClearMode {
+    /// Clear from cursor to end of line
+    Right,
+    /// Clear from start of line to cursor
+    Left,
+    /// Clear entire line
+    All,
+}
 
 #[cfg(test)]
 mod tests {
@@ -2129,6 +3991,309 @@ mod tests {
         assert_eq!(screen.grid().row(2).unwrap().text(), "d");
     }
 
+    #[test]
+    fn test_long_line_truncate() {
+        let mut screen = Screen::new(5, 3, ScreenConfig::default());
+        screen.set_long_line_mode(LongLineMode::Truncate);
+
+        for c in "Hello World".chars() {
+            screen.put_char(c);
+        }
+        let row = screen.grid().row(0).unwrap();
+        assert_eq!(row.text(), "Hello World");
+        assert_eq!(row.overflow_len(), 6);
+        assert!(!row.wrapped);
+        assert_eq!(screen.grid().row(1).unwrap().text(), "");
+        assert_eq!((screen.cursor.row, screen.cursor.col), (0, 5));
+        assert!(screen.line_continues_right(0));
+
+        // Scrolling right shows the rest, up to the end of the longest line
+        screen.scroll_horizontal(4);
+        assert_eq!(screen.horizontal_offset(), 4);
+        assert_eq!(screen.visible_cell(0, 0).unwrap().c, 'o');
+        assert!(screen.line_continues_right(0));
+        screen.scroll_horizontal(100);
+        assert_eq!(screen.horizontal_offset(), 6);
+        assert_eq!(screen.visible_cell(0, 4).unwrap().c, 'd');
+        assert!(!screen.line_continues_right(0));
+        screen.scroll_horizontal(-100);
+        assert_eq!(screen.horizontal_offset(), 0);
+
+        // Rewriting the line from the start replaces the overflow
+        screen.carriage_return();
+        for c in "abcdefg".chars() {
+            screen.put_char(c);
+        }
+        assert_eq!(screen.grid().row(0).unwrap().text(), "abcdefgorld");
+        screen.clear_line(LineClearMode::Right);
+        assert_eq!(screen.grid().row(0).unwrap().text(), "abcdefg");
+
+        // Clearing the line drops the overflow and the offset with it
+        screen.scroll_horizontal(2);
+        screen.clear_line(LineClearMode::All);
+        assert_eq!(screen.grid().row(0).unwrap().overflow_len(), 0);
+        assert_eq!(screen.horizontal_offset(), 0);
+
+        // Widening the screen brings the cut-off cells back
+        screen.carriage_return();
+        for c in "12345678".chars() {
+            screen.put_char(c);
+        }
+        screen.resize(8, 3);
+        assert_eq!(screen.get_cell(0, 7).unwrap().c, '8');
+        assert_eq!(screen.grid().row(0).unwrap().overflow_len(), 0);
+    }
+
+    #[test]
+    fn test_pending_wrap() {
+        let mut screen = Screen::new(5, 4, ScreenConfig::default());
+
+        // Filling the row leaves the wrap pending with the cursor drawn on
+        // the last column
+        for c in "Hello".chars() {
+            screen.put_char(c);
+        }
+        assert_eq!((screen.cursor.row, screen.cursor.col), (0, 5));
+        assert_eq!(screen.cursor_render_col(), 4);
+
+        // CR LF after an exactly full row doesn't leave a blank line
+        screen.carriage_return();
+        screen.line_feed();
+        for c in "World!".chars() {
+            screen.put_char(c);
+        }
+        assert_eq!(screen.grid().row(1).unwrap().text(), "World");
+        assert_eq!(screen.grid().row(2).unwrap().text(), "!");
+        assert!(!screen.grid().row(0).unwrap().wrapped);
+        assert!(screen.grid().row(1).unwrap().wrapped);
+
+        screen.carriage_return();
+        screen.line_feed();
+        for c in "abc".chars() {
+            screen.put_char(c);
+        }
+
+        // Copied text joins the wrapped row and keeps the real line breaks
+        screen.start_selection(0, 0, SelectionMode::Char);
+        screen.extend_selection(3, 4);
+        assert_eq!(screen.get_selected_text().unwrap(), "Hello\nWorld!\nabc");
+    }
+
+    #[test]
+    fn test_secret_masking() {
+        let mut screen = Screen::new(20, 3, ScreenConfig::default());
+        for c in "key=s3cret ok".chars() {
+            screen.put_char(c);
+        }
+        let line = screen.visible_row_to_absolute_line(0);
+        screen.start_selection(line, 0, SelectionMode::Char);
+        screen.extend_selection(line, 12);
+
+        // Nothing is masked until patterns are set
+        assert!(screen.secret_columns(line).is_empty());
+        assert!(!screen.selection_has_secret());
+
+        screen.set_secret_matcher(Some(SecretMatcher::new(&["s3cret"]).unwrap()));
+        assert_eq!(screen.secret_columns(line), vec![4..10]);
+        assert!(screen.selection_has_secret());
+        // The real text is still there
+        assert_eq!(screen.get_selected_text().unwrap(), "key=s3cret ok");
+
+        screen.extend_selection(line, 2);
+        assert!(!screen.selection_has_secret());
+    }
+
+    #[test]
+    fn test_copy_hyperlinks() {
+        let mut screen = Screen::new(10, 3, ScreenConfig::default());
+        let print = |screen: &mut Screen, text: &str, uri: Option<&str>| {
+            screen.style.hyperlink = uri.map(|uri| Arc::new(Hyperlink::new(uri.to_string())));
+            for c in text.chars() {
+                screen.put_char(c);
+            }
+        };
+        print(&mut screen, "see ", None);
+        // Wraps onto the second row
+        print(&mut screen, "the docs", Some("https://x.y/a b"));
+        print(&mut screen, " ok", None);
+        screen.start_selection(0, 0, SelectionMode::Char);
+        screen.extend_selection(1, 9);
+
+        assert_eq!(screen.get_selected_text().unwrap(), "see the docs ok");
+        screen.set_copy_hyperlink_mode(HyperlinkCopyMode::Url);
+        assert_eq!(
+            screen.get_selected_text().unwrap(),
+            "see https://x.y/a b ok"
+        );
+        screen.set_copy_hyperlink_mode(HyperlinkCopyMode::Markdown);
+        assert_eq!(
+            screen.get_selected_text().unwrap(),
+            "see [the docs](https://x.y/a%20b) ok"
+        );
+
+        // Part of a link copies as a link too
+        screen.start_selection(0, 4, SelectionMode::Char);
+        screen.extend_selection(0, 6);
+        assert_eq!(
+            screen.get_selected_text().unwrap(),
+            "[the](https://x.y/a%20b)"
+        );
+    }
+
+    #[test]
+    fn test_concealed_text() {
+        let mut screen = Screen::new(80, 3, ScreenConfig::default());
+        for c in "Password for the production database: ".chars() {
+            screen.put_char(c);
+        }
+        screen.style.attrs.insert(crate::cell::CellAttrs::HIDDEN);
+        for c in "hunter2".chars() {
+            screen.put_char(c);
+        }
+        screen.start_selection(0, 0, SelectionMode::Char);
+        screen.extend_selection(0, 45);
+
+        assert!(screen.is_concealed(&screen.grid()[0][39]));
+        assert!(!screen.is_concealed(&screen.grid()[0][0]));
+        // Copied as plain text, but not into HTML
+        assert_eq!(
+            screen.get_selected_text().unwrap(),
+            "Password for the production database: hunter2"
+        );
+        let palette = crate::color::ColorPalette::default();
+        let html = screen.get_selected_html(&palette, 0.5).unwrap();
+        assert!(html.contains("database: "));
+        assert!(!html.contains("hunter2"));
+
+        screen.set_reveal_concealed(true);
+        assert!(!screen.is_concealed(&screen.grid()[0][39]));
+        let html = screen.get_selected_html(&palette, 0.5).unwrap();
+        assert!(html.contains("hunter2"));
+    }
+
+    #[test]
+    fn test_cell_regions() {
+        let mut screen = Screen::new(10, 4, ScreenConfig::default());
+        let text = |row: &[Cell]| row.iter().map(|cell| cell.c).collect::<String>();
+        for c in "ab cd".chars() {
+            screen.put_char(c);
+        }
+        screen.cursor.row = 1;
+        screen.cursor.col = 0;
+        screen.style.attrs.insert(crate::cell::CellAttrs::HIDDEN);
+        for c in "xyz".chars() {
+            screen.put_char(c);
+        }
+
+        // Trailing blank rows and cells are trimmed, concealed text blanked
+        let region = screen.visible_region();
+        assert_eq!(region.height(), 1);
+        assert_eq!(text(&region.rows[0]), "ab cd");
+
+        screen.set_reveal_concealed(true);
+        let region = screen.visible_region();
+        assert_eq!(region.height(), 2);
+        assert_eq!(region.width(), 5);
+        assert_eq!(text(&region.rows[1]), "xyz");
+
+        screen.start_selection(0, 3, SelectionMode::Char);
+        screen.extend_selection(1, 1);
+        let region = screen.selected_region().unwrap();
+        assert_eq!(text(&region.rows[0]), "cd");
+        assert_eq!(text(&region.rows[1]), "xy");
+
+        screen.start_selection(0, 1, SelectionMode::Block);
+        screen.extend_selection(1, 2);
+        let region = screen.selected_region().unwrap();
+        assert_eq!(text(&region.rows[0]), "b");
+        assert_eq!(text(&region.rows[1]), "yz");
+
+        // Only blanks selected
+        screen.start_selection(2, 0, SelectionMode::Char);
+        screen.extend_selection(3, 5);
+        assert!(screen.selected_region().is_none());
+    }
+
+    #[test]
+    fn test_line_feed_cancels_pending_wrap() {
+        let mut screen = Screen::new(5, 3, ScreenConfig::default());
+
+        for c in "abcde".chars() {
+            screen.put_char(c);
+        }
+        screen.line_feed();
+        screen.put_char('x');
+
+        // The next character lands on the last column of the next row rather
+        // than wrapping once more and skipping a row
+        assert_eq!(screen.grid().row(1).unwrap().text(), "    x");
+        assert_eq!((screen.cursor.row, screen.cursor.col), (1, 5));
+        assert!(screen.grid().row(2).unwrap().text().trim().is_empty());
+    }
+
+    #[test]
+    fn test_wide_char_wraps_at_last_column() {
+        let mut screen = Screen::new(5, 3, ScreenConfig::default());
+
+        for c in "abcd中".chars() {
+            screen.put_char(c);
+        }
+        assert_eq!(screen.grid().row(0).unwrap().text(), "abcd");
+        assert!(screen.grid().row(0).unwrap().wrapped);
+        assert_eq!(screen.get_cell(1, 0).unwrap().c, '中');
+        assert_eq!(screen.cursor.col, 2);
+    }
+
+    #[test]
+    fn test_emoji_grapheme_width() {
+        let mut screen = Screen::new(20, 3, ScreenConfig::default());
+
+        // A flag is two regional indicators in one wide cell
+        for c in "🇫🇷x".chars() {
+            screen.put_char(c);
+        }
+        let flag = screen.get_cell(0, 0).unwrap();
+        assert!(flag.is_wide());
+        assert_eq!(flag.grapheme_text(), "🇫🇷");
+        assert!(screen.get_cell(0, 1).unwrap().is_wide_spacer());
+        assert_eq!(screen.get_cell(0, 2).unwrap().c, 'x');
+        assert_eq!(screen.cursor.col, 3);
+
+        // A ZWJ family is one wide cell, as are an emoji with a skin tone
+        // and a heart with a variation selector
+        screen.carriage_return();
+        screen.line_feed();
+        for c in "👨\u{200D}👩\u{200D}👧👍🏽e\u{301}y".chars() {
+            screen.put_char(c);
+        }
+        let family = screen.get_cell(1, 0).unwrap();
+        assert!(family.is_wide());
+        assert_eq!(family.grapheme_text(), "👨\u{200D}👩\u{200D}👧");
+        assert_eq!(screen.get_cell(1, 2).unwrap().grapheme_text(), "👍🏽");
+        assert_eq!(screen.get_cell(1, 4).unwrap().grapheme_text(), "e\u{301}");
+        assert_eq!(screen.get_cell(1, 5).unwrap().c, 'y');
+        assert_eq!(screen.cursor.col, 6);
+
+        // Copying keeps the whole sequence
+        screen.start_selection(1, 0, SelectionMode::Char);
+        screen.extend_selection(1, 5);
+        assert_eq!(
+            screen.get_selected_text().as_deref(),
+            Some("👨\u{200D}👩\u{200D}👧👍🏽e\u{301}y")
+        );
+
+        // Without grapheme clusters, each emoji takes its own cells
+        screen.set_grapheme_clusters(false);
+        screen.carriage_return();
+        screen.line_feed();
+        for c in "👨\u{200D}👩🇫🇷".chars() {
+            screen.put_char(c);
+        }
+        assert_eq!(screen.get_cell(2, 0).unwrap().grapheme_text(), "👨\u{200D}");
+        assert_eq!(screen.get_cell(2, 2).unwrap().c, '👩');
+        assert_eq!(screen.cursor.col, 6);
+    }
+
     #[test]
     fn test_scroll_up() {
         let mut screen = Screen::new(80, 3, ScreenConfig::default());
@@ -2265,4 +4430,564 @@ mod tests {
         assert_eq!(sel.anchor, SelectionPoint::new(0, 5));
         assert_eq!(sel.end, SelectionPoint::new(0, 5));
     }
+
+    #[test]
+    fn test_word_selection_extend_backward_then_forward() {
+        // "foo bar baz" - double-click on "bar", drag back to "foo", then forward to "baz"
+        let mut screen = screen_with_text("foo bar baz");
+        screen.start_selection(0, 5, SelectionMode::Word);
+
+        screen.extend_selection(0, 1);
+        screen.extend_selection(0, 9);
+        let sel = screen.selection.as_ref().unwrap();
+        // The whole original word must stay selected
+        assert_eq!(sel.anchor, SelectionPoint::new(0, 4));
+        assert_eq!(sel.end, SelectionPoint::new(0, 10));
+    }
+
+    #[test]
+    fn test_selection_snaps_to_wide_chars() {
+        // "日本語" takes columns 0-5, each character two cells wide
+        let mut screen = screen_with_text("日本語");
+
+        // From the right half of 日 to the left half of 本
+        screen.start_selection(0, 1, SelectionMode::Char);
+        screen.extend_selection(0, 2);
+        assert_eq!(screen.get_selected_text().as_deref(), Some("日本"));
+        assert!(screen.is_selected(0, 0));
+        assert!(screen.is_selected(0, 3));
+
+        // Dragging backwards from the right half of 語 to the left half of 本
+        screen.start_selection(0, 5, SelectionMode::Char);
+        screen.extend_selection(0, 2);
+        assert_eq!(screen.get_selected_text().as_deref(), Some("本語"));
+
+        // Block selection ending on the left half of 本
+        screen.start_selection(0, 1, SelectionMode::Block);
+        screen.extend_selection(0, 2);
+        assert_eq!(screen.get_selected_text().as_deref(), Some("日本"));
+    }
+
+    #[test]
+    fn test_line_selection_extend_up_then_down() {
+        let mut screen = Screen::new(20, 5, ScreenConfig::default());
+        screen.start_selection(2, 3, SelectionMode::Line);
+
+        screen.extend_selection(0, 0);
+        screen.extend_selection(3, 0);
+        let sel = screen.selection.as_ref().unwrap();
+        assert_eq!(sel.anchor, SelectionPoint::new(2, 0));
+        assert_eq!(sel.end, SelectionPoint::new(3, COL_END_OF_ROW));
+    }
+
+    #[test]
+    fn test_command_zone_scrolled_out() {
+        let mut screen = Screen::new(
+            20,
+            3,
+            ScreenConfig {
+                scrollback_lines: 2,
+            },
+        );
+
+        screen.semantic_mark(SemanticMark::PromptStart);
+        screen.semantic_mark(SemanticMark::CommandStart);
+        for c in "seq 10".chars() {
+            screen.put_char(c);
+        }
+        screen.carriage_return();
+        screen.line_feed();
+        screen.semantic_mark(SemanticMark::OutputStart);
+        for i in 1..=3 {
+            for c in i.to_string().chars() {
+                screen.put_char(c);
+            }
+            screen.carriage_return();
+            screen.line_feed();
+        }
+        screen.semantic_mark(SemanticMark::CommandEnd(Some(0)));
+        assert_eq!(screen.last_command_output().as_deref(), Some("1\n2\n3"));
+
+        // Push the command line out of the two-line scrollback
+        for _ in 0..3 {
+            screen.line_feed();
+        }
+        assert_eq!(screen.last_command_text(), None);
+        assert_eq!(screen.last_command_output(), None);
+    }
+
+    #[test]
+    fn test_bidi_logical_col() {
+        let mut screen = Screen::new(10, 3, ScreenConfig::default());
+        for c in "a שלום".chars() {
+            screen.put_char(c);
+        }
+        assert_eq!(screen.logical_col(0, 2), 2);
+        assert!(screen.bidi_line(0).is_none());
+
+        screen.set_bidi(true);
+        // "a םולש": the ש typed in column 2 is displayed in column 5
+        assert_eq!(screen.logical_col(0, 5), 2);
+        assert_eq!(screen.logical_col(0, 0), 0);
+        assert_eq!(screen.bidi_line(0).unwrap().visual_col(3), 4);
+        assert!(screen.bidi_line(1).is_none());
+    }
+
+    #[test]
+    fn test_inline_suggestion() {
+        fn run(screen: &mut Screen, command: &str) {
+            screen.semantic_mark(SemanticMark::PromptStart);
+            screen.put_char('$');
+            screen.put_char(' ');
+            screen.semantic_mark(SemanticMark::CommandStart);
+            for c in command.chars() {
+                screen.put_char(c);
+            }
+            screen.carriage_return();
+            screen.line_feed();
+            screen.semantic_mark(SemanticMark::OutputStart);
+            screen.semantic_mark(SemanticMark::CommandEnd(Some(0)));
+        }
+
+        let mut screen = Screen::new(40, 10, ScreenConfig::default());
+        run(&mut screen, "git status");
+        run(&mut screen, "git log");
+        run(&mut screen, "ls");
+
+        screen.semantic_mark(SemanticMark::PromptStart);
+        screen.put_char('$');
+        screen.put_char(' ');
+        screen.semantic_mark(SemanticMark::CommandStart);
+        screen.put_char('g');
+
+        // Disabled by default
+        assert_eq!(screen.inline_suggestion(), None);
+        screen.set_inline_suggestions(true);
+
+        // The most recent match wins
+        assert_eq!(screen.inline_suggestion().as_deref(), Some("it log"));
+        for c in "it s".chars() {
+            screen.put_char(c);
+        }
+        assert_eq!(screen.inline_suggestion().as_deref(), Some("tatus"));
+
+        // A complete command has nothing left to suggest
+        for c in "tatus".chars() {
+            screen.put_char(c);
+        }
+        assert_eq!(screen.inline_suggestion(), None);
+
+        // Nothing is suggested while the cursor is inside the line
+        screen.cursor.col = 4;
+        assert_eq!(screen.inline_suggestion(), None);
+    }
+
+    #[test]
+    fn test_image_placements_scroll_and_stack() {
+        let mut screen = Screen::new(
+            10,
+            4,
+            ScreenConfig {
+                scrollback_lines: 2,
+            },
+        );
+        // 8x16 pixels covers exactly one cell with the default hints
+        let image = |rows: usize| SixelImage {
+            data: vec![0; 8 * 16 * rows * 4],
+            width: 8,
+            height: 16 * rows,
+        };
+        let placement = |z_index, anchor| ImagePlacement { z_index, anchor };
+
+        screen.add_image_placement(0, 2, (1, 2), image(2), placement(1, ImageAnchor::Grid));
+        screen.add_image_placement(1, 2, (1, 1), image(1), placement(-1, ImageAnchor::Grid));
+        screen.add_image_placement(2, 0, (1, 1), image(1), placement(0, ImageAnchor::Viewport));
+        let order: Vec<i32> = screen
+            .visible_images()
+            .iter()
+            .map(|img| img.placement.z_index)
+            .collect();
+        assert_eq!(order, vec![-1, 0, 1]);
+        assert_eq!(screen.image_at_position(3, 0).map(|img| img.id), Some(0));
+
+        // Grid images keep moving up once the scrollback is full, and one
+        // partly scrolled off the top reports a negative row; the viewport
+        // image stays put
+        screen.cursor.row = 3;
+        for _ in 0..3 {
+            screen.line_feed();
+        }
+        assert_eq!(screen.scrollback().len(), 2);
+        let rows: Vec<Option<isize>> = (0..3)
+            .map(|id| {
+                screen
+                    .image_by_id(id)
+                    .and_then(|img| screen.image_visible_row(img))
+            })
+            .collect();
+        assert_eq!(rows, vec![Some(-1), None, Some(0)]);
+
+        // Scrolled back into view
+        screen.scroll_offset = 2;
+        assert_eq!(screen.visible_images().len(), 3);
+        screen.scroll_offset = 0;
+
+        // Pruned once their last row leaves the scrollback
+        for _ in 0..2 {
+            screen.line_feed();
+        }
+        assert!(screen.image_by_id(1).is_none());
+        assert!(screen.image_by_id(0).is_some());
+        screen.line_feed();
+        assert!(screen.image_by_id(0).is_none());
+        assert!(screen.image_by_id(2).is_some());
+    }
+
+    #[test]
+    fn test_image_eviction() {
+        let mut screen = Screen::new(10, 4, ScreenConfig::default());
+        screen.set_max_images(3);
+        let image = || SixelImage {
+            data: vec![0; 8 * 16 * 4],
+            width: 8,
+            height: 16,
+        };
+
+        for col in 0..3 {
+            screen.add_image_with_size(col, 0, 1, 1, image());
+        }
+        assert_eq!(screen.image_memory(), (3, 3 * 8 * 16 * 4));
+        assert_eq!(screen.visible_images().len(), 3);
+
+        // The 4th image evicts the 1st, which keeps its place but isn't drawn
+        screen.add_image_with_size(3, 0, 1, 1, image());
+        assert!(screen.image_by_id(0).unwrap().is_evicted());
+        assert!(!screen.image_by_id(1).unwrap().is_evicted());
+        assert_eq!(screen.image_memory(), (4, 3 * 8 * 16 * 4));
+        let ids: Vec<u64> = screen.visible_images().iter().map(|img| img.id).collect();
+        assert_eq!(ids, vec![1, 2, 3]);
+        assert_eq!(screen.image_at_position(0, 0).map(|img| img.id), None);
+
+        // Lowering the limit evicts at once
+        screen.set_max_images(1);
+        let ids: Vec<u64> = screen.visible_images().iter().map(|img| img.id).collect();
+        assert_eq!(ids, vec![3]);
+    }
+
+    #[test]
+    fn test_clear_to_last_prompt() {
+        let mut screen = Screen::new(10, 4, ScreenConfig::default());
+        let write = |screen: &mut Screen, text: &str| {
+            for c in text.chars() {
+                screen.put_char(c);
+            }
+        };
+        let newline = |screen: &mut Screen| {
+            screen.carriage_return();
+            screen.line_feed();
+        };
+        assert!(!screen.clear_to_last_prompt());
+
+        write(&mut screen, "old");
+        newline(&mut screen);
+        screen.semantic_mark(SemanticMark::PromptStart);
+        write(&mut screen, "$ ");
+        screen.semantic_mark(SemanticMark::CommandStart);
+        write(&mut screen, "seq 5");
+        newline(&mut screen);
+        screen.semantic_mark(SemanticMark::OutputStart);
+        for i in 1..=5 {
+            write(&mut screen, &i.to_string());
+            newline(&mut screen);
+        }
+        screen.semantic_mark(SemanticMark::CommandEnd(Some(0)));
+        screen.semantic_mark(SemanticMark::PromptStart);
+        write(&mut screen, "$ ");
+        screen.toggle_bookmark();
+        assert_eq!(screen.scrollback().len(), 4);
+
+        // The output goes, from the scrollback and the screen; the prompt
+        // moves up under the command line
+        assert!(screen.clear_to_last_prompt());
+        let scrollback: Vec<String> = screen.scrollback().iter().map(|r| r.text()).collect();
+        assert_eq!(scrollback, vec!["old", "$ seq 5"]);
+        assert_eq!(screen.grid().row(0).unwrap().text(), "$");
+        assert!(screen.grid().row(1).unwrap().is_all_empty());
+        assert_eq!((screen.cursor.row, screen.cursor.col), (0, 2));
+        assert_eq!(screen.visible_bookmark_rows().collect::<Vec<_>>(), vec![0]);
+
+        // Only once per command
+        assert_eq!(screen.last_command_output(), None);
+        assert!(!screen.clear_to_last_prompt());
+    }
+
+    #[test]
+    fn test_bookmarks() {
+        let mut screen = Screen::new(
+            10,
+            3,
+            ScreenConfig {
+                scrollback_lines: 5,
+            },
+        );
+        let write_line = |screen: &mut Screen, c: char| {
+            screen.put_char(c);
+            screen.carriage_return();
+            screen.line_feed();
+        };
+
+        assert!(!screen.jump_to_bookmark());
+
+        // Bookmark the lines "a" and "c" are written on
+        assert!(screen.toggle_bookmark());
+        write_line(&mut screen, 'a');
+        write_line(&mut screen, 'b');
+        assert!(screen.toggle_bookmark());
+        for c in ['c', 'd', 'e'] {
+            write_line(&mut screen, c);
+        }
+        assert_eq!(screen.visible_bookmark_rows().count(), 0);
+
+        // Jumps go upwards and wrap around to the newest bookmark
+        assert!(screen.jump_to_bookmark());
+        assert_eq!(screen.scroll_offset, 1);
+        assert_eq!(screen.visible_bookmark_rows().collect::<Vec<_>>(), vec![0]);
+        assert!(screen.jump_to_bookmark());
+        assert_eq!(screen.scroll_offset, 3);
+        assert_eq!(
+            screen.visible_bookmark_rows().collect::<Vec<_>>(),
+            vec![0, 2]
+        );
+        assert!(screen.jump_to_bookmark());
+        assert_eq!(screen.scroll_offset, 1);
+
+        // Toggling while scrolled back removes the bookmark on the top line
+        assert!(!screen.toggle_bookmark());
+        assert_eq!(screen.visible_bookmark_rows().count(), 0);
+
+        // Bookmarks are dropped once their line leaves the scrollback
+        screen.scroll_offset = 0;
+        for c in ['f', 'g', 'h'] {
+            write_line(&mut screen, c);
+        }
+        assert!(!screen.jump_to_bookmark());
+    }
+
+    #[test]
+    fn test_badge() {
+        let mut screen = Screen::new(80, 24, ScreenConfig::default());
+        assert_eq!(screen.badge(), None);
+
+        screen.title = "prod-db".to_string();
+        screen.set_badge_format("\\(session.name) \\(user.role)");
+        assert_eq!(screen.badge().as_deref(), Some("prod-db \\(user.role)"));
+
+        screen.set_badge_format("");
+        assert_eq!(screen.badge(), None);
+    }
+
+    #[test]
+    fn test_take_damage() {
+        let mut screen = Screen::new(10, 5, ScreenConfig::default());
+        screen.cursor.row = 2;
+
+        // The first frame paints everything
+        assert!(screen.take_damage().is_full());
+        assert!(screen.take_damage().is_empty());
+
+        // Printing damages only the cursor's row
+        screen.put_char('x');
+        let damage = screen.take_damage();
+        assert!(!damage.is_full());
+        assert_eq!(damage.rows().collect::<Vec<_>>(), vec![2]);
+
+        // Moving the cursor repaints where it was and where it is
+        screen.cursor.row = 4;
+        assert_eq!(screen.take_damage().rows().collect::<Vec<_>>(), vec![2, 4]);
+
+        // Scrolling moves every row in the region
+        screen.line_feed();
+        assert_eq!(screen.take_damage().rows().count(), 5);
+
+        // Changing the selection repaints everything
+        screen.start_selection(0, 0, SelectionMode::Char);
+        assert!(screen.take_damage().is_full());
+    }
+
+    #[test]
+    fn test_search_next_cycles_matches() {
+        let mut screen = Screen::new(20, 3, ScreenConfig::default());
+        for c in "foo bar foo".chars() {
+            screen.put_char(c);
+        }
+
+        let first = screen.search_next("foo", true, false).unwrap();
+        assert_eq!((first.line, first.col), (0, 0));
+        assert_eq!(screen.search_match_count(), 2);
+        assert_eq!(
+            screen.search_highlight(0, 1),
+            Some(SearchHighlight::Current)
+        );
+        assert_eq!(screen.search_highlight(0, 8), Some(SearchHighlight::Match));
+        assert_eq!(screen.search_highlight(0, 4), None);
+
+        // Repeating the query moves to the next match and wraps around
+        let second = screen.search_next("foo", true, false).unwrap();
+        assert_eq!(second.col, 8);
+        assert_eq!(screen.search_highlight(0, 0), Some(SearchHighlight::Match));
+        assert_eq!(screen.search_next("foo", true, false).unwrap().col, 0);
+
+        // A different query starts over
+        assert_eq!(screen.search_next("bar", true, false).unwrap().col, 4);
+        assert!(screen.search_next("baz", true, false).is_none());
+        assert_eq!(screen.search_match_count(), 0);
+
+        screen.search_next("foo", true, false);
+        screen.clear_search();
+        assert_eq!(screen.search_highlight(0, 0), None);
+    }
+
+    #[test]
+    fn test_extract_matches() {
+        let mut screen = Screen::new(
+            20,
+            3,
+            ScreenConfig {
+                scrollback_lines: 10,
+            },
+        );
+        for line in ["INFO start", "ERROR disk full", "info done", "Error again"] {
+            for c in line.chars() {
+                screen.put_char(c);
+            }
+            screen.carriage_return();
+            screen.line_feed();
+        }
+
+        // Matches come from both the scrollback and the visible grid
+        assert_eq!(
+            screen.extract_matches("ERROR", true, false),
+            vec!["ERROR disk full"]
+        );
+        assert_eq!(
+            screen.extract_matches("error", false, false),
+            vec!["ERROR disk full", "Error again"]
+        );
+        assert_eq!(
+            screen.extract_matches("^(INFO|info) ", true, true),
+            vec!["INFO start", "info done"]
+        );
+        assert!(screen.extract_matches("(", false, true).is_empty());
+        assert!(screen.extract_matches("", false, false).is_empty());
+    }
+
+    #[test]
+    fn test_search_with_incoming_output() {
+        fn write_line(screen: &mut Screen, text: &str) {
+            screen.carriage_return();
+            screen.line_feed();
+            for c in text.chars() {
+                screen.put_char(c);
+            }
+        }
+
+        let mut screen = Screen::new(
+            20,
+            2,
+            ScreenConfig {
+                scrollback_lines: 3,
+            },
+        );
+        for c in "foo a".chars() {
+            screen.put_char(c);
+        }
+        write_line(&mut screen, "foo b");
+
+        screen.search_next("foo", true, false);
+        let current = screen.search_next("foo", true, false).unwrap();
+        assert_eq!(current.line, 1);
+
+        // New output scrolls "foo b" up a line; it stays the current match
+        // and the new line is searched too
+        write_line(&mut screen, "foo c");
+        screen.update_search();
+        assert_eq!(screen.search_match_count(), 3);
+        assert_eq!(
+            screen.search_highlight(1, 0),
+            Some(SearchHighlight::Current)
+        );
+        assert_eq!(screen.search_next("foo", true, false).unwrap().line, 2);
+
+        // Matches leave with their lines when the scrollback is trimmed
+        for _ in 0..3 {
+            write_line(&mut screen, "bar");
+        }
+        screen.update_search();
+        assert_eq!(screen.search_match_count(), 2);
+        assert_eq!(
+            screen.search_highlight(1, 0),
+            Some(SearchHighlight::Current)
+        );
+        assert_eq!(screen.search_next("foo", true, false).unwrap().line, 0);
+
+        // A snapshot search ignores new output
+        screen.set_search_follows_output(false);
+        screen.carriage_return();
+        for c in "foo d".chars() {
+            screen.put_char(c);
+        }
+        screen.update_search();
+        assert_eq!(screen.search_match_count(), 2);
+        screen.set_search_follows_output(true);
+        screen.update_search();
+        assert_eq!(screen.search_match_count(), 3);
+    }
+
+    #[test]
+    fn test_search_scrollback_only() {
+        let mut screen = Screen::new(
+            20,
+            2,
+            ScreenConfig {
+                scrollback_lines: 10,
+            },
+        );
+        for line in ["foo 1", "foo 2", "foo 3", "bar"] {
+            screen.carriage_return();
+            screen.line_feed();
+            for c in line.chars() {
+                screen.put_char(c);
+            }
+        }
+        // Scrollback: "", "foo 1", "foo 2"; screen: "foo 3", "bar"
+        assert_eq!(screen.find("foo", true, false).len(), 3);
+        screen.search_next("foo", true, false);
+        assert_eq!(screen.search_match_count(), 3);
+
+        screen.set_search_scrollback_only(true);
+        assert_eq!(screen.search_match_count(), 0);
+        let lines: Vec<usize> = screen
+            .find("foo", true, false)
+            .iter()
+            .map(|m| m.line)
+            .collect();
+        assert_eq!(lines, vec![1, 2]);
+        assert_eq!(screen.search_next("foo", true, false).unwrap().line, 1);
+        assert_eq!(screen.search_next("foo", true, false).unwrap().line, 2);
+        assert_eq!(screen.search_next("foo", true, false).unwrap().line, 1);
+        assert!(screen.search_next("bar", true, false).is_none());
+
+        // The screen repainting doesn't add matches; lines scrolling into
+        // the scrollback do
+        screen.carriage_return();
+        for c in "foo 4".chars() {
+            screen.put_char(c);
+        }
+        screen.search_next("foo", true, false);
+        screen.update_search();
+        assert_eq!(screen.search_match_count(), 2);
+        screen.line_feed();
+        screen.update_search();
+        assert_eq!(screen.search_match_count(), 3);
+    }
 }
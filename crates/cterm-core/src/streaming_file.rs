@@ -8,6 +8,7 @@ use base64::Engine;
 use std::fs::File;
 use std::io::{self, BufWriter, Write};
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
 
 /// Threshold for switching from memory buffer to temp file (1 MB)
 const MEMORY_THRESHOLD: usize = 1024 * 1024;
@@ -15,6 +16,9 @@ const MEMORY_THRESHOLD: usize = 1024 * 1024;
 /// Base64 decode chunk size (must be multiple of 4 for base64)
 const BASE64_CHUNK_SIZE: usize = 4096;
 
+/// Counter keeping the temp files of concurrent transfers apart
+static TEMP_FILE_COUNTER: AtomicU64 = AtomicU64::new(0);
+
 /// State of the streaming file receiver
 #[derive(Debug)]
 enum StorageState {
@@ -45,6 +49,8 @@ pub struct StreamingFileReceiver {
     storage: StorageState,
     /// Total decoded bytes received
     total_bytes: usize,
+    /// Largest file accepted, in decoded bytes (None = no limit)
+    max_bytes: Option<usize>,
     /// Whether we've encountered an error
     error: Option<String>,
 }
@@ -52,15 +58,33 @@ pub struct StreamingFileReceiver {
 impl StreamingFileReceiver {
     /// Create a new streaming file receiver with parsed parameters
     pub fn new(params: Iterm2FileParams) -> Self {
+        Self::with_max_bytes(params, None)
+    }
+
+    /// Create a receiver that aborts the transfer once the file grows past
+    /// `max_bytes` (or right away if its declared size is larger)
+    pub fn with_max_bytes(params: Iterm2FileParams, max_bytes: Option<usize>) -> Self {
         // Pre-allocate memory based on expected size if known
-        let initial_capacity = params.size.map(|s| s.min(MEMORY_THRESHOLD)).unwrap_or(8192);
+        let initial_capacity = params
+            .size
+            .map(|s| s.min(MEMORY_THRESHOLD).min(max_bytes.unwrap_or(usize::MAX)))
+            .unwrap_or(8192);
+
+        let error = match (params.size, max_bytes) {
+            (Some(size), Some(max)) if size > max => Some(format!(
+                "File of {} bytes exceeds the {} byte transfer limit",
+                size, max
+            )),
+            _ => None,
+        };
 
         Self {
             params,
             base64_buffer: Vec::with_capacity(BASE64_CHUNK_SIZE),
             storage: StorageState::Memory(Vec::with_capacity(initial_capacity)),
             total_bytes: 0,
-            error: None,
+            max_bytes,
+            error,
         }
     }
 
@@ -170,6 +194,11 @@ impl StreamingFileReceiver {
     fn write_decoded(&mut self, data: &[u8]) -> bool {
         self.total_bytes += data.len();
 
+        if let Some(max) = self.max_bytes.filter(|&max| self.total_bytes > max) {
+            self.abort(format!("File exceeds the {} byte transfer limit", max));
+            return false;
+        }
+
         // Check if we need to spill from memory to disk
         let should_spill = match &self.storage {
             StorageState::Memory(buffer) => buffer.len() + data.len() > MEMORY_THRESHOLD,
@@ -212,11 +241,27 @@ impl StreamingFileReceiver {
         }
     }
 
+    /// Stop the transfer with an error, dropping the data received so far
+    fn abort(&mut self, error: String) {
+        let storage = std::mem::replace(&mut self.storage, StorageState::Memory(Vec::new()));
+        if let StorageState::File { path, writer, .. } = storage {
+            drop(writer);
+            if let Err(e) = std::fs::remove_file(&path) {
+                log::warn!("Failed to remove temp file {}: {}", path.display(), e);
+            }
+        }
+        self.error = Some(error);
+    }
+
     /// Spill memory buffer to a temp file
     fn spill_to_disk(&mut self, existing_data: &[u8], new_data: &[u8]) -> io::Result<()> {
         // Create temp file
         let temp_dir = std::env::temp_dir();
-        let temp_path = temp_dir.join(format!("cterm_transfer_{}", std::process::id()));
+        let temp_path = temp_dir.join(format!(
+            "cterm_transfer_{}_{}",
+            std::process::id(),
+            TEMP_FILE_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
 
         let file = File::create(&temp_path)?;
         let mut writer = BufWriter::new(file);
@@ -249,20 +294,21 @@ impl StreamingFileReceiver {
     /// Returns the file parameters, data (memory or temp file path), and total size.
     pub fn finish(mut self) -> Result<StreamingFileResult, String> {
         // Decode any remaining base64 data
-        if !self.base64_buffer.is_empty() {
+        if self.error.is_none() && !self.base64_buffer.is_empty() {
             // Pad with '=' if needed for final chunk (at most 3 padding chars)
             for _ in 0..3 {
-                if self.base64_buffer.len() % 4 == 0 {
+                if self.base64_buffer.len().is_multiple_of(4) {
                     break;
                 }
                 self.base64_buffer.push(b'=');
             }
-            if !self.decode_chunk() {
-                return Err(self.error.unwrap_or_else(|| "Unknown error".to_string()));
-            }
+            // A failure is recorded in self.error
+            self.decode_chunk();
         }
 
-        if let Some(error) = self.error {
+        if let Some(error) = self.error.take() {
+            // Don't leave a partial temp file behind
+            self.abort(error.clone());
             return Err(error);
         }
 
@@ -401,6 +447,36 @@ mod tests {
         assert_eq!(bytes, b"Hello, World!");
     }
 
+    #[test]
+    fn test_streaming_size_limit() {
+        // Declared size over the limit: refused before any data
+        let params = Iterm2FileParams {
+            size: Some(100),
+            ..Default::default()
+        };
+        let mut receiver = StreamingFileReceiver::with_max_bytes(params, Some(10));
+        assert!(!receiver.put_bytes(b"SGVs"));
+        assert!(receiver.finish().is_err());
+
+        // Undeclared size: aborted once the data grows past the limit,
+        // including after spilling to disk
+        let mut receiver = StreamingFileReceiver::with_max_bytes(
+            Iterm2FileParams::default(),
+            Some(MEMORY_THRESHOLD + 8192),
+        );
+        let chunk = base64::engine::general_purpose::STANDARD.encode([0u8; 3 * 1024]);
+        while receiver.put_bytes(chunk.as_bytes()) {}
+        assert!(receiver.has_error());
+        assert!(!receiver.is_on_disk());
+        assert!(receiver.finish().is_err());
+
+        // Within the limit
+        let mut receiver =
+            StreamingFileReceiver::with_max_bytes(Iterm2FileParams::default(), Some(13));
+        assert!(receiver.put_bytes(b"SGVsbG8sIFdvcmxkIQ=="));
+        assert_eq!(receiver.finish().unwrap().total_bytes, 13);
+    }
+
     #[test]
     fn test_streaming_with_whitespace() {
         let params = Iterm2FileParams::default();
@@ -2,14 +2,22 @@
 //!
 //! Provides a high-level interface for terminal emulation.
 
-use crate::parser::Parser;
-use crate::pty::{Pty, PtyConfig, PtyError};
+use crate::directory::ReportedDirectory;
+use crate::encoding::{OutputDecoder, TerminalEncoding};
+use crate::local_echo::LocalEchoMode;
+use crate::parser::{Parser, UnknownSequenceMode};
+use crate::pty::{Pty, PtyConfig, PtyError, PtySize};
+use crate::recording::SessionRecorder;
 use crate::screen::{ClipboardOperation, Screen, ScreenConfig, SearchResult};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
 /// Events emitted by the terminal
 #[derive(Debug, Clone)]
 pub enum TerminalEvent {
-    /// Terminal title changed
+    /// Terminal title changed, or the directory reported with OSC 7 (which
+    /// titles can show)
     TitleChanged(String),
     /// Bell was rung
     Bell,
@@ -30,12 +38,89 @@ pub struct TerminalConfig {
     pub pty: PtyConfig,
 }
 
+/// What the Backspace or Delete key sends to the PTY
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EraseSequence {
+    /// ASCII DEL (`0x7f`)
+    Delete,
+    /// ASCII BS (`0x08`, Ctrl+H)
+    BackSpace,
+    /// VT220 Remove escape sequence (`CSI 3 ~`)
+    Escape,
+}
+
+/// Which sequences the Home and End keys send
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HomeEndStyle {
+    /// `CSI H` / `CSI F`, or `SS3 H` / `SS3 F` in application cursor mode
+    #[default]
+    Xterm,
+    /// Always `SS3 H` / `SS3 F`, whatever the cursor mode
+    Application,
+    /// VT220 editing keypad `CSI 1 ~` / `CSI 4 ~` (Find / Select), used by
+    /// screen, tmux and many older terminfo entries
+    Vt220,
+}
+
+/// Events that scroll the viewport back to the bottom
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScrollTrigger {
+    /// Input is written (typing, pasting)
+    Keypress,
+    /// The program writes output
+    Output,
+    /// The bell rings
+    Bell,
+    /// The shell marks a new prompt (OSC 133 A)
+    Prompt,
+}
+
+/// Event that clears the selection
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SelectionClearTrigger {
+    /// The program writes output
+    Output,
+    /// A key is typed into the terminal
+    #[default]
+    Keypress,
+    /// Keep the selection until the user clears it
+    Never,
+}
+
 /// Terminal instance managing screen, parser, and PTY
 pub struct Terminal {
     screen: Screen,
     parser: Parser,
     pty: Option<Pty>,
     last_title: String,
+    /// Directory reported with OSC 7 when the last events were emitted
+    last_directory: Option<ReportedDirectory>,
+    /// Sequence sent by the Backspace key
+    backspace_sends: EraseSequence,
+    /// Sequence sent by the Delete key
+    delete_sends: EraseSequence,
+    /// Sequences sent by the Home and End keys
+    home_end_style: HomeEndStyle,
+    /// Active session recording, if any
+    recorder: Option<SessionRecorder>,
+    /// When typed characters are echoed locally
+    local_echo: LocalEchoMode,
+    /// Echo latency above which `LocalEchoMode::Auto` predicts the echo
+    local_echo_threshold: Duration,
+    /// When input was written that no output has followed yet
+    input_sent_at: Option<Instant>,
+    /// Smoothed time between writing input and the next output
+    echo_latency: Option<Duration>,
+    /// Events that scroll the viewport back to the bottom
+    scroll_to_bottom_on: Vec<ScrollTrigger>,
+    /// Event that clears the selection
+    clear_selection_on: SelectionClearTrigger,
+    /// Converts output in a legacy encoding to UTF-8
+    decoder: OutputDecoder,
 }
 
 impl Terminal {
@@ -46,6 +131,18 @@ impl Terminal {
             parser: Parser::new(),
             pty: None,
             last_title: String::new(),
+            last_directory: None,
+            backspace_sends: EraseSequence::Delete,
+            delete_sends: EraseSequence::Escape,
+            home_end_style: HomeEndStyle::Xterm,
+            recorder: None,
+            local_echo: LocalEchoMode::Off,
+            local_echo_threshold: Duration::ZERO,
+            input_sent_at: None,
+            echo_latency: None,
+            scroll_to_bottom_on: vec![ScrollTrigger::Keypress],
+            clear_selection_on: SelectionClearTrigger::Keypress,
+            decoder: OutputDecoder::new(TerminalEncoding::Utf8),
         }
     }
 
@@ -59,6 +156,18 @@ impl Terminal {
             parser: Parser::new(),
             pty: Some(pty),
             last_title: title,
+            last_directory: None,
+            backspace_sends: EraseSequence::Delete,
+            delete_sends: EraseSequence::Escape,
+            home_end_style: HomeEndStyle::Xterm,
+            recorder: None,
+            local_echo: LocalEchoMode::Off,
+            local_echo_threshold: Duration::ZERO,
+            input_sent_at: None,
+            echo_latency: None,
+            scroll_to_bottom_on: vec![ScrollTrigger::Keypress],
+            clear_selection_on: SelectionClearTrigger::Keypress,
+            decoder: OutputDecoder::new(TerminalEncoding::Utf8),
         }
     }
 
@@ -83,6 +192,18 @@ impl Terminal {
             parser: Parser::new(),
             pty: Some(pty),
             last_title: title,
+            last_directory: None,
+            backspace_sends: EraseSequence::Delete,
+            delete_sends: EraseSequence::Escape,
+            home_end_style: HomeEndStyle::Xterm,
+            recorder: None,
+            local_echo: LocalEchoMode::Off,
+            local_echo_threshold: Duration::ZERO,
+            input_sent_at: None,
+            echo_latency: None,
+            scroll_to_bottom_on: vec![ScrollTrigger::Keypress],
+            clear_selection_on: SelectionClearTrigger::Keypress,
+            decoder: OutputDecoder::new(TerminalEncoding::Utf8),
         }
     }
 
@@ -99,11 +220,30 @@ impl Terminal {
 
         let pty = Pty::new(&config)?;
 
+        // The cell size hints follow the pixel size the shell was told
+        let mut screen = Screen::new(cols, rows, screen_config);
+        if config.size.pixel_width > 0 && config.size.pixel_height > 0 {
+            screen.set_cell_width_hint(config.size.pixel_width as f64 / cols as f64);
+            screen.set_cell_height_hint(config.size.pixel_height as f64 / rows as f64);
+        }
+
         Ok(Self {
-            screen: Screen::new(cols, rows, screen_config),
+            screen,
             parser: Parser::new(),
             pty: Some(pty),
             last_title: String::new(),
+            last_directory: None,
+            backspace_sends: EraseSequence::Delete,
+            delete_sends: EraseSequence::Escape,
+            home_end_style: HomeEndStyle::Xterm,
+            recorder: None,
+            local_echo: LocalEchoMode::Off,
+            local_echo_threshold: Duration::ZERO,
+            input_sent_at: None,
+            echo_latency: None,
+            scroll_to_bottom_on: vec![ScrollTrigger::Keypress],
+            clear_selection_on: SelectionClearTrigger::Keypress,
+            decoder: OutputDecoder::new(TerminalEncoding::Utf8),
         })
     }
 
@@ -132,25 +272,49 @@ impl Terminal {
         self.pty = Some(pty);
     }
 
+    /// Take the PTY out of this terminal, leaving it detached
+    pub fn take_pty(&mut self) -> Option<Pty> {
+        self.pty.take()
+    }
+
     /// Restore the screen state (for crash recovery)
     ///
     /// Replaces the current screen with the provided one, preserving the PTY.
     pub fn restore_screen(&mut self, screen: Screen) {
         self.last_title = screen.title.clone();
+        self.last_directory = screen.current_directory.clone();
         self.screen = screen;
+        self.update_local_echo();
     }
 
     /// Process input from the PTY and update the screen
     pub fn process(&mut self, data: &[u8]) -> Vec<TerminalEvent> {
         let mut events = Vec::new();
+        let decoded = self.decoder.decode(data);
+        let data = &*decoded;
+
+        if let Some(recorder) = self.recorder.as_mut() {
+            if let Err(e) = recorder.record_output(data) {
+                log::error!("Recording to {:?} failed, stopping: {}", recorder.path(), e);
+                self.recorder = None;
+            }
+        }
+
+        if let Some(sent_at) = self.input_sent_at.take() {
+            self.update_echo_latency(sent_at.elapsed());
+        }
 
         self.parser.parse(&mut self.screen, data);
+        self.screen.reconcile_predictions();
+
+        // Keep an open search in step with the new output
+        self.screen.update_search();
 
         // Send any pending responses back to the PTY
         if self.screen.has_pending_responses() {
             let responses = self.screen.take_pending_responses();
             for response in responses {
-                if let Err(e) = self.write(&response) {
+                if let Err(e) = self.write_pty(&response) {
                     log::error!("Failed to send response to PTY: {}", e);
                 }
             }
@@ -163,15 +327,30 @@ impl Terminal {
             }
         }
 
+        // Snap back to the bottom on the configured events
+        if !data.is_empty() {
+            self.scroll_to_bottom_for(ScrollTrigger::Output);
+            self.clear_selection_for(SelectionClearTrigger::Output);
+        }
+        if self.screen.bell {
+            self.scroll_to_bottom_for(ScrollTrigger::Bell);
+        }
+        if self.screen.take_prompt_started() {
+            self.scroll_to_bottom_for(ScrollTrigger::Prompt);
+        }
+
         // Check for bell
         if self.screen.bell {
             self.screen.bell = false;
             events.push(TerminalEvent::Bell);
         }
 
-        // Check for title change
-        if self.screen.title != self.last_title {
+        // Check for title change (or a new directory to format it with)
+        if self.screen.title != self.last_title
+            || self.screen.current_directory != self.last_directory
+        {
             self.last_title = self.screen.title.clone();
+            self.last_directory = self.screen.current_directory.clone();
             events.push(TerminalEvent::TitleChanged(self.last_title.clone()));
         }
 
@@ -185,6 +364,23 @@ impl Terminal {
 
     /// Write input to the PTY (keyboard input)
     pub fn write(&mut self, data: &[u8]) -> Result<(), PtyError> {
+        self.scroll_to_bottom_for(ScrollTrigger::Keypress);
+        if let Some(recorder) = self.recorder.as_mut() {
+            if let Err(e) = recorder.record_input(data) {
+                log::error!("Recording to {:?} failed, stopping: {}", recorder.path(), e);
+                self.recorder = None;
+            }
+        }
+        if self.local_echo != LocalEchoMode::Off {
+            self.input_sent_at.get_or_insert_with(Instant::now);
+            self.screen.predict_input(data);
+        }
+        let encoded = self.decoder.encoding().encode(data);
+        self.write_pty(&encoded)
+    }
+
+    /// Write to the PTY without recording (replies to the application)
+    fn write_pty(&mut self, data: &[u8]) -> Result<(), PtyError> {
         if let Some(ref mut pty) = self.pty {
             pty.write(data)?;
         }
@@ -196,6 +392,18 @@ impl Terminal {
         self.write(s.as_bytes())
     }
 
+    /// Send the last finished command (OSC 133 marks) to the shell again
+    ///
+    /// Returns false if no command has been recorded.
+    pub fn rerun_last_command(&mut self) -> Result<bool, PtyError> {
+        let Some(command) = self.screen.last_command_text() else {
+            return Ok(false);
+        };
+        // Terminate with CR, which is what the Enter key sends
+        self.write_str(&format!("{}\r", command))?;
+        Ok(true)
+    }
+
     /// Send clipboard data as OSC 52 response
     pub fn send_clipboard_response(
         &mut self,
@@ -213,17 +421,67 @@ impl Terminal {
 
         let encoded = base64::engine::general_purpose::STANDARD.encode(data);
         let response = format!("\x1b]52;{};{}\x07", selection_char, encoded);
-        self.write(response.as_bytes())
+        self.write_pty(response.as_bytes())
+    }
+
+    /// Start mirroring the session to a recorder, replacing any active one
+    pub fn start_recording(&mut self, recorder: SessionRecorder) {
+        if let Some(Err(e)) = self.stop_recording() {
+            log::error!("Failed to finish previous recording: {}", e);
+        }
+        self.recorder = Some(recorder);
+    }
+
+    /// Stop recording, returning the path of the finished file
+    ///
+    /// Returns `None` if the session wasn't being recorded.
+    pub fn stop_recording(&mut self) -> Option<std::io::Result<PathBuf>> {
+        self.recorder.take().map(SessionRecorder::finish)
+    }
+
+    /// Path of the active recording, if the session is being recorded
+    pub fn recording_path(&self) -> Option<&Path> {
+        self.recorder.as_ref().map(SessionRecorder::path)
     }
 
     /// Resize the terminal
     pub fn resize(&mut self, cols: usize, rows: usize) {
         self.screen.resize(cols, rows);
         if let Some(ref pty) = self.pty {
-            let _ = pty.resize(rows as u16, cols as u16);
+            let size = self.pty_size();
+            log::debug!(
+                "Resizing PTY to {}x{} cells, {}x{} pixels",
+                size.cols,
+                size.rows,
+                size.pixel_width,
+                size.pixel_height
+            );
+            if let Err(e) = pty.resize(size) {
+                log::warn!("Failed to resize PTY: {}", e);
+            }
         }
     }
 
+    /// Set the cell size in pixels (call from UI layer when font metrics are known)
+    ///
+    /// Used to size images and for the pixel size reported to the program,
+    /// which the PTY is told on the next `resize`.
+    pub fn set_cell_size(&mut self, width: f64, height: f64) {
+        self.screen.set_cell_width_hint(width);
+        self.screen.set_cell_height_hint(height);
+    }
+
+    /// Size to report to the PTY, with the pixel size worked out from the
+    /// cell size
+    pub fn pty_size(&self) -> PtySize {
+        PtySize::with_cell_size(
+            self.rows() as u16,
+            self.cols() as u16,
+            self.screen.cell_width_hint(),
+            self.screen.cell_height_hint(),
+        )
+    }
+
     /// Check if the process is still running
     pub fn is_running(&mut self) -> bool {
         if let Some(ref mut pty) = self.pty {
@@ -296,6 +554,36 @@ impl Terminal {
         self.pty.as_ref().and_then(|p| p.foreground_cwd())
     }
 
+    /// Directory to start new shells in, next to this one
+    ///
+    /// The foreground process's directory where the OS tells it, or else
+    /// the one the shell reported with OSC 7 if it's on this machine.
+    pub fn working_directory(&self) -> Option<PathBuf> {
+        #[cfg(unix)]
+        if let Some(cwd) = self.foreground_cwd() {
+            return Some(cwd);
+        }
+        self.screen
+            .current_directory
+            .as_ref()
+            .filter(|directory| directory.is_local())
+            .map(|directory| directory.path.clone())
+    }
+
+    /// Directory to show in titles
+    ///
+    /// The one the shell reported with OSC 7 (also over SSH), or else the
+    /// foreground process's directory.
+    pub fn display_directory(&self) -> Option<PathBuf> {
+        if let Some(directory) = &self.screen.current_directory {
+            return Some(directory.path.clone());
+        }
+        #[cfg(unix)]
+        return self.foreground_cwd();
+        #[cfg(not(unix))]
+        None
+    }
+
     /// Get terminal width
     pub fn cols(&self) -> usize {
         self.screen.width()
@@ -327,11 +615,78 @@ impl Terminal {
         self.screen.scroll_offset = 0;
     }
 
+    /// Scroll the viewport to the bottom if `trigger` is one of the events
+    /// set with `set_scroll_to_bottom_on`
+    ///
+    /// Returns whether the viewport moved.
+    pub fn scroll_to_bottom_for(&mut self, trigger: ScrollTrigger) -> bool {
+        let snap = self.screen.scroll_offset != 0 && self.scroll_to_bottom_on.contains(&trigger);
+        if snap {
+            self.scroll_viewport_to_bottom();
+        }
+        snap
+    }
+
+    /// Set the events that scroll the viewport back to the bottom (none
+    /// keeps it where the user scrolled it)
+    pub fn set_scroll_to_bottom_on(&mut self, triggers: &[ScrollTrigger]) {
+        self.scroll_to_bottom_on = triggers.to_vec();
+    }
+
+    /// Clear the selection if `trigger` is the event set with
+    /// `set_clear_selection_on`
+    ///
+    /// Returns whether a selection was cleared.
+    pub fn clear_selection_for(&mut self, trigger: SelectionClearTrigger) -> bool {
+        let clear = self.screen.selection.is_some() && self.clear_selection_on == trigger;
+        if clear {
+            self.screen.clear_selection();
+        }
+        clear
+    }
+
+    /// Set the event that clears the selection
+    pub fn set_clear_selection_on(&mut self, trigger: SelectionClearTrigger) {
+        self.clear_selection_on = trigger;
+    }
+
+    /// Set the character encoding of the program's output and input
+    pub fn set_encoding(&mut self, encoding: TerminalEncoding) {
+        if encoding != self.decoder.encoding() {
+            self.decoder = OutputDecoder::new(encoding);
+        }
+    }
+
+    /// Character encoding of the program's output and input
+    pub fn encoding(&self) -> TerminalEncoding {
+        self.decoder.encoding()
+    }
+
     /// Check if viewport is at bottom
     pub fn is_at_bottom(&self) -> bool {
         self.screen.scroll_offset == 0
     }
 
+    /// Arrow keys that step through the shell's history by `lines`, for a
+    /// wheel scroll up or down, or None if the wheel should scroll the
+    /// scrollback as usual
+    ///
+    /// Arrows are only sent while the primary screen is shown at the bottom
+    /// and the shell is idle at its prompt, with no program in the
+    /// foreground.
+    #[cfg(unix)]
+    pub fn wheel_history_keys(&self, up: bool, lines: usize) -> Option<Vec<u8>> {
+        let idle = self
+            .pty
+            .as_ref()
+            .is_some_and(|pty| !pty.has_foreground_process());
+        if !idle || self.screen.modes.alternate_screen || !self.is_at_bottom() {
+            return None;
+        }
+        let key = if up { Key::Up } else { Key::Down };
+        Some(self.handle_key(key, Modifiers::empty())?.repeat(lines))
+    }
+
     /// Search for text in scrollback and visible buffer
     pub fn find(&self, pattern: &str, case_sensitive: bool, regex: bool) -> Vec<SearchResult> {
         self.screen.find(pattern, case_sensitive, regex)
@@ -342,10 +697,96 @@ impl Terminal {
         self.screen.scroll_offset = self.screen.line_to_scroll_offset(line_idx);
     }
 
+    /// Set how unknown or unsupported escape sequences are handled
+    pub fn set_unknown_sequence_mode(&mut self, mode: UnknownSequenceMode) {
+        self.parser.set_unknown_sequence_mode(mode);
+    }
+
+    /// Set whether unhandled C0 controls are printed in caret notation (^@, ^A, ...)
+    pub fn set_print_control_as_caret(&mut self, enabled: bool) {
+        self.parser.set_print_control_as_caret(enabled);
+    }
+
+    /// Set the largest file accepted over OSC 1337 File= (None = no limit)
+    pub fn set_max_file_transfer_bytes(&mut self, max_bytes: Option<usize>) {
+        self.parser.set_max_file_transfer_bytes(max_bytes);
+    }
+
+    /// Set whether an open search picks up matches in new output
+    pub fn set_search_follows_output(&mut self, enabled: bool) {
+        self.screen.set_search_follows_output(enabled);
+    }
+
+    /// Set how many images keep their pixel data (0 = no limit)
+    pub fn set_max_images(&mut self, limit: usize) {
+        self.screen.set_max_images(limit);
+    }
+
+    /// Set whether leaving the alternate screen restores the primary
+    /// screen's cursor visibility
+    pub fn set_cursor_visibility_per_screen(&mut self, enabled: bool) {
+        self.screen.set_cursor_visibility_per_screen(enabled);
+    }
+
+    /// Set what the Backspace and Delete keys send
+    pub fn set_erase_sequences(&mut self, backspace: EraseSequence, delete: EraseSequence) {
+        self.backspace_sends = backspace;
+        self.delete_sends = delete;
+    }
+
+    /// Set which sequences the Home and End keys send
+    pub fn set_home_end_style(&mut self, style: HomeEndStyle) {
+        self.home_end_style = style;
+    }
+
+    /// Set when typed characters are echoed locally
+    ///
+    /// In `Auto` mode the echo is predicted while the echo latency is at
+    /// least `threshold`.
+    pub fn set_local_echo(&mut self, mode: LocalEchoMode, threshold: Duration) {
+        self.local_echo = mode;
+        self.local_echo_threshold = threshold;
+        self.update_local_echo();
+    }
+
+    /// Smoothed time between writing input and the next output, once measured
+    pub fn echo_latency(&self) -> Option<Duration> {
+        self.echo_latency
+    }
+
+    /// Add a latency sample and turn local echo on or off to match
+    fn update_echo_latency(&mut self, sample: Duration) {
+        // Smooth like TCP's round-trip estimate so one slow reply doesn't
+        // switch local echo on
+        self.echo_latency = Some(match self.echo_latency {
+            Some(latency) => (latency * 7 + sample) / 8,
+            None => sample,
+        });
+        self.update_local_echo();
+    }
+
+    fn update_local_echo(&mut self) {
+        let enabled = match self.local_echo {
+            LocalEchoMode::Off => false,
+            LocalEchoMode::On => true,
+            LocalEchoMode::Auto => self
+                .echo_latency
+                .is_some_and(|latency| latency >= self.local_echo_threshold),
+        };
+        self.screen.set_local_echo(enabled);
+    }
+
     /// Handle keyboard input and generate appropriate escape sequences
     pub fn handle_key(&self, key: Key, modifiers: Modifiers) -> Option<Vec<u8>> {
         let app_cursor = self.screen.modes.application_cursor;
-        let _app_keypad = self.screen.modes.application_keypad;
+        let app_keypad = self.screen.modes.application_keypad;
+
+        // Right/End accept the inline suggestion by typing the rest of it
+        if matches!(key, Key::Right | Key::End) && modifiers.is_empty() {
+            if let Some(suggestion) = self.screen.inline_suggestion() {
+                return Some(suggestion.into_bytes());
+            }
+        }
 
         match key {
             Key::Char(c) => {
@@ -390,33 +831,88 @@ impl Terminal {
                     Some(b"\t".to_vec())
                 }
             }
-            Key::Backspace => {
-                if modifiers.contains(Modifiers::ALT) {
-                    // Alt+Backspace
-                    Some(b"\x1b\x7f".to_vec())
-                } else if modifiers.contains(Modifiers::CTRL) {
-                    // Ctrl+Backspace - send Ctrl+W (delete word) or \x08
-                    Some(b"\x08".to_vec())
-                } else {
-                    Some(b"\x7f".to_vec())
-                }
-            }
+            Key::Backspace => Some(erase_key(self.backspace_sends, modifiers)),
             Key::Escape => Some(b"\x1b".to_vec()),
             Key::Up => Some(cursor_key(b'A', modifiers, app_cursor)),
             Key::Down => Some(cursor_key(b'B', modifiers, app_cursor)),
             Key::Right => Some(cursor_key(b'C', modifiers, app_cursor)),
             Key::Left => Some(cursor_key(b'D', modifiers, app_cursor)),
-            Key::Home => Some(cursor_key(b'H', modifiers, app_cursor)),
-            Key::End => Some(cursor_key(b'F', modifiers, app_cursor)),
+            Key::Home => Some(home_end_key(
+                b'H',
+                1,
+                self.home_end_style,
+                modifiers,
+                app_cursor,
+            )),
+            Key::End => Some(home_end_key(
+                b'F',
+                4,
+                self.home_end_style,
+                modifiers,
+                app_cursor,
+            )),
             Key::PageUp => Some(tilde_key(5, modifiers)),
             Key::PageDown => Some(tilde_key(6, modifiers)),
             Key::Insert => Some(tilde_key(2, modifiers)),
-            Key::Delete => Some(tilde_key(3, modifiers)),
+            Key::Delete => Some(erase_key(self.delete_sends, modifiers)),
             Key::F(n) => Some(function_key(n, modifiers)),
+            Key::KeypadEnter if app_keypad => Some(keypad_key(b'M', modifiers)),
+            Key::KeypadEnter => self.handle_key(Key::Enter, modifiers),
+            Key::Keypad(c) => match keypad_final(c).filter(|_| app_keypad) {
+                Some(final_byte) => Some(keypad_key(final_byte, modifiers)),
+                None => self.handle_key(Key::Char(c), modifiers),
+            },
         }
     }
 }
 
+/// SS3 final byte for a keypad digit or operator in application keypad mode
+fn keypad_final(c: char) -> Option<u8> {
+    Some(match c {
+        '0'..='9' => b'p' + (c as u8 - b'0'),
+        '*' => b'j',
+        '+' => b'k',
+        ',' => b'l',
+        '-' => b'm',
+        '.' => b'n',
+        '/' => b'o',
+        '=' => b'X',
+        _ => return None,
+    })
+}
+
+/// Generate the bytes for a keypad key in application keypad mode:
+/// `SS3 final`, or `SS3 modifier final` with modifiers as xterm does
+fn keypad_key(final_byte: u8, modifiers: Modifiers) -> Vec<u8> {
+    let modifier = modifier_param(modifiers);
+
+    if modifier > 1 {
+        format!("\x1bO{}{}", modifier, final_byte as char).into_bytes()
+    } else {
+        vec![0x1b, b'O', final_byte]
+    }
+}
+
+/// Generate the bytes for Backspace or Delete
+///
+/// With a single-byte mapping, Alt prefixes ESC and Ctrl sends the other
+/// byte (so Ctrl+Backspace sends BS when Backspace sends DEL).
+fn erase_key(sequence: EraseSequence, modifiers: Modifiers) -> Vec<u8> {
+    let (byte, other) = match sequence {
+        EraseSequence::Delete => (0x7f, 0x08),
+        EraseSequence::BackSpace => (0x08, 0x7f),
+        EraseSequence::Escape => return tilde_key(3, modifiers),
+    };
+
+    if modifiers.contains(Modifiers::ALT) {
+        vec![0x1b, byte]
+    } else if modifiers.contains(Modifiers::CTRL) {
+        vec![other]
+    } else {
+        vec![byte]
+    }
+}
+
 fn cursor_key(key: u8, modifiers: Modifiers, app_cursor: bool) -> Vec<u8> {
     let modifier = modifier_param(modifiers);
 
@@ -429,6 +925,22 @@ fn cursor_key(key: u8, modifiers: Modifiers, app_cursor: bool) -> Vec<u8> {
     }
 }
 
+/// Generate the bytes for Home or End: `key` is the final byte of the cursor
+/// style sequence and `code` the VT220 tilde code
+fn home_end_key(
+    key: u8,
+    code: u8,
+    style: HomeEndStyle,
+    modifiers: Modifiers,
+    app_cursor: bool,
+) -> Vec<u8> {
+    match style {
+        HomeEndStyle::Xterm => cursor_key(key, modifiers, app_cursor),
+        HomeEndStyle::Application => cursor_key(key, modifiers, true),
+        HomeEndStyle::Vt220 => tilde_key(code, modifiers),
+    }
+}
+
 /// Generate escape sequence for tilde-style keys (PageUp, PageDown, Insert, Delete)
 /// Format: CSI code ~ or CSI code ; modifier ~ with modifiers
 fn tilde_key(code: u8, modifiers: Modifiers) -> Vec<u8> {
@@ -500,6 +1012,13 @@ pub enum Key {
     Insert,
     Delete,
     F(u8),
+    /// Enter on the numeric keypad, which sends `SS3 M` in application
+    /// keypad mode (DECKPAM) and `\r` otherwise
+    KeypadEnter,
+    /// Numeric keypad digit or operator (`0`-`9`, `*`, `+`, `,`, `-`, `.`,
+    /// `/`, `=`), sent as `SS3` sequences in application keypad mode and
+    /// as the character otherwise
+    Keypad(char),
 }
 
 bitflags::bitflags! {
@@ -534,6 +1053,45 @@ mod tests {
         assert_eq!(term.screen().get_cell(0, 12).unwrap().c, '!');
     }
 
+    #[test]
+    fn test_process_legacy_encoding() {
+        let mut term = Terminal::new(80, 24, ScreenConfig::default());
+        term.set_encoding(TerminalEncoding::Gbk);
+
+        // "中" in GBK, in two reads, then a colored "é" in the next byte pair
+        term.process(b"\xd6");
+        term.process(b"\xd0\x1b[31m\xa8\xa6");
+
+        assert_eq!(term.screen().get_cell(0, 0).unwrap().c, '中');
+        assert_eq!(term.screen().get_cell(0, 2).unwrap().c, 'é');
+
+        // An 8-bit CSI in Latin-1 moves the cursor instead of being printed
+        term.set_encoding(TerminalEncoding::Latin1);
+        term.process(b"\x9b2;1H\xe9");
+        assert_eq!(term.screen().get_cell(1, 0).unwrap().c, 'é');
+        assert_eq!(term.screen().cursor.col, 1);
+    }
+
+    #[test]
+    fn test_recording() {
+        let path = std::env::temp_dir().join(format!("cterm-term-rec-{}.txt", std::process::id()));
+        let mut term = Terminal::new(80, 24, ScreenConfig::default());
+
+        let recorder =
+            SessionRecorder::create(&path, crate::recording::RecordingMode::Text, true).unwrap();
+        term.start_recording(recorder);
+        assert_eq!(term.recording_path(), Some(path.as_path()));
+
+        // Device attribute replies aren't recorded as input
+        term.process(b"\x1b[31mred\x1b[0m\r\n\x1b[c");
+        assert_eq!(term.stop_recording().unwrap().unwrap(), path);
+        assert!(term.recording_path().is_none());
+        term.process(b"not recorded");
+
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "red\n");
+        std::fs::remove_file(&path).ok();
+    }
+
     #[test]
     fn test_terminal_resize() {
         let mut term = Terminal::new(80, 24, ScreenConfig::default());
@@ -546,6 +1104,20 @@ mod tests {
         assert_eq!(term.screen().get_cell(0, 0).unwrap().c, 'X');
     }
 
+    #[test]
+    fn test_pty_size() {
+        let mut term = Terminal::new(80, 24, ScreenConfig::default());
+        term.set_cell_size(9.0, 18.5);
+        let size = term.pty_size();
+        assert_eq!((size.cols, size.rows), (80, 24));
+        assert_eq!((size.pixel_width, size.pixel_height), (720, 444));
+
+        term.resize(100, 30);
+        let size = term.pty_size();
+        assert_eq!((size.pixel_width, size.pixel_height), (900, 555));
+        assert_eq!(term.screen().cell_width_hint(), 9.0);
+    }
+
     #[test]
     fn test_handle_key() {
         let term = Terminal::new(80, 24, ScreenConfig::default());
@@ -572,4 +1144,327 @@ mod tests {
         let up = term.handle_key(Key::Up, Modifiers::empty());
         assert_eq!(up, Some(b"\x1b[A".to_vec()));
     }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_wheel_history_keys() {
+        // No shell to send history keys to
+        let term = Terminal::new(80, 24, ScreenConfig::default());
+        assert_eq!(term.wheel_history_keys(true, 1), None);
+
+        // The shell is the only process, so it's at its prompt
+        let config = PtyConfig {
+            shell: Some("/bin/sh".to_string()),
+            args: vec!["-c".to_string(), "sleep 10".to_string()],
+            ..Default::default()
+        };
+        let mut term = Terminal::with_shell(80, 24, ScreenConfig::default(), &config).unwrap();
+        // Wait for the shell to become the terminal's foreground process
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while term.wheel_history_keys(true, 1).is_none() && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(
+            term.wheel_history_keys(true, 2),
+            Some(b"\x1b[A\x1b[A".to_vec())
+        );
+        term.process(b"\x1b[?1h");
+        assert_eq!(term.wheel_history_keys(false, 1), Some(b"\x1bOB".to_vec()));
+
+        // Full-screen programs get their own wheel handling
+        term.process(b"\x1b[?1049h");
+        assert_eq!(term.wheel_history_keys(true, 1), None);
+
+        let _ = term.send_signal(15);
+    }
+
+    #[test]
+    fn test_home_end_keys() {
+        let mut term = Terminal::new(80, 24, ScreenConfig::default());
+
+        // Normal cursor mode: CSI H / CSI F
+        assert_eq!(
+            term.handle_key(Key::Home, Modifiers::empty()),
+            Some(b"\x1b[H".to_vec())
+        );
+        assert_eq!(
+            term.handle_key(Key::End, Modifiers::empty()),
+            Some(b"\x1b[F".to_vec())
+        );
+        assert_eq!(
+            term.handle_key(Key::End, Modifiers::CTRL),
+            Some(b"\x1b[1;5F".to_vec())
+        );
+
+        // Application cursor mode (DECCKM): SS3 H / SS3 F
+        term.process(b"\x1b[?1h");
+        assert_eq!(
+            term.handle_key(Key::Home, Modifiers::empty()),
+            Some(b"\x1bOH".to_vec())
+        );
+        assert_eq!(
+            term.handle_key(Key::End, Modifiers::empty()),
+            Some(b"\x1bOF".to_vec())
+        );
+
+        term.set_home_end_style(HomeEndStyle::Vt220);
+        assert_eq!(
+            term.handle_key(Key::Home, Modifiers::empty()),
+            Some(b"\x1b[1~".to_vec())
+        );
+        assert_eq!(
+            term.handle_key(Key::End, Modifiers::SHIFT),
+            Some(b"\x1b[4;2~".to_vec())
+        );
+
+        term.process(b"\x1b[?1l");
+        term.set_home_end_style(HomeEndStyle::Application);
+        assert_eq!(
+            term.handle_key(Key::Home, Modifiers::empty()),
+            Some(b"\x1bOH".to_vec())
+        );
+    }
+
+    #[test]
+    fn test_keypad_keys() {
+        let mut term = Terminal::new(80, 24, ScreenConfig::default());
+
+        // Numeric keypad mode (DECKPNM): same as the main keys
+        assert_eq!(
+            term.handle_key(Key::KeypadEnter, Modifiers::empty()),
+            Some(b"\r".to_vec())
+        );
+        assert_eq!(
+            term.handle_key(Key::Keypad('7'), Modifiers::empty()),
+            Some(b"7".to_vec())
+        );
+        assert_eq!(
+            term.handle_key(Key::Keypad('+'), Modifiers::empty()),
+            Some(b"+".to_vec())
+        );
+
+        // Application keypad mode (DECKPAM): SS3 sequences
+        term.process(b"\x1b=");
+        assert_eq!(
+            term.handle_key(Key::KeypadEnter, Modifiers::empty()),
+            Some(b"\x1bOM".to_vec())
+        );
+        assert_eq!(
+            term.handle_key(Key::Enter, Modifiers::empty()),
+            Some(b"\r".to_vec())
+        );
+        let keys = [
+            ('0', "p"),
+            ('5', "u"),
+            ('9', "y"),
+            ('*', "j"),
+            ('+', "k"),
+            ('-', "m"),
+            ('.', "n"),
+            ('/', "o"),
+            ('=', "X"),
+        ];
+        for (c, final_byte) in keys {
+            assert_eq!(
+                term.handle_key(Key::Keypad(c), Modifiers::empty()),
+                Some(format!("\x1bO{}", final_byte).into_bytes()),
+                "keypad {}",
+                c
+            );
+        }
+        assert_eq!(
+            term.handle_key(Key::KeypadEnter, Modifiers::SHIFT),
+            Some(b"\x1bO2M".to_vec())
+        );
+
+        // Back to numeric mode (DECKPNM)
+        term.process(b"\x1b>");
+        assert_eq!(
+            term.handle_key(Key::Keypad('5'), Modifiers::empty()),
+            Some(b"5".to_vec())
+        );
+    }
+
+    #[test]
+    fn test_accept_inline_suggestion() {
+        let mut term = Terminal::new(80, 24, ScreenConfig::default());
+        term.screen_mut().set_inline_suggestions(true);
+        term.process(b"\x1b]133;A\x07$ \x1b]133;B\x07make test\r\n\x1b]133;C\x07");
+        term.process(b"\x1b]133;D;0\x07\x1b]133;A\x07$ \x1b]133;B\x07ma");
+
+        assert_eq!(
+            term.handle_key(Key::Right, Modifiers::empty()),
+            Some(b"ke test".to_vec())
+        );
+        assert_eq!(
+            term.handle_key(Key::End, Modifiers::empty()),
+            Some(b"ke test".to_vec())
+        );
+        // Modified keys keep their usual meaning
+        assert_eq!(
+            term.handle_key(Key::Right, Modifiers::CTRL),
+            Some(b"\x1b[1;5C".to_vec())
+        );
+    }
+
+    #[test]
+    fn test_erase_sequences() {
+        let mut term = Terminal::new(80, 24, ScreenConfig::default());
+
+        // Defaults: Backspace sends DEL, Delete sends CSI 3 ~
+        assert_eq!(
+            term.handle_key(Key::Backspace, Modifiers::empty()),
+            Some(vec![0x7f])
+        );
+        assert_eq!(
+            term.handle_key(Key::Backspace, Modifiers::CTRL),
+            Some(vec![0x08])
+        );
+        assert_eq!(
+            term.handle_key(Key::Backspace, Modifiers::ALT),
+            Some(b"\x1b\x7f".to_vec())
+        );
+        assert_eq!(
+            term.handle_key(Key::Delete, Modifiers::empty()),
+            Some(b"\x1b[3~".to_vec())
+        );
+
+        term.set_erase_sequences(EraseSequence::BackSpace, EraseSequence::Delete);
+        assert_eq!(
+            term.handle_key(Key::Backspace, Modifiers::empty()),
+            Some(vec![0x08])
+        );
+        assert_eq!(
+            term.handle_key(Key::Backspace, Modifiers::CTRL),
+            Some(vec![0x7f])
+        );
+        assert_eq!(
+            term.handle_key(Key::Delete, Modifiers::empty()),
+            Some(vec![0x7f])
+        );
+        assert_eq!(
+            term.handle_key(Key::Delete, Modifiers::ALT),
+            Some(b"\x1b\x7f".to_vec())
+        );
+    }
+
+    #[test]
+    fn test_local_echo() {
+        let mut term = Terminal::new(80, 24, ScreenConfig::default());
+        term.set_local_echo(LocalEchoMode::On, Duration::ZERO);
+        term.process(b"$ ");
+
+        // Nothing is shown until the shell has echoed something on the line
+        term.write(b"l").unwrap();
+        assert_eq!(term.screen().predicted_input(), None);
+        term.process(b"l");
+
+        term.write(b"s -").unwrap();
+        assert_eq!(term.screen().predicted_input().as_deref(), Some("s -"));
+        assert_eq!(term.screen().cursor_render_col(), 6);
+
+        // Backspace takes back the last prediction
+        term.write(b"\x7f").unwrap();
+        assert_eq!(term.screen().predicted_input().as_deref(), Some("s "));
+
+        // The echo replaces the predictions
+        term.process(b"s");
+        assert_eq!(term.screen().predicted_input().as_deref(), Some(" "));
+        term.process(b" -\x08 \x08");
+        assert_eq!(term.screen().predicted_input(), None);
+        assert_eq!(term.screen().cursor_render_col(), 5);
+
+        // Output that doesn't match drops them
+        term.write(b"x").unwrap();
+        assert_eq!(term.screen().predicted_input().as_deref(), Some("x"));
+        term.process(b"\x07y");
+        assert_eq!(term.screen().predicted_input(), None);
+
+        // After Enter the next line has to echo again (password prompts)
+        term.write(b"\r").unwrap();
+        term.process(b"\r\nPassword: ");
+        term.write(b"secret").unwrap();
+        assert_eq!(term.screen().predicted_input(), None);
+    }
+
+    #[test]
+    fn test_scroll_to_bottom_on() {
+        let mut term = Terminal::new(80, 5, ScreenConfig::default());
+        term.process(&b"line\r\n".repeat(20));
+        let scrolled_back = |term: &mut Terminal| {
+            term.scroll_viewport_up(3);
+            term.screen().scroll_offset
+        };
+
+        // By default only input snaps back
+        assert_eq!(scrolled_back(&mut term), 3);
+        term.process(b"output\x07\x1b]133;A\x07");
+        assert!(!term.is_at_bottom());
+        term.write(b"x").unwrap();
+        assert!(term.is_at_bottom());
+
+        term.set_scroll_to_bottom_on(&[ScrollTrigger::Prompt]);
+        scrolled_back(&mut term);
+        term.write(b"x").unwrap();
+        term.process(b"output\x07");
+        assert!(!term.is_at_bottom());
+        term.process(b"\x1b]133;A\x07$ ");
+        assert!(term.is_at_bottom());
+
+        term.set_scroll_to_bottom_on(&[ScrollTrigger::Bell]);
+        scrolled_back(&mut term);
+        term.process(b"\x07");
+        assert!(term.is_at_bottom());
+
+        term.set_scroll_to_bottom_on(&[ScrollTrigger::Output]);
+        scrolled_back(&mut term);
+        term.process(b"more");
+        assert!(term.is_at_bottom());
+    }
+
+    #[test]
+    fn test_clear_selection_on() {
+        let mut term = Terminal::new(80, 5, ScreenConfig::default());
+        term.process(b"hello\r\nworld");
+        let select = |term: &mut Terminal| {
+            term.screen_mut()
+                .start_selection(0, 0, crate::screen::SelectionMode::Char);
+            term.screen_mut().extend_selection(0, 4);
+        };
+
+        // By default output leaves the selection and a keypress clears it
+        select(&mut term);
+        term.process(b"!");
+        assert!(term.screen().selection.is_some());
+        assert!(term.clear_selection_for(SelectionClearTrigger::Keypress));
+        assert!(term.screen().selection.is_none());
+
+        term.set_clear_selection_on(SelectionClearTrigger::Output);
+        select(&mut term);
+        assert!(!term.clear_selection_for(SelectionClearTrigger::Keypress));
+        term.process(b"!");
+        assert!(term.screen().selection.is_none());
+
+        term.set_clear_selection_on(SelectionClearTrigger::Never);
+        select(&mut term);
+        term.process(b"!");
+        assert!(!term.clear_selection_for(SelectionClearTrigger::Keypress));
+        assert!(term.screen().selection.is_some());
+    }
+
+    #[test]
+    fn test_local_echo_auto() {
+        let mut term = Terminal::new(80, 24, ScreenConfig::default());
+        term.set_local_echo(LocalEchoMode::Auto, Duration::ZERO);
+        assert!(!term.screen().local_echo());
+
+        // Enabled once the echo latency has been measured above the threshold
+        term.write(b"a").unwrap();
+        term.process(b"a");
+        assert!(term.echo_latency().is_some());
+        assert!(term.screen().local_echo());
+
+        term.set_local_echo(LocalEchoMode::Auto, Duration::from_secs(3600));
+        assert!(!term.screen().local_echo());
+    }
 }
@@ -1,8 +1,9 @@
 //! Application setup and management
 
+use gtk4::prelude::*;
 use gtk4::{gdk, Application, CssProvider};
 
-use cterm_app::config::{load_config, Config};
+use cterm_app::config::{is_first_run, load_config, Config};
 use cterm_ui::theme::Theme;
 
 use crate::window::CtermWindow;
@@ -20,14 +21,83 @@ pub fn build_ui(app: &Application) {
         Config::default()
     });
 
+    // On first run, pick a theme, font and shell before the first window opens
+    if is_first_run() {
+        let app_for_done = app.clone();
+        crate::dialogs::show_setup_assistant(app, &config, move |config| {
+            open_main_window(&app_for_done, config);
+        });
+        return;
+    }
+    let show_crash_report = config.general.show_crash_report;
+    open_main_window(app, config);
+    show_pending_crash_report(app, show_crash_report);
+}
+
+/// Show the crash report written by the watchdog, if there is one
+#[cfg(unix)]
+fn show_pending_crash_report(app: &Application, show_dialog: bool) {
+    let Some(path) = cterm_app::take_pending_crash_report() else {
+        return;
+    };
+    log::warn!("Previous process crashed, report at {}", path.display());
+    if show_dialog {
+        if let Some(window) = app.active_window() {
+            crate::dialogs::show_crash_report(&window, &path);
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn show_pending_crash_report(_app: &Application, _show_dialog: bool) {}
+
+/// Open the first window, with the command line options applied to `config`
+fn open_main_window(app: &Application, mut config: Config) {
+    // Run the -e command in the main window instead of the shell
+    let args = crate::get_args();
+    if args.no_shell {
+        config.general.execute_without_shell = true;
+    }
+    if args.raw {
+        config.general.raw_pty = true;
+    }
+    if let Some(ref class) = args.class {
+        config.general.app_id = Some(class.clone());
+    }
+    if let Some(ref command) = args.command {
+        if let Err(e) = config.general.set_execute_command(command) {
+            log::error!("Ignoring -e: {}", e);
+        }
+    }
+    cterm_app::clipboard_history::configure(&config.general);
+
     // Load theme
     let theme = get_theme(&config);
 
     // Apply CSS styling
     apply_css(&theme);
 
-    // Create the main window
-    let window = CtermWindow::new(app, &config, &theme);
+    // Open the workspace's windows instead of the main window
+    if let Some(ref path) = args.workspace {
+        match cterm_app::workspace::load_workspace(path) {
+            Ok(workspace) => {
+                crate::window::open_workspace(app, &config, &theme, &workspace);
+                return;
+            }
+            Err(e) => log::error!("Ignoring --workspace: {}", e),
+        }
+    }
+
+    // Create the main window, its first tab from --template if given
+    let template = args.template.as_deref().and_then(|name| {
+        cterm_app::config::load_template(name)
+            .map_err(|e| log::error!("Ignoring --template: {}", e))
+            .ok()
+    });
+    let window = match template {
+        Some(template) => CtermWindow::from_template(app, &config, &theme, &template),
+        None => CtermWindow::new(app, &config, &theme, None),
+    };
     window.present();
 }
 
@@ -75,6 +145,10 @@ fn apply_css(_theme: &Theme) {
             font-weight: bold;
         }
 
+        .tab-bar button.pinned-tab {
+            border-bottom: 2px solid alpha(currentColor, 0.4);
+        }
+
         .tab-close-button {
             padding: 0px 2px;
             min-width: 14px;
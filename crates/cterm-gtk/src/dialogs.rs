@@ -11,9 +11,12 @@ use gtk4::{
 };
 
 use cterm_app::config::{
-    config_dir, Config, CursorStyleConfig, NewTabPosition, TabBarPosition, TabBarVisibility,
+    config_dir, Config, CursorStyleConfig, GeneralConfig, NewTabPosition, TabBarPosition,
+    TabBarVisibility, TabCloseButton,
 };
+use cterm_app::send_bytes::parse_send_bytes;
 use cterm_app::{git_sync, PullResult};
+use cterm_ui::theme::Theme;
 
 /// Type alias for the on_save callback to avoid clippy::type_complexity warning
 type SaveCallback = Rc<RefCell<Option<Box<dyn Fn(Config)>>>>;
@@ -87,9 +90,16 @@ where
 }
 
 /// Show the "Set Color" dialog
-pub fn show_set_color_dialog<F>(parent: &impl IsA<Window>, callback: F)
-where
-    F: Fn(Option<String>) + 'static,
+///
+/// The callback receives the tab color and the title text color; "Clear"
+/// passes None for both.
+pub fn show_set_color_dialog<F>(
+    parent: &impl IsA<Window>,
+    current_color: Option<&str>,
+    current_title_color: Option<&str>,
+    callback: F,
+) where
+    F: Fn(Option<String>, Option<String>) + 'static,
 {
     let dialog = Dialog::builder()
         .title("Set Tab Color")
@@ -206,15 +216,50 @@ where
     }
     content.append(&colors_box);
 
+    if let Some(color) = current_color {
+        hex_entry.set_text(color);
+    }
+
+    // Title text color, so the title stays readable on any tab color
+    let title_box = GtkBox::new(Orientation::Horizontal, 12);
+    title_box.set_margin_top(8);
+    let title_check = gtk4::CheckButton::with_label("Title color:");
+    title_box.append(&title_check);
+    let title_color_button = gtk4::ColorButton::new();
+    title_color_button.set_tooltip_text(Some("Choose the tab title text color"));
+    title_box.append(&title_color_button);
+    content.append(&title_box);
+
+    match current_title_color.and_then(parse_hex_to_rgba) {
+        Some(rgba) => {
+            title_color_button.set_rgba(&rgba);
+            title_check.set_active(true);
+        }
+        None => title_color_button.set_sensitive(false),
+    }
+    let title_color_button_clone = title_color_button.clone();
+    title_check.connect_toggled(move |check| {
+        title_color_button_clone.set_sensitive(check.is_active());
+    });
+
     let selected_for_response = Rc::clone(&selected_color);
     dialog.connect_response(move |dialog, response| {
         match response {
             ResponseType::Ok => {
                 let color = selected_for_response.borrow().clone();
-                callback(color);
+                let title_color = title_check.is_active().then(|| {
+                    let rgba = title_color_button.rgba();
+                    format!(
+                        "#{:02X}{:02X}{:02X}",
+                        (rgba.red() * 255.0) as u8,
+                        (rgba.green() * 255.0) as u8,
+                        (rgba.blue() * 255.0) as u8
+                    )
+                });
+                callback(color, title_color);
             }
             ResponseType::Reject => {
-                callback(None);
+                callback(None, None);
             }
             _ => {}
         }
@@ -239,9 +284,14 @@ fn parse_hex_to_rgba(hex: &str) -> Option<gtk4::gdk::RGBA> {
 }
 
 /// Show the "Find" dialog
-pub fn show_find_dialog<F>(parent: &impl IsA<Window>, callback: F)
+///
+/// `callback` runs for each "Find Next" with the query and whether it's case
+/// sensitive, a regex and limited to the scrollback; `on_close` runs when the
+/// dialog is dismissed.
+pub fn show_find_dialog<F, C>(parent: &impl IsA<Window>, callback: F, on_close: C)
 where
-    F: Fn(String, bool, bool) + 'static,
+    F: Fn(String, bool, bool, bool) + 'static,
+    C: Fn() + 'static,
 {
     let dialog = Dialog::builder()
         .title("Find in Terminal")
@@ -277,6 +327,11 @@ where
     let regex_check = gtk4::CheckButton::with_label("Regular expression");
     grid.attach(&regex_check, 2, 1, 1, 1);
 
+    // Skip the visible screen, so a repainting full-screen app doesn't move
+    // the matches
+    let scrollback_check = gtk4::CheckButton::with_label("Scrollback only");
+    grid.attach(&scrollback_check, 1, 2, 1, 1);
+
     content.append(&grid);
 
     let entry_clone = search_entry.clone();
@@ -288,8 +343,10 @@ where
             let text = entry_clone.text().to_string();
             let case_sensitive = case_clone.is_active();
             let regex = regex_clone.is_active();
-            callback(text, case_sensitive, regex);
+            let scrollback_only = scrollback_check.is_active();
+            callback(text, case_sensitive, regex, scrollback_only);
         } else {
+            on_close();
             dialog.close();
         }
     });
@@ -297,6 +354,199 @@ where
     dialog.present();
 }
 
+/// Show the "Filter Scrollback" dialog
+///
+/// `callback` runs with the query and its options when "Filter" is clicked.
+pub fn show_filter_dialog<F>(parent: &impl IsA<Window>, callback: F)
+where
+    F: Fn(String, bool, bool) + 'static,
+{
+    let dialog = Dialog::builder()
+        .title("Filter Scrollback")
+        .transient_for(parent)
+        .modal(true)
+        .build();
+
+    dialog.add_button("Cancel", ResponseType::Cancel);
+    dialog.add_button("Filter", ResponseType::Ok);
+    dialog.set_default_response(ResponseType::Ok);
+
+    let content = dialog.content_area();
+    content.set_spacing(12);
+    content.set_margin_top(12);
+    content.set_margin_bottom(12);
+    content.set_margin_start(12);
+    content.set_margin_end(12);
+
+    let grid = Grid::new();
+    grid.set_row_spacing(8);
+    grid.set_column_spacing(12);
+
+    let pattern_label = Label::new(Some("Show lines matching:"));
+    pattern_label.set_halign(Align::End);
+    grid.attach(&pattern_label, 0, 0, 1, 1);
+
+    let pattern_entry = Entry::new();
+    pattern_entry.set_hexpand(true);
+    pattern_entry.set_activates_default(true);
+    grid.attach(&pattern_entry, 1, 0, 2, 1);
+
+    let case_check = gtk4::CheckButton::with_label("Case sensitive");
+    grid.attach(&case_check, 1, 1, 1, 1);
+
+    let regex_check = gtk4::CheckButton::with_label("Regular expression");
+    regex_check.set_active(true);
+    grid.attach(&regex_check, 2, 1, 1, 1);
+
+    content.append(&grid);
+
+    dialog.connect_response(move |dialog, response| {
+        if response == ResponseType::Ok {
+            callback(
+                pattern_entry.text().to_string(),
+                case_check.is_active(),
+                regex_check.is_active(),
+            );
+        }
+        dialog.close();
+    });
+
+    dialog.present();
+}
+
+/// Show the "Send Bytes" debug dialog
+///
+/// `callback` runs with the decoded bytes; invalid input keeps the dialog
+/// open with the error shown.
+pub fn show_send_bytes_dialog<F>(parent: &impl IsA<Window>, callback: F)
+where
+    F: Fn(Vec<u8>) + 'static,
+{
+    let dialog = Dialog::builder()
+        .title("Send Bytes")
+        .transient_for(parent)
+        .modal(true)
+        .build();
+
+    dialog.add_button("Cancel", ResponseType::Cancel);
+    dialog.add_button("Send", ResponseType::Ok);
+    dialog.set_default_response(ResponseType::Ok);
+
+    let content = dialog.content_area();
+    content.set_spacing(12);
+    content.set_margin_top(12);
+    content.set_margin_bottom(12);
+    content.set_margin_start(12);
+    content.set_margin_end(12);
+
+    let label = Label::new(Some(
+        "Hex (1b 5b 41) or text with escapes (\\e[A, \\x07, \\t, \\n):",
+    ));
+    label.set_halign(Align::Start);
+    content.append(&label);
+
+    let entry = Entry::new();
+    entry.set_hexpand(true);
+    entry.set_activates_default(true);
+    content.append(&entry);
+
+    let error_label = Label::new(None);
+    error_label.set_halign(Align::Start);
+    error_label.add_css_class("error");
+    content.append(&error_label);
+
+    dialog.connect_response(move |dialog, response| {
+        if response == ResponseType::Ok {
+            match parse_send_bytes(&entry.text()) {
+                Ok(bytes) => callback(bytes),
+                Err(e) => {
+                    error_label.set_text(&e.to_string());
+                    return;
+                }
+            }
+        }
+        dialog.close();
+    });
+
+    dialog.present();
+}
+
+/// Show the clipboard history picker
+///
+/// `entries` are the remembered copies, most recent first; `paste` runs with
+/// the chosen one.
+pub fn show_clipboard_history<F>(parent: &impl IsA<Window>, entries: Vec<String>, paste: F)
+where
+    F: Fn(String) + 'static,
+{
+    let dialog = Dialog::builder()
+        .title("Paste from History")
+        .transient_for(parent)
+        .modal(true)
+        .default_width(500)
+        .default_height(350)
+        .build();
+
+    dialog.add_button("Cancel", ResponseType::Cancel);
+    dialog.add_button("Paste", ResponseType::Ok);
+    dialog.set_default_response(ResponseType::Ok);
+
+    let content = dialog.content_area();
+    content.set_margin_top(12);
+    content.set_margin_bottom(12);
+    content.set_margin_start(12);
+    content.set_margin_end(12);
+
+    if entries.is_empty() {
+        content.append(&Label::new(Some("Nothing has been copied yet")));
+    }
+
+    let list = gtk4::ListBox::new();
+    list.set_selection_mode(gtk4::SelectionMode::Single);
+    for entry in &entries {
+        let label = Label::new(Some(&cterm_app::clipboard_history::preview(entry, 80)));
+        label.set_halign(Align::Start);
+        label.set_margin_top(4);
+        label.set_margin_bottom(4);
+        label.set_margin_start(8);
+        label.set_margin_end(8);
+        list.append(&label);
+    }
+    if let Some(first) = list.row_at_index(0) {
+        list.select_row(Some(&first));
+    }
+
+    let scroll = ScrolledWindow::new();
+    scroll.set_vexpand(true);
+    scroll.set_child(Some(&list));
+    content.append(&scroll);
+
+    let entries = Rc::new(entries);
+    let paste = Rc::new(paste);
+    {
+        let entries = Rc::clone(&entries);
+        let paste = Rc::clone(&paste);
+        let dialog = dialog.clone();
+        list.connect_row_activated(move |_, row| {
+            if let Some(text) = entries.get(row.index() as usize) {
+                paste(text.clone());
+            }
+            dialog.close();
+        });
+    }
+    dialog.connect_response(move |dialog, response| {
+        if response == ResponseType::Ok {
+            let selected = list.selected_row().map(|row| row.index() as usize);
+            if let Some(text) = selected.and_then(|index| entries.get(index)) {
+                paste(text.clone());
+            }
+        }
+        dialog.close();
+    });
+
+    dialog.present();
+}
+
 /// Show the About dialog
 pub fn show_about_dialog(parent: &impl IsA<Window>) {
     let about = gtk4::AboutDialog::builder()
@@ -314,25 +564,95 @@ pub fn show_about_dialog(parent: &impl IsA<Window>) {
     about.present();
 }
 
+/// Show the first-run setup assistant
+///
+/// Offers the appearance settings of the Preferences dialog plus the shell,
+/// then runs `on_done` with the chosen config. The config is saved even when
+/// skipped (with the defaults), so the assistant is only shown once.
+pub fn show_setup_assistant(
+    app: &gtk4::Application,
+    config: &Config,
+    on_done: impl FnOnce(Config) + 'static,
+) {
+    let dialog = Dialog::builder()
+        .title("Welcome to cterm")
+        .application(app)
+        .modal(true)
+        .default_width(500)
+        .build();
+
+    dialog.add_button("Skip", ResponseType::Cancel);
+    dialog.add_button("Continue", ResponseType::Ok);
+    dialog.set_default_response(ResponseType::Ok);
+
+    let content = dialog.content_area();
+    content.set_spacing(0);
+
+    let intro = Label::new(Some(
+        "Pick a theme, font and shell to get started. Everything can be changed later in Preferences.",
+    ));
+    intro.set_wrap(true);
+    intro.set_margin_top(12);
+    intro.set_margin_start(12);
+    intro.set_margin_end(12);
+    content.append(&intro);
+
+    let (appearance_page, appearance) = create_appearance_preferences(config);
+    content.append(&appearance_page);
+
+    // Shell, from /etc/shells
+    let shell_box = GtkBox::new(Orientation::Horizontal, 12);
+    shell_box.set_margin_bottom(12);
+    shell_box.set_margin_start(12);
+    shell_box.set_margin_end(12);
+    shell_box.append(&Label::new(Some("Shell:")));
+    let shell_combo = ComboBoxText::new();
+    shell_combo.append(Some(""), "System default");
+    for shell in cterm_app::config::available_shells() {
+        shell_combo.append(Some(&shell), &shell);
+    }
+    shell_combo.set_active_id(Some(config.general.default_shell.as_deref().unwrap_or("")));
+    shell_combo.set_hexpand(true);
+    shell_box.append(&shell_combo);
+    content.append(&shell_box);
+
+    let base_config = config.clone();
+    let on_done = RefCell::new(Some(on_done));
+    dialog.connect_response(move |dialog, response| {
+        let mut config = base_config.clone();
+        if response == ResponseType::Ok {
+            appearance.apply(&mut config);
+            config.general.default_shell = shell_combo
+                .active_id()
+                .filter(|id| !id.is_empty())
+                .map(|id| id.to_string());
+        }
+        if let Err(e) = cterm_app::config::save_config(&config) {
+            log::error!("Failed to save config: {}", e);
+        }
+        if let Some(on_done) = on_done.borrow_mut().take() {
+            on_done(config);
+        }
+        dialog.close();
+    });
+
+    dialog.present();
+}
+
 /// Widgets for collecting preference values
 struct PreferencesWidgets {
     // General
     scrollback_spin: SpinButton,
     confirm_switch: Switch,
+    confirm_quit_switch: Switch,
     copy_select_switch: Switch,
-    // Appearance
-    theme_combo: ComboBoxText,
-    font_entry: Entry,
-    size_spin: SpinButton,
-    cursor_combo: ComboBoxText,
-    blink_switch: Switch,
-    opacity_scale: gtk4::Scale,
-    bold_switch: Switch,
+    appearance: AppearanceWidgets,
     // Tabs
     show_combo: ComboBoxText,
     position_combo: ComboBoxText,
     new_combo: ComboBoxText,
-    close_switch: Switch,
+    close_combo: ComboBoxText,
+    middle_click_switch: Switch,
     // Shortcuts
     shortcut_entries: Vec<(String, Entry)>,
     // Git Sync
@@ -471,22 +791,10 @@ impl PreferencesWidgets {
         // General
         config.general.scrollback_lines = self.scrollback_spin.value() as usize;
         config.general.confirm_close_with_running = self.confirm_switch.is_active();
+        config.general.confirm_quit = self.confirm_quit_switch.is_active();
         config.general.copy_on_select = self.copy_select_switch.is_active();
 
-        // Appearance
-        if let Some(theme_id) = self.theme_combo.active_id() {
-            config.appearance.theme = theme_id.to_string();
-        }
-        config.appearance.font.family = self.font_entry.text().to_string();
-        config.appearance.font.size = self.size_spin.value();
-        config.appearance.cursor_style = match self.cursor_combo.active_id().as_deref() {
-            Some("underline") => CursorStyleConfig::Underline,
-            Some("bar") => CursorStyleConfig::Bar,
-            _ => CursorStyleConfig::Block,
-        };
-        config.appearance.cursor_blink = self.blink_switch.is_active();
-        config.appearance.opacity = self.opacity_scale.value();
-        config.appearance.bold_is_bright = self.bold_switch.is_active();
+        self.appearance.apply(&mut config);
 
         // Tabs
         config.tabs.show_tab_bar = match self.show_combo.active_id().as_deref() {
@@ -502,7 +810,12 @@ impl PreferencesWidgets {
             Some("after_current") => NewTabPosition::AfterCurrent,
             _ => NewTabPosition::End,
         };
-        config.tabs.show_close_button = self.close_switch.is_active();
+        config.tabs.close_button = match self.close_combo.active_id().as_deref() {
+            Some("hover") => TabCloseButton::Hover,
+            Some("never") => TabCloseButton::Never,
+            _ => TabCloseButton::Always,
+        };
+        config.tabs.middle_click_closes_tab = self.middle_click_switch.is_active();
 
         // Shortcuts
         for (name, entry) in &self.shortcut_entries {
@@ -557,25 +870,16 @@ pub fn show_preferences_dialog(
     content.append(&notebook);
 
     // General tab
-    let (general_page, scrollback_spin, confirm_switch, copy_select_switch) =
+    let (general_page, scrollback_spin, confirm_switch, confirm_quit_switch, copy_select_switch) =
         create_general_preferences(config);
     notebook.append_page(&general_page, Some(&Label::new(Some("General"))));
 
     // Appearance tab
-    let (
-        appearance_page,
-        theme_combo,
-        font_entry,
-        size_spin,
-        cursor_combo,
-        blink_switch,
-        opacity_scale,
-        bold_switch,
-    ) = create_appearance_preferences(config);
+    let (appearance_page, appearance) = create_appearance_preferences(config);
     notebook.append_page(&appearance_page, Some(&Label::new(Some("Appearance"))));
 
     // Tabs tab
-    let (tabs_page, show_combo, position_combo, new_combo, close_switch) =
+    let (tabs_page, show_combo, position_combo, new_combo, close_combo, middle_click_switch) =
         create_tabs_preferences(config);
     notebook.append_page(&tabs_page, Some(&Label::new(Some("Tabs"))));
 
@@ -605,18 +909,14 @@ pub fn show_preferences_dialog(
     let widgets = Rc::new(PreferencesWidgets {
         scrollback_spin,
         confirm_switch,
+        confirm_quit_switch,
         copy_select_switch,
-        theme_combo,
-        font_entry,
-        size_spin,
-        cursor_combo,
-        blink_switch,
-        opacity_scale,
-        bold_switch,
+        appearance,
         show_combo,
         position_combo,
         new_combo,
-        close_switch,
+        close_combo,
+        middle_click_switch,
         shortcut_entries,
         git_remote_entry,
         git_status_label,
@@ -698,7 +998,7 @@ pub fn show_preferences_dialog(
     dialog.present();
 }
 
-fn create_general_preferences(config: &Config) -> (GtkBox, SpinButton, Switch, Switch) {
+fn create_general_preferences(config: &Config) -> (GtkBox, SpinButton, Switch, Switch, Switch) {
     let page = GtkBox::new(Orientation::Vertical, 12);
     page.set_margin_top(12);
     page.set_margin_bottom(12);
@@ -728,32 +1028,68 @@ fn create_general_preferences(config: &Config) -> (GtkBox, SpinButton, Switch, S
     confirm_switch.set_halign(Align::Start);
     grid.attach(&confirm_switch, 1, 1, 1, 1);
 
+    // Confirm quit
+    let confirm_quit_label = Label::new(Some("Confirm before quitting:"));
+    confirm_quit_label.set_halign(Align::End);
+    grid.attach(&confirm_quit_label, 0, 2, 1, 1);
+
+    let confirm_quit_switch = Switch::new();
+    confirm_quit_switch.set_active(config.general.confirm_quit);
+    confirm_quit_switch.set_halign(Align::Start);
+    grid.attach(&confirm_quit_switch, 1, 2, 1, 1);
+
     // Copy on select
     let copy_select_label = Label::new(Some("Copy on select:"));
     copy_select_label.set_halign(Align::End);
-    grid.attach(&copy_select_label, 0, 2, 1, 1);
+    grid.attach(&copy_select_label, 0, 3, 1, 1);
 
     let copy_select_switch = Switch::new();
     copy_select_switch.set_active(config.general.copy_on_select);
     copy_select_switch.set_halign(Align::Start);
-    grid.attach(&copy_select_switch, 1, 2, 1, 1);
+    grid.attach(&copy_select_switch, 1, 3, 1, 1);
 
     page.append(&grid);
-    (page, scrollback_spin, confirm_switch, copy_select_switch)
+    (
+        page,
+        scrollback_spin,
+        confirm_switch,
+        confirm_quit_switch,
+        copy_select_switch,
+    )
 }
 
-fn create_appearance_preferences(
-    config: &Config,
-) -> (
-    GtkBox,
-    ComboBoxText,
-    Entry,
-    SpinButton,
-    ComboBoxText,
-    Switch,
-    gtk4::Scale,
-    Switch,
-) {
+/// Widgets of the Appearance preferences page
+pub struct AppearanceWidgets {
+    theme_combo: ComboBoxText,
+    font_entry: Entry,
+    size_spin: SpinButton,
+    cursor_combo: ComboBoxText,
+    blink_switch: Switch,
+    opacity_scale: gtk4::Scale,
+    bold_switch: Switch,
+}
+
+impl AppearanceWidgets {
+    /// Store the values of the widgets in `config`
+    pub fn apply(&self, config: &mut Config) {
+        if let Some(theme_id) = self.theme_combo.active_id() {
+            config.appearance.theme = theme_id.to_string();
+        }
+        config.appearance.font.family = self.font_entry.text().to_string();
+        config.appearance.font.size = self.size_spin.value();
+        config.appearance.cursor_style = match self.cursor_combo.active_id().as_deref() {
+            Some("underline") => CursorStyleConfig::Underline,
+            Some("bar") => CursorStyleConfig::Bar,
+            _ => CursorStyleConfig::Block,
+        };
+        config.appearance.cursor_blink = self.blink_switch.is_active();
+        config.appearance.opacity = self.opacity_scale.value();
+        config.appearance.bold_is_bright = self.bold_switch.is_active();
+    }
+}
+
+/// Build the Appearance preferences page (also used by the setup assistant)
+pub fn create_appearance_preferences(config: &Config) -> (GtkBox, AppearanceWidgets) {
     let page = GtkBox::new(Orientation::Vertical, 12);
     page.set_margin_top(12);
     page.set_margin_bottom(12);
@@ -769,12 +1105,11 @@ fn create_appearance_preferences(
     theme_label.set_halign(Align::End);
     grid.attach(&theme_label, 0, 0, 1, 1);
 
+    // Themes are looked up by name, so the names are the ids
     let theme_combo = ComboBoxText::new();
-    theme_combo.append(Some("dark"), "Default Dark");
-    theme_combo.append(Some("light"), "Default Light");
-    theme_combo.append(Some("tokyo_night"), "Tokyo Night");
-    theme_combo.append(Some("dracula"), "Dracula");
-    theme_combo.append(Some("nord"), "Nord");
+    for theme in Theme::builtin_themes() {
+        theme_combo.append(Some(&theme.name), &theme.name);
+    }
     theme_combo.set_active_id(Some(&config.appearance.theme));
     grid.attach(&theme_combo, 1, 0, 1, 1);
 
@@ -847,19 +1182,28 @@ fn create_appearance_preferences(
     page.append(&grid);
     (
         page,
-        theme_combo,
-        font_entry,
-        size_spin,
-        cursor_combo,
-        blink_switch,
-        opacity_scale,
-        bold_switch,
+        AppearanceWidgets {
+            theme_combo,
+            font_entry,
+            size_spin,
+            cursor_combo,
+            blink_switch,
+            opacity_scale,
+            bold_switch,
+        },
     )
 }
 
 fn create_tabs_preferences(
     config: &Config,
-) -> (GtkBox, ComboBoxText, ComboBoxText, ComboBoxText, Switch) {
+) -> (
+    GtkBox,
+    ComboBoxText,
+    ComboBoxText,
+    ComboBoxText,
+    ComboBoxText,
+    Switch,
+) {
     let page = GtkBox::new(Orientation::Vertical, 12);
     page.set_margin_top(12);
     page.set_margin_bottom(12);
@@ -917,18 +1261,42 @@ fn create_tabs_preferences(
     new_combo.set_active_id(Some(new_id));
     grid.attach(&new_combo, 1, 2, 1, 1);
 
-    // Show close button
-    let close_label = Label::new(Some("Show close button:"));
+    // Close buttons
+    let close_label = Label::new(Some("Close buttons:"));
     close_label.set_halign(Align::End);
     grid.attach(&close_label, 0, 3, 1, 1);
 
-    let close_switch = Switch::new();
-    close_switch.set_active(config.tabs.show_close_button);
-    close_switch.set_halign(Align::Start);
-    grid.attach(&close_switch, 1, 3, 1, 1);
+    let close_combo = ComboBoxText::new();
+    close_combo.append(Some("always"), "Always");
+    close_combo.append(Some("hover"), "On hover");
+    close_combo.append(Some("never"), "Never");
+    let close_id = match config.tabs.close_button {
+        TabCloseButton::Always => "always",
+        TabCloseButton::Hover => "hover",
+        TabCloseButton::Never => "never",
+    };
+    close_combo.set_active_id(Some(close_id));
+    grid.attach(&close_combo, 1, 3, 1, 1);
+
+    // Middle-click closes tabs
+    let middle_click_label = Label::new(Some("Middle-click closes tabs:"));
+    middle_click_label.set_halign(Align::End);
+    grid.attach(&middle_click_label, 0, 4, 1, 1);
+
+    let middle_click_switch = Switch::new();
+    middle_click_switch.set_active(config.tabs.middle_click_closes_tab);
+    middle_click_switch.set_halign(Align::Start);
+    grid.attach(&middle_click_switch, 1, 4, 1, 1);
 
     page.append(&grid);
-    (page, show_combo, position_combo, new_combo, close_switch)
+    (
+        page,
+        show_combo,
+        position_combo,
+        new_combo,
+        close_combo,
+        middle_click_switch,
+    )
 }
 
 fn create_shortcuts_preferences(config: &Config) -> (GtkBox, Vec<(String, Entry)>) {
@@ -1357,6 +1725,192 @@ pub fn show_close_confirmation_dialog<F>(
     dialog.present();
 }
 
+/// Show a confirmation dialog before quitting the application
+///
+/// `details` describes what is open, e.g. from `QuitSummary::details`.
+pub fn show_quit_confirmation_dialog<F>(parent: &impl IsA<Window>, details: &str, callback: F)
+where
+    F: Fn(bool) + 'static,
+{
+    let dialog = Dialog::builder()
+        .title("Quit cterm?")
+        .transient_for(parent)
+        .modal(true)
+        .build();
+
+    dialog.add_button("Cancel", ResponseType::Cancel);
+    dialog.add_button("Quit", ResponseType::Ok);
+
+    let content = dialog.content_area();
+    content.set_spacing(12);
+    content.set_margin_top(12);
+    content.set_margin_bottom(12);
+    content.set_margin_start(12);
+    content.set_margin_end(12);
+
+    let message_label = Label::new(Some(details));
+    message_label.set_halign(Align::Start);
+    message_label.set_wrap(true);
+    content.append(&message_label);
+
+    dialog.connect_response(move |dialog, response| {
+        callback(response == ResponseType::Ok);
+        dialog.close();
+    });
+
+    dialog.present();
+}
+
+/// Show the crash report written by the watchdog after the previous process
+/// crashed
+#[cfg(unix)]
+pub fn show_crash_report(parent: &impl IsA<Window>, path: &std::path::Path) {
+    const OPEN_FOLDER: u16 = 1;
+    const COPY_PATH: u16 = 2;
+
+    let dialog = Dialog::builder()
+        .title("cterm crashed")
+        .transient_for(parent)
+        .modal(true)
+        .build();
+
+    dialog.add_button("Open Folder", ResponseType::Other(OPEN_FOLDER));
+    dialog.add_button("Copy Path", ResponseType::Other(COPY_PATH));
+    dialog.add_button("Close", ResponseType::Close);
+
+    let content = dialog.content_area();
+    content.set_spacing(12);
+    content.set_margin_top(12);
+    content.set_margin_bottom(12);
+    content.set_margin_start(12);
+    content.set_margin_end(12);
+
+    let message_label = Label::new(Some(&format!(
+        "A crash report was saved to:\n\n{}\n\nIt contains the version, platform, open tabs \
+         and recent log lines. Please review it before attaching it to a bug report.",
+        path.display()
+    )));
+    message_label.set_halign(Align::Start);
+    message_label.set_wrap(true);
+    message_label.set_selectable(true);
+    content.append(&message_label);
+
+    let path = path.to_path_buf();
+    dialog.connect_response(move |dialog, response| match response {
+        ResponseType::Other(OPEN_FOLDER) => {
+            let folder = path.parent().unwrap_or(&path);
+            let uri = gio::File::for_path(folder).uri();
+            if let Err(e) =
+                gio::AppInfo::launch_default_for_uri(&uri, None::<&gio::AppLaunchContext>)
+            {
+                log::error!("Failed to open {}: {}", folder.display(), e);
+            }
+        }
+        ResponseType::Other(COPY_PATH) => {
+            dialog.clipboard().set_text(&path.display().to_string());
+        }
+        _ => dialog.close(),
+    });
+
+    dialog.present();
+}
+
+/// Ask before copying a selection that contains a secret (`mask_secrets`),
+/// running `copy` if the user confirms
+pub fn confirm_copy_secret<F>(parent: Option<&Window>, copy: F)
+where
+    F: Fn() + 'static,
+{
+    let dialog = Dialog::builder().title("Copy Secret?").modal(true).build();
+    dialog.set_transient_for(parent);
+
+    dialog.add_button("Cancel", ResponseType::Cancel);
+    dialog.add_button("Copy", ResponseType::Ok);
+
+    let content = dialog.content_area();
+    content.set_spacing(12);
+    content.set_margin_top(12);
+    content.set_margin_bottom(12);
+    content.set_margin_start(12);
+    content.set_margin_end(12);
+
+    let message_label = Label::new(Some(
+        "The selection looks like it contains a secret (a key or token).\nCopy it anyway?",
+    ));
+    message_label.set_halign(Align::Start);
+    content.append(&message_label);
+
+    dialog.connect_response(move |dialog, response| {
+        if response == ResponseType::Ok {
+            copy();
+        }
+        dialog.close();
+    });
+
+    dialog.present();
+}
+
+/// Run `paste` right away, or once the user confirms when the terminal's
+/// foreground program `process` is listed in `confirm_paste_into`
+pub fn confirm_paste<F>(
+    parent: Option<&Window>,
+    general: &GeneralConfig,
+    process: Option<String>,
+    text: &str,
+    paste: F,
+) where
+    F: Fn() + 'static,
+{
+    let Some(process) = process.filter(|p| general.confirm_paste_for(p)) else {
+        paste();
+        return;
+    };
+
+    let dialog = Dialog::builder().title("Confirm Paste").modal(true).build();
+    dialog.set_transient_for(parent);
+
+    dialog.add_button("Cancel", ResponseType::Cancel);
+    dialog.add_button("Paste", ResponseType::Ok);
+
+    let content = dialog.content_area();
+    content.set_spacing(12);
+    content.set_margin_top(12);
+    content.set_margin_bottom(12);
+    content.set_margin_start(12);
+    content.set_margin_end(12);
+
+    let lines = text.lines().count().max(1);
+    let message = format!(
+        "Paste {} line{} into \"{}\"?",
+        lines,
+        if lines == 1 { "" } else { "s" },
+        process
+    );
+    let message_label = Label::new(Some(&message));
+    message_label.set_halign(Align::Start);
+    content.append(&message_label);
+
+    // Show the start of the clipboard so the user can see what would be sent
+    let preview: String = text.lines().take(5).collect::<Vec<_>>().join("\n");
+    let preview_label = Label::new(Some(&preview));
+    preview_label.set_halign(Align::Start);
+    preview_label.set_wrap(true);
+    preview_label.set_max_width_chars(60);
+    preview_label.set_ellipsize(gtk4::pango::EllipsizeMode::End);
+    preview_label.set_lines(5);
+    preview_label.add_css_class("monospace");
+    content.append(&preview_label);
+
+    dialog.connect_response(move |dialog, response| {
+        if response == ResponseType::Ok {
+            paste();
+        }
+        dialog.close();
+    });
+
+    dialog.present();
+}
+
 /// Result of a file drop dialog
 pub enum FileDropChoice {
     PastePath,
@@ -0,0 +1,43 @@
+//! Filter Scrollback results for GTK4
+//!
+//! Read-only window listing the scrollback lines that matched a filter.
+
+use gtk4::prelude::*;
+use gtk4::{ScrolledWindow, TextView, Window};
+
+/// Show the lines that matched `pattern` in a read-only window
+pub fn show_filter_results(parent: &impl IsA<Window>, pattern: &str, lines: &[String]) {
+    let title = match lines.len() {
+        1 => format!("Filter: {} (1 line)", pattern),
+        n => format!("Filter: {} ({} lines)", pattern, n),
+    };
+    let window = Window::builder()
+        .title(title)
+        .transient_for(parent)
+        .default_width(800)
+        .default_height(500)
+        .build();
+
+    let scroll = ScrolledWindow::new();
+    scroll.set_vexpand(true);
+    scroll.set_hexpand(true);
+
+    let text_view = TextView::new();
+    text_view.set_editable(false);
+    text_view.set_monospace(true);
+    text_view.set_wrap_mode(gtk4::WrapMode::None);
+    text_view.set_left_margin(8);
+    text_view.set_right_margin(8);
+    text_view.set_top_margin(8);
+    text_view.set_bottom_margin(8);
+
+    if lines.is_empty() {
+        text_view.buffer().set_text("No matching lines");
+    } else {
+        text_view.buffer().set_text(&lines.join("\n"));
+    }
+
+    scroll.set_child(Some(&text_view));
+    window.set_child(Some(&scroll));
+    window.present();
+}
@@ -6,6 +6,7 @@ mod app;
 mod dialogs;
 mod docker_dialog;
 mod file_transfer;
+mod filter_viewer;
 mod log_viewer;
 mod menu;
 mod notification_bar;
@@ -19,7 +20,7 @@ mod window;
 
 use clap::Parser;
 use gtk4::prelude::*;
-use gtk4::Application;
+use gtk4::{gio, glib, Application};
 use std::path::PathBuf;
 
 /// Command-line arguments for cterm
@@ -34,6 +35,23 @@ pub struct Args {
     #[arg(short = 'e', long = "execute")]
     pub command: Option<String>,
 
+    /// Run the -e command directly instead of through the shell
+    #[arg(long, requires = "command")]
+    pub no_shell: bool,
+
+    /// Start the terminal in raw mode (no echo or line editing), for
+    /// programs that manage their own line discipline
+    #[arg(long)]
+    pub raw: bool,
+
+    /// Open the first tab from the named tab template
+    #[arg(long, visible_alias = "profile", conflicts_with = "command")]
+    pub template: Option<String>,
+
+    /// Open the windows and tabs of a workspace file
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["command", "template"])]
+    pub workspace: Option<PathBuf>,
+
     /// Set the working directory
     #[arg(short = 'd', long = "directory")]
     pub directory: Option<PathBuf>,
@@ -50,6 +68,11 @@ pub struct Args {
     #[arg(short = 't', long = "title")]
     pub title: Option<String>,
 
+    /// Set the application id / window class (X11 WM_CLASS, Wayland app id)
+    /// for window manager rules
+    #[arg(long)]
+    pub class: Option<String>,
+
     /// Receive upgrade state from parent process via inherited FD/handle (internal use)
     /// On Unix: file descriptor (i32), On Windows: handle value (usize)
     #[arg(long, hide = true)]
@@ -58,6 +81,14 @@ pub struct Args {
     /// Disable watchdog supervision (run directly without crash recovery)
     #[arg(long)]
     pub no_watchdog: bool,
+
+    /// Reopen the sessions left running by File > Detach Sessions
+    #[arg(long, conflicts_with_all = ["command", "template", "workspace"])]
+    pub attach: bool,
+
+    /// Hold detached sessions in the background (internal use)
+    #[arg(long, hide = true)]
+    pub session_keeper: bool,
 }
 
 /// Global application arguments (accessible from window creation)
@@ -85,13 +116,58 @@ pub fn run() {
         std::process::exit(exit_code.value());
     }
 
+    #[cfg(unix)]
+    if args.session_keeper {
+        let config = cterm_app::config::load_config().unwrap_or_default();
+        let screen_config = cterm_core::screen::ScreenConfig {
+            scrollback_lines: config.general.scrollback_lines,
+        };
+        if let Err(e) = cterm_app::session_keeper::run_session_keeper(screen_config) {
+            log::error!("Session keeper failed: {}", e);
+            std::process::exit(1);
+        }
+        std::process::exit(0);
+    }
+
+    #[cfg(unix)]
+    if args.attach {
+        match cterm_app::session_keeper::attach_sessions() {
+            Ok(Some((state, fds))) => {
+                let exit_code = upgrade_receiver::run_attached(state, fds);
+                std::process::exit(exit_code.value());
+            }
+            Ok(None) => eprintln!("cterm: no detached sessions"),
+            Err(e) => eprintln!("cterm: failed to attach: {}", e),
+        }
+        std::process::exit(1);
+    }
+
+    // Fail before opening a window if the template doesn't exist
+    if let Some(ref name) = args.template {
+        if let Err(e) = cterm_app::config::load_template(name) {
+            eprintln!("cterm: {}", e);
+            std::process::exit(2);
+        }
+    }
+    if let Some(ref path) = args.workspace {
+        if let Err(e) = cterm_app::workspace::load_workspace(path) {
+            eprintln!("cterm: {}: {}", path.display(), e);
+            std::process::exit(2);
+        }
+    }
+
+    // --class takes precedence over the configured app id
+    let class = args.class.clone().or_else(|| {
+        cterm_app::config::load_config()
+            .ok()
+            .and_then(|config| config.general.app_id)
+    });
+
     // Store args for later access
     let _ = APP_ARGS.set(args);
 
     // Create the GTK application
-    let app = Application::builder()
-        .application_id("com.cterm.terminal")
-        .build();
+    let app = build_application(class.as_deref(), gio::ApplicationFlags::empty());
 
     // Connect to the activate signal
     app.connect_activate(|app| {
@@ -104,3 +180,25 @@ pub fn run() {
     let exit_code = app.run_with_args(&[] as &[&str]);
     std::process::exit(exit_code.value());
 }
+
+/// Create the GTK application with the configured app id / window class
+///
+/// The class also becomes the program name, which GTK uses for the X11
+/// `WM_CLASS` and, without an application id, the Wayland app id. Names that
+/// aren't valid application ids (like "scratch") run without one, as a
+/// separate instance.
+pub(crate) fn build_application(class: Option<&str>, flags: gio::ApplicationFlags) -> Application {
+    if let Some(class) = class {
+        glib::set_prgname(Some(class));
+    }
+
+    let app_id = class.unwrap_or(cterm_app::config::DEFAULT_APP_ID);
+    let builder = Application::builder();
+    if gio::Application::id_is_valid(app_id) {
+        builder.application_id(app_id).flags(flags).build()
+    } else {
+        builder
+            .flags(flags | gio::ApplicationFlags::NON_UNIQUE)
+            .build()
+    }
+}
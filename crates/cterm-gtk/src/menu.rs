@@ -1,5 +1,6 @@
 //! Application menu system
 
+use cterm_core::encoding::TerminalEncoding;
 use gtk4::gio;
 
 /// Create the application menu model
@@ -17,8 +18,14 @@ pub fn create_menu_model_with_options(show_debug: bool) -> gio::Menu {
     // File menu
     let file_menu = gio::Menu::new();
     file_menu.append(Some("New Tab"), Some("win.new-tab"));
+    file_menu.append(Some("New Incognito Tab"), Some("win.new-incognito-tab"));
     file_menu.append(Some("New Window"), Some("win.new-window"));
+    file_menu.append(
+        Some("Reopen Closed Window"),
+        Some("win.reopen-closed-window"),
+    );
     file_menu.append(Some("Quick Open Template..."), Some("win.quick-open"));
+    file_menu.append(Some("Open Workspace..."), Some("win.open-workspace"));
 
     // Docker submenu
     let docker_menu = gio::Menu::new();
@@ -28,6 +35,8 @@ pub fn create_menu_model_with_options(show_debug: bool) -> gio::Menu {
     file_menu.append(Some("Tab Templates..."), Some("win.tab-templates"));
     file_menu.append(Some("Close Tab"), Some("win.close-tab"));
     file_menu.append(Some("Close Other Tabs"), Some("win.close-other-tabs"));
+    #[cfg(unix)]
+    file_menu.append(Some("Detach Sessions"), Some("win.detach-sessions"));
     file_menu.append(Some("Quit"), Some("win.quit"));
     menu.append_submenu(Some("File"), &file_menu);
 
@@ -35,7 +44,14 @@ pub fn create_menu_model_with_options(show_debug: bool) -> gio::Menu {
     let edit_menu = gio::Menu::new();
     edit_menu.append(Some("Copy"), Some("win.copy"));
     edit_menu.append(Some("Copy as HTML"), Some("win.copy-html"));
+    edit_menu.append(Some("Copy as Image"), Some("win.copy-image"));
+    edit_menu.append(Some("Save as Image..."), Some("win.save-image"));
+    edit_menu.append(
+        Some("Copy Last Command Output"),
+        Some("win.copy-last-output"),
+    );
     edit_menu.append(Some("Paste"), Some("win.paste"));
+    edit_menu.append(Some("Paste from History..."), Some("win.clipboard-history"));
     edit_menu.append(Some("Select All"), Some("win.select-all"));
     menu.append_submenu(Some("Edit"), &edit_menu);
 
@@ -44,12 +60,24 @@ pub fn create_menu_model_with_options(show_debug: bool) -> gio::Menu {
     terminal_menu.append(Some("Set Title..."), Some("win.set-title"));
     terminal_menu.append(Some("Set Color..."), Some("win.set-color"));
     terminal_menu.append(Some("Find..."), Some("win.find"));
+    terminal_menu.append(Some("Filter Scrollback..."), Some("win.filter-scrollback"));
+    terminal_menu.append(Some("Rerun Last Command"), Some("win.rerun-last-command"));
+    terminal_menu.append(
+        Some("Clear to Last Prompt"),
+        Some("win.clear-to-last-prompt"),
+    );
+    terminal_menu.append(Some("Set Mark"), Some("win.set-mark"));
+    terminal_menu.append(Some("Jump to Mark"), Some("win.jump-mark"));
+    terminal_menu.append(Some("Toggle Recording"), Some("win.toggle-recording"));
 
     // Encoding submenu
     let encoding_menu = gio::Menu::new();
-    encoding_menu.append(Some("UTF-8"), Some("win.set-encoding::utf8"));
-    encoding_menu.append(Some("ISO-8859-1"), Some("win.set-encoding::iso8859-1"));
-    encoding_menu.append(Some("ISO-8859-15"), Some("win.set-encoding::iso8859-15"));
+    for encoding in TerminalEncoding::ALL {
+        encoding_menu.append(
+            Some(encoding.label()),
+            Some(&format!("win.set-encoding::{}", encoding.name())),
+        );
+    }
     terminal_menu.append_submenu(Some("Set Encoding"), &encoding_menu);
 
     // Signal submenu
@@ -82,6 +110,15 @@ pub fn create_menu_model_with_options(show_debug: bool) -> gio::Menu {
     // Help menu
     let help_menu = gio::Menu::new();
     help_menu.append(Some("Preferences..."), Some("win.preferences"));
+    help_menu.append(Some("Import Theme..."), Some("win.import-theme"));
+    help_menu.append(
+        Some("Import Keybindings..."),
+        Some("win.import-keybindings"),
+    );
+    help_menu.append(
+        Some("Export Keybindings..."),
+        Some("win.export-keybindings"),
+    );
     help_menu.append(Some("Check for Updates..."), Some("win.check-updates"));
     help_menu.append(Some("About"), Some("win.about"));
 
@@ -91,6 +128,15 @@ pub fn create_menu_model_with_options(show_debug: bool) -> gio::Menu {
         debug_menu.append(Some("View Logs"), Some("win.view-logs"));
         debug_menu.append(Some("Re-launch cterm"), Some("win.debug-relaunch"));
         debug_menu.append(Some("Dump State"), Some("win.debug-dump-state"));
+        debug_menu.append(Some("Send Bytes..."), Some("win.debug-send-bytes"));
+        debug_menu.append(Some("Replay File..."), Some("win.debug-replay-file"));
+        debug_menu.append(Some("Stop Replay"), Some("win.debug-stop-replay"));
+        // Informational only (no action, so it is shown insensitive)
+        let unknown_sequences = format!(
+            "Unknown Sequences: {}",
+            cterm_core::parser::unknown_sequence_count()
+        );
+        debug_menu.append(Some(&unknown_sequences), None);
         help_menu.append_submenu(Some("Debug"), &debug_menu);
     }
 
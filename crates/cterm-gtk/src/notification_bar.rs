@@ -2,6 +2,8 @@
 //!
 //! Shows a dismissible notification when files are received via iTerm2 protocol.
 //! Format: "Received file: Name.bin (1.2 MB)" [Save] [Save As...] [Discard]
+//!
+//! Also shows plain messages (e.g. a missing font) with just [Dismiss].
 
 use std::cell::{Cell, RefCell};
 use std::rc::Rc;
@@ -27,14 +29,11 @@ pub struct NotificationBar {
     container: GtkBox,
     /// Label showing file name and size
     label: Label,
-    /// Save button (kept for potential future use)
-    #[allow(dead_code)]
+    /// Save button, hidden for plain messages
     save_button: Button,
-    /// Save As button (kept for potential future use)
-    #[allow(dead_code)]
+    /// Save As button, hidden for plain messages
     save_as_button: Button,
-    /// Discard button (kept for potential future use)
-    #[allow(dead_code)]
+    /// Discard button, labelled "Dismiss" for plain messages
     discard_button: Button,
     /// Current file ID
     file_id: Rc<Cell<u64>>,
@@ -163,11 +162,24 @@ impl NotificationBar {
         let text = format!("Received file: {} ({})", display_name, size_str);
 
         self.label.set_text(&text);
+        self.save_button.set_visible(true);
+        self.save_as_button.set_visible(true);
+        self.discard_button.set_label("Discard");
         self.container.set_visible(true);
 
         log::debug!("Showing notification for file {} (id={})", display_name, id);
     }
 
+    /// Show a message with only a Dismiss button
+    pub fn show_message(&self, text: &str) {
+        self.file_id.set(0);
+        self.label.set_text(text);
+        self.save_button.set_visible(false);
+        self.save_as_button.set_visible(false);
+        self.discard_button.set_label("Dismiss");
+        self.container.set_visible(true);
+    }
+
     /// Hide the notification bar
     pub fn hide(&self) {
         self.container.set_visible(false);
@@ -1,12 +1,19 @@
 //! Custom tab bar widget
 
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
 use std::rc::Rc;
+use std::time::Duration;
 
 use gtk4::gio::{Menu, SimpleAction, SimpleActionGroup};
 use gtk4::prelude::*;
-use gtk4::{Box as GtkBox, Button, GestureClick, Label, Orientation, PopoverMenu};
+use gtk4::{
+    glib, Box as GtkBox, Button, EventControllerMotion, GestureClick, Label, Orientation,
+    PopoverMenu,
+};
+
+use cterm_app::config::{NewTabButton, TabCloseButton, TabsConfig};
+use cterm_ui::theme::Theme;
 
 /// Callback type for tab bar events
 type TabCallback = Rc<RefCell<Option<Box<dyn Fn()>>>>;
@@ -29,8 +36,18 @@ pub struct TabBar {
     on_click_callbacks: TabCallbackMap,
     on_rename: TabIdCallback,
     on_set_color: TabIdCallback,
+    on_new_tab_after: TabIdCallback,
+    on_toggle_pin: TabIdCallback,
     /// Current tab ID for context menu actions
     context_menu_tab_id: Rc<RefCell<Option<u64>>>,
+    /// When tabs show their close button
+    close_button: TabCloseButton,
+    /// Middle-click closes a tab instead of opening one after it
+    middle_click_closes_tab: bool,
+    /// Tab under the mouse
+    hovered_tab: Rc<Cell<Option<u64>>>,
+    /// Styles of tabs tinted by a finished command, in the theme's colors
+    exit_status_css: gtk4::CssProvider,
 }
 
 struct TabInfo {
@@ -38,13 +55,17 @@ struct TabInfo {
     button: Button,
     label: Label,
     bell_icon: Label,
-    #[allow(dead_code)] // Kept to prevent button from being dropped
+    incognito_icon: Label,
+    recording_icon: Label,
+    pin_icon: Label,
+    /// Context menu, so its pin item can read "Unpin Tab" once pinned
+    menu: Menu,
     close_button: Button,
 }
 
 impl TabBar {
     /// Create a new tab bar
-    pub fn new() -> Self {
+    pub fn new(config: &TabsConfig) -> Self {
         let container = GtkBox::builder()
             .orientation(Orientation::Horizontal)
             .spacing(0)
@@ -61,7 +82,11 @@ impl TabBar {
         new_tab_button.add_css_class("new-tab-button");
 
         container.append(&tabs_box);
-        container.append(&new_tab_button);
+        match config.new_tab_button {
+            NewTabButton::Start => container.prepend(&new_tab_button),
+            NewTabButton::End => container.append(&new_tab_button),
+            NewTabButton::Hidden => {}
+        }
 
         let tab_bar = Self {
             container,
@@ -74,8 +99,22 @@ impl TabBar {
             on_click_callbacks: Rc::new(RefCell::new(HashMap::new())),
             on_rename: Rc::new(RefCell::new(None)),
             on_set_color: Rc::new(RefCell::new(None)),
+            on_new_tab_after: Rc::new(RefCell::new(None)),
+            on_toggle_pin: Rc::new(RefCell::new(None)),
             context_menu_tab_id: Rc::new(RefCell::new(None)),
+            close_button: config.close_button,
+            middle_click_closes_tab: config.middle_click_closes_tab,
+            hovered_tab: Rc::new(Cell::new(None)),
+            exit_status_css: gtk4::CssProvider::new(),
         };
+        if let Some(display) = gtk4::gdk::Display::default() {
+            gtk4::style_context_add_provider_for_display(
+                &display,
+                &tab_bar.exit_status_css,
+                gtk4::STYLE_PROVIDER_PRIORITY_APPLICATION,
+            );
+        }
+        tab_bar.set_exit_status_colors(&Theme::default());
 
         // Set up new tab button click
         let on_new_tab = Rc::clone(&tab_bar.on_new_tab);
@@ -93,21 +132,45 @@ impl TabBar {
         &self.container
     }
 
-    /// Add a new tab
+    /// Add a new tab at the end
     pub fn add_tab(&self, id: u64, title: &str) {
+        let index = self.tabs.borrow().len();
+        self.insert_tab(index, id, title);
+    }
+
+    /// Insert a new tab at the given position
+    pub fn insert_tab(&self, index: usize, id: u64, title: &str) {
         let tab_box = GtkBox::new(Orientation::Horizontal, 4);
 
+        // Pin marker (hidden by default)
+        let pin_icon = Label::new(Some("📌"));
+        pin_icon.set_visible(false);
+        pin_icon.set_tooltip_text(Some("Pinned"));
+
         // Bell icon (hidden by default)
         let bell_icon = Label::new(Some("🔔"));
         bell_icon.set_visible(false);
         bell_icon.add_css_class("tab-bell-icon");
 
+        // Incognito marker (hidden by default)
+        let incognito_icon = Label::new(Some("🕶"));
+        incognito_icon.set_visible(false);
+        incognito_icon.set_tooltip_text(Some("Incognito: no scrollback, not saved"));
+
+        // Session recording marker (hidden by default)
+        let recording_icon = Label::new(Some(cterm_app::recording::RECORDING_INDICATOR));
+        recording_icon.set_visible(false);
+
         let label = Label::new(Some(title));
 
         let close_button = Button::builder().label("×").focusable(false).build();
         close_button.add_css_class("tab-close-button");
+        close_button.set_visible(self.close_button != TabCloseButton::Never);
 
+        tab_box.append(&pin_icon);
         tab_box.append(&bell_icon);
+        tab_box.append(&incognito_icon);
+        tab_box.append(&recording_icon);
         tab_box.append(&label);
         tab_box.append(&close_button);
 
@@ -143,6 +206,8 @@ impl TabBar {
         let context_menu_tab_id = Rc::clone(&self.context_menu_tab_id);
         let on_rename = Rc::clone(&self.on_rename);
         let on_set_color = Rc::clone(&self.on_set_color);
+        let on_new_tab_after = Rc::clone(&self.on_new_tab_after);
+        let on_toggle_pin = Rc::clone(&self.on_toggle_pin);
 
         // Create action group for this tab's context menu
         let action_group = SimpleActionGroup::new();
@@ -171,12 +236,37 @@ impl TabBar {
         });
         action_group.add_action(&color_action);
 
+        let new_after_action = SimpleAction::new("new-after", None);
+        let context_id_new_after = Rc::clone(&context_menu_tab_id);
+        let on_new_tab_after_clone = Rc::clone(&on_new_tab_after);
+        new_after_action.connect_activate(move |_, _| {
+            if let Some(id) = *context_id_new_after.borrow() {
+                if let Some(ref callback) = *on_new_tab_after_clone.borrow() {
+                    callback(id);
+                }
+            }
+        });
+        action_group.add_action(&new_after_action);
+
+        let pin_action = SimpleAction::new("toggle-pin", None);
+        let context_id_pin = Rc::clone(&context_menu_tab_id);
+        pin_action.connect_activate(move |_, _| {
+            if let Some(id) = *context_id_pin.borrow() {
+                if let Some(ref callback) = *on_toggle_pin.borrow() {
+                    callback(id);
+                }
+            }
+        });
+        action_group.add_action(&pin_action);
+
         button.insert_action_group("tab", Some(&action_group));
 
         // Create context menu
         let menu = Menu::new();
         menu.append(Some("Rename Tab..."), Some("tab.rename"));
         menu.append(Some("Set Tab Color..."), Some("tab.set-color"));
+        menu.append(Some("New Tab to the Right"), Some("tab.new-after"));
+        menu.append(Some("Pin Tab"), Some("tab.toggle-pin"));
 
         let popover = PopoverMenu::from_model(Some(&menu));
         popover.set_parent(&button);
@@ -197,15 +287,71 @@ impl TabBar {
         });
         button.add_controller(gesture);
 
-        self.tabs_box.append(&button);
-
-        self.tabs.borrow_mut().push(TabInfo {
-            id,
-            button,
-            label,
-            bell_icon,
-            close_button,
+        // Middle-click closes the tab, or opens a new tab right after it
+        let middle_click = GestureClick::new();
+        middle_click.set_button(2); // Middle mouse button
+        let middle_click_closes_tab = self.middle_click_closes_tab;
+        let close_callbacks = Rc::clone(&self.on_close_callbacks);
+        middle_click.connect_pressed(move |gesture, _, _, _| {
+            if middle_click_closes_tab {
+                if let Some(callback) = close_callbacks.borrow().get(&tab_id) {
+                    callback();
+                }
+            } else if let Some(ref callback) = *on_new_tab_after.borrow() {
+                callback(tab_id);
+            }
+            gesture.set_state(gtk4::EventSequenceState::Claimed);
         });
+        button.add_controller(middle_click);
+
+        // With close buttons on hover, show this tab's while the mouse is
+        // over it
+        if self.close_button == TabCloseButton::Hover {
+            let motion = EventControllerMotion::new();
+            let hovered_tab = Rc::clone(&self.hovered_tab);
+            let close_button_enter = close_button.clone();
+            motion.connect_enter(move |_, _, _| {
+                hovered_tab.set(Some(tab_id));
+                show_close_button(&close_button_enter, true);
+            });
+            let hovered_tab = Rc::clone(&self.hovered_tab);
+            let active_tab = Rc::clone(&self.active_tab);
+            let close_button_leave = close_button.clone();
+            motion.connect_leave(move |_| {
+                if hovered_tab.get() == Some(tab_id) {
+                    hovered_tab.set(None);
+                }
+                let active = *active_tab.borrow() == Some(tab_id);
+                show_close_button(&close_button_leave, active);
+            });
+            button.add_controller(motion);
+            show_close_button(&close_button, false);
+        }
+
+        let mut tabs = self.tabs.borrow_mut();
+        let index = index.min(tabs.len());
+        match index.checked_sub(1).and_then(|prev| tabs.get(prev)) {
+            Some(prev) => self
+                .tabs_box
+                .insert_child_after(&button, Some(&prev.button)),
+            None => self.tabs_box.prepend(&button),
+        }
+
+        tabs.insert(
+            index,
+            TabInfo {
+                id,
+                button,
+                label,
+                bell_icon,
+                incognito_icon,
+                recording_icon,
+                pin_icon,
+                menu,
+                close_button,
+            },
+        );
+        drop(tabs);
 
         // Set as active if first tab
         if self.tabs.borrow().len() == 1 {
@@ -226,9 +372,41 @@ impl TabBar {
         self.on_click_callbacks.borrow_mut().remove(&id);
     }
 
+    /// Move a tab's button to a new position
+    pub fn move_tab(&self, id: u64, index: usize) {
+        let mut tabs = self.tabs.borrow_mut();
+        let Some(from) = tabs.iter().position(|t| t.id == id) else {
+            return;
+        };
+        let tab = tabs.remove(from);
+        let index = index.min(tabs.len());
+        let prev = index.checked_sub(1).and_then(|prev| tabs.get(prev));
+        self.tabs_box
+            .reorder_child_after(&tab.button, prev.map(|prev| &prev.button));
+        tabs.insert(index, tab);
+    }
+
+    /// Show a tab as pinned or not
+    pub fn set_pinned(&self, id: u64, pinned: bool) {
+        if let Some(tab) = self.tabs.borrow().iter().find(|tab| tab.id == id) {
+            tab.pin_icon.set_visible(pinned);
+            if pinned {
+                tab.button.add_css_class("pinned-tab");
+            } else {
+                tab.button.remove_css_class("pinned-tab");
+            }
+            // The pin item is the last one in the menu
+            let item = tab.menu.n_items() - 1;
+            tab.menu.remove(item);
+            let label = if pinned { "Unpin Tab" } else { "Pin Tab" };
+            tab.menu.append(Some(label), Some("tab.toggle-pin"));
+        }
+    }
+
     /// Set the active tab
     pub fn set_active(&self, id: u64) {
         *self.active_tab.borrow_mut() = Some(id);
+        self.set_exit_status(id, None);
 
         for tab in self.tabs.borrow().iter() {
             if tab.id == id {
@@ -236,6 +414,10 @@ impl TabBar {
             } else {
                 tab.button.remove_css_class("active");
             }
+            if self.close_button == TabCloseButton::Hover {
+                let hovered = self.hovered_tab.get() == Some(tab.id);
+                show_close_button(&tab.close_button, tab.id == id || hovered);
+            }
         }
     }
 
@@ -251,31 +433,50 @@ impl TabBar {
 
     /// Set tab color
     pub fn set_color(&self, id: u64, color: Option<&str>) {
+        self.set_tab_css(
+            id,
+            "colored-tab",
+            color.map(|color| {
+                format!(
+                    "button.colored-tab-{} {{ background-color: {}; }}",
+                    id, color
+                )
+            }),
+        );
+    }
+
+    /// Set tab title text color
+    pub fn set_title_color(&self, id: u64, color: Option<&str>) {
+        self.set_tab_css(
+            id,
+            "titled-tab",
+            color.map(|color| format!("button.titled-tab-{} label {{ color: {}; }}", id, color)),
+        );
+    }
+
+    /// Style a tab button through a per-tab `<class_prefix>-<id>` CSS class,
+    /// or remove the class when `css` is None
+    fn set_tab_css(&self, id: u64, class_prefix: &str, css: Option<String>) {
         for tab in self.tabs.borrow().iter() {
             if tab.id == id {
-                if let Some(color) = color {
+                // Remove old class if any
+                let classes: Vec<_> = tab
+                    .button
+                    .css_classes()
+                    .iter()
+                    .filter(|c| c.starts_with(class_prefix))
+                    .map(|c| c.to_string())
+                    .collect();
+                for class in classes {
+                    tab.button.remove_css_class(&class);
+                }
+
+                if let Some(css) = css {
                     // Apply inline style using CSS provider
-                    let css = format!(
-                        "button.colored-tab-{} {{ background-color: {}; }}",
-                        id, color
-                    );
                     let provider = gtk4::CssProvider::new();
                     provider.load_from_data(&css);
 
-                    // Remove old colored-tab class if any
-                    let classes: Vec<_> = tab
-                        .button
-                        .css_classes()
-                        .iter()
-                        .filter(|c| c.starts_with("colored-tab"))
-                        .map(|c| c.to_string())
-                        .collect();
-                    for class in classes {
-                        tab.button.remove_css_class(&class);
-                    }
-
-                    // Add new class and style
-                    let class_name = format!("colored-tab-{}", id);
+                    let class_name = format!("{}-{}", class_prefix, id);
                     tab.button.add_css_class(&class_name);
 
                     if let Some(display) = gtk4::gdk::Display::default() {
@@ -285,24 +486,59 @@ impl TabBar {
                             gtk4::STYLE_PROVIDER_PRIORITY_APPLICATION,
                         );
                     }
-                } else {
-                    // Remove any colored-tab class
-                    let classes: Vec<_> = tab
-                        .button
-                        .css_classes()
-                        .iter()
-                        .filter(|c| c.starts_with("colored-tab"))
-                        .map(|c| c.to_string())
-                        .collect();
-                    for class in classes {
-                        tab.button.remove_css_class(&class);
-                    }
                 }
                 break;
             }
         }
     }
 
+    /// Take the green and red that tabs are tinted with from `theme`
+    pub fn set_exit_status_colors(&self, theme: &Theme) {
+        let mut css = String::new();
+        for (class, success) in [("tab-succeeded", true), ("tab-failed", false)] {
+            let color = theme.exit_status_color(success).to_hex();
+            css.push_str(&format!(
+                "button.{class} {{ background-color: alpha({color}, 0.25); \
+                 transition: background-color 600ms ease-out; }}\n\
+                 button.{class}.tab-flash {{ background-color: alpha({color}, 0.7); \
+                 transition: none; }}\n"
+            ));
+        }
+        self.exit_status_css.load_from_data(&css);
+    }
+
+    /// Show how a command in a tab finished: flash the tab, then leave it
+    /// tinted green (`Some(true)`) or red (`Some(false)`) until it's
+    /// activated; `None` clears the tint
+    pub fn set_exit_status(&self, id: u64, success: Option<bool>) {
+        let Some(button) = self
+            .tabs
+            .borrow()
+            .iter()
+            .find(|tab| tab.id == id)
+            .map(|tab| tab.button.clone())
+        else {
+            return;
+        };
+        for class in ["tab-succeeded", "tab-failed", "tab-flash"] {
+            button.remove_css_class(class);
+        }
+        let Some(success) = success else {
+            return;
+        };
+
+        button.add_css_class(if success {
+            "tab-succeeded"
+        } else {
+            "tab-failed"
+        });
+        // Dropping the flash class fades the tab to its tint
+        button.add_css_class("tab-flash");
+        glib::timeout_add_local_once(Duration::from_millis(400), move || {
+            button.remove_css_class("tab-flash");
+        });
+    }
+
     /// Mark tab as having unread content
     #[allow(dead_code)]
     pub fn set_unread(&self, id: u64, unread: bool) {
@@ -343,6 +579,17 @@ impl TabBar {
         *self.on_rename.borrow_mut() = Some(Box::new(callback));
     }
 
+    /// Set callback for opening a new tab right after a given tab
+    /// (middle-click or context menu)
+    pub fn set_on_new_tab_after<F: Fn(u64) + 'static>(&self, callback: F) {
+        *self.on_new_tab_after.borrow_mut() = Some(Box::new(callback));
+    }
+
+    /// Set callback for pinning or unpinning a tab (from context menu)
+    pub fn set_on_toggle_pin<F: Fn(u64) + 'static>(&self, callback: F) {
+        *self.on_toggle_pin.borrow_mut() = Some(Box::new(callback));
+    }
+
     /// Set callback for tab set color (from context menu)
     #[allow(dead_code)]
     pub fn set_on_set_color<F: Fn(u64) + 'static>(&self, callback: F) {
@@ -365,6 +612,22 @@ impl TabBar {
         }
     }
 
+    /// Show or hide the incognito marker for a tab
+    pub fn set_incognito(&self, id: u64, incognito: bool) {
+        if let Some(tab) = self.tabs.borrow().iter().find(|tab| tab.id == id) {
+            tab.incognito_icon.set_visible(incognito);
+        }
+    }
+
+    /// Show the recording marker for a tab while its session is recorded
+    pub fn set_recording(&self, id: u64, path: Option<&std::path::Path>) {
+        if let Some(tab) = self.tabs.borrow().iter().find(|tab| tab.id == id) {
+            tab.recording_icon.set_visible(path.is_some());
+            let tooltip = path.map(|path| format!("Recording to {}", path.display()));
+            tab.recording_icon.set_tooltip_text(tooltip.as_deref());
+        }
+    }
+
     /// Clear bell indicator for a tab (convenience wrapper)
     pub fn clear_bell(&self, id: u64) {
         self.set_bell(id, false);
@@ -397,6 +660,13 @@ impl TabBar {
 
 impl Default for TabBar {
     fn default() -> Self {
-        Self::new()
+        Self::new(&TabsConfig::default())
     }
 }
+
+/// Show or hide a tab's close button without changing the tab's width; a
+/// hidden button can't be clicked
+fn show_close_button(close_button: &Button, shown: bool) {
+    close_button.set_opacity(if shown { 1.0 } else { 0.0 });
+    close_button.set_can_target(shown);
+}
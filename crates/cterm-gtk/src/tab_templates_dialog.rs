@@ -28,6 +28,7 @@ struct TemplateWidgets {
     path_entry: Entry,
     git_remote_entry: Entry,
     color_button: ColorButton,
+    title_color_button: ColorButton,
     theme_entry: Entry,
     unique_check: CheckButton,
     auto_start_check: CheckButton,
@@ -275,6 +276,7 @@ fn create_widgets(notebook: &Notebook) -> TemplateWidgets {
         path_entry,
         git_remote_entry,
         color_button,
+        title_color_button,
         theme_entry,
         unique_check,
         auto_start_check,
@@ -327,6 +329,7 @@ fn create_widgets(notebook: &Notebook) -> TemplateWidgets {
         path_entry,
         git_remote_entry,
         color_button,
+        title_color_button,
         theme_entry,
         unique_check,
         auto_start_check,
@@ -364,6 +367,7 @@ fn create_general_tab() -> (
     Entry,
     Entry,
     ColorButton,
+    ColorButton,
     Entry,
     CheckButton,
     CheckButton,
@@ -447,6 +451,23 @@ fn create_general_tab() -> (
     grid.attach(&color_box, 1, row, 1, 1);
     row += 1;
 
+    // Title color
+    let title_color_label = Label::new(Some("Title Color:"));
+    title_color_label.set_halign(Align::End);
+    grid.attach(&title_color_label, 0, row, 1, 1);
+    let title_color_box = GtkBox::new(Orientation::Horizontal, 8);
+    let title_color_button = ColorButton::new();
+    title_color_button.set_tooltip_text(Some("Tab title text color"));
+    title_color_box.append(&title_color_button);
+    let clear_title_color_button = Button::with_label("Clear");
+    let title_color_button_clone = title_color_button.clone();
+    clear_title_color_button.connect_clicked(move |_| {
+        title_color_button_clone.set_rgba(&gtk4::gdk::RGBA::new(0.0, 0.0, 0.0, 0.0));
+    });
+    title_color_box.append(&clear_title_color_button);
+    grid.attach(&title_color_box, 1, row, 1, 1);
+    row += 1;
+
     // Theme
     let theme_label = Label::new(Some("Theme:"));
     theme_label.set_halign(Align::End);
@@ -482,6 +503,7 @@ fn create_general_tab() -> (
         path_entry,
         git_remote_entry,
         color_button,
+        title_color_button,
         theme_entry,
         unique_check,
         auto_start_check,
@@ -827,16 +849,9 @@ fn load_template_into_widgets(widgets: &TemplateWidgets, template: &StickyTabCon
         .git_remote_entry
         .set_text(template.git_remote.as_deref().unwrap_or(""));
 
-    // Color
-    if let Some(hex) = &template.color {
-        if let Some(rgba) = parse_hex_color(hex) {
-            widgets.color_button.set_rgba(&rgba);
-        }
-    } else {
-        widgets
-            .color_button
-            .set_rgba(&gtk4::gdk::RGBA::new(0.0, 0.0, 0.0, 0.0));
-    }
+    // Colors
+    set_color_button(&widgets.color_button, template.color.as_deref());
+    set_color_button(&widgets.title_color_button, template.title_color.as_deref());
 
     widgets
         .theme_entry
@@ -966,18 +981,9 @@ fn save_widgets_to_template(widgets: &TemplateWidgets, template: &mut StickyTabC
         Some(git_remote)
     };
 
-    // Color
-    let rgba = widgets.color_button.rgba();
-    if rgba.alpha() < 0.1 {
-        template.color = None;
-    } else {
-        template.color = Some(format!(
-            "#{:02X}{:02X}{:02X}",
-            (rgba.red() * 255.0) as u8,
-            (rgba.green() * 255.0) as u8,
-            (rgba.blue() * 255.0) as u8
-        ));
-    }
+    // Colors
+    template.color = color_button_hex(&widgets.color_button);
+    template.title_color = color_button_hex(&widgets.title_color_button);
 
     let theme = widgets.theme_entry.text().to_string();
     template.theme = if theme.is_empty() { None } else { Some(theme) };
@@ -1110,6 +1116,33 @@ fn create_preset_template(preset_index: usize) -> Option<StickyTabConfig> {
     }
 }
 
+/// Show a template color, or a transparent swatch when unset
+fn set_color_button(button: &ColorButton, hex: Option<&str>) {
+    match hex {
+        Some(hex) => {
+            if let Some(rgba) = parse_hex_color(hex) {
+                button.set_rgba(&rgba);
+            }
+        }
+        None => button.set_rgba(&gtk4::gdk::RGBA::new(0.0, 0.0, 0.0, 0.0)),
+    }
+}
+
+/// Read a color button as hex; a cleared (transparent) swatch means unset
+fn color_button_hex(button: &ColorButton) -> Option<String> {
+    let rgba = button.rgba();
+    if rgba.alpha() < 0.1 {
+        None
+    } else {
+        Some(format!(
+            "#{:02X}{:02X}{:02X}",
+            (rgba.red() * 255.0) as u8,
+            (rgba.green() * 255.0) as u8,
+            (rgba.blue() * 255.0) as u8
+        ))
+    }
+}
+
 fn parse_hex_color(hex: &str) -> Option<gtk4::gdk::RGBA> {
     let hex = hex.trim_start_matches('#');
     if hex.len() != 6 {
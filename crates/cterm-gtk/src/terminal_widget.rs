@@ -1,10 +1,12 @@
 //! Terminal rendering widget using Cairo
 
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
 use std::io::Read;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use gtk4::prelude::*;
 use gtk4::{
@@ -12,14 +14,22 @@ use gtk4::{
 };
 use parking_lot::Mutex;
 
-use cterm_app::config::{Config, StickyTabConfig};
+use cterm_app::config::{
+    badge_color, ruler_color, ruler_positions, AppearanceConfig, BackgroundImageMode, Config,
+    CursorLineHighlight, GeneralConfig, ScrollPositionIndicator, StickyTabConfig,
+};
+use cterm_app::recording::RecordingToggle;
+use cterm_app::replay::Replay;
 use cterm_app::upgrade::TerminalUpgradeState;
 use cterm_core::cell::CellAttrs;
-use cterm_core::color::{Color, Rgb};
-use cterm_core::pty::{PtyConfig, PtyError};
-use cterm_core::screen::{ClipboardOperation, CursorStyle, ScreenConfig};
-use cterm_core::term::{Key, Modifiers, Terminal, TerminalEvent};
-use cterm_ui::theme::Theme;
+use cterm_core::color::{Color, ColorPalette, Rgb};
+use cterm_core::encoding::TerminalEncoding;
+use cterm_core::pty::{PtyConfig, PtyError, PtySize};
+use cterm_core::screen::{CellRegion, ClipboardOperation, CursorStyle, ScreenConfig};
+use cterm_core::term::{Key, Modifiers, SelectionClearTrigger, Terminal, TerminalEvent};
+use cterm_ui::minimap::{minimap_line_at, minimap_viewport, Minimap, MINIMAP_WIDTH};
+use cterm_ui::theme::{estimated_cell_size, FontConfig, FontSubstitution, Theme, SYSTEM_MONOSPACE};
+use cterm_ui::utils::{selection_autoscroll_lines, ClickCounter, SELECTION_AUTOSCROLL_INTERVAL};
 
 /// Cell dimensions calculated from font metrics
 #[derive(Debug, Clone, Copy)]
@@ -28,35 +38,50 @@ pub struct CellDimensions {
     pub height: f64,
 }
 
+thread_local! {
+    /// Decoded background images by path, shared by all terminals
+    static BACKGROUND_IMAGES: RefCell<HashMap<PathBuf, Option<cairo::ImageSurface>>> =
+        RefCell::new(HashMap::new());
+}
+
 /// Callback type for terminal events
 type EventCallback = Rc<RefCell<Option<Box<dyn Fn()>>>>;
 /// Callback type for title change events
-type TitleCallback = Rc<RefCell<Option<Box<dyn Fn(&str)>>>>;
+type TitleCallback = Rc<RefCell<Option<Box<dyn Fn(&str, Option<&Path>)>>>>;
 /// Callback type for file transfer events
 type FileTransferCallback = Rc<RefCell<Option<Box<dyn Fn(cterm_core::FileTransferOperation)>>>>;
+/// Callback type for finished commands, with their exit code if reported
+type CommandFinishedCallback = Rc<RefCell<Option<Box<dyn Fn(Option<i32>)>>>>;
 
 /// Terminal widget wrapping GTK drawing area
 pub struct TerminalWidget {
     drawing_area: DrawingArea,
     terminal: Arc<Mutex<Terminal>>,
     theme: Theme,
+    /// Configuration snapshot taken when the widget was created
+    config: Config,
     font_family: String,
     font_size: Rc<RefCell<f64>>,
     default_font_size: f64,
     cell_dims: Rc<RefCell<CellDimensions>>,
     /// Optional background color override (from template)
     background_override: Rc<RefCell<Option<cterm_core::color::Rgb>>>,
+    /// Scrollback minimap drawn on the right edge, if enabled
+    minimap: Option<Rc<RefCell<Minimap>>>,
     on_exit: EventCallback,
     on_bell: EventCallback,
     on_title_change: TitleCallback,
     on_file_transfer: FileTransferCallback,
+    on_command_finished: CommandFinishedCallback,
+    /// Sender the PTY output is handled through, for replaying captures
+    output_tx: Rc<RefCell<Option<std::sync::mpsc::Sender<PtyMessage>>>>,
 }
 
 impl TerminalWidget {
     /// Create a new terminal widget
     pub fn new(config: &Config, theme: &Theme) -> Result<Self, PtyError> {
         // Get font settings
-        let font_family = config.appearance.font.family.clone();
+        let (font_family, _) = resolve_font_family(&config.appearance.font);
         let font_size = config.appearance.font.size;
 
         // Calculate cell dimensions using Pango font metrics
@@ -81,10 +106,16 @@ impl TerminalWidget {
 
         // Create terminal
         let screen_config = ScreenConfig {
-            scrollback_lines: config.general.scrollback_lines,
+            scrollback_lines: config.general.effective_scrollback_lines(),
         };
 
         let pty_config = PtyConfig {
+            size: PtySize::with_cell_size(
+                rows as u16,
+                cols as u16,
+                cell_dims.width,
+                cell_dims.height,
+            ),
             shell: config.general.default_shell.clone(),
             args: config.general.shell_args.clone(),
             cwd: config.general.working_directory.clone(),
@@ -95,7 +126,8 @@ impl TerminalWidget {
                 .map(|(k, v)| (k.clone(), v.clone()))
                 .collect(),
             term: config.general.term.clone(),
-            ..Default::default()
+            advertise_truecolor: config.general.advertise_truecolor,
+            raw: config.general.raw_pty,
         };
 
         let terminal = Terminal::with_shell(cols, rows, screen_config, &pty_config)?;
@@ -107,15 +139,22 @@ impl TerminalWidget {
             drawing_area: drawing_area.clone(),
             terminal: Arc::clone(&terminal),
             theme: theme.clone(),
+            config: config.clone(),
             font_family,
             font_size: Rc::new(RefCell::new(font_size)),
             default_font_size: font_size,
             cell_dims,
             background_override: Rc::new(RefCell::new(None)),
+            minimap: config
+                .appearance
+                .minimap
+                .then(|| Rc::new(RefCell::new(Minimap::new()))),
             on_exit: Rc::new(RefCell::new(None)),
             on_bell: Rc::new(RefCell::new(None)),
             on_title_change: Rc::new(RefCell::new(None)),
             on_file_transfer: Rc::new(RefCell::new(None)),
+            on_command_finished: Rc::new(RefCell::new(None)),
+            output_tx: Rc::new(RefCell::new(None)),
         };
 
         // Set up drawing
@@ -143,7 +182,7 @@ impl TerminalWidget {
         cwd: Option<String>,
     ) -> Result<Self, PtyError> {
         // Get font settings
-        let font_family = config.appearance.font.family.clone();
+        let (font_family, _) = resolve_font_family(&config.appearance.font);
         let font_size = config.appearance.font.size;
 
         // Calculate cell dimensions using Pango font metrics
@@ -168,7 +207,7 @@ impl TerminalWidget {
 
         // Create terminal
         let screen_config = ScreenConfig {
-            scrollback_lines: config.general.scrollback_lines,
+            scrollback_lines: config.general.effective_scrollback_lines(),
         };
 
         // Use provided cwd or fall back to config setting
@@ -177,6 +216,12 @@ impl TerminalWidget {
             .or_else(|| config.general.working_directory.clone());
 
         let pty_config = PtyConfig {
+            size: PtySize::with_cell_size(
+                rows as u16,
+                cols as u16,
+                cell_dims.width,
+                cell_dims.height,
+            ),
             shell: config.general.default_shell.clone(),
             args: config.general.shell_args.clone(),
             cwd: effective_cwd,
@@ -187,7 +232,8 @@ impl TerminalWidget {
                 .map(|(k, v)| (k.clone(), v.clone()))
                 .collect(),
             term: config.general.term.clone(),
-            ..Default::default()
+            advertise_truecolor: config.general.advertise_truecolor,
+            raw: config.general.raw_pty,
         };
 
         let terminal = Terminal::with_shell(cols, rows, screen_config, &pty_config)?;
@@ -199,15 +245,22 @@ impl TerminalWidget {
             drawing_area: drawing_area.clone(),
             terminal: Arc::clone(&terminal),
             theme: theme.clone(),
+            config: config.clone(),
             font_family,
             font_size: Rc::new(RefCell::new(font_size)),
             default_font_size: font_size,
             cell_dims,
             background_override: Rc::new(RefCell::new(None)),
+            minimap: config
+                .appearance
+                .minimap
+                .then(|| Rc::new(RefCell::new(Minimap::new()))),
             on_exit: Rc::new(RefCell::new(None)),
             on_bell: Rc::new(RefCell::new(None)),
             on_title_change: Rc::new(RefCell::new(None)),
             on_file_transfer: Rc::new(RefCell::new(None)),
+            on_command_finished: Rc::new(RefCell::new(None)),
+            output_tx: Rc::new(RefCell::new(None)),
         };
 
         // Set up drawing
@@ -234,8 +287,13 @@ impl TerminalWidget {
         theme: &Theme,
         template: &StickyTabConfig,
     ) -> Result<Self, PtyError> {
+        let mut config = config.clone();
+        config.general.no_scrollback |= template.no_scrollback;
+        let config = &config;
+        let theme = &template.resolve_theme(theme);
+
         // Get font settings
-        let font_family = config.appearance.font.family.clone();
+        let (font_family, _) = resolve_font_family(&config.appearance.font);
         let font_size = config.appearance.font.size;
 
         // Calculate cell dimensions using Pango font metrics
@@ -260,13 +318,19 @@ impl TerminalWidget {
 
         // Create terminal with template settings
         let screen_config = ScreenConfig {
-            scrollback_lines: config.general.scrollback_lines,
+            scrollback_lines: config.general.effective_scrollback_lines(),
         };
 
         // Get command and args from template
         let (command, args) = template.get_command_args();
 
         let pty_config = PtyConfig {
+            size: PtySize::with_cell_size(
+                rows as u16,
+                cols as u16,
+                cell_dims.width,
+                cell_dims.height,
+            ),
             shell: command,
             args,
             cwd: template.working_directory.clone(),
@@ -276,6 +340,7 @@ impl TerminalWidget {
                 .map(|(k, v)| (k.clone(), v.clone()))
                 .collect(),
             term: config.general.term.clone(),
+            advertise_truecolor: config.general.advertise_truecolor,
             ..Default::default()
         };
 
@@ -288,15 +353,22 @@ impl TerminalWidget {
             drawing_area: drawing_area.clone(),
             terminal: Arc::clone(&terminal),
             theme: theme.clone(),
+            config: config.clone(),
             font_family,
             font_size: Rc::new(RefCell::new(font_size)),
             default_font_size: font_size,
             cell_dims,
             background_override: Rc::new(RefCell::new(None)),
+            minimap: config
+                .appearance
+                .minimap
+                .then(|| Rc::new(RefCell::new(Minimap::new()))),
             on_exit: Rc::new(RefCell::new(None)),
             on_bell: Rc::new(RefCell::new(None)),
             on_title_change: Rc::new(RefCell::new(None)),
             on_file_transfer: Rc::new(RefCell::new(None)),
+            on_command_finished: Rc::new(RefCell::new(None)),
+            output_tx: Rc::new(RefCell::new(None)),
         };
 
         // Set up drawing
@@ -329,7 +401,7 @@ impl TerminalWidget {
     #[cfg(unix)]
     pub fn from_restored(terminal: Terminal, config: &Config, theme: &Theme) -> Self {
         // Get font settings
-        let font_family = config.appearance.font.family.clone();
+        let (font_family, _) = resolve_font_family(&config.appearance.font);
         let font_size = config.appearance.font.size;
 
         // Calculate cell dimensions using Pango font metrics
@@ -355,15 +427,22 @@ impl TerminalWidget {
             drawing_area: drawing_area.clone(),
             terminal: Arc::clone(&terminal),
             theme: theme.clone(),
+            config: config.clone(),
             font_family,
             font_size: Rc::new(RefCell::new(font_size)),
             default_font_size: font_size,
             cell_dims,
             background_override: Rc::new(RefCell::new(None)),
+            minimap: config
+                .appearance
+                .minimap
+                .then(|| Rc::new(RefCell::new(Minimap::new()))),
             on_exit: Rc::new(RefCell::new(None)),
             on_bell: Rc::new(RefCell::new(None)),
             on_title_change: Rc::new(RefCell::new(None)),
             on_file_transfer: Rc::new(RefCell::new(None)),
+            on_command_finished: Rc::new(RefCell::new(None)),
+            output_tx: Rc::new(RefCell::new(None)),
         };
 
         // Set up drawing
@@ -387,29 +466,7 @@ impl TerminalWidget {
     /// Export terminal state for seamless upgrade
     #[cfg(unix)]
     pub fn export_state(&self) -> TerminalUpgradeState {
-        let term = self.terminal.lock();
-        let screen = term.screen();
-
-        TerminalUpgradeState {
-            cols: screen.grid().width(),
-            rows: screen.grid().height(),
-            grid: screen.grid().clone(),
-            scrollback: screen.scrollback().iter().cloned().collect(),
-            scrollback_file: None,
-            alternate_grid: screen.alternate_grid().cloned(),
-            cursor: screen.cursor.clone(),
-            saved_cursor: screen.saved_cursor().cloned(),
-            alt_saved_cursor: screen.alt_saved_cursor().cloned(),
-            scroll_region: *screen.scroll_region(),
-            style: screen.style.clone(),
-            modes: screen.modes.clone(),
-            title: screen.title.clone(),
-            scroll_offset: screen.scroll_offset,
-            tab_stops: screen.tab_stops().to_vec(),
-            alternate_active: screen.alternate_grid().is_some(),
-            cursor_style: screen.cursor.style,
-            mouse_mode: screen.modes.mouse_mode,
-        }
+        TerminalUpgradeState::from_screen(self.terminal.lock().screen())
     }
 
     /// Get the widget for adding to containers
@@ -433,8 +490,9 @@ impl TerminalWidget {
         *self.on_bell.borrow_mut() = Some(Box::new(callback));
     }
 
-    /// Set callback for when the terminal title changes
-    pub fn set_on_title_change<F: Fn(&str) + 'static>(&self, callback: F) {
+    /// Set callback for when the terminal title changes, called with the
+    /// title and the directory titles can show
+    pub fn set_on_title_change<F: Fn(&str, Option<&Path>) + 'static>(&self, callback: F) {
         *self.on_title_change.borrow_mut() = Some(Box::new(callback));
     }
 
@@ -446,17 +504,34 @@ impl TerminalWidget {
         *self.on_file_transfer.borrow_mut() = Some(Box::new(callback));
     }
 
+    /// Set callback for when a command finishes (OSC 133 D), called with
+    /// its exit code if the shell reported one
+    pub fn set_on_command_finished<F: Fn(Option<i32>) + 'static>(&self, callback: F) {
+        *self.on_command_finished.borrow_mut() = Some(Box::new(callback));
+    }
+
     /// Get the terminal for file transfer operations
     pub fn terminal(&self) -> &Arc<Mutex<Terminal>> {
         &self.terminal
     }
 
-    /// Get the current working directory of the foreground process (if any)
+    /// Whether this is an incognito terminal (no scrollback, never saved)
+    pub fn is_incognito(&self) -> bool {
+        self.config.general.no_scrollback
+    }
+
+    /// Start recording the session to a new file, or stop the recording
+    pub fn toggle_recording(&self, general: &GeneralConfig) -> std::io::Result<RecordingToggle> {
+        cterm_app::recording::toggle(&mut self.terminal.lock(), general)
+    }
+
+    /// Get the directory to start new shells in: the foreground process's,
+    /// or else the one reported by the shell (OSC 7)
     #[cfg(unix)]
-    pub fn foreground_cwd(&self) -> Option<String> {
+    pub fn working_directory(&self) -> Option<String> {
         self.terminal
             .lock()
-            .foreground_cwd()
+            .working_directory()
             .map(|p| p.to_string_lossy().into_owned())
     }
 
@@ -472,6 +547,14 @@ impl TerminalWidget {
         self.terminal.lock().foreground_process_name()
     }
 
+    /// Write raw bytes to the terminal (for the "Send Bytes" debug action)
+    pub fn write_bytes(&self, bytes: &[u8]) {
+        let mut term = self.terminal.lock();
+        if let Err(e) = term.write(bytes) {
+            log::error!("Failed to write to terminal: {}", e);
+        }
+    }
+
     /// Write a string to the terminal (for paste operations)
     pub fn write_str(&self, s: &str) {
         let mut term = self.terminal.lock();
@@ -480,6 +563,17 @@ impl TerminalWidget {
         }
     }
 
+    /// Write pasted text to the terminal, using bracketed paste if the
+    /// program enabled it
+    pub fn paste_str(&self, text: &str) {
+        let mut term = self.terminal.lock();
+        let text = self.config.general.quote_pasted_path(text, &term);
+        let text = self.config.general.bracketed_paste_text(&text, &term);
+        if let Err(e) = term.write_str(&text) {
+            log::error!("Failed to write to terminal: {}", e);
+        }
+    }
+
     /// Set an optional background color override (hex string like "#1a1b26")
     pub fn set_background_override(&self, color: Option<&str>) {
         let rgb = color.and_then(|hex| {
@@ -527,7 +621,11 @@ impl TerminalWidget {
 
     /// Update cell dimensions after font size change
     fn update_cell_dimensions(&self, font_size: f64) {
-        let new_dims = calculate_cell_dimensions(&self.font_family, font_size);
+        let new_dims = measure_cell_dimensions(
+            &self.drawing_area.pango_context(),
+            &self.font_family,
+            font_size,
+        );
         *self.cell_dims.borrow_mut() = new_dims;
     }
 
@@ -577,27 +675,48 @@ impl TerminalWidget {
         }
     }
 
-    /// Search for text in terminal buffer (scrollback + visible)
-    ///
-    /// Returns the number of matches found. If matches are found, scrolls to the first match.
-    pub fn find(&self, pattern: &str, case_sensitive: bool, regex: bool) -> usize {
-        let term = self.terminal.lock();
-        let results = term.find(pattern, case_sensitive, regex);
-        let count = results.len();
-
-        if let Some(first) = results.first() {
-            // Need to release the lock before we can take mutable lock
-            let line_idx = first.line;
-            drop(term);
+    /// Get the scrollback lines matching a query, oldest first
+    pub fn extract_matches(&self, pattern: &str, case_sensitive: bool, regex: bool) -> Vec<String> {
+        self.terminal
+            .lock()
+            .screen()
+            .extract_matches(pattern, case_sensitive, regex)
+    }
 
-            let mut term = self.terminal.lock();
-            term.scroll_to_line(line_idx);
-            self.drawing_area.queue_draw();
+    /// Search for text in terminal buffer (scrollback + visible, or the
+    /// scrollback only if `scrollback_only`)
+    ///
+    /// Returns the number of matches found. All matches are highlighted and the
+    /// view scrolls to the current one; repeating the search moves to the next match.
+    pub fn find(
+        &self,
+        pattern: &str,
+        case_sensitive: bool,
+        regex: bool,
+        scrollback_only: bool,
+    ) -> usize {
+        let mut term = self.terminal.lock();
+        term.screen_mut()
+            .set_search_scrollback_only(scrollback_only);
+        let current = term
+            .screen_mut()
+            .search_next(pattern, case_sensitive, regex);
+        if let Some(current) = current {
+            term.scroll_to_line(current.line);
         }
+        let count = term.screen().search_match_count();
+        drop(term);
 
+        self.drawing_area.queue_draw();
         count
     }
 
+    /// Stop highlighting search matches
+    pub fn clear_search(&self) {
+        self.terminal.lock().screen_mut().clear_search();
+        self.drawing_area.queue_draw();
+    }
+
     /// Search and return all matches (for iteration/highlighting)
     #[allow(dead_code)]
     pub fn find_all(
@@ -638,7 +757,7 @@ impl TerminalWidget {
         let (visible_row, col) = self.pixel_to_cell(x, y);
         let term = self.terminal.lock();
         let absolute_line = term.screen().visible_row_to_absolute_line(visible_row);
-        (absolute_line, col)
+        (absolute_line, term.screen().logical_col(absolute_line, col))
     }
 
     /// Start a new selection at the given pixel coordinates
@@ -678,19 +797,87 @@ impl TerminalWidget {
     }
 
     /// Copy the current selection to clipboard
+    ///
+    /// Asks first when the selection contains a masked secret.
     pub fn copy_selection(&self) {
-        if let Some(text) = self.get_selected_text() {
+        let Some(text) = self.get_selected_text() else {
+            return;
+        };
+        let incognito = self.is_incognito();
+        let copy = move || {
             if let Some(display) = gdk::Display::default() {
                 let clipboard = display.clipboard();
                 clipboard.set_text(&text);
             }
+            cterm_app::clipboard_history::record(&text, incognito);
+        };
+
+        if self.terminal.lock().screen().selection_has_secret() {
+            let parent = self.drawing_area.root().and_downcast::<gtk4::Window>();
+            crate::dialogs::confirm_copy_secret(parent.as_ref(), copy);
+        } else {
+            copy();
+        }
+    }
+
+    /// Copy the output of the last finished command (OSC 133 marks) to clipboard
+    pub fn copy_last_command_output(&self) {
+        let output = self.terminal.lock().screen().last_command_output();
+        match output {
+            Some(text) => {
+                if let Some(display) = gdk::Display::default() {
+                    display.clipboard().set_text(&text);
+                }
+                cterm_app::clipboard_history::record(&text, self.is_incognito());
+            }
+            None => log::debug!("No command output to copy (shell integration not active?)"),
+        }
+    }
+
+    /// Run the last finished command (OSC 133 marks) again
+    pub fn rerun_last_command(&self) {
+        let mut term = self.terminal.lock();
+        match term.rerun_last_command() {
+            Ok(true) => {}
+            Ok(false) => log::debug!("No command to rerun (shell integration not active?)"),
+            Err(e) => log::error!("Failed to write to terminal: {}", e),
+        }
+    }
+
+    /// Set the character encoding of the program's output and input
+    pub fn set_encoding(&self, encoding: TerminalEncoding) {
+        self.terminal.lock().set_encoding(encoding);
+        log::info!("Encoding set to {}", encoding.label());
+    }
+
+    /// Delete the output of the last command, keeping earlier history
+    pub fn clear_to_last_prompt(&self) {
+        if self.terminal.lock().screen_mut().clear_to_last_prompt() {
+            self.drawing_area.queue_draw();
+        } else {
+            log::debug!("No command output to clear (shell integration not active?)");
+        }
+    }
+
+    /// Toggle a scrollback bookmark at the current position
+    pub fn set_mark(&self) {
+        self.terminal.lock().screen_mut().toggle_bookmark();
+        self.drawing_area.queue_draw();
+    }
+
+    /// Scroll to the previous scrollback bookmark
+    pub fn jump_to_mark(&self) {
+        if self.terminal.lock().screen_mut().jump_to_bookmark() {
+            self.drawing_area.queue_draw();
         }
     }
 
     /// Copy the current selection to clipboard as HTML
     pub fn copy_selection_html(&self) {
         let term = self.terminal.lock();
-        let html = term.screen().get_selected_html(&self.theme.colors);
+        let html = term
+            .screen()
+            .get_selected_html(&self.theme.colors, self.config.appearance.faint_opacity);
         let text = term.screen().get_selected_text();
         drop(term);
 
@@ -706,6 +893,57 @@ impl TerminalWidget {
         }
     }
 
+    /// Render the selection, or the visible screen when nothing is
+    /// selected, to a PNG with the terminal's colors and font
+    pub fn render_image(&self) -> Option<Vec<u8>> {
+        let term = self.terminal.lock();
+        let screen = term.screen();
+        let region = screen
+            .selected_region()
+            .unwrap_or_else(|| screen.visible_region());
+        let theme = self.theme.with_color_overrides(screen.color_overrides());
+        let background_override = *self.background_override.borrow();
+        let bg = background_override
+            .filter(|_| screen.color_overrides().background.is_none())
+            .unwrap_or(theme.colors.background);
+        drop(term);
+
+        if region.is_empty() {
+            return None;
+        }
+        let result = render_region_png(
+            &region,
+            &theme,
+            bg,
+            &self.font_family,
+            *self.font_size.borrow(),
+            *self.cell_dims.borrow(),
+            self.config.appearance.faint_opacity,
+            self.drawing_area.scale_factor() as f64,
+        );
+        match result {
+            Ok(png) => Some(png),
+            Err(e) => {
+                log::error!("Failed to render image: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Copy the selection, or the visible screen, to the clipboard as a PNG
+    pub fn copy_as_image(&self) {
+        let Some(png) = self.render_image() else {
+            return;
+        };
+        if let Some(display) = gdk::Display::default() {
+            let provider =
+                gdk::ContentProvider::for_bytes("image/png", &glib::Bytes::from_owned(png));
+            if let Err(e) = display.clipboard().set_content(Some(&provider)) {
+                log::error!("Failed to copy image: {}", e);
+            }
+        }
+    }
+
     /// Select all text in the terminal
     pub fn select_all(&self) {
         let mut term = self.terminal.lock();
@@ -734,51 +972,34 @@ impl TerminalWidget {
         }
     }
 
-    /// Paste from primary selection (Unix middle-click paste)
-    #[cfg(unix)]
-    #[allow(dead_code)]
-    pub fn paste_primary(&self) {
-        let Some(display) = gdk::Display::default() else {
-            return;
-        };
-        let primary = display.primary_clipboard();
-        let terminal = Arc::clone(&self.terminal);
-        let drawing_area = self.drawing_area.clone();
-
-        primary.read_text_async(None::<&gio::Cancellable>, move |result| {
-            if let Ok(Some(text)) = result {
-                let mut term = terminal.lock();
-                // Use bracketed paste if enabled
-                let paste_text = if term.screen().modes.bracketed_paste {
-                    format!("\x1b[200~{}\x1b[201~", text)
-                } else {
-                    text.to_string()
-                };
-                let _ = term.write_str(&paste_text);
-                drawing_area.queue_draw();
-            }
-        });
-    }
-
     /// Trigger a resize to recalculate terminal dimensions
     fn trigger_resize(&self) {
         // Force a resize by getting current size
         let width = self.drawing_area.width();
         let height = self.drawing_area.height();
 
-        let dims = self.cell_dims.borrow();
-        let cols = ((width as f64) / dims.width).floor() as usize;
+        let dims = *self.cell_dims.borrow();
+        let cols = ((width as f64 - self.minimap_width()) / dims.width).floor() as usize;
         let rows = ((height as f64) / dims.height).floor() as usize;
-        drop(dims);
 
         if cols > 0 && rows > 0 {
             let mut term = self.terminal.lock();
+            term.set_cell_size(dims.width, dims.height);
             term.resize(cols, rows);
         }
 
         self.drawing_area.queue_draw();
     }
 
+    /// Width taken from the terminal by the minimap strip
+    fn minimap_width(&self) -> f64 {
+        if self.minimap.is_some() {
+            MINIMAP_WIDTH
+        } else {
+            0.0
+        }
+    }
+
     /// Set up the draw function
     fn setup_drawing(&self) {
         let terminal = Arc::clone(&self.terminal);
@@ -787,9 +1008,16 @@ impl TerminalWidget {
         let font_size = Rc::clone(&self.font_size);
         let cell_dims = Rc::clone(&self.cell_dims);
         let background_override = Rc::clone(&self.background_override);
+        let cursor_line = self.config.appearance.highlight_cursor_line;
+        let show_badge = self.config.appearance.show_badge;
+        let faint_opacity = self.config.appearance.faint_opacity;
+        let scroll_position = self.config.appearance.show_scroll_position;
+        let ruler_columns = self.config.appearance.ruler_columns.clone();
+        let minimap = self.minimap.clone();
+        let background_image = BackgroundImage::from_config(&self.config.appearance);
 
         self.drawing_area
-            .set_draw_func(move |_area, cr, _width, _height| {
+            .set_draw_func(move |_area, cr, width, height| {
                 let font_size = *font_size.borrow();
                 let dims = *cell_dims.borrow();
                 let bg_override = *background_override.borrow();
@@ -801,7 +1029,23 @@ impl TerminalWidget {
                     font_size,
                     dims,
                     bg_override,
+                    background_image.as_ref(),
+                    cursor_line,
+                    show_badge,
+                    faint_opacity,
+                    scroll_position,
+                    &ruler_columns,
                 );
+                if let Some(minimap) = &minimap {
+                    draw_minimap(
+                        cr,
+                        &terminal,
+                        &theme,
+                        &mut minimap.borrow_mut(),
+                        width as f64,
+                        height as f64,
+                    );
+                }
             });
     }
 
@@ -810,11 +1054,79 @@ impl TerminalWidget {
         let terminal = Arc::clone(&self.terminal);
         let cell_dims = Rc::clone(&self.cell_dims);
 
+        {
+            let mut term = terminal.lock();
+            term.set_erase_sequences(
+                self.config.general.backspace_sends,
+                self.config.general.delete_sends,
+            );
+            term.set_home_end_style(self.config.general.home_end_style);
+            term.set_encoding(self.config.general.encoding);
+            term.set_local_echo(
+                self.config.general.local_echo,
+                Duration::from_millis(self.config.general.local_echo_threshold_ms),
+            );
+            term.screen_mut()
+                .set_inline_suggestions(self.config.general.inline_suggestions);
+            term.screen_mut()
+                .set_bidi(self.config.appearance.enable_bidi);
+            term.screen_mut()
+                .set_copy_hyperlink_mode(self.config.general.copy_hyperlink_mode);
+            term.screen_mut()
+                .set_tab_width(self.config.general.tab_width);
+            term.screen_mut()
+                .set_long_line_mode(self.config.general.long_line_mode);
+            term.screen_mut()
+                .set_grapheme_clusters(self.config.general.grapheme_clusters);
+            term.screen_mut()
+                .set_secret_matcher(self.config.general.secret_matcher());
+            term.screen_mut()
+                .set_reveal_concealed(self.config.general.reveal_concealed);
+            term.set_unknown_sequence_mode(self.config.general.unknown_sequence_mode);
+            term.set_print_control_as_caret(self.config.general.print_control_as_caret);
+            term.set_max_file_transfer_bytes(self.config.general.file_transfer_limit());
+            term.set_search_follows_output(self.config.general.search_follows_output);
+            term.set_cursor_visibility_per_screen(self.config.general.cursor_visibility_per_screen);
+            term.set_max_images(self.config.general.max_scrollback_images);
+            term.set_scroll_to_bottom_on(&self.config.general.scroll_to_bottom_on);
+            term.set_clear_selection_on(self.config.general.clear_selection_on);
+        }
+
         // Keyboard input
         let key_controller = EventControllerKey::new();
         let terminal_key = Arc::clone(&terminal);
+        let area_key = self.drawing_area.clone();
+
+        // Input method for dead keys and compose sequences. It commits the
+        // characters the keyboard layout produces; Ctrl/Alt combinations and
+        // special keys aren't filtered and still reach the handler below.
+        let im_context = gtk4::IMMulticontext::new();
+        im_context.set_client_widget(Some(&self.drawing_area));
+        let terminal_im = Arc::clone(&terminal);
+        let area_im = self.drawing_area.clone();
+        im_context.connect_commit(move |_, text| {
+            let mut term = terminal_im.lock();
+            if term.clear_selection_for(SelectionClearTrigger::Keypress) {
+                area_im.queue_draw();
+            }
+            if let Err(e) = term.write(text.as_bytes()) {
+                log::error!("Failed to write to PTY: {}", e);
+            }
+            // Draw predicted echo now rather than with the next output
+            if term.screen().local_echo() {
+                area_im.queue_draw();
+            }
+        });
+        key_controller.set_im_context(Some(&im_context));
 
-        key_controller.connect_key_pressed(move |_, keyval, _keycode, state| {
+        let focus_controller = gtk4::EventControllerFocus::new();
+        let im_context_enter = im_context.clone();
+        focus_controller.connect_enter(move |_| im_context_enter.focus_in());
+        focus_controller.connect_leave(move |_| im_context.focus_out());
+        self.drawing_area.add_controller(focus_controller);
+
+        key_controller.connect_key_pressed(move |controller, keyval, _keycode, state| {
+            let state = unconsumed_state(controller, state);
             let modifiers = gtk_state_to_modifiers(state);
             let has_ctrl = state.contains(gdk::ModifierType::CONTROL_MASK);
             let has_alt = state.contains(gdk::ModifierType::ALT_MASK);
@@ -825,14 +1137,46 @@ impl TerminalWidget {
                 return glib::Propagation::Proceed;
             }
 
+            // Holding Shift to extend the selection doesn't clear it
+            let is_modifier = matches!(
+                keyval,
+                gdk::Key::Shift_L
+                    | gdk::Key::Shift_R
+                    | gdk::Key::Control_L
+                    | gdk::Key::Control_R
+                    | gdk::Key::Alt_L
+                    | gdk::Key::Alt_R
+                    | gdk::Key::Super_L
+                    | gdk::Key::Super_R
+                    | gdk::Key::Meta_L
+                    | gdk::Key::Meta_R
+            );
+            if !is_modifier
+                && terminal_key
+                    .lock()
+                    .clear_selection_for(SelectionClearTrigger::Keypress)
+            {
+                area_key.queue_draw();
+            }
+
+            // Keypad digits and operators only differ from the main keys in
+            // application keypad mode; otherwise they go through text input
+            let key = keyval_to_key(keyval).or_else(|| {
+                let app_keypad = terminal_key.lock().screen().modes.application_keypad;
+                app_keypad.then(|| keypad_keyval_to_key(keyval)).flatten()
+            });
+
             // Handle special keys (arrows, function keys, etc.)
-            if let Some(key) = keyval_to_key(keyval) {
+            if let Some(key) = key {
                 let mut term = terminal_key.lock();
                 if let Some(bytes) = term.handle_key(key, modifiers) {
                     if let Err(e) = term.write(&bytes) {
                         log::error!("Failed to write to PTY: {}", e);
                     }
                 }
+                if term.screen().local_echo() {
+                    area_key.queue_draw();
+                }
                 return glib::Propagation::Stop;
             }
 
@@ -882,6 +1226,9 @@ impl TerminalWidget {
                     if let Err(e) = term.write(s.as_bytes()) {
                         log::error!("Failed to write to PTY: {}", e);
                     }
+                    if term.screen().local_echo() {
+                        area_key.queue_draw();
+                    }
                     return glib::Propagation::Stop;
                 }
             }
@@ -902,10 +1249,34 @@ impl TerminalWidget {
         let cell_dims_click = Rc::clone(&cell_dims);
         let drawing_area_click = self.drawing_area.clone();
         let selecting_pressed = Rc::clone(&selecting);
+        // Whether the button went down in the minimap (dragging scrolls)
+        let minimap_dragging = Rc::new(Cell::new(false));
+        let minimap_pressed = self.minimap.is_some().then(|| Rc::clone(&minimap_dragging));
+
+        // Use our own click counting when a custom multi-click interval is configured
+        let click_counter = self
+            .config
+            .general
+            .multi_click_ms
+            .map(|ms| RefCell::new(ClickCounter::new(Duration::from_millis(ms))));
 
         click_controller.connect_pressed(move |_, n_press, x, y| {
             drawing_area_click.grab_focus();
 
+            if let Some(ref dragging) = minimap_pressed {
+                if x >= drawing_area_click.width() as f64 - MINIMAP_WIDTH {
+                    dragging.set(true);
+                    minimap_jump(&terminal_click, y, drawing_area_click.height() as f64);
+                    drawing_area_click.queue_draw();
+                    return;
+                }
+            }
+
+            let n_press = match click_counter {
+                Some(ref counter) => counter.borrow_mut().click(x, y, Instant::now()) as i32,
+                None => n_press,
+            };
+
             // Determine selection mode based on click count
             let mode = match n_press {
                 2 => cterm_core::SelectionMode::Word,
@@ -921,6 +1292,7 @@ impl TerminalWidget {
 
             let mut term = terminal_click.lock();
             let line = term.screen().visible_row_to_absolute_line(row);
+            let col = term.screen().logical_col(line, col);
             term.screen_mut().start_selection(line, col, mode);
             drop(term);
 
@@ -931,9 +1303,11 @@ impl TerminalWidget {
         let terminal_released = Arc::clone(&terminal);
         let drawing_area_released = self.drawing_area.clone();
         let selecting_released = Rc::clone(&selecting);
+        let minimap_released = Rc::clone(&minimap_dragging);
 
         click_controller.connect_released(move |_, _n_press, _x, _y| {
             *selecting_released.borrow_mut() = false;
+            minimap_released.set(false);
 
             // Check if selection is empty (same start and end) and clear it
             // Only clear char/block selections - word/line selections are never "empty"
@@ -971,30 +1345,13 @@ impl TerminalWidget {
             let middle_click_controller = GestureClick::new();
             middle_click_controller.set_button(gdk::BUTTON_MIDDLE);
 
-            let terminal_middle = Arc::clone(&terminal);
             let drawing_area_middle = self.drawing_area.clone();
 
+            // The window's paste-primary action pastes like Ctrl+Shift+V
             middle_click_controller.connect_pressed(move |_, _n_press, _x, _y| {
-                let Some(display) = gdk::Display::default() else {
-                    return;
-                };
-                let primary = display.primary_clipboard();
-                let terminal = Arc::clone(&terminal_middle);
-                let drawing_area = drawing_area_middle.clone();
-
-                primary.read_text_async(None::<&gio::Cancellable>, move |result| {
-                    if let Ok(Some(text)) = result {
-                        let mut term = terminal.lock();
-                        // Use bracketed paste if enabled
-                        let paste_text = if term.screen().modes.bracketed_paste {
-                            format!("\x1b[200~{}\x1b[201~", text)
-                        } else {
-                            text.to_string()
-                        };
-                        let _ = term.write_str(&paste_text);
-                        drawing_area.queue_draw();
-                    }
-                });
+                if let Err(e) = drawing_area_middle.activate_action("win.paste-primary", None) {
+                    log::error!("Failed to activate paste-primary action: {}", e);
+                }
             });
 
             self.drawing_area.add_controller(middle_click_controller);
@@ -1007,8 +1364,17 @@ impl TerminalWidget {
         let cell_dims_motion = Rc::clone(&cell_dims);
         let drawing_area_motion = self.drawing_area.clone();
         let selecting_motion = Rc::clone(&selecting);
+        // Last pointer position and the timer that scrolls while dragging past an edge
+        let autoscroll_pointer = Rc::new(Cell::new((0.0, 0.0)));
+        let autoscroll_timer: Rc<RefCell<Option<glib::SourceId>>> = Rc::new(RefCell::new(None));
+        let autoscroll_speed = self.config.general.selection_autoscroll_speed;
 
         motion_controller.connect_motion(move |_, x, y| {
+            if minimap_dragging.get() {
+                minimap_jump(&terminal_motion, y, drawing_area_motion.height() as f64);
+                drawing_area_motion.queue_draw();
+                return;
+            }
             if !*selecting_motion.borrow() {
                 return;
             }
@@ -1020,22 +1386,95 @@ impl TerminalWidget {
 
             let mut term = terminal_motion.lock();
             let line = term.screen().visible_row_to_absolute_line(row);
+            let col = term.screen().logical_col(line, col);
             term.screen_mut().extend_selection(line, col);
             drop(term);
 
             drawing_area_motion.queue_draw();
+
+            autoscroll_pointer.set((x, y));
+            let height = drawing_area_motion.height() as f64;
+            let lines = selection_autoscroll_lines(
+                y,
+                height,
+                cell_dims_motion.borrow().height,
+                autoscroll_speed,
+            );
+            if lines == 0 || autoscroll_timer.borrow().is_some() {
+                return;
+            }
+
+            let terminal = Arc::clone(&terminal_motion);
+            let cell_dims = Rc::clone(&cell_dims_motion);
+            let drawing_area = drawing_area_motion.clone();
+            let selecting = Rc::clone(&selecting_motion);
+            let pointer = Rc::clone(&autoscroll_pointer);
+            let timer = Rc::clone(&autoscroll_timer);
+            let id = glib::timeout_add_local(SELECTION_AUTOSCROLL_INTERVAL, move || {
+                let (x, y) = pointer.get();
+                let dims = *cell_dims.borrow();
+                let height = drawing_area.height() as f64;
+                let lines = selection_autoscroll_lines(y, height, dims.height, autoscroll_speed);
+                if !*selecting.borrow() || lines == 0 {
+                    *timer.borrow_mut() = None;
+                    return glib::ControlFlow::Break;
+                }
+
+                let col = (x / dims.width).floor() as usize;
+                let mut term = terminal.lock();
+                let row = if lines < 0 {
+                    term.scroll_viewport_up(lines.unsigned_abs());
+                    0
+                } else {
+                    term.scroll_viewport_down(lines as usize);
+                    term.screen().height().saturating_sub(1)
+                };
+                let line = term.screen().visible_row_to_absolute_line(row);
+                let col = term.screen().logical_col(line, col);
+                term.screen_mut().extend_selection(line, col);
+                drop(term);
+
+                drawing_area.queue_draw();
+                glib::ControlFlow::Continue
+            });
+            *autoscroll_timer.borrow_mut() = Some(id);
         });
 
         self.drawing_area.add_controller(motion_controller);
 
-        // Scroll handling
+        // Scroll handling; Shift+wheel and horizontal swipes scroll truncated
+        // long lines sideways
         let scroll_controller =
-            EventControllerScroll::new(gtk4::EventControllerScrollFlags::VERTICAL);
+            EventControllerScroll::new(gtk4::EventControllerScrollFlags::BOTH_AXES);
         let terminal_scroll = Arc::clone(&terminal);
         let drawing_area_scroll = self.drawing_area.clone();
+        let wheel_scrolls_history = self.config.general.wheel_scrolls_history;
 
-        scroll_controller.connect_scroll(move |_, _dx, dy| {
+        scroll_controller.connect_scroll(move |controller, dx, dy| {
             let mut term = terminal_scroll.lock();
+            let shift = controller
+                .current_event_state()
+                .contains(gdk::ModifierType::SHIFT_MASK);
+            let horizontal = if shift && dx == 0.0 { dy } else { dx };
+            if shift || dy == 0.0 {
+                if horizontal != 0.0 {
+                    let cols = if horizontal < 0.0 { -3 } else { 3 };
+                    term.screen_mut().scroll_horizontal(cols);
+                    drawing_area_scroll.queue_draw();
+                }
+                return glib::Propagation::Stop;
+            }
+            // At an idle shell prompt the wheel can step through its history
+            #[cfg(unix)]
+            if let Some(keys) = wheel_scrolls_history
+                .then(|| term.wheel_history_keys(dy < 0.0, 3))
+                .flatten()
+            {
+                if let Err(e) = term.write(&keys) {
+                    log::error!("Failed to write to terminal: {}", e);
+                }
+                return glib::Propagation::Stop;
+            }
             if dy < 0.0 {
                 term.scroll_viewport_up(3);
             } else {
@@ -1050,16 +1489,29 @@ impl TerminalWidget {
 
     /// Set up file drag-and-drop
     fn setup_drop(&self) {
-        let drop_target = gtk4::DropTarget::new(gio::File::static_type(), gdk::DragAction::COPY);
+        let drop_target =
+            gtk4::DropTarget::new(gdk::FileList::static_type(), gdk::DragAction::COPY);
         let terminal = Arc::clone(&self.terminal);
         let drawing_area = self.drawing_area.clone();
+        let general = Rc::new(self.config.general.clone());
 
         drop_target.connect_drop(move |_, value, _, _| {
-            let file = match value.get::<gio::File>() {
-                Ok(f) => f,
-                Err(_) => return false,
+            let Ok(files) = value.get::<gdk::FileList>() else {
+                return false;
             };
-            let Some(path) = file.path() else {
+            let paths: Vec<std::path::PathBuf> = files
+                .files()
+                .iter()
+                .filter_map(|file| file.path())
+                .collect();
+
+            // Several files, or no dialog wanted: paste the quoted paths
+            if paths.len() > 1 || (paths.len() == 1 && !general.file_drop_dialog) {
+                let text = cterm_app::file_drop::paste_paths(&paths);
+                let _ = terminal.lock().write_str(&text);
+                return true;
+            }
+            let Some(path) = paths.into_iter().next() else {
                 return false;
             };
             let info = match cterm_app::file_drop::FileDropInfo::from_path(&path) {
@@ -1079,6 +1531,7 @@ impl TerminalWidget {
             };
 
             let terminal = Arc::clone(&terminal);
+            let general = Rc::clone(&general);
             let info = std::rc::Rc::new(info);
             let info_for_cb = std::rc::Rc::clone(&info);
 
@@ -1102,8 +1555,8 @@ impl TerminalWidget {
                 match build_pty_input(&info_for_cb, action) {
                     Ok(text) => {
                         let mut term = terminal.lock();
-                        if use_bracketed && term.screen().modes.bracketed_paste {
-                            let paste = format!("\x1b[200~{}\x1b[201~", text);
+                        if use_bracketed {
+                            let paste = general.bracketed_paste_text(&text, &term);
                             let _ = term.write_str(&paste);
                         } else {
                             let _ = term.write_str(&text);
@@ -1121,6 +1574,16 @@ impl TerminalWidget {
         self.drawing_area.add_controller(drop_target);
     }
 
+    /// Feed captured output to the terminal as if the program wrote it
+    ///
+    /// Returns None if the PTY reader isn't running.
+    pub fn replay(&self, data: Vec<u8>, bytes_per_sec: u64) -> Option<Replay> {
+        let tx = self.output_tx.borrow().clone()?;
+        Some(Replay::start(data, bytes_per_sec, move |chunk| {
+            tx.send(PtyMessage::Data(chunk.to_vec())).is_ok()
+        }))
+    }
+
     /// Set up PTY reader
     fn setup_pty_reader(&self) {
         let terminal = Arc::clone(&self.terminal);
@@ -1128,6 +1591,7 @@ impl TerminalWidget {
 
         // Spawn a thread to read from PTY
         let (tx, rx) = std::sync::mpsc::channel::<PtyMessage>();
+        *self.output_tx.borrow_mut() = Some(tx.clone());
 
         std::thread::spawn(move || {
             let mut buf = vec![0u8; 4096];
@@ -1170,6 +1634,7 @@ impl TerminalWidget {
         let on_bell = Rc::clone(&self.on_bell);
         let on_title_change = Rc::clone(&self.on_title_change);
         let on_file_transfer = Rc::clone(&self.on_file_transfer);
+        let on_command_finished = Rc::clone(&self.on_command_finished);
         glib::timeout_add_local(Duration::from_millis(10), move || {
             // Process all pending messages
             while let Ok(msg) = rx.try_recv() {
@@ -1231,7 +1696,7 @@ impl TerminalWidget {
                                 }
                                 TerminalEvent::TitleChanged(ref title) => {
                                     if let Some(ref callback) = *on_title_change.borrow() {
-                                        callback(title);
+                                        callback(title, term.display_directory().as_deref());
                                     }
                                 }
                                 TerminalEvent::ContentChanged => {
@@ -1251,19 +1716,37 @@ impl TerminalWidget {
                             }
                         }
 
-                        // Check for file transfers
+                        // Check for file transfers and finished commands
                         let transfers = term.screen_mut().take_file_transfers();
+                        let finished = term
+                            .screen_mut()
+                            .take_command_finished()
+                            .then(|| term.screen().last_command_exit_code());
                         drop(term); // Release lock before callbacks
 
+                        if let Some(exit_code) = finished {
+                            if let Some(ref callback) = *on_command_finished.borrow() {
+                                callback(exit_code);
+                            }
+                        }
+
                         for transfer in transfers {
                             if let Some(ref callback) = *on_file_transfer.borrow() {
                                 callback(transfer);
                             }
                         }
 
-                        // Re-acquire lock to clear dirty flag
-                        terminal_main.lock().screen_mut().dirty = false;
-                        drawing_area.queue_draw();
+                        // Re-acquire lock to clear dirty flag; output that didn't
+                        // change anything visible (e.g. only OSC sequences) skips
+                        // the redraw
+                        let damage = {
+                            let mut term = terminal_main.lock();
+                            term.screen_mut().dirty = false;
+                            term.screen_mut().take_damage()
+                        };
+                        if !damage.is_empty() {
+                            drawing_area.queue_draw();
+                        }
                     }
                     PtyMessage::Exited => {
                         log::info!("Terminal process exited");
@@ -1283,36 +1766,103 @@ impl TerminalWidget {
     fn setup_resize(&self) {
         let terminal = Arc::clone(&self.terminal);
         let cell_dims = Rc::clone(&self.cell_dims);
+        let minimap_width = self.minimap_width();
 
         self.drawing_area
             .connect_resize(move |_area, width, height| {
-                let dims = cell_dims.borrow();
-                let cols = ((width as f64) / dims.width).floor() as usize;
+                let dims = *cell_dims.borrow();
+                let cols = ((width as f64 - minimap_width) / dims.width).floor() as usize;
                 let rows = ((height as f64) / dims.height).floor() as usize;
-                drop(dims);
 
                 if cols > 0 && rows > 0 {
                     let mut term = terminal.lock();
+                    term.set_cell_size(dims.width, dims.height);
                     term.resize(cols, rows);
                 }
             });
+
+        // Moving the window to a monitor with a different scale factor changes
+        // the font metrics, so re-measure the cells and resize the terminal
+        let terminal = Arc::clone(&self.terminal);
+        let cell_dims = Rc::clone(&self.cell_dims);
+        let font_family = self.font_family.clone();
+        let font_size = Rc::clone(&self.font_size);
+
+        self.drawing_area.connect_scale_factor_notify(move |area| {
+            let new_dims =
+                measure_cell_dimensions(&area.pango_context(), &font_family, *font_size.borrow());
+            log::debug!(
+                "Scale factor changed to {}, cell={}x{}",
+                area.scale_factor(),
+                new_dims.width,
+                new_dims.height
+            );
+            *cell_dims.borrow_mut() = new_dims;
+
+            let cols = ((area.width() as f64 - minimap_width) / new_dims.width).floor() as usize;
+            let rows = ((area.height() as f64) / new_dims.height).floor() as usize;
+            if cols > 0 && rows > 0 {
+                let mut term = terminal.lock();
+                term.set_cell_size(new_dims.width, new_dims.height);
+                term.resize(cols, rows);
+            }
+            area.queue_resize();
+            area.queue_draw();
+        });
     }
 }
 
+/// Pick the font family to draw with: the first installed one of
+/// `font.family`, the `font.fallback` chain and the system monospace font
+///
+/// Pango substitutes fonts it can't find without saying so, so a font counts
+/// as installed only if the one loaded has the name asked for.
+pub fn resolve_font_family(font: &FontConfig) -> (String, Option<FontSubstitution>) {
+    let font_map = pangocairo::FontMap::default();
+    let context = font_map.create_context();
+
+    let (family, substitution) = font.resolve(|name| {
+        if name.eq_ignore_ascii_case(SYSTEM_MONOSPACE) {
+            return true;
+        }
+        let mut font_desc = pango::FontDescription::new();
+        font_desc.set_family(name);
+        font_map
+            .load_font(&context, &font_desc)
+            .and_then(|loaded| loaded.describe().family())
+            .is_some_and(|loaded| loaded.eq_ignore_ascii_case(name))
+    });
+    if let Some(ref substitution) = substitution {
+        log::warn!("{}", substitution);
+    }
+    (family, substitution)
+}
+
 /// Calculate cell dimensions using Pango font metrics
-fn calculate_cell_dimensions(font_family: &str, font_size: f64) -> CellDimensions {
+pub fn calculate_cell_dimensions(font_family: &str, font_size: f64) -> CellDimensions {
     // Get the default font map and create a context
     let font_map = pangocairo::FontMap::default();
     let context = font_map.create_context();
+    measure_cell_dimensions(&context, font_family, font_size)
+}
 
+/// Calculate cell dimensions with the font metrics of a Pango context
+///
+/// A widget's context (`pango_context()`) carries its display's font
+/// options and resolution, which change with the scale factor.
+fn measure_cell_dimensions(
+    context: &pango::Context,
+    font_family: &str,
+    font_size: f64,
+) -> CellDimensions {
     // Try the requested font first, then fall back to generic monospace
-    let fonts_to_try = [font_family.to_string(), "monospace".to_string()];
+    let fonts_to_try = [font_family, SYSTEM_MONOSPACE];
 
     for font_name in &fonts_to_try {
         let font_desc =
             pango::FontDescription::from_string(&format!("{} {}", font_name, font_size));
 
-        if let Some(font) = font_map.load_font(&context, &font_desc) {
+        if let Some(font) = context.load_font(&font_desc) {
             let metrics = font.metrics(None);
             // Use the approximate char width for monospace fonts
             let char_width = metrics.approximate_char_width() as f64 / pango::SCALE as f64;
@@ -1339,7 +1889,7 @@ fn calculate_cell_dimensions(font_family: &str, font_size: f64) -> CellDimension
     }
 
     // Last resort: use a Pango layout to measure a character directly
-    let layout = pango::Layout::new(&context);
+    let layout = pango::Layout::new(context);
     let font_desc = pango::FontDescription::from_string(&format!("monospace {}", font_size));
     layout.set_font_description(Some(&font_desc));
     layout.set_text("M");
@@ -1357,11 +1907,15 @@ fn calculate_cell_dimensions(font_family: &str, font_size: f64) -> CellDimension
         };
     }
 
-    // This should never happen on a functioning system with fonts installed
-    panic!(
-        "Failed to load any font or measure text. \
-         Please ensure fonts are installed (e.g., fonts-dejavu or similar)."
+    // No fonts at all: estimate, so the terminal is still usable
+    let (width, height) = estimated_cell_size(font_size);
+    log::warn!(
+        "Failed to load any font or measure text, estimating cell size {}x{}. \
+         Please ensure fonts are installed (e.g., fonts-dejavu or similar).",
+        width,
+        height
     );
+    CellDimensions { width, height }
 }
 
 /// Messages from PTY reader thread
@@ -1370,7 +1924,180 @@ enum PtyMessage {
     Exited,
 }
 
+/// Scroll so the line under `y` in the minimap is in the middle of the view
+fn minimap_jump(terminal: &Arc<Mutex<Terminal>>, y: f64, height: f64) {
+    let mut term = terminal.lock();
+    let line = minimap_line_at(term.screen(), y, height);
+    term.scroll_to_line(line);
+}
+
+/// Draw the scrollback minimap along the right edge
+fn draw_minimap(
+    cr: &cairo::Context,
+    terminal: &Arc<Mutex<Terminal>>,
+    theme: &Theme,
+    minimap: &mut Minimap,
+    width: f64,
+    height: f64,
+) {
+    let term = terminal.lock();
+    let screen = term.screen();
+    minimap.update(screen, &theme.colors);
+
+    let left = width - MINIMAP_WIDTH;
+    let (r, g, b) = theme.ui.scrollbar.to_f64();
+    cr.set_source_rgba(r, g, b, 0.25);
+    cr.rectangle(left, 0.0, MINIMAP_WIDTH, height);
+    cr.fill().ok();
+
+    let inset = 2.0;
+    for bar in minimap.bars(height) {
+        let (r, g, b) = bar.color.to_f64();
+        cr.set_source_rgba(r, g, b, 0.8);
+        cr.rectangle(
+            left + inset,
+            bar.y,
+            (MINIMAP_WIDTH - 2.0 * inset) * bar.width,
+            bar.height,
+        );
+        cr.fill().ok();
+    }
+
+    // Outline the part of the scrollback that's on screen
+    let (top, view_height) = minimap_viewport(screen, height);
+    let (r, g, b) = theme.ui.scrollbar_hover.to_f64();
+    cr.set_source_rgba(r, g, b, 0.35);
+    cr.rectangle(left, top, MINIMAP_WIDTH, view_height.max(2.0));
+    cr.fill().ok();
+}
+
+/// The configured background image (`appearance.background_image`)
+struct BackgroundImage {
+    image: cairo::ImageSurface,
+    mode: BackgroundImageMode,
+    opacity: f64,
+    /// The image laid out at the last drawn size, reused until the size
+    /// changes so redraws don't scale it again
+    scaled: RefCell<Option<cairo::ImageSurface>>,
+}
+
+impl BackgroundImage {
+    /// Load the configured background image, if any
+    ///
+    /// Each file is decoded once and shared by all terminals; a file that
+    /// can't be read is logged and left out.
+    fn from_config(appearance: &AppearanceConfig) -> Option<Self> {
+        let path = appearance.background_image.as_ref()?;
+        let image = BACKGROUND_IMAGES.with(|images| {
+            images
+                .borrow_mut()
+                .entry(path.clone())
+                .or_insert_with(|| match load_image_surface(path) {
+                    Ok(image) => Some(image),
+                    Err(e) => {
+                        log::warn!("Failed to load background image {}: {}", path.display(), e);
+                        None
+                    }
+                })
+                .clone()
+        })?;
+        Some(Self {
+            image,
+            mode: appearance.background_image_mode,
+            opacity: appearance.background_image_opacity.clamp(0.0, 1.0),
+            scaled: RefCell::new(None),
+        })
+    }
+
+    /// Paint the image over the area `cr` draws to
+    fn paint(&self, cr: &cairo::Context) {
+        let Ok((_, _, width, height)) = cr.clip_extents() else {
+            return;
+        };
+        let (scale_x, scale_y) = cr.target().device_scale();
+        let pixel_width = (width * scale_x).ceil() as i32;
+        let pixel_height = (height * scale_y).ceil() as i32;
+
+        let mut scaled = self.scaled.borrow_mut();
+        if scaled
+            .as_ref()
+            .is_none_or(|s| s.width() != pixel_width || s.height() != pixel_height)
+        {
+            *scaled = self
+                .layout(pixel_width, pixel_height, (scale_x, scale_y))
+                .ok();
+        }
+        if let Some(ref surface) = *scaled {
+            if cr.set_source_surface(surface, 0.0, 0.0).is_ok() {
+                cr.paint_with_alpha(self.opacity).ok();
+            }
+        }
+    }
+
+    /// Draw the image laid out for an area `width` x `height` pixels in size
+    fn layout(
+        &self,
+        width: i32,
+        height: i32,
+        device_scale: (f64, f64),
+    ) -> Result<cairo::ImageSurface, cairo::Error> {
+        let surface = cairo::ImageSurface::create(cairo::Format::ARgb32, width, height)?;
+        surface.set_device_scale(device_scale.0, device_scale.1);
+        let cr = cairo::Context::new(&surface)?;
+
+        let image_size = (self.image.width() as f64, self.image.height() as f64);
+        let area = (
+            width as f64 / device_scale.0,
+            height as f64 / device_scale.1,
+        );
+        let (x, y, w, h) = self.mode.layout(image_size, area);
+        if w <= 0.0 || h <= 0.0 {
+            return Ok(surface);
+        }
+        cr.translate(x, y);
+        cr.scale(w / image_size.0, h / image_size.1);
+        cr.set_source_surface(&self.image, 0.0, 0.0)?;
+        if self.mode == BackgroundImageMode::Tile {
+            cr.source().set_extend(cairo::Extend::Repeat);
+        }
+        cr.paint()?;
+        drop(cr);
+        Ok(surface)
+    }
+}
+
+/// Decode an image file into a cairo surface
+fn load_image_surface(path: &Path) -> Result<cairo::ImageSurface, Box<dyn std::error::Error>> {
+    let data = std::fs::read(path)?;
+    let image = cterm_core::decode_image(&data)?;
+
+    // Cairo wants premultiplied ARGB in native byte order
+    let mut pixels = image.data;
+    for pixel in pixels.chunks_exact_mut(4) {
+        let alpha = pixel[3] as u32;
+        let premultiply = |c: u8| (c as u32 * alpha / 255) as u8;
+        let argb = u32::from_be_bytes([
+            pixel[3],
+            premultiply(pixel[0]),
+            premultiply(pixel[1]),
+            premultiply(pixel[2]),
+        ]);
+        pixel.copy_from_slice(&argb.to_ne_bytes());
+    }
+
+    let stride = image.width as i32 * 4;
+    let surface = cairo::ImageSurface::create_for_data(
+        pixels,
+        cairo::Format::ARgb32,
+        image.width as i32,
+        image.height as i32,
+        stride,
+    )?;
+    Ok(surface)
+}
+
 /// Draw the terminal contents
+#[allow(clippy::too_many_arguments)]
 fn draw_terminal(
     cr: &cairo::Context,
     terminal: &Arc<Mutex<Terminal>>,
@@ -1379,9 +2106,26 @@ fn draw_terminal(
     font_size: f64,
     cell_dims: CellDimensions,
     background_override: Option<cterm_core::color::Rgb>,
+    background_image: Option<&BackgroundImage>,
+    cursor_line: CursorLineHighlight,
+    show_badge: bool,
+    faint_opacity: f64,
+    scroll_position: ScrollPositionIndicator,
+    ruler_columns: &[usize],
 ) {
     let term = terminal.lock();
     let screen = term.screen();
+
+    // Colors set by the program (OSC 10/11/12) replace the theme's
+    let overridden_theme;
+    let theme = if screen.color_overrides().is_empty() {
+        theme
+    } else {
+        overridden_theme = theme.with_color_overrides(screen.color_overrides());
+        &overridden_theme
+    };
+    let background_override =
+        background_override.filter(|_| screen.color_overrides().background.is_none());
     let palette = &theme.colors;
 
     // Draw background (use override if set, otherwise use theme)
@@ -1390,6 +2134,11 @@ fn draw_terminal(
     cr.set_source_rgb(r, g, b);
     cr.paint().ok();
 
+    // The background image shows through the background color, under the text
+    if let Some(image) = background_image {
+        image.paint(cr);
+    }
+
     // Create Pango layout for text rendering
     let pango_context = pangocairo::functions::create_context(cr);
     let layout = pango::Layout::new(&pango_context);
@@ -1402,10 +2151,58 @@ fn draw_terminal(
     let cell_width = cell_dims.width;
     let cell_height = cell_dims.height;
 
+    // Tint the cursor line (drawn first so cell backgrounds and selection cover it)
+    if let Some(row) = cursor_line.highlighted_row(screen) {
+        let tint = CursorLineHighlight::tint(*bg, palette.foreground);
+        let (r, g, b) = tint.to_f64();
+        cr.set_source_rgb(r, g, b);
+        cr.rectangle(
+            0.0,
+            row as f64 * cell_height,
+            screen.width() as f64 * cell_width,
+            cell_height,
+        );
+        cr.fill().ok();
+    }
+
+    // Column rulers, under the text
+    let rulers = ruler_positions(ruler_columns, screen);
+    if !rulers.is_empty() {
+        let (r, g, b) = ruler_color(*bg, palette.foreground).to_f64();
+        cr.set_source_rgb(r, g, b);
+        for col in rulers {
+            cr.rectangle(
+                col as f64 * cell_width,
+                0.0,
+                1.0,
+                screen.height() as f64 * cell_height,
+            );
+        }
+        cr.fill().ok();
+    }
+
+    // Draw the badge (iTerm2 SetBadgeFormat) behind the text, top-right
+    if let Some(badge) = show_badge.then(|| screen.badge()).flatten() {
+        let badge_layout = pango::Layout::new(&pango_context);
+        let badge_font =
+            pango::FontDescription::from_string(&format!("Sans Bold {}", font_size * 2.0));
+        badge_layout.set_font_description(Some(&badge_font));
+        badge_layout.set_text(&badge);
+        let (badge_width, _) = badge_layout.pixel_size();
+
+        let (r, g, b) = badge_color(*bg, palette.foreground).to_f64();
+        cr.set_source_rgb(r, g, b);
+        let x = (screen.width() as f64 * cell_width - badge_width as f64 - cell_width).max(0.0);
+        cr.move_to(x, cell_height * 0.5);
+        pangocairo::functions::show_layout(cr, &badge_layout);
+    }
+
     // Draw cells
     let grid = screen.grid();
     let scroll_offset = screen.scroll_offset;
     let scrollback_len = screen.scrollback().len();
+    // Columns truncated long lines are scrolled right by
+    let h_offset = screen.horizontal_offset();
 
     for row_idx in 0..grid.height() {
         if let Some(row) = grid.row(row_idx) {
@@ -1414,114 +2211,113 @@ fn draw_terminal(
             // Calculate absolute line for selection checking
             let absolute_line = scrollback_len.saturating_sub(scroll_offset) + row_idx;
 
+            // Display order of right-to-left text (None = as stored)
+            let bidi = screen.bidi_line(absolute_line);
+
+            // A truncated line that goes on past the right edge ends in "…"
+            let continues_right = row.overflow_len() > h_offset;
+
+            // Secrets are drawn as "•" (mask_secrets)
+            let secrets = screen.secret_columns(absolute_line);
+
             for col_idx in 0..grid.width() {
-                let cell = &row[col_idx];
-                let x = col_idx as f64 * cell_width;
+                let logical_col = col_idx + h_offset;
+                let Some(cell) = row.get_extended(logical_col) else {
+                    break;
+                };
+                let visual_col = bidi.as_ref().map_or(col_idx, |b| b.visual_col(col_idx));
+                let x = visual_col as f64 * cell_width;
+                let ellipsis = continues_right && col_idx + 1 == grid.width();
 
                 // Skip wide char spacers
-                if cell.attrs.contains(CellAttrs::WIDE_SPACER) {
+                if cell.attrs.contains(CellAttrs::WIDE_SPACER) && !ellipsis {
                     continue;
                 }
 
                 // Check if this cell is selected
-                let is_selected = screen.is_selected(absolute_line, col_idx);
+                let is_selected = screen.is_selected(absolute_line, logical_col);
 
                 // Determine if cell has INVERSE attribute (XOR with selection)
                 let is_inverted = cell.attrs.contains(CellAttrs::INVERSE) != is_selected;
 
-                // Draw background (always draw for selected cells to show highlight)
-                let needs_bg = cell.bg != Color::Default || is_inverted || is_selected;
-
-                if needs_bg {
-                    let bg_color = if is_inverted {
-                        // Inverted: use foreground color as background
-                        if cell.fg == Color::Default {
-                            palette.foreground
-                        } else {
-                            cell.fg.to_rgb(palette)
-                        }
-                    } else {
-                        cell.bg.to_rgb(palette)
-                    };
-
-                    let (r, g, b) = bg_color.to_f64();
-                    cr.set_source_rgb(r, g, b);
-
-                    let char_width = if cell.attrs.contains(CellAttrs::WIDE) {
-                        cell_width * 2.0
-                    } else {
-                        cell_width
-                    };
-
-                    cr.rectangle(x, y, char_width, cell_height);
-                    cr.fill().ok();
-                }
-
-                // Draw character
-                if cell.c != ' ' {
-                    let fg_color = if is_inverted {
-                        // Inverted: use background color as foreground
-                        cell.bg.to_rgb(palette)
-                    } else if cell.fg == Color::Default {
-                        palette.foreground
-                    } else {
-                        cell.fg.to_rgb(palette)
-                    };
-
-                    // Apply dim
-                    let fg_color = if cell.attrs.contains(CellAttrs::DIM) {
-                        Rgb::new(
-                            (fg_color.r as f64 * 0.5) as u8,
-                            (fg_color.g as f64 * 0.5) as u8,
-                            (fg_color.b as f64 * 0.5) as u8,
-                        )
-                    } else {
-                        fg_color
-                    };
-
-                    let (r, g, b) = fg_color.to_f64();
-                    cr.set_source_rgb(r, g, b);
-
-                    // Apply text attributes to font
-                    let attrs = pango::AttrList::new();
-
-                    if cell.attrs.contains(CellAttrs::BOLD) {
-                        let attr = pango::AttrInt::new_weight(pango::Weight::Bold);
-                        attrs.insert(attr);
-                    }
-
-                    if cell.attrs.contains(CellAttrs::ITALIC) {
-                        let attr = pango::AttrInt::new_style(pango::Style::Italic);
-                        attrs.insert(attr);
-                    }
-
-                    if cell.attrs.contains(CellAttrs::UNDERLINE) {
-                        let attr = pango::AttrInt::new_underline(pango::Underline::Single);
-                        attrs.insert(attr);
-                    }
-
-                    if cell.attrs.contains(CellAttrs::STRIKETHROUGH) {
-                        let attr = pango::AttrInt::new_strikethrough(true);
-                        attrs.insert(attr);
-                    }
-
-                    layout.set_attributes(Some(&attrs));
-                    layout.set_text(&cell.c.to_string());
-
-                    cr.move_to(x, y);
-                    pangocairo::functions::show_layout(cr, &layout);
-
-                    // Reset attributes
-                    layout.set_attributes(None::<&pango::AttrList>);
-                }
+                // Search matches get the theme's highlight, unless selected
+                let search_bg = screen
+                    .search_highlight(absolute_line, logical_col)
+                    .filter(|_| !is_selected)
+                    .map(|highlight| theme.search_bg(highlight));
+
+                // Concealed text (SGR 8) keeps only its background
+                let c = if ellipsis {
+                    Some('…')
+                } else if secrets.iter().any(|r| r.contains(&logical_col)) {
+                    (!screen.is_concealed(cell)).then_some('•')
+                } else {
+                    (!screen.is_concealed(cell)).then(|| {
+                        bidi.as_ref()
+                            .map_or(cell.c, |b| b.display_char(col_idx, cell.c))
+                    })
+                };
+                draw_cell(
+                    cr,
+                    &layout,
+                    palette,
+                    *bg,
+                    cell,
+                    c,
+                    x,
+                    y,
+                    cell_dims,
+                    is_inverted,
+                    search_bg,
+                    is_selected,
+                    faint_opacity,
+                );
             }
         }
     }
 
+    // Mark bookmarked rows with a bar in the left gutter
+    let (r, g, b) = theme.ui.scrollbar_hover.to_f64();
+    cr.set_source_rgb(r, g, b);
+    for row in screen.visible_bookmark_rows() {
+        cr.rectangle(
+            0.0,
+            row as f64 * cell_height,
+            (cell_width / 4.0).max(2.0),
+            cell_height,
+        );
+    }
+    cr.fill().ok();
+
+    // Draw the predicted local echo, or else the inline suggestion, as faint
+    // text at the cursor
+    if let Some(overlay) = screen
+        .predicted_input()
+        .or_else(|| screen.inline_suggestion())
+        .filter(|_| h_offset == 0)
+    {
+        let cursor = &screen.cursor;
+        let room = screen.width().saturating_sub(cursor.col);
+        let text: String = overlay.chars().take(room).collect();
+        let (r, g, b) = palette.foreground.faint(*bg, faint_opacity).to_f64();
+        cr.set_source_rgb(r, g, b);
+        layout.set_text(&text);
+        cr.move_to(
+            cursor.col as f64 * cell_width,
+            cursor.row as f64 * cell_height,
+        );
+        pangocairo::functions::show_layout(cr, &layout);
+    }
+
     // Draw cursor
-    if screen.modes.show_cursor && scroll_offset == 0 {
+    if screen.modes.show_cursor && scroll_offset == 0 && h_offset == 0 {
         let cursor = &screen.cursor;
-        let x = cursor.col as f64 * cell_width;
+        let cursor_col = screen.cursor_render_col();
+        let bidi = screen.bidi_line(screen.visible_row_to_absolute_line(cursor.row));
+        let x = bidi
+            .as_ref()
+            .map_or(cursor_col, |b| b.visual_col(cursor_col)) as f64
+            * cell_width;
         let y = cursor.row as f64 * cell_height;
 
         let (r, g, b) = theme.cursor.color.to_f64();
@@ -1533,11 +2329,14 @@ fn draw_terminal(
                 cr.fill().ok();
 
                 // Draw character under cursor with inverted color
-                if let Some(cell) = screen.get_cell(cursor.row, cursor.col) {
+                if let Some(cell) = screen.get_cell(cursor.row, cursor_col) {
                     if cell.c != ' ' {
                         let (r, g, b) = theme.cursor.text_color.to_f64();
                         cr.set_source_rgb(r, g, b);
-                        layout.set_text(&cell.c.to_string());
+                        let c = bidi
+                            .as_ref()
+                            .map_or(cell.c, |b| b.display_char(cursor_col, cell.c));
+                        layout.set_text(&c.to_string());
                         cr.move_to(x, y);
                         pangocairo::functions::show_layout(cr, &layout);
                     }
@@ -1553,6 +2352,251 @@ fn draw_terminal(
             }
         }
     }
+
+    // Scroll position indicator, bottom-right, while scrolled back
+    if let Some(label) = scroll_position.label(screen) {
+        layout.set_text(&label);
+        let (label_width, label_height) = layout.pixel_size();
+        let (label_width, label_height) = (label_width as f64, label_height as f64);
+        let pad = cell_width * 0.5;
+        let x = screen.width() as f64 * cell_width - label_width - pad * 3.0;
+        let y = screen.height() as f64 * cell_height - label_height - pad * 2.0;
+
+        let (r, g, b) = badge_color(*bg, palette.foreground).to_f64();
+        cr.set_source_rgb(r, g, b);
+        cr.rectangle(x - pad, y, label_width + pad * 2.0, label_height);
+        cr.fill().ok();
+
+        let (r, g, b) = palette.foreground.to_f64();
+        cr.set_source_rgb(r, g, b);
+        cr.move_to(x, y);
+        pangocairo::functions::show_layout(cr, &layout);
+    }
+}
+
+/// Draw one cell's background and character with its top-left at (x, y)
+///
+/// `c` is the character to show (None hides it, for concealed text); when
+/// it's the cell's own character, the rest of its grapheme cluster is drawn
+/// with it. Wide cells are centered in and clipped to their two columns.
+/// `inverted` swaps the cell's colors (SGR 7 or selection), `highlight`
+/// replaces its background (search matches) and `fill` draws the background
+/// even when it's the default (selection). `bg` is the terminal background
+/// that faint text fades toward.
+#[allow(clippy::too_many_arguments)]
+fn draw_cell(
+    cr: &cairo::Context,
+    layout: &pango::Layout,
+    palette: &ColorPalette,
+    bg: Rgb,
+    cell: &cterm_core::Cell,
+    c: Option<char>,
+    x: f64,
+    y: f64,
+    cell_dims: CellDimensions,
+    inverted: bool,
+    highlight: Option<Rgb>,
+    fill: bool,
+    faint_opacity: f64,
+) {
+    // Inverted: use foreground color as background
+    let inverted_bg = || {
+        if cell.fg == Color::Default {
+            palette.foreground
+        } else {
+            cell.fg.to_rgb(palette)
+        }
+    };
+
+    if cell.bg != Color::Default || inverted || fill || highlight.is_some() {
+        let bg_color = if let Some(highlight) = highlight {
+            highlight
+        } else if inverted {
+            inverted_bg()
+        } else {
+            cell.bg.to_rgb(palette)
+        };
+
+        let (r, g, b) = bg_color.to_f64();
+        cr.set_source_rgb(r, g, b);
+
+        let char_width = if cell.attrs.contains(CellAttrs::WIDE) {
+            cell_dims.width * 2.0
+        } else {
+            cell_dims.width
+        };
+
+        cr.rectangle(x, y, char_width, cell_dims.height);
+        cr.fill().ok();
+    }
+
+    let Some(c) = c.filter(|&c| c != ' ') else {
+        return;
+    };
+
+    let fg_color = if inverted {
+        // Inverted: use background color as foreground
+        cell.bg.to_rgb(palette)
+    } else if cell.fg == Color::Default {
+        palette.foreground
+    } else {
+        cell.fg.to_rgb(palette)
+    };
+
+    // Apply dim: fade toward whatever the text is drawn over
+    let fg_color = if cell.attrs.contains(CellAttrs::DIM) {
+        let text_bg = if let Some(highlight) = highlight {
+            highlight
+        } else if inverted {
+            inverted_bg()
+        } else if cell.bg == Color::Default {
+            bg
+        } else {
+            cell.bg.to_rgb(palette)
+        };
+        fg_color.faint(text_bg, faint_opacity)
+    } else {
+        fg_color
+    };
+
+    let (r, g, b) = fg_color.to_f64();
+    cr.set_source_rgb(r, g, b);
+
+    // Apply text attributes to font
+    let attrs = pango::AttrList::new();
+
+    if cell.attrs.contains(CellAttrs::BOLD) {
+        let attr = pango::AttrInt::new_weight(pango::Weight::Bold);
+        attrs.insert(attr);
+    }
+
+    if cell.attrs.contains(CellAttrs::ITALIC) {
+        let attr = pango::AttrInt::new_style(pango::Style::Italic);
+        attrs.insert(attr);
+    }
+
+    if cell.attrs.contains(CellAttrs::UNDERLINE) {
+        let attr = pango::AttrInt::new_underline(pango::Underline::Single);
+        attrs.insert(attr);
+    }
+
+    if cell.attrs.contains(CellAttrs::STRIKETHROUGH) {
+        let attr = pango::AttrInt::new_strikethrough(true);
+        attrs.insert(attr);
+    }
+
+    layout.set_attributes(Some(&attrs));
+    if c == cell.c {
+        layout.set_text(&cell.grapheme_text());
+    } else {
+        layout.set_text(&c.to_string());
+    }
+
+    if cell.attrs.contains(CellAttrs::WIDE) {
+        // Emoji fonts may be wider or narrower than two columns
+        let wide_width = cell_dims.width * 2.0;
+        let (_, logical) = layout.pixel_extents();
+        cr.save().ok();
+        cr.rectangle(x, y, wide_width, cell_dims.height);
+        cr.clip();
+        cr.move_to(x + (wide_width - logical.width() as f64) / 2.0, y);
+        pangocairo::functions::show_layout(cr, layout);
+        cr.restore().ok();
+    } else {
+        cr.move_to(x, y);
+        pangocairo::functions::show_layout(cr, layout);
+    }
+
+    // Reset attributes
+    layout.set_attributes(None::<&pango::AttrList>);
+}
+
+/// Render a block of cells to a PNG, on the terminal background with a
+/// one-cell margin
+///
+/// `scale` is the display's scale factor, so the image is as sharp as the
+/// text on screen.
+#[allow(clippy::too_many_arguments)]
+fn render_region_png(
+    region: &CellRegion,
+    theme: &Theme,
+    bg: Rgb,
+    font_family: &str,
+    font_size: f64,
+    cell_dims: CellDimensions,
+    faint_opacity: f64,
+    scale: f64,
+) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let margin_x = cell_dims.width;
+    let margin_y = cell_dims.height / 2.0;
+    let width = region.width() as f64 * cell_dims.width + 2.0 * margin_x;
+    let height = region.height() as f64 * cell_dims.height + 2.0 * margin_y;
+
+    let surface = cairo::ImageSurface::create(
+        cairo::Format::ARgb32,
+        (width * scale).ceil() as i32,
+        (height * scale).ceil() as i32,
+    )?;
+    let cr = cairo::Context::new(&surface)?;
+    cr.scale(scale, scale);
+
+    let (r, g, b) = bg.to_f64();
+    cr.set_source_rgb(r, g, b);
+    cr.paint()?;
+
+    let pango_context = pangocairo::functions::create_context(&cr);
+    let layout = pango::Layout::new(&pango_context);
+    let font_desc = pango::FontDescription::from_string(&format!("{} {}", font_family, font_size));
+    layout.set_font_description(Some(&font_desc));
+
+    for (row_idx, row) in region.rows.iter().enumerate() {
+        let y = margin_y + row_idx as f64 * cell_dims.height;
+        for (col_idx, cell) in row.iter().enumerate() {
+            if cell.attrs.contains(CellAttrs::WIDE_SPACER) {
+                continue;
+            }
+            let x = margin_x + col_idx as f64 * cell_dims.width;
+            let inverted = cell.attrs.contains(CellAttrs::INVERSE);
+            draw_cell(
+                &cr,
+                &layout,
+                &theme.colors,
+                bg,
+                cell,
+                Some(cell.c),
+                x,
+                y,
+                cell_dims,
+                inverted,
+                None,
+                false,
+                faint_opacity,
+            );
+        }
+    }
+    drop(cr);
+
+    let mut png = Vec::new();
+    surface.write_to_png(&mut png)?;
+    Ok(png)
+}
+
+/// Modifier state without the Ctrl/Alt the keyboard layout used to produce
+/// the key
+///
+/// Some backends report AltGr as Ctrl+Alt. The key's character (`{` from
+/// AltGr+7 on a German keyboard) already accounts for it, so it must be
+/// typed rather than treated as a Ctrl+Alt combination.
+pub(crate) fn unconsumed_state(
+    controller: &EventControllerKey,
+    state: gdk::ModifierType,
+) -> gdk::ModifierType {
+    let consumed = controller
+        .current_event()
+        .and_then(|event| event.downcast::<gdk::KeyEvent>().ok())
+        .map(|event| event.consumed_modifiers())
+        .unwrap_or(gdk::ModifierType::empty());
+    state.difference(consumed & (gdk::ModifierType::CONTROL_MASK | gdk::ModifierType::ALT_MASK))
 }
 
 /// Convert GTK modifier state to our Modifiers
@@ -1575,6 +2619,32 @@ fn gtk_state_to_modifiers(state: gdk::ModifierType) -> Modifiers {
     modifiers
 }
 
+/// Convert a GDK keypad digit or operator keyval to terminal Key
+fn keypad_keyval_to_key(keyval: gdk::Key) -> Option<Key> {
+    use gdk::Key as GK;
+
+    Some(Key::Keypad(match keyval {
+        GK::KP_0 => '0',
+        GK::KP_1 => '1',
+        GK::KP_2 => '2',
+        GK::KP_3 => '3',
+        GK::KP_4 => '4',
+        GK::KP_5 => '5',
+        GK::KP_6 => '6',
+        GK::KP_7 => '7',
+        GK::KP_8 => '8',
+        GK::KP_9 => '9',
+        GK::KP_Add => '+',
+        GK::KP_Subtract => '-',
+        GK::KP_Multiply => '*',
+        GK::KP_Divide => '/',
+        GK::KP_Decimal => '.',
+        GK::KP_Separator => ',',
+        GK::KP_Equal => '=',
+        _ => return None,
+    }))
+}
+
 /// Convert GDK keyval to terminal Key
 fn keyval_to_key(keyval: gdk::Key) -> Option<Key> {
     use gdk::Key as GK;
@@ -1591,7 +2661,8 @@ fn keyval_to_key(keyval: gdk::Key) -> Option<Key> {
         GK::Insert => Key::Insert,
         GK::Delete => Key::Delete,
         GK::BackSpace => Key::Backspace,
-        GK::Return | GK::KP_Enter => Key::Enter,
+        GK::Return => Key::Enter,
+        GK::KP_Enter => Key::KeypadEnter,
         GK::Tab | GK::ISO_Left_Tab => Key::Tab,
         GK::Escape => Key::Escape,
         GK::F1 => Key::F(1),
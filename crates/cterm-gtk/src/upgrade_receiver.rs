@@ -5,6 +5,7 @@
 //! and PTY file descriptors/handles, then reconstructs the windows and tabs.
 
 use cterm_app::config::{load_config, Config};
+use cterm_app::notify::BellRateLimiter;
 use cterm_app::upgrade::{receive_upgrade, TabUpgradeState, UpgradeState, WindowUpgradeState};
 use cterm_core::pty::Pty;
 use cterm_core::screen::{Screen, ScreenConfig};
@@ -16,6 +17,7 @@ use gtk4::glib;
 use gtk4::prelude::*;
 use std::cell::RefCell;
 use std::rc::Rc;
+use std::time::Instant;
 
 #[cfg(unix)]
 use std::os::unix::io::RawFd;
@@ -45,6 +47,18 @@ pub fn run_receiver(handle: u64) -> glib::ExitCode {
     }
 }
 
+/// Reopen the windows of sessions taken back from the session keeper
+#[cfg(unix)]
+pub fn run_attached(state: UpgradeState, fds: Vec<RawFd>) -> glib::ExitCode {
+    match run_gtk_with_state_unix(state, fds) {
+        Ok(()) => glib::ExitCode::SUCCESS,
+        Err(e) => {
+            log::error!("Reattaching sessions failed: {}", e);
+            glib::ExitCode::FAILURE
+        }
+    }
+}
+
 #[cfg(unix)]
 fn receive_and_reconstruct(handle: u64) -> Result<(), Box<dyn std::error::Error>> {
     // Use the upgrade module to receive the state
@@ -92,7 +106,6 @@ fn run_gtk_with_state_unix(
     fds: Vec<RawFd>,
 ) -> Result<(), Box<dyn std::error::Error>> {
     use gtk4::gio;
-    use gtk4::Application;
 
     // Store state and FDs for use during window construction
     // We use thread-local storage since GTK callbacks don't easily pass data
@@ -102,10 +115,8 @@ fn run_gtk_with_state_unix(
 
     // Use NON_UNIQUE flag to prevent DBus conflicts with the old instance
     // that may still be shutting down
-    let app = Application::builder()
-        .application_id("com.cterm.terminal")
-        .flags(gio::ApplicationFlags::NON_UNIQUE)
-        .build();
+    let class = load_config().unwrap_or_default().general.app_id;
+    let app = crate::build_application(class.as_deref(), gio::ApplicationFlags::NON_UNIQUE);
 
     app.connect_activate(|app| {
         // Retrieve the stored state
@@ -129,7 +140,6 @@ fn run_gtk_with_state_windows(
     handles: Vec<(RawHandle, RawHandle, RawHandle, RawHandle, u32)>,
 ) -> Result<(), Box<dyn std::error::Error>> {
     use gtk4::gio;
-    use gtk4::Application;
 
     // Store state and handles for use during window construction
     UPGRADE_STATE_WINDOWS.with(|s| {
@@ -137,10 +147,8 @@ fn run_gtk_with_state_windows(
     });
 
     // Use NON_UNIQUE flag to prevent conflicts with the old instance
-    let app = Application::builder()
-        .application_id("com.cterm.terminal")
-        .flags(gio::ApplicationFlags::NON_UNIQUE)
-        .build();
+    let class = load_config().unwrap_or_default().general.app_id;
+    let app = crate::build_application(class.as_deref(), gio::ApplicationFlags::NON_UNIQUE);
 
     app.connect_activate(|app| {
         // Retrieve the stored state
@@ -162,6 +170,17 @@ thread_local! {
         const { std::cell::RefCell::new(None) };
 }
 
+/// Tabs of a restored window
+#[cfg(unix)]
+type RestoredTabs = RefCell<Vec<(u64, String, TerminalWidget)>>;
+
+// Restored windows, for detaching all of their sessions at once (Unix)
+#[cfg(unix)]
+thread_local! {
+    static RESTORED_WINDOWS: RefCell<Vec<(glib::WeakRef<gtk4::ApplicationWindow>, std::rc::Weak<RestoredTabs>)>> =
+        const { RefCell::new(Vec::new()) };
+}
+
 // Thread-local storage for upgrade state (Windows)
 #[cfg(windows)]
 thread_local! {
@@ -281,12 +300,14 @@ where
     use gtk4::{ApplicationWindow, Box as GtkBox, Notebook, Orientation, PopoverMenuBar};
 
     // Create the main window
+    let window_title = config.general.window_title("cterm");
     let window = ApplicationWindow::builder()
         .application(app)
-        .title("cterm")
+        .title(window_title.as_str())
         .default_width(window_state.width)
         .default_height(window_state.height)
         .build();
+    window.set_opacity(config.appearance.opacity);
 
     // Create the main container
     let main_box = GtkBox::new(Orientation::Vertical, 0);
@@ -297,7 +318,7 @@ where
     main_box.append(&menu_bar);
 
     // Create tab bar
-    let tab_bar = TabBar::new();
+    let tab_bar = TabBar::new(&config.tabs);
     main_box.append(tab_bar.widget());
 
     // Create notebook for terminal tabs
@@ -335,6 +356,7 @@ where
 
         // Extract customization state before tab_state is consumed
         let tab_color = tab_state.color.clone();
+        let tab_title_color = tab_state.title_color.clone();
 
         match create_tab(tab_state) {
             Ok((tab_id, title, terminal_widget)) => {
@@ -348,6 +370,9 @@ where
                 if let Some(ref color) = tab_color {
                     tab_bar.set_color(tab_id, Some(color));
                 }
+                if let Some(ref color) = tab_title_color {
+                    tab_bar.set_title_color(tab_id, Some(color));
+                }
 
                 // Set up close callback
                 let notebook_close = notebook.clone();
@@ -401,8 +426,14 @@ where
                 let notebook_bell = notebook.clone();
                 let tabs_bell = Rc::clone(&tabs);
                 let window_bell = window.clone();
+                let bell_title = config.general.window_title("🔔 cterm");
                 let has_bell_bell = Rc::clone(&has_bell);
+                let bell_limiter = RefCell::new(BellRateLimiter::from_config(&config.general));
                 terminal_widget.set_on_bell(move || {
+                    if !bell_limiter.borrow_mut().allow(Instant::now()) {
+                        return;
+                    }
+
                     let is_window_active = window_bell.is_active();
                     let is_current_tab = if let Some(current_page) = notebook_bell.current_page() {
                         let tabs = tabs_bell.borrow();
@@ -421,7 +452,7 @@ where
                     // Update window title if window is not active
                     if !is_window_active {
                         *has_bell_bell.borrow_mut() = true;
-                        window_bell.set_title(Some("🔔 cterm"));
+                        window_bell.set_title(Some(&bell_title));
                     }
                 });
 
@@ -442,6 +473,7 @@ where
     {
         let has_bell_focus = Rc::clone(&has_bell);
         let window_focus = window.clone();
+        let window_title = window_title.clone();
         let tab_bar_focus = tab_bar.clone();
         let tabs_focus = Rc::clone(&tabs);
         let notebook_focus = notebook.clone();
@@ -460,7 +492,7 @@ where
                 let mut bell = has_bell_focus.borrow_mut();
                 if *bell {
                     *bell = false;
-                    window_focus.set_title(Some("cterm"));
+                    window_focus.set_title(Some(&window_title));
 
                     // Clear bell on the currently active tab
                     if let Some(page_idx) = notebook_focus.current_page() {
@@ -597,26 +629,9 @@ where
 #[cfg(unix)]
 /// Reconstruct a Screen from saved terminal state
 fn reconstruct_screen(config: &Config, tab_state: &TabUpgradeState) -> Screen {
-    let term_state = &tab_state.terminal;
-    let screen_config = ScreenConfig {
+    tab_state.terminal.to_screen(ScreenConfig {
         scrollback_lines: config.general.scrollback_lines,
-    };
-
-    Screen::from_upgrade_state(
-        term_state.grid.clone(),
-        term_state.scrollback.clone(),
-        term_state.alternate_grid.clone(),
-        term_state.cursor.clone(),
-        term_state.saved_cursor.clone(),
-        term_state.alt_saved_cursor.clone(),
-        term_state.scroll_region,
-        term_state.style.clone(),
-        term_state.modes.clone(),
-        term_state.title.clone(),
-        term_state.scroll_offset,
-        term_state.tab_stops.clone(),
-        screen_config,
-    )
+    })
 }
 
 fn create_restored_tab_unix(
@@ -722,6 +737,47 @@ fn close_tab_by_id(
     }
 }
 
+/// Hand the sessions of all restored windows back to the session keeper
+#[cfg(unix)]
+fn detach_restored_sessions(window: &gtk4::ApplicationWindow) {
+    let mut state = UpgradeState::new(env!("CARGO_PKG_VERSION"));
+    let mut fds: Vec<RawFd> = Vec::new();
+    RESTORED_WINDOWS.with(|windows| {
+        for (win, tabs) in windows.borrow().iter() {
+            let (Some(win), Some(tabs)) = (win.upgrade(), tabs.upgrade()) else {
+                continue;
+            };
+            let mut window_state = WindowUpgradeState::new();
+            window_state.width = win.default_width();
+            window_state.height = win.default_height();
+            window_state.maximized = win.is_maximized();
+            window_state.fullscreen = win.is_fullscreen();
+
+            for (id, title, terminal) in tabs.borrow().iter() {
+                let terminal_state = terminal.export_state();
+                let term = terminal.terminal().lock();
+                let Some(fd) = term.dup_pty_fd() else {
+                    log::warn!("Tab {}: Failed to get PTY FD", id);
+                    continue;
+                };
+                let mut tab_state =
+                    TabUpgradeState::new(*id, fds.len(), term.child_pid().unwrap_or(0));
+                tab_state.title = title.clone();
+                tab_state.cwd = term
+                    .foreground_cwd()
+                    .map(|p| p.to_string_lossy().into_owned());
+                tab_state.terminal = terminal_state;
+                fds.push(fd);
+                window_state.tabs.push(tab_state);
+            }
+            if !window_state.tabs.is_empty() {
+                state.windows.push(window_state);
+            }
+        }
+    });
+    crate::window::detach_and_exit(window, &state, fds);
+}
+
 /// Set up window actions for menu items
 #[allow(clippy::too_many_arguments, clippy::type_complexity)]
 fn setup_window_actions(
@@ -733,6 +789,22 @@ fn setup_window_actions(
     _config: &Config,
     _theme: &Theme,
 ) {
+    // Detach Sessions action
+    #[cfg(unix)]
+    {
+        RESTORED_WINDOWS.with(|windows| {
+            windows
+                .borrow_mut()
+                .push((window.downgrade(), Rc::downgrade(tabs)))
+        });
+        let action = gio::SimpleAction::new("detach-sessions", None);
+        let window_clone = window.clone();
+        action.connect_activate(move |_, _| {
+            detach_restored_sessions(&window_clone);
+        });
+        window.add_action(&action);
+    }
+
     // New Tab action (stub - full implementation requires more complex setup)
     {
         let action = gio::SimpleAction::new("new-tab", None);
@@ -843,15 +915,23 @@ fn setup_window_actions(
     // These are no-ops but prevent "unknown action" warnings
     for name in &[
         "new-window",
+        "open-workspace",
         "close-other-tabs",
         "docker-picker",
         "copy",
         "paste",
         "select-all",
         "copy-html",
+        "copy-image",
+        "save-image",
+        "copy-last-output",
         "set-title",
         "set-color",
         "find",
+        "rerun-last-command",
+        "clear-to-last-prompt",
+        "set-mark",
+        "jump-mark",
         "set-encoding",
         "send-signal",
         "reset",
@@ -1,7 +1,9 @@
 //! Main window implementation
 
-use std::cell::RefCell;
-use std::rc::Rc;
+use std::cell::{Cell, RefCell};
+use std::path::PathBuf;
+use std::rc::{Rc, Weak};
+use std::time::Instant;
 
 use gtk4::prelude::*;
 use gtk4::{
@@ -9,11 +11,22 @@ use gtk4::{
     Orientation, PopoverMenuBar,
 };
 
-use cterm_app::config::Config;
+use cterm_app::config::{AppearanceConfig, CloseLastTab, Config, StickyTabConfig, TitleFilter};
 use cterm_app::file_transfer::PendingFileManager;
-use cterm_app::shortcuts::ShortcutManager;
+use cterm_app::notify::{
+    bell_notification, sound_for, BellEscalation, BellRateLimiter, Notification, Sound, SoundEvent,
+};
+use cterm_app::recording::RecordingToggle;
+use cterm_app::replay::Replay;
+use cterm_app::session::{
+    pin_target, unpinned_position, ClosedWindows, QuitSummary, TabSessionState, WindowGeometry,
+    WindowSessionState,
+};
+use cterm_app::shortcuts::{describe_conflicts, ShortcutManager};
+use cterm_app::workspace::{Workspace, WorkspaceWindow, WORKSPACE_EXTENSION};
+use cterm_core::encoding::TerminalEncoding;
 use cterm_ui::events::{Action, KeyCode, Modifiers};
-use cterm_ui::theme::Theme;
+use cterm_ui::theme::{FontSubstitution, Theme};
 
 use crate::dialogs;
 use crate::docker_dialog::{self, DockerSelection};
@@ -21,7 +34,42 @@ use crate::menu;
 use crate::notification_bar::NotificationBar;
 use crate::quick_open::QuickOpenOverlay;
 use crate::tab_bar::TabBar;
-use crate::terminal_widget::{CellDimensions, TerminalWidget};
+use crate::terminal_widget::{
+    calculate_cell_dimensions, resolve_font_family, unconsumed_state, CellDimensions,
+    TerminalWidget,
+};
+
+thread_local! {
+    /// Recently closed windows for "Reopen Closed Window"
+    static CLOSED_WINDOWS: RefCell<ClosedWindows> = RefCell::new(ClosedWindows::new());
+
+    /// Open windows and their tabs, for quitting the application
+    static OPEN_WINDOWS: RefCell<Vec<OpenWindow>> = const { RefCell::new(Vec::new()) };
+
+    /// Whether the missing font message has been shown
+    static FONT_WARNING_SHOWN: Cell<bool> = const { Cell::new(false) };
+
+    /// The sound playing, kept alive until it is replaced
+    static PLAYING_SOUND: RefCell<Option<gtk4::MediaFile>> = const { RefCell::new(None) };
+}
+
+/// What the first tab(s) of a new window start from
+enum FirstTabs<'a> {
+    /// A shell, in the given directory if any
+    Shell(Option<String>),
+    /// The tabs of a closed window, in their saved directories
+    Closed(&'a WindowSessionState),
+    /// A tab template
+    Template(&'a StickyTabConfig),
+    /// The tabs of a workspace window
+    Workspace(&'a WorkspaceWindow),
+}
+
+/// An open window in `OPEN_WINDOWS`
+struct OpenWindow {
+    window: glib::WeakRef<ApplicationWindow>,
+    tabs: Weak<RefCell<Vec<TabEntry>>>,
+}
 
 /// Tab entry tracking terminal and its ID
 struct TabEntry {
@@ -32,6 +80,10 @@ struct TabEntry {
     title_locked: bool,
     /// Tab color override
     color: Option<String>,
+    /// Tab title text color override
+    title_color: Option<String>,
+    /// Whether the tab is pinned to the start of the tab bar
+    pinned: bool,
 }
 
 /// Main window container
@@ -94,11 +146,171 @@ fn show_upgrade_error_dialog(window: &ApplicationWindow, error: &dyn std::fmt::D
     dialog.present();
 }
 
+/// Capture a window's tabs for handing them to another process, adding
+/// a duplicate of each tab's PTY FD to `fds`
+///
+/// Incognito tabs are never serialized; they close with this process.
+#[cfg(unix)]
+fn window_upgrade_state(
+    window: &ApplicationWindow,
+    tabs: &[TabEntry],
+    fds: &mut Vec<std::os::unix::io::RawFd>,
+) -> cterm_app::upgrade::WindowUpgradeState {
+    let mut window_state = cterm_app::upgrade::WindowUpgradeState::new();
+    window_state.width = window.default_width();
+    window_state.height = window.default_height();
+    window_state.maximized = window.is_maximized();
+    window_state.fullscreen = window.is_fullscreen();
+
+    for tab in tabs.iter().filter(|t| !t.terminal.is_incognito()) {
+        let mut tab_state = cterm_app::upgrade::TabUpgradeState::new(tab.id, 0, 0);
+        tab_state.title = tab.title.clone();
+        if tab.title_locked {
+            tab_state.custom_title = Some(tab.title.clone());
+        }
+
+        // Export terminal state
+        tab_state.terminal = tab.terminal.export_state();
+
+        tab_state.color = tab.color.clone();
+        tab_state.title_color = tab.title_color.clone();
+
+        // Try to get PTY file descriptor
+        let term = tab.terminal.terminal().lock();
+        tab_state.cwd = term
+            .foreground_cwd()
+            .map(|p| p.to_string_lossy().into_owned());
+        if let Some(fd) = term.dup_pty_fd() {
+            tab_state.pty_fd_index = fds.len();
+            tab_state.child_pid = term.child_pid().unwrap_or(0);
+            fds.push(fd);
+            log::info!(
+                "Tab {}: Got PTY FD {} (index {}), child_pid={}",
+                tab.id,
+                fd,
+                tab_state.pty_fd_index,
+                tab_state.child_pid
+            );
+        } else {
+            log::warn!("Tab {}: Failed to get PTY FD", tab.id);
+        }
+        drop(term);
+
+        window_state.tabs.push(tab_state);
+    }
+
+    // Set active tab
+    // Note: We'd need access to the notebook to know which tab is active
+    window_state.active_tab = 0;
+    window_state
+}
+
+/// Hand the sessions of every open window to the session keeper and exit,
+/// so `cterm --attach` can pick them up again
+#[cfg(unix)]
+fn detach_all_sessions(window: &ApplicationWindow) {
+    let mut state = cterm_app::upgrade::UpgradeState::new(env!("CARGO_PKG_VERSION"));
+    let mut fds: Vec<std::os::unix::io::RawFd> = Vec::new();
+    OPEN_WINDOWS.with(|windows| {
+        for open in windows.borrow().iter() {
+            let (Some(win), Some(tabs)) = (open.window.upgrade(), open.tabs.upgrade()) else {
+                continue;
+            };
+            let window_state = window_upgrade_state(&win, &tabs.borrow(), &mut fds);
+            if !window_state.tabs.is_empty() {
+                state.windows.push(window_state);
+            }
+        }
+    });
+    detach_and_exit(window, &state, fds);
+}
+
+/// Send `state` and the PTY FDs to the session keeper and exit, or report
+/// the failure on `window` and keep running
+#[cfg(unix)]
+pub(crate) fn detach_and_exit(
+    window: &ApplicationWindow,
+    state: &cterm_app::upgrade::UpgradeState,
+    fds: Vec<std::os::unix::io::RawFd>,
+) {
+    let result = std::env::current_exe()
+        .map_err(cterm_app::session_keeper::SessionKeeperError::from)
+        .and_then(|binary| cterm_app::session_keeper::detach_sessions(&binary, state, &fds));
+    match result {
+        Ok(()) => {
+            // Exit without dropping the PTYs, which would hang up the shells
+            log::info!("Sessions detached, exiting");
+            std::process::exit(0);
+        }
+        Err(e) => {
+            log::error!("Detaching sessions failed: {}", e);
+            for fd in fds {
+                unsafe { libc::close(fd) };
+            }
+            let dialog = gtk4::MessageDialog::new(
+                Some(window),
+                gtk4::DialogFlags::MODAL,
+                gtk4::MessageType::Error,
+                gtk4::ButtonsType::Ok,
+                format!("Detaching sessions failed: {}", e),
+            );
+            dialog.connect_response(|d, _| d.close());
+            dialog.present();
+        }
+    }
+}
+
+/// Make the window more or less opaque (opacity shortcuts)
+fn step_window_opacity(window: &ApplicationWindow, config: &Config, increase: bool) {
+    let opacity = AppearanceConfig::step_opacity(window.opacity(), increase);
+    window.set_opacity(opacity);
+
+    if config.appearance.persist_opacity {
+        if let Err(e) = cterm_app::config::save_opacity(opacity) {
+            log::error!("Failed to save opacity: {}", e);
+        }
+    }
+}
+
 impl CtermWindow {
-    /// Create a new window
-    pub fn new(app: &Application, config: &Config, theme: &Theme) -> Self {
+    /// Create a new window, its first tab starting in `cwd` if given
+    pub fn new(app: &Application, config: &Config, theme: &Theme, cwd: Option<String>) -> Self {
+        Self::build(app, config, theme, FirstTabs::Shell(cwd))
+    }
+
+    /// Create a new window whose first tab is opened from a tab template
+    pub fn from_template(
+        app: &Application,
+        config: &Config,
+        theme: &Theme,
+        template: &StickyTabConfig,
+    ) -> Self {
+        Self::build(app, config, theme, FirstTabs::Template(template))
+    }
+
+    /// Recreate a closed window with fresh shells in the saved directories
+    pub fn from_closed(
+        app: &Application,
+        config: &Config,
+        theme: &Theme,
+        closed: &WindowSessionState,
+    ) -> Self {
+        Self::build(app, config, theme, FirstTabs::Closed(closed))
+    }
+
+    /// Create a window with the tabs of a workspace window
+    pub fn from_workspace(
+        app: &Application,
+        config: &Config,
+        theme: &Theme,
+        workspace_window: &WorkspaceWindow,
+    ) -> Self {
+        Self::build(app, config, theme, FirstTabs::Workspace(workspace_window))
+    }
+
+    fn build(app: &Application, config: &Config, theme: &Theme, first_tabs: FirstTabs) -> Self {
         // Calculate cell dimensions for initial window sizing
-        let cell_dims = calculate_initial_cell_dimensions(config);
+        let (cell_dims, font_substitution) = calculate_initial_cell_dimensions(config);
 
         // Calculate window size for 80x24 terminal plus chrome (menu bar ~30px, tab bar ~24px)
         let chrome_height = 54; // Approximate height for menu bar + tab bar
@@ -108,10 +320,11 @@ impl CtermWindow {
         // Create the main window
         let window = ApplicationWindow::builder()
             .application(app)
-            .title("cterm")
+            .title(config.general.window_title("cterm").as_str())
             .default_width(default_width)
             .default_height(default_height)
             .build();
+        window.set_opacity(config.appearance.opacity);
 
         // Create the main container
         let main_box = GtkBox::new(Orientation::Vertical, 0);
@@ -122,12 +335,19 @@ impl CtermWindow {
         main_box.append(&menu_bar);
 
         // Create tab bar
-        let tab_bar = TabBar::new();
+        let tab_bar = TabBar::new(&config.tabs);
+        tab_bar.set_exit_status_colors(theme);
         main_box.append(tab_bar.widget());
 
         // Create notification bar for file transfers (initially hidden)
         let notification_bar = NotificationBar::new();
         main_box.append(notification_bar.widget());
+        if let Some(substitution) = font_substitution {
+            // Once per run, not in every new window
+            if !FONT_WARNING_SHOWN.replace(true) {
+                notification_bar.show_message(&substitution.to_string());
+            }
+        }
 
         // Create Quick Open overlay (initially hidden)
         let quick_open = QuickOpenOverlay::new();
@@ -190,8 +410,15 @@ impl CtermWindow {
         // Set up notification bar callbacks for file transfers
         cterm_window.setup_notification_bar();
 
-        // Create initial tab
-        cterm_window.new_tab();
+        // Create initial tab(s)
+        match first_tabs {
+            FirstTabs::Shell(cwd) => cterm_window.new_tab_with_cwd(cwd),
+            FirstTabs::Closed(closed) => cterm_window.restore_closed_tabs(closed),
+            FirstTabs::Template(template) => cterm_window.new_template_tab(template),
+            FirstTabs::Workspace(workspace_window) => {
+                cterm_window.open_workspace_tabs(workspace_window)
+            }
+        }
 
         // Initially hide tab bar (only one tab)
         cterm_window.tab_bar.update_visibility();
@@ -202,6 +429,11 @@ impl CtermWindow {
         // Set up close request handler for process confirmation
         cterm_window.setup_close_request_handler();
 
+        // Remember the window when closed so it can be reopened
+        cterm_window.setup_closed_window_tracking();
+
+        cterm_window.register_open_window();
+
         cterm_window
     }
 
@@ -217,7 +449,7 @@ impl CtermWindow {
         let has_bell = Rc::clone(&self.has_bell);
 
         // File menu actions
-        {
+        for (name, incognito) in [("new-tab", false), ("new-incognito-tab", true)] {
             let notebook = notebook.clone();
             let tabs = Rc::clone(&tabs);
             let next_tab_id = Rc::clone(&next_tab_id);
@@ -228,7 +460,7 @@ impl CtermWindow {
             let has_bell = Rc::clone(&has_bell);
             let file_manager = Rc::clone(&self.file_manager);
             let notification_bar = self.notification_bar.clone();
-            let action = gio::SimpleAction::new("new-tab", None);
+            let action = gio::SimpleAction::new(name, None);
             action.connect_activate(move |_, _| {
                 // Get the current working directory from the active terminal
                 #[cfg(unix)]
@@ -237,7 +469,7 @@ impl CtermWindow {
                     if let Some(page_idx) = notebook.current_page() {
                         tabs_borrow
                             .get(page_idx as usize)
-                            .and_then(|entry| entry.terminal.foreground_cwd())
+                            .and_then(|entry| entry.terminal.working_directory())
                     } else {
                         None
                     }
@@ -257,6 +489,8 @@ impl CtermWindow {
                     &file_manager,
                     &notification_bar,
                     cwd,
+                    None,
+                    incognito,
                 );
             });
             window.add_action(&action);
@@ -264,13 +498,104 @@ impl CtermWindow {
 
         {
             let app = window.application().unwrap();
+            let notebook = notebook.clone();
+            let tabs = Rc::clone(&tabs);
             let config = Rc::clone(&config);
             let theme = theme.clone();
             let action = gio::SimpleAction::new("new-window", None);
             action.connect_activate(move |_, _| {
+                // Start in the working directory of the active terminal
+                #[cfg(unix)]
+                let cwd = notebook.current_page().and_then(|page_idx| {
+                    tabs.borrow()
+                        .get(page_idx as usize)
+                        .and_then(|entry| entry.terminal.working_directory())
+                });
+                #[cfg(not(unix))]
+                let cwd: Option<String> = None;
+
+                let cfg = config.borrow();
+                if let Some(gtk_app) = app.downcast_ref::<Application>() {
+                    let new_win = CtermWindow::new(gtk_app, &cfg, &theme, cwd);
+                    new_win.present();
+                }
+            });
+            window.add_action(&action);
+        }
+
+        // Open a workspace file in new windows
+        {
+            let app = window.application().unwrap();
+            let window_clone = window.clone();
+            let config = Rc::clone(&config);
+            let theme = theme.clone();
+            let action = gio::SimpleAction::new("open-workspace", None);
+            action.connect_activate(move |_, _| {
+                let file_chooser = gtk4::FileChooserDialog::new(
+                    Some("Open Workspace"),
+                    Some(&window_clone),
+                    gtk4::FileChooserAction::Open,
+                    &[
+                        ("Cancel", gtk4::ResponseType::Cancel),
+                        ("Open", gtk4::ResponseType::Accept),
+                    ],
+                );
+                let filter = gtk4::FileFilter::new();
+                filter.set_name(Some("Workspaces"));
+                let workspace_pattern = format!("*.{}", WORKSPACE_EXTENSION);
+                for pattern in [workspace_pattern.as_str(), "*.toml", "*.json"] {
+                    filter.add_pattern(pattern);
+                }
+                file_chooser.add_filter(&filter);
+
+                let app = app.clone();
+                let window = window_clone.clone();
+                let config = Rc::clone(&config);
+                let theme = theme.clone();
+                file_chooser.connect_response(move |dialog, response| {
+                    let path = dialog.file().and_then(|f| f.path());
+                    dialog.close();
+                    let (Some(path), gtk4::ResponseType::Accept) = (path, response) else {
+                        return;
+                    };
+
+                    match cterm_app::workspace::load_workspace(&path) {
+                        Ok(workspace) => {
+                            if let Some(gtk_app) = app.downcast_ref::<Application>() {
+                                open_workspace(gtk_app, &config.borrow(), &theme, &workspace);
+                            }
+                        }
+                        Err(e) => {
+                            log::error!("Failed to open workspace {:?}: {}", path, e);
+                            let message_dialog = gtk4::MessageDialog::new(
+                                Some(&window),
+                                gtk4::DialogFlags::MODAL,
+                                gtk4::MessageType::Error,
+                                gtk4::ButtonsType::Ok,
+                                e.to_string(),
+                            );
+                            message_dialog.connect_response(|d, _| d.close());
+                            message_dialog.present();
+                        }
+                    }
+                });
+                file_chooser.present();
+            });
+            window.add_action(&action);
+        }
+
+        {
+            let app = window.application().unwrap();
+            let config = Rc::clone(&config);
+            let theme = theme.clone();
+            let action = gio::SimpleAction::new("reopen-closed-window", None);
+            action.connect_activate(move |_, _| {
+                let Some(closed) = CLOSED_WINDOWS.with(|c| c.borrow_mut().pop()) else {
+                    return;
+                };
                 let cfg = config.borrow();
                 if let Some(gtk_app) = app.downcast_ref::<Application>() {
-                    let new_win = CtermWindow::new(gtk_app, &cfg, &theme);
+                    let new_win = CtermWindow::from_closed(gtk_app, &cfg, &theme, &closed);
                     new_win.present();
                 }
             });
@@ -304,9 +629,23 @@ impl CtermWindow {
 
         {
             let window_clone = window.clone();
+            let config = Rc::clone(&self.config);
             let action = gio::SimpleAction::new("quit", None);
             action.connect_activate(move |_, _| {
-                window_clone.close();
+                let summary = quit_summary();
+                if !summary.needs_confirmation(&config.borrow().general) {
+                    destroy_all_windows();
+                    return;
+                }
+                dialogs::show_quit_confirmation_dialog(
+                    &window_clone,
+                    &summary.details(),
+                    |confirmed| {
+                        if confirmed {
+                            destroy_all_windows();
+                        }
+                    },
+                );
             });
             window.add_action(&action);
         }
@@ -420,14 +759,157 @@ impl CtermWindow {
         }
 
         {
+            // Copy the selection (or the screen) as an image
+            let notebook = notebook.clone();
+            let tabs = Rc::clone(&tabs);
+            let action = gio::SimpleAction::new("copy-image", None);
+            action.connect_activate(move |_, _| {
+                if let Some(page_idx) = notebook.current_page() {
+                    let tabs = tabs.borrow();
+                    if let Some(tab) = tabs.get(page_idx as usize) {
+                        tab.terminal.copy_as_image();
+                    }
+                }
+            });
+            window.add_action(&action);
+        }
+
+        {
+            // Save the selection (or the screen) as a PNG file
+            let notebook = notebook.clone();
+            let tabs = Rc::clone(&tabs);
+            let window_clone = window.clone();
+            let action = gio::SimpleAction::new("save-image", None);
+            action.connect_activate(move |_, _| {
+                // Render now, so the image matches what was selected
+                let png = notebook.current_page().and_then(|page_idx| {
+                    let tabs = tabs.borrow();
+                    tabs.get(page_idx as usize)
+                        .and_then(|tab| tab.terminal.render_image())
+                });
+                let Some(png) = png else {
+                    return;
+                };
+
+                let file_chooser = gtk4::FileChooserDialog::new(
+                    Some("Save as Image"),
+                    Some(&window_clone),
+                    gtk4::FileChooserAction::Save,
+                    &[
+                        ("Cancel", gtk4::ResponseType::Cancel),
+                        ("Save", gtk4::ResponseType::Accept),
+                    ],
+                );
+                file_chooser.set_current_name("cterm.png");
+                file_chooser.connect_response(move |dialog, response| {
+                    if response == gtk4::ResponseType::Accept {
+                        if let Some(path) = dialog.file().and_then(|file| file.path()) {
+                            match std::fs::write(&path, &png) {
+                                Ok(()) => log::info!("Saved image to {:?}", path),
+                                Err(e) => log::error!("Failed to save image: {}", e),
+                            }
+                        }
+                    }
+                    dialog.close();
+                });
+                file_chooser.present();
+            });
+            window.add_action(&action);
+        }
+
+        {
+            // Copy output of the last command (OSC 133)
             let notebook = notebook.clone();
             let tabs = Rc::clone(&tabs);
+            let action = gio::SimpleAction::new("copy-last-output", None);
+            action.connect_activate(move |_, _| {
+                if let Some(page_idx) = notebook.current_page() {
+                    let tabs = tabs.borrow();
+                    if let Some(tab) = tabs.get(page_idx as usize) {
+                        tab.terminal.copy_last_command_output();
+                    }
+                }
+            });
+            window.add_action(&action);
+        }
+
+        {
+            // Rerun the last command (OSC 133)
+            let notebook = notebook.clone();
+            let tabs = Rc::clone(&tabs);
+            let action = gio::SimpleAction::new("rerun-last-command", None);
+            action.connect_activate(move |_, _| {
+                if let Some(page_idx) = notebook.current_page() {
+                    let tabs = tabs.borrow();
+                    if let Some(tab) = tabs.get(page_idx as usize) {
+                        tab.terminal.rerun_last_command();
+                    }
+                }
+            });
+            window.add_action(&action);
+        }
+
+        {
+            // Delete the last command's output (needs OSC 133 prompt marks)
+            let notebook = notebook.clone();
+            let tabs = Rc::clone(&tabs);
+            let action = gio::SimpleAction::new("clear-to-last-prompt", None);
+            action.connect_activate(move |_, _| {
+                if let Some(page_idx) = notebook.current_page() {
+                    let tabs = tabs.borrow();
+                    if let Some(tab) = tabs.get(page_idx as usize) {
+                        tab.terminal.clear_to_last_prompt();
+                    }
+                }
+            });
+            window.add_action(&action);
+        }
+
+        {
+            // Toggle a scrollback bookmark
+            let notebook = notebook.clone();
+            let tabs = Rc::clone(&tabs);
+            let action = gio::SimpleAction::new("set-mark", None);
+            action.connect_activate(move |_, _| {
+                if let Some(page_idx) = notebook.current_page() {
+                    let tabs = tabs.borrow();
+                    if let Some(tab) = tabs.get(page_idx as usize) {
+                        tab.terminal.set_mark();
+                    }
+                }
+            });
+            window.add_action(&action);
+        }
+
+        {
+            // Jump to the previous scrollback bookmark
+            let notebook = notebook.clone();
+            let tabs = Rc::clone(&tabs);
+            let action = gio::SimpleAction::new("jump-mark", None);
+            action.connect_activate(move |_, _| {
+                if let Some(page_idx) = notebook.current_page() {
+                    let tabs = tabs.borrow();
+                    if let Some(tab) = tabs.get(page_idx as usize) {
+                        tab.terminal.jump_to_mark();
+                    }
+                }
+            });
+            window.add_action(&action);
+        }
+
+        {
+            let notebook = notebook.clone();
+            let tabs = Rc::clone(&tabs);
+            let config = Rc::clone(&config);
+            let window_paste = window.clone();
             let action = gio::SimpleAction::new("paste", None);
             action.connect_activate(move |_, _| {
                 if let Some(display) = gdk::Display::default() {
                     let clipboard = display.clipboard();
                     let tabs_paste = Rc::clone(&tabs);
                     let notebook_paste = notebook.clone();
+                    let config_paste = Rc::clone(&config);
+                    let window_paste = window_paste.clone();
                     clipboard.read_text_async(None::<&gio::Cancellable>, move |result| {
                         if let Ok(Some(text)) = result {
                             // Warn on very large clipboard pastes (> 1 MB)
@@ -437,12 +919,13 @@ impl CtermWindow {
                                     text.len()
                                 );
                             }
-                            if let Some(page_idx) = notebook_paste.current_page() {
-                                let tabs = tabs_paste.borrow();
-                                if let Some(tab) = tabs.get(page_idx as usize) {
-                                    tab.terminal.write_str(&text);
-                                }
-                            }
+                            paste_into_current_tab(
+                                &window_paste,
+                                &notebook_paste,
+                                &tabs_paste,
+                                &config_paste.borrow(),
+                                &text,
+                            );
                         }
                     });
                 }
@@ -450,6 +933,59 @@ impl CtermWindow {
             window.add_action(&action);
         }
 
+        // Paste the primary selection (middle-click)
+        #[cfg(unix)]
+        {
+            let notebook = notebook.clone();
+            let tabs = Rc::clone(&tabs);
+            let config = Rc::clone(&config);
+            let window_paste = window.clone();
+            let action = gio::SimpleAction::new("paste-primary", None);
+            action.connect_activate(move |_, _| {
+                if let Some(display) = gdk::Display::default() {
+                    let primary = display.primary_clipboard();
+                    let tabs_paste = Rc::clone(&tabs);
+                    let notebook_paste = notebook.clone();
+                    let config_paste = Rc::clone(&config);
+                    let window_paste = window_paste.clone();
+                    primary.read_text_async(None::<&gio::Cancellable>, move |result| {
+                        if let Ok(Some(text)) = result {
+                            paste_into_current_tab(
+                                &window_paste,
+                                &notebook_paste,
+                                &tabs_paste,
+                                &config_paste.borrow(),
+                                &text,
+                            );
+                        }
+                    });
+                }
+            });
+            window.add_action(&action);
+        }
+
+        {
+            let notebook = notebook.clone();
+            let tabs = Rc::clone(&tabs);
+            let config = Rc::clone(&config);
+            let window_clone = window.clone();
+            let action = gio::SimpleAction::new("clipboard-history", None);
+            action.connect_activate(move |_, _| {
+                let notebook = notebook.clone();
+                let tabs = Rc::clone(&tabs);
+                let config = Rc::clone(&config);
+                let window = window_clone.clone();
+                dialogs::show_clipboard_history(
+                    &window_clone,
+                    cterm_app::clipboard_history::entries(),
+                    move |text| {
+                        paste_into_current_tab(&window, &notebook, &tabs, &config.borrow(), &text);
+                    },
+                );
+            });
+            window.add_action(&action);
+        }
+
         {
             // Select All
             let notebook_select = notebook.clone();
@@ -511,15 +1047,30 @@ impl CtermWindow {
                 let tabs_clone = Rc::clone(&tabs);
                 let notebook_clone = notebook.clone();
                 let tab_bar_clone = tab_bar.clone();
-                dialogs::show_set_color_dialog(&window_clone, move |color| {
-                    if let Some(page_idx) = notebook_clone.current_page() {
-                        let mut tabs = tabs_clone.borrow_mut();
-                        if let Some(tab) = tabs.get_mut(page_idx as usize) {
-                            tab_bar_clone.set_color(tab.id, color.as_deref());
-                            tab.color = color;
+                let (current_color, current_title_color) = notebook
+                    .current_page()
+                    .and_then(|idx| {
+                        let tabs = tabs.borrow();
+                        let tab = tabs.get(idx as usize)?;
+                        Some((tab.color.clone(), tab.title_color.clone()))
+                    })
+                    .unwrap_or_default();
+                dialogs::show_set_color_dialog(
+                    &window_clone,
+                    current_color.as_deref(),
+                    current_title_color.as_deref(),
+                    move |color, title_color| {
+                        if let Some(page_idx) = notebook_clone.current_page() {
+                            let mut tabs = tabs_clone.borrow_mut();
+                            if let Some(tab) = tabs.get_mut(page_idx as usize) {
+                                tab_bar_clone.set_color(tab.id, color.as_deref());
+                                tab_bar_clone.set_title_color(tab.id, title_color.as_deref());
+                                tab.color = color;
+                                tab.title_color = title_color;
+                            }
                         }
-                    }
-                });
+                    },
+                );
             });
             window.add_action(&action);
         }
@@ -532,33 +1083,91 @@ impl CtermWindow {
             action.connect_activate(move |_, _| {
                 let tabs = Rc::clone(&tabs);
                 let notebook = notebook.clone();
-                dialogs::show_find_dialog(&window_clone, move |text, case_sensitive, regex| {
-                    log::info!("Find: '{}' case={} regex={}", text, case_sensitive, regex);
-                    if let Some(page_idx) = notebook.current_page() {
-                        let tabs = tabs.borrow();
-                        if let Some(tab) = tabs.get(page_idx as usize) {
-                            let count = tab.terminal.find(&text, case_sensitive, regex);
-                            log::info!("Found {} matches", count);
+                let tabs_close = Rc::clone(&tabs);
+                let notebook_close = notebook.clone();
+                dialogs::show_find_dialog(
+                    &window_clone,
+                    move |text, case_sensitive, regex, scrollback_only| {
+                        log::info!(
+                            "Find: '{}' case={} regex={} scrollback_only={}",
+                            text,
+                            case_sensitive,
+                            regex,
+                            scrollback_only
+                        );
+                        if let Some(page_idx) = notebook.current_page() {
+                            let tabs = tabs.borrow();
+                            if let Some(tab) = tabs.get(page_idx as usize) {
+                                let count = tab.terminal.find(
+                                    &text,
+                                    case_sensitive,
+                                    regex,
+                                    scrollback_only,
+                                );
+                                log::info!("Found {} matches", count);
+                            }
                         }
-                    }
-                });
+                    },
+                    move || {
+                        if let Some(page_idx) = notebook_close.current_page() {
+                            let tabs = tabs_close.borrow();
+                            if let Some(tab) = tabs.get(page_idx as usize) {
+                                tab.terminal.clear_search();
+                            }
+                        }
+                    },
+                );
             });
             window.add_action(&action);
         }
 
         {
-            let action =
-                gio::SimpleAction::new("set-encoding", Some(&glib::VariantType::new("s").unwrap()));
-            action.connect_activate(|_, param| {
-                if let Some(encoding) = param.and_then(|p| p.get::<String>()) {
-                    if encoding == "utf8" {
-                        log::info!("Encoding set to UTF-8");
-                    } else {
-                        // Terminal currently only supports UTF-8
-                        log::warn!(
-                            "Encoding '{}' requested but only UTF-8 is currently supported",
-                            encoding
-                        );
+            let window_clone = window.clone();
+            let tabs = Rc::clone(&tabs);
+            let notebook = notebook.clone();
+            let action = gio::SimpleAction::new("filter-scrollback", None);
+            action.connect_activate(move |_, _| {
+                let window = window_clone.clone();
+                let tabs = Rc::clone(&tabs);
+                let notebook = notebook.clone();
+                dialogs::show_filter_dialog(
+                    &window_clone,
+                    move |pattern, case_sensitive, regex| {
+                        let Some(page_idx) = notebook.current_page() else {
+                            return;
+                        };
+                        let lines = match tabs.borrow().get(page_idx as usize) {
+                            Some(tab) => {
+                                tab.terminal
+                                    .extract_matches(&pattern, case_sensitive, regex)
+                            }
+                            None => return,
+                        };
+                        crate::filter_viewer::show_filter_results(&window, &pattern, &lines);
+                    },
+                );
+            });
+            window.add_action(&action);
+        }
+
+        {
+            // Character encoding of the current tab
+            let notebook = notebook.clone();
+            let tabs = Rc::clone(&tabs);
+            let action =
+                gio::SimpleAction::new("set-encoding", Some(&glib::VariantType::new("s").unwrap()));
+            action.connect_activate(move |_, param| {
+                let Some(name) = param.and_then(|p| p.get::<String>()) else {
+                    return;
+                };
+                let Some(encoding) = TerminalEncoding::from_name(&name) else {
+                    log::warn!("Unknown encoding '{}'", name);
+                    return;
+                };
+                if let Some(page_idx) = notebook.current_page() {
+                    let tabs = tabs.borrow();
+                    if let Some(tab) = tabs.get(page_idx as usize) {
+                        tab.terminal.set_encoding(encoding);
                     }
                 }
             });
@@ -586,6 +1195,47 @@ impl CtermWindow {
             window.add_action(&action);
         }
 
+        {
+            // Start or stop recording the current tab's session to a file
+            let window_clone = window.clone();
+            let tabs = Rc::clone(&tabs);
+            let notebook = notebook.clone();
+            let tab_bar = tab_bar.clone();
+            let config = Rc::clone(&config);
+            let action = gio::SimpleAction::new("toggle-recording", None);
+            action.connect_activate(move |_, _| {
+                let Some(page_idx) = notebook.current_page() else {
+                    return;
+                };
+                let tabs = tabs.borrow();
+                let Some(tab) = tabs.get(page_idx as usize) else {
+                    return;
+                };
+                match tab.terminal.toggle_recording(&config.borrow().general) {
+                    Ok(RecordingToggle::Started(path)) => {
+                        tab_bar.set_recording(tab.id, Some(&path));
+                    }
+                    Ok(RecordingToggle::Stopped(_)) => {
+                        tab_bar.set_recording(tab.id, None);
+                    }
+                    Err(e) => {
+                        log::error!("Session recording failed: {}", e);
+                        tab_bar.set_recording(tab.id, None);
+                        let dialog = gtk4::MessageDialog::new(
+                            Some(&window_clone),
+                            gtk4::DialogFlags::MODAL,
+                            gtk4::MessageType::Error,
+                            gtk4::ButtonsType::Ok,
+                            format!("Session recording failed: {}", e),
+                        );
+                        dialog.connect_response(|d, _| d.close());
+                        dialog.present();
+                    }
+                }
+            });
+            window.add_action(&action);
+        }
+
         {
             let tabs = Rc::clone(&tabs);
             let notebook = notebook.clone();
@@ -718,7 +1368,7 @@ impl CtermWindow {
                                     if let Some(page_idx) = notebook.current_page() {
                                         tabs_borrow
                                             .get(page_idx as usize)
-                                            .and_then(|entry| entry.terminal.foreground_cwd())
+                                            .and_then(|entry| entry.terminal.working_directory())
                                     } else {
                                         None
                                     }
@@ -769,12 +1419,172 @@ impl CtermWindow {
                         log::info!("Configuration saved to disk");
                     }
                     // Update internal config state
+                    cterm_app::clipboard_history::configure(&new_config.general);
                     *config_for_save.borrow_mut() = new_config;
                 });
             });
             window.add_action(&action);
         }
 
+        // Import a color scheme from another terminal as the custom theme
+        {
+            let window_clone = window.clone();
+            let config = Rc::clone(&config);
+            let action = gio::SimpleAction::new("import-theme", None);
+            action.connect_activate(move |_, _| {
+                let file_chooser = gtk4::FileChooserDialog::new(
+                    Some("Import Theme"),
+                    Some(&window_clone),
+                    gtk4::FileChooserAction::Open,
+                    &[
+                        ("Cancel", gtk4::ResponseType::Cancel),
+                        ("Import", gtk4::ResponseType::Accept),
+                    ],
+                );
+                let filter = gtk4::FileFilter::new();
+                filter.set_name(Some("Color schemes (iTerm2, Alacritty, Windows Terminal)"));
+                for pattern in ["*.itermcolors", "*.toml", "*.yml", "*.yaml", "*.json"] {
+                    filter.add_pattern(pattern);
+                }
+                file_chooser.add_filter(&filter);
+
+                let window = window_clone.clone();
+                let config = Rc::clone(&config);
+                file_chooser.connect_response(move |dialog, response| {
+                    let path = dialog.file().and_then(|f| f.path());
+                    dialog.close();
+                    let (Some(path), gtk4::ResponseType::Accept) = (path, response) else {
+                        return;
+                    };
+
+                    let result = cterm_app::config::import_theme(&mut config.borrow_mut(), &path);
+                    let (message_type, message) = match result {
+                        Ok(theme) => {
+                            log::info!("Imported theme {:?} from {:?}", theme.name, path);
+                            (
+                                gtk4::MessageType::Info,
+                                format!(
+                                    "Imported theme \"{}\".\n\nRestart cterm to use it.",
+                                    theme.name
+                                ),
+                            )
+                        }
+                        Err(e) => {
+                            log::error!("Failed to import theme from {:?}: {}", path, e);
+                            (gtk4::MessageType::Error, e.to_string())
+                        }
+                    };
+                    let message_dialog = gtk4::MessageDialog::new(
+                        Some(&window),
+                        gtk4::DialogFlags::MODAL,
+                        message_type,
+                        gtk4::ButtonsType::Ok,
+                        message,
+                    );
+                    message_dialog.connect_response(|d, _| d.close());
+                    message_dialog.present();
+                });
+                file_chooser.present();
+            });
+            window.add_action(&action);
+        }
+
+        // Export the keyboard shortcuts to a file of their own
+        {
+            let window_clone = window.clone();
+            let config = Rc::clone(&config);
+            let action = gio::SimpleAction::new("export-keybindings", None);
+            action.connect_activate(move |_, _| {
+                let file_chooser = gtk4::FileChooserDialog::new(
+                    Some("Export Keybindings"),
+                    Some(&window_clone),
+                    gtk4::FileChooserAction::Save,
+                    &[
+                        ("Cancel", gtk4::ResponseType::Cancel),
+                        ("Export", gtk4::ResponseType::Accept),
+                    ],
+                );
+                file_chooser.set_current_name("keybindings.toml");
+
+                let config = Rc::clone(&config);
+                file_chooser.connect_response(move |dialog, response| {
+                    if response == gtk4::ResponseType::Accept {
+                        if let Some(path) = dialog.file().and_then(|file| file.path()) {
+                            let shortcuts = &config.borrow().shortcuts;
+                            match ShortcutManager::to_file(shortcuts, &path) {
+                                Ok(()) => log::info!("Exported keybindings to {:?}", path),
+                                Err(e) => log::error!("Failed to export keybindings: {}", e),
+                            }
+                        }
+                    }
+                    dialog.close();
+                });
+                file_chooser.present();
+            });
+            window.add_action(&action);
+        }
+
+        // Replace the keyboard shortcuts with those of a keybindings file
+        {
+            let window_clone = window.clone();
+            let config = Rc::clone(&config);
+            let action = gio::SimpleAction::new("import-keybindings", None);
+            action.connect_activate(move |_, _| {
+                let file_chooser = gtk4::FileChooserDialog::new(
+                    Some("Import Keybindings"),
+                    Some(&window_clone),
+                    gtk4::FileChooserAction::Open,
+                    &[
+                        ("Cancel", gtk4::ResponseType::Cancel),
+                        ("Import", gtk4::ResponseType::Accept),
+                    ],
+                );
+                let filter = gtk4::FileFilter::new();
+                filter.set_name(Some("Keybindings (*.toml)"));
+                filter.add_pattern("*.toml");
+                file_chooser.add_filter(&filter);
+
+                let window = window_clone.clone();
+                let config = Rc::clone(&config);
+                file_chooser.connect_response(move |dialog, response| {
+                    let path = dialog.file().and_then(|f| f.path());
+                    dialog.close();
+                    let (Some(path), gtk4::ResponseType::Accept) = (path, response) else {
+                        return;
+                    };
+
+                    let result =
+                        cterm_app::config::import_keybindings(&mut config.borrow_mut(), &path);
+                    let (message_type, message) = match result {
+                        Ok(conflicts) => {
+                            log::info!("Imported keybindings from {:?}", path);
+                            let mut message =
+                                "Imported keybindings.\n\nRestart cterm to use them.".to_string();
+                            if let Some(note) = describe_conflicts(&conflicts) {
+                                message.push_str(&format!("\n\n{}", note));
+                            }
+                            (gtk4::MessageType::Info, message)
+                        }
+                        Err(e) => {
+                            log::error!("Failed to import keybindings from {:?}: {}", path, e);
+                            (gtk4::MessageType::Error, e.to_string())
+                        }
+                    };
+                    let message_dialog = gtk4::MessageDialog::new(
+                        Some(&window),
+                        gtk4::DialogFlags::MODAL,
+                        message_type,
+                        gtk4::ButtonsType::Ok,
+                        message,
+                    );
+                    message_dialog.connect_response(|d, _| d.close());
+                    message_dialog.present();
+                });
+                file_chooser.present();
+            });
+            window.add_action(&action);
+        }
+
         // Check for updates action
         {
             let window_clone = window.clone();
@@ -785,6 +1595,17 @@ impl CtermWindow {
             window.add_action(&action);
         }
 
+        // Detach sessions action
+        #[cfg(unix)]
+        {
+            let window_clone = window.clone();
+            let action = gio::SimpleAction::new("detach-sessions", None);
+            action.connect_activate(move |_, _| {
+                detach_all_sessions(&window_clone);
+            });
+            window.add_action(&action);
+        }
+
         // Execute upgrade action (called from update dialog)
         #[cfg(unix)]
         {
@@ -805,55 +1626,10 @@ impl CtermWindow {
                     let mut upgrade_state =
                         cterm_app::upgrade::UpgradeState::new(env!("CARGO_PKG_VERSION"));
 
-                    // Collect window state
-                    let mut window_state = cterm_app::upgrade::WindowUpgradeState::new();
-                    window_state.width = window_clone.default_width();
-                    window_state.height = window_clone.default_height();
-                    window_state.maximized = window_clone.is_maximized();
-                    window_state.fullscreen = window_clone.is_fullscreen();
-
                     // Collect FDs for terminals
                     let mut fds: Vec<std::os::unix::io::RawFd> = Vec::new();
-
-                    for tab in tabs_borrowed.iter() {
-                        let mut tab_state = cterm_app::upgrade::TabUpgradeState::new(tab.id, 0, 0);
-                        tab_state.title = tab.title.clone();
-                        if tab.title_locked {
-                            tab_state.custom_title = Some(tab.title.clone());
-                        }
-
-                        // Export terminal state
-                        tab_state.terminal = tab.terminal.export_state();
-
-                        tab_state.color = tab.color.clone();
-
-                        // Try to get PTY file descriptor
-                        let term = tab.terminal.terminal().lock();
-                        tab_state.cwd = term
-                            .foreground_cwd()
-                            .map(|p| p.to_string_lossy().into_owned());
-                        if let Some(fd) = term.dup_pty_fd() {
-                            tab_state.pty_fd_index = fds.len();
-                            tab_state.child_pid = term.child_pid().unwrap_or(0);
-                            fds.push(fd);
-                            log::info!(
-                                "Tab {}: Got PTY FD {} (index {}), child_pid={}",
-                                tab.id,
-                                fd,
-                                tab_state.pty_fd_index,
-                                tab_state.child_pid
-                            );
-                        } else {
-                            log::warn!("Tab {}: Failed to get PTY FD", tab.id);
-                        }
-                        drop(term);
-
-                        window_state.tabs.push(tab_state);
-                    }
-
-                    // Set active tab
-                    // Note: We'd need access to the notebook to know which tab is active
-                    window_state.active_tab = 0;
+                    let window_state =
+                        window_upgrade_state(&window_clone, &tabs_borrowed, &mut fds);
 
                     upgrade_state.windows.push(window_state);
 
@@ -934,7 +1710,8 @@ impl CtermWindow {
                         u32,
                     )> = Vec::new();
 
-                    for tab in tabs_borrowed.iter() {
+                    // Incognito tabs are never serialized; they close with the old process
+                    for tab in tabs_borrowed.iter().filter(|t| !t.terminal.is_incognito()) {
                         let mut tab_state = cterm_app::upgrade::TabUpgradeState::new(tab.id, 0, 0);
                         tab_state.title = tab.title.clone();
                         if tab.title_locked {
@@ -945,6 +1722,7 @@ impl CtermWindow {
                         tab_state.terminal = tab.terminal.export_state();
 
                         tab_state.color = tab.color.clone();
+                        tab_state.title_color = tab.title_color.clone();
 
                         // Try to get PTY handles
                         let term = tab.terminal.terminal().lock();
@@ -1121,6 +1899,93 @@ impl CtermWindow {
             window.add_action(&action);
         }
 
+        {
+            // Send Bytes - write hand-typed bytes to the PTY
+            let window_clone = window.clone();
+            let tabs = Rc::clone(&tabs);
+            let notebook = notebook.clone();
+            let action = gio::SimpleAction::new("debug-send-bytes", None);
+            action.connect_activate(move |_, _| {
+                let tabs = Rc::clone(&tabs);
+                let notebook = notebook.clone();
+                dialogs::show_send_bytes_dialog(&window_clone, move |bytes| {
+                    if let Some(page_idx) = notebook.current_page() {
+                        if let Some(tab) = tabs.borrow().get(page_idx as usize) {
+                            tab.terminal.write_bytes(&bytes);
+                        }
+                    }
+                });
+            });
+            window.add_action(&action);
+        }
+
+        // Replay File - feed a captured byte stream to the current tab
+        let replay: Rc<RefCell<Option<Replay>>> = Rc::new(RefCell::new(None));
+        {
+            let window_clone = window.clone();
+            let tabs = Rc::clone(&tabs);
+            let notebook = notebook.clone();
+            let config = Rc::clone(&config);
+            let replay = Rc::clone(&replay);
+            let action = gio::SimpleAction::new("debug-replay-file", None);
+            action.connect_activate(move |_, _| {
+                let file_chooser = gtk4::FileChooserDialog::new(
+                    Some("Replay File"),
+                    Some(&window_clone),
+                    gtk4::FileChooserAction::Open,
+                    &[
+                        ("Cancel", gtk4::ResponseType::Cancel),
+                        ("Replay", gtk4::ResponseType::Accept),
+                    ],
+                );
+
+                let tabs = Rc::clone(&tabs);
+                let notebook = notebook.clone();
+                let config = Rc::clone(&config);
+                let replay = Rc::clone(&replay);
+                file_chooser.connect_response(move |dialog, response| {
+                    let path = dialog.file().and_then(|f| f.path());
+                    dialog.close();
+                    let (Some(path), gtk4::ResponseType::Accept) = (path, response) else {
+                        return;
+                    };
+
+                    let data = match std::fs::read(&path) {
+                        Ok(data) => data,
+                        Err(e) => {
+                            log::error!("Failed to read replay file {:?}: {}", path, e);
+                            return;
+                        }
+                    };
+                    let Some(page_idx) = notebook.current_page() else {
+                        return;
+                    };
+                    let tabs = tabs.borrow();
+                    let Some(tab) = tabs.get(page_idx as usize) else {
+                        return;
+                    };
+                    log::info!("Replaying {} bytes from {:?}", data.len(), path);
+                    // Replacing a running replay stops it
+                    *replay.borrow_mut() = tab
+                        .terminal
+                        .replay(data, config.borrow().general.replay_bytes_per_sec);
+                });
+                file_chooser.present();
+            });
+            window.add_action(&action);
+        }
+
+        {
+            // Stop Replay
+            let action = gio::SimpleAction::new("debug-stop-replay", None);
+            action.connect_activate(move |_, _| {
+                if replay.borrow_mut().take().is_some() {
+                    log::info!("Replay stopped");
+                }
+            });
+            window.add_action(&action);
+        }
+
         {
             // Dump State - dump current terminal state for debugging
             let tabs = Rc::clone(&tabs);
@@ -1131,6 +1996,8 @@ impl CtermWindow {
                 log::info!("Number of tabs: {}", tabs.len());
                 for (i, tab) in tabs.iter().enumerate() {
                     log::info!("Tab {}: id={}, title=\"{}\"", i, tab.id, tab.title);
+                    let (images, bytes) = tab.terminal.terminal().lock().screen().image_memory();
+                    log::info!("  Images: {} ({} bytes of pixel data)", images, bytes);
                 }
             });
             window.add_action(&action);
@@ -1170,12 +2037,21 @@ impl CtermWindow {
         let file_manager = Rc::clone(&self.file_manager);
         let notification_bar = self.notification_bar.clone();
 
-        key_controller.connect_key_pressed(move |_, keyval, _keycode, state| {
-            // Convert GTK modifiers to our modifiers
+        key_controller.connect_key_pressed(move |controller, keyval, keycode, state| {
+            // Convert GTK modifiers to our modifiers, leaving out the ones
+            // the layout used to produce the key (AltGr)
+            let state = unconsumed_state(controller, state);
             let modifiers = gtk_modifiers_to_modifiers(state);
 
-            // Convert keyval to our key code
-            if let Some(key) = keyval_to_keycode(keyval) {
+            // Convert keyval to our key code. When the active layout doesn't
+            // produce a shortcut key (e.g. Cyrillic), use what the same
+            // physical key produces in the first layout
+            let key = keyval_to_keycode(keyval).or_else(|| {
+                let display = controller.widget()?.display();
+                let (base_keyval, ..) = display.translate_key(keycode, state, 0)?;
+                keyval_to_keycode(base_keyval)
+            });
+            if let Some(key) = key {
                 // Check for shortcut match
                 if let Some(action) = shortcuts.match_event(key, modifiers) {
                     match action {
@@ -1187,7 +2063,7 @@ impl CtermWindow {
                                 if let Some(page_idx) = notebook.current_page() {
                                     tabs_borrow
                                         .get(page_idx as usize)
-                                        .and_then(|entry| entry.terminal.foreground_cwd())
+                                        .and_then(|entry| entry.terminal.working_directory())
                                 } else {
                                     None
                                 }
@@ -1207,6 +2083,16 @@ impl CtermWindow {
                                 &file_manager,
                                 &notification_bar,
                                 cwd,
+                                None,
+                                false,
+                            );
+                            return glib::Propagation::Stop;
+                        }
+                        Action::NewIncognitoTab => {
+                            gtk4::prelude::ActionGroupExt::activate_action(
+                                &window,
+                                "new-incognito-tab",
+                                None,
                             );
                             return glib::Propagation::Stop;
                         }
@@ -1276,54 +2162,132 @@ impl CtermWindow {
                                 let clipboard = display.clipboard();
                                 let tabs_paste = Rc::clone(&tabs);
                                 let notebook_paste = notebook.clone();
+                                let config_paste = Rc::clone(&config);
+                                let window_paste = window.clone();
                                 clipboard.read_text_async(
                                     None::<&gio::Cancellable>,
                                     move |result| {
                                         if let Ok(Some(text)) = result {
-                                            // Find current terminal and write
-                                            if let Some(page_idx) = notebook_paste.current_page() {
-                                                let tabs = tabs_paste.borrow();
-                                                if let Some(tab) = tabs.get(page_idx as usize) {
-                                                    tab.terminal.write_str(&text);
-                                                }
-                                            }
+                                            paste_into_current_tab(
+                                                &window_paste,
+                                                &notebook_paste,
+                                                &tabs_paste,
+                                                &config_paste.borrow(),
+                                                &text,
+                                            );
                                         }
                                     },
                                 );
                             }
                             return glib::Propagation::Stop;
                         }
-                        Action::ZoomIn => {
-                            if let Some(page_idx) = notebook.current_page() {
-                                let tabs_ref = tabs.borrow();
-                                if let Some(tab) = tabs_ref.get(page_idx as usize) {
-                                    tab.terminal.zoom_in();
-                                }
-                            }
+                        Action::ZoomIn => {
+                            if let Some(page_idx) = notebook.current_page() {
+                                let tabs_ref = tabs.borrow();
+                                if let Some(tab) = tabs_ref.get(page_idx as usize) {
+                                    tab.terminal.zoom_in();
+                                }
+                            }
+                            return glib::Propagation::Stop;
+                        }
+                        Action::ZoomOut => {
+                            if let Some(page_idx) = notebook.current_page() {
+                                let tabs_ref = tabs.borrow();
+                                if let Some(tab) = tabs_ref.get(page_idx as usize) {
+                                    tab.terminal.zoom_out();
+                                }
+                            }
+                            return glib::Propagation::Stop;
+                        }
+                        Action::ZoomReset => {
+                            if let Some(page_idx) = notebook.current_page() {
+                                let tabs_ref = tabs.borrow();
+                                if let Some(tab) = tabs_ref.get(page_idx as usize) {
+                                    tab.terminal.zoom_reset();
+                                }
+                            }
+                            return glib::Propagation::Stop;
+                        }
+                        Action::IncreaseOpacity | Action::DecreaseOpacity => {
+                            let increase = *action == Action::IncreaseOpacity;
+                            step_window_opacity(&window, &config.borrow(), increase);
+                            return glib::Propagation::Stop;
+                        }
+                        Action::CloseWindow => {
+                            window.close();
+                            return glib::Propagation::Stop;
+                        }
+                        Action::ReopenClosedWindow => {
+                            gtk4::prelude::ActionGroupExt::activate_action(
+                                &window,
+                                "reopen-closed-window",
+                                None,
+                            );
+                            return glib::Propagation::Stop;
+                        }
+                        Action::CopyLastCommandOutput => {
+                            gtk4::prelude::ActionGroupExt::activate_action(
+                                &window,
+                                "copy-last-output",
+                                None,
+                            );
+                            return glib::Propagation::Stop;
+                        }
+                        Action::RerunLastCommand => {
+                            gtk4::prelude::ActionGroupExt::activate_action(
+                                &window,
+                                "rerun-last-command",
+                                None,
+                            );
+                            return glib::Propagation::Stop;
+                        }
+                        Action::ClearToLastPrompt => {
+                            gtk4::prelude::ActionGroupExt::activate_action(
+                                &window,
+                                "clear-to-last-prompt",
+                                None,
+                            );
+                            return glib::Propagation::Stop;
+                        }
+                        Action::ClipboardHistory => {
+                            gtk4::prelude::ActionGroupExt::activate_action(
+                                &window,
+                                "clipboard-history",
+                                None,
+                            );
+                            return glib::Propagation::Stop;
+                        }
+                        Action::ToggleRecording => {
+                            gtk4::prelude::ActionGroupExt::activate_action(
+                                &window,
+                                "toggle-recording",
+                                None,
+                            );
+                            return glib::Propagation::Stop;
+                        }
+                        Action::SetMark => {
+                            gtk4::prelude::ActionGroupExt::activate_action(
+                                &window, "set-mark", None,
+                            );
                             return glib::Propagation::Stop;
                         }
-                        Action::ZoomOut => {
-                            if let Some(page_idx) = notebook.current_page() {
-                                let tabs_ref = tabs.borrow();
-                                if let Some(tab) = tabs_ref.get(page_idx as usize) {
-                                    tab.terminal.zoom_out();
-                                }
-                            }
+                        Action::JumpMark => {
+                            gtk4::prelude::ActionGroupExt::activate_action(
+                                &window,
+                                "jump-mark",
+                                None,
+                            );
                             return glib::Propagation::Stop;
                         }
-                        Action::ZoomReset => {
+                        Action::SendText(text) => {
                             if let Some(page_idx) = notebook.current_page() {
                                 let tabs_ref = tabs.borrow();
                                 if let Some(tab) = tabs_ref.get(page_idx as usize) {
-                                    tab.terminal.zoom_reset();
+                                    tab.terminal.write_str(text);
                                 }
                             }
                             return glib::Propagation::Stop;
                         }
-                        Action::CloseWindow => {
-                            window.close();
-                            return glib::Propagation::Stop;
-                        }
                         Action::QuickOpenTemplate => {
                             // Activate the quick-open action
                             gtk4::prelude::ActionGroupExt::activate_action(
@@ -1422,6 +2386,7 @@ impl CtermWindow {
         let tab_bar = self.tab_bar.clone();
         let tabs = Rc::clone(&self.tabs);
         let notebook = self.notebook.clone();
+        let window_title = self.config.borrow().general.window_title("cterm");
 
         self.window.connect_is_active_notify(move |win| {
             let is_active = win.is_active();
@@ -1435,11 +2400,18 @@ impl CtermWindow {
             }
 
             if is_active {
+                // The tint of a finished command goes once it's been seen
+                if let Some(page_idx) = notebook.current_page() {
+                    if let Some(tab) = tabs.borrow().get(page_idx as usize) {
+                        tab_bar.set_exit_status(tab.id, None);
+                    }
+                }
+
                 // Window became active, clear bell indicator
                 let mut bell = has_bell.borrow_mut();
                 if *bell {
                     *bell = false;
-                    window.set_title(Some("cterm"));
+                    window.set_title(Some(&window_title));
 
                     // Clear bell on the currently active tab
                     if let Some(page_idx) = notebook.current_page() {
@@ -1466,6 +2438,7 @@ impl CtermWindow {
         let tabs = Rc::clone(&self.tabs);
 
         focus_controller.connect_key_pressed(move |controller, keyval, _keycode, state| {
+            let state = unconsumed_state(controller, state);
             // Skip modifier keys and menu activation keys
             let is_modifier = matches!(
                 keyval,
@@ -1693,7 +2666,7 @@ impl CtermWindow {
                 if let Some(page_idx) = notebook.current_page() {
                     tabs_borrow
                         .get(page_idx as usize)
-                        .and_then(|entry| entry.terminal.foreground_cwd())
+                        .and_then(|entry| entry.terminal.working_directory())
                 } else {
                     None
                 }
@@ -1713,9 +2686,52 @@ impl CtermWindow {
                 &file_manager,
                 &notification_bar,
                 cwd,
+                None,
+                false,
             );
         });
 
+        // New tab right after a given tab (middle-click or context menu)
+        {
+            let notebook = self.notebook.clone();
+            let tabs = Rc::clone(&self.tabs);
+            let next_tab_id = Rc::clone(&self.next_tab_id);
+            let config = self.config.clone();
+            let theme = self.theme.clone();
+            let tab_bar = self.tab_bar.clone();
+            let window = self.window.clone();
+            let has_bell = Rc::clone(&self.has_bell);
+            let file_manager = Rc::clone(&self.file_manager);
+            let notification_bar = self.notification_bar.clone();
+            self.tab_bar.set_on_new_tab_after(move |tab_id| {
+                let Some(idx) = tabs.borrow().iter().position(|t| t.id == tab_id) else {
+                    return;
+                };
+
+                // Start in the same directory as the tab it's placed next to
+                #[cfg(unix)]
+                let cwd = tabs.borrow()[idx].terminal.working_directory();
+                #[cfg(not(unix))]
+                let cwd: Option<String> = None;
+
+                create_new_tab(
+                    &notebook,
+                    &tabs,
+                    &next_tab_id,
+                    &config,
+                    &theme,
+                    &tab_bar,
+                    &window,
+                    &has_bell,
+                    &file_manager,
+                    &notification_bar,
+                    cwd,
+                    Some(idx as u32 + 1),
+                    false,
+                );
+            });
+        }
+
         // Rename tab (right-click context menu)
         {
             let tabs = Rc::clone(&self.tabs);
@@ -1742,6 +2758,29 @@ impl CtermWindow {
             });
         }
 
+        // Pin or unpin tab (right-click context menu)
+        {
+            let notebook = self.notebook.clone();
+            let tabs = Rc::clone(&self.tabs);
+            let tab_bar = self.tab_bar.clone();
+            self.tab_bar.set_on_toggle_pin(move |tab_id| {
+                let mut tabs = tabs.borrow_mut();
+                let Some(idx) = tabs.iter().position(|t| t.id == tab_id) else {
+                    return;
+                };
+                let pin = !tabs[idx].pinned;
+                let pinned = tabs.iter().filter(|t| t.pinned).count();
+                let target = pin_target(pinned, pin);
+
+                let mut entry = tabs.remove(idx);
+                entry.pinned = pin;
+                notebook.reorder_child(entry.terminal.widget(), Some(target as u32));
+                tabs.insert(target, entry);
+                tab_bar.move_tab(tab_id, target);
+                tab_bar.set_pinned(tab_id, pin);
+            });
+        }
+
         // Set tab color (right-click context menu)
         {
             let tabs = Rc::clone(&self.tabs);
@@ -1750,13 +2789,26 @@ impl CtermWindow {
             self.tab_bar.set_on_set_color(move |tab_id| {
                 let tab_bar_clone = tab_bar.clone();
                 let tabs_clone = Rc::clone(&tabs);
-                dialogs::show_set_color_dialog(&window, move |color| {
-                    let mut tabs = tabs_clone.borrow_mut();
-                    if let Some(tab) = tabs.iter_mut().find(|t| t.id == tab_id) {
-                        tab_bar_clone.set_color(tab_id, color.as_deref());
-                        tab.color = color;
-                    }
-                });
+                let (current_color, current_title_color) = tabs
+                    .borrow()
+                    .iter()
+                    .find(|t| t.id == tab_id)
+                    .map(|t| (t.color.clone(), t.title_color.clone()))
+                    .unwrap_or_default();
+                dialogs::show_set_color_dialog(
+                    &window,
+                    current_color.as_deref(),
+                    current_title_color.as_deref(),
+                    move |color, title_color| {
+                        let mut tabs = tabs_clone.borrow_mut();
+                        if let Some(tab) = tabs.iter_mut().find(|t| t.id == tab_id) {
+                            tab_bar_clone.set_color(tab_id, color.as_deref());
+                            tab_bar_clone.set_title_color(tab_id, title_color.as_deref());
+                            tab.color = color;
+                            tab.title_color = title_color;
+                        }
+                    },
+                );
             });
         }
     }
@@ -1818,7 +2870,162 @@ impl CtermWindow {
         // No process detection on non-Unix platforms
     }
 
-    /// Create a new tab
+    /// Set up tracking of this window for "Reopen Closed Window"
+    fn setup_closed_window_tracking(&self) {
+        let notebook = self.notebook.clone();
+        let tabs = Rc::clone(&self.tabs);
+        let config = Rc::clone(&self.config);
+
+        self.window.connect_destroy(move |window| {
+            if !config.borrow().general.remember_closed_windows {
+                return;
+            }
+            if let Some(closed) = snapshot_window(window, &notebook, &tabs) {
+                CLOSED_WINDOWS.with(|c| c.borrow_mut().push(closed));
+            }
+        });
+    }
+
+    /// Track this window in `OPEN_WINDOWS` until it is destroyed
+    fn register_open_window(&self) {
+        OPEN_WINDOWS.with(|windows| {
+            windows.borrow_mut().push(OpenWindow {
+                window: self.window.downgrade(),
+                tabs: Rc::downgrade(&self.tabs),
+            })
+        });
+
+        self.window.connect_destroy(|window| {
+            OPEN_WINDOWS.with(|windows| {
+                windows
+                    .borrow_mut()
+                    .retain(|w| w.window.upgrade().is_some_and(|w| &w != window));
+            });
+        });
+    }
+
+    /// Recreate the tabs of a closed window
+    fn restore_closed_tabs(&self, closed: &WindowSessionState) {
+        let (width, height) = (closed.geometry.width as i32, closed.geometry.height as i32);
+        if width > 0 && height > 0 {
+            self.window.set_default_size(width, height);
+        }
+        if closed.geometry.maximized {
+            self.window.maximize();
+        }
+
+        for tab in &closed.tabs {
+            let count = self.tabs.borrow().len();
+            create_new_tab(
+                &self.notebook,
+                &self.tabs,
+                &self.next_tab_id,
+                &self.config,
+                &self.theme,
+                &self.tab_bar,
+                &self.window,
+                &self.has_bell,
+                &self.file_manager,
+                &self.notification_bar,
+                tab.cwd.as_ref().map(|p| p.to_string_lossy().into_owned()),
+                None,
+                false,
+            );
+
+            let mut tabs = self.tabs.borrow_mut();
+            if tabs.len() == count {
+                continue;
+            }
+            let Some(entry) = tabs.last_mut() else {
+                continue;
+            };
+            if let Some(ref title) = tab.custom_title {
+                entry.title = title.clone();
+                entry.title_locked = true;
+                self.tab_bar.set_title(entry.id, title);
+            }
+            if let Some(ref color) = tab.color {
+                entry.color = Some(color.clone());
+                self.tab_bar.set_color(entry.id, Some(color));
+            }
+            if let Some(ref title_color) = tab.title_color {
+                entry.title_color = Some(title_color.clone());
+                self.tab_bar.set_title_color(entry.id, Some(title_color));
+            }
+            // Pinned tabs were saved first, so they are already in place
+            if tab.pinned {
+                entry.pinned = true;
+                self.tab_bar.set_pinned(entry.id, true);
+            }
+        }
+
+        let n = self.notebook.n_pages();
+        if n == 0 {
+            self.new_tab();
+            return;
+        }
+        self.notebook
+            .set_current_page(Some((closed.active_tab as u32).min(n - 1)));
+        sync_tab_bar_active(&self.tab_bar, &self.tabs, &self.notebook);
+    }
+
+    /// Open the tabs of a workspace window, in order
+    fn open_workspace_tabs(&self, workspace_window: &WorkspaceWindow) {
+        let (width, height) = (
+            workspace_window.width as i32,
+            workspace_window.height as i32,
+        );
+        if width > 0 && height > 0 {
+            self.window.set_default_size(width, height);
+        }
+        if workspace_window.maximized {
+            self.window.maximize();
+        }
+
+        let templates = cterm_app::config::load_sticky_tabs().unwrap_or_default();
+        for tab in &workspace_window.tabs {
+            let template = match tab.to_template(&templates) {
+                Ok(template) => template,
+                Err(e) => {
+                    log::error!("Skipping workspace tab: {}", e);
+                    continue;
+                }
+            };
+            let count = self.tabs.borrow().len();
+            create_tab_from_template(
+                &self.notebook,
+                &self.tabs,
+                &self.next_tab_id,
+                &self.config,
+                &self.theme,
+                &self.tab_bar,
+                &self.window,
+                &self.has_bell,
+                &self.file_manager,
+                &self.notification_bar,
+                &template,
+            );
+
+            let mut tabs = self.tabs.borrow_mut();
+            if tab.pinned && tabs.len() > count {
+                if let Some(entry) = tabs.last_mut() {
+                    entry.pinned = true;
+                    self.tab_bar.set_pinned(entry.id, true);
+                }
+            }
+        }
+
+        let n = self.notebook.n_pages();
+        if n == 0 {
+            self.new_tab_with_cwd(None);
+            return;
+        }
+        self.notebook
+            .set_current_page(Some((workspace_window.active_tab as u32).min(n - 1)));
+        sync_tab_bar_active(&self.tab_bar, &self.tabs, &self.notebook);
+    }
+
+    /// Create a new tab in the working directory of the active one
     pub fn new_tab(&self) {
         // Get the current working directory from the active terminal
         #[cfg(unix)]
@@ -1826,7 +3033,7 @@ impl CtermWindow {
             let tabs = self.tabs.borrow();
             if let Some(page_idx) = self.notebook.current_page() {
                 tabs.get(page_idx as usize)
-                    .and_then(|entry| entry.terminal.foreground_cwd())
+                    .and_then(|entry| entry.terminal.working_directory())
             } else {
                 None
             }
@@ -1834,6 +3041,31 @@ impl CtermWindow {
         #[cfg(not(unix))]
         let cwd: Option<String> = None;
 
+        self.new_tab_with_cwd(cwd);
+    }
+
+    /// Create a new tab from a template, or a shell if that fails
+    fn new_template_tab(&self, template: &StickyTabConfig) {
+        create_tab_from_template(
+            &self.notebook,
+            &self.tabs,
+            &self.next_tab_id,
+            &self.config,
+            &self.theme,
+            &self.tab_bar,
+            &self.window,
+            &self.has_bell,
+            &self.file_manager,
+            &self.notification_bar,
+            template,
+        );
+        if self.tabs.borrow().is_empty() {
+            self.new_tab_with_cwd(None);
+        }
+    }
+
+    /// Create a new tab starting in `cwd` (or the configured directory)
+    fn new_tab_with_cwd(&self, cwd: Option<String>) {
         create_new_tab(
             &self.notebook,
             &self.tabs,
@@ -1846,10 +3078,155 @@ impl CtermWindow {
             &self.file_manager,
             &self.notification_bar,
             cwd,
+            None,
+            false,
         );
     }
 }
 
+/// Open every window of a workspace
+pub fn open_workspace(app: &Application, config: &Config, theme: &Theme, workspace: &Workspace) {
+    log::info!(
+        "Opening workspace {} with {} window(s)",
+        workspace.name.as_deref().unwrap_or("(unnamed)"),
+        workspace.windows.len()
+    );
+    for workspace_window in &workspace.windows {
+        CtermWindow::from_workspace(app, config, theme, workspace_window).present();
+    }
+}
+
+/// Paste clipboard text into the current tab, asking first if its foreground
+/// program is listed in `confirm_paste_into`
+fn paste_into_current_tab(
+    window: &ApplicationWindow,
+    notebook: &Notebook,
+    tabs: &Rc<RefCell<Vec<TabEntry>>>,
+    config: &Config,
+    text: &str,
+) {
+    let Some(page_idx) = notebook.current_page() else {
+        return;
+    };
+    let Some(tab_id) = tabs.borrow().get(page_idx as usize).map(|t| t.id) else {
+        return;
+    };
+
+    #[cfg(unix)]
+    let process = tabs.borrow()[page_idx as usize]
+        .terminal
+        .foreground_process_name();
+    #[cfg(not(unix))]
+    let process: Option<String> = None;
+
+    let text = config.general.paste_text(text);
+    let paste_text = text.to_string();
+    let tabs = Rc::clone(tabs);
+    dialogs::confirm_paste(
+        Some(window.upcast_ref()),
+        &config.general,
+        process,
+        text,
+        move || {
+            // The tab may have closed while the confirmation was open
+            if let Some(tab) = tabs.borrow().iter().find(|t| t.id == tab_id) {
+                tab.terminal.paste_str(&paste_text);
+            }
+        },
+    );
+}
+
+/// Count the open windows and tabs, and the programs running in them
+fn quit_summary() -> QuitSummary {
+    let mut summary = QuitSummary::default();
+    OPEN_WINDOWS.with(|windows| {
+        for open in windows.borrow().iter() {
+            let Some(tabs) = open.tabs.upgrade() else {
+                continue;
+            };
+            let tabs = tabs.borrow();
+            summary.windows += 1;
+            summary.tabs += tabs.len();
+            #[cfg(unix)]
+            summary.running.extend(
+                tabs.iter()
+                    .filter(|tab| tab.terminal.has_foreground_process())
+                    .map(|tab| {
+                        tab.terminal
+                            .foreground_process_name()
+                            .unwrap_or_else(|| "a process".to_string())
+                    }),
+            );
+        }
+    });
+    summary
+}
+
+/// Destroy every open window, skipping their close confirmations
+fn destroy_all_windows() {
+    let windows: Vec<ApplicationWindow> = OPEN_WINDOWS.with(|windows| {
+        windows
+            .borrow()
+            .iter()
+            .filter_map(|w| w.window.upgrade())
+            .collect()
+    });
+    for window in windows {
+        window.destroy();
+    }
+}
+
+/// Capture the tabs of a window so it can be reopened later
+///
+/// Incognito tabs are left out; returns `None` if no other tabs remain.
+fn snapshot_window(
+    window: &ApplicationWindow,
+    notebook: &Notebook,
+    tabs: &Rc<RefCell<Vec<TabEntry>>>,
+) -> Option<WindowSessionState> {
+    let (width, height) = window.default_size();
+    let current = notebook.current_page().unwrap_or(0) as usize;
+    let tabs_borrowed = tabs.borrow();
+    let active_tab = tabs_borrowed
+        .iter()
+        .take(current)
+        .filter(|entry| !entry.terminal.is_incognito())
+        .count();
+    let tabs: Vec<TabSessionState> = tabs_borrowed
+        .iter()
+        .filter(|entry| !entry.terminal.is_incognito())
+        .map(|entry| {
+            #[cfg(unix)]
+            let cwd = entry.terminal.working_directory().map(PathBuf::from);
+            #[cfg(not(unix))]
+            let cwd: Option<PathBuf> = None;
+
+            TabSessionState {
+                template_name: None,
+                custom_title: entry.title_locked.then(|| entry.title.clone()),
+                cwd,
+                color: entry.color.clone(),
+                title_color: entry.title_color.clone(),
+                pinned: entry.pinned,
+            }
+        })
+        .collect();
+    if tabs.is_empty() {
+        return None;
+    }
+
+    Some(WindowSessionState {
+        geometry: WindowGeometry {
+            width: width.max(0) as u32,
+            height: height.max(0) as u32,
+            maximized: window.is_maximized(),
+            ..WindowGeometry::default()
+        },
+        active_tab: active_tab.min(tabs.len() - 1),
+        tabs,
+    })
+}
+
 /// Generate a unique tab ID from the shared counter
 fn generate_tab_id(next_tab_id: &Rc<RefCell<u64>>) -> u64 {
     let mut id = next_tab_id.borrow_mut();
@@ -1911,6 +3288,7 @@ fn setup_tab_callbacks(
     let tabs_exit = Rc::clone(tabs);
     let tab_bar_exit = tab_bar.clone();
     let window_exit = window.clone();
+    let config_exit = Rc::clone(config);
     terminal.set_on_exit(move || {
         if !keep_open {
             close_tab_by_id(
@@ -1918,6 +3296,7 @@ fn setup_tab_callbacks(
                 &tabs_exit,
                 &tab_bar_exit,
                 &window_exit,
+                &config_exit,
                 tab_id,
             );
         }
@@ -1929,7 +3308,18 @@ fn setup_tab_callbacks(
     let tabs_bell = Rc::clone(tabs);
     let window_bell = window.clone();
     let has_bell_bell = Rc::clone(has_bell);
+    let config_bell = Rc::clone(config);
+    let bell_title = config.borrow().general.window_title("🔔 cterm");
+    let bell_escalation = RefCell::new(BellEscalation::from_config(&config.borrow().general));
+    let bell_limiter = RefCell::new(BellRateLimiter::from_config(&config.borrow().general));
     terminal.set_on_bell(move || {
+        if !bell_limiter.borrow_mut().allow(Instant::now()) {
+            return;
+        }
+        if let Some(sound) = sound_for(&config_bell.borrow().general, SoundEvent::Bell) {
+            play_sound(&window_bell, &sound);
+        }
+
         let is_window_active = window_bell.is_active();
         let is_current_tab = if let Some(current_page) = notebook_bell.current_page() {
             let tabs = tabs_bell.borrow();
@@ -1942,30 +3332,72 @@ fn setup_tab_callbacks(
 
         if !is_current_tab || !is_window_active {
             tab_bar_bell.set_bell(tab_id, true);
+
+            if bell_escalation.borrow_mut().ring(Instant::now()) {
+                let title = tabs_bell
+                    .borrow()
+                    .iter()
+                    .find(|t| t.id == tab_id)
+                    .map(|t| t.title.clone())
+                    .unwrap_or_default();
+                send_notification(
+                    &window_bell,
+                    &format!("bell-{}", tab_id),
+                    &bell_notification(&title),
+                );
+            }
+        } else {
+            bell_escalation.borrow_mut().reset();
         }
 
         if !is_window_active {
             *has_bell_bell.borrow_mut() = true;
-            window_bell.set_title(Some("🔔 cterm"));
+            window_bell.set_title(Some(&bell_title));
         }
     });
 
+    // Finished command callback: tint the tab by the exit status, unless
+    // it's the one being looked at
+    if config.borrow().general.flash_tab_on_exit {
+        let tab_bar_done = tab_bar.clone();
+        let notebook_done = notebook.clone();
+        let tabs_done = Rc::clone(tabs);
+        let window_done = window.clone();
+        terminal.set_on_command_finished(move |exit_code| {
+            let is_current_tab = notebook_done.current_page().is_some_and(|page| {
+                tabs_done
+                    .borrow()
+                    .get(page as usize)
+                    .is_some_and(|t| t.id == tab_id)
+            });
+            if !is_current_tab || !window_done.is_active() {
+                tab_bar_done.set_exit_status(tab_id, Some(exit_code.unwrap_or(0) == 0));
+            }
+        });
+    }
+
     // Title change callback
     let tab_bar_title = tab_bar.clone();
     let tabs_title = Rc::clone(tabs);
     let window_title = window.clone();
     let notebook_title = notebook.clone();
     let has_bell_title = Rc::clone(has_bell);
-    terminal.set_on_title_change(move |title| {
-        // Check if title is locked (user-set or template)
+    let config_title = Rc::clone(config);
+    let title_filter = TitleFilter::new(&config.borrow().appearance);
+    terminal.set_on_title_change(move |title, cwd| {
+        // Check the update mode, ignore patterns and whether the title is
+        // locked (user-set or template)
         {
             let tabs = tabs_title.borrow();
-            if let Some(entry) = tabs.iter().find(|t| t.id == tab_id) {
-                if entry.title_locked {
-                    return;
-                }
+            let locked = tabs
+                .iter()
+                .find(|t| t.id == tab_id)
+                .is_some_and(|t| t.title_locked);
+            if !title_filter.accepts(title, locked) {
+                return;
             }
         }
+        let title = &config_title.borrow().tabs.format_title(title, cwd);
 
         // Update tab bar
         tab_bar_title.set_title(tab_id, title);
@@ -1987,7 +3419,8 @@ fn setup_tab_callbacks(
                 .unwrap_or(false)
             {
                 *has_bell_title.borrow_mut() = false;
-                window_title.set_title(Some(title));
+                let title = config_title.borrow().general.window_title(title);
+                window_title.set_title(Some(&title));
             }
         }
     });
@@ -1995,9 +3428,16 @@ fn setup_tab_callbacks(
     // File transfer callback
     let file_manager_transfer = Rc::clone(file_manager);
     let notification_bar_transfer = notification_bar.clone();
+    let window_transfer = window.clone();
+    let config_transfer = Rc::clone(config);
     terminal.set_on_file_transfer(move |transfer| {
         use cterm_core::FileTransferOperation;
 
+        if let Some(sound) = sound_for(&config_transfer.borrow().general, SoundEvent::FileReceived)
+        {
+            play_sound(&window_transfer, &sound);
+        }
+
         match transfer {
             FileTransferOperation::FileReceived { id, name, data } => {
                 log::info!(
@@ -2042,13 +3482,21 @@ fn finalize_new_tab(
     terminal: TerminalWidget,
     title_locked: bool,
 ) {
-    tabs.borrow_mut().push(TabEntry {
-        id: tab_id,
-        title,
-        terminal,
-        title_locked,
-        color: None,
-    });
+    tab_bar.set_incognito(tab_id, terminal.is_incognito());
+
+    // The page may have been inserted rather than appended; keep `tabs` in notebook order
+    tabs.borrow_mut().insert(
+        page_num as usize,
+        TabEntry {
+            id: tab_id,
+            title,
+            terminal,
+            title_locked,
+            color: None,
+            title_color: None,
+            pinned: false,
+        },
+    );
 
     tab_bar.update_visibility();
     notebook.set_current_page(Some(page_num));
@@ -2060,6 +3508,9 @@ fn finalize_new_tab(
 }
 
 /// Create a new terminal tab
+///
+/// The tab is inserted at `position` in both the notebook and `tabs`,
+/// or appended when `position` is `None`.
 #[allow(clippy::too_many_arguments)]
 fn create_new_tab(
     notebook: &Notebook,
@@ -2073,8 +3524,11 @@ fn create_new_tab(
     file_manager: &Rc<RefCell<PendingFileManager>>,
     notification_bar: &NotificationBar,
     cwd: Option<String>,
+    position: Option<u32>,
+    incognito: bool,
 ) {
-    let cfg = config.borrow();
+    let mut cfg = config.borrow().clone();
+    cfg.general.no_scrollback |= incognito;
 
     // Get shell basename for initial title
     let shell = cfg
@@ -2096,9 +3550,13 @@ fn create_new_tab(
         }
     };
 
+    // Never place a new tab in front of pinned ones
+    let pinned = tabs.borrow().iter().filter(|t| t.pinned).count();
+    let position = position.map(|p| unpinned_position(p as usize, pinned) as u32);
+
     let tab_id = generate_tab_id(next_tab_id);
-    let page_num = notebook.append_page(terminal.widget(), None::<&gtk4::Widget>);
-    tab_bar.add_tab(tab_id, &initial_title);
+    let page_num = notebook.insert_page(terminal.widget(), None::<&gtk4::Widget>, position);
+    tab_bar.insert_tab(page_num as usize, tab_id, &initial_title);
 
     setup_tab_callbacks(
         notebook,
@@ -2208,14 +3666,15 @@ fn create_tab_from_template(
     notification_bar: &NotificationBar,
     template: &cterm_app::config::StickyTabConfig,
 ) {
-    // Prepare working directory (clone from git if needed)
+    // Prepare working directory (expand tokens, clone from git if needed)
+    let mut template = template.clone();
     if let Some(ref working_dir) = template.working_directory {
-        if let Err(e) =
-            cterm_app::prepare_working_directory(working_dir, template.git_remote.as_deref())
-        {
-            log::error!("Failed to prepare working directory: {}", e);
+        match cterm_app::prepare_working_directory(working_dir, template.git_remote.as_deref()) {
+            Ok((dir, _)) => template.working_directory = Some(dir),
+            Err(e) => log::error!("Failed to prepare working directory: {}", e),
         }
     }
+    let template = &template;
 
     let cfg = config.borrow();
     let terminal = match TerminalWidget::from_template(&cfg, theme, template) {
@@ -2234,6 +3693,10 @@ fn create_tab_from_template(
     if let Some(ref color) = tab_color {
         tab_bar.set_color(tab_id, Some(color));
     }
+    let tab_title_color = template.title_color.clone();
+    if let Some(ref color) = tab_title_color {
+        tab_bar.set_title_color(tab_id, Some(color));
+    }
 
     setup_tab_callbacks(
         notebook,
@@ -2260,10 +3723,11 @@ fn create_tab_from_template(
         true,
     );
 
-    // Store the template color in the tab entry
-    if tab_color.is_some() {
+    // Store the template colors in the tab entry
+    if tab_color.is_some() || tab_title_color.is_some() {
         if let Some(tab) = tabs.borrow_mut().iter_mut().find(|t| t.id == tab_id) {
             tab.color = tab_color;
+            tab.title_color = tab_title_color;
         }
     }
 
@@ -2290,11 +3754,15 @@ fn close_current_tab(
 }
 
 /// Close tab by ID (unconditionally - used when process has already exited)
+///
+/// Closing the last tab closes the window, or opens a fresh tab instead when
+/// `close_last_tab` is set to `new_tab`.
 fn close_tab_by_id(
     notebook: &Notebook,
     tabs: &Rc<RefCell<Vec<TabEntry>>>,
     tab_bar: &TabBar,
     window: &ApplicationWindow,
+    config: &Rc<RefCell<Config>>,
     id: u64,
 ) {
     // Find index of this tab
@@ -2317,9 +3785,16 @@ fn close_tab_by_id(
     // Update tab bar visibility (hide if only one tab)
     tab_bar.update_visibility();
 
-    // Close window if no tabs left
+    // Close the window, or replace the tab, if no tabs are left
     if tabs.borrow().is_empty() {
-        window.close();
+        // A window that is already going away (shells exiting after it was
+        // closed) doesn't get a new tab
+        let new_tab = config.borrow().general.close_last_tab == CloseLastTab::NewTab;
+        if new_tab && window.is_visible() {
+            gtk4::prelude::ActionGroupExt::activate_action(window, "new-tab", None);
+        } else {
+            window.close();
+        }
         return;
     }
 
@@ -2370,19 +3845,20 @@ fn request_close_tab_by_id(
         let tab_bar = tab_bar.clone();
         let window = window.clone();
         let window_for_closure = window.clone();
+        let config = Rc::clone(config);
 
         dialogs::show_close_confirmation_dialog(
             &window,
             vec![(tab_title, process_name)],
             move |confirmed| {
                 if confirmed {
-                    close_tab_by_id(&notebook, &tabs, &tab_bar, &window_for_closure, id);
+                    close_tab_by_id(&notebook, &tabs, &tab_bar, &window_for_closure, &config, id);
                 }
             },
         );
     } else {
         // No running process or confirmation disabled - close directly
-        close_tab_by_id(notebook, tabs, tab_bar, window, id);
+        close_tab_by_id(notebook, tabs, tab_bar, window, config, id);
     }
 }
 
@@ -2393,10 +3869,10 @@ fn request_close_tab_by_id(
     tabs: &Rc<RefCell<Vec<TabEntry>>>,
     tab_bar: &TabBar,
     window: &ApplicationWindow,
-    _config: &Rc<RefCell<Config>>,
+    config: &Rc<RefCell<Config>>,
     id: u64,
 ) {
-    close_tab_by_id(notebook, tabs, tab_bar, window, id);
+    close_tab_by_id(notebook, tabs, tab_bar, window, config, id);
 }
 
 /// Close all tabs except the current one
@@ -2461,6 +3937,52 @@ fn sync_tab_bar_active(tab_bar: &TabBar, tabs: &Rc<RefCell<Vec<TabEntry>>>, note
 }
 
 /// Convert GTK modifier state to our Modifiers
+/// Show a desktop notification from the window's application
+///
+/// Notifications with the same `id` replace each other.
+fn send_notification(window: &ApplicationWindow, id: &str, notification: &Notification) {
+    let Some(app) = window.application() else {
+        return;
+    };
+    let gio_notification = gio::Notification::new(&notification.title);
+    gio_notification.set_body(Some(&notification.body));
+    app.send_notification(Some(id), &gio_notification);
+}
+
+/// Play an event's sound, beeping if a sound can't be found
+fn play_sound(window: &ApplicationWindow, sound: &Sound) {
+    let path = match sound {
+        Sound::Beep => None,
+        Sound::Named(name) => theme_sound_file(name),
+        Sound::File(path) => Some(path.clone()).filter(|path| path.is_file()),
+    };
+    let Some(path) = path else {
+        if *sound != Sound::Beep {
+            log::warn!("Sound {:?} not found, beeping instead", sound);
+        }
+        WidgetExt::display(window).beep();
+        return;
+    };
+
+    let media = gtk4::MediaFile::for_filename(&path);
+    media.play();
+    PLAYING_SOUND.with(|playing| *playing.borrow_mut() = Some(media));
+}
+
+/// Find a sound of the freedesktop sound theme by name ("complete",
+/// "message-new-instant", ...)
+fn theme_sound_file(name: &str) -> Option<PathBuf> {
+    std::iter::once(glib::user_data_dir())
+        .chain(glib::system_data_dirs())
+        .flat_map(|dir| {
+            ["oga", "ogg", "wav"].map(|ext| {
+                dir.join("sounds/freedesktop/stereo")
+                    .join(format!("{}.{}", name, ext))
+            })
+        })
+        .find(|path| path.is_file())
+}
+
 fn gtk_modifiers_to_modifiers(state: gdk::ModifierType) -> Modifiers {
     let mut modifiers = Modifiers::empty();
 
@@ -2484,33 +4006,12 @@ fn gtk_modifiers_to_modifiers(state: gdk::ModifierType) -> Modifiers {
 fn keyval_to_keycode(keyval: gdk::Key) -> Option<KeyCode> {
     use gdk::Key;
 
+    // Letters are the same key in either case (Caps Lock, Shift)
+    if let Some(c) = keyval.to_unicode().filter(char::is_ascii_alphabetic) {
+        return KeyCode::from_char(c);
+    }
+
     Some(match keyval {
-        Key::a | Key::A => KeyCode::A,
-        Key::b | Key::B => KeyCode::B,
-        Key::c | Key::C => KeyCode::C,
-        Key::d | Key::D => KeyCode::D,
-        Key::e | Key::E => KeyCode::E,
-        Key::f | Key::F => KeyCode::F,
-        Key::g | Key::G => KeyCode::G,
-        Key::h | Key::H => KeyCode::H,
-        Key::i | Key::I => KeyCode::I,
-        Key::j | Key::J => KeyCode::J,
-        Key::k | Key::K => KeyCode::K,
-        Key::l | Key::L => KeyCode::L,
-        Key::m | Key::M => KeyCode::M,
-        Key::n | Key::N => KeyCode::N,
-        Key::o | Key::O => KeyCode::O,
-        Key::p | Key::P => KeyCode::P,
-        Key::q | Key::Q => KeyCode::Q,
-        Key::r | Key::R => KeyCode::R,
-        Key::s | Key::S => KeyCode::S,
-        Key::t | Key::T => KeyCode::T,
-        Key::u | Key::U => KeyCode::U,
-        Key::v | Key::V => KeyCode::V,
-        Key::w | Key::W => KeyCode::W,
-        Key::x | Key::X => KeyCode::X,
-        Key::y | Key::Y => KeyCode::Y,
-        Key::z | Key::Z => KeyCode::Z,
         Key::_0 => KeyCode::Key0,
         Key::_1 => KeyCode::Key1,
         Key::_2 => KeyCode::Key2,
@@ -2563,57 +4064,12 @@ fn keyval_to_keycode(keyval: gdk::Key) -> Option<KeyCode> {
     })
 }
 
-/// Calculate initial cell dimensions for window sizing
-/// Uses Pango font metrics to get accurate measurements
-fn calculate_initial_cell_dimensions(config: &Config) -> CellDimensions {
-    use gtk4::pango;
-
-    let font_family = &config.appearance.font.family;
-    let font_size = config.appearance.font.size;
-
-    // Get the default font map and create a context
-    let font_map = pangocairo::FontMap::default();
-    let context = font_map.create_context();
-
-    // Try the requested font first, then fall back to generic monospace
-    let fonts_to_try = [font_family.to_string(), "monospace".to_string()];
-
-    for font_name in &fonts_to_try {
-        let font_desc =
-            pango::FontDescription::from_string(&format!("{} {}", font_name, font_size));
-
-        if let Some(font) = font_map.load_font(&context, &font_desc) {
-            let metrics = font.metrics(None);
-            let char_width = metrics.approximate_char_width() as f64 / pango::SCALE as f64;
-            let ascent = metrics.ascent() as f64 / pango::SCALE as f64;
-            let descent = metrics.descent() as f64 / pango::SCALE as f64;
-            let height = ascent + descent;
-
-            if char_width > 0.0 && height > 0.0 {
-                return CellDimensions {
-                    width: char_width,
-                    height: height * 1.1,
-                };
-            }
-        }
-    }
-
-    // Last resort: use a Pango layout to measure a character directly
-    let layout = pango::Layout::new(&context);
-    let font_desc = pango::FontDescription::from_string(&format!("monospace {}", font_size));
-    layout.set_font_description(Some(&font_desc));
-    layout.set_text("M");
-
-    let (width, height) = layout.pixel_size();
-    if width > 0 && height > 0 {
-        return CellDimensions {
-            width: width as f64,
-            height: height as f64 * 1.1,
-        };
-    }
-
-    panic!(
-        "Failed to load any font or measure text. \
-         Please ensure fonts are installed (e.g., fonts-dejavu or similar)."
-    );
+/// Calculate initial cell dimensions for window sizing, and the font used
+/// instead of the configured one if it isn't installed
+fn calculate_initial_cell_dimensions(
+    config: &Config,
+) -> (CellDimensions, Option<FontSubstitution>) {
+    let (font_family, substitution) = resolve_font_family(&config.appearance.font);
+    let cell_dims = calculate_cell_dimensions(&font_family, config.appearance.font.size);
+    (cell_dims, substitution)
 }
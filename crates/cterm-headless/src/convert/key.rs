@@ -28,6 +28,7 @@ pub fn proto_to_key(key: &proto::Key) -> Option<Key> {
                 SpecialKey::PageDown => Some(Key::PageDown),
                 SpecialKey::Insert => Some(Key::Insert),
                 SpecialKey::Delete => Some(Key::Delete),
+                SpecialKey::KeypadEnter => Some(Key::KeypadEnter),
             }
         }
         Some(KeyType::Function(n)) => {
@@ -37,6 +38,7 @@ pub fn proto_to_key(key: &proto::Key) -> Option<Key> {
                 None
             }
         }
+        Some(KeyType::Keypad(s)) => s.chars().next().map(Key::Keypad),
         None => None,
     }
 }
@@ -81,6 +83,8 @@ pub fn key_to_proto(key: Key) -> proto::Key {
         Key::Insert => Some(KeyType::Special(SpecialKey::Insert as i32)),
         Key::Delete => Some(KeyType::Special(SpecialKey::Delete as i32)),
         Key::F(n) => Some(KeyType::Function(n as u32)),
+        Key::KeypadEnter => Some(KeyType::Special(SpecialKey::KeypadEnter as i32)),
+        Key::Keypad(c) => Some(KeyType::Keypad(c.to_string())),
     };
 
     proto::Key { key_type }
@@ -116,6 +120,14 @@ mod tests {
         assert_eq!(back, Some(key));
     }
 
+    #[test]
+    fn test_keypad_key_roundtrip() {
+        for key in [Key::KeypadEnter, Key::Keypad('7')] {
+            let proto = key_to_proto(key);
+            assert_eq!(proto_to_key(&proto), Some(key));
+        }
+    }
+
     #[test]
     fn test_modifiers_roundtrip() {
         let mods = Modifiers::SHIFT | Modifiers::CTRL;
@@ -79,7 +79,7 @@ pub fn proto_to_attrs(attrs: &proto::CellAttributes) -> CellAttrs {
 /// Convert a cell to proto
 pub fn cell_to_proto(cell: &Cell) -> proto::Cell {
     proto::Cell {
-        char: cell.c.to_string(),
+        char: cell.grapheme_text(),
         fg: Some(color_to_proto(&cell.fg)),
         bg: Some(color_to_proto(&cell.bg)),
         attrs: Some(attrs_to_proto(cell.attrs)),
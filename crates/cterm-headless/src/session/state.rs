@@ -56,6 +56,8 @@ impl SessionState {
             cwd,
             env,
             term,
+            advertise_truecolor: true,
+            raw: false,
         };
 
         let screen_config = ScreenConfig { scrollback_lines };
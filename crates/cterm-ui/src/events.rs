@@ -3,6 +3,7 @@
 //! Defines platform-agnostic input events.
 
 use bitflags::bitflags;
+use cterm_core::term::Key;
 
 bitflags! {
     /// Keyboard modifiers
@@ -214,6 +215,64 @@ pub enum InputEvent {
 }
 
 impl KeyCode {
+    /// Get the key for a character (the inverse of `to_char`)
+    ///
+    /// Letters map to the same key in either case, so Caps Lock or Shift
+    /// never change which key a shortcut sees.
+    pub fn from_char(c: char) -> Option<Self> {
+        match c.to_ascii_lowercase() {
+            'a' => Some(Self::A),
+            'b' => Some(Self::B),
+            'c' => Some(Self::C),
+            'd' => Some(Self::D),
+            'e' => Some(Self::E),
+            'f' => Some(Self::F),
+            'g' => Some(Self::G),
+            'h' => Some(Self::H),
+            'i' => Some(Self::I),
+            'j' => Some(Self::J),
+            'k' => Some(Self::K),
+            'l' => Some(Self::L),
+            'm' => Some(Self::M),
+            'n' => Some(Self::N),
+            'o' => Some(Self::O),
+            'p' => Some(Self::P),
+            'q' => Some(Self::Q),
+            'r' => Some(Self::R),
+            's' => Some(Self::S),
+            't' => Some(Self::T),
+            'u' => Some(Self::U),
+            'v' => Some(Self::V),
+            'w' => Some(Self::W),
+            'x' => Some(Self::X),
+            'y' => Some(Self::Y),
+            'z' => Some(Self::Z),
+            '0' => Some(Self::Key0),
+            '1' => Some(Self::Key1),
+            '2' => Some(Self::Key2),
+            '3' => Some(Self::Key3),
+            '4' => Some(Self::Key4),
+            '5' => Some(Self::Key5),
+            '6' => Some(Self::Key6),
+            '7' => Some(Self::Key7),
+            '8' => Some(Self::Key8),
+            '9' => Some(Self::Key9),
+            ' ' => Some(Self::Space),
+            '-' => Some(Self::Minus),
+            '=' => Some(Self::Equals),
+            '[' => Some(Self::LeftBracket),
+            ']' => Some(Self::RightBracket),
+            ';' => Some(Self::Semicolon),
+            '\'' => Some(Self::Quote),
+            '`' => Some(Self::Backquote),
+            '\\' => Some(Self::Backslash),
+            ',' => Some(Self::Comma),
+            '.' => Some(Self::Period),
+            '/' => Some(Self::Slash),
+            _ => None,
+        }
+    }
+
     /// Get the character for this key (without modifiers)
     pub fn to_char(&self) -> Option<char> {
         match self {
@@ -323,6 +382,33 @@ impl KeyCode {
             _ => None,
         }
     }
+
+    /// Get the terminal key for a numeric keypad key
+    ///
+    /// Keypad keys send their own sequences in application keypad mode
+    /// (DECKPAM), so they are kept apart from the main keys.
+    pub fn to_keypad_key(&self) -> Option<Key> {
+        let c = match self {
+            Self::NumpadEnter => return Some(Key::KeypadEnter),
+            Self::Numpad0 => '0',
+            Self::Numpad1 => '1',
+            Self::Numpad2 => '2',
+            Self::Numpad3 => '3',
+            Self::Numpad4 => '4',
+            Self::Numpad5 => '5',
+            Self::Numpad6 => '6',
+            Self::Numpad7 => '7',
+            Self::Numpad8 => '8',
+            Self::Numpad9 => '9',
+            Self::NumpadAdd => '+',
+            Self::NumpadSubtract => '-',
+            Self::NumpadMultiply => '*',
+            Self::NumpadDivide => '/',
+            Self::NumpadDecimal => '.',
+            _ => return None,
+        };
+        Some(Key::Keypad(c))
+    }
 }
 
 /// Action that can be bound to a shortcut
@@ -330,6 +416,7 @@ impl KeyCode {
 pub enum Action {
     // Tab actions
     NewTab,
+    NewIncognitoTab,
     CloseTab,
     NextTab,
     PrevTab,
@@ -339,17 +426,24 @@ pub enum Action {
     // Window actions
     NewWindow,
     CloseWindow,
+    ReopenClosedWindow,
 
     // Edit actions
     Copy,
     Paste,
     SelectAll,
+    CopyLastCommandOutput,
+    RerunLastCommand,
+    ClearToLastPrompt,
+    ClipboardHistory,
 
     // View actions
     ZoomIn,
     ZoomOut,
     ZoomReset,
     ToggleFullscreen,
+    IncreaseOpacity,
+    DecreaseOpacity,
 
     // Scroll actions
     ScrollUp,
@@ -358,12 +452,17 @@ pub enum Action {
     ScrollPageDown,
     ScrollToTop,
     ScrollToBottom,
+    SetMark,
+    JumpMark,
 
     // Other
     OpenPreferences,
     FindText,
     ResetTerminal,
     QuickOpenTemplate,
+    ToggleRecording,
+    /// Type a snippet into the terminal (escapes already expanded)
+    SendText(String),
 }
 
 /// A keyboard shortcut
@@ -5,11 +5,15 @@
 //! interface.
 
 pub mod events;
+pub mod minimap;
 pub mod theme;
+pub mod theme_import;
 pub mod traits;
 pub mod utils;
 
 pub use events::*;
+pub use minimap::*;
 pub use theme::*;
+pub use theme_import::*;
 pub use traits::*;
 pub use utils::*;
@@ -0,0 +1,303 @@
+//! Scrollback minimap
+//!
+//! With a long scrollback the position of the view says little about what's
+//! around it. The minimap is a thin strip beside the terminal showing every
+//! line of the scrollback and screen as a bar, as long as the line has text
+//! and in its most used color, so errors, prompts and long output stand out
+//! and can be jumped to with a click.
+//!
+//! Line summaries are cached by stable line number: each update only
+//! summarizes the lines pushed into scrollback since the last one (plus the
+//! visible screen, which can change anywhere), and the bars for a given strip
+//! height are kept until something changes.
+
+use std::collections::VecDeque;
+
+use cterm_core::cell::Cell;
+use cterm_core::color::{ColorPalette, Rgb};
+use cterm_core::screen::Screen;
+
+/// Width of the minimap strip in pixels
+pub const MINIMAP_WIDTH: f64 = 48.0;
+
+/// Summary of one line of text
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MinimapLine {
+    /// Fraction of the line's cells that aren't blank (0.0 - 1.0)
+    pub density: f32,
+    /// Most used color among those cells
+    pub color: Rgb,
+}
+
+impl MinimapLine {
+    fn summarize<'a>(cells: impl IntoIterator<Item = &'a Cell>, palette: &ColorPalette) -> Self {
+        let mut total = 0;
+        let mut used = 0;
+        let mut counts: Vec<(Rgb, usize)> = Vec::new();
+        for cell in cells {
+            total += 1;
+            // Text counts in its color, blank cells with a background in that
+            // (status bars, highlighted lines)
+            let color = if cell.c != ' ' && cell.c != '\0' {
+                cell.fg.to_rgb(palette)
+            } else if !cell.bg.is_default() {
+                cell.bg.to_rgb(palette)
+            } else {
+                continue;
+            };
+            used += 1;
+            match counts.iter_mut().find(|(c, _)| *c == color) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((color, 1)),
+            }
+        }
+
+        Self {
+            density: if total == 0 {
+                0.0
+            } else {
+                used as f32 / total as f32
+            },
+            color: counts
+                .iter()
+                .max_by_key(|(_, count)| *count)
+                .map_or(palette.foreground, |(color, _)| *color),
+        }
+    }
+}
+
+/// A bar to draw in the strip
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MinimapBar {
+    /// Top of the bar, in pixels from the top of the strip
+    pub y: f64,
+    pub height: f64,
+    /// Length of the bar as a fraction of the strip width
+    pub width: f64,
+    pub color: Rgb,
+}
+
+/// Cached minimap of one terminal
+#[derive(Debug, Clone, Default)]
+pub struct Minimap {
+    /// Stable line number of the first summarized scrollback line
+    first_line: usize,
+    /// Summaries of scrollback lines, oldest first
+    scrollback: VecDeque<MinimapLine>,
+    /// Summaries of the visible grid rows
+    screen: Vec<MinimapLine>,
+    /// Bars computed for a strip height, until the summaries change
+    bars: Option<(u32, Vec<MinimapBar>)>,
+}
+
+impl Minimap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Forget all summaries (e.g. after the theme changed)
+    pub fn invalidate(&mut self) {
+        self.scrollback.clear();
+        self.screen.clear();
+        self.bars = None;
+    }
+
+    /// Bring the summaries up to date with the screen
+    pub fn update(&mut self, screen: &Screen, palette: &ColorPalette) {
+        let first = screen.first_scrollback_line();
+        let end = first + screen.scrollback().len();
+
+        // Lines removed from the middle of the scrollback (clearing the last
+        // command's output) shift everything after them, start over then
+        if self.first_line > first || self.first_line + self.scrollback.len() > end {
+            self.scrollback.clear();
+            self.first_line = first;
+        }
+
+        let mut changed = false;
+        while self.first_line < first {
+            changed = true;
+            if self.scrollback.pop_front().is_none() {
+                self.first_line = first;
+                break;
+            }
+            self.first_line += 1;
+        }
+
+        let summarized = self.first_line + self.scrollback.len();
+        if summarized < end {
+            changed = true;
+            let new_lines = screen.scrollback().range(summarized - first..);
+            self.scrollback
+                .extend(new_lines.map(|row| MinimapLine::summarize(row.iter(), palette)));
+        }
+
+        let grid = screen.grid();
+        let rows: Vec<MinimapLine> = grid
+            .iter()
+            .map(|row| MinimapLine::summarize(row.iter(), palette))
+            .collect();
+        if rows != self.screen {
+            changed = true;
+            self.screen = rows;
+        }
+
+        if changed {
+            self.bars = None;
+        }
+    }
+
+    /// Number of lines shown (scrollback and screen)
+    pub fn len(&self) -> usize {
+        self.scrollback.len() + self.screen.len()
+    }
+
+    /// Whether there is nothing to show
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn line(&self, index: usize) -> MinimapLine {
+        self.scrollback
+            .get(index)
+            .or_else(|| self.screen.get(index - self.scrollback.len()))
+            .copied()
+            .expect("minimap line index in range")
+    }
+
+    /// Bars for a strip `height` pixels tall
+    ///
+    /// When there are more lines than pixels, each pixel row shows the
+    /// densest of its lines; otherwise each line gets an equal share.
+    pub fn bars(&mut self, height: f64) -> &[MinimapBar] {
+        let key = height.max(0.0).round() as u32;
+        if self.bars.as_ref().is_none_or(|(h, _)| *h != key) {
+            let bars = self.compute_bars(key as usize);
+            self.bars = Some((key, bars));
+        }
+        &self.bars.as_ref().expect("bars just computed").1
+    }
+
+    fn compute_bars(&self, height: usize) -> Vec<MinimapBar> {
+        let len = self.len();
+        if len == 0 || height == 0 {
+            return Vec::new();
+        }
+
+        let bar = |y: f64, height: f64, line: MinimapLine| MinimapBar {
+            y,
+            height,
+            width: line.density as f64,
+            color: line.color,
+        };
+
+        if len <= height {
+            let line_height = height as f64 / len as f64;
+            (0..len)
+                .map(|i| self.line(i))
+                .enumerate()
+                .filter(|(_, line)| line.density > 0.0)
+                .map(|(i, line)| bar(i as f64 * line_height, line_height, line))
+                .collect()
+        } else {
+            (0..height)
+                .filter_map(|y| {
+                    let start = y * len / height;
+                    let end = ((y + 1) * len / height).max(start + 1);
+                    (start..end)
+                        .map(|i| self.line(i))
+                        .max_by(|a, b| a.density.total_cmp(&b.density))
+                        .filter(|line| line.density > 0.0)
+                        .map(|line| bar(y as f64, 1.0, line))
+                })
+                .collect()
+        }
+    }
+}
+
+/// Vertical span (top, height) of the visible part of the screen in a strip
+/// `height` pixels tall
+pub fn minimap_viewport(screen: &Screen, height: f64) -> (f64, f64) {
+    let total = screen.total_lines().max(1) as f64;
+    let top = screen
+        .scrollback()
+        .len()
+        .saturating_sub(screen.scroll_offset) as f64;
+    (
+        top / total * height,
+        screen.height() as f64 / total * height,
+    )
+}
+
+/// Absolute line to scroll to (as the top of the view) for a click at `y`
+/// in a strip `height` pixels tall, centering the clicked line in the view
+pub fn minimap_line_at(screen: &Screen, y: f64, height: f64) -> usize {
+    if height <= 0.0 {
+        return screen.scrollback().len();
+    }
+    let total = screen.total_lines();
+    let line = ((y / height).clamp(0.0, 1.0) * total as f64) as usize;
+    line.saturating_sub(screen.height() / 2)
+        .min(screen.scrollback().len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cterm_core::screen::ScreenConfig;
+    use cterm_core::term::Terminal;
+
+    #[test]
+    fn test_incremental_update() {
+        let palette = ColorPalette::default_dark();
+        let mut term = Terminal::new(10, 3, ScreenConfig::default());
+        let mut minimap = Minimap::new();
+
+        term.process(b"hello\r\n\x1b[31mxxxxxxxxxx\x1b[0m\r\n\r\nend");
+        minimap.update(term.screen(), &palette);
+        assert_eq!(minimap.len(), 4);
+        assert_eq!(minimap.line(0).density, 0.5);
+        assert_eq!(minimap.line(1).density, 1.0);
+        assert_eq!(minimap.line(1).color, palette.ansi[1]);
+        assert_eq!(minimap.line(2).density, 0.0);
+
+        // Only the newly scrolled lines are added
+        term.process(b"\r\na\r\nb");
+        minimap.update(term.screen(), &palette);
+        assert_eq!(minimap.len(), 6);
+        assert_eq!(minimap.line(0).density, 0.5);
+
+        // A reset drops the scrollback
+        term.process(b"\x1bc");
+        minimap.update(term.screen(), &palette);
+        assert_eq!(minimap.len(), 3);
+    }
+
+    #[test]
+    fn test_bars_and_navigation() {
+        let palette = ColorPalette::default_dark();
+        let mut term = Terminal::new(4, 2, ScreenConfig::default());
+        for _ in 0..9 {
+            term.process(b"xxxx\r\n");
+        }
+        let mut minimap = Minimap::new();
+        minimap.update(term.screen(), &palette);
+        assert_eq!(minimap.len(), 10);
+
+        // One bar per line with text when the strip is tall enough
+        let bars = minimap.bars(100.0).to_vec();
+        assert_eq!(bars.len(), 9);
+        assert_eq!(bars[1].y, 10.0);
+        assert_eq!(bars[1].height, 10.0);
+        assert_eq!(bars[1].width, 1.0);
+
+        // Lines share pixel rows in a short strip
+        assert!(minimap.bars(5.0).len() <= 5);
+
+        let screen = term.screen();
+        assert_eq!(minimap_viewport(screen, 100.0), (80.0, 20.0));
+        assert_eq!(minimap_line_at(screen, 0.0, 100.0), 0);
+        assert_eq!(minimap_line_at(screen, 50.0, 100.0), 4);
+        assert_eq!(minimap_line_at(screen, 100.0, 100.0), 8);
+    }
+}
@@ -2,7 +2,10 @@
 //!
 //! Defines the theme structure for customizing terminal appearance.
 
-use cterm_core::color::{ColorPalette, Rgb};
+use std::collections::BTreeMap;
+
+use cterm_core::color::{ColorOverrides, ColorPalette, Rgb};
+use cterm_core::SearchHighlight;
 use serde::{Deserialize, Serialize};
 
 /// Complete terminal theme
@@ -18,8 +21,31 @@ pub struct Theme {
     pub ui: UiColors,
     /// Cursor appearance
     pub cursor: CursorTheme,
+    /// Background of search matches
+    #[serde(default = "default_search_match_bg")]
+    pub search_match_bg: Rgb,
+    /// Background of the current search match
+    #[serde(default = "default_search_current_bg")]
+    pub search_current_bg: Rgb,
+}
+
+fn default_search_match_bg() -> Rgb {
+    Rgb::new(0x61, 0x52, 0x1f)
+}
+
+fn default_search_current_bg() -> Rgb {
+    Rgb::new(0xb3, 0x6b, 0x00)
 }
 
+/// Contrast a cursor needs against the background to be easy to find
+const MIN_CURSOR_CONTRAST: f64 = 3.0;
+
+/// Contrast the selection needs against the background to be noticed
+const MIN_SELECTION_CONTRAST: f64 = 1.15;
+
+/// Contrast text needs against the selection to stay readable
+const MIN_SELECTED_TEXT_CONTRAST: f64 = 3.0;
+
 impl Default for Theme {
     fn default() -> Self {
         Self::dark()
@@ -35,6 +61,8 @@ impl Theme {
             colors: ColorPalette::default_dark(),
             ui: UiColors::dark(),
             cursor: CursorTheme::default(),
+            search_match_bg: default_search_match_bg(),
+            search_current_bg: default_search_current_bg(),
         }
     }
 
@@ -49,6 +77,8 @@ impl Theme {
                 color: Rgb::new(0, 0, 0),
                 text_color: Rgb::new(255, 255, 255),
             },
+            search_match_bg: Rgb::new(0xff, 0xe5, 0x8f),
+            search_current_bg: Rgb::new(0xff, 0xb3, 0x4d),
         }
     }
 
@@ -80,6 +110,7 @@ impl Theme {
                 background: Rgb::new(0x1a, 0x1b, 0x26),
                 cursor: Rgb::new(0xc0, 0xca, 0xf5),
                 selection: Rgb::new(0x28, 0x3b, 0x61),
+                indexed: BTreeMap::new(),
             },
             ui: UiColors {
                 tab_bar_background: Rgb::new(0x16, 0x16, 0x1e),
@@ -95,6 +126,8 @@ impl Theme {
                 color: Rgb::new(0xc0, 0xca, 0xf5),
                 text_color: Rgb::new(0x1a, 0x1b, 0x26),
             },
+            search_match_bg: default_search_match_bg(),
+            search_current_bg: default_search_current_bg(),
         }
     }
 
@@ -126,6 +159,7 @@ impl Theme {
                 background: Rgb::new(0x28, 0x2a, 0x36),
                 cursor: Rgb::new(0xf8, 0xf8, 0xf2),
                 selection: Rgb::new(0x44, 0x47, 0x5a),
+                indexed: BTreeMap::new(),
             },
             ui: UiColors {
                 tab_bar_background: Rgb::new(0x21, 0x22, 0x2c),
@@ -141,6 +175,8 @@ impl Theme {
                 color: Rgb::new(0xf8, 0xf8, 0xf2),
                 text_color: Rgb::new(0x28, 0x2a, 0x36),
             },
+            search_match_bg: default_search_match_bg(),
+            search_current_bg: default_search_current_bg(),
         }
     }
 
@@ -172,6 +208,7 @@ impl Theme {
                 background: Rgb::new(0x2e, 0x34, 0x40),
                 cursor: Rgb::new(0xd8, 0xde, 0xe9),
                 selection: Rgb::new(0x43, 0x4c, 0x5e),
+                indexed: BTreeMap::new(),
             },
             ui: UiColors {
                 tab_bar_background: Rgb::new(0x2e, 0x34, 0x40),
@@ -187,9 +224,132 @@ impl Theme {
                 color: Rgb::new(0xd8, 0xde, 0xe9),
                 text_color: Rgb::new(0x2e, 0x34, 0x40),
             },
+            search_match_bg: default_search_match_bg(),
+            search_current_bg: default_search_current_bg(),
+        }
+    }
+
+    /// Color for a finished command: the palette's green if it succeeded,
+    /// red if it failed
+    pub fn exit_status_color(&self, success: bool) -> Rgb {
+        if success {
+            self.colors.ansi[2]
+        } else {
+            self.colors.ansi[1]
+        }
+    }
+
+    /// Background color for a cell highlighted by the active search
+    pub fn search_bg(&self, highlight: SearchHighlight) -> Rgb {
+        match highlight {
+            SearchHighlight::Match => self.search_match_bg,
+            SearchHighlight::Current => self.search_current_bg,
+        }
+    }
+
+    /// Replace cursor and selection colors that would be hard to see on the
+    /// background
+    ///
+    /// Called when the foreground or background changes under colors the
+    /// user didn't choose, e.g. a template with a light background on a dark
+    /// theme. The cursor becomes the foreground color or, if that doesn't
+    /// stand out either, the palette color with the most contrast. The
+    /// selection becomes a mix of the foreground into the background.
+    pub fn derive_ui_colors(&mut self) {
+        let background = self.colors.background;
+        if self.cursor.color.contrast(background) < MIN_CURSOR_CONTRAST {
+            let candidates = std::iter::once(self.colors.foreground).chain(self.colors.ansi);
+            let cursor = candidates
+                .clone()
+                .find(|c| c.contrast(background) >= MIN_CURSOR_CONTRAST)
+                .unwrap_or_else(|| {
+                    candidates.fold(self.colors.foreground, |best, c| {
+                        if c.contrast(background) > best.contrast(background) {
+                            c
+                        } else {
+                            best
+                        }
+                    })
+                });
+            self.colors.cursor = cursor;
+            self.cursor.color = cursor;
+            self.cursor.text_color = background;
+        }
+
+        let selection = self.colors.selection;
+        if selection.contrast(background) < MIN_SELECTION_CONTRAST
+            || self.colors.foreground.contrast(selection) < MIN_SELECTED_TEXT_CONTRAST
+        {
+            self.colors.selection = background.blend(self.colors.foreground, 0.25);
         }
     }
 
+    /// Copy of this theme with the fields set in `overrides` replaced
+    ///
+    /// Colors that aren't valid `#RRGGBB` hex are ignored. A new foreground
+    /// or background also replaces cursor and selection colors that would
+    /// no longer stand out, unless those are overridden too.
+    pub fn with_overrides(&self, overrides: &ThemeOverrides) -> Theme {
+        let mut theme = self.clone();
+        let color = |hex: &Option<String>| hex.as_deref().and_then(Rgb::from_hex);
+        if let Some(rgb) = color(&overrides.foreground) {
+            theme.colors.foreground = rgb;
+        }
+        if let Some(rgb) = color(&overrides.background) {
+            theme.colors.background = rgb;
+        }
+        if overrides.foreground.is_some() || overrides.background.is_some() {
+            theme.derive_ui_colors();
+        }
+        if let Some(rgb) = color(&overrides.cursor) {
+            theme.colors.cursor = rgb;
+            theme.cursor.color = rgb;
+        }
+        if let Some(rgb) = color(&overrides.cursor_text) {
+            theme.cursor.text_color = rgb;
+        }
+        if let Some(rgb) = color(&overrides.selection) {
+            theme.colors.selection = rgb;
+        }
+        theme
+    }
+
+    /// Copy of this theme with the default colors (OSC 10/11/12) and
+    /// palette entries (OSC 4) set by the program replaced
+    ///
+    /// As with [`Theme::with_overrides`], the cursor and selection follow a
+    /// new foreground or background unless the program sets the cursor color.
+    pub fn with_color_overrides(&self, overrides: &ColorOverrides) -> Theme {
+        let mut theme = self.clone();
+        if let Some(rgb) = overrides.foreground {
+            theme.colors.foreground = rgb;
+        }
+        if let Some(rgb) = overrides.background {
+            theme.colors.background = rgb;
+        }
+        if overrides.foreground.is_some() || overrides.background.is_some() {
+            theme.derive_ui_colors();
+        }
+        if let Some(rgb) = overrides.cursor {
+            theme.colors.cursor = rgb;
+            theme.cursor.color = rgb;
+        }
+        for (&index, &rgb) in &overrides.palette {
+            match theme.colors.ansi.get_mut(index as usize) {
+                Some(ansi) => *ansi = rgb,
+                None => {
+                    theme.colors.indexed.insert(index, rgb);
+                }
+            }
+        }
+        theme
+    }
+
+    /// Find a built-in theme by name
+    pub fn builtin(name: &str) -> Option<Theme> {
+        Self::builtin_themes().into_iter().find(|t| t.name == name)
+    }
+
     /// Get all built-in themes
     pub fn builtin_themes() -> Vec<Theme> {
         vec![
@@ -202,6 +362,32 @@ impl Theme {
     }
 }
 
+/// Theme colors overridden on top of a base theme (hex `#RRGGBB`)
+///
+/// Lets a tab template tint a few colors, e.g. a red cursor for production
+/// hosts, without redefining the whole palette.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ThemeOverrides {
+    /// Default text color
+    pub foreground: Option<String>,
+    /// Default background color
+    pub background: Option<String>,
+    /// Cursor color
+    pub cursor: Option<String>,
+    /// Color of the text under a block cursor
+    pub cursor_text: Option<String>,
+    /// Selection background color
+    pub selection: Option<String>,
+}
+
+impl ThemeOverrides {
+    /// Whether no color is overridden
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
 /// UI element colors
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UiColors {
@@ -284,6 +470,40 @@ pub struct FontConfig {
     pub line_height: f64,
     /// Letter spacing adjustment
     pub letter_spacing: f64,
+    /// Fonts to try, in order, when none of `family` is installed
+    #[serde(default)]
+    pub fallback: Vec<String>,
+}
+
+/// Generic family the platform always resolves to an installed monospace font
+pub const SYSTEM_MONOSPACE: &str = "monospace";
+
+/// A configured font that isn't installed, and the one used instead
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FontSubstitution {
+    /// The configured `font.family`
+    pub requested: String,
+    /// The family actually used
+    pub used: String,
+}
+
+impl std::fmt::Display for FontSubstitution {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Font '{}' not found, using '{}'",
+            self.requested, self.used
+        )
+    }
+}
+
+/// Cell size in pixels (width, height) estimated from the font size alone
+///
+/// Used when no font can be measured at all, so the terminal still gets a
+/// usable grid. Assumes 96 DPI and the usual monospace advance of 0.6em.
+pub fn estimated_cell_size(font_size: f64) -> (f64, f64) {
+    let pixels = font_size.max(1.0) * 96.0 / 72.0;
+    (pixels * 0.6, pixels * 1.2)
 }
 
 impl Default for FontConfig {
@@ -294,6 +514,7 @@ impl Default for FontConfig {
             ligatures: true,
             line_height: 1.0,
             letter_spacing: 0.0,
+            fallback: Vec::new(),
         }
     }
 }
@@ -320,6 +541,50 @@ impl FontConfig {
         }
     }
 
+    /// Font families to try, in order: each name in `family` (a
+    /// comma-separated list), then `fallback`, then the system monospace font
+    pub fn candidates(&self) -> Vec<&str> {
+        let mut candidates: Vec<&str> = self
+            .family
+            .split(',')
+            .chain(self.fallback.iter().map(String::as_str))
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .collect();
+        if !candidates
+            .iter()
+            .any(|name| name.eq_ignore_ascii_case(SYSTEM_MONOSPACE))
+        {
+            candidates.push(SYSTEM_MONOSPACE);
+        }
+        candidates
+    }
+
+    /// Pick the first candidate font that `is_installed` accepts
+    ///
+    /// Falls back to [`SYSTEM_MONOSPACE`] if none is, so there is always a
+    /// font to use. The substitution is returned when the font picked isn't
+    /// one named in `family`, so the user can be told.
+    pub fn resolve(
+        &self,
+        mut is_installed: impl FnMut(&str) -> bool,
+    ) -> (String, Option<FontSubstitution>) {
+        let used = self
+            .candidates()
+            .into_iter()
+            .find(|name| is_installed(name))
+            .unwrap_or(SYSTEM_MONOSPACE);
+        let configured = self
+            .family
+            .split(',')
+            .any(|name| name.trim().eq_ignore_ascii_case(used));
+        let substitution = (!configured).then(|| FontSubstitution {
+            requested: self.family.trim().to_string(),
+            used: used.to_string(),
+        });
+        (used.to_string(), substitution)
+    }
+
     /// Create config for JetBrains Mono
     pub fn jetbrains_mono() -> Self {
         Self {
@@ -344,3 +609,119 @@ impl FontConfig {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cterm_core::parser::Parser;
+    use cterm_core::screen::{Screen, ScreenConfig};
+
+    #[test]
+    fn test_palette_overrides() {
+        let theme = Theme::dark();
+        let mut screen = Screen::new(80, 24, ScreenConfig::default());
+        let mut parser = Parser::new();
+        parser.parse(&mut screen, b"\x1b[31mA\x1b[38;5;200mB");
+        let fg = |screen: &Screen, col| {
+            let palette = theme.with_color_overrides(screen.color_overrides()).colors;
+            screen.get_cell(0, col).unwrap().fg.to_rgb(&palette)
+        };
+        let (red, pink) = (fg(&screen, 0), fg(&screen, 1));
+
+        parser.parse(&mut screen, b"\x1b]4;1;#123456;200;#abcdef\x07");
+        assert_eq!(fg(&screen, 0), Rgb::new(0x12, 0x34, 0x56));
+        assert_eq!(fg(&screen, 1), Rgb::new(0xab, 0xcd, 0xef));
+
+        parser.parse(&mut screen, b"\x1b]104\x07");
+        assert_eq!(fg(&screen, 0), red);
+        assert_eq!(fg(&screen, 1), pink);
+    }
+
+    #[test]
+    fn test_derive_ui_colors() {
+        // A light background on a dark theme
+        let dark = Theme::dark();
+        let overrides = ThemeOverrides {
+            background: Some("#fdf6e3".into()),
+            foreground: Some("#333333".into()),
+            ..Default::default()
+        };
+        let theme = dark.with_overrides(&overrides);
+        let background = theme.colors.background;
+        assert!(theme.cursor.color.contrast(background) >= MIN_CURSOR_CONTRAST);
+        assert_eq!(theme.colors.cursor, theme.cursor.color);
+        assert_eq!(theme.cursor.text_color, background);
+        assert!(theme.colors.selection.contrast(background) >= MIN_SELECTION_CONTRAST);
+        assert!(
+            theme.colors.foreground.contrast(theme.colors.selection) >= MIN_SELECTED_TEXT_CONTRAST
+        );
+
+        // Colors the user picked are kept, as are ones that still stand out
+        let overrides = ThemeOverrides {
+            cursor: Some("#eeeeee".into()),
+            ..overrides
+        };
+        let theme = dark.with_overrides(&overrides);
+        assert_eq!(theme.cursor.color, Rgb::new(0xee, 0xee, 0xee));
+        let theme = dark.with_overrides(&ThemeOverrides {
+            background: Some("#000000".into()),
+            ..Default::default()
+        });
+        assert_eq!(theme.cursor.color, dark.cursor.color);
+        assert_eq!(theme.colors.selection, dark.colors.selection);
+
+        // A program switching to a light background (OSC 11)
+        let overrides = ColorOverrides {
+            background: Some(Rgb::new(0xff, 0xff, 0xff)),
+            foreground: Some(Rgb::new(0, 0, 0)),
+            ..Default::default()
+        };
+        let theme = dark.with_color_overrides(&overrides);
+        assert!(theme.cursor.color.contrast(theme.colors.background) >= MIN_CURSOR_CONTRAST);
+        let theme = dark.with_color_overrides(&ColorOverrides {
+            cursor: Some(Rgb::new(0xff, 0xff, 0xfe)),
+            ..overrides
+        });
+        assert_eq!(theme.cursor.color, Rgb::new(0xff, 0xff, 0xfe));
+    }
+
+    #[test]
+    fn test_font_resolve() {
+        let font = FontConfig {
+            family: "Bogus Mono, Other Bogus".into(),
+            fallback: vec!["Fira Code".into()],
+            ..Default::default()
+        };
+        assert_eq!(
+            font.candidates(),
+            ["Bogus Mono", "Other Bogus", "Fira Code", "monospace"]
+        );
+
+        // The second configured family is still the user's choice
+        let (used, substitution) = font.resolve(|name| name == "Other Bogus");
+        assert_eq!(used, "Other Bogus");
+        assert_eq!(substitution, None);
+
+        let (used, substitution) = font.resolve(|name| name == "Fira Code");
+        assert_eq!(used, "Fira Code");
+        assert_eq!(
+            substitution.unwrap().to_string(),
+            "Font 'Bogus Mono, Other Bogus' not found, using 'Fira Code'"
+        );
+
+        // Nothing installed still yields a font and a usable cell size
+        let (used, substitution) = font.resolve(|_| false);
+        assert_eq!(used, SYSTEM_MONOSPACE);
+        assert!(substitution.is_some());
+        let (width, height) = estimated_cell_size(font.size);
+        assert!(width > 0.0 && height > width);
+        let (width, height) = estimated_cell_size(0.0);
+        assert!(width > 0.0 && height > 0.0);
+
+        let font = FontConfig {
+            family: "Monospace".into(),
+            ..Default::default()
+        };
+        assert_eq!(font.candidates(), ["Monospace"]);
+    }
+}
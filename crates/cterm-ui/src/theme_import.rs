@@ -0,0 +1,539 @@
+//! Importing color schemes from other terminals
+//!
+//! Reads the color scheme formats of iTerm2 (`.itermcolors`), Alacritty
+//! (TOML, or the YAML of older versions) and Windows Terminal (a JSON color
+//! scheme or settings fragment) into a [`Theme`]. Only the terminal colors
+//! are imported; the tab bar and other UI colors are the light or dark
+//! defaults, whichever suits the background.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use cterm_core::color::Rgb;
+use thiserror::Error;
+
+use crate::theme::{Theme, UiColors};
+
+/// Theme import errors
+#[derive(Error, Debug)]
+pub enum ThemeImportError {
+    #[error("Failed to read theme file: {0}")]
+    Read(#[from] std::io::Error),
+
+    #[error("Unsupported theme file: {0} (expected .itermcolors, .toml, .yml or .json)")]
+    UnsupportedFormat(String),
+
+    #[error("Failed to parse theme file: {0}")]
+    Parse(String),
+
+    #[error("No foreground and background colors found in theme file")]
+    MissingColors,
+}
+
+/// Color scheme formats that can be imported
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThemeFormat {
+    /// iTerm2 `.itermcolors` (XML property list)
+    ItermColors,
+    /// Alacritty configuration or theme file (TOML)
+    Alacritty,
+    /// Alacritty configuration of versions before 0.13 (YAML)
+    AlacrittyYaml,
+    /// Windows Terminal color scheme (JSON)
+    WindowsTerminal,
+}
+
+impl ThemeFormat {
+    /// Guess the format from a file's extension
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+        match ext.as_str() {
+            "itermcolors" => Some(Self::ItermColors),
+            "toml" => Some(Self::Alacritty),
+            "yml" | "yaml" => Some(Self::AlacrittyYaml),
+            "json" => Some(Self::WindowsTerminal),
+            _ => None,
+        }
+    }
+}
+
+/// Names of the 16 ANSI colors as Alacritty spells them
+const ALACRITTY_COLORS: [&str; 8] = [
+    "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+];
+
+/// Names of the 16 ANSI colors as Windows Terminal spells them
+const WINDOWS_TERMINAL_COLORS: [&str; 16] = [
+    "black",
+    "red",
+    "green",
+    "yellow",
+    "blue",
+    "purple",
+    "cyan",
+    "white",
+    "brightBlack",
+    "brightRed",
+    "brightGreen",
+    "brightYellow",
+    "brightBlue",
+    "brightPurple",
+    "brightCyan",
+    "brightWhite",
+];
+
+/// Colors read from a scheme; the ones it doesn't set keep the defaults
+#[derive(Debug, Default)]
+struct SchemeColors {
+    ansi: [Option<Rgb>; 16],
+    foreground: Option<Rgb>,
+    background: Option<Rgb>,
+    cursor: Option<Rgb>,
+    cursor_text: Option<Rgb>,
+    selection: Option<Rgb>,
+}
+
+impl SchemeColors {
+    fn into_theme(self, name: &str) -> Result<Theme, ThemeImportError> {
+        let (Some(foreground), Some(background)) = (self.foreground, self.background) else {
+            return Err(ThemeImportError::MissingColors);
+        };
+
+        let (r, g, b) = background.to_f64();
+        let light = 0.299 * r + 0.587 * g + 0.114 * b > 0.5;
+        let mut theme = if light { Theme::light() } else { Theme::dark() };
+        theme.name = name.to_string();
+        theme.author = None;
+        theme.ui = if light {
+            UiColors::light()
+        } else {
+            UiColors::dark()
+        };
+
+        for (slot, color) in theme.colors.ansi.iter_mut().zip(self.ansi) {
+            if let Some(color) = color {
+                *slot = color;
+            }
+        }
+        theme.colors.foreground = foreground;
+        theme.colors.background = background;
+        let cursor = self.cursor.unwrap_or(foreground);
+        theme.colors.cursor = cursor;
+        theme.cursor.color = cursor;
+        theme.cursor.text_color = self.cursor_text.unwrap_or(background);
+        if let Some(selection) = self.selection {
+            theme.colors.selection = selection;
+        }
+        Ok(theme)
+    }
+}
+
+impl Theme {
+    /// Import a color scheme, picking the format from the file extension
+    ///
+    /// The theme is named after the file (or the scheme's own name, for
+    /// Windows Terminal schemes).
+    pub fn import(path: &Path) -> Result<Theme, ThemeImportError> {
+        let format = ThemeFormat::from_path(path)
+            .ok_or_else(|| ThemeImportError::UnsupportedFormat(path.display().to_string()))?;
+        Self::import_as(path, format)
+    }
+
+    /// Import an iTerm2 `.itermcolors` file
+    pub fn from_iterm_colors(path: &Path) -> Result<Theme, ThemeImportError> {
+        Self::import_as(path, ThemeFormat::ItermColors)
+    }
+
+    /// Import the colors of an Alacritty configuration or theme file (TOML,
+    /// or YAML for `.yml` files)
+    pub fn from_alacritty(path: &Path) -> Result<Theme, ThemeImportError> {
+        let format = match ThemeFormat::from_path(path) {
+            Some(ThemeFormat::AlacrittyYaml) => ThemeFormat::AlacrittyYaml,
+            _ => ThemeFormat::Alacritty,
+        };
+        Self::import_as(path, format)
+    }
+
+    /// Import a Windows Terminal color scheme
+    pub fn from_windows_terminal(path: &Path) -> Result<Theme, ThemeImportError> {
+        Self::import_as(path, ThemeFormat::WindowsTerminal)
+    }
+
+    fn import_as(path: &Path, format: ThemeFormat) -> Result<Theme, ThemeImportError> {
+        let text = std::fs::read_to_string(path)?;
+        let name = path
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "Imported".into());
+        Theme::parse_import(&text, format, &name)
+    }
+
+    /// Parse a color scheme in the given format into a theme called `name`
+    pub fn parse_import(
+        text: &str,
+        format: ThemeFormat,
+        name: &str,
+    ) -> Result<Theme, ThemeImportError> {
+        match format {
+            ThemeFormat::ItermColors => parse_iterm_colors(text)?.into_theme(name),
+            ThemeFormat::Alacritty => {
+                let table: toml::Table =
+                    toml::from_str(text).map_err(|e| ThemeImportError::Parse(e.to_string()))?;
+                let mut values = HashMap::new();
+                flatten_toml(&table, "", &mut values);
+                alacritty_colors(&values).into_theme(name)
+            }
+            ThemeFormat::AlacrittyYaml => alacritty_colors(&flatten_yaml(text)).into_theme(name),
+            ThemeFormat::WindowsTerminal => parse_windows_terminal(text, name),
+        }
+    }
+}
+
+/// Read the colors of an iTerm2 property list
+///
+/// Each color is a `<key>` naming it followed by a `<dict>` with its
+/// "Red/Green/Blue Component" as reals between 0 and 1.
+fn parse_iterm_colors(text: &str) -> Result<SchemeColors, ThemeImportError> {
+    let mut colors = SchemeColors::default();
+    let mut rest = text;
+    while let Some((name, after)) = next_element(rest, "key") {
+        rest = after;
+        let Some(body) = rest.trim_start().strip_prefix("<dict>") else {
+            continue;
+        };
+        let end = body
+            .find("</dict>")
+            .ok_or_else(|| ThemeImportError::Parse(format!("unterminated <dict> for {}", name)))?;
+        rest = &body[end + "</dict>".len()..];
+
+        let color = parse_iterm_color(&body[..end])
+            .ok_or_else(|| ThemeImportError::Parse(format!("invalid color {}", name)))?;
+        let slot = match name {
+            "Foreground Color" => &mut colors.foreground,
+            "Background Color" => &mut colors.background,
+            "Cursor Color" => &mut colors.cursor,
+            "Cursor Text Color" => &mut colors.cursor_text,
+            "Selection Color" => &mut colors.selection,
+            _ => match name
+                .strip_prefix("Ansi ")
+                .and_then(|n| n.strip_suffix(" Color"))
+                .and_then(|n| n.parse::<usize>().ok())
+                .and_then(|i| colors.ansi.get_mut(i))
+            {
+                Some(slot) => slot,
+                None => continue,
+            },
+        };
+        *slot = Some(color);
+    }
+    Ok(colors)
+}
+
+/// Read the components of one iTerm2 color dictionary
+fn parse_iterm_color(dict: &str) -> Option<Rgb> {
+    let (mut r, mut g, mut b) = (None, None, None);
+    let mut rest = dict;
+    while let Some((key, after)) = next_element(rest, "key") {
+        let (tag, value, after) = next_value(after)?;
+        rest = after;
+        let slot = match key {
+            "Red Component" => &mut r,
+            "Green Component" => &mut g,
+            "Blue Component" => &mut b,
+            _ => continue,
+        };
+        if tag != "real" && tag != "integer" {
+            return None;
+        }
+        let component = value.trim().parse::<f64>().ok()?.clamp(0.0, 1.0);
+        *slot = Some((component * 255.0).round() as u8);
+    }
+    Some(Rgb::new(r?, g?, b?))
+}
+
+/// Read the element following a `<key>`, returning its tag, text and what
+/// follows it
+fn next_value(text: &str) -> Option<(&str, &str, &str)> {
+    let start = text.find('<')? + 1;
+    let tag_len = text[start..].find('>')?;
+    let tag = &text[start..start + tag_len];
+    let after_tag = &text[start + tag_len + 1..];
+    // Self-closing, e.g. <true/>
+    if let Some(tag) = tag.strip_suffix('/') {
+        return Some((tag, "", after_tag));
+    }
+    let close = format!("</{}>", tag);
+    let len = after_tag.find(&close)?;
+    Some((tag, &after_tag[..len], &after_tag[len + close.len()..]))
+}
+
+/// Find the next `<tag>...</tag>` element, returning its text and what
+/// follows it
+///
+/// Elements with other tags (e.g. `<string>`) before it are skipped.
+fn next_element<'a>(text: &'a str, tag: &str) -> Option<(&'a str, &'a str)> {
+    let open = format!("<{}>", tag);
+    let close = format!("</{}>", tag);
+    let start = text.find(&open)? + open.len();
+    let len = text[start..].find(&close)?;
+    Some((
+        &text[start..start + len],
+        &text[start + len + close.len()..],
+    ))
+}
+
+/// Flatten a TOML table into dotted keys ("colors.primary.background")
+fn flatten_toml(table: &toml::Table, prefix: &str, out: &mut HashMap<String, String>) {
+    for (key, value) in table {
+        let path = if prefix.is_empty() {
+            key.clone()
+        } else {
+            format!("{}.{}", prefix, key)
+        };
+        match value {
+            toml::Value::Table(table) => flatten_toml(table, &path, out),
+            toml::Value::String(s) => {
+                out.insert(path, s.clone());
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Flatten the nested mappings of a YAML document into dotted keys
+///
+/// Only handles what Alacritty color configurations use: `key: value`
+/// mappings nested by indentation, with optional quotes and comments.
+fn flatten_yaml(text: &str) -> HashMap<String, String> {
+    let mut out = HashMap::new();
+    // (indentation, key) of the mappings the current line is nested in
+    let mut parents: Vec<(usize, &str)> = Vec::new();
+
+    for line in text.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') || trimmed.starts_with('-') {
+            continue;
+        }
+        let Some((key, value)) = trimmed.split_once(':') else {
+            continue;
+        };
+        let indent = line.len() - line.trim_start().len();
+        while parents.last().is_some_and(|&(i, _)| i >= indent) {
+            parents.pop();
+        }
+
+        let value = value.split(" #").next().unwrap_or_default().trim();
+        let key = key.trim().trim_matches(['"', '\'']);
+        if value.is_empty() {
+            parents.push((indent, key));
+        } else {
+            let mut path: Vec<&str> = parents.iter().map(|&(_, k)| k).collect();
+            path.push(key);
+            out.insert(path.join("."), value.trim_matches(['"', '\'']).to_string());
+        }
+    }
+    out
+}
+
+/// Parse an Alacritty color ("#rrggbb" or "0xrrggbb")
+fn alacritty_color(value: &str) -> Option<Rgb> {
+    Rgb::from_hex(value.strip_prefix("0x").unwrap_or(value))
+}
+
+/// Pick the colors out of a flattened Alacritty configuration
+fn alacritty_colors(values: &HashMap<String, String>) -> SchemeColors {
+    let color = |key: &str| values.get(key).and_then(|v| alacritty_color(v));
+
+    let mut colors = SchemeColors {
+        foreground: color("colors.primary.foreground"),
+        background: color("colors.primary.background"),
+        cursor: color("colors.cursor.cursor"),
+        cursor_text: color("colors.cursor.text"),
+        selection: color("colors.selection.background"),
+        ..Default::default()
+    };
+    for (i, name) in ALACRITTY_COLORS.iter().enumerate() {
+        colors.ansi[i] = color(&format!("colors.normal.{}", name));
+        colors.ansi[i + 8] = color(&format!("colors.bright.{}", name));
+    }
+    colors
+}
+
+/// Parse a Windows Terminal color scheme
+///
+/// Accepts a single scheme object, an array of schemes, or a settings file
+/// or fragment with a `schemes` array; the first scheme is used.
+fn parse_windows_terminal(text: &str, default_name: &str) -> Result<Theme, ThemeImportError> {
+    let value: serde_json::Value =
+        serde_json::from_str(text).map_err(|e| ThemeImportError::Parse(e.to_string()))?;
+    let scheme = match &value {
+        serde_json::Value::Array(schemes) => schemes.first(),
+        serde_json::Value::Object(object) => match object.get("schemes") {
+            Some(serde_json::Value::Array(schemes)) => schemes.first(),
+            _ => Some(&value),
+        },
+        _ => None,
+    }
+    .ok_or(ThemeImportError::MissingColors)?;
+
+    let color = |key: &str| scheme.get(key)?.as_str().and_then(Rgb::from_hex);
+    let mut colors = SchemeColors {
+        foreground: color("foreground"),
+        background: color("background"),
+        cursor: color("cursorColor"),
+        selection: color("selectionBackground"),
+        ..Default::default()
+    };
+    for (slot, name) in colors.ansi.iter_mut().zip(WINDOWS_TERMINAL_COLORS) {
+        *slot = color(name);
+    }
+
+    let name = scheme
+        .get("name")
+        .and_then(|n| n.as_str())
+        .unwrap_or(default_name);
+    colors.into_theme(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ITERM: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+	<key>Ansi 1 Color</key>
+	<dict>
+		<key>Alpha Component</key>
+		<real>1</real>
+		<key>Blue Component</key>
+		<real>0.0</real>
+		<key>Color Space</key>
+		<string>sRGB</string>
+		<key>Green Component</key>
+		<real>0.0</real>
+		<key>Red Component</key>
+		<real>1</real>
+	</dict>
+	<key>Background Color</key>
+	<dict>
+		<key>Blue Component</key>
+		<real>0.1</real>
+		<key>Green Component</key>
+		<real>0.1</real>
+		<key>Red Component</key>
+		<real>0.1</real>
+	</dict>
+	<key>Foreground Color</key>
+	<dict>
+		<key>Blue Component</key>
+		<real>0.8</real>
+		<key>Green Component</key>
+		<real>0.8</real>
+		<key>Red Component</key>
+		<real>0.8</real>
+	</dict>
+</dict>
+</plist>
+"#;
+
+    #[test]
+    fn test_import_iterm_colors() {
+        let theme = Theme::parse_import(ITERM, ThemeFormat::ItermColors, "Test").unwrap();
+        assert_eq!(theme.name, "Test");
+        assert_eq!(theme.colors.ansi[1], Rgb::new(255, 0, 0));
+        assert_eq!(theme.colors.background, Rgb::new(26, 26, 26));
+        assert_eq!(theme.colors.foreground, Rgb::new(204, 204, 204));
+        // Unset colors fall back: cursor to the foreground, ANSI to the defaults
+        assert_eq!(theme.cursor.color, theme.colors.foreground);
+        assert_eq!(theme.colors.ansi[2], Theme::dark().colors.ansi[2]);
+    }
+
+    #[test]
+    fn test_import_alacritty() {
+        let toml = r##"
+[font]
+size = 12.0
+
+[colors.primary]
+background = "#fdf6e3"
+foreground = "0x657b83"
+
+[colors.normal]
+red = "#dc322f"
+
+[colors.bright]
+black = "#002b36"
+
+[colors.cursor]
+cursor = "#ff0000"
+text = "CellBackground"
+"##;
+        let theme = Theme::parse_import(toml, ThemeFormat::Alacritty, "Solarized").unwrap();
+        assert_eq!(theme.colors.background, Rgb::new(0xfd, 0xf6, 0xe3));
+        assert_eq!(theme.colors.foreground, Rgb::new(0x65, 0x7b, 0x83));
+        assert_eq!(theme.colors.ansi[1], Rgb::new(0xdc, 0x32, 0x2f));
+        assert_eq!(theme.colors.ansi[8], Rgb::new(0x00, 0x2b, 0x36));
+        assert_eq!(theme.cursor.color, Rgb::new(0xff, 0x00, 0x00));
+        // Light background picks the light UI colors
+        assert_eq!(
+            theme.ui.tab_bar_background,
+            UiColors::light().tab_bar_background
+        );
+
+        let yaml = "\
+# Colors (Solarized Light)
+colors:
+  # Default colors
+  primary:
+    background: '#fdf6e3' # base3
+    foreground: '0x657b83'
+  normal:
+    red:     '#dc322f'
+";
+        let from_yaml = Theme::parse_import(yaml, ThemeFormat::AlacrittyYaml, "Solarized").unwrap();
+        assert_eq!(from_yaml.colors.background, theme.colors.background);
+        assert_eq!(from_yaml.colors.foreground, theme.colors.foreground);
+        assert_eq!(from_yaml.colors.ansi[1], theme.colors.ansi[1]);
+
+        assert!(matches!(
+            Theme::parse_import("[font]\nsize = 12.0\n", ThemeFormat::Alacritty, "x"),
+            Err(ThemeImportError::MissingColors)
+        ));
+    }
+
+    #[test]
+    fn test_import_windows_terminal() {
+        let json = r##"{
+            "schemes": [{
+                "name": "Campbell",
+                "foreground": "#CCCCCC",
+                "background": "#0C0C0C",
+                "cursorColor": "#FFFFFF",
+                "selectionBackground": "#FFFFFF",
+                "purple": "#881798",
+                "brightPurple": "#B4009E"
+            }]
+        }"##;
+        let theme = Theme::parse_import(json, ThemeFormat::WindowsTerminal, "file").unwrap();
+        assert_eq!(theme.name, "Campbell");
+        assert_eq!(theme.colors.foreground, Rgb::new(0xcc, 0xcc, 0xcc));
+        assert_eq!(theme.colors.ansi[5], Rgb::new(0x88, 0x17, 0x98));
+        assert_eq!(theme.colors.ansi[13], Rgb::new(0xb4, 0x00, 0x9e));
+        assert_eq!(theme.colors.selection, Rgb::new(0xff, 0xff, 0xff));
+    }
+
+    #[test]
+    fn test_format_from_path() {
+        assert_eq!(
+            ThemeFormat::from_path(Path::new("Dracula.itermcolors")),
+            Some(ThemeFormat::ItermColors)
+        );
+        assert_eq!(
+            ThemeFormat::from_path(Path::new("alacritty.YML")),
+            Some(ThemeFormat::AlacrittyYaml)
+        );
+        assert_eq!(ThemeFormat::from_path(Path::new("theme.txt")), None);
+    }
+}
@@ -1,5 +1,7 @@
 //! Shared utility functions for UI components
 
+use std::time::{Duration, Instant};
+
 /// Format a byte size for human-readable display
 ///
 /// Returns a string like "1.5 KB", "2.3 MB", "1.0 GB", or "123 bytes"
@@ -19,6 +21,88 @@ pub fn format_size(bytes: usize) -> String {
     }
 }
 
+/// Maximum pointer movement (in pixels) between clicks that still counts
+/// as a repeated click
+const MULTI_CLICK_DISTANCE: f64 = 4.0;
+
+/// Counts consecutive clicks for double/triple-click detection
+///
+/// Used instead of the platform click count when the user configures a
+/// custom multi-click interval. The count cycles 1 → 2 → 3 → 1.
+#[derive(Debug, Clone)]
+pub struct ClickCounter {
+    interval: Duration,
+    last_click: Option<(Instant, f64, f64)>,
+    count: u32,
+}
+
+impl ClickCounter {
+    /// Create a counter with the given maximum interval between clicks
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            last_click: None,
+            count: 0,
+        }
+    }
+
+    /// Register a click at the given position and return the click count
+    pub fn click(&mut self, x: f64, y: f64, now: Instant) -> u32 {
+        let repeated = self.last_click.is_some_and(|(at, last_x, last_y)| {
+            now.duration_since(at) <= self.interval
+                && (x - last_x).abs() <= MULTI_CLICK_DISTANCE
+                && (y - last_y).abs() <= MULTI_CLICK_DISTANCE
+        });
+
+        self.count = if repeated { self.count % 3 + 1 } else { 1 };
+        self.last_click = Some((now, x, y));
+        self.count
+    }
+}
+
+/// Time between auto-scroll steps while a selection is dragged past an edge
+pub const SELECTION_AUTOSCROLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Furthest distance (in rows) past the edge that still speeds up auto-scroll
+const SELECTION_AUTOSCROLL_MAX_ROWS: usize = 10;
+
+/// Lines to scroll per auto-scroll step for a selection dragged to `y`
+///
+/// Negative scrolls back into the scrollback (pointer above the view),
+/// positive toward newer lines (pointer below a view `height` pixels tall)
+/// and zero while the pointer is inside. Each row of distance past the edge
+/// adds `speed` lines per step; a `speed` of 0 turns auto-scroll off.
+pub fn selection_autoscroll_lines(y: f64, height: f64, cell_height: f64, speed: usize) -> isize {
+    let distance = if y < 0.0 {
+        -y
+    } else if y >= height {
+        y - height
+    } else {
+        return 0;
+    };
+
+    let rows = ((distance / cell_height.max(1.0)) as usize + 1).min(SELECTION_AUTOSCROLL_MAX_ROWS);
+    let lines = (rows * speed) as isize;
+    if y < 0.0 {
+        -lines
+    } else {
+        lines
+    }
+}
+
+/// Window size, in pixels, for a window dragged to `size`, snapped down to
+/// whole cells
+///
+/// `chrome` is the part of the size that isn't terminal grid (borders, title
+/// and tab bars). At least one cell is kept.
+pub fn snap_to_cells(size: i32, chrome: i32, cell: f64) -> i32 {
+    if cell <= 0.0 {
+        return size;
+    }
+    let cells = (((size - chrome).max(0) as f64 / cell).floor() as i32).max(1);
+    chrome + (cells as f64 * cell).ceil() as i32
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -34,4 +118,52 @@ mod tests {
         assert_eq!(format_size(1572864), "1.5 MB");
         assert_eq!(format_size(1073741824), "1.0 GB");
     }
+
+    #[test]
+    fn test_click_counter_cycles() {
+        let mut counter = ClickCounter::new(Duration::from_millis(300));
+        let t = Instant::now();
+        assert_eq!(counter.click(10.0, 10.0, t), 1);
+        assert_eq!(counter.click(11.0, 10.0, t + Duration::from_millis(100)), 2);
+        assert_eq!(counter.click(11.0, 11.0, t + Duration::from_millis(200)), 3);
+        assert_eq!(counter.click(11.0, 11.0, t + Duration::from_millis(300)), 1);
+    }
+
+    #[test]
+    fn test_selection_autoscroll_lines() {
+        // Inside the view
+        assert_eq!(selection_autoscroll_lines(0.0, 100.0, 10.0, 1), 0);
+        assert_eq!(selection_autoscroll_lines(99.0, 100.0, 10.0, 1), 0);
+
+        // Past the edges, faster the further out
+        assert_eq!(selection_autoscroll_lines(-1.0, 100.0, 10.0, 1), -1);
+        assert_eq!(selection_autoscroll_lines(-25.0, 100.0, 10.0, 1), -3);
+        assert_eq!(selection_autoscroll_lines(100.0, 100.0, 10.0, 2), 2);
+        assert_eq!(selection_autoscroll_lines(1000.0, 100.0, 10.0, 1), 10);
+
+        // Disabled
+        assert_eq!(selection_autoscroll_lines(-50.0, 100.0, 10.0, 0), 0);
+    }
+
+    #[test]
+    fn test_snap_to_cells() {
+        assert_eq!(snap_to_cells(830, 30, 10.0), 830);
+        assert_eq!(snap_to_cells(837, 30, 10.0), 830);
+        // Fractional cells round up so the grid still fits
+        assert_eq!(snap_to_cells(100, 0, 8.4), 93);
+        // At least one cell
+        assert_eq!(snap_to_cells(20, 30, 10.0), 40);
+        assert_eq!(snap_to_cells(123, 30, 0.0), 123);
+    }
+
+    #[test]
+    fn test_click_counter_resets() {
+        let mut counter = ClickCounter::new(Duration::from_millis(300));
+        let t = Instant::now();
+        assert_eq!(counter.click(10.0, 10.0, t), 1);
+        // Too slow
+        assert_eq!(counter.click(10.0, 10.0, t + Duration::from_millis(500)), 1);
+        // Moved too far
+        assert_eq!(counter.click(50.0, 10.0, t + Duration::from_millis(600)), 1);
+    }
 }
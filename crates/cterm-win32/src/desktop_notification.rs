@@ -0,0 +1,91 @@
+//! Desktop notifications and event sounds
+//!
+//! Shows notifications as balloons from a notification area icon owned by
+//! the window. The icon is added with the first notification and removed
+//! when the window is destroyed. Sounds are played with `PlaySound`.
+
+use winapi::um::playsoundapi::{PlaySoundW, SND_ALIAS, SND_ASYNC, SND_FILENAME, SND_NODEFAULT};
+use winapi::um::winuser::{MessageBeep, MB_OK};
+use windows::Win32::Foundation::HWND;
+use windows::Win32::UI::Shell::{
+    Shell_NotifyIconW, NIF_ICON, NIF_INFO, NIF_TIP, NIIF_INFO, NIM_ADD, NIM_DELETE, NIM_MODIFY,
+    NOTIFYICONDATAW,
+};
+use windows::Win32::UI::WindowsAndMessaging::{LoadIconW, IDI_APPLICATION};
+
+use cterm_app::notify::{Notification, Sound};
+
+/// Id of the window's notification area icon
+const ICON_ID: u32 = 1;
+
+/// Copy a string into a fixed-size, NUL-terminated UTF-16 buffer,
+/// truncating it if needed
+fn copy_wide(dest: &mut [u16], s: &str) {
+    let len = dest.len() - 1;
+    for (slot, unit) in dest.iter_mut().zip(s.encode_utf16().take(len)) {
+        *slot = unit;
+    }
+}
+
+/// Show a notification as a balloon from the window's notification area icon
+pub fn show(hwnd: HWND, notification: &Notification) {
+    let mut data = NOTIFYICONDATAW {
+        cbSize: std::mem::size_of::<NOTIFYICONDATAW>() as u32,
+        hWnd: hwnd,
+        uID: ICON_ID,
+        uFlags: NIF_ICON | NIF_TIP | NIF_INFO,
+        dwInfoFlags: NIIF_INFO,
+        ..Default::default()
+    };
+    if let Ok(icon) = unsafe { LoadIconW(None, IDI_APPLICATION) } {
+        data.hIcon = icon;
+    }
+    copy_wide(&mut data.szTip, "cterm");
+    copy_wide(&mut data.szInfoTitle, &notification.title);
+    copy_wide(&mut data.szInfo, &notification.body);
+
+    unsafe {
+        if !Shell_NotifyIconW(NIM_MODIFY, &data).as_bool()
+            && !Shell_NotifyIconW(NIM_ADD, &data).as_bool()
+        {
+            log::warn!("Failed to show notification: {}", notification.body);
+        }
+    }
+}
+
+/// Remove the window's notification area icon, if it was added
+pub fn remove(hwnd: HWND) {
+    let data = NOTIFYICONDATAW {
+        cbSize: std::mem::size_of::<NOTIFYICONDATAW>() as u32,
+        hWnd: hwnd,
+        uID: ICON_ID,
+        ..Default::default()
+    };
+    unsafe {
+        let _ = Shell_NotifyIconW(NIM_DELETE, &data);
+    }
+}
+
+/// Play an event's sound, beeping if it can't be found
+pub fn play_sound(sound: &Sound) {
+    let (name, flags) = match sound {
+        Sound::Beep => {
+            unsafe { MessageBeep(MB_OK) };
+            return;
+        }
+        Sound::Named(name) => (name.clone(), SND_ALIAS),
+        Sound::File(path) => (path.to_string_lossy().into_owned(), SND_FILENAME),
+    };
+    let wide: Vec<u16> = name.encode_utf16().chain(std::iter::once(0)).collect();
+    let played = unsafe {
+        PlaySoundW(
+            wide.as_ptr(),
+            std::ptr::null_mut(),
+            flags | SND_ASYNC | SND_NODEFAULT,
+        )
+    };
+    if played == 0 {
+        log::warn!("Sound {:?} not found, beeping instead", sound);
+        unsafe { MessageBeep(MB_OK) };
+    }
+}
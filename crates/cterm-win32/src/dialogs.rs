@@ -525,6 +525,60 @@ pub fn show_save_file_dialog(
     }
 }
 
+/// Show an open dialog for choosing an existing file
+pub fn show_open_file_dialog(
+    parent: HWND,
+    title: &str,
+    filter: &str,
+) -> Option<std::path::PathBuf> {
+    use winapi::um::commdlg::{
+        GetOpenFileNameW, OFN_FILEMUSTEXIST, OFN_PATHMUSTEXIST, OPENFILENAMEW,
+    };
+
+    let title = to_wide(title);
+    let filter = to_wide(filter);
+    let mut filename = [0u16; 260];
+
+    let mut ofn = OPENFILENAMEW {
+        lStructSize: std::mem::size_of::<OPENFILENAMEW>() as u32,
+        hwndOwner: parent,
+        hInstance: ptr::null_mut(),
+        lpstrFilter: filter.as_ptr(),
+        lpstrCustomFilter: ptr::null_mut(),
+        nMaxCustFilter: 0,
+        nFilterIndex: 1,
+        lpstrFile: filename.as_mut_ptr(),
+        nMaxFile: filename.len() as u32,
+        lpstrFileTitle: ptr::null_mut(),
+        nMaxFileTitle: 0,
+        lpstrInitialDir: ptr::null(),
+        lpstrTitle: title.as_ptr(),
+        Flags: OFN_FILEMUSTEXIST | OFN_PATHMUSTEXIST,
+        nFileOffset: 0,
+        nFileExtension: 0,
+        lpstrDefExt: ptr::null(),
+        lCustData: 0,
+        lpfnHook: None,
+        lpTemplateName: ptr::null(),
+        pvReserved: ptr::null_mut(),
+        dwReserved: 0,
+        FlagsEx: 0,
+    };
+
+    unsafe {
+        if GetOpenFileNameW(&mut ofn) != 0 {
+            let len = filename
+                .iter()
+                .position(|&c| c == 0)
+                .unwrap_or(filename.len());
+            let path = String::from_utf16_lossy(&filename[..len]);
+            Some(std::path::PathBuf::from(path))
+        } else {
+            None
+        }
+    }
+}
+
 /// Show a save dialog for file transfer (wrapper for show_save_file_dialog)
 ///
 /// Takes a windows crate HWND and converts it for winapi
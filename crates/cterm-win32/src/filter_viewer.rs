@@ -0,0 +1,183 @@
+//! Filter Scrollback results window for cterm
+//!
+//! Read-only window listing the scrollback lines that matched a filter.
+
+use std::ptr;
+
+use winapi::shared::minwindef::{LPARAM, LRESULT, UINT, WPARAM};
+use winapi::shared::windef::HWND;
+use winapi::um::winbase::MulDiv;
+use winapi::um::wingdi::{CreateFontW, GetDeviceCaps, FW_NORMAL, LOGPIXELSY};
+use winapi::um::winuser::*;
+
+use crate::dialog_utils::{set_edit_text, to_wide};
+
+/// Window class name for the filter results window
+const FILTER_VIEWER_CLASS: &str = "cterm_filter_viewer";
+
+// Control IDs
+const IDC_RESULTS_EDIT: i32 = 1001;
+
+/// Register the filter results window class
+fn register_window_class() -> bool {
+    let class_name = to_wide(FILTER_VIEWER_CLASS);
+
+    let wc = WNDCLASSEXW {
+        cbSize: std::mem::size_of::<WNDCLASSEXW>() as u32,
+        style: CS_HREDRAW | CS_VREDRAW,
+        lpfnWndProc: Some(window_proc),
+        cbClsExtra: 0,
+        cbWndExtra: 0,
+        hInstance: ptr::null_mut(),
+        hIcon: ptr::null_mut(),
+        hCursor: unsafe { LoadCursorW(ptr::null_mut(), IDC_ARROW) },
+        hbrBackground: (COLOR_WINDOW + 1) as *mut _,
+        lpszMenuName: ptr::null(),
+        lpszClassName: class_name.as_ptr(),
+        hIconSm: ptr::null_mut(),
+    };
+
+    unsafe { RegisterClassExW(&wc) != 0 }
+}
+
+/// Show the lines that matched `pattern` in a read-only window
+pub fn show_filter_results(parent: HWND, pattern: &str, lines: &[String]) {
+    // Register window class (only once)
+    static REGISTERED: std::sync::OnceLock<bool> = std::sync::OnceLock::new();
+    let _ = REGISTERED.get_or_init(register_window_class);
+
+    let class_name = to_wide(FILTER_VIEWER_CLASS);
+    let title = match lines.len() {
+        1 => format!("Filter: {} (1 line)", pattern),
+        n => format!("Filter: {} ({} lines)", pattern, n),
+    };
+    let title = to_wide(&title);
+
+    // The text is handed to WM_CREATE, which takes ownership of it
+    let text = if lines.is_empty() {
+        "No matching lines".to_string()
+    } else {
+        lines.join("\r\n")
+    };
+    let text = Box::into_raw(Box::new(text));
+
+    let hwnd = unsafe {
+        CreateWindowExW(
+            0,
+            class_name.as_ptr(),
+            title.as_ptr(),
+            WS_OVERLAPPEDWINDOW,
+            CW_USEDEFAULT,
+            CW_USEDEFAULT,
+            800,
+            500,
+            parent,
+            ptr::null_mut(),
+            ptr::null_mut(),
+            text as *mut _,
+        )
+    };
+
+    if !hwnd.is_null() {
+        unsafe {
+            ShowWindow(hwnd, SW_SHOW);
+            UpdateWindow(hwnd);
+        }
+    }
+}
+
+/// Window procedure for the filter results window
+unsafe extern "system" fn window_proc(
+    hwnd: HWND,
+    msg: UINT,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    match msg {
+        WM_CREATE => {
+            let create = &*(lparam as *const CREATESTRUCTW);
+            let text = Box::from_raw(create.lpCreateParams as *mut String);
+            init_window(hwnd, &text);
+            0
+        }
+        WM_SIZE => {
+            let edit = GetDlgItem(hwnd, IDC_RESULTS_EDIT);
+            if !edit.is_null() {
+                let mut rect = std::mem::zeroed();
+                GetClientRect(hwnd, &mut rect);
+                SetWindowPos(
+                    edit,
+                    ptr::null_mut(),
+                    0,
+                    0,
+                    rect.right - rect.left,
+                    rect.bottom - rect.top,
+                    SWP_NOZORDER,
+                );
+            }
+            0
+        }
+        WM_CLOSE => {
+            DestroyWindow(hwnd);
+            0
+        }
+        _ => DefWindowProcW(hwnd, msg, wparam, lparam),
+    }
+}
+
+/// Create the read-only edit control filling the window
+unsafe fn init_window(hwnd: HWND, text: &str) {
+    let mut rect = std::mem::zeroed();
+    GetClientRect(hwnd, &mut rect);
+
+    let edit_class = to_wide("EDIT");
+    let edit = CreateWindowExW(
+        0,
+        edit_class.as_ptr(),
+        ptr::null(),
+        WS_CHILD
+            | WS_VISIBLE
+            | WS_VSCROLL
+            | WS_HSCROLL
+            | ES_MULTILINE
+            | ES_AUTOVSCROLL
+            | ES_AUTOHSCROLL
+            | ES_READONLY,
+        0,
+        0,
+        rect.right - rect.left,
+        rect.bottom - rect.top,
+        hwnd,
+        IDC_RESULTS_EDIT as *mut _,
+        ptr::null_mut(),
+        ptr::null_mut(),
+    );
+
+    // Monospace font, like the log viewer
+    let hdc = GetDC(hwnd);
+    let log_pixels_y = GetDeviceCaps(hdc, LOGPIXELSY);
+    ReleaseDC(hwnd, hdc);
+
+    let font_name = to_wide("Consolas");
+    let font = CreateFontW(
+        -MulDiv(10, log_pixels_y, 72), // 10pt font
+        0,
+        0,
+        0,
+        FW_NORMAL,
+        0,
+        0,
+        0,
+        0, // DEFAULT_CHARSET
+        0,
+        0,
+        0,
+        1, // FIXED_PITCH
+        font_name.as_ptr(),
+    );
+    if !font.is_null() {
+        SendMessageW(edit, WM_SETFONT, font as WPARAM, 1);
+    }
+
+    set_edit_text(edit, text);
+}
@@ -148,6 +148,18 @@ pub fn get_modifiers() -> Modifiers {
     mods
 }
 
+/// Whether AltGr is held
+///
+/// Windows reports AltGr as Right Alt plus a synthesized Left Ctrl, so it
+/// looks like Ctrl+Alt. Keys pressed with it type the layout's third-level
+/// characters (`{` is AltGr+7 on German keyboards), which arrive as WM_CHAR.
+pub fn is_altgr_down() -> bool {
+    unsafe {
+        winapi::um::winuser::GetKeyState(winuser::VK_RMENU) & 0x8000u16 as i16 != 0
+            && winapi::um::winuser::GetKeyState(winuser::VK_LCONTROL) & 0x8000u16 as i16 != 0
+    }
+}
+
 /// Convert virtual key to terminal escape sequence for special keys
 pub fn vk_to_terminal_seq(
     vk: u16,
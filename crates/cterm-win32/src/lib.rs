@@ -7,10 +7,12 @@
 #![allow(clippy::not_unsafe_ptr_arg_deref)]
 
 pub mod clipboard;
+pub mod desktop_notification;
 pub mod dialog_utils;
 pub mod dialogs;
 pub mod docker_dialog;
 pub mod dpi;
+pub mod filter_viewer;
 pub mod keycode;
 pub mod log_viewer;
 pub mod menu;
@@ -44,6 +46,19 @@ pub struct Args {
     #[arg(short = 'e', long = "execute")]
     pub command: Option<String>,
 
+    /// Run the -e command directly instead of through the shell
+    #[arg(long, requires = "command")]
+    pub no_shell: bool,
+
+    /// Start the terminal in raw mode (no echo or line editing), for
+    /// programs that manage their own line discipline
+    #[arg(long)]
+    pub raw: bool,
+
+    /// Open the first tab from the named tab template
+    #[arg(long, visible_alias = "profile", conflicts_with = "command")]
+    pub template: Option<String>,
+
     /// Set the working directory
     #[arg(short = 'd', long = "directory")]
     pub directory: Option<PathBuf>,
@@ -94,6 +109,14 @@ pub fn run() {
         std::process::exit(exit_code);
     }
 
+    // Fail before opening a window if the template doesn't exist
+    let template = args.template.as_deref().map(|name| {
+        cterm_app::config::load_template(name).unwrap_or_else(|e| {
+            eprintln!("cterm: {}", e);
+            std::process::exit(2);
+        })
+    });
+
     // Store args for later access
     let _ = APP_ARGS.set(args);
 
@@ -104,7 +127,7 @@ pub fn run() {
     dialog_utils::init_common_controls();
 
     // Load configuration
-    let config = match cterm_app::load_config() {
+    let mut config = match cterm_app::load_config() {
         Ok(c) => c,
         Err(e) => {
             log::warn!("Failed to load config, using defaults: {}", e);
@@ -112,6 +135,20 @@ pub fn run() {
         }
     };
 
+    // Run the -e command in the main window instead of the shell
+    let args = get_args();
+    if args.no_shell {
+        config.general.execute_without_shell = true;
+    }
+    if args.raw {
+        config.general.raw_pty = true;
+    }
+    if let Some(ref command) = args.command {
+        if let Err(e) = config.general.set_execute_command(command) {
+            log::error!("Ignoring -e: {}", e);
+        }
+    }
+
     // Load theme
     let theme = load_theme(&config);
 
@@ -119,7 +156,7 @@ pub fn run() {
     // native_windows_gui::init().expect("Failed to initialize NWG");
 
     // Register window class and create window
-    if let Err(e) = run_main_loop(&config, &theme) {
+    if let Err(e) = run_main_loop(&config, &theme, template.as_ref()) {
         log::error!("Application error: {}", e);
         std::process::exit(1);
     }
@@ -152,12 +189,13 @@ fn load_theme(config: &cterm_app::Config) -> cterm_ui::theme::Theme {
 fn run_main_loop(
     config: &cterm_app::Config,
     theme: &cterm_ui::theme::Theme,
+    template: Option<&cterm_app::config::StickyTabConfig>,
 ) -> windows::core::Result<()> {
     // Register window class
     window::register_window_class()?;
 
     // Create main window
-    let _hwnd = window::create_window(config, theme)?;
+    let _hwnd = window::create_window(config, theme, template)?;
 
     // Message loop
     let mut msg = MSG::default();
@@ -193,6 +231,8 @@ mod tests {
         // Just verify the Args struct can be constructed
         let args = Args {
             command: None,
+            no_shell: false,
+            raw: false,
             directory: None,
             fullscreen: false,
             maximized: false,
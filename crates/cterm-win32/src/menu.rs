@@ -6,7 +6,7 @@ use std::ptr;
 
 use winapi::shared::windef::HMENU;
 use winapi::um::winuser::{
-    AppendMenuW, CreateMenu, CreatePopupMenu, SetMenu, MF_POPUP, MF_SEPARATOR, MF_STRING,
+    AppendMenuW, CreateMenu, CreatePopupMenu, SetMenu, MF_GRAYED, MF_POPUP, MF_SEPARATOR, MF_STRING,
 };
 
 /// Menu action identifiers
@@ -15,6 +15,7 @@ use winapi::um::winuser::{
 pub enum MenuAction {
     // File menu
     NewTab = 1001,
+    NewIncognitoTab = 1008,
     NewWindow = 1002,
     QuickOpen = 1007,
     CloseTab = 1003,
@@ -27,12 +28,15 @@ pub enum MenuAction {
     CopyHtml = 2002,
     Paste = 2003,
     SelectAll = 2004,
+    CopyLastOutput = 2005,
 
     // View menu
     ZoomIn = 2501,
     ZoomOut = 2502,
     ZoomReset = 2503,
     Fullscreen = 2504,
+    IncreaseOpacity = 2505,
+    DecreaseOpacity = 2506,
 
     // Terminal menu
     SetTitle = 3001,
@@ -44,6 +48,12 @@ pub enum MenuAction {
     SendSignalKill = 3007,
     SendSignalHup = 3008,
     SendSignalTerm = 3009,
+    RerunLastCommand = 3010,
+    SetMark = 3011,
+    JumpMark = 3012,
+    ClearToLastPrompt = 3013,
+    FilterScrollback = 3014,
+    ToggleRecording = 3015,
 
     // Tabs menu
     PrevTab = 4001,
@@ -64,11 +74,17 @@ pub enum MenuAction {
     CheckUpdates = 5002,
     TabTemplates = 5003,
     About = 5004,
+    ImportTheme = 5005,
+    ImportKeybindings = 5006,
+    ExportKeybindings = 5007,
 
     // Debug menu (shown when Shift is held)
     DebugRelaunch = 6001,
     DebugDumpState = 6002,
     ViewLogs = 6003,
+    DebugSendBytes = 6004,
+    DebugReplayFile = 6005,
+    DebugStopReplay = 6006,
 }
 
 impl MenuAction {
@@ -76,6 +92,7 @@ impl MenuAction {
     pub fn from_id(id: u16) -> Option<Self> {
         match id {
             1001 => Some(Self::NewTab),
+            1008 => Some(Self::NewIncognitoTab),
             1002 => Some(Self::NewWindow),
             1007 => Some(Self::QuickOpen),
             1003 => Some(Self::CloseTab),
@@ -86,10 +103,13 @@ impl MenuAction {
             2002 => Some(Self::CopyHtml),
             2003 => Some(Self::Paste),
             2004 => Some(Self::SelectAll),
+            2005 => Some(Self::CopyLastOutput),
             2501 => Some(Self::ZoomIn),
             2502 => Some(Self::ZoomOut),
             2503 => Some(Self::ZoomReset),
             2504 => Some(Self::Fullscreen),
+            2505 => Some(Self::IncreaseOpacity),
+            2506 => Some(Self::DecreaseOpacity),
             3001 => Some(Self::SetTitle),
             3002 => Some(Self::SetColor),
             3003 => Some(Self::Find),
@@ -99,6 +119,12 @@ impl MenuAction {
             3007 => Some(Self::SendSignalKill),
             3008 => Some(Self::SendSignalHup),
             3009 => Some(Self::SendSignalTerm),
+            3010 => Some(Self::RerunLastCommand),
+            3011 => Some(Self::SetMark),
+            3012 => Some(Self::JumpMark),
+            3013 => Some(Self::ClearToLastPrompt),
+            3014 => Some(Self::FilterScrollback),
+            3015 => Some(Self::ToggleRecording),
             4001 => Some(Self::PrevTab),
             4002 => Some(Self::NextTab),
             4003 => Some(Self::NextAlertedTab),
@@ -115,9 +141,15 @@ impl MenuAction {
             5002 => Some(Self::CheckUpdates),
             5003 => Some(Self::TabTemplates),
             5004 => Some(Self::About),
+            5005 => Some(Self::ImportTheme),
+            5006 => Some(Self::ImportKeybindings),
+            5007 => Some(Self::ExportKeybindings),
             6001 => Some(Self::DebugRelaunch),
             6002 => Some(Self::DebugDumpState),
             6003 => Some(Self::ViewLogs),
+            6004 => Some(Self::DebugSendBytes),
+            6005 => Some(Self::DebugReplayFile),
+            6006 => Some(Self::DebugStopReplay),
             _ => None,
         }
     }
@@ -141,6 +173,11 @@ pub fn create_menu_bar(show_debug: bool) -> HMENU {
         // File menu
         let file_menu = CreatePopupMenu();
         append_menu_item(file_menu, MenuAction::NewTab, "&New Tab\tCtrl+T");
+        append_menu_item(
+            file_menu,
+            MenuAction::NewIncognitoTab,
+            "New &Incognito Tab\tCtrl+Shift+P",
+        );
         append_menu_item(file_menu, MenuAction::NewWindow, "New &Window\tCtrl+N");
         append_menu_item(file_menu, MenuAction::QuickOpen, "&Quick Open\tCtrl+G");
         append_separator(file_menu);
@@ -156,6 +193,11 @@ pub fn create_menu_bar(show_debug: bool) -> HMENU {
         let edit_menu = CreatePopupMenu();
         append_menu_item(edit_menu, MenuAction::Copy, "&Copy\tCtrl+Shift+C");
         append_menu_item(edit_menu, MenuAction::CopyHtml, "Copy as &HTML");
+        append_menu_item(
+            edit_menu,
+            MenuAction::CopyLastOutput,
+            "Copy &Last Command Output\tCtrl+Shift+Y",
+        );
         append_menu_item(edit_menu, MenuAction::Paste, "&Paste\tCtrl+Shift+V");
         append_separator(edit_menu);
         append_menu_item(
@@ -171,6 +213,17 @@ pub fn create_menu_bar(show_debug: bool) -> HMENU {
         append_menu_item(view_menu, MenuAction::ZoomOut, "Zoom &Out\tCtrl+-");
         append_menu_item(view_menu, MenuAction::ZoomReset, "&Reset Zoom\tCtrl+0");
         append_separator(view_menu);
+        append_menu_item(
+            view_menu,
+            MenuAction::IncreaseOpacity,
+            "Increase O&pacity\tCtrl+Shift+Up",
+        );
+        append_menu_item(
+            view_menu,
+            MenuAction::DecreaseOpacity,
+            "Decrease Opa&city\tCtrl+Shift+Down",
+        );
+        append_separator(view_menu);
         append_menu_item(view_menu, MenuAction::Fullscreen, "&Fullscreen\tF11");
         append_popup_menu(menu_bar, view_menu, "&View");
 
@@ -180,6 +233,37 @@ pub fn create_menu_bar(show_debug: bool) -> HMENU {
         append_menu_item(terminal_menu, MenuAction::SetColor, "Set &Color...");
         append_separator(terminal_menu);
         append_menu_item(terminal_menu, MenuAction::Find, "&Find...\tCtrl+Shift+F");
+        append_menu_item(
+            terminal_menu,
+            MenuAction::FilterScrollback,
+            "Filter Scroll&back...",
+        );
+        append_menu_item(
+            terminal_menu,
+            MenuAction::RerunLastCommand,
+            "&Rerun Last Command\tCtrl+Shift+E",
+        );
+        append_menu_item(
+            terminal_menu,
+            MenuAction::ClearToLastPrompt,
+            "Clear to Last &Prompt\tCtrl+Shift+K",
+        );
+        append_separator(terminal_menu);
+        append_menu_item(
+            terminal_menu,
+            MenuAction::SetMark,
+            "Set &Mark\tCtrl+Shift+M",
+        );
+        append_menu_item(
+            terminal_menu,
+            MenuAction::JumpMark,
+            "&Jump to Mark\tCtrl+Shift+J",
+        );
+        append_menu_item(
+            terminal_menu,
+            MenuAction::ToggleRecording,
+            "Toggle Recor&ding\tCtrl+Shift+L",
+        );
         append_separator(terminal_menu);
 
         // Signal submenu
@@ -232,6 +316,17 @@ pub fn create_menu_bar(show_debug: bool) -> HMENU {
         // Help menu
         let help_menu = CreatePopupMenu();
         append_menu_item(help_menu, MenuAction::Preferences, "&Preferences...");
+        append_menu_item(help_menu, MenuAction::ImportTheme, "&Import Theme...");
+        append_menu_item(
+            help_menu,
+            MenuAction::ImportKeybindings,
+            "Import &Keybindings...",
+        );
+        append_menu_item(
+            help_menu,
+            MenuAction::ExportKeybindings,
+            "&Export Keybindings...",
+        );
         append_menu_item(help_menu, MenuAction::TabTemplates, "&Tab Templates...");
         append_separator(help_menu);
         append_menu_item(help_menu, MenuAction::CheckUpdates, "Check for &Updates...");
@@ -244,6 +339,16 @@ pub fn create_menu_bar(show_debug: bool) -> HMENU {
             let debug_menu = CreatePopupMenu();
             append_menu_item(debug_menu, MenuAction::ViewLogs, "&View Logs...");
             append_menu_item(debug_menu, MenuAction::DebugDumpState, "&Dump State");
+            append_menu_item(debug_menu, MenuAction::DebugSendBytes, "&Send Bytes...");
+            append_menu_item(debug_menu, MenuAction::DebugReplayFile, "&Replay File...");
+            append_menu_item(debug_menu, MenuAction::DebugStopReplay, "S&top Replay");
+            append_disabled_item(
+                debug_menu,
+                &format!(
+                    "Unknown Sequences: {}",
+                    cterm_core::parser::unknown_sequence_count()
+                ),
+            );
             append_separator(debug_menu);
             append_menu_item(
                 debug_menu,
@@ -265,6 +370,14 @@ fn append_menu_item(menu: HMENU, action: MenuAction, text: &str) {
     }
 }
 
+/// Append a disabled, informational item to a menu
+fn append_disabled_item(menu: HMENU, text: &str) {
+    let wide = to_wide_string(text);
+    unsafe {
+        AppendMenuW(menu, MF_STRING | MF_GRAYED, 0, wide.as_ptr());
+    }
+}
+
 /// Append a separator to a menu
 fn append_separator(menu: HMENU) {
     unsafe {
@@ -316,6 +429,11 @@ pub fn get_accelerators() -> Vec<Accelerator> {
             key: 'T' as u16,
             modifiers: AcceleratorModifiers::CTRL,
         },
+        Accelerator {
+            action: MenuAction::NewIncognitoTab,
+            key: 'P' as u16,
+            modifiers: AcceleratorModifiers::CTRL | AcceleratorModifiers::SHIFT,
+        },
         Accelerator {
             action: MenuAction::NewWindow,
             key: 'N' as u16,
@@ -347,12 +465,53 @@ pub fn get_accelerators() -> Vec<Accelerator> {
             key: 'A' as u16,
             modifiers: AcceleratorModifiers::CTRL | AcceleratorModifiers::SHIFT,
         },
+        Accelerator {
+            action: MenuAction::CopyLastOutput,
+            key: 'Y' as u16,
+            modifiers: AcceleratorModifiers::CTRL | AcceleratorModifiers::SHIFT,
+        },
+        // View menu
+        Accelerator {
+            action: MenuAction::IncreaseOpacity,
+            key: VK_UP as u16,
+            modifiers: AcceleratorModifiers::CTRL | AcceleratorModifiers::SHIFT,
+        },
+        Accelerator {
+            action: MenuAction::DecreaseOpacity,
+            key: VK_DOWN as u16,
+            modifiers: AcceleratorModifiers::CTRL | AcceleratorModifiers::SHIFT,
+        },
         // Terminal menu
         Accelerator {
             action: MenuAction::Find,
             key: 'F' as u16,
             modifiers: AcceleratorModifiers::CTRL | AcceleratorModifiers::SHIFT,
         },
+        Accelerator {
+            action: MenuAction::RerunLastCommand,
+            key: 'E' as u16,
+            modifiers: AcceleratorModifiers::CTRL | AcceleratorModifiers::SHIFT,
+        },
+        Accelerator {
+            action: MenuAction::ClearToLastPrompt,
+            key: 'K' as u16,
+            modifiers: AcceleratorModifiers::CTRL | AcceleratorModifiers::SHIFT,
+        },
+        Accelerator {
+            action: MenuAction::SetMark,
+            key: 'M' as u16,
+            modifiers: AcceleratorModifiers::CTRL | AcceleratorModifiers::SHIFT,
+        },
+        Accelerator {
+            action: MenuAction::JumpMark,
+            key: 'J' as u16,
+            modifiers: AcceleratorModifiers::CTRL | AcceleratorModifiers::SHIFT,
+        },
+        Accelerator {
+            action: MenuAction::ToggleRecording,
+            key: 'L' as u16,
+            modifiers: AcceleratorModifiers::CTRL | AcceleratorModifiers::SHIFT,
+        },
         // Tabs menu
         Accelerator {
             action: MenuAction::PrevTab,
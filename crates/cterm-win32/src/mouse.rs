@@ -95,6 +95,7 @@ impl MouseState {
         // Return initial selection
         Some(Selection {
             anchor: point,
+            anchor_start: None,
             anchor_end: None,
             end: point,
             mode: self.mode,
@@ -119,6 +120,7 @@ impl MouseState {
 
         self.start.map(|anchor| Selection {
             anchor,
+            anchor_start: None,
             anchor_end: None,
             end: point,
             mode: self.mode,
@@ -145,6 +147,7 @@ impl MouseState {
 
         self.start.map(|anchor| Selection {
             anchor,
+            anchor_start: None,
             anchor_end: None,
             end: point,
             mode: self.mode,
@@ -169,6 +172,7 @@ impl MouseState {
         match (self.start, self.current) {
             (Some(anchor), Some(end)) => Some(Selection {
                 anchor,
+                anchor_start: None,
                 anchor_end: None,
                 end,
                 mode: self.mode,
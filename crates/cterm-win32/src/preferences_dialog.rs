@@ -17,6 +17,7 @@ use std::time::{SystemTime, UNIX_EPOCH};
 use crate::dialog_utils::*;
 use cterm_app::config::{
     config_dir, Config, CursorStyleConfig, NewTabPosition, TabBarPosition, TabBarVisibility,
+    TabCloseButton,
 };
 use cterm_app::{git_sync, PullResult};
 
@@ -42,7 +43,8 @@ const IDC_BOLD_BRIGHT: i32 = 1028;
 const IDC_SHOW_TABBAR: i32 = 1030;
 const IDC_TAB_POSITION: i32 = 1031;
 const IDC_NEW_TAB_POS: i32 = 1032;
-const IDC_SHOW_CLOSE_BTN: i32 = 1033;
+const IDC_TAB_CLOSE_BTN: i32 = 1033;
+const IDC_MIDDLE_CLICK_CLOSES: i32 = 1034;
 
 // Control IDs - Shortcuts tab
 const IDC_SHORTCUTS_LIST: i32 = 1040;
@@ -608,12 +610,36 @@ unsafe fn create_tabs_controls(hwnd: HWND, x: i32, y: i32, _w: i32, _h: i32) {
     add_combobox_item(new_tab_combo, "After current");
     controls.push(new_tab_combo);
 
-    // Show close button
+    // Close buttons
+    cy += row_height + 5;
+    controls.push(create_label(
+        hwnd,
+        -1,
+        "Close buttons:",
+        x,
+        cy + 3,
+        label_width,
+        18,
+    ));
+    let close_combo = create_combobox(
+        hwnd,
+        IDC_TAB_CLOSE_BTN,
+        x + label_width + 10,
+        cy,
+        control_width,
+        22,
+    );
+    add_combobox_item(close_combo, "Always");
+    add_combobox_item(close_combo, "On hover");
+    add_combobox_item(close_combo, "Never");
+    controls.push(close_combo);
+
+    // Middle-click closes tabs
     cy += row_height + 5;
     controls.push(create_checkbox(
         hwnd,
-        IDC_SHOW_CLOSE_BTN,
-        "Show close button on tabs",
+        IDC_MIDDLE_CLICK_CLOSES,
+        "Middle-click closes tabs",
         x,
         cy,
         250,
@@ -1078,8 +1104,16 @@ fn populate_controls() {
                 };
                 set_combobox_selection(combo, idx);
             }
-            if let Some(&checkbox) = state.tabs_controls.get(6) {
-                set_checkbox_state(checkbox, config.tabs.show_close_button);
+            if let Some(&combo) = state.tabs_controls.get(7) {
+                let idx = match config.tabs.close_button {
+                    TabCloseButton::Always => 0,
+                    TabCloseButton::Hover => 1,
+                    TabCloseButton::Never => 2,
+                };
+                set_combobox_selection(combo, idx);
+            }
+            if let Some(&checkbox) = state.tabs_controls.get(8) {
+                set_checkbox_state(checkbox, config.tabs.middle_click_closes_tab);
             }
 
             // Shortcuts tab - populate listview
@@ -1194,8 +1228,15 @@ fn collect_config() -> Config {
                     _ => NewTabPosition::End,
                 };
             }
-            if let Some(&checkbox) = state.tabs_controls.get(6) {
-                config.tabs.show_close_button = get_checkbox_state(checkbox);
+            if let Some(&combo) = state.tabs_controls.get(7) {
+                config.tabs.close_button = match get_combobox_selection(combo) {
+                    Some(1) => TabCloseButton::Hover,
+                    Some(2) => TabCloseButton::Never,
+                    _ => TabCloseButton::Always,
+                };
+            }
+            if let Some(&checkbox) = state.tabs_controls.get(8) {
+                config.tabs.middle_click_closes_tab = get_checkbox_state(checkbox);
             }
         }
     });
@@ -2,6 +2,7 @@
 //!
 //! Provides a tab bar similar to modern browsers with close buttons and indicators.
 
+use cterm_app::config::TabCloseButton;
 use cterm_core::color::Rgb;
 use cterm_ui::theme::Theme;
 use windows::core::Interface;
@@ -37,6 +38,10 @@ pub struct TabInfo {
     pub title: String,
     pub color: Option<Rgb>,
     pub has_bell: bool,
+    /// Incognito tab (no scrollback, never saved)
+    pub incognito: bool,
+    /// Session is being recorded to a file
+    pub recording: bool,
     pub is_active: bool,
 }
 
@@ -57,6 +62,8 @@ pub struct TabBar {
     dpi: DpiInfo,
     hover_tab_id: Option<u64>,
     hover_close_button: bool,
+    /// When tabs show their close button
+    close_button: TabCloseButton,
     visible: bool,
 }
 
@@ -72,6 +79,7 @@ impl TabBar {
             dpi: DpiInfo::default(),
             hover_tab_id: None,
             hover_close_button: false,
+            close_button: TabCloseButton::Always,
             visible: false, // Initially hidden, shown when there are multiple tabs
         }
     }
@@ -113,6 +121,8 @@ impl TabBar {
             title: title.to_string(),
             color: None,
             has_bell: false,
+            incognito: false,
+            recording: false,
             is_active: false,
         });
         self.update_visibility();
@@ -157,6 +167,20 @@ impl TabBar {
         }
     }
 
+    /// Set incognito marker
+    pub fn set_incognito(&mut self, id: u64, incognito: bool) {
+        if let Some(tab) = self.tabs.iter_mut().find(|t| t.id == id) {
+            tab.incognito = incognito;
+        }
+    }
+
+    /// Set session recording marker
+    pub fn set_recording(&mut self, id: u64, recording: bool) {
+        if let Some(tab) = self.tabs.iter_mut().find(|t| t.id == id) {
+            tab.recording = recording;
+        }
+    }
+
     /// Clear bell indicator
     pub fn clear_bell(&mut self, id: u64) {
         self.set_bell(id, false);
@@ -173,6 +197,17 @@ impl TabBar {
         self.hover_close_button = on_close_button;
     }
 
+    /// Set when tabs show their close button
+    pub fn set_close_button(&mut self, close_button: TabCloseButton) {
+        self.close_button = close_button;
+    }
+
+    /// Whether the tab shows its close button
+    fn shows_close_button(&self, tab: &TabInfo) -> bool {
+        self.close_button
+            .is_shown(tab.is_active, self.hover_tab_id == Some(tab.id))
+    }
+
     /// Hit test - returns (tab_id, is_close_button, is_new_tab_button)
     pub fn hit_test(&self, x: f32, y: f32) -> (Option<u64>, bool, bool) {
         // Check new tab button
@@ -182,7 +217,12 @@ impl TabBar {
 
         // Check tabs (in reverse order so foreground tabs are hit first)
         for (id, rect) in self.tab_rects.iter().rev() {
-            if point_in_rect(x, y, &rect.close_button) {
+            let shows_close_button = self
+                .tabs
+                .iter()
+                .find(|tab| tab.id == *id)
+                .is_some_and(|tab| self.shows_close_button(tab));
+            if shows_close_button && point_in_rect(x, y, &rect.close_button) {
                 return (Some(*id), true, false);
             }
             if point_in_rect(x, y, &rect.bounds) {
@@ -377,12 +417,21 @@ impl TabBar {
         let text_brush =
             unsafe { base.CreateSolidColorBrush(&rgb_to_d2d_color(text_color), None)? };
 
-        // Create text with bell indicator if needed
-        let display_title = if tab.has_bell {
-            format!("* {}", tab.title)
-        } else {
-            tab.title.clone()
-        };
+        // Create text with bell and incognito indicators if needed
+        let mut display_title = tab.title.clone();
+        if tab.incognito {
+            display_title = format!("(incognito) {}", display_title);
+        }
+        if tab.recording {
+            display_title = format!(
+                "{} {}",
+                cterm_app::recording::RECORDING_INDICATOR,
+                display_title
+            );
+        }
+        if tab.has_bell {
+            display_title = format!("* {}", display_title);
+        }
 
         let text_wide: Vec<u16> = display_title.encode_utf16().collect();
         let close_size = self.dpi.scale_f32(CLOSE_BUTTON_SIZE);
@@ -410,6 +459,9 @@ impl TabBar {
         unsafe { base.DrawTextLayout(text_origin, &layout, &text_brush, Default::default()) };
 
         // Close button
+        if !self.shows_close_button(tab) {
+            return Ok(());
+        }
         let close_hover = self.hover_tab_id == Some(tab.id) && self.hover_close_button;
         let close_color = if close_hover {
             Rgb::new(255, 100, 100)
@@ -563,6 +615,34 @@ mod tests {
         assert!(!tab_bar.is_visible());
     }
 
+    #[test]
+    fn test_close_button_hit_test() {
+        let theme = Theme::dark();
+        let mut tab_bar = TabBar::new(&theme);
+        tab_bar.add_tab(1, "Tab 1");
+        tab_bar.add_tab(2, "Tab 2");
+        tab_bar.set_active(1);
+        tab_bar.calculate_layout(800.0);
+        let close_of = |tab_bar: &TabBar, id: u64| {
+            let (_, rect) = tab_bar.tab_rects.iter().find(|(i, _)| *i == id).unwrap();
+            (rect.close_button.left + 1.0, rect.close_button.top + 1.0)
+        };
+
+        let (x, y) = close_of(&tab_bar, 2);
+        assert_eq!(tab_bar.hit_test(x, y), (Some(2), true, false));
+
+        // Hidden close buttons click the tab itself
+        tab_bar.set_close_button(TabCloseButton::Hover);
+        assert_eq!(tab_bar.hit_test(x, y), (Some(2), false, false));
+        tab_bar.set_hover(Some(2), false);
+        assert_eq!(tab_bar.hit_test(x, y), (Some(2), true, false));
+        let (x, y) = close_of(&tab_bar, 1);
+        assert_eq!(tab_bar.hit_test(x, y), (Some(1), true, false));
+
+        tab_bar.set_close_button(TabCloseButton::Never);
+        assert_eq!(tab_bar.hit_test(x, y), (Some(1), false, false));
+    }
+
     #[test]
     fn test_point_in_rect() {
         let rect = D2D_RECT_F {
@@ -4,7 +4,10 @@
 
 use std::collections::HashMap;
 
-use cterm_core::color::{Color, Rgb};
+use cterm_app::config::{
+    badge_color, ruler_color, ruler_positions, CursorLineHighlight, ScrollPositionIndicator,
+};
+use cterm_core::color::{Color, ColorOverrides, Rgb, DEFAULT_FAINT_OPACITY};
 use cterm_core::{Cell, CellAttrs, Screen, Selection};
 use cterm_ui::theme::Theme;
 use windows::core::{Interface, PCWSTR};
@@ -15,8 +18,8 @@ use windows::Win32::Graphics::Direct2D::Common::{
 };
 use windows::Win32::Graphics::Direct2D::{
     D2D1CreateFactory, ID2D1Factory, ID2D1HwndRenderTarget, ID2D1RenderTarget,
-    ID2D1SolidColorBrush, D2D1_ANTIALIAS_MODE_PER_PRIMITIVE, D2D1_FACTORY_OPTIONS,
-    D2D1_FACTORY_TYPE_SINGLE_THREADED, D2D1_FEATURE_LEVEL_DEFAULT,
+    ID2D1SolidColorBrush, D2D1_ANTIALIAS_MODE_PER_PRIMITIVE, D2D1_DRAW_TEXT_OPTIONS_CLIP,
+    D2D1_FACTORY_OPTIONS, D2D1_FACTORY_TYPE_SINGLE_THREADED, D2D1_FEATURE_LEVEL_DEFAULT,
     D2D1_HWND_RENDER_TARGET_PROPERTIES, D2D1_PRESENT_OPTIONS_NONE, D2D1_RENDER_TARGET_PROPERTIES,
     D2D1_RENDER_TARGET_TYPE_DEFAULT, D2D1_RENDER_TARGET_USAGE_NONE,
     D2D1_TEXT_ANTIALIAS_MODE_CLEARTYPE,
@@ -24,7 +27,8 @@ use windows::Win32::Graphics::Direct2D::{
 use windows::Win32::Graphics::DirectWrite::{
     DWriteCreateFactory, IDWriteFactory, IDWriteTextFormat, IDWriteTextLayout,
     DWRITE_FACTORY_TYPE_SHARED, DWRITE_FONT_STRETCH_NORMAL, DWRITE_FONT_STYLE_NORMAL,
-    DWRITE_FONT_WEIGHT_BOLD, DWRITE_FONT_WEIGHT_NORMAL, DWRITE_TEXT_METRICS,
+    DWRITE_FONT_WEIGHT_BOLD, DWRITE_FONT_WEIGHT_NORMAL, DWRITE_TEXT_ALIGNMENT_CENTER,
+    DWRITE_TEXT_METRICS,
 };
 use windows::Win32::Graphics::Dxgi::Common::DXGI_FORMAT_B8G8R8A8_UNORM;
 use windows::Win32::UI::WindowsAndMessaging::GetClientRect;
@@ -56,15 +60,32 @@ pub struct TerminalRenderer {
     render_target: Option<ID2D1HwndRenderTarget>,
     text_format: Option<IDWriteTextFormat>,
     text_format_bold: Option<IDWriteTextFormat>,
+    /// Large bold UI font for the badge overlay
+    badge_format: Option<IDWriteTextFormat>,
     cell_dims: CellDimensions,
     font_size: f32,
     font_family: String,
+    /// Theme with the program's color overrides applied
     theme: Theme,
+    /// Theme as configured
+    base_theme: Theme,
+    /// Default colors set by the program (OSC 10/11/12)
+    color_overrides: ColorOverrides,
     dpi: DpiInfo,
     brush_cache: HashMap<u32, ID2D1SolidColorBrush>,
     hwnd: HWND,
     /// Optional background color override (from template)
     background_override: Option<Rgb>,
+    /// Cursor line highlight mode
+    cursor_line: CursorLineHighlight,
+    /// Whether to draw the badge set by programs (iTerm2 SetBadgeFormat)
+    show_badge: bool,
+    /// How visible faint (SGR 2) text is over its background
+    faint_opacity: f64,
+    /// Scroll position shown in the bottom-right corner while scrolled back
+    scroll_position: ScrollPositionIndicator,
+    /// Columns to draw a ruler after (`appearance.ruler_columns`)
+    ruler_columns: Vec<usize>,
 }
 
 impl TerminalRenderer {
@@ -93,14 +114,22 @@ impl TerminalRenderer {
             render_target: None,
             text_format: None,
             text_format_bold: None,
+            badge_format: None,
             cell_dims: CellDimensions::default(),
             font_size,
             font_family: font_family.to_string(),
             theme: theme.clone(),
+            base_theme: theme.clone(),
+            color_overrides: ColorOverrides::default(),
             dpi: DpiInfo::system(),
             brush_cache: HashMap::new(),
             hwnd,
             background_override: None,
+            cursor_line: CursorLineHighlight::Off,
+            show_badge: true,
+            faint_opacity: DEFAULT_FAINT_OPACITY,
+            scroll_position: ScrollPositionIndicator::Off,
+            ruler_columns: Vec::new(),
         };
 
         renderer.create_device_resources()?;
@@ -238,6 +267,25 @@ impl TerminalRenderer {
             baseline: metrics.height * 0.85, // Approximate baseline
         };
 
+        // Badge font (twice the terminal font size); the badge is optional,
+        // so failing to create it only disables the overlay
+        let badge_family: Vec<u16> = "Segoe UI"
+            .encode_utf16()
+            .chain(std::iter::once(0))
+            .collect();
+        self.badge_format = unsafe {
+            self.dwrite_factory.CreateTextFormat(
+                PCWSTR(badge_family.as_ptr()),
+                None,
+                DWRITE_FONT_WEIGHT_BOLD,
+                DWRITE_FONT_STYLE_NORMAL,
+                DWRITE_FONT_STRETCH_NORMAL,
+                scaled_font_size * 2.0,
+                PCWSTR(locale.as_ptr()),
+            )
+        }
+        .ok();
+
         self.text_format = Some(text_format);
         self.text_format_bold = Some(text_format_bold);
 
@@ -297,6 +345,31 @@ impl TerminalRenderer {
         });
     }
 
+    /// Set whether the row the cursor is on gets a background tint
+    pub fn set_cursor_line_highlight(&mut self, mode: CursorLineHighlight) {
+        self.cursor_line = mode;
+    }
+
+    /// Set whether the badge set by programs is drawn
+    pub fn set_show_badge(&mut self, show: bool) {
+        self.show_badge = show;
+    }
+
+    /// Set how visible faint (SGR 2) text is
+    pub fn set_faint_opacity(&mut self, opacity: f64) {
+        self.faint_opacity = opacity;
+    }
+
+    /// Set how the scroll position is shown while scrolled back
+    pub fn set_scroll_position(&mut self, indicator: ScrollPositionIndicator) {
+        self.scroll_position = indicator;
+    }
+
+    /// Set the columns to draw a ruler after
+    pub fn set_ruler_columns(&mut self, columns: &[usize]) {
+        self.ruler_columns = columns.to_vec();
+    }
+
     /// Calculate terminal size in cells
     pub fn terminal_size(&self, width: u32, height: u32) -> (usize, usize) {
         let cols = (width as f32 / self.cell_dims.width).floor() as usize;
@@ -310,20 +383,30 @@ impl TerminalRenderer {
             return Ok(());
         }
 
+        self.set_color_overrides(screen.color_overrides());
+
         // Begin drawing
         unsafe {
             let rt = self.render_target.as_ref().unwrap();
             rt.BeginDraw();
 
             // Clear with background color (use override if set)
-            let bg = self
-                .background_override
-                .as_ref()
-                .unwrap_or(&self.theme.colors.background);
-            let bg_color = rgb_to_d2d_color(*bg);
+            let bg_color = rgb_to_d2d_color(self.background());
             rt.Clear(Some(&bg_color));
         }
 
+        // Tint the cursor line (drawn first so cell backgrounds and selection cover it)
+        self.draw_cursor_line(screen)?;
+
+        // Column rulers, under the text
+        self.draw_rulers(screen)?;
+
+        // Draw the badge behind the text
+        self.draw_badge(screen)?;
+
+        // Highlight search matches behind the text
+        self.draw_search_matches(screen)?;
+
         // Draw grid cells
         self.draw_grid(screen)?;
 
@@ -332,9 +415,18 @@ impl TerminalRenderer {
             self.draw_selection(screen, &selection)?;
         }
 
+        // Mark bookmarked rows in the left gutter
+        self.draw_bookmarks(screen)?;
+
+        // Draw the inline suggestion after the cursor
+        self.draw_inline_suggestion(screen)?;
+
         // Draw cursor
         self.draw_cursor(screen)?;
 
+        // Show the scroll position while scrolled back
+        self.draw_scroll_position(screen)?;
+
         // End drawing
         unsafe {
             let rt = self.render_target.as_ref().unwrap();
@@ -362,9 +454,36 @@ impl TerminalRenderer {
                 continue;
             }
 
+            // Display order of right-to-left text (None = as stored)
+            let bidi = screen.bidi_line(screen.scrollback().len() + grid_row);
+
+            // A truncated long line ends in "…"
+            let continues_right = grid.row(grid_row).is_some_and(|r| r.overflow_len() > 0);
+
+            // Secrets are drawn as "•" (mask_secrets)
+            let secrets = screen.secret_columns(screen.scrollback().len() + grid_row);
+
             for col in 0..cols {
                 if let Some(cell) = grid.get(grid_row, col) {
-                    self.draw_cell(row, col, cell)?;
+                    if continues_right && col + 1 == cols {
+                        self.draw_cell(row, col, cell, '…', false)?;
+                        continue;
+                    }
+                    let concealed = screen.is_concealed(cell);
+                    if secrets.iter().any(|r| r.contains(&col)) {
+                        self.draw_cell(row, col, cell, '•', concealed)?;
+                        continue;
+                    }
+                    match &bidi {
+                        Some(bidi) => self.draw_cell(
+                            row,
+                            bidi.visual_col(col),
+                            cell,
+                            bidi.display_char(col, cell.c),
+                            concealed,
+                        )?,
+                        None => self.draw_cell(row, col, cell, cell.c, concealed)?,
+                    }
                 }
             }
         }
@@ -372,8 +491,16 @@ impl TerminalRenderer {
         Ok(())
     }
 
-    /// Draw a single cell
-    fn draw_cell(&mut self, row: usize, col: usize, cell: &Cell) -> windows::core::Result<()> {
+    /// Draw a single cell, showing `c` (its character, or the mirrored one)
+    /// at visual column `col`; concealed cells only get their background
+    fn draw_cell(
+        &mut self,
+        row: usize,
+        col: usize,
+        cell: &Cell,
+        c: char,
+        concealed: bool,
+    ) -> windows::core::Result<()> {
         let x = col as f32 * self.cell_dims.width;
         let y = row as f32 * self.cell_dims.height;
 
@@ -387,10 +514,10 @@ impl TerminalRenderer {
             None
         };
 
-        let c = cell.c;
-        let needs_fg = c != ' ' && c != '\0'
-            || attrs.has_underline()
-            || attrs.contains(CellAttrs::STRIKETHROUGH);
+        let needs_fg = !concealed
+            && (c != ' ' && c != '\0'
+                || attrs.has_underline()
+                || attrs.contains(CellAttrs::STRIKETHROUGH));
         let fg_brush = if needs_fg {
             Some(self.get_brush(fg)?)
         } else {
@@ -412,6 +539,10 @@ impl TerminalRenderer {
             unsafe { base.FillRectangle(&rect, brush) };
         }
 
+        if concealed {
+            return Ok(());
+        }
+
         // Draw character
         if c != ' ' && c != '\0' {
             let text_format = if attrs.contains(CellAttrs::BOLD) {
@@ -420,27 +551,33 @@ impl TerminalRenderer {
                 self.text_format.as_ref().unwrap()
             };
 
-            let mut buf = [0u16; 2];
-            let text: &[u16] = c.encode_utf16(&mut buf);
+            // The cell's own character brings the rest of its grapheme cluster
+            let text: Vec<u16> = if c == cell.c {
+                cell.grapheme_text().encode_utf16().collect()
+            } else {
+                c.to_string().encode_utf16().collect()
+            };
 
             let layout: IDWriteTextLayout = unsafe {
                 self.dwrite_factory.CreateTextLayout(
-                    text,
+                    &text,
                     text_format,
                     self.cell_dims.width * 2.0, // Allow for wide chars
                     self.cell_dims.height,
                 )?
             };
 
-            let origin = D2D_POINT_2F { x, y };
-            unsafe {
-                base.DrawTextLayout(
-                    origin,
-                    &layout,
-                    fg_brush.as_ref().unwrap(),
-                    Default::default(),
-                )
+            // Center wide glyphs (emoji) in their two columns and keep them
+            // from bleeding into the next cell
+            let options = if attrs.contains(CellAttrs::WIDE) {
+                unsafe { layout.SetTextAlignment(DWRITE_TEXT_ALIGNMENT_CENTER)? };
+                D2D1_DRAW_TEXT_OPTIONS_CLIP
+            } else {
+                Default::default()
             };
+
+            let origin = D2D_POINT_2F { x, y };
+            unsafe { base.DrawTextLayout(origin, &layout, fg_brush.as_ref().unwrap(), options) };
         }
 
         // Draw underline
@@ -498,7 +635,7 @@ impl TerminalRenderer {
 
         // Handle dim
         if cell.attrs.contains(CellAttrs::DIM) {
-            fg = Rgb::new(fg.r / 2, fg.g / 2, fg.b / 2);
+            fg = fg.faint(bg, self.faint_opacity);
         }
 
         (fg, bg)
@@ -550,6 +687,240 @@ impl TerminalRenderer {
         Ok(())
     }
 
+    /// Draw the cursor line highlight
+    fn draw_cursor_line(&mut self, screen: &Screen) -> windows::core::Result<()> {
+        let Some(row) = self.cursor_line.highlighted_row(screen) else {
+            return Ok(());
+        };
+
+        let bg = self.background();
+        let brush = self.get_brush(CursorLineHighlight::tint(bg, self.theme.colors.foreground))?;
+
+        let rt = self.render_target.clone().unwrap();
+        let base: ID2D1RenderTarget = rt.cast()?;
+
+        let y = row as f32 * self.cell_dims.height;
+        let rect = D2D_RECT_F {
+            left: 0.0,
+            top: y,
+            right: screen.width() as f32 * self.cell_dims.width,
+            bottom: y + self.cell_dims.height,
+        };
+        unsafe { base.FillRectangle(&rect, &brush) };
+
+        Ok(())
+    }
+
+    /// Draw a faint vertical line after each ruler column
+    fn draw_rulers(&mut self, screen: &Screen) -> windows::core::Result<()> {
+        let rulers = ruler_positions(&self.ruler_columns, screen);
+        if rulers.is_empty() {
+            return Ok(());
+        }
+
+        let bg = self.background();
+        let brush = self.get_brush(ruler_color(bg, self.theme.colors.foreground))?;
+
+        let rt = self.render_target.clone().unwrap();
+        let base: ID2D1RenderTarget = rt.cast()?;
+
+        let bottom = screen.height() as f32 * self.cell_dims.height;
+        for col in rulers {
+            let x = col as f32 * self.cell_dims.width;
+            let rect = D2D_RECT_F {
+                left: x,
+                top: 0.0,
+                right: x + 1.0,
+                bottom,
+            };
+            unsafe { base.FillRectangle(&rect, &brush) };
+        }
+
+        Ok(())
+    }
+
+    /// Draw the highlights of the active search
+    fn draw_search_matches(&mut self, screen: &Screen) -> windows::core::Result<()> {
+        if screen.search_match_count() == 0 {
+            return Ok(());
+        }
+
+        let rt = self.render_target.clone().unwrap();
+        let base: ID2D1RenderTarget = rt.cast()?;
+
+        for row in 0..screen.height() {
+            let line = screen.visible_row_to_absolute_line(row);
+            let bidi = screen.bidi_line(line);
+            for col in 0..screen.width() {
+                let Some(highlight) = screen.search_highlight(line, col) else {
+                    continue;
+                };
+                let brush = self.get_brush(self.theme.search_bg(highlight))?;
+
+                let visual_col = bidi.as_ref().map_or(col, |b| b.visual_col(col));
+                let x = visual_col as f32 * self.cell_dims.width;
+                let y = row as f32 * self.cell_dims.height;
+                let rect = D2D_RECT_F {
+                    left: x,
+                    top: y,
+                    right: x + self.cell_dims.width,
+                    bottom: y + self.cell_dims.height,
+                };
+                unsafe { base.FillRectangle(&rect, &brush) };
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Draw a bar in the left gutter of each bookmarked row
+    fn draw_bookmarks(&mut self, screen: &Screen) -> windows::core::Result<()> {
+        let rows: Vec<usize> = screen.visible_bookmark_rows().collect();
+        if rows.is_empty() {
+            return Ok(());
+        }
+
+        let rt = self.render_target.clone().unwrap();
+        let base: ID2D1RenderTarget = rt.cast()?;
+        let brush = self.get_brush(self.theme.ui.scrollbar_hover)?;
+        let width = (self.cell_dims.width / 4.0).max(2.0);
+
+        for row in rows {
+            let y = row as f32 * self.cell_dims.height;
+            let rect = D2D_RECT_F {
+                left: 0.0,
+                top: y,
+                right: width,
+                bottom: y + self.cell_dims.height,
+            };
+            unsafe { base.FillRectangle(&rect, &brush) };
+        }
+
+        Ok(())
+    }
+
+    /// Draw the predicted local echo, or else the inline suggestion, as faint
+    /// text at the cursor
+    fn draw_inline_suggestion(&mut self, screen: &Screen) -> windows::core::Result<()> {
+        let Some(overlay) = screen
+            .predicted_input()
+            .or_else(|| screen.inline_suggestion())
+        else {
+            return Ok(());
+        };
+        let cursor = &screen.cursor;
+        let room = screen.width().saturating_sub(cursor.col);
+        let text: Vec<u16> = overlay
+            .chars()
+            .take(room)
+            .collect::<String>()
+            .encode_utf16()
+            .collect();
+
+        let bg = self.background();
+        let brush = self.get_brush(self.theme.colors.foreground.faint(bg, self.faint_opacity))?;
+        let rt = self.render_target.clone().unwrap();
+        let base: ID2D1RenderTarget = rt.cast()?;
+
+        let layout: IDWriteTextLayout = unsafe {
+            self.dwrite_factory.CreateTextLayout(
+                &text,
+                self.text_format.as_ref().unwrap(),
+                room as f32 * self.cell_dims.width,
+                self.cell_dims.height,
+            )?
+        };
+        let origin = D2D_POINT_2F {
+            x: cursor.col as f32 * self.cell_dims.width,
+            y: cursor.row as f32 * self.cell_dims.height,
+        };
+        unsafe { base.DrawTextLayout(origin, &layout, &brush, Default::default()) };
+
+        Ok(())
+    }
+
+    /// Draw the badge (iTerm2 SetBadgeFormat) in the top-right corner
+    fn draw_badge(&mut self, screen: &Screen) -> windows::core::Result<()> {
+        if !self.show_badge {
+            return Ok(());
+        }
+        let (Some(badge), Some(format)) = (screen.badge(), self.badge_format.clone()) else {
+            return Ok(());
+        };
+
+        let bg = self.background();
+        let brush = self.get_brush(badge_color(bg, self.theme.colors.foreground))?;
+
+        let rt = self.render_target.clone().unwrap();
+        let base: ID2D1RenderTarget = rt.cast()?;
+
+        let screen_width = screen.width() as f32 * self.cell_dims.width;
+        let text: Vec<u16> = badge.encode_utf16().collect();
+        let layout: IDWriteTextLayout = unsafe {
+            self.dwrite_factory.CreateTextLayout(
+                &text,
+                &format,
+                screen_width,
+                self.cell_dims.height * 4.0,
+            )?
+        };
+
+        let mut metrics = DWRITE_TEXT_METRICS::default();
+        unsafe { layout.GetMetrics(&mut metrics)? };
+
+        let origin = D2D_POINT_2F {
+            x: (screen_width - metrics.width - self.cell_dims.width).max(0.0),
+            y: self.cell_dims.height * 0.5,
+        };
+        unsafe { base.DrawTextLayout(origin, &layout, &brush, Default::default()) };
+
+        Ok(())
+    }
+
+    /// Draw the scroll position label in a box in the bottom-right corner
+    fn draw_scroll_position(&mut self, screen: &Screen) -> windows::core::Result<()> {
+        let Some(label) = self.scroll_position.label(screen) else {
+            return Ok(());
+        };
+
+        let bg = self.background();
+        let box_brush = self.get_brush(badge_color(bg, self.theme.colors.foreground))?;
+        let text_brush = self.get_brush(self.theme.colors.foreground)?;
+        let rt = self.render_target.clone().unwrap();
+        let base: ID2D1RenderTarget = rt.cast()?;
+
+        let screen_width = screen.width() as f32 * self.cell_dims.width;
+        let screen_height = screen.height() as f32 * self.cell_dims.height;
+        let text: Vec<u16> = label.encode_utf16().collect();
+        let layout: IDWriteTextLayout = unsafe {
+            self.dwrite_factory.CreateTextLayout(
+                &text,
+                self.text_format.as_ref().unwrap(),
+                screen_width,
+                self.cell_dims.height,
+            )?
+        };
+
+        let mut metrics = DWRITE_TEXT_METRICS::default();
+        unsafe { layout.GetMetrics(&mut metrics)? };
+
+        let pad = self.cell_dims.width * 0.5;
+        let x = (screen_width - metrics.width - pad * 3.0).max(0.0);
+        let y = (screen_height - self.cell_dims.height - pad * 2.0).max(0.0);
+        let rect = D2D_RECT_F {
+            left: x - pad,
+            top: y,
+            right: x + metrics.width + pad,
+            bottom: y + self.cell_dims.height,
+        };
+        unsafe { base.FillRectangle(&rect, &box_brush) };
+
+        let origin = D2D_POINT_2F { x, y };
+        unsafe { base.DrawTextLayout(origin, &layout, &text_brush, Default::default()) };
+
+        Ok(())
+    }
+
     /// Draw the cursor
     fn draw_cursor(&mut self, screen: &Screen) -> windows::core::Result<()> {
         // Check DECTCEM mode for cursor visibility
@@ -558,8 +929,12 @@ impl TerminalRenderer {
         }
 
         let cursor = &screen.cursor;
+        let cursor_col = screen.cursor_render_col();
+        let visual_col = screen
+            .bidi_line(screen.visible_row_to_absolute_line(cursor.row))
+            .map_or(cursor_col, |b| b.visual_col(cursor_col));
 
-        let x = cursor.col as f32 * self.cell_dims.width;
+        let x = visual_col as f32 * self.cell_dims.width;
         let y = cursor.row as f32 * self.cell_dims.height;
 
         let cursor_color = self.theme.cursor.color;
@@ -583,7 +958,7 @@ impl TerminalRenderer {
 
         // Draw the character under cursor with inverted color
         let grid = screen.grid();
-        if let Some(cell) = grid.get(cursor.row, cursor.col) {
+        if let Some(cell) = grid.get(cursor.row, cursor_col) {
             let c = cell.c;
 
             if c != ' ' && c != '\0' {
@@ -615,10 +990,30 @@ impl TerminalRenderer {
 
     /// Update the theme
     pub fn set_theme(&mut self, theme: &Theme) {
-        self.theme = theme.clone();
+        self.base_theme = theme.clone();
+        self.theme = theme.with_color_overrides(&self.color_overrides);
         self.brush_cache.clear();
     }
 
+    /// Apply the default colors set by the program (OSC 10/11/12)
+    fn set_color_overrides(&mut self, overrides: &ColorOverrides) {
+        if *overrides != self.color_overrides {
+            self.color_overrides = overrides.clone();
+            self.theme = self.base_theme.with_color_overrides(overrides);
+        }
+    }
+
+    /// Background color: set by the program, else the template's, else the
+    /// theme's
+    fn background(&self) -> Rgb {
+        match self.color_overrides.background {
+            Some(_) => self.theme.colors.background,
+            None => self
+                .background_override
+                .unwrap_or(self.theme.colors.background),
+        }
+    }
+
     /// Update font settings
     pub fn set_font(&mut self, family: &str, size: f32) -> windows::core::Result<()> {
         self.font_family = family.to_string();
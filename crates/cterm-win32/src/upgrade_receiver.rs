@@ -279,7 +279,7 @@ fn create_restored_tab(
         scrollback_lines: config.general.scrollback_lines,
     };
 
-    let screen = Screen::from_upgrade_state(
+    let mut screen = Screen::from_upgrade_state(
         term_state.grid.clone(),
         term_state.scrollback.clone(),
         term_state.alternate_grid.clone(),
@@ -294,6 +294,7 @@ fn create_restored_tab(
         term_state.tab_stops.clone(),
         screen_config,
     );
+    term_state.apply_display_hints(&mut screen);
 
     // Create Terminal with the restored screen and PTY
     let terminal = Terminal::from_restored(screen, pty);
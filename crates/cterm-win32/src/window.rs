@@ -2,29 +2,38 @@
 //!
 //! Manages the main window, tabs, terminal rendering, and message handling.
 
+use std::collections::HashMap;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
 use std::thread;
+use std::time::Instant;
 
 use windows::core::PCWSTR;
-use windows::Win32::Foundation::{HWND, LPARAM, LRESULT, RECT, WPARAM};
+use windows::Win32::Foundation::{COLORREF, HWND, LPARAM, LRESULT, RECT, WPARAM};
 use windows::Win32::Graphics::Gdi::{
     BeginPaint, EndPaint, InvalidateRect, UpdateWindow, HBRUSH, PAINTSTRUCT,
 };
 use windows::Win32::UI::WindowsAndMessaging::GetClientRect;
 use windows::Win32::UI::WindowsAndMessaging::*;
 
-use cterm_app::config::Config;
+use cterm_app::config::{AppearanceConfig, Config, TitleFilter};
 use cterm_app::file_transfer::PendingFileManager;
-use cterm_app::shortcuts::ShortcutManager;
+use cterm_app::notify::{
+    bell_notification, sound_for, BellEscalation, BellRateLimiter, SoundEvent,
+};
+use cterm_app::recording::RecordingToggle;
+use cterm_app::replay::Replay;
+use cterm_app::shortcuts::{describe_conflicts, ShortcutManager};
 use cterm_core::color::Rgb;
 use cterm_core::pty::{PtyConfig, PtySize};
 use cterm_core::screen::{FileTransferOperation, ScreenConfig};
 use cterm_core::term::{Terminal, TerminalEvent};
 use cterm_ui::events::{Action, Modifiers};
 use cterm_ui::theme::Theme;
+use cterm_ui::utils::snap_to_cells;
 
 use crate::clipboard;
+use crate::desktop_notification;
 use crate::dpi::{self, DpiInfo};
 use crate::keycode;
 use crate::menu::{self, MenuAction};
@@ -58,6 +67,8 @@ pub struct WindowState {
     pub config: Config,
     pub theme: Theme,
     pub shortcuts: ShortcutManager,
+    /// Which program title changes are applied to tabs
+    pub title_filter: TitleFilter,
     pub tabs: Vec<TabEntry>,
     pub active_tab_index: usize,
     pub next_tab_id: AtomicU64,
@@ -67,10 +78,18 @@ pub struct WindowState {
     pub file_manager: PendingFileManager,
     pub dpi: DpiInfo,
     pub mouse_state: MouseState,
+    /// Bells counted towards a desktop notification, by tab id
+    bell_escalations: HashMap<u64, BellEscalation>,
+    /// Coalesces bells ringing in quick succession, by tab id
+    bell_limiters: HashMap<u64, BellRateLimiter>,
     #[allow(dead_code)]
     menu_handle: winapi::shared::windef::HMENU,
     /// Skip close confirmation (set during relaunch)
     pub skip_close_confirm: bool,
+    /// Drop the WM_CHAR for a keypad key already sent as an SS3 sequence
+    skip_keypad_char: bool,
+    /// Capture being replayed by the "Replay File" debug action
+    replay: Option<Replay>,
 }
 
 impl WindowState {
@@ -81,6 +100,7 @@ impl WindowState {
 
         let mut tab_bar = TabBar::new(theme);
         tab_bar.set_dpi(dpi);
+        tab_bar.set_close_button(config.tabs.close_button);
 
         let mut notification_bar = NotificationBar::new(theme);
         notification_bar.set_dpi(dpi);
@@ -89,11 +109,12 @@ impl WindowState {
         let menu_handle = menu::create_menu_bar(false);
         menu::set_window_menu(hwnd.0 as *mut _, menu_handle);
 
-        Self {
+        let state = Self {
             hwnd,
             config: config.clone(),
             theme: theme.clone(),
             shortcuts,
+            title_filter: TitleFilter::new(&config.appearance),
             tabs: Vec::new(),
             active_tab_index: 0,
             next_tab_id: AtomicU64::new(0),
@@ -103,9 +124,15 @@ impl WindowState {
             file_manager: PendingFileManager::new(),
             dpi,
             mouse_state: MouseState::new(),
+            bell_escalations: HashMap::new(),
+            bell_limiters: HashMap::new(),
             menu_handle,
             skip_close_confirm: false,
-        }
+            skip_keypad_char: false,
+            replay: None,
+        };
+        state.apply_opacity();
+        state
     }
 
     /// Initialize the renderer
@@ -113,30 +140,37 @@ impl WindowState {
         let font_family = &self.config.appearance.font.family;
         let font_size = self.config.appearance.font.size as f32;
 
-        let renderer = TerminalRenderer::new(self.hwnd, &self.theme, font_family, font_size)?;
+        let mut renderer = TerminalRenderer::new(self.hwnd, &self.theme, font_family, font_size)?;
+        renderer.set_cursor_line_highlight(self.config.appearance.highlight_cursor_line);
+        renderer.set_show_badge(self.config.appearance.show_badge);
+        renderer.set_faint_opacity(self.config.appearance.faint_opacity);
+        renderer.set_scroll_position(self.config.appearance.show_scroll_position);
+        renderer.set_ruler_columns(&self.config.appearance.ruler_columns);
         self.renderer = Some(renderer);
         Ok(())
     }
 
     /// Create a new tab
-    pub fn new_tab(&mut self) -> Result<u64, Box<dyn std::error::Error>> {
+    ///
+    /// Incognito tabs keep no scrollback.
+    pub fn new_tab(&mut self, incognito: bool) -> Result<u64, Box<dyn std::error::Error>> {
         let tab_id = self.next_tab_id.fetch_add(1, Ordering::SeqCst);
+        let incognito = incognito || self.config.general.no_scrollback;
 
         // Get terminal size
         let (cols, rows) = self.terminal_size();
 
         // Create terminal
         let screen_config = ScreenConfig {
-            scrollback_lines: self.config.general.scrollback_lines,
+            scrollback_lines: if incognito {
+                0
+            } else {
+                self.config.general.scrollback_lines
+            },
         };
 
         let pty_config = PtyConfig {
-            size: PtySize {
-                cols: cols as u16,
-                rows: rows as u16,
-                pixel_width: 0,
-                pixel_height: 0,
-            },
+            size: self.pty_size(cols, rows),
             shell: self.config.general.default_shell.clone(),
             args: self.config.general.shell_args.clone(),
             cwd: self.config.general.working_directory.clone(),
@@ -148,9 +182,48 @@ impl WindowState {
                 .map(|(k, v)| (k.clone(), v.clone()))
                 .collect(),
             term: self.config.general.term.clone(),
+            advertise_truecolor: self.config.general.advertise_truecolor,
+            raw: self.config.general.raw_pty,
         };
 
-        let terminal = Terminal::with_shell(cols, rows, screen_config, &pty_config)?;
+        let mut terminal = Terminal::with_shell(cols, rows, screen_config, &pty_config)?;
+        terminal.set_unknown_sequence_mode(self.config.general.unknown_sequence_mode);
+        terminal.set_print_control_as_caret(self.config.general.print_control_as_caret);
+        terminal.set_max_file_transfer_bytes(self.config.general.file_transfer_limit());
+        terminal.set_search_follows_output(self.config.general.search_follows_output);
+        terminal.set_cursor_visibility_per_screen(self.config.general.cursor_visibility_per_screen);
+        terminal.set_max_images(self.config.general.max_scrollback_images);
+        terminal.set_scroll_to_bottom_on(&self.config.general.scroll_to_bottom_on);
+        terminal.set_home_end_style(self.config.general.home_end_style);
+        terminal.set_encoding(self.config.general.encoding);
+        terminal.set_local_echo(
+            self.config.general.local_echo,
+            std::time::Duration::from_millis(self.config.general.local_echo_threshold_ms),
+        );
+        terminal
+            .screen_mut()
+            .set_inline_suggestions(self.config.general.inline_suggestions);
+        terminal
+            .screen_mut()
+            .set_bidi(self.config.appearance.enable_bidi);
+        terminal
+            .screen_mut()
+            .set_copy_hyperlink_mode(self.config.general.copy_hyperlink_mode);
+        terminal
+            .screen_mut()
+            .set_tab_width(self.config.general.tab_width);
+        terminal
+            .screen_mut()
+            .set_long_line_mode(self.config.general.long_line_mode);
+        terminal
+            .screen_mut()
+            .set_grapheme_clusters(self.config.general.grapheme_clusters);
+        terminal
+            .screen_mut()
+            .set_secret_matcher(self.config.general.secret_matcher());
+        terminal
+            .screen_mut()
+            .set_reveal_concealed(self.config.general.reveal_concealed);
         let terminal = Arc::new(Mutex::new(terminal));
 
         // Get shell basename for initial title
@@ -184,6 +257,7 @@ impl WindowState {
 
         // Update tab bar with shell basename
         self.tab_bar.add_tab(tab_id, &initial_title);
+        self.tab_bar.set_incognito(tab_id, incognito);
         self.tab_bar.set_active(tab_id);
 
         Ok(tab_id)
@@ -196,12 +270,28 @@ impl WindowState {
     ) -> Result<u64, Box<dyn std::error::Error>> {
         let tab_id = self.next_tab_id.fetch_add(1, Ordering::SeqCst);
 
+        // Prepare working directory (expand tokens, clone from git if needed)
+        let mut template = template.clone();
+        if let Some(ref working_dir) = template.working_directory {
+            match cterm_app::prepare_working_directory(working_dir, template.git_remote.as_deref())
+            {
+                Ok((dir, _)) => template.working_directory = Some(dir),
+                Err(e) => log::error!("Failed to prepare working directory: {}", e),
+            }
+        }
+        let template = &template;
+
         // Get terminal size
         let (cols, rows) = self.terminal_size();
 
         // Create terminal
+        let incognito = template.no_scrollback || self.config.general.no_scrollback;
         let screen_config = ScreenConfig {
-            scrollback_lines: self.config.general.scrollback_lines,
+            scrollback_lines: if incognito {
+                0
+            } else {
+                self.config.general.scrollback_lines
+            },
         };
 
         // Build the shell command and args from the template
@@ -241,12 +331,7 @@ impl WindowState {
         };
 
         let pty_config = PtyConfig {
-            size: PtySize {
-                cols: cols as u16,
-                rows: rows as u16,
-                pixel_width: 0,
-                pixel_height: 0,
-            },
+            size: self.pty_size(cols, rows),
             shell,
             args,
             cwd: template
@@ -266,9 +351,48 @@ impl WindowState {
                 )
                 .collect(),
             term: self.config.general.term.clone(),
+            advertise_truecolor: self.config.general.advertise_truecolor,
+            raw: false,
         };
 
-        let terminal = Terminal::with_shell(cols, rows, screen_config, &pty_config)?;
+        let mut terminal = Terminal::with_shell(cols, rows, screen_config, &pty_config)?;
+        terminal.set_unknown_sequence_mode(self.config.general.unknown_sequence_mode);
+        terminal.set_print_control_as_caret(self.config.general.print_control_as_caret);
+        terminal.set_max_file_transfer_bytes(self.config.general.file_transfer_limit());
+        terminal.set_search_follows_output(self.config.general.search_follows_output);
+        terminal.set_cursor_visibility_per_screen(self.config.general.cursor_visibility_per_screen);
+        terminal.set_max_images(self.config.general.max_scrollback_images);
+        terminal.set_scroll_to_bottom_on(&self.config.general.scroll_to_bottom_on);
+        terminal.set_home_end_style(self.config.general.home_end_style);
+        terminal.set_encoding(self.config.general.encoding);
+        terminal.set_local_echo(
+            self.config.general.local_echo,
+            std::time::Duration::from_millis(self.config.general.local_echo_threshold_ms),
+        );
+        terminal
+            .screen_mut()
+            .set_inline_suggestions(self.config.general.inline_suggestions);
+        terminal
+            .screen_mut()
+            .set_bidi(self.config.appearance.enable_bidi);
+        terminal
+            .screen_mut()
+            .set_copy_hyperlink_mode(self.config.general.copy_hyperlink_mode);
+        terminal
+            .screen_mut()
+            .set_tab_width(self.config.general.tab_width);
+        terminal
+            .screen_mut()
+            .set_long_line_mode(self.config.general.long_line_mode);
+        terminal
+            .screen_mut()
+            .set_grapheme_clusters(self.config.general.grapheme_clusters);
+        terminal
+            .screen_mut()
+            .set_secret_matcher(self.config.general.secret_matcher());
+        terminal
+            .screen_mut()
+            .set_reveal_concealed(self.config.general.reveal_concealed);
         let terminal = Arc::new(Mutex::new(terminal));
 
         // Start PTY reader thread
@@ -289,6 +413,7 @@ impl WindowState {
 
         // Update tab bar
         self.tab_bar.add_tab(tab_id, &template.name);
+        self.tab_bar.set_incognito(tab_id, incognito);
         self.tab_bar.set_active(tab_id);
 
         // Set tab color if specified
@@ -311,7 +436,7 @@ impl WindowState {
         let (cols, rows) = self.terminal_size();
 
         let screen_config = ScreenConfig {
-            scrollback_lines: self.config.general.scrollback_lines,
+            scrollback_lines: self.config.general.effective_scrollback_lines(),
         };
 
         // Build the docker command based on selection
@@ -346,12 +471,7 @@ impl WindowState {
         };
 
         let pty_config = PtyConfig {
-            size: PtySize {
-                cols: cols as u16,
-                rows: rows as u16,
-                pixel_width: 0,
-                pixel_height: 0,
-            },
+            size: self.pty_size(cols, rows),
             shell,
             args,
             cwd: self.config.general.working_directory.clone(),
@@ -363,9 +483,48 @@ impl WindowState {
                 .map(|(k, v)| (k.clone(), v.clone()))
                 .collect(),
             term: self.config.general.term.clone(),
+            advertise_truecolor: self.config.general.advertise_truecolor,
+            raw: false,
         };
 
-        let terminal = Terminal::with_shell(cols, rows, screen_config, &pty_config)?;
+        let mut terminal = Terminal::with_shell(cols, rows, screen_config, &pty_config)?;
+        terminal.set_unknown_sequence_mode(self.config.general.unknown_sequence_mode);
+        terminal.set_print_control_as_caret(self.config.general.print_control_as_caret);
+        terminal.set_max_file_transfer_bytes(self.config.general.file_transfer_limit());
+        terminal.set_search_follows_output(self.config.general.search_follows_output);
+        terminal.set_cursor_visibility_per_screen(self.config.general.cursor_visibility_per_screen);
+        terminal.set_max_images(self.config.general.max_scrollback_images);
+        terminal.set_scroll_to_bottom_on(&self.config.general.scroll_to_bottom_on);
+        terminal.set_home_end_style(self.config.general.home_end_style);
+        terminal.set_encoding(self.config.general.encoding);
+        terminal.set_local_echo(
+            self.config.general.local_echo,
+            std::time::Duration::from_millis(self.config.general.local_echo_threshold_ms),
+        );
+        terminal
+            .screen_mut()
+            .set_inline_suggestions(self.config.general.inline_suggestions);
+        terminal
+            .screen_mut()
+            .set_bidi(self.config.appearance.enable_bidi);
+        terminal
+            .screen_mut()
+            .set_copy_hyperlink_mode(self.config.general.copy_hyperlink_mode);
+        terminal
+            .screen_mut()
+            .set_tab_width(self.config.general.tab_width);
+        terminal
+            .screen_mut()
+            .set_long_line_mode(self.config.general.long_line_mode);
+        terminal
+            .screen_mut()
+            .set_grapheme_clusters(self.config.general.grapheme_clusters);
+        terminal
+            .screen_mut()
+            .set_secret_matcher(self.config.general.secret_matcher());
+        terminal
+            .screen_mut()
+            .set_reveal_concealed(self.config.general.reveal_concealed);
         let terminal = Arc::new(Mutex::new(terminal));
 
         let reader_handle = self.start_pty_reader(tab_id, Arc::clone(&terminal));
@@ -529,6 +688,8 @@ impl WindowState {
         if let Some(index) = self.tabs.iter().position(|t| t.id == tab_id) {
             self.tabs.remove(index);
             self.tab_bar.remove_tab(tab_id);
+            self.bell_escalations.remove(&tab_id);
+            self.bell_limiters.remove(&tab_id);
 
             if self.tabs.is_empty() {
                 // Close window
@@ -554,6 +715,9 @@ impl WindowState {
             self.tab_bar.set_active(tab_id);
             self.tab_bar.clear_bell(tab_id);
             self.tabs[index].has_bell = false;
+            if let Some(escalation) = self.bell_escalations.get_mut(&tab_id) {
+                escalation.reset();
+            }
             self.invalidate();
         }
     }
@@ -635,16 +799,101 @@ impl WindowState {
         }
     }
 
+    /// PTY size for a new terminal, with the pixel size from the cell size
+    fn pty_size(&self, cols: usize, rows: usize) -> PtySize {
+        match self.renderer.as_ref().map(|r| r.cell_dimensions()) {
+            Some(cell) => PtySize::with_cell_size(
+                rows as u16,
+                cols as u16,
+                cell.width as f64,
+                cell.height as f64,
+            ),
+            None => PtySize {
+                cols: cols as u16,
+                rows: rows as u16,
+                ..Default::default()
+            },
+        }
+    }
+
     /// Handle window resize
     pub fn on_resize(&mut self, width: u32, height: u32) {
         if let Some(ref mut renderer) = self.renderer {
             renderer.resize(width, height).ok();
         }
 
-        // Resize all terminals
+        self.resize_terminals();
+    }
+
+    /// Snap a window rect being resized (WM_SIZING) to whole cells, moving
+    /// the edges `edge` says are dragged
+    pub fn snap_sizing_rect(&self, edge: u32, rect: &mut RECT) {
+        let Some(cell) = self.renderer.as_ref().map(|r| r.cell_dimensions()) else {
+            return;
+        };
+        let (chrome_width, chrome_height) = self.chrome_size();
+        let width = snap_to_cells(rect.right - rect.left, chrome_width, cell.width as f64);
+        let height = snap_to_cells(rect.bottom - rect.top, chrome_height, cell.height as f64);
+
+        if matches!(edge, WMSZ_LEFT | WMSZ_TOPLEFT | WMSZ_BOTTOMLEFT) {
+            rect.left = rect.right - width;
+        } else {
+            rect.right = rect.left + width;
+        }
+        if matches!(edge, WMSZ_TOP | WMSZ_TOPLEFT | WMSZ_TOPRIGHT) {
+            rect.top = rect.bottom - height;
+        } else {
+            rect.bottom = rect.top + height;
+        }
+    }
+
+    /// Pixels of the window that aren't terminal grid: borders, title bar,
+    /// tab bar and notification bar
+    fn chrome_size(&self) -> (i32, i32) {
+        let mut window = RECT::default();
+        let mut client = RECT::default();
+        unsafe {
+            GetWindowRect(self.hwnd, &mut window).ok();
+            GetClientRect(self.hwnd, &mut client).ok();
+        }
+        let width = (window.right - window.left) - (client.right - client.left);
+        let height = (window.bottom - window.top) - (client.bottom - client.top)
+            + self.tab_bar.height() as i32
+            + self.notification_bar.height() as i32;
+        (width, height)
+    }
+
+    /// Shrink the window to whole cells, e.g. after the font size changed
+    fn snap_window_size(&self) {
+        if !self.config.appearance.resize_snap || unsafe { IsZoomed(self.hwnd) }.as_bool() {
+            return;
+        }
+        let mut rect = RECT::default();
+        unsafe { GetWindowRect(self.hwnd, &mut rect).ok() };
+        self.snap_sizing_rect(WMSZ_BOTTOMRIGHT, &mut rect);
+        unsafe {
+            SetWindowPos(
+                self.hwnd,
+                None,
+                0,
+                0,
+                rect.right - rect.left,
+                rect.bottom - rect.top,
+                SWP_NOMOVE | SWP_NOZORDER | SWP_NOACTIVATE,
+            )
+        }
+        .ok();
+    }
+
+    /// Resize all terminals to fit the window and tell them the cell size
+    fn resize_terminals(&self) {
         let (cols, rows) = self.terminal_size();
+        let cell = self.renderer.as_ref().map(|r| r.cell_dimensions());
         for tab in &self.tabs {
             let mut term = tab.terminal.lock().unwrap();
+            if let Some(cell) = cell {
+                term.set_cell_size(cell.width as f64, cell.height as f64);
+            }
             term.resize(cols, rows);
         }
     }
@@ -658,6 +907,10 @@ impl WindowState {
         if let Some(ref mut renderer) = self.renderer {
             renderer.update_dpi(dpi).ok();
         }
+
+        // The text format was recreated at the new DPI, so the cell size may
+        // have changed; resize the terminals to match
+        self.on_font_size_changed();
     }
 
     /// Invalidate and request redraw
@@ -692,8 +945,14 @@ impl WindowState {
     }
 
     /// Handle keyboard input
-    pub fn on_key_down(&mut self, vk: u16, _scancode: u16) -> bool {
-        let modifiers = keycode::get_modifiers();
+    pub fn on_key_down(&mut self, vk: u16, _scancode: u16, extended: bool) -> bool {
+        let mut modifiers = keycode::get_modifiers();
+
+        // AltGr types layout characters via WM_CHAR; don't treat it as
+        // Ctrl+Alt for shortcuts or control characters
+        if keycode::is_altgr_down() {
+            modifiers.remove(Modifiers::CTRL | Modifiers::ALT);
+        }
 
         // Check for shortcuts first
         if let Some(key) = keycode::vk_to_keycode(vk) {
@@ -713,6 +972,49 @@ impl WindowState {
             let mut term = terminal.lock().unwrap();
             let app_cursor = term.screen().modes.application_cursor;
 
+            // Home/End depend on the cursor mode and the configured style, and
+            // Right/End accept an inline suggestion
+            let home_end = match vk as i32 {
+                winapi::um::winuser::VK_HOME => Some(cterm_core::term::Key::Home),
+                winapi::um::winuser::VK_END => Some(cterm_core::term::Key::End),
+                winapi::um::winuser::VK_RIGHT if term.screen().inline_suggestion().is_some() => {
+                    Some(cterm_core::term::Key::Right)
+                }
+                _ => None,
+            };
+            if let Some(key) = home_end {
+                let core_mods = cterm_core::term::Modifiers::from_bits_truncate(modifiers.bits());
+                if let Some(bytes) = term.handle_key(key, core_mods) {
+                    term.write(&bytes).ok();
+                }
+                drop(term);
+                self.invalidate();
+                return true;
+            }
+
+            // Keypad Enter is Return with the extended-key bit; the other
+            // keypad keys only differ from the main keys in application keypad mode
+            let keypad = if vk as i32 == winapi::um::winuser::VK_RETURN && extended {
+                Some(cterm_core::term::Key::KeypadEnter)
+            } else if term.screen().modes.application_keypad {
+                keycode::vk_to_keycode(vk).and_then(|key| key.to_keypad_key())
+            } else {
+                None
+            };
+            if let Some(key) = keypad {
+                let core_mods = cterm_core::term::Modifiers::from_bits_truncate(modifiers.bits());
+                if let Some(bytes) = term.handle_key(key, core_mods) {
+                    term.write(&bytes).ok();
+                }
+                // Keypad Enter's WM_CHAR is a control character and already
+                // skipped, and Ctrl/Alt keypad keys don't produce one
+                self.skip_keypad_char = matches!(key, cterm_core::term::Key::Keypad(_))
+                    && !modifiers.intersects(Modifiers::CTRL | Modifiers::ALT);
+                drop(term);
+                self.invalidate();
+                return true;
+            }
+
             // Get terminal sequence for special keys
             if let Some(seq) = keycode::vk_to_terminal_seq(vk, modifiers, app_cursor) {
                 term.write(seq.as_bytes()).ok();
@@ -743,6 +1045,9 @@ impl WindowState {
 
     /// Handle character input
     pub fn on_char(&mut self, c: char) {
+        if std::mem::take(&mut self.skip_keypad_char) {
+            return;
+        }
         if let Some(terminal) = self.active_terminal() {
             let mut term = terminal.lock().unwrap();
             let mut buf = [0u8; 4];
@@ -755,11 +1060,86 @@ impl WindowState {
         self.invalidate();
     }
 
+    /// Import a color scheme from another terminal as the custom theme
+    fn import_theme(&mut self) {
+        let parent = self.hwnd.0 as *mut _;
+        let Some(path) = crate::dialogs::show_open_file_dialog(
+            parent,
+            "Import Theme",
+            "Color Schemes (*.itermcolors;*.toml;*.yml;*.json)\0*.itermcolors;*.toml;*.yml;*.yaml;*.json\0All Files\0*.*\0\0",
+        ) else {
+            return;
+        };
+        match cterm_app::config::import_theme(&mut self.config, &path) {
+            Ok(theme) => {
+                log::info!("Imported theme {:?} from {:?}", theme.name, path);
+                crate::dialogs::show_info(
+                    parent,
+                    "Theme Imported",
+                    &format!(
+                        "Imported theme \"{}\".\n\nRestart cterm to use it.",
+                        theme.name
+                    ),
+                );
+            }
+            Err(e) => {
+                log::error!("Failed to import theme from {:?}: {}", path, e);
+                crate::dialogs::show_error(parent, "Import Theme Failed", &e.to_string());
+            }
+        }
+    }
+
+    /// Replace the keyboard shortcuts with those of a keybindings file
+    fn import_keybindings(&mut self) {
+        let parent = self.hwnd.0 as *mut _;
+        let Some(path) = crate::dialogs::show_open_file_dialog(
+            parent,
+            "Import Keybindings",
+            "Keybindings (*.toml)\0*.toml\0All Files\0*.*\0\0",
+        ) else {
+            return;
+        };
+        match cterm_app::config::import_keybindings(&mut self.config, &path) {
+            Ok(conflicts) => {
+                log::info!("Imported keybindings from {:?}", path);
+                let mut message = "Imported keybindings.\n\nRestart cterm to use them.".to_string();
+                if let Some(note) = describe_conflicts(&conflicts) {
+                    message.push_str(&format!("\n\n{}", note));
+                }
+                crate::dialogs::show_info(parent, "Keybindings Imported", &message);
+            }
+            Err(e) => {
+                log::error!("Failed to import keybindings from {:?}: {}", path, e);
+                crate::dialogs::show_error(parent, "Import Keybindings Failed", &e.to_string());
+            }
+        }
+    }
+
+    /// Export the keyboard shortcuts to a file of their own
+    fn export_keybindings(&self) {
+        let parent = self.hwnd.0 as *mut _;
+        let Some(path) = crate::dialogs::show_save_file_dialog(
+            parent,
+            "Export Keybindings",
+            Some("keybindings.toml"),
+            "Keybindings (*.toml)\0*.toml\0All Files\0*.*\0\0",
+        ) else {
+            return;
+        };
+        match ShortcutManager::to_file(&self.config.shortcuts, &path) {
+            Ok(()) => log::info!("Exported keybindings to {:?}", path),
+            Err(e) => {
+                log::error!("Failed to export keybindings to {:?}: {}", path, e);
+                crate::dialogs::show_error(parent, "Export Keybindings Failed", &e.to_string());
+            }
+        }
+    }
+
     /// Handle an action
     fn handle_action(&mut self, action: Action) {
         match action {
-            Action::NewTab => {
-                self.new_tab().ok();
+            Action::NewTab | Action::NewIncognitoTab => {
+                self.new_tab(action == Action::NewIncognitoTab).ok();
                 self.invalidate();
             }
             Action::CloseTab => {
@@ -780,6 +1160,8 @@ impl WindowState {
             Action::ZoomIn => self.zoom_in(),
             Action::ZoomOut => self.zoom_out(),
             Action::ZoomReset => self.zoom_reset(),
+            Action::IncreaseOpacity => self.step_opacity(true),
+            Action::DecreaseOpacity => self.step_opacity(false),
             Action::CloseWindow => {
                 unsafe {
                     let _ = PostMessageW(Some(self.hwnd), WM_CLOSE, WPARAM(0), LPARAM(0));
@@ -790,6 +1172,18 @@ impl WindowState {
                 log::debug!("NewWindow action from shortcut not implemented");
             }
             Action::FindText => self.show_find_dialog(),
+            Action::ClearToLastPrompt => self.clear_to_last_prompt(),
+            Action::SetMark => self.set_mark(),
+            Action::JumpMark => self.jump_to_mark(),
+            Action::ToggleRecording => self.toggle_recording(),
+            Action::SendText(text) => {
+                if let Some(terminal) = self.active_terminal() {
+                    let mut term = terminal.lock().unwrap();
+                    term.write(text.as_bytes()).ok();
+                    drop(term);
+                }
+                self.invalidate();
+            }
             Action::ResetTerminal => {
                 if let Some(terminal) = self.active_terminal() {
                     let mut term = terminal.lock().unwrap();
@@ -807,7 +1201,10 @@ impl WindowState {
         if let Some(action) = MenuAction::from_id(cmd) {
             match action {
                 MenuAction::NewTab => {
-                    self.new_tab().ok();
+                    self.new_tab(false).ok();
+                }
+                MenuAction::NewIncognitoTab => {
+                    self.new_tab(true).ok();
                 }
                 MenuAction::NewWindow => {
                     // Launch a new instance of the application
@@ -871,13 +1268,22 @@ impl WindowState {
                 MenuAction::CopyHtml => self.copy_selection_as_html(),
                 MenuAction::Paste => self.paste(),
                 MenuAction::SelectAll => self.select_all(),
+                MenuAction::CopyLastOutput => self.copy_last_command_output(),
+                MenuAction::RerunLastCommand => self.rerun_last_command(),
+                MenuAction::ClearToLastPrompt => self.clear_to_last_prompt(),
+                MenuAction::SetMark => self.set_mark(),
+                MenuAction::JumpMark => self.jump_to_mark(),
+                MenuAction::ToggleRecording => self.toggle_recording(),
                 MenuAction::ZoomIn => self.zoom_in(),
                 MenuAction::ZoomOut => self.zoom_out(),
                 MenuAction::ZoomReset => self.zoom_reset(),
+                MenuAction::IncreaseOpacity => self.step_opacity(true),
+                MenuAction::DecreaseOpacity => self.step_opacity(false),
                 MenuAction::Fullscreen => self.toggle_fullscreen(),
                 MenuAction::SetTitle => self.show_set_title_dialog(),
                 MenuAction::SetColor => self.show_set_color_dialog(),
                 MenuAction::Find => self.show_find_dialog(),
+                MenuAction::FilterScrollback => self.show_filter_scrollback_dialog(),
                 MenuAction::Reset => {
                     if let Some(terminal) = self.active_terminal() {
                         let mut term = terminal.lock().unwrap();
@@ -920,6 +1326,9 @@ impl WindowState {
                         }
                     }
                 }
+                MenuAction::ImportTheme => self.import_theme(),
+                MenuAction::ImportKeybindings => self.import_keybindings(),
+                MenuAction::ExportKeybindings => self.export_keybindings(),
                 MenuAction::TabTemplates => {
                     if crate::templates_dialog::show_templates_dialog(self.hwnd.0 as *mut _) {
                         log::info!("Tab templates saved");
@@ -948,10 +1357,21 @@ impl WindowState {
                     log::info!("Active tab: {}", self.active_tab_index);
                     for (i, tab) in self.tabs.iter().enumerate() {
                         log::info!("  Tab {}: id={}, title={}", i, tab.id, tab.title);
+                        if let Ok(terminal) = tab.terminal.lock() {
+                            let (images, bytes) = terminal.screen().image_memory();
+                            log::info!("    Images: {} ({} bytes of pixel data)", images, bytes);
+                        }
                     }
                     log::info!("DPI: {:?}", self.dpi);
                     log::info!("========================");
                 }
+                MenuAction::DebugSendBytes => self.show_send_bytes_dialog(),
+                MenuAction::DebugReplayFile => self.replay_file(),
+                MenuAction::DebugStopReplay => {
+                    if self.replay.take().is_some() {
+                        log::info!("Replay stopped");
+                    }
+                }
                 MenuAction::ViewLogs => {
                     // Show the in-app log viewer
                     crate::log_viewer::show_log_viewer(self.hwnd.0 as *mut _);
@@ -1001,15 +1421,20 @@ impl WindowState {
             // Perform search in terminal
             if let Some(terminal) = self.active_terminal() {
                 let mut term = terminal.lock().unwrap();
-                let results =
-                    term.screen()
-                        .find(&options.text, options.case_sensitive, options.regex);
-                if !results.is_empty() {
-                    log::info!("Found {} matches for: {}", results.len(), options.text);
-                    // Scroll to first result
-                    if let Some(first) = results.first() {
-                        term.scroll_to_line(first.line);
-                    }
+                // Highlights all matches; searching again for the same text
+                // moves on to the next one
+                let current = term.screen_mut().search_next(
+                    &options.text,
+                    options.case_sensitive,
+                    options.regex,
+                );
+                if let Some(current) = current {
+                    log::info!(
+                        "Found {} matches for: {}",
+                        term.screen().search_match_count(),
+                        options.text
+                    );
+                    term.scroll_to_line(current.line);
                 } else {
                     drop(term);
                     crate::dialogs::show_message(
@@ -1023,9 +1448,105 @@ impl WindowState {
                 drop(term);
             }
             self.invalidate();
+        } else if let Some(terminal) = self.active_terminal() {
+            // Cancelling the dialog ends the search
+            terminal.lock().unwrap().screen_mut().clear_search();
+            self.invalidate();
+        }
+    }
+
+    /// Show the scrollback lines matching a regex in a read-only window
+    fn show_filter_scrollback_dialog(&mut self) {
+        let parent = self.hwnd.0 as *mut _;
+        let pattern = match crate::dialogs::show_input_dialog(
+            parent,
+            "Filter Scrollback",
+            "Show lines matching (regular expression):",
+            "",
+        ) {
+            crate::dialogs::InputDialogResult::Ok(pattern) if !pattern.is_empty() => pattern,
+            _ => return,
+        };
+        if let Some(terminal) = self.active_terminal() {
+            let lines = terminal
+                .lock()
+                .unwrap()
+                .screen()
+                .extract_matches(&pattern, true, true);
+            crate::filter_viewer::show_filter_results(parent, &pattern, &lines);
+        }
+    }
+
+    /// Debug: write hand-typed bytes to the PTY
+    fn show_send_bytes_dialog(&mut self) {
+        let parent = self.hwnd.0 as *mut _;
+        let input = match crate::dialogs::show_input_dialog(
+            parent,
+            "Send Bytes",
+            "Hex (1b 5b 41) or text with escapes (\\e[A, \\x07, \\t, \\n):",
+            "",
+        ) {
+            crate::dialogs::InputDialogResult::Ok(input) => input,
+            crate::dialogs::InputDialogResult::Cancel => return,
+        };
+        match cterm_app::send_bytes::parse_send_bytes(&input) {
+            Ok(bytes) => {
+                if let Some(terminal) = self.active_terminal() {
+                    terminal.lock().unwrap().write(&bytes).ok();
+                }
+                self.invalidate();
+            }
+            Err(e) => crate::dialogs::show_error(parent, "Send Bytes", &e.to_string()),
         }
     }
 
+    /// Debug: feed a captured byte stream to the active terminal as if the
+    /// program wrote it
+    fn replay_file(&mut self) {
+        let parent = self.hwnd.0 as *mut _;
+        let Some(path) =
+            crate::dialogs::show_open_file_dialog(parent, "Replay File", "All Files\0*.*\0\0")
+        else {
+            return;
+        };
+        let data = match std::fs::read(&path) {
+            Ok(data) => data,
+            Err(e) => {
+                crate::dialogs::show_error(
+                    parent,
+                    "Replay File",
+                    &format!("Failed to read {}: {}", path.display(), e),
+                );
+                return;
+            }
+        };
+        let Some(tab) = self.tabs.get(self.active_tab_index) else {
+            return;
+        };
+        log::info!("Replaying {} bytes from {:?}", data.len(), path);
+
+        let hwnd = self.hwnd.0 as usize;
+        let tab_id = tab.id;
+        let terminal = Arc::clone(&tab.terminal);
+        // Replacing a running replay stops it
+        self.replay = Some(Replay::start(
+            data,
+            self.config.general.replay_bytes_per_sec,
+            move |chunk| {
+                terminal.lock().unwrap().process(chunk);
+                unsafe {
+                    PostMessageW(
+                        Some(HWND(hwnd as *mut _)),
+                        WM_APP_PTY_DATA,
+                        WPARAM(tab_id as usize),
+                        LPARAM(0),
+                    )
+                    .is_ok()
+                }
+            },
+        ));
+    }
+
     /// Select all text in the terminal
     fn select_all(&mut self) {
         if let Some(terminal) = self.active_terminal() {
@@ -1047,7 +1568,10 @@ impl WindowState {
     fn copy_selection_as_html(&mut self) {
         if let Some(terminal) = self.active_terminal() {
             let term = terminal.lock().unwrap();
-            if let Some(html) = term.screen().get_selected_html(&self.theme.colors) {
+            if let Some(html) = term
+                .screen()
+                .get_selected_html(&self.theme.colors, self.config.appearance.faint_opacity)
+            {
                 // Copy HTML to clipboard
                 clipboard::copy_to_clipboard(&html).ok();
                 log::debug!("Copied {} chars as HTML to clipboard", html.len());
@@ -1101,14 +1625,43 @@ impl WindowState {
 
     /// Called when font size changes to resize terminals
     fn on_font_size_changed(&mut self) {
-        let (cols, rows) = self.terminal_size();
-        for tab in &self.tabs {
-            let mut term = tab.terminal.lock().unwrap();
-            term.resize(cols, rows);
-        }
+        self.resize_terminals();
+        self.snap_window_size();
         self.invalidate();
     }
 
+    /// Apply the current opacity, making the window layered only when needed
+    fn apply_opacity(&self) {
+        let opacity = self.config.appearance.opacity.clamp(0.0, 1.0);
+        unsafe {
+            let ex_style = GetWindowLongPtrW(self.hwnd, GWL_EXSTYLE);
+            if opacity < 1.0 {
+                SetWindowLongPtrW(self.hwnd, GWL_EXSTYLE, ex_style | WS_EX_LAYERED.0 as isize);
+                let alpha = (opacity * 255.0).round() as u8;
+                let _ = SetLayeredWindowAttributes(self.hwnd, COLORREF(0), alpha, LWA_ALPHA);
+            } else {
+                SetWindowLongPtrW(
+                    self.hwnd,
+                    GWL_EXSTYLE,
+                    ex_style & !(WS_EX_LAYERED.0 as isize),
+                );
+            }
+        }
+    }
+
+    /// Make the window more or less opaque, saving it if configured
+    fn step_opacity(&mut self, increase: bool) {
+        let opacity = AppearanceConfig::step_opacity(self.config.appearance.opacity, increase);
+        self.config.appearance.opacity = opacity;
+        self.apply_opacity();
+
+        if self.config.appearance.persist_opacity {
+            if let Err(e) = cterm_app::config::save_opacity(opacity) {
+                log::error!("Failed to save opacity: {}", e);
+            }
+        }
+    }
+
     /// Toggle fullscreen mode
     fn toggle_fullscreen(&mut self) {
         use windows::Win32::UI::WindowsAndMessaging::{
@@ -1146,20 +1699,111 @@ impl WindowState {
     }
 
     /// Copy selection to clipboard
+    ///
+    /// Asks first when the selection contains a masked secret.
     fn copy_selection(&mut self) {
         if let Some(terminal) = self.active_terminal() {
             let term = terminal.lock().unwrap();
             if let Some(text) = term.screen().get_selected_text() {
+                let has_secret = term.screen().selection_has_secret();
+                drop(term);
+                if has_secret
+                    && !crate::dialogs::show_confirm(
+                        self.hwnd.0 as *mut _,
+                        "Copy Secret?",
+                        "The selection looks like it contains a secret (a key or token). \
+                         Copy it anyway?",
+                    )
+                {
+                    return;
+                }
                 clipboard::copy_to_clipboard(&text).ok();
             }
         }
     }
 
+    /// Copy the output of the last finished command (OSC 133 marks)
+    fn copy_last_command_output(&mut self) {
+        if let Some(terminal) = self.active_terminal() {
+            let term = terminal.lock().unwrap();
+            if let Some(text) = term.screen().last_command_output() {
+                clipboard::copy_to_clipboard(&text).ok();
+            }
+        }
+    }
+
+    /// Run the last finished command (OSC 133 marks) again
+    fn rerun_last_command(&mut self) {
+        if let Some(terminal) = self.active_terminal() {
+            let mut term = terminal.lock().unwrap();
+            if let Err(e) = term.rerun_last_command() {
+                log::error!("Failed to write to terminal: {}", e);
+            }
+        }
+    }
+
+    /// Delete the output of the last command, keeping earlier history
+    fn clear_to_last_prompt(&mut self) {
+        if let Some(terminal) = self.active_terminal() {
+            let cleared = terminal.lock().unwrap().screen_mut().clear_to_last_prompt();
+            if cleared {
+                self.invalidate();
+            }
+        }
+    }
+
+    /// Toggle a scrollback bookmark at the current position
+    fn set_mark(&mut self) {
+        if let Some(terminal) = self.active_terminal() {
+            terminal.lock().unwrap().screen_mut().toggle_bookmark();
+            self.invalidate();
+        }
+    }
+
+    /// Scroll to the previous scrollback bookmark
+    fn jump_to_mark(&mut self) {
+        if let Some(terminal) = self.active_terminal() {
+            let jumped = terminal.lock().unwrap().screen_mut().jump_to_bookmark();
+            if jumped {
+                self.invalidate();
+            }
+        }
+    }
+
+    /// Start or stop recording the active tab's session to a file
+    fn toggle_recording(&mut self) {
+        let Some(tab) = self.tabs.get(self.active_tab_index) else {
+            return;
+        };
+        let id = tab.id;
+        let result =
+            cterm_app::recording::toggle(&mut tab.terminal.lock().unwrap(), &self.config.general);
+        match result {
+            Ok(toggle) => {
+                let recording = matches!(toggle, RecordingToggle::Started(_));
+                self.tab_bar.set_recording(id, recording);
+            }
+            Err(e) => {
+                log::error!("Session recording failed: {}", e);
+                self.tab_bar.set_recording(id, false);
+                crate::dialogs::show_error(
+                    self.hwnd.0 as *mut _,
+                    "Session Recording Failed",
+                    &e.to_string(),
+                );
+            }
+        }
+        self.invalidate();
+    }
+
     /// Paste from clipboard
     fn paste(&mut self) {
         if let Ok(text) = clipboard::paste_from_clipboard() {
             if let Some(terminal) = self.active_terminal() {
+                let text = self.config.general.paste_text(&text);
                 let mut term = terminal.lock().unwrap();
+                let text = self.config.general.quote_pasted_path(text, &term);
+                let text = self.config.general.bracketed_paste_text(&text, &term);
                 term.write(text.as_bytes()).ok();
                 drop(term);
             }
@@ -1169,10 +1813,19 @@ impl WindowState {
 
     /// Handle PTY data received
     pub fn on_pty_data(&mut self, tab_id: u64) {
+        let mut redraw = true;
+
         // Check for file transfers from the terminal
         if let Some(tab) = self.tabs.iter().find(|t| t.id == tab_id) {
             if let Ok(mut terminal) = tab.terminal.lock() {
                 let transfers = terminal.screen_mut().take_file_transfers();
+                // Output that didn't change anything visible skips the redraw
+                redraw = !transfers.is_empty() || !terminal.screen_mut().take_damage().is_empty();
+                if !transfers.is_empty() {
+                    if let Some(sound) = sound_for(&self.config.general, SoundEvent::FileReceived) {
+                        desktop_notification::play_sound(&sound);
+                    }
+                }
                 for transfer in transfers {
                     match transfer {
                         FileTransferOperation::FileReceived { id, name, data } => {
@@ -1205,7 +1858,9 @@ impl WindowState {
         }
 
         // Invalidate to redraw
-        self.invalidate();
+        if redraw {
+            self.invalidate();
+        }
     }
 
     /// Handle PTY exit
@@ -1215,6 +1870,17 @@ impl WindowState {
 
     /// Handle bell
     pub fn on_bell(&mut self, tab_id: u64) {
+        let limiter = self
+            .bell_limiters
+            .entry(tab_id)
+            .or_insert_with(|| BellRateLimiter::from_config(&self.config.general));
+        if !limiter.allow(Instant::now()) {
+            return;
+        }
+        if let Some(sound) = sound_for(&self.config.general, SoundEvent::Bell) {
+            desktop_notification::play_sound(&sound);
+        }
+
         // Only show bell indicator if this tab is not the current tab
         let is_current_tab = self
             .tabs
@@ -1222,6 +1888,8 @@ impl WindowState {
             .map(|t| t.id == tab_id)
             .unwrap_or(false);
 
+        let is_window_active = unsafe { GetForegroundWindow() } == self.hwnd;
+
         if let Some(tab) = self.tabs.iter_mut().find(|t| t.id == tab_id) {
             if !is_current_tab {
                 tab.has_bell = true;
@@ -1230,20 +1898,45 @@ impl WindowState {
                 self.invalidate();
             }
         }
+
+        // Escalate repeated bells in an unfocused tab to a notification
+        let escalation = self
+            .bell_escalations
+            .entry(tab_id)
+            .or_insert_with(|| BellEscalation::from_config(&self.config.general));
+        if is_current_tab && is_window_active {
+            escalation.reset();
+        } else if escalation.ring(Instant::now()) {
+            let title = self
+                .tabs
+                .iter()
+                .find(|t| t.id == tab_id)
+                .map(|t| t.title.as_str())
+                .unwrap_or_default();
+            desktop_notification::show(self.hwnd, &bell_notification(title));
+        }
     }
 
     /// Handle title change from terminal
     pub fn on_title_changed(&mut self, tab_id: u64) {
         if let Some(tab) = self.tabs.iter_mut().find(|t| t.id == tab_id) {
-            // Don't update if title is locked (user-set or template)
-            if tab.title_locked {
-                return;
-            }
-
             // Get title from terminal's screen
-            let new_title = {
+            let (new_title, directory) = {
                 let term = tab.terminal.lock().unwrap();
-                term.screen().title.clone()
+                (term.screen().title.clone(), term.display_directory())
+            };
+
+            // Respect the update mode, ignore patterns and locked
+            // (user-set or template) titles
+            if !self.title_filter.accepts(&new_title, tab.title_locked) {
+                return;
+            }
+            let new_title = if new_title.is_empty() {
+                new_title
+            } else {
+                self.config
+                    .tabs
+                    .format_title(&new_title, directory.as_deref())
             };
 
             if !new_title.is_empty() {
@@ -1269,6 +1962,22 @@ impl WindowState {
         }
     }
 
+    /// Handle middle-click, which closes the tab under the mouse if
+    /// `middle_click_closes_tab` is set
+    pub fn on_middle_click(&mut self, x: f32, y: f32) {
+        let tab_bar_height = self.dpi.scale_f32(TAB_BAR_HEIGHT as f32);
+        if y >= tab_bar_height
+            || !self.tab_bar.is_visible()
+            || !self.config.tabs.middle_click_closes_tab
+        {
+            return;
+        }
+        if let (Some(tab_id), _, _) = self.tab_bar.hit_test(x, y) {
+            self.close_tab(tab_id);
+            self.invalidate();
+        }
+    }
+
     /// Handle right-click for context menu
     pub fn on_right_click(&mut self, x: f32, y: f32) {
         // Check if click is in tab bar area
@@ -1476,8 +2185,12 @@ pub fn register_window_class() -> windows::core::Result<()> {
     Ok(())
 }
 
-/// Create the main window
-pub fn create_window(config: &Config, theme: &Theme) -> windows::core::Result<HWND> {
+/// Create the main window, its first tab from `template` if given
+pub fn create_window(
+    config: &Config,
+    theme: &Theme,
+    template: Option<&cterm_app::config::StickyTabConfig>,
+) -> windows::core::Result<HWND> {
     let class_name: Vec<u16> = WINDOW_CLASS
         .encode_utf16()
         .chain(std::iter::once(0))
@@ -1508,7 +2221,11 @@ pub fn create_window(config: &Config, theme: &Theme) -> windows::core::Result<HW
     // Create window state
     let mut state = Box::new(WindowState::new(hwnd, config, theme));
     state.init_renderer()?;
-    state.new_tab().map_err(|e| {
+    let first_tab = match template {
+        Some(template) => state.new_tab_from_template(template),
+        None => state.new_tab(false),
+    };
+    first_tab.map_err(|e| {
         log::error!("Failed to create initial tab: {}", e);
         windows::core::Error::from_win32()
     })?;
@@ -1541,6 +2258,14 @@ extern "system" fn window_proc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPA
             LRESULT(0)
         }
 
+        WM_SIZING => {
+            if state.config.appearance.resize_snap {
+                let rect = unsafe { &mut *(lparam.0 as *mut RECT) };
+                state.snap_sizing_rect(wparam.0 as u32, rect);
+            }
+            LRESULT(1)
+        }
+
         WM_SIZE => {
             let width = (lparam.0 & 0xFFFF) as u32;
             let height = ((lparam.0 >> 16) & 0xFFFF) as u32;
@@ -1571,7 +2296,8 @@ extern "system" fn window_proc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPA
         WM_KEYDOWN | WM_SYSKEYDOWN => {
             let vk = (wparam.0 & 0xFFFF) as u16;
             let scancode = ((lparam.0 >> 16) & 0xFF) as u16;
-            if state.on_key_down(vk, scancode) {
+            let extended = lparam.0 & (1 << 24) != 0;
+            if state.on_key_down(vk, scancode, extended) {
                 LRESULT(0)
             } else {
                 unsafe { DefWindowProcW(hwnd, msg, wparam, lparam) }
@@ -1606,6 +2332,13 @@ extern "system" fn window_proc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPA
             LRESULT(0)
         }
 
+        WM_MBUTTONDOWN => {
+            let x = (lparam.0 & 0xFFFF) as i16 as f32;
+            let y = ((lparam.0 >> 16) & 0xFFFF) as i16 as f32;
+            state.on_middle_click(x, y);
+            LRESULT(0)
+        }
+
         WM_COMMAND => {
             let cmd = (wparam.0 & 0xFFFF) as u16;
             state.on_menu_command(cmd);
@@ -1670,6 +2403,7 @@ extern "system" fn window_proc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPA
 
         WM_DESTROY => {
             // Clean up
+            desktop_notification::remove(hwnd);
             let state = unsafe { Box::from_raw(state_ptr) };
             drop(state);
             unsafe { SetWindowLongPtrW(hwnd, GWLP_USERDATA, 0) };